@@ -0,0 +1,488 @@
+//! Summarises the transactional emails from their schema.org annotations.
+//!
+//! Airlines, carriers and shops embed the data of a reservation, a delivery
+//! or an order in their HTML emails, as JSON-LD scripts:
+//!
+//! ```html
+//! <script type="application/ld+json">
+//! {"@type": "ParcelDelivery", "trackingNumber": "1Z999",
+//!  "carrier": {"name": "UPS"}, "expectedArrivalUntil": "2024-05-04"}
+//! </script>
+//! ```
+//!
+//! The `FlightReservation`, `ParcelDelivery` and `Order` annotations are read,
+//! also when they are listed in an array or in a `@graph`, and summarised in
+//! one line each at the top of the viewer, e.g. `Parcel 1Z999 from UPS,
+//! expected on 2024-05-04`. The other types, and the scripts that aren't valid
+//! JSON, are ignored.
+
+use core::iter::Peekable;
+use core::str::Chars;
+
+/// Arrow between the airports of a flight
+const ARROW: char = '\u{2192}';
+/// Maximum nesting of the JSON arrays and objects, not to overflow the stack
+/// on a malicious email
+const MAX_DEPTH: usize = 32;
+/// Prefixes of the schema.org types and enumeration values
+const SCHEMA_PREFIXES: [&str; 2] =
+    ["http://schema.org/", "https://schema.org/"];
+/// Type of the JSON-LD scripts
+const SCRIPT_TYPE: &str = "application/ld+json";
+
+/// Parsed JSON value
+#[derive(Debug, PartialEq, Eq)]
+enum Value {
+    /// Array of values
+    Array(Vec<Self>),
+    /// `true` or `false`
+    Bool(bool),
+    /// `null`
+    Null,
+    /// Number, kept as written
+    Number(String),
+    /// Object, with its keys in order
+    Object(Vec<(String, Self)>),
+    /// String, with its escapes decoded
+    String(String),
+}
+
+impl Value {
+    /// Returns the value of a key of an object
+    ///
+    /// The first value of an array is taken, as schema.org allows a list
+    /// wherever a single value is expected.
+    fn get(&self, key: &str) -> Option<&Self> {
+        match self.to_first() {
+            Self::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.to_first()),
+            Self::Array(_)
+            | Self::Bool(_)
+            | Self::Null
+            | Self::Number(_)
+            | Self::String(_) => None,
+        }
+    }
+
+    /// Returns the text of the value at the end of a path of keys, e.g.
+    /// `["carrier", "name"]`
+    ///
+    /// The schema.org prefix of the enumeration values is removed.
+    fn get_text(&self, path: &[&str]) -> Option<&str> {
+        let value = path.iter().try_fold(self, |value, key| value.get(key))?;
+        let text = match value {
+            Self::Number(text) | Self::String(text) => text.trim(),
+            Self::Array(_) | Self::Bool(_) | Self::Null | Self::Object(_) =>
+                return None,
+        };
+        let stripped = SCHEMA_PREFIXES
+            .iter()
+            .find_map(|prefix| text.strip_prefix(prefix))
+            .unwrap_or(text);
+        (!stripped.is_empty()).then_some(stripped)
+    }
+
+    /// Checks if the value is an object of the given schema.org type
+    fn is_type(&self, name: &str) -> bool {
+        let matches = |value: &Self| match value {
+            Self::String(type_name) =>
+                SCHEMA_PREFIXES
+                    .iter()
+                    .find_map(|prefix| type_name.strip_prefix(prefix))
+                    .unwrap_or(type_name)
+                    == name,
+            Self::Array(_)
+            | Self::Bool(_)
+            | Self::Null
+            | Self::Number(_)
+            | Self::Object(_) => false,
+        };
+        match self {
+            Self::Object(entries) => entries
+                .iter()
+                .filter(|(key, _)| key == "@type")
+                .any(|(_, value)| match value {
+                    Self::Array(types) => types.iter().any(matches),
+                    Self::Bool(_)
+                    | Self::Null
+                    | Self::Number(_)
+                    | Self::Object(_)
+                    | Self::String(_) => matches(value),
+                }),
+            Self::Array(_)
+            | Self::Bool(_)
+            | Self::Null
+            | Self::Number(_)
+            | Self::String(_) => false,
+        }
+    }
+
+    /// Returns the first value of an array, or the value itself
+    fn to_first(&self) -> &Self {
+        match self {
+            Self::Array(values) => values.first().unwrap_or(self),
+            Self::Bool(_)
+            | Self::Null
+            | Self::Number(_)
+            | Self::Object(_)
+            | Self::String(_) => self,
+        }
+    }
+}
+
+/// Returns the summaries of the annotations of an HTML body, one per line
+pub fn to_summaries(html: &str) -> Vec<String> {
+    let mut summaries = vec![];
+    for script in find_scripts(html) {
+        let mut chars = script.chars().peekable();
+        let Some(value) = parse_value(&mut chars, 0) else {
+            continue;
+        };
+        let mut items = vec![];
+        collect_items(&value, &mut items);
+        for summary in items.into_iter().filter_map(to_summary) {
+            if !summaries.contains(&summary) {
+                summaries.push(summary);
+            }
+        }
+    }
+    summaries
+}
+
+/// Returns the contents of the JSON-LD scripts of an HTML body
+fn find_scripts(html: &str) -> Vec<&str> {
+    let lower = html.to_ascii_lowercase();
+    let mut scripts = vec![];
+    let mut rest = 0;
+    while let Some(start) =
+        lower.get(rest..).and_then(|tail| tail.find("<script"))
+    {
+        let tag_start = rest.saturating_add(start);
+        let Some(tag_end) = lower
+            .get(tag_start..)
+            .and_then(|tail| tail.find('>'))
+            .map(|end| tag_start.saturating_add(end).saturating_add(1))
+        else {
+            break;
+        };
+        let Some(content_end) = lower
+            .get(tag_end..)
+            .and_then(|tail| tail.find("</script"))
+            .map(|end| tag_end.saturating_add(end))
+        else {
+            break;
+        };
+        if lower
+            .get(tag_start..tag_end)
+            .is_some_and(|tag| tag.contains(SCRIPT_TYPE))
+            && let Some(content) = html.get(tag_end..content_end)
+        {
+            scripts.push(content);
+        }
+        rest = content_end;
+    }
+    scripts
+}
+
+/// Collects the annotated items of a JSON-LD document, in the arrays and in
+/// the `@graph`
+fn collect_items<'value>(value: &'value Value, items: &mut Vec<&'value Value>) {
+    match value {
+        Value::Array(values) =>
+            for item in values {
+                collect_items(item, items);
+            },
+        Value::Object(entries) => {
+            match entries.iter().find(|(key, _)| key == "@graph") {
+                Some((_, graph)) => collect_items(graph, items),
+                None => items.push(value),
+            }
+        }
+        Value::Bool(_) | Value::Null | Value::Number(_) | Value::String(_) =>
+            (),
+    }
+}
+
+/// Returns the summary of an annotated item, if its type is supported
+fn to_summary(item: &Value) -> Option<String> {
+    if item.is_type("FlightReservation") {
+        Some(to_flight_summary(item))
+    } else if item.is_type("ParcelDelivery") {
+        Some(to_parcel_summary(item))
+    } else if item.is_type("Order") {
+        Some(to_order_summary(item))
+    } else {
+        None
+    }
+}
+
+/// Returns the summary of a flight reservation, e.g. `Flight AF1234 CDG →
+/// JFK, on 2024-05-02 10:00, booking ABC123`
+fn to_flight_summary(reservation: &Value) -> String {
+    let airport = |end: &str| {
+        reservation
+            .get_text(&["reservationFor", end, "iataCode"])
+            .or_else(|| reservation.get_text(&["reservationFor", end, "name"]))
+            .unwrap_or("?")
+    };
+    let number = [
+        reservation.get_text(&["reservationFor", "airline", "iataCode"]),
+        reservation.get_text(&["reservationFor", "flightNumber"]),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .concat();
+    let mut title = vec!["Flight".to_owned()];
+    if !number.is_empty() {
+        title.push(number);
+    }
+    title.push(format!(
+        "{} {ARROW} {}",
+        airport("departureAirport"),
+        airport("arrivalAirport")
+    ));
+    let mut clauses = vec![title.join(" ")];
+    if let Some(departure) =
+        reservation.get_text(&["reservationFor", "departureTime"])
+    {
+        clauses.push(format!("on {}", to_readable_date(departure)));
+    }
+    if let Some(booking) = reservation.get_text(&["reservationNumber"]) {
+        clauses.push(format!("booking {booking}"));
+    }
+    clauses.join(", ")
+}
+
+/// Returns the summary of a parcel delivery, e.g. `Parcel 1Z999 from UPS,
+/// expected on 2024-05-04, tracked at https://…`
+fn to_parcel_summary(delivery: &Value) -> String {
+    let mut title = vec!["Parcel".to_owned()];
+    title.extend(
+        delivery
+            .get_text(&["trackingNumber"])
+            .map(ToOwned::to_owned),
+    );
+    title.extend(
+        delivery
+            .get_text(&["carrier", "name"])
+            .or_else(|| delivery.get_text(&["provider", "name"]))
+            .map(|carrier| format!("from {carrier}")),
+    );
+    let mut clauses = vec![title.join(" ")];
+    if let Some(arrival) = delivery
+        .get_text(&["expectedArrivalUntil"])
+        .or_else(|| delivery.get_text(&["expectedArrivalFrom"]))
+    {
+        clauses.push(format!("expected on {}", to_readable_date(arrival)));
+    }
+    if let Some(url) = delivery.get_text(&["trackingUrl"]) {
+        clauses.push(format!("tracked at {url}"));
+    }
+    clauses.join(", ")
+}
+
+/// Returns the summary of an order, e.g. `Order 123-456 from Shop, 42.00
+/// EUR, Processing`
+fn to_order_summary(order: &Value) -> String {
+    let mut title = vec!["Order".to_owned()];
+    title.extend(order.get_text(&["orderNumber"]).map(ToOwned::to_owned));
+    title.extend(
+        order
+            .get_text(&["seller", "name"])
+            .or_else(|| order.get_text(&["merchant", "name"]))
+            .map(|merchant| format!("from {merchant}")),
+    );
+    let mut clauses = vec![title.join(" ")];
+    let price = order
+        .get_text(&["price"])
+        .map(|price| [Some(price), order.get_text(&["priceCurrency"])])
+        .or_else(|| {
+            order.get_text(&["totalPaymentDue", "price"]).map(|price| {
+                [
+                    Some(price),
+                    order.get_text(&["totalPaymentDue", "priceCurrency"]),
+                ]
+            })
+        });
+    if let Some(amount) = price {
+        clauses
+            .push(amount.into_iter().flatten().collect::<Vec<_>>().join(" "));
+    }
+    if let Some(status) = order.get_text(&["orderStatus"]) {
+        clauses.push(status.strip_prefix("Order").unwrap_or(status).to_owned());
+    }
+    clauses.join(", ")
+}
+
+/// Converts an ISO 8601 date, e.g. `2024-05-02T10:00:00+02:00`, to a readable
+/// one, e.g. `2024-05-02 10:00`
+///
+/// The date is returned as is if it isn't in this format.
+fn to_readable_date(date: &str) -> String {
+    match (date.get(..10), date.get(10..11), date.get(11..16)) {
+        (Some(day), Some("T"), Some(time)) => format!("{day} {time}"),
+        _ => date.to_owned(),
+    }
+}
+
+/// Parses a JSON value, skipping the whitespace around it
+///
+/// Returns `None` if the value is malformed or too deeply nested.
+fn parse_value(chars: &mut Peekable<Chars<'_>>, depth: usize) -> Option<Value> {
+    skip_whitespace(chars);
+    let value = match chars.peek()? {
+        '{' if depth < MAX_DEPTH => {
+            chars.next();
+            parse_object(chars, depth.saturating_add(1))?
+        }
+        '[' if depth < MAX_DEPTH => {
+            chars.next();
+            parse_array(chars, depth.saturating_add(1))?
+        }
+        '"' => {
+            chars.next();
+            Value::String(parse_string(chars)?)
+        }
+        't' | 'f' | 'n' => {
+            let word = take_word(chars);
+            match word.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => return None,
+            }
+        }
+        '-' | '0'..='9' => Value::Number(take_word(chars)),
+        _ => return None,
+    };
+    skip_whitespace(chars);
+    Some(value)
+}
+
+/// Parses the rest of a JSON array, after its `[`
+fn parse_array(chars: &mut Peekable<Chars<'_>>, depth: usize) -> Option<Value> {
+    let mut values = vec![];
+    skip_whitespace(chars);
+    if chars.next_if_eq(&']').is_some() {
+        return Some(Value::Array(values));
+    }
+    loop {
+        values.push(parse_value(chars, depth)?);
+        match chars.next()? {
+            ',' => (),
+            ']' => return Some(Value::Array(values)),
+            _ => return None,
+        }
+    }
+}
+
+/// Parses the rest of a JSON object, after its `{`
+fn parse_object(
+    chars: &mut Peekable<Chars<'_>>,
+    depth: usize,
+) -> Option<Value> {
+    let mut entries = vec![];
+    skip_whitespace(chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Some(Value::Object(entries));
+    }
+    loop {
+        skip_whitespace(chars);
+        chars.next_if_eq(&'"')?;
+        let key = parse_string(chars)?;
+        skip_whitespace(chars);
+        chars.next_if_eq(&':')?;
+        entries.push((key, parse_value(chars, depth)?));
+        match chars.next()? {
+            ',' => (),
+            '}' => return Some(Value::Object(entries)),
+            _ => return None,
+        }
+    }
+}
+
+/// Parses the rest of a JSON string, after its opening quote, decoding its
+/// escapes
+fn parse_string(chars: &mut Peekable<Chars<'_>>) -> Option<String> {
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => string.push(match chars.next()? {
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let hex = chars.by_ref().take(4).collect::<String>();
+                    u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER)
+                }
+                other => other,
+            }),
+            other => string.push(other),
+        }
+    }
+}
+
+/// Skips the whitespace
+fn skip_whitespace(chars: &mut Peekable<Chars<'_>>) {
+    while chars.next_if(char::is_ascii_whitespace).is_some() {}
+}
+
+/// Takes the characters of a literal or a number
+fn take_word(chars: &mut Peekable<Chars<'_>>) -> String {
+    let mut word = String::new();
+    while let Some(ch) = chars.next_if(|ch| {
+        ch.is_ascii_alphanumeric() || matches!(ch, '-' | '+' | '.')
+    }) {
+        word.push(ch);
+    }
+    word
+}
+
+#[cfg(test)]
+mod test {
+    use crate::annotations::to_summaries;
+
+    #[test]
+    fn annotations() {
+        let html = r#"<html><head><script type="application/ld+json">
+            {"@context": "http://schema.org", "@type": "FlightReservation",
+             "reservationNumber": "ABC123", "reservationFor": {
+               "@type": "Flight", "flightNumber": "1234",
+               "airline": {"@type": "Airline", "iataCode": "AF"},
+               "departureAirport": {"iataCode": "CDG"},
+               "arrivalAirport": {"name": "New York \u00c9"},
+               "departureTime": "2024-05-02T10:00:00+02:00"}}
+            </script><SCRIPT TYPE="application/ld+json">[
+              {"@type": "ParcelDelivery", "trackingNumber": "1Z999",
+               "carrier": {"name": "UPS"}, "expectedArrivalUntil": "2024-05-04",
+               "trackingUrl": "https://ups.example/1Z999"},
+              {"@graph": [{"@type": ["Order"], "orderNumber": 42,
+               "seller": {"name": "Shop"}, "price": "9.99",
+               "priceCurrency": "EUR",
+               "orderStatus": "http://schema.org/OrderProcessing"},
+               {"@type": "Event", "name": "Ignored"}]}
+            ]</SCRIPT><script type="application/ld+json">{"@type": "Order"
+            </script><script>var x = {"@type": "Order"};</script></head>
+            <body>Thanks!</body></html>"#;
+        assert_eq!(
+            to_summaries(html),
+            [
+                "Flight AF1234 CDG \u{2192} New York \u{c9}, on 2024-05-02 \
+                 10:00, booking ABC123",
+                "Parcel 1Z999 from UPS, expected on 2024-05-04, tracked at \
+                 https://ups.example/1Z999",
+                "Order 42 from Shop, 9.99 EUR, Processing"
+            ]
+        );
+        assert!(to_summaries("<p>No annotations</p>").is_empty());
+    }
+}
@@ -0,0 +1,427 @@
+//! Peeks into the archive attachments, to list their files and extract them
+//! one by one.
+//!
+//! The zip archives are read from their central directory, so listing them
+//! doesn't decompress anything, and only their stored and deflated files can
+//! be extracted. The tar archives, compressed with gzip or not, are read
+//! sequentially up to the wanted file.
+//!
+//! The archives are recognised by the name of the attachment, or else by its
+//! content type. Only the regular files are listed, and they are numbered
+//! from 1 in the order of the archive, e.g. `:extract 2.3 ~/Downloads`
+//! extracts the third file of the second attachment. The directories of the
+//! entries are dropped when extracting, to prevent writing outside of the
+//! wanted directory.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use tar::Archive;
+
+use crate::errors::Result;
+use crate::fetch::parser::Attachment;
+
+/// Compression method of the zip entries stored as they are
+const ZIP_STORED: u16 = 0;
+/// Compression method of the deflated zip entries
+const ZIP_DEFLATED: u16 = 8;
+/// Signature of a file header of the central directory of a zip archive
+const ZIP_CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+/// Signature of the end of the central directory of a zip archive
+const ZIP_END_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
+/// Signature of the local header of a file of a zip archive
+const ZIP_LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+/// Size of the fixed part of a file header of the central directory
+const ZIP_CENTRAL_SIZE: usize = 46;
+/// Size of the fixed part of the local header of a file
+const ZIP_LOCAL_SIZE: usize = 30;
+
+/// Errors that may occur while reading an archive attachment
+#[derive(Debug)]
+pub enum Error {
+    /// The archive is truncated or malformed.
+    Corrupted,
+    /// The attachment isn't an archive.
+    NotAnArchive,
+    /// The archive has no file with the given number.
+    MissingEntry(usize),
+    /// Failed to read the archive.
+    Read(io::Error),
+    /// The zip entry is compressed with an unsupported method.
+    UnsupportedCompression(u16),
+    /// Failed to write the extracted file.
+    Write(io::Error),
+}
+
+/// Format of an archive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// Uncompressed tar archive
+    Tar,
+    /// Tar archive compressed with gzip
+    TarGz,
+    /// Zip archive
+    Zip,
+}
+
+impl Format {
+    /// Recognises the format of an attachment from its name and MIME type,
+    /// if it is an archive
+    fn detect(file_name: &str, mime_type: &str) -> Option<Self> {
+        let path = Path::new(file_name);
+        let content_type = mime_type.to_lowercase();
+        let is_tar = |tar_path: &Path| has_extension(tar_path, "tar");
+        if has_extension(path, "tgz")
+            || (has_extension(path, "gz")
+                && path.file_stem().is_some_and(|stem| is_tar(stem.as_ref())))
+        {
+            Some(Self::TarGz)
+        } else if is_tar(path) || content_type == "application/x-tar" {
+            Some(Self::Tar)
+        } else if has_extension(path, "zip")
+            || content_type == "application/zip"
+            || content_type == "application/x-zip-compressed"
+        {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// File of an archive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Path of the file in the archive
+    name: String,
+    /// Size of the file once extracted, in bytes
+    size: u64,
+}
+
+impl Entry {
+    /// Returns the path of the file in the archive
+    pub fn as_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the size of the file once extracted, in bytes
+    pub const fn as_size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Zip entry, with where to find its content
+struct ZipEntry {
+    /// Compression method of the content
+    compression: u16,
+    /// Size of the compressed content
+    compressed_size: usize,
+    /// Name and size of the file
+    entry: Entry,
+    /// Offset of the local header of the file in the archive
+    offset: usize,
+}
+
+/// Extracts the file with the given number, from 1, of an archive attachment
+/// into a directory
+///
+/// Returns the path of the extracted file.
+pub fn extract(
+    attachment: &Attachment,
+    number: usize,
+    dir: &Path,
+) -> Result<PathBuf> {
+    let format = to_format(attachment).ok_or(Error::NotAnArchive)?;
+    let (name, content) = read_entry(format, attachment.as_data(), number)?;
+    let file_name = Path::new(&name)
+        .file_name()
+        .ok_or(Error::MissingEntry(number))?;
+    let path = dir.join(file_name);
+    fs::create_dir_all(dir).map_err(Error::Write)?;
+    fs::write(&path, content).map_err(Error::Write)?;
+    Ok(path)
+}
+
+/// Lists the files of an archive attachment
+///
+/// Returns `None` if the attachment isn't an archive or can't be read.
+pub fn list(attachment: &Attachment) -> Option<Vec<Entry>> {
+    read_entries(to_format(attachment)?, attachment.as_data())
+}
+
+/// Lists the files of an archive
+fn read_entries(format: Format, data: &[u8]) -> Option<Vec<Entry>> {
+    match format {
+        Format::Zip => read_zip_entries(data)
+            .ok()
+            .map(|entries| entries.into_iter().map(|zip| zip.entry).collect()),
+        Format::Tar | Format::TarGz => Some(
+            Archive::new(to_tar_reader(data, format))
+                .entries()
+                .ok()?
+                .filter_map(Result::ok)
+                .filter(|entry| entry.header().entry_type().is_file())
+                .map(|entry| Entry {
+                    name: entry
+                        .path()
+                        .map(|path| path.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    size: entry.size(),
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Returns the path and the content of the file with the given number, from
+/// 1, of an archive
+fn read_entry(
+    format: Format,
+    data: &[u8],
+    number: usize,
+) -> Result<(String, Vec<u8>), Error> {
+    let index = number.checked_sub(1).ok_or(Error::MissingEntry(number))?;
+    match format {
+        Format::Zip => {
+            let zip_entry = read_zip_entries(data)?
+                .into_iter()
+                .nth(index)
+                .ok_or(Error::MissingEntry(number))?;
+            let content = read_zip_content(data, &zip_entry)?;
+            Ok((zip_entry.entry.name, content))
+        }
+        Format::Tar | Format::TarGz => {
+            let mut archive = Archive::new(to_tar_reader(data, format));
+            let mut file = archive
+                .entries()
+                .map_err(Error::Read)?
+                .filter_map(Result::ok)
+                .filter(|entry| entry.header().entry_type().is_file())
+                .nth(index)
+                .ok_or(Error::MissingEntry(number))?;
+            let name = file
+                .path()
+                .map_err(Error::Read)?
+                .to_string_lossy()
+                .into_owned();
+            let mut content = vec![];
+            file.read_to_end(&mut content).map_err(Error::Read)?;
+            Ok((name, content))
+        }
+    }
+}
+
+/// Returns the format of an attachment, if it is an archive
+fn to_format(attachment: &Attachment) -> Option<Format> {
+    Format::detect(&attachment.to_file_name(), attachment.as_content_type())
+}
+
+/// Returns the reader of the content of a tar archive, decompressing it if
+/// needed
+fn to_tar_reader(data: &[u8], format: Format) -> Box<dyn Read + '_> {
+    match format {
+        Format::TarGz => Box::new(GzDecoder::new(data)),
+        Format::Tar | Format::Zip => Box::new(data),
+    }
+}
+
+/// Checks if a path has the given extension, ignoring the case
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|found| found.eq_ignore_ascii_case(extension))
+}
+
+/// Reads a little-endian number of 2 bytes at an offset
+#[expect(clippy::little_endian_bytes, reason = "zip numbers are little-endian")]
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Reads a little-endian number of 4 bytes at an offset
+#[expect(clippy::little_endian_bytes, reason = "zip numbers are little-endian")]
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Reads a little-endian number of 4 bytes at an offset, as a size or an
+/// offset
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    usize::try_from(read_u32(data, offset)?).ok()
+}
+
+/// Returns the decompressed content of a zip entry
+fn read_zip_content(
+    data: &[u8],
+    zip_entry: &ZipEntry,
+) -> Result<Vec<u8>, Error> {
+    let offset = zip_entry.offset;
+    if read_u32(data, offset) != Some(ZIP_LOCAL_SIGNATURE) {
+        return Err(Error::Corrupted);
+    }
+    let start = read_u16(data, offset.saturating_add(26))
+        .zip(read_u16(data, offset.saturating_add(28)))
+        .and_then(|(name_len, extra_len)| {
+            offset
+                .checked_add(ZIP_LOCAL_SIZE)?
+                .checked_add(usize::from(name_len))?
+                .checked_add(usize::from(extra_len))
+        })
+        .ok_or(Error::Corrupted)?;
+    let compressed = start
+        .checked_add(zip_entry.compressed_size)
+        .and_then(|end| data.get(start..end))
+        .ok_or(Error::Corrupted)?;
+    match zip_entry.compression {
+        ZIP_STORED => Ok(compressed.to_vec()),
+        ZIP_DEFLATED => {
+            let mut content = vec![];
+            DeflateDecoder::new(compressed)
+                .read_to_end(&mut content)
+                .map_err(Error::Read)?;
+            Ok(content)
+        }
+        method => Err(Error::UnsupportedCompression(method)),
+    }
+}
+
+/// Reads the files listed by the central directory of a zip archive
+fn read_zip_entries(data: &[u8]) -> Result<Vec<ZipEntry>, Error> {
+    let end = data
+        .windows(ZIP_END_SIGNATURE.len())
+        .rposition(|window| window == ZIP_END_SIGNATURE)
+        .ok_or(Error::Corrupted)?;
+    let count =
+        read_u16(data, end.saturating_add(10)).ok_or(Error::Corrupted)?;
+    let mut offset =
+        read_usize(data, end.saturating_add(16)).ok_or(Error::Corrupted)?;
+    let mut entries = vec![];
+    for _ in 0..count {
+        if read_u32(data, offset) != Some(ZIP_CENTRAL_SIGNATURE) {
+            return Err(Error::Corrupted);
+        }
+        let field = |position: usize| offset.saturating_add(position);
+        let (Some(compression), Some(compressed_size), Some(size)) = (
+            read_u16(data, field(10)),
+            read_usize(data, field(20)),
+            read_u32(data, field(24)),
+        ) else {
+            return Err(Error::Corrupted);
+        };
+        let (Some(name_len), Some(extra_len), Some(comment_len), Some(local)) = (
+            read_u16(data, field(28)),
+            read_u16(data, field(30)),
+            read_u16(data, field(32)),
+            read_usize(data, field(42)),
+        ) else {
+            return Err(Error::Corrupted);
+        };
+        let name_start = field(ZIP_CENTRAL_SIZE);
+        let raw_name = data
+            .get(name_start..name_start.saturating_add(usize::from(name_len)))
+            .ok_or(Error::Corrupted)?;
+        let name = String::from_utf8_lossy(raw_name).into_owned();
+        if !name.ends_with('/') {
+            entries.push(ZipEntry {
+                compression,
+                compressed_size,
+                entry: Entry { name, size: u64::from(size) },
+                offset: local,
+            });
+        }
+        offset = name_start
+            .saturating_add(usize::from(name_len))
+            .saturating_add(usize::from(extra_len))
+            .saturating_add(usize::from(comment_len));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use tar::{Builder, Header};
+
+    use crate::archive::{Entry, Format, read_entries, read_entry};
+
+    /// Builds a zip archive with a directory and a stored file
+    fn zip() -> Vec<u8> {
+        let name = b"docs/a.txt";
+        let mut data = vec![];
+        data.extend([0x50, 0x4b, 0x03, 0x04]);
+        data.extend([0; 14]);
+        data.extend([5, 0, 0, 0, 5, 0, 0, 0, 10, 0, 0, 0]);
+        data.extend(name);
+        data.extend(b"hello");
+        let central = data.len();
+        for (path, size) in [(&b"docs/"[..], 0), (&name[..], 5)] {
+            data.extend([0x50, 0x4b, 0x01, 0x02]);
+            data.extend([0; 16]);
+            data.extend([size, 0, 0, 0, size, 0, 0, 0]);
+            data.extend([u8::try_from(path.len()).unwrap_or_default(), 0]);
+            data.extend([0; 16]);
+            data.extend(path);
+        }
+        data.extend([0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0, 2, 0, 2, 0]);
+        data.extend([0; 4]);
+        data.extend([u8::try_from(central).unwrap_or_default(), 0, 0, 0]);
+        data.extend([0, 0]);
+        data
+    }
+
+    /// Builds a tar archive compressed with gzip, with one file
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn tar_gz() -> Vec<u8> {
+        let mut builder =
+            Builder::new(GzEncoder::new(vec![], Compression::default()));
+        let mut header = Header::new_gnu();
+        header.set_size(3);
+        header.set_mode(0o600);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "dir/b.csv", &b"1,2"[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(Format::detect("a.ZIP", "text/plain"), Some(Format::Zip));
+        assert_eq!(Format::detect("a", "application/zip"), Some(Format::Zip));
+        assert_eq!(Format::detect("a.tar.gz", ""), Some(Format::TarGz));
+        assert_eq!(Format::detect("a.tgz", ""), Some(Format::TarGz));
+        assert_eq!(Format::detect("a.tar", ""), Some(Format::Tar));
+        assert_eq!(Format::detect("a.pdf", "application/pdf"), None);
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn archives() {
+        let zip_data = zip();
+        assert_eq!(
+            read_entries(Format::Zip, &zip_data),
+            Some(vec![Entry { name: "docs/a.txt".to_owned(), size: 5 }])
+        );
+        assert_eq!(
+            read_entry(Format::Zip, &zip_data, 1).unwrap(),
+            ("docs/a.txt".to_owned(), b"hello".to_vec())
+        );
+        read_entry(Format::Zip, &zip_data, 2).unwrap_err();
+        read_entry(Format::Zip, &zip_data, 0).unwrap_err();
+        assert_eq!(read_entries(Format::Zip, b"not a zip"), None);
+
+        let tar_data = tar_gz();
+        assert_eq!(
+            read_entries(Format::TarGz, &tar_data),
+            Some(vec![Entry { name: "dir/b.csv".to_owned(), size: 3 }])
+        );
+        assert_eq!(
+            read_entry(Format::TarGz, &tar_data, 1).unwrap(),
+            ("dir/b.csv".to_owned(), b"1,2".to_vec())
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Computes statistics on the emails stored in the cache.
+//!
+//! The statistics are displayed by the `:stats` command of the TUI and by
+//! `mailbox stats`:
+//!
+//! - the number of emails per folder, per sender and per weekday,
+//! - the average time taken to answer an email, from the emails sent by one of
+//!   the identities of the account in reply to another one,
+//! - the storage taken by the attachments, per content type.
+//!
+//! Only the cached emails are counted, so the statistics of a folder that was
+//! never synchronised are empty. The attachments of the emails whose body was
+//! pruned aren't counted.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::cmp::Reverse;
+
+use mail_parser::{HeaderName, HeaderValue};
+
+use super::Cache;
+use super::retention::to_human_size;
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+
+/// Number of senders listed in the statistics
+const TOP_SENDERS: usize = 10;
+/// Names of the days of the week, from Sunday
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// Statistics on the emails of the cache
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// Number of emails and total size of the attachments, per content type
+    attachments: BTreeMap<String, (usize, u64)>,
+    /// Number of emails per folder
+    folders: BTreeMap<String, usize>,
+    /// Sum of the response times in seconds, and number of responses
+    responses: (u64, u64),
+    /// Number of emails per sender address
+    senders: BTreeMap<String, usize>,
+    /// Number of emails per day of the week, from Sunday
+    weekdays: [usize; 7],
+}
+
+impl Statistics {
+    /// Computes the statistics of every folder of the cache
+    ///
+    /// The emails sent from one of the given addresses are the ones of the
+    /// user, used to compute the response time.
+    pub fn compute(cache: &Cache, own_addresses: &[&str]) -> Result<Self> {
+        let mut emails = vec![];
+        let mut statistics = Self::default();
+        for folder in cache.list_folders()? {
+            for uid in cache.load_uids(&folder)? {
+                let Some(raw) = cache.load_email_or_headers(&folder, uid)?
+                else {
+                    continue;
+                };
+                if let Ok(email) = Email::try_from((uid, raw.as_bytes())) {
+                    statistics.add(&folder, &email);
+                    emails.push(email);
+                }
+            }
+        }
+        statistics.responses = to_response_times(&emails, own_addresses);
+        Ok(statistics)
+    }
+
+    /// Counts an email of a folder
+    fn add(&mut self, folder: &str, email: &Email) {
+        let count = self.folders.entry(folder.to_owned()).or_default();
+        *count = count.saturating_add(1);
+        if let Some(sender) = email.to_sender_address() {
+            let sent = self.senders.entry(sender.to_lowercase()).or_default();
+            *sent = sent.saturating_add(1);
+        }
+        if let Some(day) = to_date(email).and_then(|date| {
+            self.weekdays.get_mut(usize::from(date.day_of_week()))
+        }) {
+            *day = day.saturating_add(1);
+        }
+        for attachment in email.as_attachments() {
+            let (emails, size) = self
+                .attachments
+                .entry(attachment.as_content_type().to_lowercase())
+                .or_default();
+            *emails = emails.saturating_add(1);
+            *size = size.saturating_add(
+                u64::try_from(attachment.as_data().len()).unwrap_or(u64::MAX),
+            );
+        }
+    }
+
+    /// Returns the lines describing the statistics
+    pub fn to_lines(&self) -> Vec<String> {
+        let total = self.folders.values().sum::<usize>();
+        let mut lines = vec![
+            format!("{total} cached emails in {} folders", self.folders.len()),
+            String::new(),
+            "Emails per folder:".to_owned(),
+        ];
+        lines.extend(
+            self.folders
+                .iter()
+                .map(|(folder, count)| format!("  {folder:<30} {count}")),
+        );
+
+        lines.extend([String::new(), "Top senders:".to_owned()]);
+        let mut senders = self.senders.iter().collect::<Vec<_>>();
+        senders.sort_by_key(|(_, count)| Reverse(**count));
+        lines.extend(
+            senders
+                .iter()
+                .take(TOP_SENDERS)
+                .map(|(sender, count)| format!("  {sender:<30} {count}")),
+        );
+
+        lines.extend([String::new(), "Emails per weekday:".to_owned()]);
+        lines.extend(
+            WEEKDAYS
+                .iter()
+                .zip(self.weekdays)
+                .map(|(day, count)| format!("  {day} {count}")),
+        );
+
+        lines.push(String::new());
+        let (sum, count) = self.responses;
+        lines.push(sum.checked_div(count).map_or_else(
+            || "Average response time: no reply found".to_owned(),
+            |average| {
+                format!(
+                    "Average response time: {} (over {count} replies)",
+                    to_human_duration(average)
+                )
+            },
+        ));
+
+        lines.extend([String::new(), "Attachments per type:".to_owned()]);
+        let mut attachments = self.attachments.iter().collect::<Vec<_>>();
+        attachments.sort_by_key(|(_, (_, size))| Reverse(*size));
+        lines.extend(attachments.iter().map(
+            |(content_type, (files, size))| {
+                format!(
+                    "  {content_type:<30} {} ({files} files)",
+                    to_human_size(*size)
+                )
+            },
+        ));
+        lines
+    }
+}
+
+/// Returns the date of an email, if it has a valid one
+fn to_date(email: &Email) -> Option<&mail_parser::DateTime> {
+    email
+        .as_headers()
+        .get(&HeaderName::Date)
+        .and_then(HeaderValue::as_datetime)
+}
+
+/// Formats a number of seconds in a human-readable way, e.g. `3 h 05 min`
+fn to_human_duration(secs: u64) -> String {
+    let minutes = secs.checked_div(60).unwrap_or_default();
+    let hours = minutes.checked_div(60).unwrap_or_default();
+    let days = hours.checked_div(24).unwrap_or_default();
+    if days > 0 {
+        format!("{days} d {:02} h", hours.checked_rem(24).unwrap_or_default())
+    } else if hours > 0 {
+        format!(
+            "{hours} h {:02} min",
+            minutes.checked_rem(60).unwrap_or_default()
+        )
+    } else {
+        format!("{minutes} min")
+    }
+}
+
+/// Returns the sum of the response times in seconds, and the number of
+/// responses
+///
+/// A response is an email sent from one of the given addresses, replying to
+/// an email that wasn't.
+fn to_response_times(emails: &[Email], own_addresses: &[&str]) -> (u64, u64) {
+    let own = own_addresses
+        .iter()
+        .map(|address| address.to_lowercase())
+        .collect::<BTreeSet<_>>();
+    let is_own = |email: &Email| {
+        email
+            .to_sender_address()
+            .is_some_and(|sender| own.contains(&sender.to_lowercase()))
+    };
+    let received = emails
+        .iter()
+        .filter(|email| !is_own(email))
+        .filter_map(|email| {
+            Some((
+                email.get_header_str(&HeaderName::MessageId)?,
+                to_date(email)?.to_timestamp(),
+            ))
+        })
+        .collect::<BTreeMap<_, _>>();
+    emails
+        .iter()
+        .filter(|email| is_own(email))
+        .filter_map(|email| {
+            let replied = email.get_header_str(&HeaderName::InReplyTo)?;
+            let first = replied.split(',').next()?.trim();
+            let elapsed = to_date(email)?
+                .to_timestamp()
+                .checked_sub(*received.get(first)?)?;
+            u64::try_from(elapsed).ok()
+        })
+        .fold((0, 0), |(sum, count), elapsed| {
+            (sum.saturating_add(elapsed), count.saturating_add(1))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::analytics::{Statistics, to_response_times};
+    use crate::test_utils::email;
+
+    #[test]
+    fn statistics() {
+        let question = email(
+            "From: bob@example.com\nMessage-ID: <1@x>\nDate: Mon, 6 Jan 2025 10:00:00 +0000",
+        );
+        let answer = email(
+            "From: me@example.com\nIn-Reply-To: <1@x>\nDate: Mon, 6 Jan 2025 12:30:00 +0000",
+        );
+        assert_eq!(
+            to_response_times(
+                &[question.clone(), answer.clone()],
+                &["Me@example.com"]
+            ),
+            (9000, 1)
+        );
+
+        let mut statistics = Statistics::default();
+        statistics.add("INBOX", &question);
+        statistics.add("Sent", &answer);
+        statistics.responses = (9000, 1);
+        let lines = statistics.to_lines();
+        assert!(lines.contains(&"  Mon 2".to_owned()));
+        assert!(lines.contains(
+            &"Average response time: 2 h 30 min (over 1 replies)".to_owned()
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+//! Summarises the emails received recently, for `mailbox digest`.
+//!
+//! The digest lists the flagged and the unread emails, and counts the emails
+//! per sender and per mailing list, from their `List-Id` header. The emails of
+//! a mailing list aren't counted for their sender, as the sender of a list is
+//! often the list itself.
+//!
+//! Only the cached emails are summarised, with the flags of the last
+//! synchronisation, so the cache must be synchronised beforehand to include
+//! the latest emails.
+
+use alloc::collections::BTreeMap;
+use core::cmp::Reverse;
+
+use mail_parser::{DateTime, HeaderName, HeaderValue};
+
+use super::Cache;
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+
+/// Flag of the flagged emails
+const FLAGGED_FLAG: &str = "\\Flagged";
+/// Maximum number of flagged and unread emails listed
+const HIGHLIGHTS: usize = 20;
+/// Flag of the emails that were read
+const SEEN_FLAG: &str = "\\Seen";
+/// Number of senders and mailing lists listed
+const TOP_COUNTS: usize = 10;
+
+/// Format of the digest
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// HTML page
+    Html,
+    /// Markdown document, also readable as plain text
+    #[default]
+    Markdown,
+}
+
+impl Format {
+    /// Parses the name of a format, as given on the command line
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "html" => Some(Self::Html),
+            "markdown" | "md" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Email listed in the digest
+#[derive(Debug, PartialEq, Eq)]
+struct Highlight {
+    /// Folder of the email
+    folder: String,
+    /// Address of the sender
+    sender: String,
+    /// Subject of the email
+    subject: String,
+    /// Date of the email, as a UNIX timestamp
+    timestamp: i64,
+}
+
+/// Summary of the emails received since a date
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Digest {
+    /// Flagged emails, most recent first
+    flagged: Vec<Highlight>,
+    /// Number of emails per mailing list
+    lists: BTreeMap<String, usize>,
+    /// Number of emails per sender address, outside of the mailing lists
+    senders: BTreeMap<String, usize>,
+    /// Date of the oldest summarised emails, as a UNIX timestamp
+    since: i64,
+    /// Number of summarised emails
+    total: usize,
+    /// Unread emails, most recent first
+    unread: Vec<Highlight>,
+}
+
+impl Digest {
+    /// Summarises the emails of every folder of the cache received since the
+    /// given UNIX timestamp
+    pub fn compute(cache: &Cache, since: i64) -> Result<Self> {
+        let mut digest = Self { since, ..Self::default() };
+        for folder in cache.list_folders()? {
+            let flags = cache.load_flags(&folder)?;
+            for uid in cache.load_uids(&folder)? {
+                let Some(raw) = cache.load_email_or_headers(&folder, uid)?
+                else {
+                    continue;
+                };
+                let Ok(email) = Email::try_from((uid, raw.as_bytes())) else {
+                    continue;
+                };
+                let email_flags = flags.get(&uid);
+                digest.add(
+                    &folder,
+                    &email,
+                    email_flags.is_some_and(|set| set.contains(SEEN_FLAG)),
+                    email_flags.is_some_and(|set| set.contains(FLAGGED_FLAG)),
+                );
+            }
+        }
+        for highlights in [&mut digest.flagged, &mut digest.unread] {
+            highlights.sort_by_key(|highlight| Reverse(highlight.timestamp));
+        }
+        Ok(digest)
+    }
+
+    /// Counts an email of a folder, if it was received after the start of the
+    /// digest
+    fn add(&mut self, folder: &str, email: &Email, seen: bool, flagged: bool) {
+        let Some(timestamp) = email
+            .as_headers()
+            .get(&HeaderName::Date)
+            .and_then(HeaderValue::as_datetime)
+            .map(DateTime::to_timestamp)
+            .filter(|timestamp| *timestamp >= self.since)
+        else {
+            return;
+        };
+        self.total = self.total.saturating_add(1);
+        let sender =
+            email.to_sender_address().unwrap_or_default().to_lowercase();
+        let count = match email.get_header_by_name("List-Id") {
+            Some(list) => self.lists.entry(to_list_id(&list)).or_default(),
+            None => self.senders.entry(sender.clone()).or_default(),
+        };
+        *count = count.saturating_add(1);
+
+        let highlight = || Highlight {
+            folder: folder.to_owned(),
+            sender: sender.clone(),
+            subject: email
+                .get_header_str(&HeaderName::Subject)
+                .unwrap_or_default(),
+            timestamp,
+        };
+        if flagged {
+            self.flagged.push(highlight());
+        }
+        if !seen {
+            self.unread.push(highlight());
+        }
+    }
+
+    /// Returns the first line of the digest, e.g. `Digest since 2024-05-01`
+    pub fn to_title(&self) -> String {
+        let date = DateTime::from_timestamp(self.since);
+        format!(
+            "Digest since {:04}-{:02}-{:02}",
+            date.year, date.month, date.day
+        )
+    }
+
+    /// Formats the digest as a document
+    pub fn to_document(&self, format: Format) -> String {
+        let overview = format!(
+            "{} emails received, {} unread, {} flagged.",
+            self.total,
+            self.unread.len(),
+            self.flagged.len()
+        );
+        let sections = [
+            ("Flagged", to_highlight_rows(&self.flagged)),
+            ("Unread", to_highlight_rows(&self.unread)),
+            ("Top senders", to_count_rows(&self.senders)),
+            ("Mailing lists", to_count_rows(&self.lists)),
+        ];
+        match format {
+            Format::Html => to_html(&self.to_title(), &overview, &sections),
+            Format::Markdown =>
+                to_markdown(&self.to_title(), &overview, &sections),
+        }
+    }
+}
+
+/// Escapes the special characters of HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns the rows of the most frequent senders or lists, with their number
+/// of emails
+fn to_count_rows(counts: &BTreeMap<String, usize>) -> Vec<(String, String)> {
+    let mut sorted = counts.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|(_, count)| Reverse(**count));
+    sorted
+        .into_iter()
+        .take(TOP_COUNTS)
+        .map(|(name, count)| (name.clone(), count.to_string()))
+        .collect()
+}
+
+/// Returns the rows of the highlighted emails, with their subject and their
+/// sender and folder
+fn to_highlight_rows(highlights: &[Highlight]) -> Vec<(String, String)> {
+    highlights
+        .iter()
+        .take(HIGHLIGHTS)
+        .map(|highlight| {
+            (
+                highlight.subject.clone(),
+                format!("{} in {}", highlight.sender, highlight.folder),
+            )
+        })
+        .collect()
+}
+
+/// Formats the digest as an HTML page
+fn to_html(
+    title: &str,
+    overview: &str,
+    sections: &[(&str, Vec<(String, String)>)],
+) -> String {
+    let body = sections
+        .iter()
+        .filter(|(_, rows)| !rows.is_empty())
+        .map(|(name, rows)| {
+            let items = rows
+                .iter()
+                .map(|(main, detail)| {
+                    format!(
+                        "<li><strong>{}</strong> ({})</li>",
+                        escape_html(main),
+                        escape_html(detail)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("<h2>{name}</h2>\n<ul>\n{items}\n</ul>")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{title}</title></head>\n<body>\n<h1>{title}</h1>\n<p>{overview}</p>\n{body}\n</body>\n</html>\n"
+    )
+}
+
+/// Returns the identifier of a mailing list from its `List-Id` header, e.g.
+/// `dev.example.com` for `Developers <dev.example.com>`
+fn to_list_id(header: &str) -> String {
+    header
+        .rsplit_once('<')
+        .and_then(|(_, id)| id.split_once('>'))
+        .map_or(header, |(id, _)| id)
+        .trim()
+        .to_lowercase()
+}
+
+/// Formats the digest as a Markdown document
+fn to_markdown(
+    title: &str,
+    overview: &str,
+    sections: &[(&str, Vec<(String, String)>)],
+) -> String {
+    let body = sections
+        .iter()
+        .filter(|(_, rows)| !rows.is_empty())
+        .map(|(name, rows)| {
+            let items = rows
+                .iter()
+                .map(|(main, detail)| format!("- **{main}** ({detail})"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("## {name}\n\n{items}\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("# {title}\n\n{overview}\n\n{body}")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::digest::{Digest, Format, to_list_id};
+    use crate::test_utils::email;
+
+    #[test]
+    fn digest() {
+        assert_eq!(
+            to_list_id("Developers <dev.example.com>"),
+            "dev.example.com"
+        );
+        assert_eq!(to_list_id("dev.example.com"), "dev.example.com");
+
+        let mut digest = Digest { since: 1_700_000_000, ..Digest::default() };
+        let recent = "Date: Thu, 1 Aug 2024 10:00:00 +0000";
+        digest.add(
+            "INBOX",
+            &email(&format!(
+                "From: bob@example.com\nSubject: Report\n{recent}"
+            )),
+            false,
+            true,
+        );
+        digest.add(
+            "Lists",
+            &email(&format!(
+                "From: alice@example.com\nSubject: Release <1.0>\nList-Id: \
+                 Developers <dev.example.com>\n{recent}"
+            )),
+            false,
+            false,
+        );
+        digest.add(
+            "INBOX",
+            &email(
+                "From: old@example.com\nSubject: Old\nDate: Mon, 1 Jan 2001 \
+                 10:00:00 +0000",
+            ),
+            false,
+            false,
+        );
+
+        let markdown = digest.to_document(Format::Markdown);
+        assert!(markdown.starts_with(
+            "# Digest since 2023-11-14\n\n2 emails received, 2 unread, 1 \
+             flagged.\n\n## Flagged\n\n- **Report** (bob@example.com in \
+             INBOX)\n"
+        ));
+        assert!(
+            markdown.contains("## Mailing lists\n\n- **dev.example.com** (1)")
+        );
+        assert!(!markdown.contains("old@example.com"));
+        assert!(!markdown.contains("- **alice@example.com**"));
+
+        let html = digest.to_document(Format::Html);
+        assert!(html.contains("<h2>Top senders</h2>"));
+        assert!(html.contains("<strong>Release &lt;1.0&gt;</strong>"));
+    }
+}
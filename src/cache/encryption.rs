@@ -0,0 +1,119 @@
+//! Encrypts the files of the cache at rest.
+//!
+//! The key is derived with PBKDF2 from a secret printed by the [cache key
+//! command](crate::credentials::Credentials::as_cache_key_command) of the
+//! account, e.g. `secret-tool lookup mailbox cache` to read it from the
+//! keyring, and from a random salt stored in the cache of the account. The
+//! files are then encrypted with AES-256-GCM, so they can't be read nor
+//! tampered with without the secret.
+//!
+//! An encrypted file starts with a [header](MAGIC), followed by the nonce, the
+//! authentication tag and the ciphertext. The files written before the
+//! encryption was enabled are encrypted when the salt is generated, and a file
+//! that isn't encrypted is then refused, as it may have been planted.
+
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::rand::rand_bytes;
+use openssl::symm::{Cipher, decrypt_aead, encrypt_aead};
+
+/// Number of PBKDF2 iterations to derive the key from the secret
+const ITERATIONS: usize = 100_000;
+/// Length of the AES-256 key, in bytes
+const KEY_LEN: usize = 32;
+/// Header of the encrypted files
+const MAGIC: &[u8] = b"MAILBOX-AES-256-GCM\n";
+/// Length of the GCM nonce, in bytes
+const NONCE_LEN: usize = 12;
+/// Length of the salt of the key derivation, in bytes
+pub const SALT_LEN: usize = 16;
+/// Length of the GCM authentication tag, in bytes
+const TAG_LEN: usize = 16;
+
+/// Key used to encrypt the cache of an account
+#[derive(Clone)]
+pub struct Key([u8; KEY_LEN]);
+
+impl Key {
+    /// Decrypts the content of an encrypted file.
+    ///
+    /// Fails if the content was encrypted with another key or was tampered
+    /// with.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        let content = data.strip_prefix(MAGIC).unwrap_or(data);
+        let (nonce, rest) =
+            content.split_at_checked(NONCE_LEN).unwrap_or_default();
+        let (tag, ciphertext) =
+            rest.split_at_checked(TAG_LEN).unwrap_or_default();
+        decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(nonce),
+            MAGIC,
+            ciphertext,
+            tag,
+        )
+    }
+
+    /// Derives the key from the user's secret and the salt of the account
+    pub fn derive(secret: &[u8], salt: &[u8]) -> Result<Self, ErrorStack> {
+        let mut key = [0; KEY_LEN];
+        pbkdf2_hmac(
+            secret,
+            salt,
+            ITERATIONS,
+            MessageDigest::sha256(),
+            &mut key,
+        )?;
+        Ok(Self(key))
+    }
+
+    /// Encrypts the content of a file, with a new random nonce.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        let mut nonce = [0; NONCE_LEN];
+        rand_bytes(&mut nonce)?;
+        let mut tag = [0; TAG_LEN];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(&nonce),
+            MAGIC,
+            plaintext,
+            &mut tag,
+        )?;
+        Ok([MAGIC, &nonce, &tag, &ciphertext].concat())
+    }
+}
+
+/// Checks if the content of a file was encrypted
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::encryption::{Key, is_encrypted};
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn encrypt_and_decrypt() {
+        let key = Key::derive(b"secret", b"salt of the account").unwrap();
+        let plaintext = b"Subject: hello\r\n\r\nworld";
+
+        let encrypted = key.encrypt(plaintext).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_ne!(key.encrypt(plaintext).unwrap(), encrypted);
+        assert_eq!(key.decrypt(&encrypted).unwrap(), plaintext);
+
+        let mut tampered = encrypted.clone();
+        if let Some(last) = tampered.last_mut() {
+            *last ^= 1;
+        }
+        key.decrypt(&tampered).unwrap_err();
+
+        let other =
+            Key::derive(b"other secret", b"salt of the account").unwrap();
+        other.decrypt(&encrypted).unwrap_err();
+    }
+}
@@ -0,0 +1,235 @@
+//! Synchronises the flags of the emails in both directions.
+//!
+//! The cache keeps a snapshot of the flags as they were on the server at the
+//! last synchronisation. This snapshot is the common ancestor of the local
+//! flags (the snapshot with the queued flag changes applied) and of the
+//! current flags of the server, so that each side's changes can be told apart:
+//!
+//! - a flag changed locally only is pushed to the server,
+//! - a flag changed on the server only is pulled,
+//! - a flag changed on both sides in the same way is left as is.
+//!
+//! Neither side is blindly overwritten. Local changes on emails that don't
+//! exist on the server anymore, or made before the UIDVALIDITY of the mailbox
+//! changed, are dropped and reported as conflicts. The flag changes of the
+//! other mailboxes are left in the queue, to be replayed in their mailbox.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+
+use super::queue::{Location, Operation, Queue};
+use super::{Cache, lock};
+use crate::errors::Result;
+use crate::fetch::connection::{ImapSession, MailboxSelected};
+
+/// Flags of an email
+pub type FlagSet = BTreeSet<String>;
+
+/// Flags of the emails of a mailbox, indexed by uid
+pub type Flags = BTreeMap<u32, FlagSet>;
+
+/// Outcome of a three-way merge of the flags
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Reconciliation {
+    /// Number of local changes on emails that were removed from the server
+    conflicts: usize,
+    /// Flags of the emails after the merge, to be stored as the new snapshot
+    merged: Flags,
+    /// Flag changes to push to the server
+    to_push: Vec<Operation>,
+}
+
+/// Outcome of a synchronisation of the flags
+#[derive(Debug, Default)]
+pub struct FlagSyncSummary {
+    /// Number of local changes dropped because the email was removed
+    conflicts: usize,
+    /// Number of emails whose flags were changed on the server
+    pulled: usize,
+    /// Number of flag changes pushed to the server
+    pushed: usize,
+}
+
+impl FlagSyncSummary {
+    /// Returns a human-readable summary of the synchronisation
+    pub fn to_status(&self) -> String {
+        format!(
+            "Flags synchronised: {} pushed, {} pulled, {} conflicts",
+            self.pushed, self.pulled, self.conflicts
+        )
+    }
+}
+
+/// Synchronises the flags of a mailbox between the cache and the server.
+///
+/// The queued flag changes of the mailbox are merged with the changes of the
+/// server, pushed, and removed from the queue. The merged flags become the new
+/// snapshot.
+pub fn synchronise(
+    cache: &Cache,
+    session: &mut ImapSession<MailboxSelected>,
+    mailbox: &str,
+) -> Result<FlagSyncSummary> {
+    let _guard = lock::acquire(cache)?;
+    let queue = Queue::new(cache);
+    let base = cache.load_flags(mailbox)?;
+    let (flag_changes, other_operations): (Vec<_>, Vec<_>) =
+        queue.load()?.into_iter().partition(|operation| {
+            matches!(
+                operation,
+                Operation::AddFlag { target, .. }
+                | Operation::RemoveFlag { target, .. }
+                    if target.as_mailbox() == mailbox
+            )
+        });
+    let (valid_changes, outdated): (Vec<_>, Vec<_>) =
+        flag_changes.into_iter().partition(|operation| {
+            operation
+                .as_target()
+                .is_some_and(|target| target.is_valid_in(session.as_validity()))
+        });
+    let server = session.get_flags()?;
+    let local = apply_changes(&base, &valid_changes);
+
+    let reconciliation = reconcile(mailbox, &base, &local, &server);
+    for operation in &reconciliation.to_push {
+        match operation {
+            Operation::AddFlag { flag, target } =>
+                session.add_flag(target.as_uid(), flag)?,
+            Operation::RemoveFlag { flag, target } =>
+                session.remove_flag(target.as_uid(), flag)?,
+            Operation::Delete { .. }
+            | Operation::Move { .. }
+            | Operation::Send { .. } => (),
+        }
+    }
+
+    let pulled = server
+        .iter()
+        .filter(|(uid, flags)| base.get(uid).is_some_and(|old| old != *flags))
+        .count();
+    queue.save(&other_operations)?;
+    cache.store_flags(mailbox, &reconciliation.merged)?;
+
+    Ok(FlagSyncSummary {
+        conflicts: reconciliation.conflicts.saturating_add(outdated.len()),
+        pulled,
+        pushed: reconciliation.to_push.len(),
+    })
+}
+
+/// Applies the queued flag changes on the snapshot
+fn apply_changes(base: &Flags, changes: &[Operation]) -> Flags {
+    let mut local = base.clone();
+    for change in changes {
+        match change {
+            Operation::AddFlag { flag, target } => {
+                local
+                    .entry(target.as_uid())
+                    .or_default()
+                    .insert(flag.clone());
+            }
+            Operation::RemoveFlag { flag, target } => {
+                local.entry(target.as_uid()).or_default().remove(flag);
+            }
+            Operation::Delete { .. }
+            | Operation::Move { .. }
+            | Operation::Send { .. } => (),
+        }
+    }
+    local
+}
+
+/// Merges the local and server flags of a mailbox, from their common ancestor
+/// `base`
+fn reconcile(
+    mailbox: &str,
+    base: &Flags,
+    local: &Flags,
+    server: &Flags,
+) -> Reconciliation {
+    let mut reconciliation =
+        Reconciliation { merged: server.clone(), ..Reconciliation::default() };
+    let empty = FlagSet::new();
+
+    for (uid, local_flags) in local {
+        let base_flags = base.get(uid).unwrap_or(&empty);
+        if local_flags == base_flags {
+            continue;
+        }
+        let Some(server_flags) = server.get(uid) else {
+            reconciliation.conflicts =
+                reconciliation.conflicts.saturating_add(1);
+            continue;
+        };
+
+        let mut merged_flags = server_flags.clone();
+        for flag in local_flags.symmetric_difference(base_flags) {
+            if local_flags.contains(flag) {
+                if merged_flags.insert(flag.clone()) {
+                    reconciliation.to_push.push(Operation::AddFlag {
+                        flag: flag.clone(),
+                        target: Location::new(mailbox, *uid),
+                    });
+                }
+            } else if merged_flags.remove(flag) {
+                reconciliation.to_push.push(Operation::RemoveFlag {
+                    flag: flag.clone(),
+                    target: Location::new(mailbox, *uid),
+                });
+            } else {
+                // The flag was removed on both sides.
+            }
+        }
+        reconciliation.merged.insert(*uid, merged_flags);
+    }
+
+    reconciliation
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::flags::{FlagSet, Flags, Reconciliation, reconcile};
+    use crate::cache::queue::{Location, Operation};
+
+    /// Builds a set of flags
+    fn flags(names: &[&str]) -> FlagSet {
+        names.iter().map(|name| (*name).to_owned()).collect()
+    }
+
+    #[test]
+    fn three_way_merge() {
+        let base = Flags::from([
+            (1, flags(&["\\Seen"])),
+            (2, flags(&[])),
+            (3, flags(&[])),
+            (4, flags(&["\\Seen"])),
+        ]);
+        let local = Flags::from([
+            (1, flags(&[])),
+            (2, flags(&["\\Flagged"])),
+            (3, flags(&["\\Seen"])),
+            (4, flags(&["\\Seen"])),
+        ]);
+        let server = Flags::from([
+            (1, flags(&["\\Seen", "\\Answered"])),
+            (2, flags(&["\\Flagged", "\\Seen"])),
+            (4, flags(&[])),
+        ]);
+
+        assert_eq!(
+            reconcile("INBOX", &base, &local, &server),
+            Reconciliation {
+                conflicts: 1,
+                merged: Flags::from([
+                    (1, flags(&["\\Answered"])),
+                    (2, flags(&["\\Flagged", "\\Seen"])),
+                    (4, flags(&[])),
+                ]),
+                to_push: vec![Operation::RemoveFlag {
+                    flag: "\\Seen".to_owned(),
+                    target: Location::new("INBOX", 1)
+                }],
+            }
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Keeps the cache consistent when the app stops in the middle of a write.
+//!
+//! Every file of the cache is written with [`write_atomic`]: the content goes
+//! to a temporary file next to its target, which is flushed to the disk and
+//! then renamed over the target. A power loss thus leaves either the old or the
+//! new content, but never a truncated file. The cache is only made of plain
+//! files, so there is no database journal to configure.
+//!
+//! The files damaged before, or by the disk itself, are detected by [`repair`]
+//! when the TUI or `mailbox watch` starts. It checks the files changed since
+//! the previous check, whose time is kept by the `checked` file of the cache:
+//!
+//! - the temporary files left by an interrupted write are removed,
+//! - a file that can't be decrypted, whose text isn't valid, or an empty email,
+//!   is removed if it can be fetched again, i.e. the emails and indexes of the
+//!   folders of the server, which are synced again, and the pictures of the
+//!   senders,
+//! - the other damaged files, i.e. the state of the app, the queue, the outbox
+//!   and the [local folders](super::local), are set aside with the `.damaged`
+//!   extension, and the list of emails of a local folder is rebuilt from its
+//!   email files,
+//! - the queued operations that can't be parsed, or whose message is missing
+//!   from the outbox, are dropped.
+//!
+//! As a wrong cache key would make every file look damaged, the first check
+//! only removes the temporary files, and the next ones stop with an error if
+//! the `checked` file can't be decrypted.
+
+use core::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::queue::Queue;
+use super::{AVATARS_DIR, Cache, Error, OUTBOX_DIR, local, lock};
+use crate::errors::{self, Result};
+
+/// Name of the file of the cache marking the time of the last check
+const CHECKED_FILE: &str = "checked";
+/// Suffix of the damaged files that were set aside
+const DAMAGED_SUFFIX: &str = ".damaged";
+/// Suffix of the temporary files, renamed over their target once written
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// Outcome of a check of the cache
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Repairs {
+    /// Number of queued operations dropped
+    dropped: usize,
+    /// Number of local folders whose list of emails was rebuilt
+    rebuilt: usize,
+    /// Number of damaged files removed, to be fetched again
+    removed: usize,
+    /// Number of damaged files set aside, as they can't be fetched again
+    set_aside: usize,
+}
+
+impl fmt::Display for Repairs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cache repaired: {} damaged files removed to be fetched again, {} \
+             set aside as *{DAMAGED_SUFFIX}, {} local folders rebuilt, {} \
+             queued operations dropped",
+            self.removed, self.set_aside, self.rebuilt, self.dropped
+        )
+    }
+}
+
+impl Repairs {
+    /// Checks if nothing had to be repaired
+    pub const fn is_empty(&self) -> bool {
+        self.dropped == 0
+            && self.rebuilt == 0
+            && self.removed == 0
+            && self.set_aside == 0
+    }
+}
+
+/// Kind of the files of a directory of the cache
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Content {
+    /// Pictures of the senders, fetched again when missing
+    Avatars,
+    /// Emails and indexes of a folder of the server, synced again
+    Folder,
+    /// Emails and indexes of a local folder, only stored in the cache
+    LocalFolder,
+    /// Messages waiting to be sent
+    Outbox,
+    /// State files of the app, at the root of the cache
+    State,
+}
+
+impl Content {
+    /// Returns the kind of the files of a directory of the cache, from its
+    /// name
+    fn from_dir(name: &str) -> Self {
+        match name {
+            AVATARS_DIR => Self::Avatars,
+            OUTBOX_DIR => Self::Outbox,
+            _ if local::is_folder(name) => Self::LocalFolder,
+            _ => Self::Folder,
+        }
+    }
+
+    /// Checks if the damaged files can be removed, to be fetched again
+    const fn can_fetch(self) -> bool {
+        match self {
+            Self::Avatars | Self::Folder => true,
+            Self::LocalFolder | Self::Outbox | Self::State => false,
+        }
+    }
+
+    /// Checks if the files are binary, and can't be checked as text
+    const fn is_binary(self) -> bool {
+        match self {
+            Self::Avatars | Self::Outbox => true,
+            Self::Folder | Self::LocalFolder | Self::State => false,
+        }
+    }
+}
+
+/// Checks the files of the cache changed since the last check, and repairs
+/// the damaged ones
+///
+/// # Errors
+///
+/// Returns an error if the cache can't be read or written, or if the key
+/// can't decrypt the mark of the last check.
+pub fn repair(cache: &Cache) -> Result<Repairs> {
+    let _guard = lock::acquire(cache)?;
+    let checked = cache.as_root().join(CHECKED_FILE);
+    let since = match fs::metadata(&checked).and_then(|file| file.modified()) {
+        Ok(modified) => {
+            cache.read(&checked)?;
+            Some(modified)
+        }
+        Err(_) => None,
+    };
+    let mut repairs = Repairs::default();
+    repair_dir(cache, cache.as_root(), Content::State, since, &mut repairs)?;
+    for entry in fs::read_dir(cache.as_root()).map_err(Error::Read)? {
+        let path = entry.map_err(Error::Read)?.path();
+        if let Some(name) = path.file_name().and_then(|name| name.to_str())
+            && path.is_dir()
+        {
+            let content = Content::from_dir(name);
+            if repair_dir(cache, &path, content, since, &mut repairs)?
+                && content == Content::LocalFolder
+            {
+                rebuild_uids(cache, name, &path)?;
+                repairs.rebuilt = repairs.rebuilt.saturating_add(1);
+            }
+        }
+    }
+    repairs.dropped = Queue::new(cache).repair()?;
+    cache.write(&checked, b"checked")?;
+    Ok(repairs)
+}
+
+/// Checks the files of a directory of the cache, and repairs the damaged
+/// ones
+///
+/// Returns `true` if the list of uids was damaged.
+fn repair_dir(
+    cache: &Cache,
+    dir: &Path,
+    content: Content,
+    since: Option<SystemTime>,
+    repairs: &mut Repairs,
+) -> Result<bool> {
+    let mut has_damaged_uids = false;
+    for entry in fs::read_dir(dir).map_err(Error::Read)? {
+        let path = entry.map_err(Error::Read)?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if path.is_dir() {
+            continue;
+        }
+        if name.ends_with(TEMP_SUFFIX) {
+            fs::remove_file(&path).map_err(Error::Write)?;
+            continue;
+        }
+        let Some(last_check) = since else {
+            continue;
+        };
+        if [CHECKED_FILE, "lock", "salt"].contains(&name)
+            || name.ends_with(DAMAGED_SUFFIX)
+            || !is_modified_since(&path, last_check)
+            || !is_damaged(cache, &path, content)?
+        {
+            continue;
+        }
+        if content.can_fetch() {
+            fs::remove_file(&path).map_err(Error::Write)?;
+            repairs.removed = repairs.removed.saturating_add(1);
+        } else {
+            fs::rename(&path, with_suffix(&path, DAMAGED_SUFFIX))
+                .map_err(Error::Write)?;
+            repairs.set_aside = repairs.set_aside.saturating_add(1);
+        }
+        has_damaged_uids |= name == "uids";
+    }
+    Ok(has_damaged_uids)
+}
+
+/// Checks if a file of the cache is damaged
+fn is_damaged(cache: &Cache, path: &Path, content: Content) -> Result<bool> {
+    let data = match cache.read(path) {
+        Ok(Some(data)) => data,
+        Ok(None) => return Ok(false),
+        Err(errors::Error::Cache(
+            Error::Decrypt(_) | Error::NotEncrypted(_),
+        )) => return Ok(true),
+        Err(err) => return Err(err),
+    };
+    if content.is_binary() {
+        return Ok(false);
+    }
+    let is_email = path
+        .extension()
+        .is_some_and(|extension| extension == "eml" || extension == "headers");
+    Ok(str::from_utf8(&data).is_err()
+        || data.contains(&0)
+        || (is_email && data.is_empty()))
+}
+
+/// Checks if a file was changed since the given time
+fn is_modified_since(path: &Path, time: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified >= time)
+}
+
+/// Rebuilds the list of uids of a local folder from its email files, from the
+/// most recent
+fn rebuild_uids(cache: &Cache, folder: &str, dir: &Path) -> Result {
+    let mut uids = fs::read_dir(dir)
+        .map_err(Error::Read)?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            name.to_str()?.strip_suffix(".eml")?.parse::<u32>().ok()
+        })
+        .collect::<Vec<_>>();
+    uids.sort_unstable_by(|left, right| right.cmp(left));
+    cache.store_uids(folder, &uids)
+}
+
+/// Returns the path of a file with a suffix appended to its name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Writes a file atomically, so that it is never left truncated
+///
+/// The data is written to a temporary file, flushed to the disk, and then
+/// renamed over the file. The directory is flushed too, for the rename to
+/// survive a power loss.
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp = with_suffix(path, TEMP_SUFFIX);
+    let mut file = File::create(&temp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::cache::integrity::{repair, write_atomic};
+    use crate::cache::local::add_email;
+    use crate::cache::queue::{Location, Operation, Queue};
+    use crate::test_utils::TempDir;
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn integrity() {
+        let dir = TempDir::new("integrity");
+        let cache = dir.open_cache();
+        let root = cache.as_root().to_owned();
+        cache.store_email("INBOX", 1, "Subject: a\n\nA").unwrap();
+        cache.store_email("INBOX", 2, "Subject: b\n\nB").unwrap();
+        cache.store_uids("INBOX", &[2, 1]).unwrap();
+        let folder = "local:Archives";
+        add_email(&cache, folder, "Subject: c\n\nC", [].into()).unwrap();
+        add_email(&cache, folder, "Subject: d\n\nD", [].into()).unwrap();
+        Queue::new(&cache)
+            .push(Operation::Delete { target: Location::new("INBOX", 2) })
+            .unwrap();
+        assert!(repair(&cache).unwrap().is_empty());
+
+        fs::write(root.join("INBOX/1.eml"), "").unwrap();
+        fs::write(root.join("INBOX/2.eml.tmp"), "Subject: b").unwrap();
+        fs::write(root.join("local:Archives/uids"), [0xff, 0]).unwrap();
+        fs::write(
+            root.join("queue"),
+            "delete \"INBOX\" - 2\nsend 9.eml a@x b@x\nmov",
+        )
+        .unwrap();
+        let repairs = repair(&cache).unwrap();
+        assert_eq!(
+            repairs.to_string(),
+            "Cache repaired: 1 damaged files removed to be fetched again, 1 \
+             set aside as *.damaged, 1 local folders rebuilt, 2 queued \
+             operations dropped"
+        );
+        assert_eq!(cache.load_email("INBOX", 1).unwrap(), None);
+        assert!(cache.load_email("INBOX", 2).unwrap().is_some());
+        assert!(!root.join("INBOX/2.eml.tmp").exists());
+        assert!(root.join("local:Archives/uids.damaged").exists());
+        assert_eq!(cache.load_uids(folder).unwrap(), [2, 1]);
+        assert_eq!(
+            Queue::new(&cache).load().unwrap(),
+            [Operation::Delete { target: Location::new("INBOX", 2) }]
+        );
+        assert!(repair(&cache).unwrap().is_empty());
+
+        write_atomic(&root.join("draft"), b"Hello").unwrap();
+        assert_eq!(cache.load_state("draft").as_deref(), Some("Hello"));
+        assert!(!root.join("draft.tmp").exists());
+    }
+}
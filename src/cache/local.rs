@@ -0,0 +1,109 @@
+//! Stores folders that only exist in the cache, never synced with the server.
+//!
+//! A local folder is named with the [`FOLDER_PREFIX`], e.g.
+//! `local:Archives 2019`, and is stored like the other folders of the cache,
+//! with its own uids starting at 1. It keeps emails for the long term, after
+//! they were deleted from the server to save space there:
+//!
+//! - moving an email to a local folder stores a copy in it, and then deletes
+//!   the email from its folder,
+//! - moving an email out of a local folder uploads it to the target folder, and
+//!   then removes it from the local folder.
+//!
+//! The flags of the emails of a local folder are changed in the cache only, and
+//! the local folders are never pruned by the [`retention`](super::retention).
+
+use std::fs;
+
+use super::flags::FlagSet;
+use super::{Cache, Error, lock};
+use crate::errors::Result;
+
+/// Prefix of the names of the local folders
+pub const FOLDER_PREFIX: &str = "local:";
+
+/// Checks if a folder only exists in the cache
+pub fn is_folder(name: &str) -> bool {
+    name.starts_with(FOLDER_PREFIX)
+}
+
+/// Adds an email to a local folder, with its flags, and returns its uid
+///
+/// The email gets the uid following the highest one of the folder, and is
+/// listed as the most recent one.
+pub fn add_email(
+    cache: &Cache,
+    folder: &str,
+    raw: &str,
+    flags: FlagSet,
+) -> Result<u32> {
+    let _guard = lock::acquire(cache)?;
+    let mut uids = cache.load_uids(folder)?;
+    let uid = uids.iter().max().map_or(1, |max| max.saturating_add(1));
+    cache.store_email(folder, uid, raw)?;
+    uids.insert(0, uid);
+    cache.store_uids(folder, &uids)?;
+    let mut folder_flags = cache.load_flags(folder)?;
+    folder_flags.insert(uid, flags);
+    cache.store_flags(folder, &folder_flags)?;
+    Ok(uid)
+}
+
+/// Removes an email from a local folder, with its flags
+pub fn remove_email(cache: &Cache, folder: &str, uid: u32) -> Result {
+    let _guard = lock::acquire(cache)?;
+    let mut uids = cache.load_uids(folder)?;
+    uids.retain(|other| *other != uid);
+    cache.store_uids(folder, &uids)?;
+    let mut folder_flags = cache.load_flags(folder)?;
+    folder_flags.remove(&uid);
+    cache.store_flags(folder, &folder_flags)?;
+    let path = cache.email_path(folder, uid);
+    if path.exists() {
+        fs::remove_file(path).map_err(Error::Write)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::local::{add_email, is_folder, remove_email};
+    use crate::test_utils::TempDir;
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn local_folders() {
+        assert!(is_folder("local:Archives 2019"));
+        assert!(!is_folder("INBOX"));
+
+        let dir = TempDir::new("local");
+        let cache = dir.open_cache();
+        let folder = "local:Archives";
+        let seen = ["\\Seen".to_owned()].into();
+        assert_eq!(
+            add_email(&cache, folder, "Subject: a\n\nA", seen).unwrap(),
+            1
+        );
+        assert_eq!(
+            add_email(&cache, folder, "Subject: b\n\nB", [].into()).unwrap(),
+            2
+        );
+        assert_eq!(cache.load_uids(folder).unwrap(), [2, 1]);
+        assert!(
+            cache
+                .load_flags(folder)
+                .unwrap()
+                .get(&1)
+                .is_some_and(|flags| flags.contains("\\Seen"))
+        );
+
+        remove_email(&cache, folder, 1).unwrap();
+        assert_eq!(cache.load_uids(folder).unwrap(), [2]);
+        assert_eq!(cache.load_email(folder, 1).unwrap(), None);
+        assert!(!cache.load_flags(folder).unwrap().contains_key(&1));
+        assert_eq!(
+            add_email(&cache, folder, "Subject: c\n\nC", [].into()).unwrap(),
+            3
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Serialises the changes of the processes sharing a cache.
+//!
+//! The TUI, `mailbox watch` and `mailbox send` can run at the same time on the
+//! cache of an account. The changes that read a file and write it back, e.g.
+//! pushing an operation to the queue or merging the flags, hold the exclusive
+//! lock of the `lock` file of the cache, so that another process can't write
+//! the file in between and have its change lost.
+//!
+//! Replaying the queue holds the lock until every operation was applied, so
+//! that two processes never send the same queued message twice. To not keep
+//! the TUI waiting meanwhile, the watch asks a TUI running for the same account
+//! to replay the queue instead, through the [control
+//! socket](crate::tui::control).
+//!
+//! The lock is released when its [`Guard`] is dropped, or when the process
+//! dies, so a crash never leaves the cache locked. The locks don't nest: a
+//! process must not take the lock while it already holds it.
+
+use std::fs::{File, OpenOptions};
+
+use super::{Cache, Error};
+use crate::errors::Result;
+
+/// Name of the lock file, at the root of the cache
+const LOCK_FILE: &str = "lock";
+
+/// Exclusive lock on the cache, released when dropped
+#[derive(Debug)]
+pub struct Guard {
+    /// Open lock file, holding the lock
+    file: File,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.file.unlock().unwrap_or_default();
+    }
+}
+
+/// Takes the exclusive lock of the cache, waiting for the other processes to
+/// release it
+pub fn acquire(cache: &Cache) -> Result<Guard> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(cache.as_root().join(LOCK_FILE))
+        .map_err(Error::Lock)?;
+    file.lock().map_err(Error::Lock)?;
+    Ok(Guard { file })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+
+    use crate::cache::lock::acquire;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn lock() {
+        let dir = TempDir::new("lock");
+        let cache = dir.open_cache();
+        let path = cache.as_root().join("lock");
+        let guard = acquire(&cache).unwrap();
+        let other = File::open(&path).unwrap();
+        other.try_lock().unwrap_err();
+        drop(guard);
+        other.try_lock().unwrap();
+        other.unlock().unwrap();
+        drop(acquire(&cache).unwrap());
+    }
+}
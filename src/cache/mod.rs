@@ -0,0 +1,582 @@
+//! Stores the emails locally, to read them without network access.
+//!
+//! The cache is a directory per account, in the
+//! [cache directory](crate::config::Config::as_cache_dir):
+//!
+//! ```text
+//! bob@example.com/
+//! ├── INBOX/
+//! │   ├── uids        uids of the mailbox, most recent first
+//! │   ├── flags       flags of the emails at the last synchronisation
+//! │   ├── attachments number of attachments of every email
+//! │   ├── progress    uid of the last email fetched by a full sync
+//! │   ├── threads     conversation of every email, see [`threads`]
+//! │   ├── 1.eml       raw email with uid 1
+//! │   ├── 2.headers   headers of the email with uid 2, see [`retention`]
+//! │   └── ...
+//! ├── local:Archives/  folder that only exists in the cache, see [`local`]
+//! ├── queue           pending operations, see [`queue`]
+//! ├── outbox/         emails waiting to be sent
+//! ├── avatars/        pictures of the senders, see [`pictures`]
+//! ├── views           state of the email list of every folder in the TUI
+//! ├── folders         folder names of the account, to complete them
+//! ├── session         folder, mode and opened email of the last TUI session
+//! ├── draft           content of the writer, to recover it after a crash
+//! ├── vacation        senders answered automatically, see [`vacation`]
+//! ├── senders         blocked and allowed senders, see [`senders`]
+//! ├── migration-*     last email copied per folder by `mailbox migrate`
+//! ├── lock            held by the process changing the cache, see [`lock`]
+//! ├── checked         time of the last check of the cache, see [`integrity`]
+//! └── salt            salt of the encryption key, see [`encryption`]
+//! ```
+//!
+//! [`vacation`]: crate::send::vacation
+//! [`senders`]: crate::tui::senders
+//! [`pictures`]: crate::tui::pictures
+//!
+//! If a [cache key
+//! command](crate::credentials::Credentials::as_cache_key_command) is set for
+//! the account, every file but the salt and the lock is encrypted.
+
+pub mod analytics;
+pub mod digest;
+pub mod encryption;
+pub mod flags;
+pub mod integrity;
+pub mod local;
+pub mod lock;
+pub mod queue;
+pub mod retention;
+pub mod threads;
+
+use alloc::collections::BTreeMap;
+use alloc::string::FromUtf8Error;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use encryption::{Key, SALT_LEN, is_encrypted};
+use flags::Flags;
+use integrity::write_atomic;
+use openssl::error::ErrorStack;
+use openssl::rand::rand_bytes;
+use queue::Queue;
+use threads::ThreadIndex;
+
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::fetch::connection::ImapSession;
+use crate::fetch::mailbox::Mailbox;
+use crate::pipe::{self, pipe};
+
+/// Directory of the cache storing the pictures of the senders
+const AVATARS_DIR: &str = "avatars";
+/// Directory of the cache storing the emails waiting to be sent
+const OUTBOX_DIR: &str = "outbox";
+/// File of the cache storing the salt of the encryption key, never encrypted
+const SALT_FILE: &str = "salt";
+
+/// Errors that may occur while reading or writing the cache.
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to create a directory of the cache.
+    CreateDir(io::Error),
+    /// Failed to decrypt a file, because the key is wrong or the file was
+    /// tampered with.
+    Decrypt(ErrorStack),
+    /// The cache key command printed an empty secret.
+    EmptyKey,
+    /// Failed to encrypt a file or to derive the key.
+    Encrypt(ErrorStack),
+    /// A file of the cache isn't valid UTF-8.
+    InvalidEncoding(FromUtf8Error),
+    /// A line of the queue isn't a valid operation.
+    InvalidOperation(String),
+    /// Failed to run the cache key command.
+    KeyCommand(pipe::Error),
+    /// Failed to take the lock of the cache.
+    Lock(io::Error),
+    /// A file of the cache is encrypted, but no cache key command was set.
+    MissingKey(PathBuf),
+    /// A file of the cache isn't encrypted, although encryption is enabled,
+    /// so it may have been planted.
+    NotEncrypted(PathBuf),
+    /// Failed to read a file of the cache.
+    Read(io::Error),
+    /// Failed to write a file of the cache.
+    Write(io::Error),
+}
+
+/// Local storage of the emails of an account
+#[derive(Clone)]
+pub struct Cache {
+    /// Key to encrypt the files, if encryption is enabled
+    key: Option<Key>,
+    /// Directory of the account in the cache
+    root: PathBuf,
+}
+
+impl Cache {
+    /// Returns the directory of the account in the cache
+    pub fn as_root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Loads the number of attachments of the emails of a mailbox, indexed by
+    /// uid
+    ///
+    /// Returns no counts if they were never stored.
+    pub fn load_attachment_counts(
+        &self,
+        mailbox: &str,
+    ) -> BTreeMap<u32, usize> {
+        self.read_to_string(&self.mailbox_dir(mailbox).join("attachments"))
+            .ok()
+            .flatten()
+            .map(|content| {
+                content
+                    .lines()
+                    .filter_map(|line| {
+                        let (uid, count) = line.split_once(' ')?;
+                        Some((uid.parse().ok()?, count.parse().ok()?))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Lists the folders stored in the cache, by the names of their
+    /// directories
+    pub fn list_folders(&self) -> Result<Vec<String>> {
+        let mut folders = vec![];
+        for entry in fs::read_dir(&self.root).map_err(Error::Read)? {
+            let path = entry.map_err(Error::Read)?.path();
+            if let Some(name) = path.file_name().and_then(|name| name.to_str())
+                && path.is_dir()
+                && ![OUTBOX_DIR, AVATARS_DIR].contains(&name)
+            {
+                folders.push(name.to_owned());
+            }
+        }
+        Ok(folders)
+    }
+
+    /// Loads the picture of a sender, stored with the hash of their address
+    ///
+    /// Returns `None` if it was never fetched, and an empty picture if the
+    /// sender has none.
+    pub fn load_avatar(&self, hash: &str) -> Option<Vec<u8>> {
+        self.read(&self.root.join(AVATARS_DIR).join(hash))
+            .ok()
+            .flatten()
+    }
+
+    /// Loads a raw email from the cache, if it was stored.
+    pub fn load_email(
+        &self,
+        mailbox: &str,
+        uid: u32,
+    ) -> Result<Option<String>> {
+        self.read_to_string(&self.email_path(mailbox, uid))
+    }
+
+    /// Loads a raw email from the cache, or its headers if its body was
+    /// pruned.
+    pub fn load_email_or_headers(
+        &self,
+        mailbox: &str,
+        uid: u32,
+    ) -> Result<Option<String>> {
+        self.load_email(mailbox, uid)?.map_or_else(
+            || self.load_headers(mailbox, uid),
+            |raw| Ok(Some(raw)),
+        )
+    }
+
+    /// Loads the flags of the emails at the last synchronisation
+    ///
+    /// Returns no flags if the mailbox was never synchronised.
+    pub fn load_flags(&self, mailbox: &str) -> Result<Flags> {
+        Ok(self
+            .read_to_string(&self.mailbox_dir(mailbox).join("flags"))?
+            .map(|content| {
+                content
+                    .lines()
+                    .filter_map(|line| {
+                        let mut words = line.split(' ');
+                        let uid = words.next()?.parse().ok()?;
+                        Some((
+                            uid,
+                            words
+                                .filter(|flag| !flag.is_empty())
+                                .map(ToOwned::to_owned)
+                                .collect(),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Loads the headers of an email whose body was pruned from the cache
+    pub fn load_headers(
+        &self,
+        mailbox: &str,
+        uid: u32,
+    ) -> Result<Option<String>> {
+        self.read_to_string(&self.headers_path(mailbox, uid))
+    }
+
+    /// Loads a state file of the app, stored at the root of the cache
+    ///
+    /// Returns `None` if the state was never stored.
+    pub fn load_state(&self, name: &str) -> Option<String> {
+        self.read_to_string(&self.root.join(name)).ok().flatten()
+    }
+
+    /// Loads the uid of the last email fetched by the full sync of a mailbox
+    ///
+    /// Returns `None` if no full sync was started.
+    pub fn load_sync_progress(&self, mailbox: &str) -> Option<u32> {
+        self.read_to_string(&self.mailbox_dir(mailbox).join("progress"))
+            .ok()
+            .flatten()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Loads the conversations of the emails of a mailbox
+    ///
+    /// Returns an empty index if the mailbox was never synced.
+    pub fn load_thread_index(&self, mailbox: &str) -> ThreadIndex {
+        self.read_to_string(&self.mailbox_dir(mailbox).join("threads"))
+            .ok()
+            .flatten()
+            .map(|content| ThreadIndex::parse(&content))
+            .unwrap_or_default()
+    }
+
+    /// Loads the uids of a mailbox, most recent first
+    ///
+    /// Returns an empty list if the mailbox was never stored.
+    pub fn load_uids(&self, mailbox: &str) -> Result<Vec<u32>> {
+        Ok(self
+            .read_to_string(&self.mailbox_dir(mailbox).join("uids"))?
+            .map(|content| {
+                content
+                    .lines()
+                    .filter_map(|line| line.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Loads the UIDVALIDITY of a mailbox when its uids were last stored
+    ///
+    /// Returns `None` if the server didn't send it, or if the mailbox was
+    /// never stored.
+    pub fn load_validity(&self, mailbox: &str) -> Option<u32> {
+        self.read_to_string(&self.mailbox_dir(mailbox).join("validity"))
+            .ok()
+            .flatten()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Opens the cache of an account, creating its directory if needed.
+    ///
+    /// If a key command is given, the secret it prints is used to encrypt the
+    /// cache. The files written before the encryption was enabled are
+    /// encrypted the first time.
+    pub fn open(
+        cache_dir: &Path,
+        account: &str,
+        key_command: Option<&str>,
+    ) -> Result<Self> {
+        let root = cache_dir.join(account);
+        fs::create_dir_all(&root).map_err(Error::CreateDir)?;
+        let Some(command) = key_command else {
+            return Ok(Self { key: None, root });
+        };
+        let (key, is_new) = Self::load_key(&root, command)?;
+        let cache = Self { key: Some(key), root };
+        if is_new {
+            let _guard = lock::acquire(&cache)?;
+            cache.encrypt_dir(&cache.root)?;
+        }
+        Ok(cache)
+    }
+
+    /// Encrypts the files of a directory of the cache and of its
+    /// subdirectories that aren't encrypted yet
+    fn encrypt_dir(&self, dir: &Path) -> Result {
+        // The entries are listed first, not to list the temporary files of
+        // the atomic writes.
+        let paths = fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| Ok(entry?.path()))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .map_err(Error::Read)?;
+        for path in paths {
+            if path.is_dir() {
+                self.encrypt_dir(&path)?;
+                continue;
+            }
+            if path.parent() == Some(&self.root)
+                && (path.ends_with(SALT_FILE) || path.ends_with("lock"))
+            {
+                continue;
+            }
+            let data = fs::read(&path).map_err(Error::Read)?;
+            if !is_encrypted(&data) {
+                self.write(&path, &data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the number of attachments of the emails of a mailbox.
+    pub fn store_attachment_counts(
+        &self,
+        mailbox: &str,
+        counts: &BTreeMap<u32, usize>,
+    ) -> Result {
+        fs::create_dir_all(self.mailbox_dir(mailbox))
+            .map_err(Error::CreateDir)?;
+        let content = counts
+            .iter()
+            .map(|(uid, count)| format!("{uid} {count}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.write(
+            &self.mailbox_dir(mailbox).join("attachments"),
+            content.as_bytes(),
+        )
+    }
+
+    /// Stores the picture of a sender, with the hash of their address.
+    pub fn store_avatar(&self, hash: &str, picture: &[u8]) -> Result {
+        let dir = self.root.join(AVATARS_DIR);
+        fs::create_dir_all(&dir).map_err(Error::CreateDir)?;
+        self.write(&dir.join(hash), picture)
+    }
+
+    /// Stores a raw email in the cache.
+    pub fn store_email(&self, mailbox: &str, uid: u32, raw: &str) -> Result {
+        fs::create_dir_all(self.mailbox_dir(mailbox))
+            .map_err(Error::CreateDir)?;
+        self.write(&self.email_path(mailbox, uid), raw.as_bytes())?;
+        let headers = self.headers_path(mailbox, uid);
+        if headers.exists() {
+            fs::remove_file(headers).map_err(Error::Write)?;
+        }
+        Ok(())
+    }
+
+    /// Stores the flags of the emails of a mailbox.
+    pub fn store_flags(&self, mailbox: &str, flags: &Flags) -> Result {
+        fs::create_dir_all(self.mailbox_dir(mailbox))
+            .map_err(Error::CreateDir)?;
+        let content = flags
+            .iter()
+            .map(|(uid, email_flags)| {
+                let mut words = vec![uid.to_string()];
+                words.extend(email_flags.iter().cloned());
+                words.join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.write(&self.mailbox_dir(mailbox).join("flags"), content.as_bytes())
+    }
+
+    /// Stores a state file of the app at the root of the cache.
+    pub fn store_state(&self, name: &str, content: &str) -> Result {
+        self.write(&self.root.join(name), content.as_bytes())
+    }
+
+    /// Stores the uid of the last email fetched by the full sync of a
+    /// mailbox.
+    pub fn store_sync_progress(&self, mailbox: &str, uid: u32) -> Result {
+        fs::create_dir_all(self.mailbox_dir(mailbox))
+            .map_err(Error::CreateDir)?;
+        self.write(
+            &self.mailbox_dir(mailbox).join("progress"),
+            uid.to_string().as_bytes(),
+        )
+    }
+
+    /// Stores the conversations of the emails of a mailbox.
+    pub fn store_thread_index(
+        &self,
+        mailbox: &str,
+        index: &ThreadIndex,
+    ) -> Result {
+        fs::create_dir_all(self.mailbox_dir(mailbox))
+            .map_err(Error::CreateDir)?;
+        self.write(
+            &self.mailbox_dir(mailbox).join("threads"),
+            index.to_content().as_bytes(),
+        )
+    }
+
+    /// Stores the uids of a mailbox, most recent first.
+    pub fn store_uids(&self, mailbox: &str, uids: &[u32]) -> Result {
+        fs::create_dir_all(self.mailbox_dir(mailbox))
+            .map_err(Error::CreateDir)?;
+        let content = uids
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.write(&self.mailbox_dir(mailbox).join("uids"), content.as_bytes())
+    }
+
+    /// Stores the UIDVALIDITY of a mailbox, that the queued operations on its
+    /// emails are checked against.
+    pub fn store_validity(&self, mailbox: &str, validity: u32) -> Result {
+        fs::create_dir_all(self.mailbox_dir(mailbox))
+            .map_err(Error::CreateDir)?;
+        self.write(
+            &self.mailbox_dir(mailbox).join("validity"),
+            validity.to_string().as_bytes(),
+        )
+    }
+
+    /// Synchronises the cache with the server
+    ///
+    /// The flags are merged with the server's, and the other queued
+    /// operations are replayed. Returns a human-readable summary.
+    pub fn synchronise(
+        &self,
+        credentials: &Credentials,
+        mailbox: &str,
+    ) -> Result<String> {
+        let mut session = ImapSession::with_credentials(credentials)?
+            .select_mailbox(&Mailbox::try_from(mailbox)?)?;
+        let flags = flags::synchronise(self, &mut session, mailbox)?;
+        let replay = Queue::new(self).replay(credentials, &mut session)?;
+        Ok(format!("{} {replay}", flags.to_status()))
+    }
+
+    /// Returns the path of a raw email in the cache
+    fn email_path(&self, mailbox: &str, uid: u32) -> PathBuf {
+        self.mailbox_dir(mailbox).join(format!("{uid}.eml"))
+    }
+
+    /// Returns the path of the headers of an email whose body was pruned
+    fn headers_path(&self, mailbox: &str, uid: u32) -> PathBuf {
+        self.mailbox_dir(mailbox).join(format!("{uid}.headers"))
+    }
+
+    /// Derives the encryption key of the account
+    ///
+    /// The salt is generated the first time encryption is enabled, in which
+    /// case `true` is returned with the key.
+    fn load_key(root: &Path, command: &str) -> Result<(Key, bool)> {
+        let salt_path = root.join(SALT_FILE);
+        let (salt, is_new) = match fs::read(&salt_path) {
+            Ok(salt) => (salt, false),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let mut salt = [0; SALT_LEN];
+                rand_bytes(&mut salt).map_err(Error::Encrypt)?;
+                write_atomic(&salt_path, &salt).map_err(Error::Write)?;
+                (salt.to_vec(), true)
+            }
+            Err(err) => return Err(Error::Read(err).into()),
+        };
+        let output = pipe(command, vec![]).map_err(Error::KeyCommand)?;
+        let secret = output.trim_ascii_end();
+        if secret.is_empty() {
+            return Err(Error::EmptyKey.into());
+        }
+        Ok((Key::derive(secret, &salt).map_err(Error::Encrypt)?, is_new))
+    }
+
+    /// Reads a file of the cache, decrypting it if needed
+    ///
+    /// Returns `None` if the file doesn't exist. Once encryption is enabled,
+    /// a file that isn't encrypted is refused, as only a process without the
+    /// key could have written it.
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound =>
+                return Ok(None),
+            Err(err) => return Err(Error::Read(err).into()),
+        };
+        match (&self.key, is_encrypted(&data)) {
+            (Some(key), true) =>
+                Ok(Some(key.decrypt(&data).map_err(Error::Decrypt)?)),
+            (Some(_), false) =>
+                Err(Error::NotEncrypted(path.to_path_buf()).into()),
+            (None, true) => Err(Error::MissingKey(path.to_path_buf()).into()),
+            (None, false) => Ok(Some(data)),
+        }
+    }
+
+    /// Reads a text file of the cache, decrypting it if needed
+    ///
+    /// Returns `None` if the file doesn't exist.
+    fn read_to_string(&self, path: &Path) -> Result<Option<String>> {
+        self.read(path)?
+            .map(|data| {
+                String::from_utf8(data)
+                    .map_err(|err| Error::InvalidEncoding(err).into())
+            })
+            .transpose()
+    }
+
+    /// Writes a file of the cache atomically, encrypting it if encryption is
+    /// enabled.
+    fn write(&self, path: &Path, data: &[u8]) -> Result {
+        match &self.key {
+            Some(key) => {
+                let encrypted = key.encrypt(data).map_err(Error::Encrypt)?;
+                write_atomic(path, &encrypted)
+            }
+            None => write_atomic(path, data),
+        }
+        .map_err(Error::Write)?;
+        Ok(())
+    }
+
+    /// Returns the directory of a mailbox in the cache
+    ///
+    /// The path separators are replaced, so that the mailbox can't escape
+    /// the directory of the account.
+    fn mailbox_dir(&self, mailbox: &str) -> PathBuf {
+        self.root
+            .join(mailbox.replace(['/', '\\'], "_").replace("..", "__"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![expect(clippy::unwrap_used, reason = "test")]
+
+    use std::fs;
+
+    use crate::cache::Cache;
+    use crate::cache::encryption::is_encrypted;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn enable_encryption() {
+        let dir = TempDir::new("enable-encryption");
+        let path = dir.as_path().join("bob@example.com").join("folders");
+        dir.open_cache().write(&path, b"INBOX").unwrap();
+        assert!(!is_encrypted(&fs::read(&path).unwrap()));
+
+        let encrypted =
+            Cache::open(dir.as_path(), "bob@example.com", Some("echo secret"))
+                .unwrap();
+        assert!(is_encrypted(&fs::read(&path).unwrap()));
+        assert_eq!(encrypted.read(&path).unwrap().unwrap(), b"INBOX");
+
+        fs::write(&path, b"Planted").unwrap();
+        encrypted.read(&path).unwrap_err();
+    }
+}
@@ -0,0 +1,701 @@
+//! Queues the operations made while offline, to replay them once online.
+//!
+//! The queue is a text file with one operation per line:
+//!
+//! ```text
+//! add-flag "INBOX" 1700000000 42 \Seen
+//! remove-flag "INBOX" 1700000000 42 \Flagged
+//! move "INBOX" 1700000000 42 Archives/2024
+//! delete "Archives 2023" - 43
+//! send 1700000000000.eml bob@example.com alice@example.com
+//! send 1700000000001.eml bob@example.com notify=failure,delay eve@example.com
+//! ```
+//!
+//! The operations on an email start with the quoted name of its mailbox, the
+//! UIDVALIDITY of the mailbox when the operation was made, or `-` if it isn't
+//! known, and its uid. They are replayed in the mailbox of the email, and
+//! dropped if the UIDVALIDITY of the mailbox changed since, as the uid may
+//! then designate another email.
+//!
+//! The `notify` word lists the requested [delivery status
+//! notifications](crate::send::dsn), if any.
+//!
+//! The messages to send are stored in the `outbox` directory of the cache. Like
+//! the other files of the cache, the queue and the messages are encrypted if
+//! encryption is enabled.
+//!
+//! The flag changes are merged with the changes of the server (see
+//! [`flags`](super::flags)), and the other operations are then replayed in
+//! order. When they conflict with the server, the server wins: an operation on
+//! an email that doesn't exist on the server anymore, because another client
+//! moved or deleted it, is dropped and reported as a conflict.
+//!
+//! A message that the SMTP server refuses for good, with a `5xx` reply, is
+//! moved to the `outbox/failed` directory and reported as a conflict too, so
+//! that it doesn't block the following operations. The replay only stops on
+//! the transient failures, e.g. a lost connection or a `4xx` reply, to try
+//! again later.
+
+use core::fmt;
+use core::time::Duration;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Cache, Error, lock};
+use crate::credentials::Credentials;
+use crate::errors::{self, Result};
+use crate::fetch::connection::{ImapSession, MailboxSelected};
+use crate::fetch::mailbox::Mailbox;
+use crate::send::connection::SmtpSession;
+use crate::send::dsn::Dsn;
+use crate::send::quota::{SendLimits, Throttle};
+
+/// Directory of the outbox storing the messages refused by the server
+const FAILED_DIR: &str = "failed";
+/// Prefix of the word listing the delivery status notifications of a message
+const NOTIFY_PREFIX: &str = "notify=";
+/// Word written instead of an unknown UIDVALIDITY
+const UNKNOWN_VALIDITY: &str = "-";
+
+/// Location of the email an operation applies to
+#[derive(Debug, PartialEq, Eq)]
+pub struct Location {
+    /// Name of the mailbox of the email
+    mailbox: String,
+    /// Uid of the email in its mailbox
+    uid: u32,
+    /// UIDVALIDITY of the mailbox when the operation was made, if known
+    validity: Option<u32>,
+}
+
+impl Location {
+    /// Returns the name of the mailbox of the email
+    pub fn as_mailbox(&self) -> &str {
+        &self.mailbox
+    }
+
+    /// Returns the uid of the email in its mailbox
+    pub const fn as_uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Creates the location of the email with the given uid in a mailbox
+    ///
+    /// The UIDVALIDITY of the mailbox is read from the cache when the
+    /// operation is queued.
+    pub fn new(mailbox: &str, uid: u32) -> Self {
+        Self { mailbox: mailbox.to_owned(), uid, validity: None }
+    }
+
+    /// Checks if the email still exists on the server, given the UIDVALIDITY
+    /// and the uids of its mailbox
+    ///
+    /// The email doesn't exist anymore if the UIDVALIDITY changed since the
+    /// operation was made, as its uid may then designate another email.
+    pub fn matches(&self, validity: Option<u32>, uids: &[u32]) -> bool {
+        uids.contains(&self.uid) && self.is_valid_in(validity)
+    }
+
+    /// Checks if the UIDVALIDITY of the mailbox didn't change since the
+    /// operation was made, if both are known
+    pub fn is_valid_in(&self, validity: Option<u32>) -> bool {
+        self.validity
+            .zip(validity)
+            .is_none_or(|(queued, current)| queued == current)
+    }
+
+    /// Parses the location at the start of the arguments of an operation
+    ///
+    /// Returns the location and the remaining arguments.
+    fn parse(arguments: &str) -> Option<(Self, &str)> {
+        let (mailbox, after_mailbox) = unquote(arguments)?;
+        let mut words = after_mailbox.strip_prefix(' ')?.splitn(3, ' ');
+        let validity = match words.next()? {
+            UNKNOWN_VALIDITY => None,
+            number => Some(number.parse().ok()?),
+        };
+        let uid = words.next()?.parse().ok()?;
+        Some((Self { mailbox, uid, validity }, words.next().unwrap_or("")))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let validity = self
+            .validity
+            .map_or_else(|| UNKNOWN_VALIDITY.to_owned(), |id| id.to_string());
+        write!(f, "{} {validity} {}", quote(&self.mailbox), self.uid)
+    }
+}
+
+/// Operation made while offline
+#[derive(Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// Adds a flag to an email
+    AddFlag {
+        /// IMAP flag, e.g. `\Seen`
+        flag: String,
+        /// Email to flag
+        target: Location,
+    },
+    /// Deletes an email
+    Delete {
+        /// Email to delete
+        target: Location,
+    },
+    /// Moves an email to another mailbox
+    Move {
+        /// Name of the destination mailbox
+        mailbox: String,
+        /// Email to move
+        target: Location,
+    },
+    /// Removes a flag from an email
+    RemoveFlag {
+        /// IMAP flag, e.g. `\Seen`
+        flag: String,
+        /// Email to unflag
+        target: Location,
+    },
+    /// Sends a message stored in the outbox
+    Send {
+        /// Delivery status notifications to request
+        dsn: Dsn,
+        /// Name of the message file in the outbox
+        file: String,
+        /// Envelope sender
+        from: String,
+        /// Envelope recipients
+        recipients: Vec<String>,
+    },
+}
+
+impl Operation {
+    /// Parses a line of the queue
+    fn parse(line: &str) -> Option<Self> {
+        let (name, arguments) = line.split_once(' ')?;
+        if name == "send" {
+            return Self::parse_send(arguments);
+        }
+        let (target, rest) = Location::parse(arguments)?;
+        match (name, rest.is_empty()) {
+            ("add-flag", false) =>
+                Some(Self::AddFlag { flag: rest.to_owned(), target }),
+            ("delete", true) => Some(Self::Delete { target }),
+            ("move", false) =>
+                Some(Self::Move { mailbox: rest.to_owned(), target }),
+            ("remove-flag", false) =>
+                Some(Self::RemoveFlag { flag: rest.to_owned(), target }),
+            _ => None,
+        }
+    }
+
+    /// Parses the arguments of a message to send
+    fn parse_send(arguments: &str) -> Option<Self> {
+        let (file, rest) = arguments.split_once(' ')?;
+        let mut addresses = rest.split(' ').map(ToOwned::to_owned).peekable();
+        let from = addresses.next()?;
+        let dsn = addresses
+            .next_if(|word| word.starts_with(NOTIFY_PREFIX))
+            .map(|word| {
+                Dsn::parse(word.get(NOTIFY_PREFIX.len()..).unwrap_or_default())
+            })
+            .unwrap_or_default();
+        Some(Self::Send {
+            dsn,
+            file: file.to_owned(),
+            from,
+            recipients: addresses.collect(),
+        })
+    }
+
+    /// Returns the email the operation applies to, if any
+    pub const fn as_target(&self) -> Option<&Location> {
+        match self {
+            Self::AddFlag { target, .. }
+            | Self::Delete { target }
+            | Self::Move { target, .. }
+            | Self::RemoveFlag { target, .. } => Some(target),
+            Self::Send { .. } => None,
+        }
+    }
+
+    /// Returns the email the operation applies to, if any, to change it
+    const fn as_target_mut(&mut self) -> Option<&mut Location> {
+        match self {
+            Self::AddFlag { target, .. }
+            | Self::Delete { target }
+            | Self::Move { target, .. }
+            | Self::RemoveFlag { target, .. } => Some(target),
+            Self::Send { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddFlag { flag, target } =>
+                write!(f, "add-flag {target} {flag}"),
+            Self::Delete { target } => write!(f, "delete {target}"),
+            Self::Move { mailbox, target } =>
+                write!(f, "move {target} {mailbox}"),
+            Self::RemoveFlag { flag, target } =>
+                write!(f, "remove-flag {target} {flag}"),
+            Self::Send { dsn, file, from, recipients } => {
+                let notify = if dsn.is_requested() {
+                    format!(" {NOTIFY_PREFIX}{}", dsn.to_names())
+                } else {
+                    String::new()
+                };
+                write!(f, "send {file} {from}{notify} {}", recipients.join(" "))
+            }
+        }
+    }
+}
+
+/// Mailbox selected while replaying the queue
+struct Selection {
+    /// Name of the mailbox
+    mailbox: String,
+    /// Uids of the emails of the mailbox
+    uids: Vec<u32>,
+    /// UIDVALIDITY of the mailbox, if the server sent it
+    validity: Option<u32>,
+}
+
+/// Outcome of a replay of the queue
+#[derive(Default)]
+pub struct ReplaySummary {
+    /// Number of operations applied on the server
+    applied: usize,
+    /// Number of operations dropped because they conflicted with the server,
+    /// or messages it refused for good
+    conflicts: usize,
+}
+
+impl fmt::Display for ReplaySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Replayed {} queued operations ({} dropped after conflicts)",
+            self.applied, self.conflicts
+        )
+    }
+}
+
+/// Pending operations of an account
+pub struct Queue<'cache> {
+    /// Cache of the account, to read and write the files
+    cache: &'cache Cache,
+    /// Directory of the messages waiting to be sent
+    outbox: PathBuf,
+    /// Path of the queue file
+    path: PathBuf,
+}
+
+impl<'cache> Queue<'cache> {
+    /// Checks if there are no pending operations
+    pub fn is_empty(&self) -> bool {
+        self.load().is_ok_and(|operations| operations.is_empty())
+    }
+
+    /// Loads the pending operations, in order
+    pub fn load(&self) -> Result<Vec<Operation>> {
+        let Some(content) = self.cache.read_to_string(&self.path)? else {
+            return Ok(vec![]);
+        };
+        Ok(content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                Operation::parse(line)
+                    .ok_or_else(|| Error::InvalidOperation(line.to_owned()))
+            })
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Drops the operations that can't be parsed, and the messages to send
+    /// that are missing from the outbox
+    ///
+    /// Returns the number of dropped operations.
+    pub fn repair(&self) -> Result<usize> {
+        let Some(content) = self.cache.read_to_string(&self.path)? else {
+            return Ok(0);
+        };
+        let lines = content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let operations = lines
+            .iter()
+            .filter_map(|line| Operation::parse(line))
+            .filter(|operation| {
+                !matches!(operation, Operation::Send { file, .. }
+                    if !self.outbox.join(file).exists())
+            })
+            .collect::<Vec<_>>();
+        let dropped = lines.len().saturating_sub(operations.len());
+        if dropped > 0 {
+            self.save(&operations)?;
+        }
+        Ok(dropped)
+    }
+
+    /// Returns the queue of the account of the given cache
+    pub fn new(cache: &'cache Cache) -> Self {
+        Self {
+            cache,
+            outbox: cache.as_root().join("outbox"),
+            path: cache.as_root().join("queue"),
+        }
+    }
+
+    /// Adds an operation at the end of the queue.
+    ///
+    /// The operation is stamped with the UIDVALIDITY of the mailbox of its
+    /// email, as stored in the cache, if it has none.
+    pub fn push(&self, mut operation: Operation) -> Result {
+        let _guard = lock::acquire(self.cache)?;
+        if let Some(target) = operation.as_target_mut()
+            && target.validity.is_none()
+        {
+            target.validity = self.cache.load_validity(&target.mailbox);
+        }
+        self.append(operation)
+    }
+
+    /// Adds an operation at the end of the queue, while holding the lock of
+    /// the cache
+    fn append(&self, operation: Operation) -> Result {
+        let mut operations = self.load()?;
+        operations.push(operation);
+        self.save(&operations)
+    }
+
+    /// Stores a message in the outbox, and queues it to be sent.
+    ///
+    /// The message file is named after the current time, made unique if
+    /// several messages are queued in the same millisecond.
+    pub fn push_send(
+        &self,
+        from: String,
+        recipients: Vec<String>,
+        message: &[u8],
+        dsn: Dsn,
+    ) -> Result {
+        let _guard = lock::acquire(self.cache)?;
+        fs::create_dir_all(&self.outbox).map_err(Error::CreateDir)?;
+        let mut millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis());
+        while self.outbox.join(format!("{millis}.eml")).exists() {
+            millis = millis.saturating_add(1);
+        }
+        let file = format!("{millis}.eml");
+        self.cache.write(&self.outbox.join(&file), message)?;
+        self.append(Operation::Send { dsn, file, from, recipients })
+    }
+
+    /// Replays the pending operations on the server.
+    ///
+    /// The lock of the cache is held during the whole replay, so that another
+    /// process can't replay the same operations.
+    ///
+    /// The operations that were applied or dropped are removed from the queue.
+    /// If an operation fails, for instance because the connection was lost
+    /// again, it is kept with the following ones for the next replay. A
+    /// message refused for good by the server doesn't stop the replay, see
+    /// [`Self::send_queued`].
+    ///
+    /// The operations on emails are replayed in the mailbox of each email, so
+    /// the session may be left in another mailbox than the selected one.
+    ///
+    /// The queued messages are sent through a single SMTP session, opened
+    /// when the first one is sent, and opened again if it was idle for too
+    /// long while the emails were spaced out.
+    pub fn replay(
+        &self,
+        credentials: &Credentials,
+        session: &mut ImapSession<MailboxSelected>,
+    ) -> Result<ReplaySummary> {
+        let _guard = lock::acquire(self.cache)?;
+        let operations = self.load()?;
+        let mut summary = ReplaySummary::default();
+        if operations.is_empty() {
+            return Ok(summary);
+        }
+
+        let mut selected = None;
+        let mut smtp = None;
+
+        // The queued emails are spaced out, not to exceed the rate limit of
+        // the provider after a long time offline.
+        let mut throttle = SendLimits::new(
+            credentials,
+            credentials.as_smtp_socket_address().0,
+        )
+        .to_throttle(Duration::ZERO);
+        for (idx, operation) in operations.iter().enumerate() {
+            match self.apply(
+                operation,
+                session,
+                &mut selected,
+                credentials,
+                &mut smtp,
+                &mut throttle,
+            ) {
+                Ok(true) => summary.applied = summary.applied.saturating_add(1),
+                Ok(false) =>
+                    summary.conflicts = summary.conflicts.saturating_add(1),
+                Err(err) => {
+                    self.save(operations.get(idx..).unwrap_or_default())?;
+                    return Err(err);
+                }
+            }
+        }
+
+        self.save(&[])?;
+        if let Some(smtp_session) = smtp
+            && smtp_session.is_reusable()
+        {
+            smtp_session.quit()?;
+        }
+        Ok(summary)
+    }
+
+    /// Applies an operation on the server
+    ///
+    /// Returns `false` if the operation was dropped because of a conflict.
+    /// The mailbox of the email is selected if it isn't the `selected` one,
+    /// which is kept for the next operations.
+    /// The SMTP session is opened with the credentials if there is none yet,
+    /// or if it was idle for too long, in which case it is dropped without
+    /// waiting for the server.
+    fn apply(
+        &self,
+        operation: &Operation,
+        session: &mut ImapSession<MailboxSelected>,
+        selected: &mut Option<Selection>,
+        credentials: &Credentials,
+        smtp: &mut Option<SmtpSession>,
+        throttle: &mut Throttle,
+    ) -> Result<bool> {
+        if let Operation::Send { dsn, file, from, recipients } = operation {
+            let path = self.outbox.join(file);
+            let Some(message) = self.cache.read(&path)? else {
+                return Ok(false);
+            };
+            throttle.wait();
+            let smtp_session = match smtp.take() {
+                Some(open) if open.is_reusable() => smtp.insert(open),
+                Some(_) | None =>
+                    smtp.insert(SmtpSession::with_credentials(credentials)?),
+            };
+            return self.send_queued(
+                smtp_session,
+                file,
+                (from, recipients, *dsn),
+                &message,
+            );
+        }
+
+        let Some(target) = operation.as_target() else {
+            return Ok(false);
+        };
+        let selection = match selected.take() {
+            Some(current) if current.mailbox == target.mailbox =>
+                selected.insert(current),
+            Some(_) | None => {
+                session.switch_mailbox(&Mailbox::try_from(
+                    target.mailbox.as_str(),
+                )?)?;
+                selected.insert(Selection {
+                    mailbox: target.mailbox.clone(),
+                    uids: session.get_uids()?,
+                    validity: session.as_validity(),
+                })
+            }
+        };
+        if !target.matches(selection.validity, &selection.uids) {
+            return Ok(false);
+        }
+        let uid = target.uid;
+        match operation {
+            Operation::AddFlag { flag, .. } => session.add_flag(uid, flag)?,
+            Operation::Delete { .. } => session.delete(uid)?,
+            Operation::Move { mailbox, .. } =>
+                session.move_to(uid, &Mailbox::try_from(mailbox.as_str())?)?,
+            Operation::RemoveFlag { flag, .. } =>
+                session.remove_flag(uid, flag)?,
+            Operation::Send { .. } => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Sends a message of the outbox, with its envelope, and removes it from
+    /// the outbox once sent
+    ///
+    /// Returns `false` if the server refused the message for good: it is then
+    /// moved to the [`FAILED_DIR`] of the outbox, as sending it again can't
+    /// succeed, and the replay goes on with the next operations.
+    fn send_queued<S: Read + Write>(
+        &self,
+        smtp: &mut SmtpSession<S>,
+        file: &str,
+        (from, recipients, dsn): (&str, &[String], Dsn),
+        message: &[u8],
+    ) -> Result<bool> {
+        let path = self.outbox.join(file);
+        match smtp.send(from, recipients, message, dsn) {
+            Ok(()) => {
+                fs::remove_file(path).map_err(Error::Write)?;
+                Ok(true)
+            }
+            Err(errors::Error::SmtpConnection(err)) if err.is_permanent() => {
+                let failed = self.outbox.join(FAILED_DIR);
+                fs::create_dir_all(&failed).map_err(Error::CreateDir)?;
+                fs::rename(path, failed.join(file)).map_err(Error::Write)?;
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Overwrites the queue with the given operations.
+    pub fn save(&self, operations: &[Operation]) -> Result {
+        let content = operations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.cache.write(&self.path, content.as_bytes())
+    }
+}
+
+/// Quotes a mailbox name, escaping its quotes and backslashes
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Reads the quoted mailbox name at the start of a text
+///
+/// Returns the unescaped name and the text after the closing quote.
+fn unquote(text: &str) -> Option<(String, &str)> {
+    let mut name = String::new();
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    while let Some((index, ch)) = chars.next() {
+        match ch {
+            '"' => return Some((name, text.get(index.checked_add(2)?..)?)),
+            '\\' => name.push(chars.next()?.1),
+            _ => name.push(ch),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    #![expect(clippy::unwrap_used, reason = "test")]
+
+    use crate::cache::queue::{Location, Operation, Queue};
+    use crate::send::dsn::Dsn;
+    use crate::test_utils::{ScriptedServer, TempDir};
+
+    #[test]
+    fn parse_operations() {
+        for line in [
+            "add-flag \"INBOX\" 1700000000 42 \\Seen",
+            "remove-flag \"INBOX\" - 42 \\Flagged",
+            "move \"INBOX\" 1700000000 42 Archives/2024 Q1",
+            "delete \"Old \\\"news\\\" \\\\ 2023\" 1700000000 43",
+            "send 1.eml bob@example.com alice@example.com eve@example.com",
+            "send 2.eml bob@example.com notify=success,failure eve@example.com",
+        ] {
+            assert_eq!(
+                Operation::parse(line).map(|op| op.to_string()).as_deref(),
+                Some(line)
+            );
+        }
+        assert_eq!(
+            Operation::parse("move \"Sent items\" - 42 Archives"),
+            Some(Operation::Move {
+                mailbox: "Archives".to_owned(),
+                target: Location::new("Sent items", 42)
+            })
+        );
+        assert_eq!(Operation::parse("delete"), None);
+        assert_eq!(Operation::parse("delete 42"), None);
+        assert_eq!(Operation::parse("delete \"INBOX\" - 42 43"), None);
+        assert_eq!(Operation::parse("delete \"INBOX - 42"), None);
+        assert_eq!(Operation::parse("unknown \"INBOX\" - 42"), None);
+
+        let target =
+            Location { validity: Some(7), ..Location::new("INBOX", 42) };
+        assert!(target.matches(Some(7), &[41, 42]));
+        assert!(target.matches(None, &[42]));
+        assert!(!target.matches(Some(8), &[42]));
+        assert!(!target.matches(Some(7), &[41]));
+    }
+
+    #[test]
+    fn refused_messages() {
+        let dir = TempDir::new("refused-messages");
+        let cache = dir.open_cache();
+        let queue = Queue::new(&cache);
+        let recipients = ["eve@example.com".to_owned()];
+        for message in [b"Hello", b"Again"] {
+            queue
+                .push_send(
+                    "bob@example.com".to_owned(),
+                    recipients.to_vec(),
+                    message,
+                    Dsn::default(),
+                )
+                .unwrap();
+        }
+        let files = queue
+            .load()
+            .unwrap()
+            .into_iter()
+            .filter_map(|operation| match operation {
+                Operation::Send { file, .. } => Some(file),
+                Operation::AddFlag { .. }
+                | Operation::Delete { .. }
+                | Operation::Move { .. }
+                | Operation::RemoveFlag { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        let mut server = ScriptedServer::new(&[
+            "220 ready",
+            "250 example.com",
+            "235 ok",
+            "250 ok",
+            "550 no such user",
+            "250 reset",
+            "250 ok",
+            "451 try again later",
+            "250 reset",
+        ]);
+        let mut session = server.open_smtp();
+        let envelope =
+            ("bob@example.com", recipients.as_slice(), Dsn::default());
+        let outbox = cache.as_root().join("outbox");
+
+        let [refused, delayed] = <[String; 2]>::try_from(files).unwrap();
+        assert!(
+            !queue
+                .send_queued(&mut session, &refused, envelope, b"Hello")
+                .unwrap()
+        );
+        assert!(outbox.join("failed").join(&refused).exists());
+        assert!(!outbox.join(&refused).exists());
+
+        queue
+            .send_queued(&mut session, &delayed, envelope, b"Again")
+            .unwrap_err();
+        assert!(outbox.join(&delayed).exists());
+    }
+}
@@ -0,0 +1,307 @@
+//! Limits the size of the cache, by pruning the emails that are least likely
+//! to be read again.
+//!
+//! The pruning follows the [`Retention`] of the configuration:
+//!
+//! 1. the emails cached for longer than the maximum age are removed,
+//! 2. only the headers of the emails older than the most recent ones are kept,
+//!    so that the list of emails can still be displayed offline,
+//! 3. the emails cached the longest are removed until the cache fits in the
+//!    maximum size.
+//!
+//! The queue and the outbox are never pruned, as they contain changes that
+//! weren't sent to the server yet, nor are the [local folders](super::local),
+//! whose emails aren't on the server anymore.
+
+use core::fmt;
+use core::time::Duration;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use super::queue::Queue;
+use super::{Cache, Error, local};
+use crate::errors::Result;
+
+/// Retention policy of the cache
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Retention {
+    /// Maximum time an email is kept in the cache
+    max_age: Option<Duration>,
+    /// Maximum size of the cache, in bytes
+    max_size: Option<u64>,
+    /// Number of most recent emails per mailbox whose body is kept
+    recent_bodies: Option<usize>,
+}
+
+impl Retention {
+    /// Checks if the cache is pruned at all
+    pub const fn is_unlimited(&self) -> bool {
+        self.max_age.is_none()
+            && self.max_size.is_none()
+            && self.recent_bodies.is_none()
+    }
+
+    /// Creates a retention policy
+    ///
+    /// The age is given in days and the size in MiB.
+    pub fn new(
+        max_age_days: Option<u64>,
+        max_size_mib: Option<u64>,
+        recent_bodies: Option<usize>,
+    ) -> Self {
+        Self {
+            max_age: max_age_days
+                .map(|days| Duration::from_hours(days.saturating_mul(24))),
+            max_size: max_size_mib.map(|mib| mib.saturating_mul(1 << 20)),
+            recent_bodies,
+        }
+    }
+}
+
+/// Email stored in the cache
+struct CachedEmail {
+    /// Indicates whether only the headers are stored
+    headers_only: bool,
+    /// Time at which the email was cached
+    modified: SystemTime,
+    /// Path of the file of the email
+    path: PathBuf,
+    /// Position of the email in its mailbox, from the most recent
+    position: usize,
+    /// Size of the file, in bytes
+    size: u64,
+}
+
+/// Outcome of a pruning of the cache
+#[derive(Default)]
+pub struct Pruned {
+    /// Number of emails whose body was removed
+    bodies: usize,
+    /// Number of bytes freed
+    freed: u64,
+    /// Number of emails removed
+    removed: usize,
+}
+
+impl fmt::Display for Pruned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cache pruned: {} emails removed, {} bodies dropped, {} freed",
+            self.removed,
+            self.bodies,
+            to_human_size(self.freed)
+        )
+    }
+}
+
+impl Pruned {
+    /// Removes an email from the cache.
+    fn remove(&mut self, email: &CachedEmail) -> Result {
+        fs::remove_file(&email.path).map_err(Error::Write)?;
+        self.removed = self.removed.saturating_add(1);
+        self.freed = self.freed.saturating_add(email.size);
+        Ok(())
+    }
+}
+
+/// Statistics of the cache
+pub struct Stats {
+    /// Number of emails stored with their body
+    emails: usize,
+    /// Number of emails of which only the headers are stored
+    headers_only: usize,
+    /// Number of operations waiting to be replayed
+    queued: usize,
+    /// Total size of the cache, in bytes
+    size: u64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cache: {} emails, {} headers only, {} queued operations, {}",
+            self.emails,
+            self.headers_only,
+            self.queued,
+            to_human_size(self.size)
+        )
+    }
+}
+
+/// Removes every cached email
+///
+/// The queued operations, the outbox and the local folders are kept.
+pub fn clear(cache: &Cache) -> Result {
+    for mailbox in mailbox_dirs(cache)? {
+        fs::remove_dir_all(mailbox).map_err(Error::Write)?;
+    }
+    Ok(())
+}
+
+/// Lists the emails stored in every mailbox of the cache
+fn list_emails(cache: &Cache) -> Result<Vec<CachedEmail>> {
+    let mut emails = vec![];
+    for mailbox_dir in mailbox_dirs(cache)? {
+        let Some(mailbox) =
+            mailbox_dir.file_name().and_then(|name| name.to_str())
+        else {
+            continue;
+        };
+        for (position, uid) in cache.load_uids(mailbox)?.iter().enumerate() {
+            for (path, headers_only) in [
+                (cache.email_path(mailbox, *uid), false),
+                (cache.headers_path(mailbox, *uid), true),
+            ] {
+                if let Ok(metadata) = fs::metadata(&path) {
+                    emails.push(CachedEmail {
+                        headers_only,
+                        modified: metadata
+                            .modified()
+                            .unwrap_or(SystemTime::UNIX_EPOCH),
+                        path,
+                        position,
+                        size: metadata.len(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(emails)
+}
+
+/// Lists the directories of the mailboxes in the cache
+fn mailbox_dirs(cache: &Cache) -> Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(cache.as_root())
+        .map_err(Error::Read)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let is_local = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(local::is_folder);
+            (path.is_dir() && !path.ends_with("outbox") && !is_local)
+                .then_some(path)
+        })
+        .collect())
+}
+
+/// Prunes the cache according to the retention policy.
+pub fn prune(cache: &Cache, retention: &Retention) -> Result<Pruned> {
+    let mut pruned = Pruned::default();
+    let now = SystemTime::now();
+    let mut kept = vec![];
+
+    for email in list_emails(cache)? {
+        let age = now.duration_since(email.modified).unwrap_or_default();
+        if retention.max_age.is_some_and(|max_age| age > max_age) {
+            pruned.remove(&email)?;
+        } else if !email.headers_only
+            && retention
+                .recent_bodies
+                .is_some_and(|recent| email.position >= recent)
+        {
+            kept.push(strip_body(cache, email, &mut pruned)?);
+        } else {
+            kept.push(email);
+        }
+    }
+
+    if let Some(max_size) = retention.max_size {
+        let mut size = kept.iter().map(|email| email.size).sum::<u64>();
+        kept.sort_by_key(|email| email.modified);
+        for email in &kept {
+            if size <= max_size {
+                break;
+            }
+            pruned.remove(email)?;
+            size = size.saturating_sub(email.size);
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Computes the statistics of the cache
+pub fn stats(cache: &Cache) -> Result<Stats> {
+    let emails = list_emails(cache)?;
+    let headers_only = emails.iter().filter(|email| email.headers_only).count();
+    let outbox_size = fs::read_dir(cache.as_root().join("outbox"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum::<u64>()
+        })
+        .unwrap_or_default();
+    Ok(Stats {
+        emails: emails.len().saturating_sub(headers_only),
+        headers_only,
+        queued: Queue::new(cache).load()?.len(),
+        size: emails
+            .iter()
+            .map(|email| email.size)
+            .sum::<u64>()
+            .saturating_add(outbox_size),
+    })
+}
+
+/// Replaces a cached email by its headers
+fn strip_body(
+    cache: &Cache,
+    email: CachedEmail,
+    pruned: &mut Pruned,
+) -> Result<CachedEmail> {
+    let Some(raw) = cache.read_to_string(&email.path)? else {
+        return Ok(email);
+    };
+    let headers = raw
+        .split_once("\r\n\r\n")
+        .or_else(|| raw.split_once("\n\n"))
+        .map_or(raw.as_str(), |(headers, _)| headers);
+    let path = email.path.with_extension("headers");
+    cache.write(&path, headers.as_bytes())?;
+    fs::remove_file(&email.path).map_err(Error::Write)?;
+
+    let size = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+    pruned.bodies = pruned.bodies.saturating_add(1);
+    pruned.freed = pruned.freed.saturating_add(email.size.saturating_sub(size));
+    Ok(CachedEmail { headers_only: true, path, size, ..email })
+}
+
+/// Formats a number of bytes in a human-readable way, e.g. `12.3 MiB`
+pub fn to_human_size(bytes: u64) -> String {
+    let mut tenths = bytes.saturating_mul(10);
+    for unit in ["B", "KiB", "MiB"] {
+        if tenths < 10 * 1024 {
+            return format_tenths(tenths, unit);
+        }
+        tenths = tenths.checked_div(1024).unwrap_or_default();
+    }
+    format_tenths(tenths, "GiB")
+}
+
+/// Formats a number of tenths of a unit with one decimal
+fn format_tenths(tenths: u64, unit: &str) -> String {
+    format!(
+        "{}.{} {unit}",
+        tenths.checked_div(10).unwrap_or_default(),
+        tenths.checked_rem(10).unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::retention::to_human_size;
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(to_human_size(0), "0.0 B");
+        assert_eq!(to_human_size(1000), "1000.0 B");
+        assert_eq!(to_human_size(1536), "1.5 KiB");
+        assert_eq!(to_human_size(5 << 20), "5.0 MiB");
+        assert_eq!(to_human_size(3 << 40), "3072.0 GiB");
+    }
+}
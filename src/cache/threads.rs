@@ -0,0 +1,157 @@
+//! Indexes the conversations of a mailbox, to thread it without parsing it.
+//!
+//! Every email is assigned the id of its conversation when it is synced: the
+//! conversation of the closest email it replies to that was indexed, or else
+//! the first id of its `References` header, which is the root of the
+//! conversation even if it isn't in the mailbox. An email that doesn't reply
+//! to anything starts its own conversation.
+//!
+//! The index is stored in the `threads` file of the mailbox, with one email
+//! per line:
+//!
+//! ```text
+//! 42 1736150400.4242@example.com 1736150000.1@example.com
+//! ```
+//!
+//! The line holds the uid, the `Message-ID` and the conversation of the email.
+//! The emails without a `Message-ID` aren't indexed.
+
+use alloc::collections::BTreeMap;
+use std::collections::{HashMap, HashSet};
+
+use mail_parser::HeaderName;
+
+use crate::fetch::parser::Email;
+
+/// Conversations of the emails of a mailbox
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ThreadIndex {
+    /// Number of indexed emails of every conversation
+    sizes: HashMap<String, usize>,
+    /// Conversation of every indexed `Message-ID`
+    threads: HashMap<String, String>,
+    /// `Message-ID` of every indexed uid
+    uids: BTreeMap<u32, String>,
+}
+
+impl ThreadIndex {
+    /// Indexes an email, if it has a `Message-ID` and wasn't indexed yet
+    pub fn add(&mut self, email: &Email) {
+        if self.uids.contains_key(&email.as_uid()) {
+            return;
+        }
+        let Some(message_id) =
+            email.to_message_ids(&HeaderName::MessageId).pop()
+        else {
+            return;
+        };
+        let mut references = email.to_message_ids(&HeaderName::References);
+        references.extend(email.to_message_ids(&HeaderName::InReplyTo));
+        let thread = references
+            .iter()
+            .rev()
+            .find_map(|reference| self.threads.get(reference).cloned())
+            .or_else(|| references.first().cloned())
+            .unwrap_or_else(|| message_id.clone());
+        self.insert(email.as_uid(), message_id, thread);
+    }
+
+    /// Returns the conversation of the email with the given uid, if it was
+    /// indexed
+    pub fn as_thread(&self, uid: u32) -> Option<&str> {
+        self.threads.get(self.uids.get(&uid)?).map(String::as_str)
+    }
+
+    /// Returns the uids of the indexed emails that belong to one of the given
+    /// conversations, from the oldest
+    pub fn find_in(&self, conversations: &HashSet<&str>) -> Vec<u32> {
+        self.uids
+            .iter()
+            .filter(|(_, message_id)| {
+                self.threads.get(*message_id).is_some_and(|thread| {
+                    conversations.contains(thread.as_str())
+                })
+            })
+            .map(|(uid, _)| *uid)
+            .collect()
+    }
+
+    /// Checks if the email with the given uid is known to be the only one of
+    /// its conversation
+    ///
+    /// This is `false` for the emails that weren't indexed.
+    pub fn is_alone(&self, uid: u32) -> bool {
+        self.as_thread(uid)
+            .and_then(|thread| self.sizes.get(thread))
+            .is_some_and(|size| *size <= 1)
+    }
+
+    /// Parses the content of the `threads` file of a mailbox
+    pub fn parse(content: &str) -> Self {
+        let mut index = Self::default();
+        for line in content.lines() {
+            let mut words = line.split(' ');
+            if let (Some(uid), Some(message_id), Some(thread)) =
+                (words.next(), words.next(), words.next())
+                && let Ok(parsed_uid) = uid.parse()
+            {
+                index.insert(
+                    parsed_uid,
+                    message_id.to_owned(),
+                    thread.to_owned(),
+                );
+            }
+        }
+        index
+    }
+
+    /// Returns the content of the `threads` file of a mailbox
+    pub fn to_content(&self) -> String {
+        self.uids
+            .iter()
+            .filter_map(|(uid, message_id)| {
+                let thread = self.threads.get(message_id)?;
+                Some(format!("{uid} {message_id} {thread}"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Records the conversation of an email
+    fn insert(&mut self, uid: u32, message_id: String, thread: String) {
+        let size = self.sizes.entry(thread.clone()).or_default();
+        *size = size.saturating_add(1);
+        self.threads.insert(message_id.clone(), thread);
+        self.uids.insert(uid, message_id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::threads::ThreadIndex;
+    use crate::test_utils::email_with_uid;
+
+    #[test]
+    fn thread_index() {
+        let mut index = ThreadIndex::default();
+        index.add(&email_with_uid(1, "Message-ID: <a@x>"));
+        index.add(&email_with_uid(2, "Message-ID: <b@x>\nIn-Reply-To: <a@x>"));
+        index.add(&email_with_uid(
+            3,
+            "Message-ID: <c@x>\nReferences: <z@x> <b@x>",
+        ));
+        index.add(&email_with_uid(4, "Message-ID: <d@x>\nReferences: <y@x>"));
+        index.add(&email_with_uid(5, "Subject: no id"));
+        assert_eq!(index.as_thread(3), Some("a@x"));
+        assert_eq!(index.as_thread(4), Some("y@x"));
+        assert_eq!(index.as_thread(5), None);
+        assert!(!index.is_alone(1));
+        assert!(index.is_alone(4));
+        assert!(!index.is_alone(5));
+        assert_eq!(index.find_in(&["a@x"].into()), [1, 2, 3]);
+
+        let content = index.to_content();
+        assert_eq!(content, "1 a@x a@x\n2 b@x a@x\n3 c@x a@x\n4 d@x y@x");
+        assert_eq!(ThreadIndex::parse(&content), index);
+    }
+}
@@ -0,0 +1,150 @@
+//! Exports the calendar invites received by email to calendar tools.
+//!
+//! An invite is an attachment of type `text/calendar` or `application/ics`,
+//! or named `*.ics`. `:invite <path>` saves the invite of the opened email to
+//! the file, or in the directory with the name of the attachment, and
+//! `:invite` pipes it into the calendar command of the
+//! [`Config`](crate::config::Config), e.g.
+//!
+//! ```env
+//! CALENDAR_COMMAND=khal import --batch /dev/stdin
+//! ```
+//!
+//! or `gcalcli import`, which reads the invite from its standard input. The
+//! summary and the start of the event, read from the `SUMMARY` and `DTSTART`
+//! properties, are shown once it is exported.
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use crate::errors::Result;
+use crate::fetch::parser::{Attachment, Email};
+use crate::pipe::pipe;
+
+/// MIME types of the calendar invites
+const INVITE_TYPES: [&str; 2] = ["text/calendar", "application/ics"];
+
+/// Errors that may occur while exporting an invite
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to save the invite to a file
+    Save(io::Error),
+}
+
+/// Returns the first calendar invite attached to an email, if any
+pub fn find_invite(email: &Email) -> Option<&Attachment> {
+    email.as_attachments().iter().find(|attachment| {
+        let content_type = attachment.as_content_type();
+        INVITE_TYPES.iter().any(|invite_type| {
+            content_type
+                .get(..invite_type.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(invite_type))
+        }) || attachment.as_name().is_some_and(|name| {
+            Path::new(name)
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("ics"))
+        })
+    })
+}
+
+/// Pipes an invite into the calendar command
+pub fn import_invite(invite: &Attachment, command: &str) -> Result {
+    pipe(command, invite.as_data().to_vec())?;
+    Ok(())
+}
+
+/// Saves an invite to a file, or in a directory with the name of the
+/// attachment, and returns the path of the file
+pub fn save_invite(invite: &Attachment, path: &Path) -> Result<PathBuf> {
+    let target = if path.is_dir() {
+        let name = invite.to_file_name();
+        if Path::new(&name).extension().is_some() {
+            path.join(name)
+        } else {
+            path.join(format!("{name}.ics"))
+        }
+    } else {
+        path.to_owned()
+    };
+    fs::write(&target, invite.as_data()).map_err(Error::Save)?;
+    Ok(target)
+}
+
+/// Returns the summary and the start of the event of an invite, e.g.
+/// `'Team sync' on 2024-05-02 10:00`
+pub fn to_summary(data: &[u8]) -> String {
+    let content = String::from_utf8_lossy(data)
+        .replace("\r\n ", "")
+        .replace("\n ", "");
+    let property = |name: &str| {
+        content.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let property_name = key.split(';').next()?;
+            property_name
+                .eq_ignore_ascii_case(name)
+                .then(|| value.trim().to_owned())
+        })
+    };
+    let summary = property("SUMMARY").unwrap_or_else(|| "Event".to_owned());
+    property("DTSTART").map_or_else(
+        || format!("'{summary}'"),
+        |start| format!("'{summary}' on {}", to_readable_date(&start)),
+    )
+}
+
+/// Converts a date of an invite, e.g. `20240502T100000Z` or `20240502` for a
+/// whole day, to a readable one, e.g. `2024-05-02 10:00`
+///
+/// The date is returned as is if it isn't in one of these formats.
+fn to_readable_date(date: &str) -> String {
+    let digits = |range| {
+        date.get(range).filter(|part: &&str| {
+            part.bytes().all(|byte| byte.is_ascii_digit())
+        })
+    };
+    let day = digits(0..4).zip(digits(4..6)).zip(digits(6..8));
+    let time = date
+        .get(8..9)
+        .filter(|separator| *separator == "T")
+        .and_then(|_| digits(9..11).zip(digits(11..13)));
+    match (day, time) {
+        (Some(((year, month), day_of_month)), Some((hour, minute))) =>
+            format!("{year}-{month}-{day_of_month} {hour}:{minute}"),
+        (Some(((year, month), day_of_month)), None) if date.len() == 8 =>
+            format!("{year}-{month}-{day_of_month}"),
+        _ => date.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calendar::{find_invite, to_summary};
+    use crate::fetch::parser::Email;
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn invites() {
+        let invite = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;TZID=Europe/\r\n \
+                      Paris:20240502T100000\r\nSUMMARY;LANGUAGE=en:Team \r\n \
+                      sync\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let raw = format!(
+            "Subject: Invite\nContent-Type: multipart/mixed; \
+             boundary=b\n\n--b\nContent-Type: text/plain\n\nJoin \
+             us\n--b\nContent-Type: text/calendar; \
+             method=REQUEST\n\n{invite}\n--b--\n"
+        );
+        let email = Email::try_from((1, raw.as_bytes())).unwrap();
+        let found = find_invite(&email).unwrap();
+        assert_eq!(
+            to_summary(found.as_data()),
+            "'Team sync' on 2024-05-02 10:00"
+        );
+        assert_eq!(
+            to_summary(b"DTSTART;VALUE=DATE:20240502\nSUMMARY:Trip"),
+            "'Trip' on 2024-05-02"
+        );
+        assert_eq!(to_summary(b"DTSTART:soon"), "'Event' on soon");
+        let plain = Email::try_from((2, b"Subject: Hi\n\nHello".as_slice()));
+        assert!(find_invite(&plain.unwrap()).is_none());
+    }
+}
@@ -0,0 +1,272 @@
+//! Backs up every folder of an account to an archive, and restores it, for
+//! account migrations and disaster recovery.
+//!
+//! ```sh
+//! mailbox backup --account work --out backup.tar.zst
+//! mailbox restore --account new --in backup.tar.zst
+//! ```
+//!
+//! The archive is a zstd-compressed tar file, with one directory per folder:
+//!
+//! ```text
+//! 0/folder      name of the folder, e.g. `Clients/2024`
+//! 0/flags       flags of the emails, one `<uid> <flags>` line per email
+//! 0/1234.eml    raw email, by uid
+//! ```
+//!
+//! The folders that can't hold emails (`\Noselect`) are skipped. The emails
+//! are fetched without marking them as seen. Restoring uploads them
+//! with `APPEND`, with their flags, and creates the missing folders.
+//! `--account` selects the account, see
+//! [`Credentials`](crate::credentials::Credentials), and defaults to the main
+//! one.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Read as _, Write};
+use std::path::PathBuf;
+
+use tar::{Archive, Builder, Header};
+use zstd::{DEFAULT_COMPRESSION_LEVEL, Decoder, Encoder};
+
+use super::Error;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::fetch::connection::ImapSession;
+use crate::fetch::mailbox::Mailbox;
+
+/// Extension of the entries holding the raw emails
+const EMAIL_EXTENSION: &str = ".eml";
+/// Permissions of the entries of the archive
+const ENTRY_MODE: u32 = 0o600;
+/// Name of the entry holding the flags of the emails of a folder
+const FLAGS_ENTRY: &str = "flags";
+/// Name of the entry holding the name of a folder
+const FOLDER_ENTRY: &str = "folder";
+
+/// Arguments of the `backup` and `restore` subcommands
+pub struct BackupArgs {
+    /// Name of the account, defaults to the main one
+    account: Option<String>,
+    /// Path of the archive
+    path: PathBuf,
+}
+
+impl BackupArgs {
+    /// Backs up every folder of the account to the archive.
+    pub fn backup(self) -> Result {
+        let credentials = Credentials::load_account(self.account.as_deref())?;
+        let write_err = |err| Error::WriteFile(self.path.clone(), err);
+        let mut session = ImapSession::with_credentials(&credentials)?
+            .select_mailbox(&Mailbox::inbox())?;
+        let mut folders = session.list_mailboxes()?;
+        folders.retain(Mailbox::is_selectable);
+        let file = File::create(&self.path).map_err(write_err)?;
+        let mut archive = Builder::new(
+            Encoder::new(file, DEFAULT_COMPRESSION_LEVEL).map_err(write_err)?,
+        );
+        let mut stdout = io::stdout().lock();
+
+        for (index, folder) in folders.iter().enumerate() {
+            session.switch_mailbox(folder)?;
+            let flags = session.get_flags()?;
+            let uids = session.get_uids()?;
+            let flag_lines = uids
+                .iter()
+                .map(|uid| {
+                    let names = flags
+                        .get(uid)
+                        .map(|set| set.iter().cloned().collect::<Vec<_>>())
+                        .unwrap_or_default();
+                    format!("{uid} {}", names.join(" "))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            add_entry(
+                &mut archive,
+                &format!("{index}/{FOLDER_ENTRY}"),
+                folder.as_name().as_bytes(),
+            )
+            .map_err(write_err)?;
+            add_entry(
+                &mut archive,
+                &format!("{index}/{FLAGS_ENTRY}"),
+                flag_lines.as_bytes(),
+            )
+            .map_err(write_err)?;
+            for uid in &uids {
+                let raw = session.peek_mail_from_uid(*uid)?;
+                add_entry(
+                    &mut archive,
+                    &format!("{index}/{uid}{EMAIL_EXTENSION}"),
+                    raw.as_bytes(),
+                )
+                .map_err(write_err)?;
+            }
+            writeln!(stdout, "{folder}: {} emails", uids.len())
+                .map_err(Error::Stdout)?;
+        }
+
+        archive
+            .into_inner()
+            .and_then(Encoder::finish)
+            .map_err(write_err)?;
+        Ok(())
+    }
+
+    /// Parses the arguments of the `backup` and `restore` subcommands
+    ///
+    /// The path of the archive is given with `path_option`, `--out` to back up
+    /// and `--in` to restore.
+    pub fn parse<I: Iterator<Item = String>>(
+        args: I,
+        path_option: &str,
+    ) -> Result<Self, Error> {
+        let mut account = None;
+        let mut path = None;
+        let mut args_iter = args;
+
+        while let Some(arg) = args_iter.next() {
+            match arg.as_str() {
+                "--account" =>
+                    account =
+                        Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                option if option == path_option =>
+                    path =
+                        Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                _ => return Err(Error::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(Self {
+            account,
+            path: PathBuf::from(
+                path.ok_or_else(|| {
+                    Error::MissingValue(path_option.to_owned())
+                })?,
+            ),
+        })
+    }
+
+    /// Uploads every email of the archive to the account.
+    pub fn restore(self) -> Result {
+        let credentials = Credentials::load_account(self.account.as_deref())?;
+        let read_err = |err| Error::ReadFile(self.path.clone(), err);
+        let mut session = ImapSession::with_credentials(&credentials)?;
+        let mut existing = session
+            .list_mailboxes()?
+            .iter()
+            .map(|mailbox| mailbox.as_name().to_owned())
+            .collect::<BTreeSet<_>>();
+        let file = File::open(&self.path).map_err(read_err)?;
+        let mut archive = Archive::new(Decoder::new(file).map_err(read_err)?);
+        let mut folder = None;
+        let mut flags = BTreeMap::new();
+        let mut folders: usize = 0;
+        let mut restored: usize = 0;
+
+        for entry in archive.entries().map_err(read_err)? {
+            let mut file_entry = entry.map_err(read_err)?;
+            let path =
+                file_entry.path().map_err(read_err)?.display().to_string();
+            let mut content = vec![];
+            file_entry.read_to_end(&mut content).map_err(read_err)?;
+            let name = path.rsplit('/').next().unwrap_or_default();
+            match (name, name.strip_suffix(EMAIL_EXTENSION)) {
+                (FOLDER_ENTRY, _) => {
+                    let folder_name = String::from_utf8(content)
+                        .map_err(|_err| Error::InvalidArchive(path.clone()))?;
+                    let mailbox = Mailbox::try_from(folder_name.as_str())?;
+                    if existing.insert(folder_name) {
+                        session.create_mailbox(&mailbox)?;
+                    }
+                    folder = Some(mailbox);
+                    folders = folders.saturating_add(1);
+                }
+                (FLAGS_ENTRY, _) =>
+                    flags = to_flags(&String::from_utf8_lossy(&content)),
+                (_, Some(uid)) => {
+                    let mailbox = folder
+                        .as_ref()
+                        .ok_or_else(|| Error::InvalidArchive(path.clone()))?;
+                    session.append(
+                        mailbox,
+                        &content,
+                        flags.get(uid).map_or(&[], Vec::as_slice),
+                        None,
+                    )?;
+                    restored = restored.saturating_add(1);
+                }
+                (_, None) => return Err(Error::InvalidArchive(path).into()),
+            }
+        }
+
+        writeln!(
+            io::stdout().lock(),
+            "Restored {restored} emails in {folders} folders"
+        )
+        .map_err(Error::Stdout)?;
+        Ok(())
+    }
+}
+
+/// Adds a file to the archive.
+fn add_entry<W: Write>(
+    archive: &mut Builder<W>,
+    path: &str,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(u64::try_from(data.len()).unwrap_or(u64::MAX));
+    header.set_mode(ENTRY_MODE);
+    archive.append_data(&mut header, path, data)
+}
+
+/// Parses the flags entry of a folder, returning the flags of every uid
+fn to_flags(content: &str) -> BTreeMap<String, Vec<String>> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            Some((words.next()?.to_owned(), words.map(str::to_owned).collect()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read as _;
+
+    use tar::{Archive, Builder};
+    use zstd::{Decoder, Encoder};
+
+    use crate::cli::backup::{add_entry, to_flags};
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn archive() {
+        let mut builder = Builder::new(Encoder::new(vec![], 0).unwrap());
+        add_entry(&mut builder, "0/flags", b"12 \\Seen \\Flagged\n13 ")
+            .unwrap();
+        let compressed =
+            builder.into_inner().and_then(Encoder::finish).unwrap();
+
+        let mut archive =
+            Archive::new(Decoder::new(compressed.as_slice()).unwrap());
+        let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap().to_str(), Some("0/flags"));
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+
+        assert_eq!(
+            to_flags(&content).into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    "12".to_owned(),
+                    vec!["\\Seen".to_owned(), "\\Flagged".to_owned()]
+                ),
+                ("13".to_owned(), vec![])
+            ]
+        );
+    }
+}
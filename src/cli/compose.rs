@@ -0,0 +1,40 @@
+//! Opens the writer, or forwards it to the TUI already running for the account.
+//!
+//! ```sh
+//! mailbox compose
+//! mailbox compose 'mailto:bob@example.com?subject=Hi'
+//! ```
+//!
+//! If a TUI is running for the same account, the writer is opened in it
+//! through its [control socket](crate::tui::control), instead of starting a
+//! second instance whose flags would conflict with the ones of the first.
+
+use std::io::{self, Write as _};
+
+use super::Error;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::mailto::Mailto;
+use crate::tui::app::Tui;
+use crate::tui::control::{is_running, send_request};
+
+/// Opens the writer, pre-filled with the given `mailto:` URL if any
+pub fn run(url: Option<&str>) -> Result {
+    let credentials = Credentials::load()?;
+    if is_running(credentials.as_email())
+        && send_request(
+            credentials.as_email(),
+            &format!("compose {}", url.unwrap_or_default()),
+        )
+        .is_ok_and(|answer| answer == "ok")
+    {
+        writeln!(
+            io::stdout().lock(),
+            "The writer was opened in the running instance"
+        )
+        .map_err(Error::Stdout)?;
+        return Ok(());
+    }
+    let mailto = url.and_then(Mailto::parse);
+    Tui::new(false)?.compose(mailto.as_ref()).run()
+}
@@ -0,0 +1,33 @@
+//! Sends a request to the running TUI, through its control socket.
+//!
+//! ```sh
+//! mailbox control refresh
+//! mailbox control goto Clients/2024
+//! mailbox control compose 'mailto:bob@example.com'
+//! ```
+//!
+//! The answer of the TUI is printed, see [`control`](crate::tui::control) for
+//! the available requests.
+
+use std::io::{self, Write as _};
+
+use super::Error;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::tui::control::send_request;
+
+/// Sends the request to the TUI of the account and prints its answer.
+pub fn run(request: &str) -> Result {
+    if request.is_empty() {
+        return Err(Error::MissingValue("control".to_owned()).into());
+    }
+    let credentials = Credentials::load()?;
+    let answer = send_request(credentials.as_email(), request)
+        .map_err(Error::Control)?;
+    writeln!(io::stdout().lock(), "{answer}").map_err(Error::Stdout)?;
+    if answer == "ok" {
+        Ok(())
+    } else {
+        Err(Error::RejectedRequest(answer).into())
+    }
+}
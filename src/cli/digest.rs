@@ -0,0 +1,158 @@
+//! Summarises the emails received recently, from the cache.
+//!
+//! ```sh
+//! mailbox digest                          # last 7 days, as Markdown
+//! mailbox digest --since 30d --format html > digest.html
+//! mailbox digest --since 24h --send       # sends the digest to yourself
+//! ```
+//!
+//! The period is a number of hours (`h`), days (`d`) or weeks (`w`), see
+//! [`digest`](crate::cache::digest) for its content. With `--send`, the digest
+//! is sent to the email of the credentials instead of being printed, with the
+//! HTML page attached if that format was requested.
+
+use core::time::Duration;
+use std::io::{self, Write as _};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Error, send_or_queue};
+use crate::cache::Cache;
+use crate::cache::digest::{Digest, Format};
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::send::dsn::Dsn;
+use crate::send::message::OutgoingEmail;
+
+/// Default period of the digest, a week
+const DEFAULT_PERIOD: Duration = Duration::from_hours(168);
+/// Name of the attached HTML digest
+const HTML_ATTACHMENT: &str = "digest.html";
+/// Number of seconds in an hour
+const SECS_PER_HOUR: u64 = 3600;
+
+/// Arguments of the `digest` subcommand
+pub struct DigestArgs {
+    /// Format of the digest
+    format: Format,
+    /// Whether the digest must be sent instead of printed
+    send: bool,
+    /// Period of the summarised emails, up to now
+    since: Duration,
+}
+
+impl DigestArgs {
+    /// Parses the arguments of the `digest` subcommand
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, Error> {
+        let mut digest_args = Self {
+            format: Format::default(),
+            send: false,
+            since: DEFAULT_PERIOD,
+        };
+        let mut args_iter = args;
+
+        while let Some(arg) = args_iter.next() {
+            match arg.as_str() {
+                "--format" => {
+                    let name =
+                        args_iter.next().ok_or(Error::MissingValue(arg))?;
+                    digest_args.format = Format::parse(&name)
+                        .ok_or(Error::InvalidFormat(name))?;
+                }
+                "--send" => digest_args.send = true,
+                "--since" => {
+                    let period =
+                        args_iter.next().ok_or(Error::MissingValue(arg))?;
+                    digest_args.since = to_period(&period)
+                        .ok_or(Error::InvalidPeriod(period))?;
+                }
+                _ => return Err(Error::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(digest_args)
+    }
+
+    /// Summarises the cache, and prints or sends the digest.
+    pub fn run(self) -> Result {
+        let credentials = Credentials::load()?;
+        let config = Config::load();
+        let cache_dir = config.as_cache_dir().ok_or(Error::MissingCache)?;
+        let cache = Cache::open(
+            cache_dir,
+            credentials.as_email(),
+            credentials.as_cache_key_command(),
+        )?;
+        let since = SystemTime::now()
+            .checked_sub(self.since)
+            .and_then(|start| start.duration_since(UNIX_EPOCH).ok())
+            .and_then(|start| i64::try_from(start.as_secs()).ok())
+            .unwrap_or_default();
+        let digest = Digest::compute(&cache, since)?;
+
+        if !self.send {
+            return write!(
+                io::stdout().lock(),
+                "{}",
+                digest.to_document(self.format)
+            )
+            .map_err(|err| Error::Stdout(err).into());
+        }
+
+        let Some(identity) = credentials.as_identities().first() else {
+            return Err(Error::MissingRecipient.into());
+        };
+        let mut email = OutgoingEmail::new(
+            identity.clone(),
+            vec![credentials.as_email().to_owned()],
+        )
+        .with_subject(digest.to_title())
+        .with_body(digest.to_document(Format::Markdown));
+        if self.format == Format::Html {
+            email = email.with_attachment(
+                HTML_ATTACHMENT.to_owned(),
+                digest.to_document(Format::Html).into_bytes(),
+            );
+        }
+        send_or_queue(
+            &credentials,
+            email.as_from(),
+            &email.to_recipients(),
+            email.to_rfc5322().as_bytes(),
+            Dsn::default(),
+        )
+    }
+}
+
+/// Parses a period, e.g. `24h`, `7d` or `2w`
+fn to_period(period: &str) -> Option<Duration> {
+    let split = period.len().checked_sub(1)?;
+    let (number, unit) = period.split_at_checked(split)?;
+    let hours = match unit {
+        "h" => 1,
+        "d" => 24,
+        "w" => 168,
+        _ => return None,
+    };
+    let count = number.parse::<u64>().ok()?;
+    Some(Duration::from_secs(
+        count.checked_mul(hours)?.checked_mul(SECS_PER_HOUR)?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use crate::cli::digest::to_period;
+
+    #[test]
+    fn period() {
+        assert_eq!(to_period("24h"), Some(Duration::from_hours(24)));
+        assert_eq!(to_period("7d"), Some(Duration::from_hours(168)));
+        assert_eq!(to_period("2w"), Some(Duration::from_hours(336)));
+        assert_eq!(to_period("7"), None);
+        assert_eq!(to_period("d"), None);
+        assert_eq!(to_period("\u{e9}"), None);
+    }
+}
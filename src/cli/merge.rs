@@ -0,0 +1,298 @@
+//! Sends one personalised email per row of a CSV file, for newsletters and
+//! invitations.
+//!
+//! ```sh
+//! mailbox merge --template invitation.md --csv people.csv
+//! mailbox merge --template invitation.md --csv people.csv --delay 5 --dry-run
+//! ```
+//!
+//! The template starts with its headers, then an empty line and the body:
+//!
+//! ```text
+//! To: {email}
+//! Subject: See you on {date}, {name}!
+//!
+//! Hello {name},
+//! ```
+//!
+//! The first row of the CSV file names the columns, and every `{column}` of the
+//! template is replaced with the value of the row. The `To` header defaults to
+//! the `email` column. Every message is rendered before sending the first one,
+//! so a missing column doesn't leave the mailing half sent.
+//!
+//! The messages are sent one by one, waiting `--delay` seconds between them (1
+//! by default), or more if the [limits](crate::send::quota) of the provider
+//! require it, and are queued in the outbox if the server can't be reached. No
+//! message is sent if one of them has more recipients than the provider
+//! allows. `--dry-run` prints them instead.
+
+use core::time::Duration;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+
+use super::{Error, check_recipients, send_or_queue, to_smtp_route};
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::send::message::OutgoingEmail;
+use crate::send::quota::SendLimits;
+
+/// Column used as destination if the template has no `To` header
+const DEFAULT_TO: &str = "{email}";
+/// Default delay between two messages, in seconds
+const DEFAULT_DELAY: u64 = 1;
+
+/// Arguments of the `merge` subcommand
+pub struct MergeArgs {
+    /// Path of the CSV file, with one recipient per row
+    csv: PathBuf,
+    /// Delay between two messages
+    delay: Duration,
+    /// Whether the messages must be printed instead of sent
+    dry_run: bool,
+    /// Address of the identity to send from, defaults to the email of the
+    /// credentials
+    from: Option<String>,
+    /// Path of the template of the messages
+    template: PathBuf,
+}
+
+impl MergeArgs {
+    /// Parses the arguments of the `merge` subcommand
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, Error> {
+        let mut csv = None;
+        let mut template = None;
+        let mut merge_args = Self {
+            csv: PathBuf::new(),
+            delay: Duration::from_secs(DEFAULT_DELAY),
+            dry_run: false,
+            from: None,
+            template: PathBuf::new(),
+        };
+        let mut args_iter = args;
+
+        while let Some(arg) = args_iter.next() {
+            match arg.as_str() {
+                "--csv" =>
+                    csv =
+                        Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                "--delay" => {
+                    let secs =
+                        args_iter.next().ok_or(Error::MissingValue(arg))?;
+                    merge_args.delay = Duration::from_secs(
+                        secs.parse().map_err(Error::InvalidDelay)?,
+                    );
+                }
+                "--dry-run" => merge_args.dry_run = true,
+                "--from" =>
+                    merge_args.from =
+                        Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                "--template" =>
+                    template =
+                        Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                _ => return Err(Error::UnexpectedArgument(arg)),
+            }
+        }
+
+        merge_args.csv = PathBuf::from(
+            csv.ok_or_else(|| Error::MissingValue("--csv".to_owned()))?,
+        );
+        merge_args.template = PathBuf::from(
+            template
+                .ok_or_else(|| Error::MissingValue("--template".to_owned()))?,
+        );
+        Ok(merge_args)
+    }
+
+    /// Renders the messages and sends them.
+    pub fn run(self) -> Result {
+        let credentials = Credentials::load()?;
+        let address = self
+            .from
+            .as_deref()
+            .unwrap_or_else(|| credentials.as_email());
+        let identity = credentials
+            .find_identity(address)
+            .ok_or_else(|| Error::UnknownIdentity(address.to_owned()))?
+            .clone();
+
+        let dsn = Config::load().as_dsn();
+
+        let template = fs::read_to_string(&self.template)
+            .map_err(|err| Error::ReadFile(self.template.clone(), err))?;
+        let csv = fs::read_to_string(&self.csv)
+            .map_err(|err| Error::ReadFile(self.csv.clone(), err))?;
+        let rendered = render_all(&template, &csv)?
+            .into_iter()
+            .map(|(to, subject, body)| {
+                let recipients = to
+                    .split(',')
+                    .map(|recipient| recipient.trim().to_owned())
+                    .collect::<Vec<_>>();
+                check_recipients(
+                    &credentials,
+                    identity.as_address(),
+                    recipients.len(),
+                )?;
+                Ok((to, recipients, subject, body))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut stdout = io::stdout().lock();
+        let mut throttle = SendLimits::new(
+            &credentials,
+            to_smtp_route(&credentials, identity.as_address()).0,
+        )
+        .to_throttle(self.delay);
+        for (to, recipients, subject, body) in rendered {
+            let message =
+                OutgoingEmail::new(identity.clone(), recipients.clone())
+                    .with_subject(subject)
+                    .with_body(body)
+                    .to_rfc5322();
+            if self.dry_run {
+                writeln!(stdout, "{message}\n").map_err(Error::Stdout)?;
+            } else {
+                throttle.wait();
+                send_or_queue(
+                    &credentials,
+                    identity.as_address(),
+                    &recipients,
+                    message.as_bytes(),
+                    dsn,
+                )?;
+                writeln!(stdout, "Sent to {to}").map_err(Error::Stdout)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a CSV file into rows of fields
+///
+/// Fields can be quoted to contain commas, line breaks or quotes, which are
+/// then doubled. Empty lines are ignored.
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (ch, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => row.push(field.split_off(0)),
+            ('\n', false) => {
+                row.push(field.split_off(0));
+                rows.push(row.split_off(0));
+            }
+            ('\r', false) => (),
+            _ => field.push(ch),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|fields| fields.iter().any(|value| !value.trim().is_empty()));
+    rows
+}
+
+/// Replaces the `{column}` placeholders of a text with the values of a row
+fn render(text: &str, columns: &[String], row: &[String]) -> Result<String> {
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some((before, after)) = rest.split_once('{') {
+        rendered.push_str(before);
+        let Some((name, remaining)) = after.split_once('}') else {
+            return Err(Error::InvalidTemplate(format!(
+                "unclosed placeholder {{{after}"
+            ))
+            .into());
+        };
+        let value = columns
+            .iter()
+            .position(|column| column.trim() == name.trim())
+            .and_then(|idx| row.get(idx))
+            .ok_or_else(|| Error::MissingColumn(name.to_owned()))?;
+        rendered.push_str(value);
+        rest = remaining;
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Renders the destination, the subject and the body of the message of every
+/// row.
+fn render_all(
+    template: &str,
+    csv: &str,
+) -> Result<Vec<(String, String, String)>> {
+    let normalised = template.replace("\r\n", "\n");
+    let (headers, body) = normalised.split_once("\n\n").ok_or_else(|| {
+        Error::InvalidTemplate(
+            "missing empty line after the headers".to_owned(),
+        )
+    })?;
+    let mut to = DEFAULT_TO;
+    let mut subject = "";
+    for line in headers.lines() {
+        match line.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("to") =>
+                to = value.trim(),
+            Some((name, value)) if name.eq_ignore_ascii_case("subject") =>
+                subject = value.trim(),
+            _ => return Err(Error::InvalidTemplate(line.to_owned()).into()),
+        }
+    }
+
+    let mut rows = parse_csv(csv).into_iter();
+    let columns = rows.next().unwrap_or_default();
+    rows.map(|row| {
+        Ok((
+            render(to, &columns, &row)?,
+            render(subject, &columns, &row)?,
+            render(body, &columns, &row)?,
+        ))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::merge::{parse_csv, render_all};
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            parse_csv("name,email\r\n\"Doe, \"\"J\"\"\",j@x\n\nBob,b@x"),
+            [
+                vec!["name", "email"],
+                vec!["Doe, \"J\"", "j@x"],
+                vec!["Bob", "b@x"]
+            ]
+        );
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn merge() {
+        let template = "Subject: Hi {name}\n\nHello {name},\nbye";
+        assert_eq!(
+            render_all(template, "name,email\nBob,b@x\n").unwrap(),
+            [(
+                "b@x".to_owned(),
+                "Hi Bob".to_owned(),
+                "Hello Bob,\nbye".to_owned()
+            )]
+        );
+        render_all(template, "email\nb@x").unwrap_err();
+        render_all("Cc: a@x\n\nHello", "email\nb@x").unwrap_err();
+        render_all("Hello {name}", "name\nBob").unwrap_err();
+    }
+}
@@ -0,0 +1,226 @@
+//! Exposes the metrics of `mailbox watch` to Prometheus.
+//!
+//! ```sh
+//! mailbox watch --metrics 127.0.0.1:9187
+//! curl http://127.0.0.1:9187/metrics
+//! ```
+//!
+//! With `--metrics`, the watch answers `GET /metrics` on the given address, in
+//! the text format of Prometheus:
+//!
+//! - `mailbox_checks_total`: checks of the inbox, successful or not,
+//! - `mailbox_check_errors_total`: failed checks of the inbox,
+//! - `mailbox_emails_received_total`: new emails printed by the watch,
+//! - `mailbox_replies_sent_total`: automatic replies sent while on vacation,
+//! - `mailbox_inbox_emails`: emails in the inbox at the last check,
+//! - `mailbox_inbox_unread`: unread emails in the inbox at the last check,
+//! - `mailbox_queue_depth`: operations queued in the cache, e.g. replies that
+//!   couldn't be sent,
+//! - `mailbox_last_check_timestamp_seconds`: time of the last successful check.
+//!
+//! The server runs in a background thread, and only serves the metrics.
+
+use alloc::sync::Arc;
+use core::time::Duration;
+use std::io::{self, BufRead as _, BufReader, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Maximum duration to wait for the request of a scraper
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Metrics of the watch since it started
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Metrics {
+    /// Number of failed checks
+    check_errors: u64,
+    /// Number of checks, successful or not
+    checks: u64,
+    /// Number of new emails
+    emails_received: u64,
+    /// Number of emails in the inbox at the last check
+    inbox_emails: usize,
+    /// Number of unread emails in the inbox at the last check
+    inbox_unread: usize,
+    /// Time of the last successful check, in seconds since the epoch
+    last_check: u64,
+    /// Number of operations queued in the cache at the last check
+    queue_depth: usize,
+    /// Number of automatic replies sent
+    replies_sent: u64,
+}
+
+impl Metrics {
+    /// Returns the metrics in the text format of Prometheus
+    fn to_text(&self) -> String {
+        [
+            ("checks_total", "counter", "Checks of the inbox.", self.checks),
+            (
+                "check_errors_total",
+                "counter",
+                "Failed checks of the inbox.",
+                self.check_errors,
+            ),
+            (
+                "emails_received_total",
+                "counter",
+                "New emails received in the inbox.",
+                self.emails_received,
+            ),
+            (
+                "replies_sent_total",
+                "counter",
+                "Automatic replies sent while on vacation.",
+                self.replies_sent,
+            ),
+            (
+                "inbox_emails",
+                "gauge",
+                "Emails in the inbox at the last check.",
+                to_u64(self.inbox_emails),
+            ),
+            (
+                "inbox_unread",
+                "gauge",
+                "Unread emails in the inbox at the last check.",
+                to_u64(self.inbox_unread),
+            ),
+            (
+                "queue_depth",
+                "gauge",
+                "Operations queued in the cache.",
+                to_u64(self.queue_depth),
+            ),
+            (
+                "last_check_timestamp_seconds",
+                "gauge",
+                "Time of the last successful check.",
+                self.last_check,
+            ),
+        ]
+        .into_iter()
+        .map(|(name, kind, help, value)| {
+            format!(
+                "# HELP mailbox_{name} {help}\n# TYPE mailbox_{name} \
+                 {kind}\nmailbox_{name} {value}\n"
+            )
+        })
+        .collect::<Vec<_>>()
+        .concat()
+    }
+}
+
+/// Handle on the metrics served in the background
+#[derive(Clone, Debug, Default)]
+pub struct Exporter(Arc<Mutex<Metrics>>);
+
+impl Exporter {
+    /// Records a failed check of the inbox
+    pub fn record_error(&self) {
+        let mut metrics = self.lock();
+        metrics.checks = metrics.checks.saturating_add(1);
+        metrics.check_errors = metrics.check_errors.saturating_add(1);
+    }
+
+    /// Records a successful check of the inbox, with the state of the inbox
+    /// and of the queue
+    pub fn record_check(&self, emails: usize, unread: usize, queued: usize) {
+        let mut metrics = self.lock();
+        metrics.checks = metrics.checks.saturating_add(1);
+        metrics.inbox_emails = emails;
+        metrics.inbox_unread = unread;
+        metrics.queue_depth = queued;
+        metrics.last_check = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+    }
+
+    /// Records a new email, and whether it was answered
+    pub fn record_email(&self, answered: bool) {
+        let mut metrics = self.lock();
+        metrics.emails_received = metrics.emails_received.saturating_add(1);
+        if answered {
+            metrics.replies_sent = metrics.replies_sent.saturating_add(1);
+        }
+    }
+
+    /// Serves the metrics on the given address, in a background thread
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address can't be listened on.
+    pub fn serve(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let exporter = Self::default();
+        let served = exporter.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                served.respond(stream).unwrap_or_default();
+            }
+        });
+        Ok(exporter)
+    }
+
+    /// Locks the metrics, even if a thread panicked while holding them
+    fn lock(&self) -> MutexGuard<'_, Metrics> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Answers the request of a scraper
+    fn respond(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut words = request_line.split_whitespace();
+        let (status, body) = match (words.next(), words.next()) {
+            (Some("GET"), Some("/metrics")) =>
+                ("200 OK", self.lock().to_text()),
+            _ => ("404 Not Found", "Not found: try /metrics\n".to_owned()),
+        };
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; \
+             version=0.0.4\r\nContent-Length: {}\r\nConnection: \
+             close\r\n\r\n{body}",
+            body.len()
+        );
+        reader.get_mut().write_all(response.as_bytes())
+    }
+}
+
+/// Converts a count to the type of the counters
+fn to_u64(count: usize) -> u64 {
+    u64::try_from(count).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::metrics::Exporter;
+
+    #[test]
+    fn metrics() {
+        let exporter = Exporter::default();
+        exporter.record_email(true);
+        exporter.record_email(false);
+        exporter.record_error();
+        exporter.record_check(42, 3, 1);
+        let text = exporter.lock().to_text();
+        assert!(text.starts_with(
+            "# HELP mailbox_checks_total Checks of the inbox.\n# TYPE \
+             mailbox_checks_total counter\nmailbox_checks_total 2\n"
+        ));
+        for line in [
+            "mailbox_check_errors_total 1",
+            "mailbox_emails_received_total 2",
+            "mailbox_replies_sent_total 1",
+            "mailbox_inbox_emails 42",
+            "mailbox_inbox_unread 3",
+            "mailbox_queue_depth 1",
+        ] {
+            assert!(text.lines().any(|other| other == line), "{line}");
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Copies every folder of an account to another one, for provider changes.
+//!
+//! ```sh
+//! mailbox migrate --from old --to new
+//! mailbox migrate --to new          # from the main account
+//! ```
+//!
+//! The accounts are configured in the `.env` file, see
+//! [`Credentials`](crate::credentials::Credentials). The missing folders are
+//! created, except the ones that can't hold emails (`\Noselect`), and the
+//! emails are uploaded with `APPEND`, keeping their flags and the date at
+//! which the old server received them. They are fetched without marking them
+//! as seen.
+//!
+//! The emails are copied from the oldest to the newest, and the last one
+//! copied in every folder is stored in the cache of the source account, with
+//! the UIDVALIDITY of the folder. An interrupted migration thus resumes where
+//! it stopped, without duplicating the emails. A folder whose UIDVALIDITY
+//! changed since is copied again from the beginning, as its uids aren't
+//! comparable anymore. Without a cache, a migration always starts from the
+//! beginning.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write as _};
+
+use super::Error;
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::fetch::connection::ImapSession;
+use crate::fetch::mailbox::Mailbox;
+
+/// Prefix of the name of the state file of a migration in the cache, followed
+/// by the email of the destination account
+const MIGRATION_STATE: &str = "migration-";
+/// Word of the state file written instead of an unknown UIDVALIDITY
+const UNKNOWN_VALIDITY: &str = "-";
+
+/// Progress of a migration in a folder
+#[derive(Debug, PartialEq, Eq)]
+struct FolderProgress {
+    /// Uid of the last copied email
+    uid: u32,
+    /// UIDVALIDITY of the folder when the email was copied, if known
+    validity: Option<u32>,
+}
+
+/// Arguments of the `migrate` subcommand
+pub struct MigrateArgs {
+    /// Name of the account to copy from, defaults to the main one
+    from: Option<String>,
+    /// Name of the account to copy to
+    to: String,
+}
+
+impl MigrateArgs {
+    /// Parses the arguments of the `migrate` subcommand
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, Error> {
+        let mut from = None;
+        let mut to = None;
+        let mut args_iter = args;
+
+        while let Some(arg) = args_iter.next() {
+            match arg.as_str() {
+                "--from" =>
+                    from =
+                        Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                "--to" =>
+                    to = Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                _ => return Err(Error::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(Self {
+            from,
+            to: to.ok_or_else(|| Error::MissingValue("--to".to_owned()))?,
+        })
+    }
+
+    /// Copies the emails that weren't copied yet.
+    pub fn run(self) -> Result {
+        let source = Credentials::load_account(self.from.as_deref())?;
+        let destination = Credentials::load_account(Some(&self.to))?;
+        let config = Config::load();
+        let source_cache = config
+            .as_cache_dir()
+            .map(|dir| {
+                Cache::open(
+                    dir,
+                    source.as_email(),
+                    source.as_cache_key_command(),
+                )
+            })
+            .transpose()?;
+        let state_name = format!(
+            "{MIGRATION_STATE}{}",
+            destination.as_email().to_lowercase()
+        );
+        let mut progress = source_cache
+            .as_ref()
+            .and_then(|cache| cache.load_state(&state_name))
+            .map(|state| to_progress(&state))
+            .unwrap_or_default();
+
+        let mut reader = ImapSession::with_credentials(&source)?
+            .select_mailbox(&Mailbox::inbox())?;
+        let mut writer = ImapSession::with_credentials(&destination)?;
+        let existing = writer
+            .list_mailboxes()?
+            .iter()
+            .map(|mailbox| mailbox.as_name().to_owned())
+            .collect::<BTreeSet<_>>();
+        let mut stdout = io::stdout().lock();
+
+        let mut folders = reader.list_mailboxes()?;
+        folders.retain(Mailbox::is_selectable);
+        for folder in folders {
+            reader.switch_mailbox(&folder)?;
+            if !existing.contains(folder.as_name()) {
+                writer.create_mailbox(&folder)?;
+            }
+            let validity = reader.as_validity();
+            let copied_uid = match progress.get(folder.as_name()) {
+                Some(copied)
+                    if copied
+                        .validity
+                        .zip(validity)
+                        .is_some_and(|(stored, current)| stored != current) =>
+                {
+                    writeln!(
+                        stdout,
+                        "{folder}: UIDVALIDITY changed, copying it again"
+                    )
+                    .map_err(Error::Stdout)?;
+                    0
+                }
+                Some(copied) => copied.uid,
+                None => 0,
+            };
+            let flags = reader.get_flags()?;
+            let mut uids = reader.get_uids()?;
+            uids.retain(|uid| *uid > copied_uid);
+            uids.reverse();
+            for uid in &uids {
+                let (raw, date) = reader.peek_dated_mail_from_uid(*uid)?;
+                let email_flags = flags
+                    .get(uid)
+                    .map(|set| set.iter().cloned().collect::<Vec<_>>())
+                    .unwrap_or_default();
+                writer.append(&folder, raw.as_bytes(), &email_flags, date)?;
+                progress.insert(
+                    folder.as_name().to_owned(),
+                    FolderProgress { uid: *uid, validity },
+                );
+                if let Some(cache) = &source_cache {
+                    cache.store_state(&state_name, &to_state(&progress))?;
+                }
+            }
+            writeln!(stdout, "{folder}: {} emails copied", uids.len())
+                .map_err(Error::Stdout)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the state file of a migration, returning the progress in every
+/// folder
+///
+/// The lines are of the form `<uid> <validity> <folder>`.
+fn to_progress(state: &str) -> BTreeMap<String, FolderProgress> {
+    state
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.splitn(3, ' ');
+            let uid = words.next()?.parse().ok()?;
+            let validity = match words.next()? {
+                UNKNOWN_VALIDITY => None,
+                number => Some(number.parse().ok()?),
+            };
+            Some((words.next()?.to_owned(), FolderProgress { uid, validity }))
+        })
+        .collect()
+}
+
+/// Returns the content of the state file of a migration, to be parsed with
+/// [`to_progress`]
+fn to_state(progress: &BTreeMap<String, FolderProgress>) -> String {
+    progress
+        .iter()
+        .map(|(folder, copied)| {
+            let validity = copied.validity.map_or_else(
+                || UNKNOWN_VALIDITY.to_owned(),
+                |number| number.to_string(),
+            );
+            format!("{} {validity} {folder}", copied.uid)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::collections::BTreeMap;
+
+    use crate::cli::migrate::{FolderProgress, to_progress, to_state};
+
+    #[test]
+    fn progress() {
+        let progress = BTreeMap::from([
+            (
+                "INBOX".to_owned(),
+                FolderProgress { uid: 42, validity: Some(1_700_000_000) },
+            ),
+            (
+                "Clients/Big Corp".to_owned(),
+                FolderProgress { uid: 7, validity: None },
+            ),
+        ]);
+        assert_eq!(to_progress(&to_state(&progress)), progress);
+        assert!(to_progress("42 INBOX").is_empty());
+    }
+}
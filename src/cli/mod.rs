@@ -0,0 +1,275 @@
+//! Parses the command line arguments.
+//!
+//! ```sh
+//! mailbox                                   # opens the TUI
+//! mailbox --fresh                           # opens the TUI on the inbox
+//! mailbox compose                           # opens the writer
+//! mailbox compose 'mailto:bob@example.com'  # opens a pre-filled writer
+//! mailbox control refresh                   # drives the running TUI
+//! mailbox send --to bob@example.com          # sends the standard input
+//! mailbox sendmail -t                         # sendmail replacement
+//! mailbox merge --template t.md --csv people.csv # one email per row
+//! mailbox watch                             # prints and answers new emails
+//! mailbox stats                             # prints statistics of the cache
+//! mailbox digest --since 7d                 # summarises the recent emails
+//! mailbox backup --out backup.tar.zst       # archives every folder
+//! mailbox restore --in backup.tar.zst       # uploads an archive
+//! mailbox migrate --from old --to new       # copies an account to another
+//! ```
+//!
+//! A failed command exits with 78 if it was misconfigured, e.g. with invalid
+//! arguments or a missing variable in the `.env` file, with 75 if the server
+//! couldn't be reached or the connection was lost, and with 1 otherwise. The
+//! `Restart=` policy of a systemd service can thus only restart the temporary
+//! failures, see [`Error::to_exit_code`](crate::errors::Error::to_exit_code).
+//!
+//! When the SMTP server can't be reached, the messages given to `send`,
+//! `sendmail` and `merge`, and the automatic replies of `watch`, are queued in
+//! the cache, and sent the next time the TUI is online.
+
+mod backup;
+pub mod compose;
+pub mod control;
+mod digest;
+mod merge;
+mod metrics;
+mod migrate;
+mod send;
+mod sendmail;
+pub mod stats;
+mod systemd;
+mod watch;
+
+use core::num::ParseIntError;
+use std::io;
+use std::path::PathBuf;
+
+use backup::BackupArgs;
+use digest::DigestArgs;
+use merge::MergeArgs;
+use migrate::MigrateArgs;
+use send::SendArgs;
+use sendmail::SendmailArgs;
+use watch::WatchArgs;
+
+use crate::cache::Cache;
+use crate::cache::queue::Queue;
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::errors::{self, Result};
+use crate::identity::Identity;
+use crate::mailto::Mailto;
+use crate::send::connection::{self, SmtpSession};
+use crate::send::dsn::Dsn;
+use crate::send::envelope::group_recipients;
+use crate::send::quota::SendLimits;
+
+/// Errors that may occur while parsing the command line arguments.
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to reach the control socket of the running TUI.
+    Control(io::Error),
+    /// The backup archive has an unexpected entry.
+    InvalidArchive(String),
+    /// The given delay isn't a number of seconds.
+    InvalidDelay(ParseIntError),
+    /// The given format isn't `markdown` or `html`.
+    InvalidFormat(String),
+    /// The given `mailto:` URL is invalid.
+    InvalidMailto(String),
+    /// The given period isn't a number of hours, days or weeks, e.g. `7d`.
+    InvalidPeriod(String),
+    /// The given priority isn't `high`, `normal` or `low`.
+    InvalidPriority(String),
+    /// The mail merge template is malformed.
+    InvalidTemplate(String),
+    /// A placeholder of the mail merge template isn't a column of the CSV
+    /// file.
+    MissingColumn(String),
+    /// The given address isn't one of the identities of the account.
+    UnknownIdentity(String),
+    /// The message has more recipients than the provider allows, with the
+    /// number of recipients and the maximum.
+    TooManyRecipients(usize, usize),
+    /// No cache directory is configured, but the command reads the cache.
+    MissingCache,
+    /// No destination was given to send the email to.
+    MissingRecipient,
+    /// The given option expects a value, but none was provided.
+    MissingValue(String),
+    /// Failed to serve the metrics of the watch.
+    Metrics(io::Error),
+    /// Failed to notify systemd of the state of the watch.
+    Notify(io::Error),
+    /// The running TUI rejected the request, with its answer.
+    RejectedRequest(String),
+    /// Failed to read a file given on the command line.
+    ReadFile(PathBuf, io::Error),
+    /// Failed to handle the termination signals.
+    Signal(io::Error),
+    /// Failed to read the standard input.
+    Stdin(io::Error),
+    /// Failed to write to the standard output.
+    Stdout(io::Error),
+    /// Failed to write a file given on the command line.
+    WriteFile(PathBuf, io::Error),
+    /// An argument was given but not expected.
+    UnexpectedArgument(String),
+    /// The given subcommand doesn't exist.
+    UnknownCommand(String),
+}
+
+/// Action requested on the command line
+pub enum Cli {
+    /// Archives every folder of an account
+    Backup(BackupArgs),
+    /// Opens the writer, pre-filled with the given `mailto:` URL, or forwards
+    /// it to the running TUI
+    Compose(Option<String>),
+    /// Sends a request to the running TUI
+    Control(String),
+    /// Summarises the emails received recently
+    Digest(DigestArgs),
+    /// Sends one personalised email per row of a CSV file
+    Merge(MergeArgs),
+    /// Copies every folder of an account to another one
+    Migrate(MigrateArgs),
+    /// Uploads the emails of an archive to an account
+    Restore(BackupArgs),
+    /// Sends an email read from the standard input
+    Send(SendArgs),
+    /// Sends an email read from the standard input, like `sendmail`
+    Sendmail(SendmailArgs),
+    /// Prints the statistics of the cached emails
+    Stats,
+    /// Opens the TUI, restoring the last session unless `fresh` is set
+    Tui {
+        /// Indicates whether to start from the inbox instead of the last
+        /// session
+        fresh: bool,
+    },
+    /// Watches the inbox, answering the emails while on vacation
+    Watch(WatchArgs),
+}
+
+impl Cli {
+    /// Parses the command line arguments, without the program name
+    pub fn parse<I: Iterator<Item = String>>(
+        mut args: I,
+    ) -> Result<Self, Error> {
+        let Some(command) = args.next() else {
+            return Ok(Self::Tui { fresh: false });
+        };
+
+        let cli = match command.as_str() {
+            "--fresh" => Self::Tui { fresh: true },
+            "compose" => Self::Compose(
+                args.next()
+                    .map(|url| {
+                        if Mailto::parse(&url).is_some() {
+                            Ok(url)
+                        } else {
+                            Err(Error::InvalidMailto(url))
+                        }
+                    })
+                    .transpose()?,
+            ),
+            "control" =>
+                return Ok(Self::Control(args.collect::<Vec<_>>().join(" "))),
+            "backup" =>
+                return Ok(Self::Backup(BackupArgs::parse(args, "--out")?)),
+            "digest" => return Ok(Self::Digest(DigestArgs::parse(args)?)),
+            "merge" => return Ok(Self::Merge(MergeArgs::parse(args)?)),
+            "migrate" => return Ok(Self::Migrate(MigrateArgs::parse(args)?)),
+            "restore" =>
+                return Ok(Self::Restore(BackupArgs::parse(args, "--in")?)),
+            "send" => return Ok(Self::Send(SendArgs::parse(args)?)),
+            "sendmail" =>
+                return Ok(Self::Sendmail(SendmailArgs::parse(args)?)),
+            "stats" => Self::Stats,
+            "watch" => return Ok(Self::Watch(WatchArgs::parse(args)?)),
+            _ => return Err(Error::UnknownCommand(command)),
+        };
+
+        args.next()
+            .map_or(Ok(cli), |arg| Err(Error::UnexpectedArgument(arg)))
+    }
+}
+
+/// Checks that a message sent from `from` can have the given number of
+/// recipients.
+fn check_recipients(
+    credentials: &Credentials,
+    from: &str,
+    count: usize,
+) -> Result<(), Error> {
+    let limits =
+        SendLimits::new(credentials, to_smtp_route(credentials, from).0);
+    if limits.allows_recipients(count) {
+        Ok(())
+    } else {
+        Err(Error::TooManyRecipients(count, limits.as_max_recipients()))
+    }
+}
+
+/// Sends a message over SMTP
+///
+/// The message is sent through the SMTP server of the identity of `from`, if
+/// it has one, with the given delivery status notifications. If the server
+/// can't be reached, the message is queued in the cache instead.
+///
+/// The envelope sender is the [return path](crate::send::envelope) of the
+/// account, if `from` is one of its identities.
+///
+/// # Errors
+///
+/// Returns an error without sending the message if it has more recipients than
+/// the [limits](crate::send::quota) of the server allow.
+pub fn send_or_queue(
+    credentials: &Credentials,
+    from: &str,
+    recipients: &[String],
+    message: &[u8],
+    dsn: Dsn,
+) -> Result {
+    let route = to_smtp_route(credentials, from);
+    check_recipients(credentials, from, recipients.len())?;
+    let envelopes =
+        group_recipients(credentials.find_return_path(from), from, recipients);
+    match SmtpSession::with_route(credentials, route) {
+        Ok(mut session) => {
+            for (sender, group) in &envelopes {
+                session.send(sender, group, message, dsn)?;
+            }
+            session.quit()
+        }
+        Err(errors::Error::SmtpConnection(connection::Error::Io(err))) => {
+            let config = Config::load();
+            let Some(cache_dir) = config.as_cache_dir() else {
+                return Err(connection::Error::Io(err).into());
+            };
+            let cache = Cache::open(
+                cache_dir,
+                credentials.as_email(),
+                credentials.as_cache_key_command(),
+            )?;
+            let queue = Queue::new(&cache);
+            for (sender, group) in envelopes {
+                queue.push_send(sender, group, message, dsn)?;
+            }
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the SMTP server the emails of `from` are sent through
+fn to_smtp_route<'credentials>(
+    credentials: &'credentials Credentials,
+    from: &str,
+) -> (&'credentials str, u16) {
+    credentials
+        .find_identity(from)
+        .and_then(Identity::as_smtp_route)
+        .unwrap_or_else(|| credentials.as_smtp_socket_address())
+}
@@ -0,0 +1,141 @@
+//! Sends an email read from the standard input, for scripts and cron jobs.
+//!
+//! ```sh
+//! echo "Everything went fine" | mailbox send --to bob@example.com --subject report
+//! echo "The server is down" | mailbox send --to bob@example.com --priority high
+//! echo "Ticket closed" | mailbox send --from support@example.com --to bob@example.com
+//! mailbox send --raw --to bob@example.com < message.eml
+//! mailbox send --notify success,failure --to bob@example.com < contract.txt
+//! mailbox send --dry-run --to bob@example.com < report.txt
+//! ```
+//!
+//! `--dry-run` prints the message that would be sent, after checking that the
+//! provider accepts its number of recipients.
+//!
+//! `--notify` requests [delivery status notifications](crate::send::dsn) for
+//! the comma-separated events, or none with `never`, instead of the ones of the
+//! `DSN_NOTIFY` variable of the config.
+
+use std::io::{self, Read as _, Write as _};
+
+use super::{Error, check_recipients, send_or_queue};
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::priority::Priority;
+use crate::send::dsn::Dsn;
+use crate::send::message::OutgoingEmail;
+
+/// Arguments of the `send` subcommand
+pub struct SendArgs {
+    /// Delivery status notifications to request, defaults to the config
+    dsn: Option<Dsn>,
+    /// Whether the message must be printed instead of sent
+    dry_run: bool,
+    /// Address of the identity to send from, defaults to the email of the
+    /// credentials
+    from: Option<String>,
+    /// Priority of the email, ignored for raw messages
+    priority: Priority,
+    /// Whether the standard input is a full RFC 5322 message, or only the body
+    raw: bool,
+    /// Subject of the email, ignored for raw messages
+    subject: String,
+    /// Destinations of the email
+    to: Vec<String>,
+}
+
+impl SendArgs {
+    /// Parses the arguments of the `send` subcommand
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, Error> {
+        let mut send_args = Self {
+            dsn: None,
+            dry_run: false,
+            from: None,
+            priority: Priority::Normal,
+            raw: false,
+            subject: String::new(),
+            to: vec![],
+        };
+        let mut args_iter = args;
+
+        while let Some(arg) = args_iter.next() {
+            match arg.as_str() {
+                "--dry-run" => send_args.dry_run = true,
+                "--from" =>
+                    send_args.from =
+                        Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                "--notify" =>
+                    send_args.dsn = Some(Dsn::parse(
+                        &args_iter.next().ok_or(Error::MissingValue(arg))?,
+                    )),
+                "--priority" => {
+                    let name =
+                        args_iter.next().ok_or(Error::MissingValue(arg))?;
+                    send_args.priority = Priority::parse(&name)
+                        .ok_or(Error::InvalidPriority(name))?;
+                }
+                "--raw" => send_args.raw = true,
+                "--subject" =>
+                    send_args.subject =
+                        args_iter.next().ok_or(Error::MissingValue(arg))?,
+                "--to" => send_args
+                    .to
+                    .push(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                _ => return Err(Error::UnexpectedArgument(arg)),
+            }
+        }
+
+        if send_args.to.is_empty() {
+            return Err(Error::MissingRecipient);
+        }
+
+        Ok(send_args)
+    }
+
+    /// Reads the standard input and sends it.
+    pub fn run(self) -> Result {
+        let credentials = Credentials::load()?;
+        let address = self
+            .from
+            .as_deref()
+            .unwrap_or_else(|| credentials.as_email());
+        let identity = credentials
+            .find_identity(address)
+            .ok_or_else(|| Error::UnknownIdentity(address.to_owned()))?
+            .clone();
+
+        let mut stdin = vec![];
+        io::stdin().read_to_end(&mut stdin).map_err(Error::Stdin)?;
+
+        let message = if self.raw {
+            stdin
+        } else {
+            OutgoingEmail::new(identity.clone(), self.to.clone())
+                .with_subject(self.subject)
+                .with_priority(self.priority)
+                .with_body(String::from_utf8_lossy(&stdin).into_owned())
+                .to_rfc5322()
+                .into_bytes()
+        };
+
+        if self.dry_run {
+            check_recipients(
+                &credentials,
+                identity.as_address(),
+                self.to.len(),
+            )?;
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&message).map_err(Error::Stdout)?;
+            return writeln!(stdout).map_err(|err| Error::Stdout(err).into());
+        }
+
+        send_or_queue(
+            &credentials,
+            identity.as_address(),
+            &self.to,
+            &message,
+            self.dsn.unwrap_or_else(|| Config::load().as_dsn()),
+        )
+    }
+}
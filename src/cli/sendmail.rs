@@ -0,0 +1,256 @@
+//! Sendmail-compatible interface, so that [`mailbox`](crate) can be used as
+//! the mail transfer agent of other tools, like `git send-email` or `cron`.
+//!
+//! ```sh
+//! mailbox sendmail -t < message.eml
+//! mailbox sendmail -f bob@example.com -i alice@example.com < message.eml
+//! ```
+//!
+//! The supported flags are:
+//! - `-t`: read the recipients from the `To`, `Cc` and `Bcc` headers. The `Bcc`
+//!   header is then removed from the message.
+//! - `-f <address>`: set the envelope sender, which receives the bounces. The
+//!   [return path](crate::send::envelope) of the account replaces the addresses
+//!   of its identities.
+//! - `-N <events>`: request [delivery status notifications](crate::send::dsn)
+//!   for the comma-separated events, or none with `never`. This defaults to the
+//!   `DSN_NOTIFY` variable of the config.
+//!
+//! Other flags commonly passed to sendmail (`-i`, `-oi`, `-F <name>`, etc.)
+//! are accepted and ignored. The value of an ignored flag that takes one, e.g.
+//! `-R hdrs`, isn't taken for a recipient.
+
+use std::io::{self, Read as _};
+
+use mail_parser::{HeaderName, MessageParser};
+
+use super::{Error, send_or_queue};
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::send::dsn::Dsn;
+
+/// Ignored flags of sendmail followed by a value, when it isn't attached to
+/// the flag, e.g. `-F Bob` instead of `-FBob`
+const IGNORED_WITH_VALUE: [&str; 9] =
+    ["-B", "-C", "-F", "-L", "-O", "-R", "-V", "-X", "-h"];
+
+/// Arguments of the `sendmail` subcommand
+pub struct SendmailArgs {
+    /// Delivery status notifications to request, defaults to the config
+    dsn: Option<Dsn>,
+    /// Envelope sender, defaults to the email of the credentials
+    envelope_from: Option<String>,
+    /// Whether the recipients must be read from the headers of the message
+    read_recipients: bool,
+    /// Recipients given on the command line
+    recipients: Vec<String>,
+}
+
+impl SendmailArgs {
+    /// Parses the arguments of the `sendmail` subcommand
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, Error> {
+        let mut sendmail_args = Self {
+            dsn: None,
+            envelope_from: None,
+            read_recipients: false,
+            recipients: vec![],
+        };
+        let mut args_iter = args;
+
+        while let Some(arg) = args_iter.next() {
+            if arg == "-t" {
+                sendmail_args.read_recipients = true;
+            } else if let Some(from) = arg.strip_prefix("-f") {
+                sendmail_args.envelope_from = Some(if from.is_empty() {
+                    args_iter.next().ok_or(Error::MissingValue(arg))?
+                } else {
+                    from.to_owned()
+                });
+            } else if let Some(events) = arg.strip_prefix("-N") {
+                sendmail_args.dsn = Some(Dsn::parse(&if events.is_empty() {
+                    args_iter.next().ok_or(Error::MissingValue(arg))?
+                } else {
+                    events.to_owned()
+                }));
+            } else if IGNORED_WITH_VALUE.contains(&arg.as_str()) {
+                args_iter.next().ok_or(Error::MissingValue(arg))?;
+            } else if arg == "--" {
+                sendmail_args.recipients.extend(args_iter.by_ref());
+            } else if arg.starts_with('-') {
+                // Other sendmail options don't apply to SMTP submission.
+            } else {
+                sendmail_args.recipients.push(arg);
+            }
+        }
+
+        Ok(sendmail_args)
+    }
+
+    /// Reads the message on the standard input and sends it.
+    pub fn run(self) -> Result {
+        let credentials = Credentials::load()?;
+
+        let mut message = vec![];
+        io::stdin()
+            .read_to_end(&mut message)
+            .map_err(Error::Stdin)?;
+
+        let mut recipients = self.recipients;
+        if self.read_recipients {
+            recipients.extend(header_recipients(&message));
+            message = remove_bcc(&message);
+        }
+        if recipients.is_empty() {
+            return Err(Error::MissingRecipient.into());
+        }
+
+        let envelope_from = self
+            .envelope_from
+            .as_deref()
+            .unwrap_or_else(|| credentials.as_email());
+        let dsn = self.dsn.unwrap_or_else(|| Config::load().as_dsn());
+        send_or_queue(&credentials, envelope_from, &recipients, &message, dsn)
+    }
+}
+
+/// Returns the addresses of the `To`, `Cc` and `Bcc` headers of a message
+fn header_recipients(message: &[u8]) -> Vec<String> {
+    let Some(parsed) = MessageParser::default().parse_headers(message) else {
+        return vec![];
+    };
+
+    [HeaderName::To, HeaderName::Cc, HeaderName::Bcc]
+        .iter()
+        .filter_map(|header_name| parsed.header(header_name.clone()))
+        .filter_map(|header| header.as_address())
+        .flat_map(|address| {
+            address
+                .iter()
+                .filter_map(|addr| addr.address().map(ToOwned::to_owned))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Removes the `Bcc` header of a message, to keep blind carbon copies hidden
+/// from the other recipients.
+fn remove_bcc(message: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(message.len());
+    let mut in_headers = true;
+    let mut in_bcc = false;
+
+    for line in message.split_inclusive(|byte| *byte == b'\n') {
+        if in_headers {
+            if line == b"\n" || line == b"\r\n" {
+                in_headers = false;
+            } else if line
+                .first()
+                .is_some_and(|byte| *byte == b' ' || *byte == b'\t')
+            {
+                // Continuation lines belong to the previous header.
+                if in_bcc {
+                    continue;
+                }
+            } else {
+                in_bcc = line
+                    .get(..4)
+                    .is_some_and(|name| name.eq_ignore_ascii_case(b"bcc:"));
+                if in_bcc {
+                    continue;
+                }
+            }
+        }
+        output.extend_from_slice(line);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::Error;
+    use crate::cli::sendmail::{SendmailArgs, header_recipients, remove_bcc};
+
+    const MESSAGE: &[u8] = b"From: bob@example.com\r
+To: Alice <alice@example.com>, carol@example.com\r
+BCC: dave@example.com,\r
+ eve@example.com\r
+Subject: Bcc: test\r
+\r
+Bcc: this is the body\r
+";
+
+    #[test]
+    fn recipients() {
+        assert_eq!(
+            header_recipients(MESSAGE),
+            [
+                "alice@example.com",
+                "carol@example.com",
+                "dave@example.com",
+                "eve@example.com"
+            ]
+        );
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn arguments() {
+        let args = SendmailArgs::parse(
+            [
+                "-oi",
+                "-R",
+                "hdrs",
+                "-V",
+                "envid",
+                "-Fbob",
+                "-F",
+                "Bob",
+                "-h",
+                "3",
+                "-B",
+                "8BITMIME",
+                "-O",
+                "DeliveryMode=b",
+                "-f",
+                "bob@example.com",
+                "-X",
+                "log",
+                "-L",
+                "label",
+                "-C",
+                "file",
+                "alice@example.com",
+                "--",
+                "-carol@example.com",
+            ]
+            .into_iter()
+            .map(ToOwned::to_owned),
+        )
+        .unwrap();
+        assert_eq!(
+            args.recipients,
+            ["alice@example.com", "-carol@example.com"]
+        );
+        assert_eq!(args.envelope_from.as_deref(), Some("bob@example.com"));
+        assert!(!args.read_recipients);
+        assert!(matches!(
+            SendmailArgs::parse(["-t", "-R"].into_iter().map(ToOwned::to_owned)),
+            Err(Error::MissingValue(flag)) if flag == "-R"
+        ));
+    }
+
+    #[test]
+    fn bcc_removal() {
+        assert_eq!(
+            remove_bcc(MESSAGE),
+            b"From: bob@example.com\r
+To: Alice <alice@example.com>, carol@example.com\r
+Subject: Bcc: test\r
+\r
+Bcc: this is the body\r
+"
+        );
+    }
+}
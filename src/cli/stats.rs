@@ -0,0 +1,41 @@
+//! Prints the statistics of the emails stored in the cache.
+//!
+//! ```sh
+//! mailbox stats
+//! ```
+//!
+//! The statistics are the ones of the `:stats` command of the TUI, see
+//! [`analytics`](crate::cache::analytics). Nothing is fetched from the server,
+//! so a cache must be configured.
+
+use std::io::{self, Write as _};
+
+use super::Error;
+use crate::cache::Cache;
+use crate::cache::analytics::Statistics;
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+use crate::identity::Identity;
+
+/// Computes the statistics of the cache and prints them.
+pub fn run() -> Result {
+    let credentials = Credentials::load()?;
+    let config = Config::load();
+    let cache_dir = config.as_cache_dir().ok_or(Error::MissingCache)?;
+    let cache = Cache::open(
+        cache_dir,
+        credentials.as_email(),
+        credentials.as_cache_key_command(),
+    )?;
+    let addresses = credentials
+        .as_identities()
+        .iter()
+        .map(Identity::as_address)
+        .collect::<Vec<_>>();
+    let mut stdout = io::stdout().lock();
+    for line in Statistics::compute(&cache, &addresses)?.to_lines() {
+        writeln!(stdout, "{line}").map_err(Error::Stdout)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Integrates `mailbox watch` with systemd, when it runs as a `Type=notify`
+//! service, e.g. `assets/mailbox-watch.service`.
+//!
+//! systemd is told when the watch is ready and when it stops, through the
+//! socket of the `NOTIFY_SOCKET` variable. If the service has a
+//! `WatchdogSec=`, the watch pings systemd at half that interval while it
+//! waits for the next check, so that a watch stuck on a dead connection is
+//! restarted.
+//!
+//! Without the `NOTIFY_SOCKET` variable, e.g. when the watch is started from a
+//! terminal, nothing is sent.
+
+use core::time::Duration;
+use std::env::var;
+use std::os::linux::net::SocketAddrExt as _;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::time::Instant;
+use std::{io, process};
+
+/// Client of the notification socket of systemd
+pub struct Notifier {
+    /// Time at which the watchdog was last pinged
+    last_ping: Instant,
+    /// Socket of systemd, if the process is a `Type=notify` service
+    socket: Option<(UnixDatagram, SocketAddr)>,
+    /// Interval at which the watchdog expects a ping, if it is enabled for
+    /// this process
+    watchdog: Option<Duration>,
+}
+
+impl Notifier {
+    /// Connects to the socket of systemd given by the environment, if any
+    pub fn from_env() -> io::Result<Self> {
+        let socket = var("NOTIFY_SOCKET")
+            .ok()
+            .map(|path| {
+                let address = path.strip_prefix('@').map_or_else(
+                    || SocketAddr::from_pathname(&path),
+                    SocketAddr::from_abstract_name,
+                )?;
+                Ok::<_, io::Error>((UnixDatagram::unbound()?, address))
+            })
+            .transpose()?;
+        let watchdog = var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse().ok())
+            .filter(|_| {
+                var("WATCHDOG_PID").map_or(true, |pid| {
+                    pid.parse::<u32>()
+                        .is_ok_and(|parsed| parsed == process::id())
+                })
+            })
+            .map(Duration::from_micros);
+        Ok(Self { last_ping: Instant::now(), socket, watchdog })
+    }
+
+    /// Pings the watchdog, if half its interval elapsed since the last ping
+    pub fn ping_watchdog(&mut self) -> io::Result<()> {
+        if let Some(interval) = self.watchdog
+            && self.last_ping.elapsed()
+                >= interval.checked_div(2).unwrap_or(interval)
+        {
+            self.send("WATCHDOG=1")?;
+            self.last_ping = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Tells systemd that the watch is ready
+    pub fn ready(&mut self) -> io::Result<()> {
+        self.last_ping = Instant::now();
+        self.send("READY=1")
+    }
+
+    /// Tells systemd that the watch is stopping, with a status message
+    pub fn stopping(&self, status: &str) -> io::Result<()> {
+        self.send(&format!("STOPPING=1\nSTATUS={status}"))
+    }
+
+    /// Sends a state to systemd, if the process is a `Type=notify` service
+    fn send(&self, state: &str) -> io::Result<()> {
+        if let Some((socket, address)) = &self.socket {
+            socket.send_to_addr(state.as_bytes(), address)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//! Watches the inbox, printing the received emails and answering them while
+//! on vacation.
+//!
+//! ```sh
+//! mailbox watch
+//! mailbox watch --interval 300
+//! mailbox watch --metrics 127.0.0.1:9187
+//! ```
+//!
+//! The inbox is checked every `--interval` seconds (60 by default), without
+//! marking the emails as seen. If the `VACATION_TEMPLATE` of the
+//! [`Config`] is set, the received emails are answered with it, see
+//! [`vacation`](crate::send::vacation). The answered senders are stored in the
+//! cache, so that restarting the watch doesn't answer them twice. The
+//! one-time code of a received email, e.g. of a sign-in, is printed with it,
+//! see [`otp`](crate::otp).
+//!
+//! A failed check is reported, and the watch connects again to the server for
+//! the next one: it only stops if the server can't be reached anymore. With
+//! `--metrics`, the checks are counted and exposed to Prometheus, see
+//! [`metrics`](super::metrics).
+//!
+//! When it starts, the cache is checked for the files damaged by a crash, see
+//! [`integrity`](crate::cache::integrity).
+//!
+//! The watch stops on `SIGTERM` or `SIGINT` between two checks, after
+//! replaying the operations queued in the cache, e.g. the automatic replies
+//! that couldn't be sent. If a TUI is running for the same account, the replay
+//! is delegated to it, see [`lock`](crate::cache::lock). It can run as a
+//! systemd service, see [`systemd`](super::systemd).
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use std::fs;
+use std::io::{self, StdoutLock, Write as _};
+use std::thread::sleep;
+use std::time::{Instant, SystemTime};
+
+use mail_parser::HeaderName;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
+
+use super::metrics::Exporter;
+use super::systemd::Notifier;
+use super::{Error, send_or_queue};
+use crate::cache::queue::Queue;
+use crate::cache::{Cache, integrity};
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::errors::{self, Result};
+use crate::fetch::connection::{ImapSession, MailboxSelected};
+use crate::fetch::mailbox::Mailbox;
+use crate::fetch::parser::Email;
+use crate::identity::find_for_reply;
+use crate::otp::find_code;
+use crate::send::dsn::Dsn;
+use crate::send::vacation::Vacation;
+use crate::tui::control::{is_running, send_request};
+
+/// Default delay between two checks of the inbox, in seconds
+const DEFAULT_INTERVAL: u64 = 60;
+/// Flag of the emails that were read
+const SEEN_FLAG: &str = "\\Seen";
+/// Delay between two checks of the termination signals while waiting
+const TICK: Duration = Duration::from_secs(1);
+/// Name of the state file of the answered senders in the cache
+const VACATION_STATE: &str = "vacation";
+
+/// Arguments of the `watch` subcommand
+pub struct WatchArgs {
+    /// Delay between two checks of the inbox
+    interval: Duration,
+    /// Address the metrics are served on, if any
+    metrics: Option<String>,
+}
+
+impl WatchArgs {
+    /// Parses the arguments of the `watch` subcommand
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, Error> {
+        let mut watch_args = Self {
+            interval: Duration::from_secs(DEFAULT_INTERVAL),
+            metrics: None,
+        };
+        let mut args_iter = args;
+
+        while let Some(arg) = args_iter.next() {
+            match arg.as_str() {
+                "--interval" => {
+                    let secs =
+                        args_iter.next().ok_or(Error::MissingValue(arg))?;
+                    watch_args.interval = Duration::from_secs(
+                        secs.parse().map_err(Error::InvalidDelay)?,
+                    );
+                }
+                "--metrics" =>
+                    watch_args.metrics =
+                        Some(args_iter.next().ok_or(Error::MissingValue(arg))?),
+                _ => return Err(Error::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(watch_args)
+    }
+
+    /// Checks the inbox until the process is stopped by a signal.
+    pub fn run(self) -> Result {
+        let credentials = Credentials::load()?;
+        let config = Config::load();
+        let cache = config
+            .as_cache_dir()
+            .map(|dir| {
+                Cache::open(
+                    dir,
+                    credentials.as_email(),
+                    credentials.as_cache_key_command(),
+                )
+            })
+            .transpose()?;
+        let mut vacation = config
+            .as_vacation_template()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .map_err(|err| Error::ReadFile(path.to_owned(), err))
+            })
+            .transpose()?
+            .map(|template| {
+                Vacation::parse(&template, config.as_vacation_window())
+            });
+        if let Some(state) = cache
+            .as_ref()
+            .and_then(|opened| opened.load_state(VACATION_STATE))
+            && let Some(replies) = &mut vacation
+        {
+            replies.load_state(&state);
+        }
+
+        let terminated = Arc::new(AtomicBool::new(false));
+        for signal in [SIGINT, SIGTERM] {
+            flag::register(signal, Arc::clone(&terminated))
+                .map_err(Error::Signal)?;
+        }
+        let mut notifier = Notifier::from_env().map_err(Error::Notify)?;
+        let exporter = self
+            .metrics
+            .as_deref()
+            .map(Exporter::serve)
+            .transpose()
+            .map_err(Error::Metrics)?;
+
+        let mut session = ImapSession::with_credentials(&credentials)?
+            .select_mailbox(&Mailbox::inbox())?;
+        let mut last_uid = session.get_uids()?.first().copied().unwrap_or(0);
+        let mut stdout = io::stdout().lock();
+        if let Some(opened) = &cache {
+            let repairs = integrity::repair(opened)?;
+            if !repairs.is_empty() {
+                writeln!(stdout, "{repairs}").map_err(Error::Stdout)?;
+            }
+        }
+        notifier.ready().map_err(Error::Notify)?;
+        while wait(self.interval, &terminated, &mut notifier)? {
+            if let Err(err) = check(
+                &credentials,
+                cache.as_ref(),
+                &mut session,
+                vacation.as_mut(),
+                &mut last_uid,
+                exporter.as_ref(),
+                &mut stdout,
+            ) {
+                session = reconnect(
+                    &credentials,
+                    &err,
+                    exporter.as_ref(),
+                    &mut stdout,
+                )?;
+            }
+        }
+
+        notifier
+            .stopping("Replaying the queued operations")
+            .map_err(Error::Notify)?;
+        stop(&credentials, cache.as_ref(), &mut session, &mut stdout)
+    }
+}
+
+/// Waits until the next check, pinging the watchdog of systemd meanwhile
+///
+/// Returns `false` if the watch was asked to stop.
+fn wait(
+    interval: Duration,
+    terminated: &AtomicBool,
+    notifier: &mut Notifier,
+) -> Result<bool> {
+    let start = Instant::now();
+    while !terminated.load(Ordering::Relaxed) {
+        let remaining = interval.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Ok(true);
+        }
+        notifier.ping_watchdog().map_err(Error::Notify)?;
+        sleep(remaining.min(TICK));
+    }
+    Ok(false)
+}
+
+/// Prints and answers the emails received since the last check, and records
+/// the state of the inbox in the metrics
+fn check(
+    credentials: &Credentials,
+    cache: Option<&Cache>,
+    session: &mut ImapSession<MailboxSelected>,
+    mut vacation: Option<&mut Vacation>,
+    last_uid: &mut u32,
+    exporter: Option<&Exporter>,
+    stdout: &mut StdoutLock<'_>,
+) -> Result {
+    let uids = session.get_uids()?;
+    let new_uids = uids
+        .iter()
+        .rev()
+        .copied()
+        .filter(|uid| uid > last_uid)
+        .collect::<Vec<_>>();
+    for uid in new_uids {
+        *last_uid = uid;
+        let raw = session.peek_mail_from_uid(uid)?;
+        let email = Email::try_from((uid, raw.as_bytes()))?;
+        let answered = print_and_answer(
+            credentials,
+            cache,
+            vacation.as_deref_mut(),
+            &email,
+            stdout,
+        )?;
+        if let Some(metrics) = exporter {
+            metrics.record_email(answered);
+        }
+    }
+    if let Some(metrics) = exporter {
+        let unread = session
+            .get_flags()?
+            .values()
+            .filter(|flags| !flags.contains(SEEN_FLAG))
+            .count();
+        let queued = cache
+            .map(|opened| Queue::new(opened).load())
+            .transpose()?
+            .map_or(0, |operations| operations.len());
+        metrics.record_check(uids.len(), unread, queued);
+    }
+    Ok(())
+}
+
+/// Reports a failed check, and connects again to the server for the next one
+#[expect(clippy::use_debug, reason = "the errors only implement Debug")]
+fn reconnect(
+    credentials: &Credentials,
+    err: &errors::Error,
+    exporter: Option<&Exporter>,
+    stdout: &mut StdoutLock<'_>,
+) -> Result<ImapSession<MailboxSelected>> {
+    if let Some(metrics) = exporter {
+        metrics.record_error();
+    }
+    writeln!(stdout, "Failed to check the inbox, reconnecting: {err:?}")
+        .map_err(Error::Stdout)?;
+    ImapSession::with_credentials(credentials)?
+        .select_mailbox(&Mailbox::inbox())
+}
+
+/// Prints a received email, and answers it while on vacation
+///
+/// The answered senders are stored in the cache. Returns whether the email
+/// was answered.
+fn print_and_answer(
+    credentials: &Credentials,
+    cache: Option<&Cache>,
+    vacation: Option<&mut Vacation>,
+    email: &Email,
+    stdout: &mut StdoutLock<'_>,
+) -> Result<bool> {
+    writeln!(
+        stdout,
+        "New email from {}: {}{}",
+        email.get_header_str(&HeaderName::From).unwrap_or_default(),
+        email
+            .get_header_str(&HeaderName::Subject)
+            .unwrap_or_default(),
+        find_code(email)
+            .map(|code| format!(" (code {code})"))
+            .unwrap_or_default()
+    )
+    .map_err(Error::Stdout)?;
+    if let Some(replies) = vacation
+        && let Some(recipients) = answer(credentials, replies, email)?
+    {
+        writeln!(stdout, "Answered {recipients}").map_err(Error::Stdout)?;
+        if let Some(opened) = cache {
+            opened.store_state(
+                VACATION_STATE,
+                &replies.to_state(SystemTime::now()),
+            )?;
+        }
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Replays the operations queued in the cache before stopping, e.g. the
+/// automatic replies that couldn't be sent
+///
+/// If a TUI is running for the same account, it is asked to replay them, so
+/// that it isn't kept waiting for the lock of the cache meanwhile.
+fn stop(
+    credentials: &Credentials,
+    cache: Option<&Cache>,
+    session: &mut ImapSession<MailboxSelected>,
+    stdout: &mut StdoutLock<'_>,
+) -> Result {
+    let Some(opened) = cache else {
+        return Ok(());
+    };
+    if is_running(credentials.as_email())
+        && send_request(credentials.as_email(), "sync")
+            .is_ok_and(|answer| answer == "ok")
+    {
+        writeln!(stdout, "Stopped. The TUI replays the queued operations")
+            .map_err(Error::Stdout)?;
+        return Ok(());
+    }
+    let summary = Queue::new(opened).replay(credentials, session)?;
+    writeln!(stdout, "Stopped. {summary}").map_err(Error::Stdout)?;
+    Ok(())
+}
+
+/// Sends the automatic reply to a received email, if its sender must be
+/// answered
+///
+/// The reply is sent from the identity the email was sent to. Returns the
+/// recipients of the reply, if one was sent.
+fn answer(
+    credentials: &Credentials,
+    vacation: &mut Vacation,
+    email: &Email,
+) -> Result<Option<String>> {
+    let identities = credentials.as_identities();
+    let identity = identities
+        .get(find_for_reply(identities, email))
+        .ok_or_else(|| {
+            Error::UnknownIdentity(credentials.as_email().to_owned())
+        })?;
+    let Some(reply) = vacation.answer(email, identity, SystemTime::now())
+    else {
+        return Ok(None);
+    };
+    send_or_queue(
+        credentials,
+        reply.as_from(),
+        &reply.to_recipients(),
+        reply.to_rfc5322().as_bytes(),
+        Dsn::default(),
+    )?;
+    Ok(Some(reply.to_recipients().join(", ")))
+}
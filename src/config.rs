@@ -0,0 +1,720 @@
+//! Handles the user configuration, by loading it from the `.env` file.
+//!
+//! Unlike the [`Credentials`](crate::credentials::Credentials), every
+//! configuration variable is optional and falls back to a sensible default:
+//!
+//! ```env
+//! ATTACHMENT_OPENER=xdg-open %s
+//! BADGES=bob@example.com=Work:blue; support@example.com=SUP:yellow
+//! BLOCKED_SENDERS=junk
+//! CACHE_DIR=/home/bob/.cache/mailbox
+//! CACHE_MAX_AGE=90
+//! CACHE_MAX_SIZE=500
+//! CACHE_RECENT_BODIES=1000
+//! CALENDAR_COMMAND=khal import --batch /dev/stdin
+//! CLIPBOARD_COMMAND=wl-copy
+//! MAILCAP_PATH=/home/bob/.mailcap
+//! PREVIEW_PANE=true
+//! PRINT_COMMAND=lpr -P office
+//! DISPLAY_FILTER_TEXT_HTML=w3m -dump -T text/html
+//! DISPLAY_FILTER_TEXT_PLAIN=par 72
+//! DSN_NOTIFY=failure,delay
+//! FOLDER_SHORTCUTS=INBOX, Clients/2024, local:Archives
+//! SYNC_INCLUDE=INBOX, Work/*
+//! SYNC_EXCLUDE=Spam, Archive*
+//! THEME=high-contrast
+//! TRUSTED_HTML=false
+//! INDEX_FORMAT=%date %-20from %subject
+//! JUNK_FOLDER=Junk
+//! LEARN_SPAM_COMMAND=rspamc learn_spam
+//! LEARN_HAM_COMMAND=rspamc learn_ham
+//! LINK_REFERENCES=true
+//! LIST_MARKERS=true
+//! RELATIVE_NUMBERS=true
+//! REMOTE_CONTENT_SENDERS=boss@example.com, newsletter.example.org
+//! RESPONSE_TEMPLATES=Thanks, received.|I will get back to you soon.
+//! SENDER_PICTURES=true
+//! SPLIT_ORIENTATION=auto
+//! SPLIT_RATIO=40
+//! WRAP_COLUMN=72
+//! FORMAT_FLOWED=true
+//! UNDO_SEND_DELAY=10
+//! VACATION_TEMPLATE=/home/bob/.config/mailbox/vacation.txt
+//! VACATION_WINDOW=7
+//! ```
+//!
+//! The badges tell which identity of the account the emails were sent to, see
+//! [`badges`](crate::tui::badges).
+//!
+//! The folder shortcuts are opened with `g1` to `g9`, see
+//! [`shortcuts`](crate::tui::shortcuts).
+//!
+//! The cache retention is given in days for the maximum age, in MiB for the
+//! maximum size, and in number of emails per mailbox whose body is kept. The
+//! cache isn't pruned if none of these are set.
+//!
+//! The sync variables are comma-separated globs selecting the folders that are
+//! stored in the cache, see [`folders`](crate::fetch::folders).
+//!
+//! The split ratio is the percentage of the screen taken by the list when an
+//! email is opened, and the orientation is `horizontal`, `vertical` or `auto`,
+//! see [`split`](crate::tui::split).
+//!
+//! The body of the written emails is wrapped at the wrap column, or not at all
+//! if it is 0, and sent as `format=flowed` if the flag is set, see
+//! [`wrap`](crate::send::wrap).
+//!
+//! The delivery status notifications of the comma-separated events are
+//! requested for the emails written by the user, see
+//! [`dsn`](crate::send::dsn).
+//!
+//! The emails written in the TUI are sent after the undo send delay, in
+//! seconds, during which the sending can be cancelled. They are sent right
+//! away if it is 0.
+//!
+//! While the vacation template is set, `mailbox watch` answers the received
+//! emails with it, at most once per sender every vacation window, in days, see
+//! [`vacation`](crate::send::vacation).
+//!
+//! The emails moved to the junk folder are piped into the learn spam command,
+//! and the ones rescued from it into the learn ham command, see
+//! [`junk`](crate::junk).
+//!
+//! The emails of the blocked senders are hidden from the list, or moved to the
+//! junk folder if the blocked senders variable is `junk`, see
+//! [`senders`](crate::tui::senders).
+//!
+//! The response templates, separated by `|`, are sent as replies to the opened
+//! email with the digit keys, see [`responses`](crate::tui::responses).
+//!
+//! The HTML bodies are sanitized before being rendered or saved, unless the
+//! trusted HTML flag is set, and keep their remote content only for the remote
+//! content senders, see [`html`](crate::html).
+//!
+//! The URLs of the displayed bodies are replaced with numbered references if
+//! the link references flag is set, see [`links`](crate::tui::links).
+//!
+//! The pictures of the senders are fetched from Gravatar only if the sender
+//! pictures flag is set, see [`pictures`](crate::tui::pictures).
+//!
+//! The calendar invites of the opened email are piped into the calendar
+//! command with `:invite`, see [`calendar`](crate::calendar).
+//!
+//! The one-time codes are copied with `y` by piping them into the clipboard
+//! command, see [`otp`](crate::otp).
+//!
+//! The theme is forced to `no-color` if the `NO_COLOR` variable is set, see
+//! <https://no-color.org>.
+
+use core::str::FromStr;
+use core::time::Duration;
+use std::env::var;
+use std::path::{Path, PathBuf};
+
+use crate::cache::retention::Retention;
+use crate::fetch::folders::FolderFilter;
+use crate::html::Policy;
+use crate::junk::Training;
+use crate::send::dsn::Dsn;
+use crate::tui::badges::Badges;
+use crate::tui::index_format::IndexFormat;
+use crate::tui::responses::ResponseTemplates;
+use crate::tui::senders::BlockedAction;
+use crate::tui::shortcuts::FavouriteFolders;
+use crate::tui::split::{Orientation, Split};
+use crate::tui::theme::Theme;
+
+/// User configuration of the app.
+///
+/// The configuration is read from the environment, so the `.env` file must
+/// have been loaded beforehand, which is done when loading the
+/// [`Credentials`](crate::credentials::Credentials).
+#[expect(clippy::struct_excessive_bools, reason = "independent options")]
+pub struct Config {
+    /// Command to open attachments that are not in the mailcap file
+    ///
+    /// This is set to `xdg-open %s` if none were provided.
+    attachment_opener: String,
+    /// Badges of the identities, shown next to their emails
+    badges: Badges,
+    /// What happens to the emails of the blocked senders
+    ///
+    /// They are hidden if none were provided.
+    blocked_action: BlockedAction,
+    /// Directory where the emails are stored to be read offline
+    ///
+    /// This is set to `$XDG_CACHE_HOME/mailbox`, or `~/.cache/mailbox`, if
+    /// none were provided.
+    cache_dir: Option<PathBuf>,
+    /// Retention policy of the cache
+    ///
+    /// The cache is never pruned if none were provided.
+    cache_retention: Retention,
+    /// Command the calendar invites are piped into, to add them to a calendar
+    ///
+    /// The invites can only be saved to files if none were provided.
+    calendar_command: Option<String>,
+    /// Command the one-time codes are piped into, to copy them
+    ///
+    /// They are copied with an OSC 52 sequence if none were provided.
+    clipboard_command: Option<String>,
+    /// Delivery status notifications requested for the emails written by the
+    /// user
+    dsn: Dsn,
+    /// Folders that are stored in the cache
+    ///
+    /// Every folder is synced if none were provided.
+    folder_filter: FolderFilter,
+    /// Favourite folders, opened with `g` and a digit
+    folder_shortcuts: FavouriteFolders,
+    /// Indicates whether the written emails are sent as `format=flowed`
+    format_flowed: bool,
+    /// Display filter for HTML bodies
+    ///
+    /// If this is set, the HTML body is piped into this command and the output
+    /// is displayed instead of the plain text body.
+    html_display_filter: Option<String>,
+    /// How the HTML bodies are sanitized
+    ///
+    /// If none were provided, the HTML bodies are sanitized and their remote
+    /// content is never loaded.
+    html_policy: Policy,
+    /// Format of the rows of the email explorer
+    ///
+    /// If none were provided, the subject and date are displayed on two
+    /// lines.
+    index_format: Option<IndexFormat>,
+    /// Commands training the spam filter when emails are moved to or out of
+    /// the junk folder
+    ///
+    /// The spam filter isn't trained if none were provided.
+    junk_training: Training,
+    /// Indicates whether the URLs of the displayed bodies are replaced with
+    /// numbered references, listed at the bottom
+    link_references: bool,
+    /// Indicates whether the selection and the unread emails are shown with
+    /// `>` and `*` markers in the list
+    ///
+    /// This conveys them without relying on the colours.
+    list_markers: bool,
+    /// Path of the mailcap file
+    ///
+    /// This is set to `~/.mailcap` if none were provided.
+    mailcap_path: Option<PathBuf>,
+    /// Indicates whether the hovered email is previewed next to the list,
+    /// without having to open it
+    preview_pane: bool,
+    /// Command the printable emails are piped into.
+    ///
+    /// This is set to `lpr` if none were provided.
+    print_command: String,
+    /// Indicates whether the rows of the list are numbered relatively to the
+    /// selected one, to help with count-prefixed motions like `5j`.
+    relative_numbers: bool,
+    /// Canned responses, sent with the digit keys
+    ///
+    /// If none were provided, `1` and `2` acknowledge the email and promise
+    /// an answer.
+    response_templates: ResponseTemplates,
+    /// Indicates whether the pictures of the senders are fetched from
+    /// Gravatar, to draw them in the viewer
+    sender_pictures: bool,
+    /// Layout of the list and the opened email
+    ///
+    /// If none were provided, the panes share the screen equally, side by side
+    /// on wide screens.
+    split: Split,
+    /// Display filter for plain text bodies
+    text_display_filter: Option<String>,
+    /// Colour theme of the TUI
+    theme: Theme,
+    /// Delay before sending the emails written in the TUI, to cancel them
+    ///
+    /// This is set to 10 seconds if none were provided.
+    undo_send_delay: Duration,
+    /// Path of the template of the automatic replies
+    ///
+    /// The received emails aren't answered if none were provided.
+    vacation_template: Option<PathBuf>,
+    /// Minimum duration between two automatic replies to the same sender
+    ///
+    /// This is set to 7 days if none were provided.
+    vacation_window: Duration,
+    /// Column the body of the written emails is wrapped at
+    ///
+    /// This is set to 72 if none were provided, and 0 disables the wrapping.
+    wrap_column: usize,
+}
+
+impl Config {
+    /// Key id for the attachment opener variable in the `.env` file.
+    const ATTACHMENT_OPENER: &'static str = "ATTACHMENT_OPENER";
+    /// Key id for the badges variable in the `.env` file.
+    const BADGES: &'static str = "BADGES";
+    /// Default attachment opener.
+    const ATTACHMENT_OPENER_DEFAULT: &'static str = "xdg-open %s";
+    /// Key id for the blocked senders variable in the `.env` file.
+    const BLOCKED_SENDERS: &'static str = "BLOCKED_SENDERS";
+    /// Key id for the cache directory variable in the `.env` file.
+    const CACHE_DIR: &'static str = "CACHE_DIR";
+    /// Key id for the cache maximum age variable in the `.env` file.
+    const CACHE_MAX_AGE: &'static str = "CACHE_MAX_AGE";
+    /// Key id for the cache maximum size variable in the `.env` file.
+    const CACHE_MAX_SIZE: &'static str = "CACHE_MAX_SIZE";
+    /// Key id for the cache recent bodies variable in the `.env` file.
+    const CACHE_RECENT_BODIES: &'static str = "CACHE_RECENT_BODIES";
+    /// Key id for the calendar command variable in the `.env` file.
+    const CALENDAR_COMMAND: &'static str = "CALENDAR_COMMAND";
+    /// Key id for the clipboard command variable in the `.env` file.
+    const CLIPBOARD_COMMAND: &'static str = "CLIPBOARD_COMMAND";
+    /// Key id for the HTML display filter variable in the `.env` file.
+    const DISPLAY_FILTER_TEXT_HTML: &'static str = "DISPLAY_FILTER_TEXT_HTML";
+    /// Key id for the plain text display filter variable in the `.env` file.
+    const DISPLAY_FILTER_TEXT_PLAIN: &'static str = "DISPLAY_FILTER_TEXT_PLAIN";
+    /// Key id for the delivery status notifications variable in the `.env`
+    /// file.
+    const DSN_NOTIFY: &'static str = "DSN_NOTIFY";
+    /// Key id for the folder shortcuts variable in the `.env` file.
+    const FOLDER_SHORTCUTS: &'static str = "FOLDER_SHORTCUTS";
+    /// Key id for the format flowed variable in the `.env` file.
+    const FORMAT_FLOWED: &'static str = "FORMAT_FLOWED";
+    /// Key id for the index format variable in the `.env` file.
+    const INDEX_FORMAT: &'static str = "INDEX_FORMAT";
+    /// Key id for the junk folder variable in the `.env` file.
+    const JUNK_FOLDER: &'static str = "JUNK_FOLDER";
+    /// Key id for the learn ham command variable in the `.env` file.
+    const LEARN_HAM_COMMAND: &'static str = "LEARN_HAM_COMMAND";
+    /// Key id for the learn spam command variable in the `.env` file.
+    const LEARN_SPAM_COMMAND: &'static str = "LEARN_SPAM_COMMAND";
+    /// Key id for the link references variable in the `.env` file.
+    const LINK_REFERENCES: &'static str = "LINK_REFERENCES";
+    /// Key id for the list markers variable in the `.env` file.
+    const LIST_MARKERS: &'static str = "LIST_MARKERS";
+    /// Key id for the mailcap path variable in the `.env` file.
+    const MAILCAP_PATH: &'static str = "MAILCAP_PATH";
+    /// Variable that disables the colours when set to a non-empty value.
+    const NO_COLOR: &'static str = "NO_COLOR";
+    /// Key id for the preview pane variable in the `.env` file.
+    const PREVIEW_PANE: &'static str = "PREVIEW_PANE";
+    /// Key id for the print command variable in the `.env` file.
+    const PRINT_COMMAND: &'static str = "PRINT_COMMAND";
+    /// Default print command.
+    const PRINT_COMMAND_DEFAULT: &'static str = "lpr";
+    /// Key id for the relative numbers variable in the `.env` file.
+    const RELATIVE_NUMBERS: &'static str = "RELATIVE_NUMBERS";
+    /// Key id for the remote content senders variable in the `.env` file.
+    const REMOTE_CONTENT_SENDERS: &'static str = "REMOTE_CONTENT_SENDERS";
+    /// Key id for the response templates variable in the `.env` file.
+    const RESPONSE_TEMPLATES: &'static str = "RESPONSE_TEMPLATES";
+    /// Key id for the sender pictures variable in the `.env` file.
+    const SENDER_PICTURES: &'static str = "SENDER_PICTURES";
+    /// Key id for the split orientation variable in the `.env` file.
+    const SPLIT_ORIENTATION: &'static str = "SPLIT_ORIENTATION";
+    /// Key id for the split ratio variable in the `.env` file.
+    const SPLIT_RATIO: &'static str = "SPLIT_RATIO";
+    /// Key id for the excluded folders variable in the `.env` file.
+    const SYNC_EXCLUDE: &'static str = "SYNC_EXCLUDE";
+    /// Key id for the included folders variable in the `.env` file.
+    const SYNC_INCLUDE: &'static str = "SYNC_INCLUDE";
+    /// Key id for the theme variable in the `.env` file.
+    const THEME: &'static str = "THEME";
+    /// Key id for the trusted HTML variable in the `.env` file.
+    const TRUSTED_HTML: &'static str = "TRUSTED_HTML";
+    /// Key id for the undo send delay variable in the `.env` file.
+    const UNDO_SEND_DELAY: &'static str = "UNDO_SEND_DELAY";
+    /// Default undo send delay, in seconds.
+    const UNDO_SEND_DELAY_DEFAULT: u64 = 10;
+    /// Key id for the vacation template variable in the `.env` file.
+    const VACATION_TEMPLATE: &'static str = "VACATION_TEMPLATE";
+    /// Key id for the vacation window variable in the `.env` file.
+    const VACATION_WINDOW: &'static str = "VACATION_WINDOW";
+    /// Default vacation window, in days.
+    const VACATION_WINDOW_DEFAULT: u64 = 7;
+    /// Key id for the wrap column variable in the `.env` file.
+    const WRAP_COLUMN: &'static str = "WRAP_COLUMN";
+    /// Default wrap column.
+    const WRAP_COLUMN_DEFAULT: usize = 72;
+
+    /// Returns the command to open attachments that are not in the mailcap
+    pub fn as_attachment_opener(&self) -> &str {
+        &self.attachment_opener
+    }
+
+    /// Returns the badges of the identities
+    pub const fn as_badges(&self) -> &Badges {
+        &self.badges
+    }
+
+    /// Returns what happens to the emails of the blocked senders
+    pub const fn as_blocked_action(&self) -> BlockedAction {
+        self.blocked_action
+    }
+
+    /// Returns the directory of the local cache, if any
+    pub fn as_cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
+
+    /// Returns the retention policy of the cache
+    pub const fn as_cache_retention(&self) -> &Retention {
+        &self.cache_retention
+    }
+
+    /// Returns the command the calendar invites are piped into, if any
+    pub fn as_calendar_command(&self) -> Option<&str> {
+        self.calendar_command.as_deref()
+    }
+
+    /// Returns the command the one-time codes are piped into, if any
+    pub fn as_clipboard_command(&self) -> Option<&str> {
+        self.clipboard_command.as_deref()
+    }
+
+    /// Returns the delivery status notifications requested for the emails
+    /// written by the user
+    pub const fn as_dsn(&self) -> Dsn {
+        self.dsn
+    }
+
+    /// Returns the filter selecting the folders stored in the cache
+    pub const fn as_folder_filter(&self) -> &FolderFilter {
+        &self.folder_filter
+    }
+
+    /// Returns the favourite folders bound to the digit keys
+    pub const fn as_folder_shortcuts(&self) -> &FavouriteFolders {
+        &self.folder_shortcuts
+    }
+
+    /// Checks if the written emails must be sent as `format=flowed`
+    pub const fn has_format_flowed(&self) -> bool {
+        self.format_flowed
+    }
+
+    /// Returns the display filter for HTML bodies, if any
+    pub fn as_html_display_filter(&self) -> Option<&str> {
+        self.html_display_filter.as_deref()
+    }
+
+    /// Returns how the HTML bodies are sanitized
+    pub const fn as_html_policy(&self) -> &Policy {
+        &self.html_policy
+    }
+
+    /// Returns the format of the rows of the email explorer, if any
+    pub const fn as_index_format(&self) -> Option<&IndexFormat> {
+        self.index_format.as_ref()
+    }
+
+    /// Returns the commands training the spam filter
+    pub const fn as_junk_training(&self) -> &Training {
+        &self.junk_training
+    }
+
+    /// Checks if the URLs of the displayed bodies must be replaced with
+    /// numbered references
+    pub const fn has_link_references(&self) -> bool {
+        self.link_references
+    }
+
+    /// Checks if the selection and the unread emails must be shown with
+    /// markers in the list
+    pub const fn has_list_markers(&self) -> bool {
+        self.list_markers
+    }
+
+    /// Returns the path of the mailcap file, if any
+    pub fn as_mailcap_path(&self) -> Option<&Path> {
+        self.mailcap_path.as_deref()
+    }
+
+    /// Checks if the hovered email is previewed next to the list
+    pub const fn has_preview_pane(&self) -> bool {
+        self.preview_pane
+    }
+
+    /// Returns the print command
+    pub fn as_print_command(&self) -> &str {
+        &self.print_command
+    }
+
+    /// Checks if the rows of the list must be numbered relatively to the
+    /// selected one
+    pub const fn has_relative_numbers(&self) -> bool {
+        self.relative_numbers
+    }
+
+    /// Returns the canned responses bound to the digit keys
+    pub const fn as_response_templates(&self) -> &ResponseTemplates {
+        &self.response_templates
+    }
+
+    /// Checks if the pictures of the senders must be fetched from Gravatar
+    pub const fn has_sender_pictures(&self) -> bool {
+        self.sender_pictures
+    }
+
+    /// Returns the layout of the list and the opened email
+    pub const fn as_split(&self) -> Split {
+        self.split
+    }
+
+    /// Returns the display filter for plain text bodies, if any
+    pub fn as_text_display_filter(&self) -> Option<&str> {
+        self.text_display_filter.as_deref()
+    }
+
+    /// Returns the colour theme of the TUI
+    pub const fn as_theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Returns the delay before sending the emails written in the TUI
+    pub const fn as_undo_send_delay(&self) -> Duration {
+        self.undo_send_delay
+    }
+
+    /// Returns the path of the template of the automatic replies, if any
+    pub fn as_vacation_template(&self) -> Option<&Path> {
+        self.vacation_template.as_deref()
+    }
+
+    /// Returns the minimum duration between two automatic replies to the same
+    /// sender
+    pub const fn as_vacation_window(&self) -> Duration {
+        self.vacation_window
+    }
+
+    /// Returns the column the body of the written emails is wrapped at, 0 if
+    /// it mustn't be wrapped
+    pub const fn as_wrap_column(&self) -> usize {
+        self.wrap_column
+    }
+
+    /// Loads the configuration from the environment.
+    pub fn load() -> Self {
+        let attachment_opener = Self::load_var_or(
+            Self::ATTACHMENT_OPENER,
+            Self::ATTACHMENT_OPENER_DEFAULT,
+        );
+        let blocked_action = Self::load_var(Self::BLOCKED_SENDERS)
+            .and_then(|name| BlockedAction::parse(&name))
+            .unwrap_or_default();
+        let cache_dir = Self::load_var(Self::CACHE_DIR)
+            .map(PathBuf::from)
+            .or_else(Self::default_cache_dir);
+        let cache_retention = Self::load_retention();
+        let folder_filter = FolderFilter::new(
+            &Self::load_var(Self::SYNC_INCLUDE).unwrap_or_default(),
+            &Self::load_var(Self::SYNC_EXCLUDE).unwrap_or_default(),
+        );
+        let dsn =
+            Dsn::parse(&Self::load_var(Self::DSN_NOTIFY).unwrap_or_default());
+        let folder_shortcuts = FavouriteFolders::parse(
+            &Self::load_var(Self::FOLDER_SHORTCUTS).unwrap_or_default(),
+        );
+        let format_flowed = Self::load_flag(Self::FORMAT_FLOWED);
+        let html_display_filter =
+            Self::load_var(Self::DISPLAY_FILTER_TEXT_HTML);
+        let html_policy = Policy::new(
+            Self::load_flag(Self::TRUSTED_HTML),
+            &Self::load_var(Self::REMOTE_CONTENT_SENDERS).unwrap_or_default(),
+        );
+        let index_format = Self::load_var(Self::INDEX_FORMAT)
+            .map(|format| IndexFormat::parse(&format));
+        let junk_training = Training::new(
+            Self::load_var(Self::JUNK_FOLDER),
+            Self::load_var(Self::LEARN_SPAM_COMMAND),
+            Self::load_var(Self::LEARN_HAM_COMMAND),
+        );
+        let link_references = Self::load_flag(Self::LINK_REFERENCES);
+        let list_markers = Self::load_flag(Self::LIST_MARKERS);
+        let mailcap_path = Self::load_var(Self::MAILCAP_PATH)
+            .map(PathBuf::from)
+            .or_else(Self::default_mailcap_path);
+        let preview_pane = Self::load_flag(Self::PREVIEW_PANE);
+        let print_command =
+            Self::load_var_or(Self::PRINT_COMMAND, Self::PRINT_COMMAND_DEFAULT);
+        let relative_numbers = Self::load_flag(Self::RELATIVE_NUMBERS);
+        let response_templates = Self::load_var(Self::RESPONSE_TEMPLATES)
+            .map(|templates| ResponseTemplates::parse(&templates))
+            .unwrap_or_default();
+        let sender_pictures = Self::load_flag(Self::SENDER_PICTURES);
+        let split = Self::load_split();
+        let text_display_filter =
+            Self::load_var(Self::DISPLAY_FILTER_TEXT_PLAIN);
+        let theme = Self::load_theme();
+        let undo_send_delay = Duration::from_secs(
+            Self::load_number(Self::UNDO_SEND_DELAY)
+                .unwrap_or(Self::UNDO_SEND_DELAY_DEFAULT),
+        );
+        let vacation_template =
+            Self::load_var(Self::VACATION_TEMPLATE).map(PathBuf::from);
+        let vacation_window = Self::days_to_duration(
+            Self::load_number(Self::VACATION_WINDOW)
+                .unwrap_or(Self::VACATION_WINDOW_DEFAULT),
+        );
+        let wrap_column = Self::load_number(Self::WRAP_COLUMN)
+            .unwrap_or(Self::WRAP_COLUMN_DEFAULT);
+
+        Self {
+            attachment_opener,
+            badges: Badges::parse(
+                &Self::load_var(Self::BADGES).unwrap_or_default(),
+            ),
+            blocked_action,
+            cache_dir,
+            cache_retention,
+            calendar_command: Self::load_var(Self::CALENDAR_COMMAND),
+            clipboard_command: Self::load_var(Self::CLIPBOARD_COMMAND),
+            dsn,
+            folder_filter,
+            folder_shortcuts,
+            format_flowed,
+            html_display_filter,
+            html_policy,
+            index_format,
+            junk_training,
+            link_references,
+            list_markers,
+            mailcap_path,
+            preview_pane,
+            print_command,
+            relative_numbers,
+            response_templates,
+            sender_pictures,
+            split,
+            text_display_filter,
+            theme,
+            undo_send_delay,
+            vacation_template,
+            vacation_window,
+            wrap_column,
+        }
+    }
+
+    /// Converts a number of days to a duration
+    const fn days_to_duration(days: u64) -> Duration {
+        Duration::from_hours(days.saturating_mul(24))
+    }
+
+    /// Returns the default cache directory, following the XDG base directory
+    /// specification.
+    fn default_cache_dir() -> Option<PathBuf> {
+        var("XDG_CACHE_HOME")
+            .ok()
+            .map(PathBuf::from)
+            .or_else(|| {
+                var("HOME").ok().map(|home| Path::new(&home).join(".cache"))
+            })
+            .map(|cache| cache.join("mailbox"))
+    }
+
+    /// Returns the default path of the mailcap file, in the home directory.
+    fn default_mailcap_path() -> Option<PathBuf> {
+        var("HOME")
+            .ok()
+            .map(|home| Path::new(&home).join(".mailcap"))
+    }
+
+    /// Loads a boolean variable from the environment
+    ///
+    /// The variable is considered set if its value is `1`, `true` or `yes`.
+    fn load_flag(var_key: &'static str) -> bool {
+        Self::load_var(var_key).is_some_and(|value| {
+            matches!(value.to_lowercase().as_str(), "1" | "true" | "yes")
+        })
+    }
+
+    /// Loads the retention policy of the cache from the environment
+    fn load_retention() -> Retention {
+        Retention::new(
+            Self::load_number(Self::CACHE_MAX_AGE),
+            Self::load_number(Self::CACHE_MAX_SIZE),
+            Self::load_number(Self::CACHE_RECENT_BODIES),
+        )
+    }
+
+    /// Loads the layout of the list and the opened email from the
+    /// environment
+    fn load_split() -> Split {
+        Split::new(
+            Self::load_var(Self::SPLIT_ORIENTATION)
+                .and_then(|name| Orientation::parse(&name))
+                .unwrap_or_default(),
+            Self::load_number(Self::SPLIT_RATIO),
+        )
+    }
+
+    /// Loads the colour theme from the environment
+    ///
+    /// The colours are disabled if the `NO_COLOR` variable is set.
+    fn load_theme() -> Theme {
+        if Self::load_var(Self::NO_COLOR)
+            .is_some_and(|no_color| !no_color.is_empty())
+        {
+            Theme::NoColor
+        } else {
+            Self::load_var(Self::THEME)
+                .and_then(|name| Theme::parse(&name))
+                .unwrap_or_default()
+        }
+    }
+
+    /// Loads an optional number from the environment
+    ///
+    /// Invalid numbers are ignored.
+    fn load_number<T: FromStr>(var_key: &'static str) -> Option<T> {
+        Self::load_var(var_key)?.trim().parse().ok()
+    }
+
+    /// Loads one optional variable from the environment.
+    fn load_var(var_key: &'static str) -> Option<String> {
+        var(var_key).ok()
+    }
+
+    /// Loads one variable from the environment, or returns the default value
+    /// if it is not defined.
+    fn load_var_or(var_key: &'static str, default: &'static str) -> String {
+        var(var_key).unwrap_or_else(|_| default.to_owned())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            attachment_opener: Self::ATTACHMENT_OPENER_DEFAULT.to_owned(),
+            badges: Badges::default(),
+            blocked_action: BlockedAction::default(),
+            cache_dir: Self::default_cache_dir(),
+            cache_retention: Retention::default(),
+            calendar_command: None,
+            clipboard_command: None,
+            dsn: Dsn::default(),
+            folder_filter: FolderFilter::default(),
+            folder_shortcuts: FavouriteFolders::default(),
+            format_flowed: false,
+            html_display_filter: None,
+            html_policy: Policy::default(),
+            index_format: None,
+            junk_training: Training::default(),
+            link_references: false,
+            list_markers: false,
+            mailcap_path: Self::default_mailcap_path(),
+            preview_pane: false,
+            print_command: Self::PRINT_COMMAND_DEFAULT.to_owned(),
+            relative_numbers: false,
+            response_templates: ResponseTemplates::default(),
+            sender_pictures: false,
+            split: Split::default(),
+            text_display_filter: None,
+            theme: Theme::default(),
+            undo_send_delay: Duration::from_secs(Self::UNDO_SEND_DELAY_DEFAULT),
+            vacation_template: None,
+            vacation_window: Self::days_to_duration(
+                Self::VACATION_WINDOW_DEFAULT,
+            ),
+            wrap_column: Self::WRAP_COLUMN_DEFAULT,
+        }
+    }
+}
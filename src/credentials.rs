@@ -7,23 +7,68 @@
 //! EMAIL=bob@example.com
 //! PASSWORD=P@ssw0rd
 //! ```
+//!
+//! The SMTP server defaults to the `DOMAIN` on port 465 (implicit TLS), and
+//! can be changed with the `SMTP_DOMAIN` and `SMTP_PORT` variables.
+//!
+//! The display name of the account can be set with the `NAME` variable, and
+//! other identities can be listed in the `IDENTITIES` variable, see
+//! [`identity`](crate::identity). The bounces of the emails sent from them are
+//! sent to the `RETURN_PATH` variable if it is set, see
+//! [`envelope`](crate::send::envelope).
+//!
+//! The credentials can also be created without the `.env` file, e.g. in tests,
+//! with [`Credentials::new`] and the `with_*` methods.
+//!
+//! Other accounts are configured with the same variables, prefixed with the
+//! name of the account in uppercase, and selected with `--account` on the
+//! command line:
+//!
+//! ```env
+//! WORK_DOMAIN=work.example.com
+//! WORK_EMAIL=bob@work.example.com
+//! WORK_PASSWORD=P@ssw0rd
+//! ```
+//!
+//! Some providers temporarily ban the clients that send too many commands. The
+//! `IMAP_RATE_LIMIT` variable sets the maximum number of IMAP commands sent per
+//! second, and is unlimited by default. Likewise, `SMTP_RATE_LIMIT` sets the
+//! maximum number of emails sent per minute, and `SMTP_MAX_RECIPIENTS` the
+//! maximum number of recipients of a message, see
+//! [`quota`](crate::send::quota).
+//!
+//! The cache of an account is encrypted with the secret printed by the
+//! `CACHE_KEY_COMMAND` variable, if it is set, see
+//! [`encryption`](crate::cache::encryption):
+//!
+//! ```env
+//! CACHE_KEY_COMMAND=secret-tool lookup mailbox cache
+//! WORK_CACHE_KEY_COMMAND=pass show mailbox/work-cache
+//! ```
 
 use core::num::ParseIntError;
+use core::str::FromStr;
 use std::env::{VarError, var};
 
 use dotenv::dotenv;
 
 use crate::errors::Result;
+use crate::identity::{Identity, parse_identities};
 
 /// Credentials to interact with the email.
 ///
 /// These credentials should be stored in the `.env` file.
 
 pub struct Credentials {
+    /// Command printing the secret the cache is encrypted with, if the cache
+    /// is encrypted
+    cache_key_command: Option<String>,
     /// Email domain
     domain_name: String,
     /// Email
     email: String,
+    /// Identities emails can be sent from, the one of the email first
+    identities: Vec<Identity>,
     /// Imap encryption protocol
     ///
     /// # Examples
@@ -34,23 +79,78 @@ pub struct Credentials {
     ///
     /// This is set to 993 if none were provided.
     imap_port: u16,
+    /// Maximum number of IMAP commands per second
+    ///
+    /// This is set to 0, meaning unlimited, if none were provided.
+    imap_rate_limit: u32,
     /// Email password
     password: String,
+    /// Envelope sender of the emails sent from the identities, if it isn't
+    /// their address
+    return_path: Option<String>,
+    /// Smtp domain
+    ///
+    /// This is set to the [`domain_name`](Self::domain_name) if none were
+    /// provided.
+    smtp_domain_name: String,
+    /// Maximum number of recipients of a message
+    ///
+    /// This is set to the limit of the provider if none were provided.
+    smtp_max_recipients: Option<usize>,
+    /// Smtp port
+    ///
+    /// This is set to 465 if none were provided.
+    smtp_port: u16,
+    /// Maximum number of emails sent per minute
+    ///
+    /// This is set to the limit of the provider if none were provided.
+    smtp_rate_limit: Option<u32>,
 }
 
 impl Credentials {
+    /// Key id for the cache key command variable in the `.env` file.
+    const CACHE_KEY_COMMAND: &'static str = "CACHE_KEY_COMMAND";
     /// Key id for the domain variable in the `.env` file.
     const DOMAIN: &'static str = "DOMAIN";
     /// Key id for the email variable in the `.env` file.
     const EMAIL: &'static str = "EMAIL";
+    /// Key id for the identities variable in the `.env` file.
+    const IDENTITIES: &'static str = "IDENTITIES";
     /// Key id for the imap encryption variable in the `.env` file.
     const IMAP_ENCRYPTION_PROTOCOL: &'static str = "IMAP_ENCRYPTION_PROTOCOL";
+    /// Default imap encryption protocol.
+    const IMAP_ENCRYPTION_PROTOCOL_DEFAULT: &'static str = "TLS";
     /// Key id for the imap port variable in the `.env` file.
     const IMAP_PORT: &'static str = "IMAP_PORT";
     /// Default imap port.
     const IMAP_PORT_DEFAULT: u16 = 993;
+    /// Key id for the imap rate limit variable in the `.env` file.
+    const IMAP_RATE_LIMIT: &'static str = "IMAP_RATE_LIMIT";
+    /// Key id for the display name variable in the `.env` file.
+    const NAME: &'static str = "NAME";
     /// Key id for the password variable in the `.env` file.
     const PASSWORD: &'static str = "PASSWORD";
+    /// Key id for the return path variable in the `.env` file.
+    const RETURN_PATH: &'static str = "RETURN_PATH";
+    /// Key id for the smtp domain variable in the `.env` file.
+    const SMTP_DOMAIN: &'static str = "SMTP_DOMAIN";
+    /// Key id for the smtp maximum recipients variable in the `.env` file.
+    const SMTP_MAX_RECIPIENTS: &'static str = "SMTP_MAX_RECIPIENTS";
+    /// Key id for the smtp port variable in the `.env` file.
+    const SMTP_PORT: &'static str = "SMTP_PORT";
+    /// Default smtp port.
+    const SMTP_PORT_DEFAULT: u16 = 465;
+    /// Key id for the smtp rate limit variable in the `.env` file.
+    const SMTP_RATE_LIMIT: &'static str = "SMTP_RATE_LIMIT";
+
+    /// Returns the command printing the secret the cache of the account is
+    /// encrypted with, if any
+    ///
+    /// The command can read the secret from the keyring, e.g. `secret-tool
+    /// lookup mailbox cache` or `pass show mailbox/cache`.
+    pub fn as_cache_key_command(&self) -> Option<&str> {
+        self.cache_key_command.as_deref()
+    }
 
     /// Returns the domain
     pub fn as_domain_name(&self) -> &str {
@@ -62,6 +162,19 @@ impl Credentials {
         &self.email
     }
 
+    /// Returns the identities emails can be sent from, the one of the email
+    /// first
+    pub fn as_identities(&self) -> &[Identity] {
+        &self.identities
+    }
+
+    /// Returns the identity with the given address, ignoring the case
+    pub fn find_identity(&self, address: &str) -> Option<&Identity> {
+        self.identities.iter().find(|identity| {
+            identity.as_address().eq_ignore_ascii_case(address)
+        })
+    }
+
     /// Returns the socket address
     ///
     /// A socket address is the combination of a hostname and a port.
@@ -69,28 +182,100 @@ impl Credentials {
         (&self.domain_name, self.imap_port)
     }
 
+    /// Returns the maximum number of IMAP commands per second
+    ///
+    /// A limit of 0 means that the commands aren't limited.
+    pub const fn as_imap_rate_limit(&self) -> u32 {
+        self.imap_rate_limit
+    }
+
     /// Returns the password
     pub fn as_password(&self) -> &str {
         &self.password
     }
 
+    /// Returns the return path of the emails sent from `from`, if it is an
+    /// identity of the account and a return path is set
+    pub fn find_return_path(&self, from: &str) -> Option<&str> {
+        self.find_identity(from).and(self.return_path.as_deref())
+    }
+
+    /// Returns the maximum number of recipients of a message, if one was
+    /// provided
+    pub const fn as_smtp_max_recipients(&self) -> Option<usize> {
+        self.smtp_max_recipients
+    }
+
+    /// Returns the maximum number of emails sent per minute, if one was
+    /// provided
+    pub const fn as_smtp_rate_limit(&self) -> Option<u32> {
+        self.smtp_rate_limit
+    }
+
+    /// Returns the SMTP socket address
+    ///
+    /// A socket address is the combination of a hostname and a port.
+    pub fn as_smtp_socket_address(&self) -> (&str, u16) {
+        (&self.smtp_domain_name, self.smtp_port)
+    }
+
     /// Loads the credentials from the `.env` file.
     pub fn load() -> Result<Self, Error> {
+        Self::load_account(None)
+    }
+
+    /// Loads the credentials of an account from the `.env` file
+    ///
+    /// The variables of a named account are prefixed with its name in
+    /// uppercase, e.g. `WORK_EMAIL` for the `work` account. Without a name, the
+    /// variables of the default account are loaded.
+    pub fn load_account(account: Option<&str>) -> Result<Self, Error> {
         dotenv().map_err(Error::InvalidFile)?;
 
-        let domain_name = Self::load_var(Self::DOMAIN)?;
-        let email = Self::load_var(Self::EMAIL)?;
-        let imap_port = Self::load_imap_port()?;
+        let owned_prefix = account
+            .map(|name| format!("{}_", name.to_uppercase()))
+            .unwrap_or_default();
+        let prefix = owned_prefix.as_str();
+
+        let cache_key_command =
+            Self::load_var(prefix, Self::CACHE_KEY_COMMAND).ok();
+        let domain_name = Self::load_var(prefix, Self::DOMAIN)?;
+        let email = Self::load_var(prefix, Self::EMAIL)?;
+        let mut identities = vec![Identity::new(
+            email.clone(),
+            Self::load_var(prefix, Self::NAME).ok(),
+        )];
+        if let Ok(aliases) = Self::load_var(prefix, Self::IDENTITIES) {
+            identities.extend(parse_identities(&aliases));
+        }
+        let imap_port = Self::load_imap_port(prefix)?;
         let imap_encryption_protocol =
-            Self::load_var(Self::IMAP_ENCRYPTION_PROTOCOL)?;
-        let password = Self::load_var(Self::PASSWORD)?;
+            Self::load_var(prefix, Self::IMAP_ENCRYPTION_PROTOCOL)?;
+        let imap_rate_limit = Self::load_imap_rate_limit(prefix)?;
+        let password = Self::load_var(prefix, Self::PASSWORD)?;
+        let return_path = Self::load_var(prefix, Self::RETURN_PATH).ok();
+        let smtp_domain_name = Self::load_var(prefix, Self::SMTP_DOMAIN)
+            .unwrap_or_else(|_| domain_name.clone());
+        let smtp_max_recipients =
+            Self::load_smtp_limit(prefix, Self::SMTP_MAX_RECIPIENTS)?;
+        let smtp_port = Self::load_smtp_port(prefix)?;
+        let smtp_rate_limit =
+            Self::load_smtp_limit(prefix, Self::SMTP_RATE_LIMIT)?;
 
         Ok(Self {
+            cache_key_command,
             domain_name,
             email,
+            identities,
             imap_encryption_protocol,
             imap_port,
+            imap_rate_limit,
             password,
+            return_path,
+            smtp_domain_name,
+            smtp_max_recipients,
+            smtp_port,
+            smtp_rate_limit,
         })
     }
 
@@ -98,16 +283,128 @@ impl Credentials {
     ///
     /// Port defaults to [`IMAP_PORT_DEFAULT`](Self::IMAP_PORT_DEFAULT) if it is
     /// not specified.
-    fn load_imap_port() -> Result<u16, Error> {
-        Self::load_var(Self::IMAP_PORT).map_or_else(
+    fn load_imap_port(prefix: &str) -> Result<u16, Error> {
+        Self::load_var(prefix, Self::IMAP_PORT).map_or_else(
             |_| Ok(Self::IMAP_PORT_DEFAULT),
             |value| value.parse().map_err(Error::InvalidPort),
         )
     }
 
-    /// Loads one variable from the `.env` file.
-    fn load_var(var_key: &'static str) -> Result<String, Error> {
-        var(var_key).map_err(|err| Error::MissingVariable(err, var_key))
+    /// Load the imap rate limit from the `.env`
+    ///
+    /// The commands are not limited if it is not specified.
+    fn load_imap_rate_limit(prefix: &str) -> Result<u32, Error> {
+        Self::load_var(prefix, Self::IMAP_RATE_LIMIT).map_or_else(
+            |_| Ok(0),
+            |value| value.parse().map_err(Error::InvalidRateLimit),
+        )
+    }
+
+    /// Load an smtp limit from the `.env`
+    ///
+    /// The limit of the provider is used if it is not specified.
+    fn load_smtp_limit<T: FromStr<Err = ParseIntError>>(
+        prefix: &str,
+        var_key: &'static str,
+    ) -> Result<Option<T>, Error> {
+        Self::load_var(prefix, var_key)
+            .ok()
+            .map(|value| value.parse().map_err(Error::InvalidRateLimit))
+            .transpose()
+    }
+
+    /// Load the smtp port from the `.env`
+    ///
+    /// Port defaults to [`SMTP_PORT_DEFAULT`](Self::SMTP_PORT_DEFAULT) if it is
+    /// not specified.
+    fn load_smtp_port(prefix: &str) -> Result<u16, Error> {
+        Self::load_var(prefix, Self::SMTP_PORT).map_or_else(
+            |_| Ok(Self::SMTP_PORT_DEFAULT),
+            |value| value.parse().map_err(Error::InvalidPort),
+        )
+    }
+
+    /// Loads one variable from the `.env` file, prefixed with the account
+    /// name if any, e.g. `WORK_EMAIL`.
+    fn load_var(prefix: &str, var_key: &'static str) -> Result<String, Error> {
+        let name = format!("{prefix}{var_key}");
+        var(&name).map_err(|err| Error::MissingVariable(err, name))
+    }
+
+    /// Creates credentials without the `.env` file
+    ///
+    /// The other values take the defaults of the `.env` file, and can be set
+    /// with the `with_*` methods.
+    pub fn new(domain_name: String, email: String, password: String) -> Self {
+        Self {
+            cache_key_command: None,
+            identities: vec![Identity::new(email.clone(), None)],
+            email,
+            imap_encryption_protocol: Self::IMAP_ENCRYPTION_PROTOCOL_DEFAULT
+                .to_owned(),
+            imap_port: Self::IMAP_PORT_DEFAULT,
+            imap_rate_limit: 0,
+            password,
+            return_path: None,
+            smtp_domain_name: domain_name.clone(),
+            domain_name,
+            smtp_max_recipients: None,
+            smtp_port: Self::SMTP_PORT_DEFAULT,
+            smtp_rate_limit: None,
+        }
+    }
+
+    /// Adds identities emails can be sent from, after the one of the email.
+    pub fn with_identities(mut self, identities: Vec<Identity>) -> Self {
+        self.identities.extend(identities);
+        self
+    }
+
+    /// Sets the imap port.
+    pub const fn with_imap_port(mut self, port: u16) -> Self {
+        self.imap_port = port;
+        self
+    }
+
+    /// Sets the maximum number of IMAP commands per second, 0 meaning
+    /// unlimited.
+    pub const fn with_imap_rate_limit(mut self, rate_limit: u32) -> Self {
+        self.imap_rate_limit = rate_limit;
+        self
+    }
+
+    /// Sets the display name of the identity of the email.
+    pub fn with_name(mut self, name: String) -> Self {
+        if let Some(identity) = self.identities.first_mut() {
+            *identity = Identity::new(self.email.clone(), Some(name));
+        }
+        self
+    }
+
+    /// Sets the SMTP server, if it isn't the domain.
+    pub fn with_smtp_domain(mut self, domain_name: String) -> Self {
+        self.smtp_domain_name = domain_name;
+        self
+    }
+
+    /// Sets the maximum number of recipients of a message, instead of the
+    /// limit of the provider.
+    pub const fn with_smtp_max_recipients(mut self, max: usize) -> Self {
+        self.smtp_max_recipients = Some(max);
+        self
+    }
+
+    /// Sets the smtp port.
+    pub const fn with_smtp_port(mut self, port: u16) -> Self {
+        self.smtp_port = port;
+        self
+    }
+
+    /// Sets the maximum number of emails sent per minute, instead of the limit
+    /// of the provider.
+    pub const fn with_smtp_rate_limit(mut self, rate_limit: u32) -> Self {
+        self.smtp_rate_limit = Some(rate_limit);
+        self
     }
 }
 
@@ -118,6 +415,8 @@ pub enum Error {
     InvalidFile(dotenv::Error),
     /// The provided IMAP port is invalid
     InvalidPort(ParseIntError),
+    /// A provided IMAP or SMTP rate limit is invalid
+    InvalidRateLimit(ParseIntError),
     /// The wanted variable is missing in the `.env` file.
-    MissingVariable(VarError, &'static str),
+    MissingVariable(VarError, String),
 }
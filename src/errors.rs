@@ -2,21 +2,65 @@
 
 use core::result;
 
-use crate::{credentials, fetch, tui};
+use crate::{
+    archive, cache, calendar, cli, credentials, fetch, mailcap, otp, patch, pipe, send, tui
+};
 
 /// Errors that may occur while running the app.
 #[derive(Debug)]
 pub enum Error {
+    /// Failure occurred while reading an archive attachment.
+    Archive(archive::Error),
+    /// Failure occurred while reading or writing the local cache.
+    Cache(cache::Error),
+    /// Failure occurred while exporting a calendar invite.
+    Calendar(calendar::Error),
+    /// Invalid command line arguments.
+    Cli(cli::Error),
     /// `dotenv` failed to read the `.env` file.
     Credentials(credentials::Error),
     /// Failure occurred while interaction with the IMAP protocol.
     ImapConnection(fetch::connection::Error),
+    /// Failure occurred while opening an attachment.
+    Mailcap(mailcap::Error),
+    /// Failure occurred while copying a one-time code.
+    Otp(otp::Error),
     /// Failure occurred while parsing the email body.
     Parsing(fetch::parser::Error),
+    /// Failure occurred while handling a patch series.
+    Patch(patch::Error),
+    /// Failure occurred while running an external command.
+    Pipe(pipe::Error),
+    /// Failure occurred while interaction with the SMTP protocol.
+    SmtpConnection(send::connection::Error),
     /// Failure occurred after TUI
     Tui(tui::app::Error),
 }
 
+impl From<archive::Error> for Error {
+    fn from(error: archive::Error) -> Self {
+        Self::Archive(error)
+    }
+}
+
+impl From<cache::Error> for Error {
+    fn from(error: cache::Error) -> Self {
+        Self::Cache(error)
+    }
+}
+
+impl From<calendar::Error> for Error {
+    fn from(error: calendar::Error) -> Self {
+        Self::Calendar(error)
+    }
+}
+
+impl From<cli::Error> for Error {
+    fn from(error: cli::Error) -> Self {
+        Self::Cli(error)
+    }
+}
+
 impl From<credentials::Error> for Error {
     fn from(error: credentials::Error) -> Self {
         Self::Credentials(error)
@@ -29,17 +73,71 @@ impl From<fetch::connection::Error> for Error {
     }
 }
 
+impl From<mailcap::Error> for Error {
+    fn from(error: mailcap::Error) -> Self {
+        Self::Mailcap(error)
+    }
+}
+
+impl From<otp::Error> for Error {
+    fn from(error: otp::Error) -> Self {
+        Self::Otp(error)
+    }
+}
+
 impl From<fetch::parser::Error> for Error {
     fn from(error: fetch::parser::Error) -> Self {
         Self::Parsing(error)
     }
 }
 
+impl From<patch::Error> for Error {
+    fn from(error: patch::Error) -> Self {
+        Self::Patch(error)
+    }
+}
+
+impl From<pipe::Error> for Error {
+    fn from(error: pipe::Error) -> Self {
+        Self::Pipe(error)
+    }
+}
+
+impl From<send::connection::Error> for Error {
+    fn from(error: send::connection::Error) -> Self {
+        Self::SmtpConnection(error)
+    }
+}
+
 impl From<tui::app::Error> for Error {
     fn from(error: tui::app::Error) -> Self {
         Self::Tui(error)
     }
 }
 
+impl Error {
+    /// Returns the exit code of the process that failed with this error
+    ///
+    /// The codes follow `sysexits.h`: a misconfiguration exits with
+    /// `EX_CONFIG` (78), as restarting won't fix it, and a connection failure
+    /// with `EX_TEMPFAIL` (75), as it may work later. The other errors exit
+    /// with 1.
+    pub const fn to_exit_code(&self) -> u8 {
+        match self {
+            Self::Cli(_) | Self::Credentials(_) => 78,
+            Self::ImapConnection(_) | Self::SmtpConnection(_) => 75,
+            Self::Archive(_)
+            | Self::Cache(_)
+            | Self::Calendar(_)
+            | Self::Mailcap(_)
+            | Self::Otp(_)
+            | Self::Parsing(_)
+            | Self::Patch(_)
+            | Self::Pipe(_)
+            | Self::Tui(_) => 1,
+        }
+    }
+}
+
 /// Overloaded result for the [`mailbox`](crate) crate
 pub type Result<T = (), E = Error> = result::Result<T, E>;
@@ -0,0 +1,187 @@
+//! Runs long fetches in the background, so that they can be cancelled.
+//!
+//! The fetch runs in a separate thread, borrowing the IMAP connection of the
+//! [`SessionManager`] for every email, and checks a [`CancellationToken`]
+//! between them. Cancelling a fetch thus never leaves a session in the middle
+//! of a command, and the TUI can use the connection between two emails.
+//!
+//! The progress of the fetch is stored in the cache after every email, so that
+//! a fetch interrupted by a cancellation, a crash or a lost connection resumes
+//! where it left off the next time the app starts. The conversations of the
+//! fetched emails are indexed along the way, and the
+//! [index](crate::cache::threads) is stored every [`THREAD_INDEX_INTERVAL`]
+//! emails.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+
+use super::mailbox::Mailbox;
+use super::parser::{Email, Metadata};
+use super::pool::SessionManager;
+use crate::cache::Cache;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+
+/// Number of fetched emails between two stores of the thread index
+const THREAD_INDEX_INTERVAL: usize = 100;
+
+/// Shared flag to ask a background task to stop
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Asks the task to stop as soon as possible.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks if the task was asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress reported by a background fetch
+pub enum FetchEvent {
+    /// An email was fetched, with its uid, raw content and metadata
+    ///
+    /// The metadata is missing if the email was read from the cache.
+    Email(u32, String, Option<Metadata>),
+    /// The fetch failed, with the description of the error
+    Failed(String),
+    /// The fetch stopped, either because every email was fetched or because it
+    /// was cancelled
+    Finished {
+        /// Indicates whether the fetch was cancelled
+        cancelled: bool,
+    },
+}
+
+/// Fetch running in the background
+pub struct FetchTask {
+    /// Receives the progress of the fetch
+    receiver: Receiver<FetchEvent>,
+    /// Token to cancel the fetch
+    token: CancellationToken,
+    /// Number of emails to fetch
+    total: usize,
+}
+
+impl FetchTask {
+    /// Returns the number of emails to fetch
+    pub const fn as_total(&self) -> usize {
+        self.total
+    }
+
+    /// Asks the fetch to stop after the email being fetched.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Returns the progress reported since the last call, without blocking.
+    pub fn poll_events(&self) -> Vec<FetchEvent> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// Starts fetching the emails with the given uids from the mailbox
+    ///
+    /// If a cache is given, the emails already cached aren't fetched again,
+    /// and the fetched ones are stored with the progress of the fetch.
+    pub fn spawn(
+        mailbox_name: String,
+        uids: Vec<u32>,
+        cache: Option<Cache>,
+        sessions: SessionManager,
+    ) -> Self {
+        let (sender, receiver) = channel();
+        let token = CancellationToken::default();
+        let total = uids.len();
+        let thread_token = token.clone();
+
+        thread::spawn(move || {
+            let event = match fetch_emails(
+                &mailbox_name,
+                &uids,
+                cache.as_ref(),
+                &sessions,
+                &thread_token,
+                &sender,
+            ) {
+                Ok(()) => FetchEvent::Finished {
+                    cancelled: thread_token.is_cancelled(),
+                },
+                Err(err) => FetchEvent::Failed(format!("{err:?}")),
+            };
+            // The receiver is dropped if the TUI stopped, in which case there
+            // is no one left to notify.
+            sender.send(event).unwrap_or_default();
+        });
+
+        Self { receiver, token, total }
+    }
+}
+
+/// Fetches the emails one by one, until they were all fetched or the token is
+/// cancelled.
+///
+/// The connection is only used once an email isn't found in the cache, so that
+/// resuming an interrupted fetch doesn't need the server for the emails that
+/// were already fetched.
+fn fetch_emails(
+    mailbox_name: &str,
+    uids: &[u32],
+    cache: Option<&Cache>,
+    sessions: &SessionManager,
+    token: &CancellationToken,
+    sender: &Sender<FetchEvent>,
+) -> Result {
+    let credentials = Credentials::load()?;
+    let mailbox = Mailbox::try_from(mailbox_name)?;
+    let mut thread_index = cache
+        .map(|storage| storage.load_thread_index(mailbox_name))
+        .unwrap_or_default();
+
+    for (idx, uid) in uids.iter().enumerate() {
+        if token.is_cancelled() {
+            break;
+        }
+        let cached = cache
+            .map(|storage| storage.load_email(mailbox_name, *uid))
+            .transpose()?
+            .flatten();
+        let (body, metadata) = if let Some(body) = cached {
+            (body, None)
+        } else {
+            let (body, metadata) =
+                sessions.run_in(&credentials, &mailbox, |selected| {
+                    selected.get_mail_with_metadata(*uid)
+                })?;
+            if let Some(storage) = cache {
+                storage.store_email(mailbox_name, *uid, &body)?;
+            }
+            (body, Some(metadata))
+        };
+        if let Some(storage) = cache {
+            storage.store_sync_progress(mailbox_name, *uid)?;
+            if let Ok(email) = Email::try_from((*uid, body.as_bytes())) {
+                thread_index.add(&email);
+            }
+            if idx.checked_rem(THREAD_INDEX_INTERVAL) == Some(0) {
+                storage.store_thread_index(mailbox_name, &thread_index)?;
+            }
+        }
+        if sender
+            .send(FetchEvent::Email(*uid, body, metadata))
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    if let Some(storage) = cache {
+        storage.store_thread_index(mailbox_name, &thread_index)?;
+    }
+    Ok(())
+}
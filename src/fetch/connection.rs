@@ -2,19 +2,60 @@
 //!
 //! IMAP is the protocol responsible for fetching emails. This will allow
 //! [`mailbox`](crate) to read the emails.
+//!
+//! A session is usually opened with the [`Credentials`] of the `.env` file.
+//! [`ImapSessionBuilder`] opens one from values set programmatically instead:
+//!
+//! ```ignore
+//! let session = ImapSessionBuilder::new(
+//!     "imap.example.com",
+//!     Auth::OAuth2("bob@example.com".to_owned(), token),
+//! )
+//! .with_tls_mode(TlsMode::StartTls)
+//! .with_timeout(Duration::from_secs(30))
+//! .connect()?;
+//! ```
 
+use alloc::collections::{BTreeMap, BTreeSet};
 use core::marker::PhantomData;
 use core::str::{Utf8Error, from_utf8};
-use std::net;
+use core::time::Duration;
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs as _};
 
-use imap::types::Fetch;
-use native_tls::TlsConnector;
+use chrono::{DateTime, FixedOffset};
+use imap::types::{Fetch, Flag};
+use imap_proto::types::BodyStructure;
+use native_tls::{HandshakeError, TlsConnector, TlsStream};
 
+use super::mailbox::Mailbox;
+use super::parser::Metadata;
+use super::scheduler::Scheduler;
 use crate::credentials::Credentials;
 use crate::errors::Result;
 
 /// Type of query made on the IMAP server.
 const QUERY: &str = "RFC822";
+/// Type of query made on the IMAP server that doesn't mark the email as seen.
+const PEEK_QUERY: &str = "BODY.PEEK[]";
+/// Type of query made on the IMAP server to get the data it keeps on the
+/// emails, besides their content.
+const METADATA_QUERY: &str = "(FLAGS INTERNALDATE RFC822.SIZE)";
+/// Type of query made on the IMAP server to get an email with its metadata.
+const QUERY_WITH_METADATA: &str = "(RFC822 FLAGS INTERNALDATE RFC822.SIZE)";
+/// Type of query made on the IMAP server that doesn't mark the email as seen,
+/// and returns the date at which the server received it.
+const PEEK_DATED_QUERY: &str = "(BODY.PEEK[] INTERNALDATE)";
+
+/// Capability and command leaving a mailbox without expunging it (RFC 3691).
+const UNSELECT: &str = "UNSELECT";
+/// Capability of the servers expunging chosen emails with `UID EXPUNGE`
+/// (RFC 4315).
+const UIDPLUS: &str = "UIDPLUS";
+/// Default port of the IMAP servers with implicit `TLS`.
+const IMPLICIT_TLS_PORT: u16 = 993;
+/// Default port of the IMAP servers upgraded with `STARTTLS`.
+const STARTTLS_PORT: u16 = 143;
 
 /// Errors that may occur while interaction in `IMAP`.
 #[derive(Debug)]
@@ -25,6 +66,12 @@ pub enum Error {
     ImapFetch(imap::Error),
     /// Given email has an invalid body.
     InvalidBody(Utf8Error),
+    /// Failed to open the TCP connection to the IMAP server.
+    Io(io::Error),
+    /// Failed to change the flags or the mailbox of an email.
+    ImapUpdate(imap::Error),
+    /// Given mailbox name can't be sent to the server.
+    InvalidMailbox(String),
     /// Failed to read the wanted mailbox name.
     InvalidMailboxName(imap::Error),
     /// Given email has no body
@@ -33,6 +80,179 @@ pub enum Error {
     NoEmail,
     /// Failed to establish `TLS` connection.
     TlsConnection(native_tls::Error),
+    /// Failed to negotiate `TLS` with the IMAP server.
+    TlsHandshake(HandshakeError<TcpStream>),
+}
+
+/// Way the user is authenticated on the IMAP server
+#[derive(Clone)]
+pub enum Auth {
+    /// `LOGIN` command, with the user name and the password
+    Login(String, String),
+    /// `XOAUTH2` mechanism, with the user name and an OAuth 2.0 access token
+    OAuth2(String, String),
+}
+
+/// Way the connection to the IMAP server is encrypted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TlsMode {
+    /// The connection is encrypted from the start, on port 993 by default
+    #[default]
+    Implicit,
+    /// The connection starts unencrypted and is upgraded with `STARTTLS`, on
+    /// port 143 by default
+    StartTls,
+}
+
+/// Opens an [`ImapSession`] from values set programmatically, instead of the
+/// [`Credentials`] of the `.env` file
+pub struct ImapSessionBuilder {
+    /// Authentication of the user
+    auth: Auth,
+    /// Domain name of the IMAP server
+    host: String,
+    /// Port of the IMAP server, defaults to the one of the [`TlsMode`]
+    port: Option<u16>,
+    /// Scheduler spacing out the commands, unlimited by default
+    scheduler: Scheduler,
+    /// Timeout of the connection and of every read and write, if any
+    timeout: Option<Duration>,
+    /// Encryption of the connection
+    tls_mode: TlsMode,
+}
+
+impl ImapSessionBuilder {
+    /// Connects to the server and authenticates the user.
+    pub fn connect(&self) -> Result<ImapSession<NoMailbox>> {
+        let port = self.port.unwrap_or(match self.tls_mode {
+            TlsMode::Implicit => IMPLICIT_TLS_PORT,
+            TlsMode::StartTls => STARTTLS_PORT,
+        });
+        let tcp_stream = self.connect_tcp(port)?;
+        let tls_connector =
+            TlsConnector::new().map_err(Error::TlsConnection)?;
+
+        let client = match self.tls_mode {
+            TlsMode::Implicit => {
+                let tls_stream = tls_connector
+                    .connect(&self.host, tcp_stream)
+                    .map_err(Error::TlsHandshake)?;
+                let mut client = imap::Client::new(tls_stream);
+                client.read_greeting().map_err(Error::ImapConnection)?;
+                client
+            }
+            TlsMode::StartTls => {
+                let mut client = imap::Client::new(tcp_stream);
+                client.read_greeting().map_err(Error::ImapConnection)?;
+                client
+                    .secure(&self.host, &tls_connector)
+                    .map_err(Error::ImapConnection)?
+            }
+        };
+
+        let session = match &self.auth {
+            Auth::Login(user, password) => client.login(user, password),
+            Auth::OAuth2(user, token) =>
+                client.authenticate("XOAUTH2", &OAuth2 { token, user }),
+        }
+        .map_err(|(err, _)| Error::ImapConnection(err))?;
+
+        Ok(ImapSession {
+            marker: PhantomData,
+            scheduler: self.scheduler.clone(),
+            session,
+            validity: None,
+        })
+    }
+
+    /// Opens the TCP connection to the server, within the timeout if any
+    fn connect_tcp(&self, port: u16) -> Result<TcpStream, Error> {
+        let address = (self.host.as_str(), port);
+        let tcp_stream = match self.timeout {
+            Some(timeout) => {
+                let mut last_err = io::Error::from(io::ErrorKind::NotFound);
+                let mut connected = None;
+                for socket in address.to_socket_addrs().map_err(Error::Io)? {
+                    match TcpStream::connect_timeout(&socket, timeout) {
+                        Ok(stream) => {
+                            connected = Some(stream);
+                            break;
+                        }
+                        Err(err) => last_err = err,
+                    }
+                }
+                connected.ok_or(Error::Io(last_err))?
+            }
+            None => TcpStream::connect(address).map_err(Error::Io)?,
+        };
+        tcp_stream
+            .set_read_timeout(self.timeout)
+            .map_err(Error::Io)?;
+        tcp_stream
+            .set_write_timeout(self.timeout)
+            .map_err(Error::Io)?;
+        Ok(tcp_stream)
+    }
+
+    /// Creates a builder for the given server and authentication, with
+    /// implicit `TLS`, no timeout and no rate limit
+    pub fn new(host: &str, auth: Auth) -> Self {
+        Self {
+            auth,
+            host: host.to_owned(),
+            port: None,
+            scheduler: Scheduler::new(0),
+            timeout: None,
+            tls_mode: TlsMode::Implicit,
+        }
+    }
+
+    /// Sets the port of the server.
+    pub const fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Sets the maximum number of commands sent per second, 0 meaning
+    /// unlimited.
+    pub fn with_rate_limit(self, rate_limit: u32) -> Self {
+        self.with_scheduler(Scheduler::new(rate_limit))
+    }
+
+    /// Sets the scheduler of the commands, to share the rate limit with the
+    /// other sessions using it.
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Sets the timeout of the connection and of every read and write.
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the encryption of the connection.
+    pub const fn with_tls_mode(mut self, tls_mode: TlsMode) -> Self {
+        self.tls_mode = tls_mode;
+        self
+    }
+}
+
+/// Answers the challenge of the `XOAUTH2` mechanism
+struct OAuth2<'auth> {
+    /// OAuth 2.0 access token
+    token: &'auth str,
+    /// User name, usually the email address
+    user: &'auth str,
+}
+
+impl imap::Authenticator for OAuth2<'_> {
+    type Response = String;
+
+    fn process(&self, _challenge: &[u8]) -> Self::Response {
+        format!("user={}\x01auth=Bearer {}\x01\x01", self.user, self.token)
+    }
 }
 
 /// Represents the Imap session to communicate with the server.
@@ -42,66 +262,301 @@ pub struct ImapSession<T> {
     /// This is a zero-sized element that informs on whether a mailbox was
     /// specified or not
     marker: PhantomData<T>,
+    /// Scheduler spacing out the commands sent to the server
+    scheduler: Scheduler,
     /// Active session
-    session: imap::Session<native_tls::TlsStream<net::TcpStream>>,
+    session: imap::Session<TlsStream<TcpStream>>,
+    /// UIDVALIDITY of the selected mailbox, if the server sent it
+    validity: Option<u32>,
 }
 
-impl ImapSession<None> {
+impl<T> ImapSession<T> {
+    /// Lists every mailbox of the account
+    pub fn list_mailboxes(&mut self) -> Result<Vec<Mailbox>> {
+        self.list_mailboxes_matching("*")
+    }
+
+    /// Lists the mailboxes of the account matching a `LIST` pattern
+    ///
+    /// In the pattern, `*` matches any characters and `%` matches any
+    /// characters but the hierarchy delimiter, to list one level of the
+    /// hierarchy at a time, e.g. `Clients/%`.
+    pub fn list_mailboxes_matching(
+        &mut self,
+        pattern: &str,
+    ) -> Result<Vec<Mailbox>> {
+        let session = &mut self.session;
+        Ok(self
+            .scheduler
+            .run(|| session.list(Some(""), Some(pattern)))
+            .map_err(Error::ImapFetch)?
+            .iter()
+            .map(Mailbox::from)
+            .collect())
+    }
+
     /// Selects a mailbox to fetch
+    ///
+    /// A session whose mailbox is already selected can select another one.
     pub fn select_mailbox(
         mut self,
-        mailbox_name: &str,
+        mailbox: &Mailbox,
     ) -> Result<ImapSession<MailboxSelected>> {
-        self.session
-            .select(mailbox_name)
+        let session = &mut self.session;
+        let selected = self
+            .scheduler
+            .run(|| session.select(mailbox.as_name()))
             .map_err(Error::InvalidMailboxName)?;
-        Ok(ImapSession { session: self.session, marker: PhantomData })
+        Ok(ImapSession {
+            marker: PhantomData,
+            scheduler: self.scheduler,
+            session: self.session,
+            validity: selected.uid_validity,
+        })
     }
+}
 
-    /// Creates a new [`ImapSession`] with the given [`Credentials`].
-    pub fn with_credentials(credentials: &Credentials) -> Result<Self> {
-        let socket_address = credentials.as_imap_socket_address();
-        let domain_name = credentials.as_domain_name();
-        let ssl_connector =
-            TlsConnector::new().map_err(Error::TlsConnection)?;
+impl ImapSession<NoMailbox> {
+    /// Uploads a raw email to a mailbox, with the given flags, e.g. `\Seen`
+    ///
+    /// The server sets the date at which it received the email to the given
+    /// one, or to the current date.
+    pub fn append(
+        &mut self,
+        mailbox: &Mailbox,
+        raw: &[u8],
+        flags: &[String],
+        internal_date: Option<DateTime<FixedOffset>>,
+    ) -> Result {
+        let imap_flags = flags
+            .iter()
+            .map(|flag| Flag::from(flag.as_str()))
+            .collect::<Vec<_>>();
+        let session = &mut self.session;
+        self.scheduler
+            .run(|| {
+                session.append_with_flags_and_date(
+                    mailbox.as_name(),
+                    raw,
+                    &imap_flags,
+                    internal_date,
+                )
+            })
+            .map_err(Error::ImapUpdate)?;
+        Ok(())
+    }
 
-        let client = imap::connect(socket_address, domain_name, &ssl_connector)
-            .map_err(Error::ImapConnection)?;
+    /// Creates a mailbox
+    pub fn create_mailbox(&mut self, mailbox: &Mailbox) -> Result {
+        let session = &mut self.session;
+        self.scheduler
+            .run(|| session.create(mailbox.as_name()))
+            .map_err(Error::ImapUpdate)?;
+        Ok(())
+    }
 
-        let session = client
-            .login(credentials.as_email(), credentials.as_password())
-            .map_err(|(err, _)| Error::ImapConnection(err))?;
+    /// Creates a new [`ImapSession`] with the given [`Credentials`].
+    pub fn with_credentials(credentials: &Credentials) -> Result<Self> {
+        Self::with_scheduler(
+            credentials,
+            Scheduler::new(credentials.as_imap_rate_limit()),
+        )
+    }
 
-        Ok(Self { session, marker: PhantomData })
+    /// Creates a new [`ImapSession`] with the given [`Credentials`], sending
+    /// its commands with a scheduler shared with the other sessions of the
+    /// account.
+    pub fn with_scheduler(
+        credentials: &Credentials,
+        scheduler: Scheduler,
+    ) -> Result<Self> {
+        let (domain_name, port) = credentials.as_imap_socket_address();
+        ImapSessionBuilder::new(
+            domain_name,
+            Auth::Login(
+                credentials.as_email().to_owned(),
+                credentials.as_password().to_owned(),
+            ),
+        )
+        .with_port(port)
+        .with_scheduler(scheduler)
+        .connect()
     }
 }
 
 impl ImapSession<MailboxSelected> {
+    /// Returns the UIDVALIDITY of the selected mailbox, if the server sent it
+    ///
+    /// The uids of the mailbox are only valid as long as it doesn't change.
+    pub const fn as_validity(&self) -> Option<u32> {
+        self.validity
+    }
+
+    /// Adds a flag, e.g. `\Seen`, to an email.
+    pub fn add_flag(&mut self, uid: u32, flag: &str) -> Result {
+        let session = &mut self.session;
+        self.scheduler
+            .run(|| {
+                session.uid_store(uid.to_string(), format!("+FLAGS ({flag})"))
+            })
+            .map_err(Error::ImapUpdate)?;
+        Ok(())
+    }
+
+    /// Deletes an email
+    ///
+    /// The email is flagged as deleted, and only expunged with `UID EXPUNGE`
+    /// if the server supports `UIDPLUS`: a plain `EXPUNGE` would also remove
+    /// the other emails of the mailbox flagged as deleted, so they are left to
+    /// be expunged by the user otherwise.
+    pub fn delete(&mut self, uid: u32) -> Result {
+        self.add_flag(uid, "\\Deleted")?;
+        let session = &mut self.session;
+        let has_uidplus = self
+            .scheduler
+            .run(|| session.capabilities())
+            .map_err(Error::ImapFetch)?
+            .has_str(UIDPLUS);
+        if has_uidplus {
+            self.scheduler
+                .run(|| session.uid_expunge(uid.to_string()))
+                .map_err(Error::ImapUpdate)?;
+        }
+        Ok(())
+    }
+
     /// Get all the emails of the chosen mailbox.
     pub fn get_all_mails(&mut self) -> Result<Vec<String>> {
-        self.session
-            .fetch("1:*", QUERY)
+        let session = &mut self.session;
+        self.scheduler
+            .run(|| session.fetch("1:*", QUERY))
             .map_err(Error::ImapFetch)?
             .into_iter()
             .map(get_email_body)
             .collect()
     }
 
+    /// Returns the number of attachments of every email of the mailbox,
+    /// indexed by uid
+    ///
+    /// Only the structure of the emails is fetched, not their content.
+    pub fn get_attachment_counts(&mut self) -> Result<BTreeMap<u32, usize>> {
+        let session = &mut self.session;
+        Ok(self
+            .scheduler
+            .run(|| session.uid_fetch("1:*", "BODYSTRUCTURE"))
+            .map_err(Error::ImapFetch)?
+            .iter()
+            .filter_map(|fetch| {
+                Some((fetch.uid?, count_attachments(fetch.bodystructure()?)))
+            })
+            .collect())
+    }
+
+    /// Returns the flags of every email of the mailbox, indexed by uid
+    ///
+    /// The `\\Recent` flag is ignored, as it is specific to the session.
+    pub fn get_flags(&mut self) -> Result<BTreeMap<u32, BTreeSet<String>>> {
+        let session = &mut self.session;
+        Ok(self
+            .scheduler
+            .run(|| session.uid_fetch("1:*", "FLAGS"))
+            .map_err(Error::ImapFetch)?
+            .iter()
+            .filter_map(|fetch| Some((fetch.uid?, to_flag_set(fetch))))
+            .collect())
+    }
+
+    /// Returns an email from its unique id, with its metadata.
+    pub fn get_mail_with_metadata(
+        &mut self,
+        uid: u32,
+    ) -> Result<(String, Metadata)> {
+        let session = &mut self.session;
+        let response = self
+            .scheduler
+            .run(|| session.uid_fetch(uid.to_string(), QUERY_WITH_METADATA))
+            .map_err(Error::ImapFetch)?;
+        let mail = response.first().ok_or(Error::NoEmail)?;
+        Ok((get_email_body(mail)?, to_metadata(mail)))
+    }
+
+    /// Returns the flags, date of reception and size of every email of the
+    /// mailbox, indexed by uid
+    pub fn get_metadata(&mut self) -> Result<BTreeMap<u32, Metadata>> {
+        let session = &mut self.session;
+        Ok(self
+            .scheduler
+            .run(|| session.uid_fetch("1:*", METADATA_QUERY))
+            .map_err(Error::ImapFetch)?
+            .iter()
+            .filter_map(|fetch| Some((fetch.uid?, to_metadata(fetch))))
+            .collect())
+    }
+
     /// Returns an email from its unique id.
     pub fn get_mail_from_uid(&mut self, uid: u32) -> Result<String> {
+        let session = &mut self.session;
+        let response = self
+            .scheduler
+            .run(|| session.uid_fetch(uid.to_string(), QUERY))
+            .map_err(Error::ImapFetch)?;
+        let mail = response.first().ok_or(Error::NoEmail)?;
+        get_email_body(mail)
+    }
+
+    /// Returns an email from its unique id, without marking it as seen.
+    pub fn peek_mail_from_uid(&mut self, uid: u32) -> Result<String> {
+        let session = &mut self.session;
         let response = self
-            .session
-            .uid_fetch(uid.to_string(), QUERY)
+            .scheduler
+            .run(|| session.uid_fetch(uid.to_string(), PEEK_QUERY))
             .map_err(Error::ImapFetch)?;
         let mail = response.first().ok_or(Error::NoEmail)?;
         get_email_body(mail)
     }
 
+    /// Returns an email from its unique id with the date at which the server
+    /// received it, without marking it as seen.
+    pub fn peek_dated_mail_from_uid(
+        &mut self,
+        uid: u32,
+    ) -> Result<(String, Option<DateTime<FixedOffset>>)> {
+        let session = &mut self.session;
+        let response = self
+            .scheduler
+            .run(|| session.uid_fetch(uid.to_string(), PEEK_DATED_QUERY))
+            .map_err(Error::ImapFetch)?;
+        let mail = response.first().ok_or(Error::NoEmail)?;
+        Ok((get_email_body(mail)?, mail.internal_date()))
+    }
+
+    /// Moves an email to another mailbox.
+    pub fn move_to(&mut self, uid: u32, mailbox: &Mailbox) -> Result {
+        let session = &mut self.session;
+        self.scheduler
+            .run(|| session.uid_mv(uid.to_string(), mailbox.as_name()))
+            .map_err(Error::ImapUpdate)?;
+        Ok(())
+    }
+
+    /// Removes a flag, e.g. `\Seen`, from an email.
+    pub fn remove_flag(&mut self, uid: u32, flag: &str) -> Result {
+        let session = &mut self.session;
+        self.scheduler
+            .run(|| {
+                session.uid_store(uid.to_string(), format!("-FLAGS ({flag})"))
+            })
+            .map_err(Error::ImapUpdate)?;
+        Ok(())
+    }
+
     /// Returns the body of the latest email in the `INBOX` folder.
     pub fn get_uids(&mut self) -> Result<Vec<u32>> {
+        let session = &mut self.session;
         let mut uids = self
-            .session
-            .uid_search("ALL")
+            .scheduler
+            .run(|| session.uid_search("ALL"))
             .map_err(Error::ImapFetch)?
             .into_iter()
             .collect::<Vec<_>>();
@@ -109,13 +564,89 @@ impl ImapSession<MailboxSelected> {
         uids.reverse();
         Ok(uids)
     }
+
+    /// Selects another mailbox on the same connection
+    pub fn switch_mailbox(&mut self, mailbox: &Mailbox) -> Result {
+        let session = &mut self.session;
+        self.validity = self
+            .scheduler
+            .run(|| session.select(mailbox.as_name()))
+            .map_err(Error::InvalidMailboxName)?
+            .uid_validity;
+        Ok(())
+    }
+
+    /// Leaves the selected mailbox, keeping the connection open
+    ///
+    /// `UNSELECT` is sent if the server supports it, and `CLOSE` otherwise,
+    /// which also expunges the emails flagged as `\Deleted`.
+    pub fn unselect(mut self) -> Result<ImapSession<NoMailbox>> {
+        let session = &mut self.session;
+        let has_unselect = self
+            .scheduler
+            .run(|| session.capabilities())
+            .map_err(Error::ImapFetch)?
+            .has_str(UNSELECT);
+        self.scheduler
+            .run(|| {
+                if has_unselect {
+                    session.run_command_and_check_ok(UNSELECT)
+                } else {
+                    session.close()
+                }
+            })
+            .map_err(Error::ImapUpdate)?;
+        Ok(ImapSession {
+            marker: PhantomData,
+            scheduler: self.scheduler,
+            session: self.session,
+            validity: None,
+        })
+    }
+}
+
+/// Counts the parts of a body structure that are attachments
+///
+/// A part is an attachment if its disposition is `attachment`.
+fn count_attachments(structure: &BodyStructure<'_>) -> usize {
+    match structure {
+        BodyStructure::Multipart { bodies, .. } =>
+            bodies.iter().map(count_attachments).sum(),
+        BodyStructure::Basic { common, .. }
+        | BodyStructure::Message { common, .. }
+        | BodyStructure::Text { common, .. } =>
+            usize::from(common.disposition.as_ref().is_some_and(
+                |disposition| disposition.ty.eq_ignore_ascii_case("attachment"),
+            )),
+    }
+}
+
+/// Returns the flags of a fetched email
+///
+/// The `\\Recent` flag is ignored, as it is specific to the session.
+fn to_flag_set(fetch: &Fetch) -> BTreeSet<String> {
+    fetch
+        .flags()
+        .iter()
+        .filter(|flag| **flag != Flag::Recent)
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Returns the metadata of a fetched email
+fn to_metadata(fetch: &Fetch) -> Metadata {
+    Metadata::new(
+        to_flag_set(fetch),
+        fetch.internal_date().map(|date| date.timestamp()),
+        fetch.size,
+    )
 }
 
-/// State of the [`ImageSession`] after a session was created.
+/// State of the [`ImapSession`] after a mailbox was selected.
 pub struct MailboxSelected;
 
-/// State of the [`ImageSession`] before a session was created.
-pub struct None;
+/// State of the [`ImapSession`] before a mailbox was selected.
+pub struct NoMailbox;
 
 /// Get the body of an email
 ///
@@ -131,6 +662,7 @@ mod test {
     use crate::credentials::Credentials;
     use crate::errors::Result;
     use crate::fetch::connection::ImapSession;
+    use crate::fetch::mailbox::Mailbox;
 
     #[expect(
         clippy::panic_in_result_fn,
@@ -141,7 +673,7 @@ mod test {
     fn check_first_last() -> Result {
         let credentials = Credentials::load()?;
         let imap_session = ImapSession::with_credentials(&credentials)?;
-        let mut inbox = imap_session.select_mailbox("INBOX")?;
+        let mut inbox = imap_session.select_mailbox(&Mailbox::inbox())?;
 
         let mails = inbox.get_all_mails()?;
         let first_all = mails.first().unwrap();
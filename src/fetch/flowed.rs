@@ -0,0 +1,90 @@
+//! Cleans up the plain text bodies before displaying them.
+//!
+//! Bodies sent with `format=flowed` (see RFC 3676) have soft line breaks, that
+//! are joined so that the paragraphs are wrapped to the width of the pane.
+
+/// Line that separates the body from the signature.
+const SIGNATURE_SEPARATOR: &str = "-- ";
+
+/// Normalises the line breaks to `\n`
+///
+/// CRLF and bare CR line breaks are replaced, as the CR would otherwise be
+/// displayed as garbage.
+pub fn normalise_line_breaks(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Joins the soft line breaks of a `format=flowed` body.
+///
+/// A line ending with a space is followed by a soft line break, and is joined
+/// with the next line if they have the same quoting level. If `delete_space`
+/// is set (`delsp=yes`), the trailing space is removed before joining.
+pub fn unflow(text: &str, delete_space: bool) -> String {
+    let mut lines = vec![];
+    let mut paragraph: Option<(usize, String)> = None;
+
+    for line in text.lines() {
+        let depth = line.chars().take_while(|ch| *ch == '>').count();
+        let unquoted = line.get(depth..).unwrap_or_default();
+        let unstuffed = unquoted.strip_prefix(' ').unwrap_or(unquoted);
+        let is_flowed =
+            unstuffed.ends_with(' ') && unstuffed != SIGNATURE_SEPARATOR;
+        let content = if is_flowed && delete_space {
+            unstuffed.strip_suffix(' ').unwrap_or(unstuffed)
+        } else {
+            unstuffed
+        };
+
+        let (current_depth, mut current) = match paragraph.take() {
+            Some((previous_depth, previous)) if previous_depth == depth =>
+                (previous_depth, previous),
+            Some((previous_depth, previous)) => {
+                lines.push(quote(previous_depth, &previous));
+                (depth, String::new())
+            }
+            None => (depth, String::new()),
+        };
+        current.push_str(content);
+
+        if is_flowed {
+            paragraph = Some((current_depth, current));
+        } else {
+            lines.push(quote(current_depth, &current));
+        }
+    }
+
+    if let Some((depth, current)) = paragraph {
+        lines.push(quote(depth, &current));
+    }
+
+    lines.join("\n")
+}
+
+/// Prefixes a line with the quoting marks of the given level
+fn quote(depth: usize, line: &str) -> String {
+    if depth == 0 {
+        line.to_owned()
+    } else {
+        format!("{} {line}", ">".repeat(depth))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fetch::flowed::{normalise_line_breaks, unflow};
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(normalise_line_breaks("a\r\nb\rc\n"), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn flowed() {
+        assert_eq!(
+            unflow("Hello \nworld\n>quoted \n>text\n-- \nme", false),
+            "Hello world\n> quoted text\n-- \nme"
+        );
+        assert_eq!(unflow("Hel \nlo\n", true), "Hello");
+        assert_eq!(unflow(" From here\n", false), "From here");
+    }
+}
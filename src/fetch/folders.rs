@@ -0,0 +1,94 @@
+//! Selects the folders that are synced to the local cache.
+//!
+//! The folders listed by the server are matched against include and exclude
+//! globs, where `*` matches any text and `?` any single character. A folder is
+//! synced if it matches an include glob, or if there are none, and matches no
+//! exclude glob. The other folders are online-only: they are read from the
+//! server, but never stored in the cache.
+
+/// Globs selecting the synced folders
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FolderFilter {
+    /// Globs of the folders that are never synced
+    exclude: Vec<String>,
+    /// Globs of the synced folders, or empty to sync every folder
+    include: Vec<String>,
+}
+
+impl FolderFilter {
+    /// Checks if a folder is synced to the cache
+    pub fn is_synced(&self, folder: &str) -> bool {
+        let name = folder.chars().collect::<Vec<_>>();
+        let matches = |glob: &String| {
+            matches_glob(&glob.chars().collect::<Vec<_>>(), &name)
+        };
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+
+    /// Creates a filter from comma-separated lists of globs
+    pub fn new(include: &str, exclude: &str) -> Self {
+        Self { exclude: split_globs(exclude), include: split_globs(include) }
+    }
+
+    /// Splits the folders into the synced and the online-only ones
+    pub fn partition<'folder>(
+        &self,
+        folders: &'folder [String],
+    ) -> (Vec<&'folder str>, Vec<&'folder str>) {
+        folders
+            .iter()
+            .map(String::as_str)
+            .partition(|folder| self.is_synced(folder))
+    }
+}
+
+/// Checks if a name, e.g. of a folder, matches a glob
+pub fn matches_glob(glob: &[char], name: &[char]) -> bool {
+    match (glob.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some(('*', glob_rest)), _) =>
+            matches_glob(glob_rest, name)
+                || name
+                    .split_first()
+                    .is_some_and(|(_, name_rest)| matches_glob(glob, name_rest)),
+        (Some(('?', glob_rest)), Some((_, name_rest))) =>
+            matches_glob(glob_rest, name_rest),
+        (Some((expected, glob_rest)), Some((found, name_rest))) =>
+            expected == found && matches_glob(glob_rest, name_rest),
+        (Some(_), None) => false,
+    }
+}
+
+/// Splits a comma-separated list of globs, ignoring the empty ones
+fn split_globs(globs: &str) -> Vec<String> {
+    globs
+        .split(',')
+        .map(str::trim)
+        .filter(|glob| !glob.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fetch::folders::FolderFilter;
+
+    #[test]
+    fn select_folders() {
+        let filter = FolderFilter::new("", "Spam, Archive*, Lists/?");
+        assert!(filter.is_synced("INBOX"));
+        assert!(filter.is_synced("Sent"));
+        assert!(!filter.is_synced("Spam"));
+        assert!(!filter.is_synced("Archive"));
+        assert!(!filter.is_synced("Archive/2024"));
+        assert!(!filter.is_synced("Lists/a"));
+        assert!(filter.is_synced("Lists/rust"));
+
+        let only_inbox = FolderFilter::new("INBOX, Work/*", "Work/Old*");
+        assert!(only_inbox.is_synced("INBOX"));
+        assert!(only_inbox.is_synced("Work/Team"));
+        assert!(!only_inbox.is_synced("Work/Older"));
+        assert!(!only_inbox.is_synced("Sent"));
+    }
+}
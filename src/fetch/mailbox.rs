@@ -0,0 +1,242 @@
+//! Represents the mailboxes of the account, instead of passing their names
+//! around.
+//!
+//! The mailboxes are listed by the server with
+//! [`ImapSession::list_mailboxes`](super::connection::ImapSession::list_mailboxes),
+//! with their hierarchy delimiter, e.g. `/` in `Clients/2024`, and their
+//! attributes. The special-use attributes of RFC 6154, e.g. `\Sent`, give the
+//! [`Role`] of the mailbox, whatever its name in the language of the user.
+//! Without them, e.g. for the folder names kept in the cache, the role of the
+//! drafts and sent folders is guessed from their usual English names, see
+//! [`Role::guess`].
+//!
+//! With thousands of mailboxes, the hierarchy is listed one level at a time
+//! with the `%` wildcard, see [`to_list_pattern`], and the mailboxes with
+//! `\HasChildren` can be expanded to list their children.
+//!
+//! A mailbox can also be created from a name, e.g. given on the command line,
+//! which is checked before being sent to the server.
+
+use core::fmt;
+
+use imap::types::{Name, NameAttribute};
+
+use super::connection::Error;
+
+/// Characters that can't be part of a mailbox name
+const FORBIDDEN_CHARS: [char; 3] = ['\r', '\n', '\0'];
+/// Name of the mailbox receiving the emails, that every server has
+const INBOX: &str = "INBOX";
+
+/// Mailbox of the account
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mailbox {
+    /// Attributes of the mailbox, e.g. `\Noselect` or `\Sent`
+    attributes: Vec<String>,
+    /// Separator between the levels of the hierarchy, e.g. `/`
+    delimiter: Option<String>,
+    /// Full name of the mailbox, e.g. `Clients/2024`
+    name: String,
+    /// Special use of the mailbox
+    role: Option<Role>,
+}
+
+impl Mailbox {
+    /// Returns the attributes of the mailbox
+    pub fn as_attributes(&self) -> &[String] {
+        &self.attributes
+    }
+
+    /// Returns the separator between the levels of the hierarchy
+    pub fn as_delimiter(&self) -> Option<&str> {
+        self.delimiter.as_deref()
+    }
+
+    /// Returns the full name of the mailbox
+    pub fn as_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the special use of the mailbox
+    pub const fn as_role(&self) -> Option<Role> {
+        self.role
+    }
+
+    /// Checks if the server reported that the mailbox has children in the
+    /// hierarchy
+    pub fn has_children(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| attribute.eq_ignore_ascii_case("\\HasChildren"))
+    }
+
+    /// Returns the mailbox receiving the emails
+    pub fn inbox() -> Self {
+        Self {
+            attributes: vec![],
+            delimiter: None,
+            name: INBOX.to_owned(),
+            role: None,
+        }
+    }
+
+    /// Checks if the mailbox can be selected, i.e. can hold emails
+    pub fn is_selectable(&self) -> bool {
+        !self
+            .attributes
+            .iter()
+            .any(|attribute| attribute.eq_ignore_ascii_case("\\Noselect"))
+    }
+
+    /// Returns the name of the mailbox as listed to the user, followed by the
+    /// delimiter if it has children to expand
+    pub fn to_listed_name(&self) -> String {
+        match (self.has_children(), &self.delimiter) {
+            (true, Some(delimiter)) => format!("{}{delimiter}", self.name),
+            (true, None) | (false, _) => self.name.clone(),
+        }
+    }
+}
+
+/// Returns the `LIST` pattern of the mailboxes to list for an argument given
+/// by the user
+///
+/// Without argument, only the top level of the hierarchy is listed. An
+/// argument ending with a delimiter, e.g. `Clients/`, lists the level below
+/// it, and any other argument lists the mailboxes whose name contains it.
+pub fn to_list_pattern(argument: Option<&str>) -> String {
+    match argument {
+        None => "%".to_owned(),
+        Some(parent) if parent.ends_with(['/', '.']) => format!("{parent}%"),
+        Some(filter) => format!("*{filter}*"),
+    }
+}
+
+impl fmt::Display for Mailbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+impl From<&Name> for Mailbox {
+    fn from(name: &Name) -> Self {
+        let attributes = name
+            .attributes()
+            .iter()
+            .map(|attribute| match attribute {
+                NameAttribute::NoInferiors => "\\Noinferiors".to_owned(),
+                NameAttribute::NoSelect => "\\Noselect".to_owned(),
+                NameAttribute::Marked => "\\Marked".to_owned(),
+                NameAttribute::Unmarked => "\\Unmarked".to_owned(),
+                NameAttribute::Custom(custom) => custom.to_string(),
+            })
+            .collect::<Vec<_>>();
+        Self {
+            role: attributes.iter().find_map(|attribute| Role::new(attribute)),
+            attributes,
+            delimiter: name.delimiter().map(str::to_owned),
+            name: name.name().to_owned(),
+        }
+    }
+}
+
+impl TryFrom<&str> for Mailbox {
+    type Error = Error;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        if name.is_empty() || name.contains(FORBIDDEN_CHARS) {
+            return Err(Error::InvalidMailbox(name.to_owned()));
+        }
+        Ok(Self {
+            attributes: vec![],
+            delimiter: None,
+            name: name.to_owned(),
+            role: None,
+        })
+    }
+}
+
+/// Special use of a mailbox, as advertised by the server
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Virtual mailbox holding every email
+    All,
+    /// Archived emails
+    Archive,
+    /// Emails being written
+    Drafts,
+    /// Virtual mailbox holding the flagged emails
+    Flagged,
+    /// Emails detected as spam
+    Junk,
+    /// Emails sent by the user
+    Sent,
+    /// Deleted emails
+    Trash,
+}
+
+impl Role {
+    /// Guesses the role of a mailbox from its name, e.g. `Sent Items` or
+    /// `INBOX.Drafts`, for the servers without special-use attributes
+    ///
+    /// Only the drafts and sent folders are recognised.
+    pub fn guess(name: &str) -> Option<Self> {
+        let last = name.rsplit(['/', '.']).next().unwrap_or(name).trim();
+        [
+            ("Draft", Self::Drafts),
+            ("Drafts", Self::Drafts),
+            ("Sent", Self::Sent),
+            ("Sent Items", Self::Sent),
+            ("Sent Mail", Self::Sent),
+            ("Sent Messages", Self::Sent),
+        ]
+        .into_iter()
+        .find_map(|(known, role)| {
+            last.eq_ignore_ascii_case(known).then_some(role)
+        })
+    }
+
+    /// Parses a special-use attribute, e.g. `\Sent`
+    fn new(attribute: &str) -> Option<Self> {
+        [
+            ("\\All", Self::All),
+            ("\\Archive", Self::Archive),
+            ("\\Drafts", Self::Drafts),
+            ("\\Flagged", Self::Flagged),
+            ("\\Junk", Self::Junk),
+            ("\\Sent", Self::Sent),
+            ("\\Trash", Self::Trash),
+        ]
+        .into_iter()
+        .find_map(|(name, role)| {
+            attribute.eq_ignore_ascii_case(name).then_some(role)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fetch::mailbox::{Mailbox, Role, to_list_pattern};
+
+    #[test]
+    fn mailbox() {
+        assert_eq!(Role::new("\\sent"), Some(Role::Sent));
+        assert_eq!(Role::new("\\Noselect"), None);
+        assert_eq!(Role::guess("INBOX.Sent Items"), Some(Role::Sent));
+        assert_eq!(Role::guess("[Gmail]/Drafts"), Some(Role::Drafts));
+        assert_eq!(Role::guess("Clients/Sentinel"), None);
+        assert_eq!(
+            Mailbox::try_from("Clients/2024")
+                .map(|mailbox| mailbox.to_string())
+                .ok(),
+            Some("Clients/2024".to_owned())
+        );
+        assert_eq!(Mailbox::try_from("").ok(), None);
+        assert_eq!(Mailbox::try_from("INBOX\r\nA1 LOGOUT").ok(), None);
+        assert!(Mailbox::inbox().is_selectable());
+        assert!(!Mailbox::inbox().has_children());
+        assert_eq!(to_list_pattern(None), "%");
+        assert_eq!(to_list_pattern(Some("Clients/")), "Clients/%");
+        assert_eq!(to_list_pattern(Some("2024")), "*2024*");
+    }
+}
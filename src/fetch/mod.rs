@@ -1,4 +1,10 @@
 //! Interacts with the server and changes the data to make it usable.
 
+pub mod background;
 pub mod connection;
+mod flowed;
+pub mod folders;
+pub mod mailbox;
 pub mod parser;
+pub mod pool;
+pub mod scheduler;
@@ -1,25 +1,135 @@
 //! Parses an HTML body to build an [`Email`] object
 
+use alloc::collections::BTreeSet;
 use std::collections::HashMap;
+use std::path::Path;
 
-use mail_parser::{HeaderName, HeaderValue, MessageParser};
+use mail_parser::{
+    Address, ContentType, DateTime, HeaderName, HeaderValue, MessageParser, MessagePart, MimeHeaders
+};
 
+use super::flowed::{normalise_line_breaks, unflow};
 use crate::errors::Result;
+use crate::priority::Priority;
 
 /// Headers of an email
 type Headers = HashMap<HeaderName<'static>, HeaderValue<'static>>;
 
+/// Attachment of an email
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attachment {
+    /// MIME type of the attachment, e.g. `application/pdf`
+    content_type: String,
+    /// Decoded content of the attachment
+    data: Vec<u8>,
+    /// File name of the attachment, if one was provided
+    name: Option<String>,
+}
+
+impl Attachment {
+    /// Default MIME type of attachments that don't specify one.
+    const DEFAULT_CONTENT_TYPE: &'static str = "application/octet-stream";
+    /// Default file name of attachments that don't specify one.
+    const DEFAULT_NAME: &'static str = "attachment";
+
+    /// Returns the MIME type of the attachment
+    pub fn as_content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    /// Returns the decoded content of the attachment
+    pub fn as_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the file name of the attachment, if one was provided
+    pub fn as_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns a file name that is safe to write the attachment to
+    ///
+    /// Directories are removed from the provided name, to prevent writing
+    /// outside of the wanted directory.
+    pub fn to_file_name(&self) -> String {
+        self.as_name()
+            .and_then(|name| Path::new(name).file_name())
+            .map_or_else(
+                || Self::DEFAULT_NAME.to_owned(),
+                |name| name.to_string_lossy().into_owned(),
+            )
+    }
+}
+
+impl From<&MessagePart<'_>> for Attachment {
+    fn from(part: &MessagePart<'_>) -> Self {
+        Self {
+            content_type: part.content_type().map_or_else(
+                || Self::DEFAULT_CONTENT_TYPE.to_owned(),
+                content_type_to_string,
+            ),
+            data: part.contents().to_vec(),
+            name: part.attachment_name().map(ToOwned::to_owned),
+        }
+    }
+}
+
+/// Data kept by the server on an email, besides its content
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    /// Flags of the email when it was fetched, e.g. `\Seen`
+    flags: BTreeSet<String>,
+    /// Date at which the server received the email, in seconds since the
+    /// epoch
+    internal_date: Option<i64>,
+    /// Size of the raw email, in bytes
+    size: Option<u32>,
+}
+
+impl Metadata {
+    /// Returns the flags of the email when it was fetched
+    pub const fn as_flags(&self) -> &BTreeSet<String> {
+        &self.flags
+    }
+
+    /// Creates the metadata of an email fetched from the server
+    pub const fn new(
+        flags: BTreeSet<String>,
+        internal_date: Option<i64>,
+        size: Option<u32>,
+    ) -> Self {
+        Self { flags, internal_date, size }
+    }
+}
+
 //TODO: this doesn't support nested messages yet. See mail-parser attachments
 // to this extent.
 /// Represents a parsed email
+///
+/// With the `serde` feature, the email can be serialised, e.g. to cache it
+/// or send it to a script. The headers are serialised as a list of pairs.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Email {
+    /// Number of attachments announced by the server, if the body of the
+    /// email wasn't loaded
+    attachment_count: Option<usize>,
+    /// Attachments of the email
+    attachments: Vec<Attachment>,
     /// Headers of the email
     ///
     /// This contains the date, the origin (`from`), the destination (`to`,
     /// `cc`, `bcc`), the subject, etc.
+    #[cfg_attr(feature = "serde", serde(with = "header_list"))]
     headers: Headers,
     /// HTML version of the email content
     html: Option<String>,
+    /// Flags, date of reception and size given by the server
+    ///
+    /// This is empty for the emails read from the cache without connection.
+    metadata: Metadata,
     /// Plain text version of the email content
     text: Option<String>,
     /// Unique ID corresponding to the email
@@ -27,16 +137,111 @@ pub struct Email {
 }
 
 impl Email {
+    /// Returns the attachments of the email
+    pub fn as_attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+
+    /// Returns the number of attachments of the email
+    ///
+    /// If only the headers of the email were loaded, this is the number
+    /// announced by the server.
+    pub fn count_attachments(&self) -> usize {
+        self.attachment_count.unwrap_or(self.attachments.len())
+    }
+
+    /// Sets the number of attachments announced by the server, for emails
+    /// whose body wasn't loaded.
+    pub const fn set_attachment_count(&mut self, count: usize) {
+        self.attachment_count = Some(count);
+    }
+
+    /// Returns the flags of the email when it was fetched, e.g. `\Seen`
+    pub const fn as_flags(&self) -> &BTreeSet<String> {
+        &self.metadata.flags
+    }
+
     /// Returns the headers of the email
     pub const fn as_headers(&self) -> &Headers {
         &self.headers
     }
 
+    /// Returns the size of the raw email in bytes, as given by the server
+    pub const fn as_size(&self) -> Option<u32> {
+        self.metadata.size
+    }
+
+    /// Returns the date at which the server received the email, in UTC
+    ///
+    /// Unlike the `Date` header, this is set by the server and can't be
+    /// forged by the sender.
+    pub fn to_internal_date(&self) -> Option<DateTime> {
+        self.metadata.internal_date.map(DateTime::from_timestamp)
+    }
+
+    /// Sets the flags, date of reception and size given by the server.
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    /// Returns the body in HTML, if the email has one
+    pub fn as_html_body(&self) -> Option<&str> {
+        self.html.as_deref()
+    }
+
     /// Returns the body in plain text
     pub fn to_plain_body(&self) -> Result<String> {
         Ok(self.text.as_ref().ok_or(Error::NoBody)?.to_owned())
     }
 
+    /// Returns the address of the sender, without their name
+    pub fn to_sender_address(&self) -> Option<String> {
+        self.as_headers()
+            .get(&HeaderName::From)
+            .and_then(HeaderValue::as_address)
+            .and_then(|address| address.first())
+            .and_then(|addr| addr.address())
+            .map(ToOwned::to_owned)
+    }
+
+    /// Returns the names of the recipients of the `To` header, or their
+    /// addresses if they have no name, e.g. `Alice, bob@example.com`
+    pub fn to_recipient_names(&self) -> String {
+        self.as_headers()
+            .get(&HeaderName::To)
+            .and_then(HeaderValue::as_address)
+            .map(|address| {
+                address
+                    .iter()
+                    .filter_map(|addr| addr.name().or_else(|| addr.address()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the message ids of a header, e.g. `References`
+    pub fn to_message_ids(&self, header: &HeaderName<'_>) -> Vec<String> {
+        match self.as_headers().get(header) {
+            Some(HeaderValue::Text(id)) => vec![id.to_string()],
+            Some(HeaderValue::TextList(ids)) =>
+                ids.iter().map(ToString::to_string).collect(),
+            Some(
+                HeaderValue::Address(_)
+                | HeaderValue::ContentType(_)
+                | HeaderValue::DateTime(_)
+                | HeaderValue::Empty
+                | HeaderValue::Received(_),
+            )
+            | None => vec![],
+        }
+    }
+
+    /// Returns the unique id of the email in its mailbox
+    pub const fn as_uid(&self) -> u32 {
+        self.uid
+    }
+
     /// Returns the value of a header
     pub fn get_header(
         &self,
@@ -48,6 +253,41 @@ impl Email {
             .ok_or(Error::MissingHeader)?
             .to_owned())
     }
+
+    /// Returns a human-readable version of a header from its name, ignoring
+    /// the case
+    ///
+    /// This is used for headers unknown to the parser, e.g. `X-Priority`.
+    /// Returns `None` if the header is missing.
+    pub fn get_header_by_name(&self, name: &str) -> Option<String> {
+        self.as_headers()
+            .iter()
+            .find(|(header_name, _)| {
+                header_name.as_str().eq_ignore_ascii_case(name)
+            })
+            .map(|(_, value)| header_value_to_string(value))
+    }
+
+    /// Returns the priority of the email, from its `X-Priority` or
+    /// `Importance` header
+    pub fn to_priority(&self) -> Priority {
+        Priority::from_headers(
+            self.get_header_by_name("X-Priority").as_deref(),
+            self.get_header_by_name("Importance").as_deref(),
+        )
+    }
+
+    /// Returns a human-readable version of a header
+    ///
+    /// Returns `None` if the header is missing.
+    pub fn get_header_str(
+        &self,
+        header_name: &HeaderName<'_>,
+    ) -> Option<String> {
+        self.as_headers()
+            .get(header_name)
+            .map(header_value_to_string)
+    }
 }
 
 impl<'body> TryFrom<(u32, &'body [u8])> for Email {
@@ -69,10 +309,107 @@ impl<'body> TryFrom<(u32, &'body [u8])> for Email {
             })
             .collect();
 
+        let attachments = message.attachments().map(Attachment::from).collect();
         let html = message.body_html(0).map(|html| html.to_string());
-        let text = message.body_text(0).map(|text| text.to_string());
+        let text = message.body_text(0).map(|text| {
+            let normalised = normalise_line_breaks(&text);
+            match message.text_part(0).and_then(MimeHeaders::content_type) {
+                Some(content_type) if is_flowed(content_type) => unflow(
+                    &normalised,
+                    content_type
+                        .attribute("delsp")
+                        .is_some_and(|delsp| delsp.eq_ignore_ascii_case("yes")),
+                ),
+                Some(_) | None => normalised,
+            }
+        });
+
+        Ok(Self {
+            attachment_count: None,
+            attachments,
+            headers,
+            html,
+            metadata: Metadata::default(),
+            text,
+            uid,
+        })
+    }
+}
+
+/// Serialises the headers of an email as a list of pairs
+///
+/// The names of the headers unknown to the parser hold a string, so they can't
+/// be the keys of a map in formats like JSON.
+#[cfg(feature = "serde")]
+mod header_list {
+    use mail_parser::{HeaderName, HeaderValue};
+    use serde::{Deserialize as _, Deserializer, Serializer};
+
+    use super::Headers;
+
+    /// Deserialises the headers from a list of pairs
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Headers, D::Error> {
+        Vec::<(HeaderName<'static>, HeaderValue<'static>)>::deserialize(
+            deserializer,
+        )
+        .map(|pairs| pairs.into_iter().collect())
+    }
 
-        Ok(Self { headers, html, text, uid })
+    /// Serialises the headers as a list of pairs
+    pub fn serialize<S: Serializer>(
+        headers: &Headers,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(headers)
+    }
+}
+
+/// Formats an address, or list of addresses, as `Name <address>`
+fn address_to_string(address: &Address<'_>) -> String {
+    address
+        .iter()
+        .map(|addr| match (addr.name(), addr.address()) {
+            (Some(name), Some(email)) => format!("{name} <{email}>"),
+            (Some(name), None) => name.to_owned(),
+            (None, Some(email)) => email.to_owned(),
+            (None, None) => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats a content type as `type/subtype`
+fn content_type_to_string(content_type: &ContentType<'_>) -> String {
+    content_type.subtype().map_or_else(
+        || content_type.ctype().to_owned(),
+        |subtype| format!("{}/{subtype}", content_type.ctype()),
+    )
+}
+
+/// Checks if a content type is `text/plain` with `format=flowed`
+fn is_flowed(content_type: &ContentType<'_>) -> bool {
+    content_type.ctype().eq_ignore_ascii_case("text")
+        && content_type
+            .subtype()
+            .is_some_and(|subtype| subtype.eq_ignore_ascii_case("plain"))
+        && content_type
+            .attribute("format")
+            .is_some_and(|format| format.eq_ignore_ascii_case("flowed"))
+}
+
+/// Formats a header value as a human-readable string
+fn header_value_to_string(value: &HeaderValue<'_>) -> String {
+    match value {
+        HeaderValue::Address(address) => address_to_string(address),
+        HeaderValue::Text(text) => text.to_string(),
+        HeaderValue::TextList(list) => list.join(", "),
+        HeaderValue::DateTime(date) => date.to_rfc822(),
+        HeaderValue::ContentType(content_type) =>
+            content_type_to_string(content_type),
+        HeaderValue::Received(received) => format!("{received:?}"),
+        HeaderValue::Empty => String::new(),
     }
 }
 
@@ -93,9 +430,28 @@ pub enum Error {
 
 #[cfg(test)]
 mod test {
+    use alloc::collections::BTreeSet;
+    use core::hint::black_box;
+    use std::fs;
+
     use mail_parser::{Addr, Group, HeaderName};
 
-    use crate::fetch::parser::Email;
+    use crate::fetch::parser::{Attachment, Email, Metadata};
+
+    /// Directory of the corpus of malformed emails
+    const FIXTURES: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    /// Number of mutated versions of every email of the corpus
+    const MUTATIONS: usize = 200;
+    /// Fragments inserted in the emails of the corpus, to break their structure
+    const MIME_TOKENS: [&[u8]; 6] = [
+        b"\n\n",
+        b"\n--",
+        b"=?utf-8?B?",
+        b"\nContent-Type: multipart/mixed; boundary=x\n",
+        b"\nContent-Transfer-Encoding: base64\n",
+        b"\n\t",
+    ];
 
     const EMAIL_EXAMPLES: &[u8] =
         br#"From: Art Vandelay <art@vandelay.com> (Vandelay Industries)
@@ -219,4 +575,166 @@ R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7
             "I was thinking about quitting the “exporting” to focus just on the “importing”,\nbut then I thought, why not do both? ☺\n"
         );
     }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn metadata() {
+        let mut email = Email::try_from((7, EMAIL_EXAMPLES)).unwrap();
+        assert_eq!(email.as_uid(), 7);
+        assert!(email.as_flags().is_empty());
+        assert_eq!(email.as_size(), None);
+
+        email.set_metadata(Metadata::new(
+            BTreeSet::from(["\\Seen".to_owned()]),
+            Some(1_637_446_921),
+            Some(1234),
+        ));
+        assert!(email.as_flags().contains("\\Seen"));
+        assert_eq!(email.as_size(), Some(1234));
+        assert_eq!(
+            email.to_internal_date().unwrap().to_rfc3339(),
+            "2021-11-20T22:22:01Z"
+        );
+    }
+
+    /// Returns the emails of the corpus, with their file names
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn corpus() -> Vec<(String, Vec<u8>)> {
+        let mut files = fs::read_dir(FIXTURES)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                (
+                    path.file_name().unwrap().to_string_lossy().into_owned(),
+                    fs::read(&path).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    /// Parses an email and reads every part of it, which must not panic
+    fn read_everything(raw: &[u8]) {
+        if let Ok(email) = Email::try_from((1, raw)) {
+            let file_names = email
+                .as_attachments()
+                .iter()
+                .map(Attachment::to_file_name)
+                .collect::<Vec<_>>();
+            assert!(!file_names.iter().any(|name| name.contains('/')));
+            black_box((
+                email.to_sender_address(),
+                email.to_plain_body().ok(),
+                email.as_html_body(),
+                email.to_priority(),
+                [HeaderName::Subject, HeaderName::Date, HeaderName::To]
+                    .map(|name| email.get_header_str(&name)),
+            ));
+        }
+    }
+
+    /// Generates pseudo-random numbers, to mutate the corpus reproducibly
+    struct XorShift(u64);
+
+    impl XorShift {
+        /// Returns a number lower than the bound, or 0 if the bound is 0
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0.wrapping_shl(13);
+            self.0 ^= self.0.wrapping_shr(7);
+            self.0 ^= self.0.wrapping_shl(17);
+            usize::try_from(self.0)
+                .unwrap_or_default()
+                .checked_rem(bound)
+                .unwrap_or_default()
+        }
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn malformed_corpus() {
+        let files = corpus();
+        assert!(files.len() >= 10);
+        for (_, raw) in &files {
+            read_everything(raw);
+        }
+        let parse = |name: &str| {
+            let (_, raw) = files.iter().find(|(file, _)| file == name).unwrap();
+            Email::try_from((1, raw.as_slice())).unwrap()
+        };
+
+        let missing = parse("missing_headers.eml");
+        assert_eq!(missing.to_sender_address(), None);
+        assert_eq!(missing.get_header_str(&HeaderName::Subject), None);
+
+        let charset = parse("bad_charset.eml");
+        assert_eq!(
+            charset.to_sender_address().as_deref(),
+            Some("jose@example.com")
+        );
+        assert!(charset.to_plain_body().unwrap().starts_with("Caf"));
+
+        let broken = parse("broken_mime.eml");
+        assert!(broken.to_plain_body().unwrap().contains("First part"));
+    }
+
+    #[test]
+    fn truncated_corpus() {
+        for (_, raw) in corpus() {
+            for len in 0..=raw.len() {
+                read_everything(raw.get(..len).unwrap_or_default());
+            }
+        }
+    }
+
+    #[test]
+    fn mutated_corpus() {
+        let mut random = XorShift(0x2545_f491_4f6c_dd1d);
+        for (_, raw) in corpus() {
+            for _ in 0..MUTATIONS {
+                let mut mutated = raw.clone();
+                for _ in 0..=random.below(8) {
+                    let position =
+                        random.below(mutated.len().saturating_add(1));
+                    let byte =
+                        u8::try_from(random.below(256)).unwrap_or_default();
+                    match (random.below(4), mutated.get_mut(position)) {
+                        (0, Some(old)) => *old = byte,
+                        (1, _) => mutated.insert(position, byte),
+                        (2, Some(_)) => {
+                            mutated.remove(position);
+                        }
+                        (_, _) => {
+                            let token = MIME_TOKENS
+                                .get(random.below(MIME_TOKENS.len()))
+                                .copied()
+                                .unwrap_or_default();
+                            mutated.splice(
+                                position..position,
+                                token.iter().copied(),
+                            );
+                        }
+                    }
+                }
+                read_everything(&mutated);
+            }
+        }
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn huge_headers() {
+        let long = format!("Subject: {}\n", "a".repeat(1_000_000));
+        let folded = format!("Subject: {}\n", vec!["word"; 50_000].join("\n "));
+        let many = "X-Header: value\n".repeat(10_000);
+        for headers in [long, folded, many] {
+            let raw = format!("From: alice@example.com\n{headers}\nBody\n");
+            let email = Email::try_from((1, raw.as_bytes())).unwrap();
+            assert_eq!(
+                email.to_sender_address().as_deref(),
+                Some("alice@example.com")
+            );
+            assert_eq!(email.to_plain_body().unwrap(), "Body\n");
+        }
+    }
 }
@@ -0,0 +1,167 @@
+//! Shares the IMAP connections between the threads of the app.
+//!
+//! Opening a session takes a TCP connection, a `TLS` handshake and a login, so
+//! the TUI and its background fetches borrow the connection of the account
+//! from a [`SessionManager`] instead of opening their own. A connection is used
+//! by one thread at a time, the others waiting for it to be released. Every
+//! account has its own lock, so a thread waiting for a slow server doesn't
+//! block the threads using the other accounts.
+//!
+//! A connection is dropped when a command fails, and a new one is opened the
+//! next time the account is used. A connection that the server closed while it
+//! wasn't used is opened again when it fails to select a mailbox.
+//!
+//! The connections of an account share its [`Scheduler`], so that the rate
+//! limit of the account holds across reconnections.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use super::connection::{ImapSession, MailboxSelected, NoMailbox};
+use super::mailbox::Mailbox;
+use super::scheduler::Scheduler;
+use crate::credentials::Credentials;
+use crate::errors::Result;
+
+/// Connection of an account, waiting to be used
+enum Connection {
+    /// Connection with a selected mailbox, with its name
+    Selected(ImapSession<MailboxSelected>, String),
+    /// Connection without a selected mailbox
+    Unselected(ImapSession<NoMailbox>),
+}
+
+/// Connection and scheduler of an account
+struct Account {
+    /// Connection waiting to be used, if any
+    connection: Option<Connection>,
+    /// Scheduler shared by the successive connections of the account
+    scheduler: Scheduler,
+}
+
+impl Account {
+    /// Creates an account without a connection, with the rate limit of the
+    /// [`Credentials`]
+    fn new(credentials: &Credentials) -> Self {
+        Self {
+            connection: None,
+            scheduler: Scheduler::new(credentials.as_imap_rate_limit()),
+        }
+    }
+
+    /// Opens a new connection to the account, with its scheduler
+    fn connect(
+        &self,
+        credentials: &Credentials,
+    ) -> Result<ImapSession<NoMailbox>> {
+        ImapSession::with_scheduler(credentials, self.scheduler.clone())
+    }
+}
+
+/// Connections of the accounts, shared between threads
+///
+/// Cloning the manager shares the same connections.
+#[derive(Clone, Default)]
+pub struct SessionManager(Arc<Mutex<BTreeMap<String, Arc<Mutex<Account>>>>>);
+
+impl SessionManager {
+    /// Returns the account of the credentials, created if it wasn't used yet
+    ///
+    /// The map of the accounts is only locked while the account is looked up.
+    fn account(&self, credentials: &Credentials) -> Arc<Mutex<Account>> {
+        let mut accounts =
+            self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(
+            accounts
+                .entry(credentials.as_email().to_lowercase())
+                .or_insert_with(|| {
+                    Arc::new(Mutex::new(Account::new(credentials)))
+                }),
+        )
+    }
+
+    /// Runs commands on the connection of the account, outside of a mailbox
+    #[expect(
+        clippy::significant_drop_tightening,
+        reason = "the lock is held while the connection is used"
+    )]
+    pub fn run<T, F>(
+        &self,
+        credentials: &Credentials,
+        operation: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(&mut ImapSession<NoMailbox>) -> Result<T>,
+    {
+        let shared = self.account(credentials);
+        let mut account = lock(&shared);
+        let reused =
+            account
+                .connection
+                .take()
+                .and_then(|connection| match connection {
+                    Connection::Selected(selected, _) =>
+                        selected.unselect().ok(),
+                    Connection::Unselected(session) => Some(session),
+                });
+        let mut session = match reused {
+            Some(session) => session,
+            None => account.connect(credentials)?,
+        };
+        let output = operation(&mut session)?;
+        account.connection = Some(Connection::Unselected(session));
+        Ok(output)
+    }
+
+    /// Runs commands on the connection of the account, in the given mailbox
+    ///
+    /// The mailbox is only selected if the connection was in another one.
+    #[expect(
+        clippy::significant_drop_tightening,
+        reason = "the lock is held while the connection is used"
+    )]
+    pub fn run_in<T, F>(
+        &self,
+        credentials: &Credentials,
+        mailbox: &Mailbox,
+        operation: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(&mut ImapSession<MailboxSelected>) -> Result<T>,
+    {
+        let shared = self.account(credentials);
+        let mut account = lock(&shared);
+        let reused =
+            account
+                .connection
+                .take()
+                .and_then(|connection| match connection {
+                    Connection::Selected(selected, name)
+                        if name == mailbox.as_name() =>
+                        Some(selected),
+                    Connection::Selected(mut selected, _) => selected
+                        .switch_mailbox(mailbox)
+                        .is_ok()
+                        .then_some(selected),
+                    Connection::Unselected(session) =>
+                        session.select_mailbox(mailbox).ok(),
+                });
+        let mut selected = match reused {
+            Some(selected) => selected,
+            None => account.connect(credentials)?.select_mailbox(mailbox)?,
+        };
+        let output = operation(&mut selected)?;
+        account.connection =
+            Some(Connection::Selected(selected, mailbox.as_name().to_owned()));
+        Ok(output)
+    }
+}
+
+/// Locks an account, while its connection is used
+///
+/// The connection used by a thread that panicked was taken out of the
+/// account, so the account can still be used.
+fn lock(account: &Mutex<Account>) -> MutexGuard<'_, Account> {
+    account.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,179 @@
+//! Spaces out the IMAP commands sent for an account.
+//!
+//! The commands are sent at most
+//! [`Credentials::as_imap_rate_limit`](crate::credentials::Credentials::as_imap_rate_limit)
+//! times per second. The limit is the one of the account, not of a
+//! connection: the [`SessionManager`](super::pool::SessionManager) gives the
+//! same [`Scheduler`] to every connection of an account, so that reconnecting
+//! doesn't reset it.
+//!
+//! The commands refused because the client is throttled are retried with an
+//! exponential backoff, from 1 second up to 1 minute.
+
+use alloc::sync::Arc;
+use core::time::Duration;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Instant;
+
+/// Delay before retrying a throttled command for the first time.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Maximum delay before retrying a throttled command.
+const MAX_BACKOFF: Duration = Duration::from_mins(1);
+/// Number of times a throttled command is retried before giving up.
+const MAX_RETRIES: u32 = 5;
+/// Words of the server responses meaning that the client is throttled.
+///
+/// Gmail answers with `[THROTTLED]`, Office 365 with `Request is throttled`.
+const THROTTLING_KEYWORDS: [&str; 4] =
+    ["THROTTLED", "[LIMIT]", "TOO MANY", "RATE LIMIT"];
+
+/// Spaces out the commands sent to the server
+///
+/// Cloning the scheduler shares the time of the last command, so the clones
+/// respect the rate limit together.
+#[derive(Clone)]
+pub struct Scheduler {
+    /// Time at which the last command was sent, or will be sent if it is
+    /// waiting for its slot
+    last_command: Arc<Mutex<Option<Instant>>>,
+    /// Minimum duration between two commands
+    min_interval: Duration,
+}
+
+impl Scheduler {
+    /// Creates a new [`Scheduler`] sending at most `rate_limit` commands per
+    /// second, or any number of commands if `rate_limit` is 0.
+    pub fn new(rate_limit: u32) -> Self {
+        Self {
+            last_command: Arc::default(),
+            min_interval: Duration::from_secs(1)
+                .checked_div(rate_limit)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Runs a command once the rate limit allows it, retrying it while the
+    /// server throttles the client.
+    pub fn run<T, F: FnMut() -> imap::Result<T>>(
+        &self,
+        mut command: F,
+    ) -> imap::Result<T> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut retries: u32 = 0;
+        loop {
+            self.wait_for_slot();
+            match command() {
+                Err(err) if retries < MAX_RETRIES && is_throttled(&err) => {
+                    thread::sleep(backoff);
+                    backoff = next_backoff(backoff);
+                    retries = retries.saturating_add(1);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Reserves the next slot, and waits until it comes
+    ///
+    /// The slot is reserved before waiting, so that the other clones wait for
+    /// the following one instead of the same.
+    fn wait_for_slot(&self) {
+        let now = Instant::now();
+        let slot = {
+            let mut last_command = self
+                .last_command
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let slot = last_command
+                .and_then(|last| last.checked_add(self.min_interval))
+                .filter(|next| *next > now)
+                .unwrap_or(now);
+            *last_command = Some(slot);
+            slot
+        };
+        thread::sleep(slot.saturating_duration_since(now));
+    }
+}
+
+/// Returns the delay before the next retry of a throttled command
+fn next_backoff(backoff: Duration) -> Duration {
+    backoff.saturating_mul(2).min(MAX_BACKOFF)
+}
+
+/// Checks if an error means that the server throttles the client
+fn is_throttled(err: &imap::Error) -> bool {
+    matches!(
+        err,
+        imap::Error::No(response) | imap::Error::Bad(response)
+            if THROTTLING_KEYWORDS
+                .iter()
+                .any(|keyword| response.to_uppercase().contains(keyword))
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+    use std::io;
+    use std::time::Instant;
+
+    use crate::fetch::scheduler::{
+        INITIAL_BACKOFF, MAX_BACKOFF, Scheduler, is_throttled, next_backoff
+    };
+
+    #[test]
+    fn throttled() {
+        assert!(is_throttled(&imap::Error::No(
+            "[THROTTLED] Too many commands".to_owned()
+        )));
+        assert!(is_throttled(&imap::Error::Bad(
+            "Request is throttled".to_owned()
+        )));
+        assert!(is_throttled(&imap::Error::No("[LIMIT] Slow down".to_owned())));
+        assert!(!is_throttled(&imap::Error::No(
+            "[NONEXISTENT] Unknown mailbox".to_owned()
+        )));
+        assert!(!is_throttled(&imap::Error::Io(io::Error::other("throttled"))));
+    }
+
+    #[test]
+    fn backoff() {
+        let mut delays = vec![INITIAL_BACKOFF];
+        while delays.len() < 8 {
+            delays.push(next_backoff(*delays.last().unwrap_or(&MAX_BACKOFF)));
+        }
+        assert_eq!(
+            delays,
+            [1, 2, 4, 8, 16, 32, 60, 60].map(Duration::from_secs)
+        );
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn rate_limit() {
+        let scheduler = Scheduler::new(20);
+        let shared = scheduler.clone();
+        let start = Instant::now();
+        let mut calls: u32 = 0;
+        scheduler.run(|| Ok(())).unwrap();
+        shared.run(|| Ok(())).unwrap();
+        scheduler.run(|| Ok(())).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        let error = shared
+            .run(|| {
+                calls += 1;
+                Err::<(), _>(imap::Error::No("[NONEXISTENT]".to_owned()))
+            })
+            .unwrap_err();
+        assert!(!is_throttled(&error));
+        assert_eq!(calls, 1);
+        let unlimited = Scheduler::new(0);
+        let start_unlimited = Instant::now();
+        let rounds: u32 = 10;
+        for _ in 0..rounds {
+            unlimited.run(|| Ok(())).unwrap();
+        }
+        assert!(start_unlimited.elapsed() < Duration::from_millis(100));
+    }
+}
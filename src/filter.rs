@@ -0,0 +1,100 @@
+//! Runs email bodies through the display filters before displaying them.
+//!
+//! Display filters are user commands, configured per content type in the
+//! [`Config`], that receive the body on their standard input and output the
+//! text to display, like mutt's `display_filter`. Filters are chained with
+//! shell pipes, e.g. `w3m -dump -T text/html | par 72`.
+
+use mail_parser::decoders::html::html_to_text;
+
+use crate::config::Config;
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+use crate::pipe::pipe;
+
+/// Returns the body to display, after running it through the display filters.
+///
+/// The HTML body, sanitized by the [`Policy`](crate::html::Policy) of the
+/// configuration, is preferred if an HTML display filter is configured,
+/// otherwise the plain text body is used, filtered if a plain text display
+/// filter is configured.
+pub fn filter_body(email: &Email, config: &Config) -> Result<String> {
+    let html = config.as_html_display_filter().and_then(|filter| {
+        Some((filter, config.as_html_policy().to_html_body(email)?))
+    });
+    apply_filters(email, html, config.as_text_display_filter())
+}
+
+/// Returns the body to display without the display filters: the plain text
+/// body, or else the text of the HTML body if the email has none.
+pub fn to_unfiltered_body(email: &Email) -> Result<String> {
+    email
+        .to_plain_body()
+        .or_else(|err| email.as_html_body().map(html_to_text).ok_or(err))
+}
+
+/// Runs the HTML body through its display filter if it is given, or else the
+/// plain text body through the plain text display filter if any
+fn apply_filters(
+    email: &Email,
+    html: Option<(&str, String)>,
+    text_filter: Option<&str>,
+) -> Result<String> {
+    if let Some((filter, body)) = html {
+        return run_filter(filter, &body);
+    }
+    let text = to_unfiltered_body(email)?;
+    match text_filter {
+        Some(filter) => run_filter(filter, &text),
+        None => Ok(text),
+    }
+}
+
+/// Pipes a body into a display filter and returns its output.
+fn run_filter(filter: &str, body: &str) -> Result<String> {
+    let output = pipe(filter, body.as_bytes().to_vec())?;
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::fetch::parser::Email;
+    use crate::filter::{apply_filters, filter_body, run_filter};
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn display_filters() {
+        assert_eq!(run_filter("tr a-z A-Z", "hello\n").unwrap(), "HELLO\n");
+        run_filter("exit 3", "hello").unwrap_err();
+
+        let plain =
+            Email::try_from((1, b"Subject: Hi\n\nhello\n".as_slice())).unwrap();
+        assert_eq!(filter_body(&plain, &Config::default()).unwrap(), "hello\n");
+        assert_eq!(
+            apply_filters(&plain, None, Some("tr a-z A-Z")).unwrap(),
+            "HELLO\n"
+        );
+        assert_eq!(
+            apply_filters(
+                &plain,
+                Some(("sed 's/<[^>]*>//g'", "<p>html</p>".to_owned())),
+                Some("tr a-z A-Z")
+            )
+            .unwrap(),
+            "html"
+        );
+
+        let html = Email::try_from((
+            2,
+            b"Subject: Hi\nContent-Type: text/html\n\n<p>Hello <b>Bob</b></p>\n"
+                .as_slice(),
+        ))
+        .unwrap();
+        assert!(
+            filter_body(&html, &Config::default())
+                .unwrap()
+                .contains("Hello Bob")
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Sanitizes the HTML bodies before they are rendered or saved.
+//!
+//! Unless the `TRUSTED_HTML` flag of the [`Config`](crate::config::Config) is
+//! set, the HTML body of an email is cleaned before being piped into the HTML
+//! display filter or saved with `:html <file>`:
+//!
+//! - the scripts, forms, frames and embedded objects are removed with their
+//!   content, as are the comments;
+//! - the event handlers, e.g. `onclick`, and the `javascript:` links are
+//!   removed;
+//! - the remote images, which are mostly used to track when the email is read,
+//!   are removed with the other remote content, e.g. style sheets.
+//!
+//! The remote content is kept for the senders of the `REMOTE_CONTENT_SENDERS`
+//! variable, a comma-separated list of addresses and domains, e.g.
+//! `boss@example.com, newsletter.example.org`.
+//!
+//! The tracking pixels are counted in the title of the viewer, and their
+//! domains are listed with `:trackers`. A remote image is a tracking pixel if
+//! it is 1x1 or hidden, or if its URL matches a pattern of the common
+//! trackers, e.g. `/wf/open`.
+
+use core::str::Chars;
+
+use crate::fetch::parser::Email;
+
+/// Elements removed with their content
+const REMOVED_ELEMENTS: [&str; 10] = [
+    "applet", "button", "embed", "form", "frame", "iframe", "object", "script",
+    "select", "textarea",
+];
+/// Elements removed, but not their content
+const REMOVED_TAGS: [&str; 6] =
+    ["base", "frameset", "input", "link", "meta", "option"];
+/// Attributes holding a URL
+const URL_ATTRIBUTES: [&str; 9] = [
+    "action",
+    "background",
+    "formaction",
+    "href",
+    "lowsrc",
+    "poster",
+    "src",
+    "srcset",
+    "xlink:href",
+];
+/// Attributes loading a resource when the email is rendered
+const REMOTE_ATTRIBUTES: [&str; 5] =
+    ["background", "poster", "src", "srcset", "xlink:href"];
+
+/// Parts of the URLs of the common tracking pixels, in lowercase
+const TRACKER_PATTERNS: [&str; 14] = [
+    "/beacon",
+    "/open.aspx",
+    "/open.gif",
+    "/open.php",
+    "/pixel",
+    "/track/open",
+    "/trk",
+    "/wf/open",
+    "doubleclick.net",
+    "google-analytics.com/collect",
+    "list-manage.com/track",
+    "mailtrack.io",
+    "mandrillapp.com/track",
+    "tracking",
+];
+
+/// How the HTML bodies are sanitized
+#[derive(Debug, Default)]
+pub struct Policy {
+    /// Addresses and domains of the senders whose remote content is loaded,
+    /// in lowercase
+    remote_senders: Vec<String>,
+    /// Indicates whether the HTML bodies are used as they are
+    trusted: bool,
+}
+
+impl Policy {
+    /// Creates the policy from the comma-separated senders whose remote
+    /// content is loaded
+    pub fn new(trusted: bool, remote_senders: &str) -> Self {
+        Self {
+            remote_senders: remote_senders
+                .split(',')
+                .map(|entry| {
+                    let trimmed = entry.trim();
+                    trimmed.strip_prefix('@').unwrap_or(trimmed).to_lowercase()
+                })
+                .filter(|entry| !entry.is_empty())
+                .collect(),
+            trusted,
+        }
+    }
+
+    /// Checks if the remote content of the emails of an address is loaded
+    fn loads_remote_content(&self, address: &str) -> bool {
+        let lowercase = address.to_lowercase();
+        let domain = lowercase.rsplit_once('@').map(|(_, domain)| domain);
+        self.remote_senders
+            .iter()
+            .any(|entry| *entry == lowercase || Some(entry.as_str()) == domain)
+    }
+
+    /// Returns the HTML body of an email, sanitized unless the HTML is
+    /// trusted
+    pub fn to_html_body(&self, email: &Email) -> Option<String> {
+        let html = email.as_html_body()?;
+        if self.trusted {
+            return Some(html.to_owned());
+        }
+        let remote_content = email
+            .to_sender_address()
+            .is_some_and(|address| self.loads_remote_content(&address));
+        Some(sanitize(html, remote_content))
+    }
+}
+
+/// Tag of an HTML element
+struct Tag {
+    /// Attributes of the tag, with their raw value if any
+    attributes: Vec<(String, Option<String>)>,
+    /// Indicates whether the tag closes an element, e.g. `</p>`
+    closing: bool,
+    /// Number of bytes of the tag in the source
+    len: usize,
+    /// Name of the element, in lowercase
+    name: String,
+    /// Indicates whether the tag ends with `/>`
+    self_closing: bool,
+}
+
+impl Tag {
+    /// Returns the value of an attribute, if it is set
+    fn get(&self, attribute: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name == attribute)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Checks if the tag is a remote image used to track when the email is
+    /// read
+    fn is_tracker(&self) -> bool {
+        let Some(src) = self.get("src").filter(|src| is_remote_url(src)) else {
+            return false;
+        };
+        if self.name != "img" || self.closing {
+            return false;
+        }
+        let is_tiny = |dimension: &str| {
+            self.get(dimension).is_some_and(|value| {
+                let digits = value.trim().trim_end_matches("px");
+                digits.parse::<u32>().is_ok_and(|size| size <= 1)
+            })
+        };
+        let style = self
+            .get("style")
+            .unwrap_or_default()
+            .chars()
+            .filter(|ch| !ch.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let url = src.to_ascii_lowercase();
+        is_tiny("width") && is_tiny("height")
+            || style.contains("display:none")
+            || style.contains("width:1px") && style.contains("height:1px")
+            || TRACKER_PATTERNS.iter().any(|pattern| url.contains(pattern))
+    }
+
+    /// Parses the tag at the start of the source
+    ///
+    /// Returns `None` if the source doesn't start with a valid tag, e.g. in
+    /// `a < b`.
+    fn parse(source: &str) -> Option<Self> {
+        let mut chars = source.strip_prefix('<')?.chars();
+        let closing = chars.as_str().starts_with('/');
+        if closing {
+            chars.next();
+        }
+        if !chars
+            .as_str()
+            .starts_with(|ch: char| ch.is_ascii_alphabetic())
+        {
+            return None;
+        }
+        let name = take_while(&mut chars, |ch| {
+            ch.is_ascii_alphanumeric() || ch == '-' || ch == ':'
+        })
+        .to_ascii_lowercase();
+        let mut attributes = vec![];
+        let mut self_closing = false;
+        loop {
+            take_while(&mut chars, char::is_whitespace);
+            match chars.next()? {
+                '>' => break,
+                '/' => self_closing = true,
+                first => {
+                    let rest = take_while(&mut chars, |ch| {
+                        !ch.is_whitespace() && !matches!(ch, '=' | '>' | '/')
+                    });
+                    take_while(&mut chars, char::is_whitespace);
+                    let value = if chars.as_str().starts_with('=') {
+                        chars.next();
+                        take_while(&mut chars, char::is_whitespace);
+                        Some(take_value(&mut chars)?)
+                    } else {
+                        None
+                    };
+                    attributes.push((
+                        format!("{first}{rest}").to_ascii_lowercase(),
+                        value,
+                    ));
+                    self_closing = false;
+                }
+            }
+        }
+        Some(Self {
+            attributes,
+            closing,
+            len: source.len().saturating_sub(chars.as_str().len()),
+            name,
+            self_closing,
+        })
+    }
+
+    /// Returns the tag without its dangerous attributes
+    ///
+    /// Returns `None` if the tag must be removed, e.g. a remote image.
+    fn to_safe(&self, remote_content: bool) -> Option<String> {
+        if self.closing {
+            return Some(format!("</{}>", self.name));
+        }
+        let mut kept = vec![format!("<{}", self.name)];
+        for (name, value) in &self.attributes {
+            let raw = value.as_deref().unwrap_or_default();
+            if name.starts_with("on")
+                || URL_ATTRIBUTES.contains(&name.as_str()) && is_script_url(raw)
+            {
+                continue;
+            }
+            let remote = if name == "style" {
+                is_remote_style(raw)
+            } else {
+                REMOTE_ATTRIBUTES.contains(&name.as_str()) && is_remote_url(raw)
+            };
+            if remote && !remote_content {
+                if self.name == "img" && name != "background" {
+                    return None;
+                }
+                continue;
+            }
+            kept.push(value.as_ref().map_or_else(
+                || format!(" {name}"),
+                |found| format!(" {name}=\"{}\"", found.replace('"', "&quot;")),
+            ));
+        }
+        kept.push(if self.self_closing { " />" } else { ">" }.to_owned());
+        Some(kept.concat())
+    }
+}
+
+/// Checks if a URL runs a script when followed, e.g. `javascript:alert(1)`
+///
+/// URLs with character references before their scheme are rejected too, as
+/// they can hide one, e.g. `&#106;avascript:`.
+fn is_script_url(url: &str) -> bool {
+    let compact = url
+        .chars()
+        .filter(|ch| !ch.is_whitespace() && !ch.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let scheme = compact.split_once(':').map_or("", |(scheme, _)| scheme);
+    scheme.contains('&')
+        || matches!(scheme, "javascript" | "vbscript")
+        || compact.starts_with("data:text/html")
+}
+
+/// Checks if a URL loads a remote resource, unlike `cid:` or `data:` URLs
+/// that are part of the email
+fn is_remote_url(url: &str) -> bool {
+    let lowercase = url.trim().to_ascii_lowercase();
+    !lowercase.is_empty()
+        && !lowercase.starts_with("cid:")
+        && !lowercase.starts_with("data:")
+}
+
+/// Checks if a style sheet loads a remote resource
+fn is_remote_style(style: &str) -> bool {
+    let lowercase = style.to_ascii_lowercase();
+    lowercase.contains("url(")
+        || lowercase.contains("@import")
+        || lowercase.contains("expression(")
+}
+
+/// Removes the scripts, forms and trackers from an HTML document
+///
+/// The remote content, e.g. images, is only kept if asked for.
+pub fn sanitize(html: &str, remote_content: bool) -> String {
+    let mut output = vec![];
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let (text, from_tag) = rest.split_at(start);
+        output.push(text.to_owned());
+        if let Some(comment) = from_tag.strip_prefix("<!--") {
+            rest = comment.split_once("-->").map_or("", |(_, after)| after);
+            continue;
+        }
+        if let Some(declaration) = from_tag
+            .strip_prefix("<!")
+            .or_else(|| from_tag.strip_prefix("<?"))
+        {
+            rest = declaration.split_once('>').map_or("", |(_, after)| after);
+            continue;
+        }
+        let Some(tag) = Tag::parse(from_tag) else {
+            output.push("&lt;".to_owned());
+            rest = from_tag.get(1..).unwrap_or_default();
+            continue;
+        };
+        rest = from_tag.get(tag.len..).unwrap_or_default();
+        let name = tag.name.as_str();
+        let removed_element = REMOVED_ELEMENTS.contains(&name);
+        if REMOVED_TAGS.contains(&name)
+            || removed_element && (tag.closing || tag.self_closing)
+        {
+            continue;
+        }
+        if removed_element
+            || name == "style"
+                && !tag.closing
+                && !remote_content
+                && is_remote_style(split_element(rest, name).0)
+        {
+            (_, rest) = split_element(rest, name);
+            continue;
+        }
+        output.extend(tag.to_safe(remote_content));
+    }
+    output.push(rest.to_owned());
+    output.concat()
+}
+
+/// Returns the domains of the tracking pixels of an HTML document, once per
+/// pixel
+pub fn find_trackers(html: &str) -> Vec<String> {
+    html.match_indices('<')
+        .filter_map(|(start, _)| Tag::parse(html.get(start..)?))
+        .filter(Tag::is_tracker)
+        .filter_map(|tag| tag.get("src").map(to_domain))
+        .collect()
+}
+
+/// Returns the domain of a URL, e.g. `example.com` for
+/// `https://user@example.com:443/pixel.gif`
+fn to_domain(url: &str) -> String {
+    let trimmed = url.trim();
+    let without_scheme =
+        trimmed.split_once("//").map_or(trimmed, |(_, after)| after);
+    let authority = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    host.split(':')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Splits the rest of an element at its closing tag, returning its content
+/// and what follows the closing tag
+fn split_element<'html>(
+    rest: &'html str,
+    name: &str,
+) -> (&'html str, &'html str) {
+    let Some(end) = rest.to_ascii_lowercase().find(&format!("</{name}")) else {
+        return (rest, "");
+    };
+    let (content, closing) = rest.split_at(end);
+    (content, closing.split_once('>').map_or("", |(_, after)| after))
+}
+
+/// Consumes the characters while they match the predicate, and returns them
+fn take_while<'html, Predicate: Fn(char) -> bool>(
+    chars: &mut Chars<'html>,
+    predicate: Predicate,
+) -> &'html str {
+    let rest = chars.as_str();
+    let end = rest.find(|ch| !predicate(ch)).unwrap_or(rest.len());
+    let (taken, after) = rest.split_at(end);
+    *chars = after.chars();
+    taken
+}
+
+/// Consumes the value of an attribute, quoted or not
+///
+/// Returns `None` if a quote isn't closed.
+fn take_value(chars: &mut Chars<'_>) -> Option<String> {
+    let rest = chars.as_str();
+    match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let (value, after) = rest.get(1..)?.split_once(quote)?;
+            *chars = after.chars();
+            Some(value.to_owned())
+        }
+        Some(_) | None => Some(
+            take_while(chars, |ch| !ch.is_whitespace() && ch != '>').to_owned(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::html::{Policy, find_trackers, sanitize};
+
+    const EMAIL: &str = "<!DOCTYPE html><html><!-- hidden --><body onload=\"track()\">\
+<p style=\"color: red\">Hi <b>Bob</b>, 1 < 2</p>\
+<script type=\"text/javascript\">alert('</p>')</script>\
+<img src=\"https://tracker.example.com/pixel.gif\" width=1>\
+<img src='cid:logo' alt=\"Logo\">\
+<a href=\" javascript:steal()\">Click</a>\
+<a href=\"https://example.com\" onclick=\"track()\">Link</a>\
+<form action=\"https://phishing.example.com\">\
+<input name=\"password\"><button>Log in</button></form>\
+<style>body { background: url(https://tracker.example.com/bg.png) }</style>\
+</body></html>";
+
+    #[test]
+    fn sanitized() {
+        assert_eq!(
+            sanitize(EMAIL, false),
+            "<html><body><p style=\"color: red\">Hi <b>Bob</b>, 1 &lt; \
+             2</p><img src=\"cid:logo\" alt=\"Logo\"><a>Click</a><a \
+             href=\"https://example.com\">Link</a></body></html>"
+        );
+        let with_remote = sanitize(EMAIL, true);
+        assert!(with_remote.contains(
+            "<img src=\"https://tracker.example.com/pixel.gif\" width=\"1\">"
+        ));
+        assert!(with_remote.contains("<style>body"));
+        assert!(!with_remote.contains("script"));
+        assert!(!with_remote.contains("form"));
+    }
+
+    #[test]
+    fn trackers() {
+        assert_eq!(find_trackers(EMAIL), ["tracker.example.com"]);
+        let html = "<img src=\"https://cdn.example.com/photo.jpg\" \
+                    width=\"600\" height=\"1\"><IMG \
+                    SRC=\"//Mail.Example.org:8080/wf/open?u=1\"><img \
+                    src=\"https://a.example.net/x.gif\" style=\"display: \
+                    none\"><img src=\"cid:logo\" width=0 height=0>";
+        assert_eq!(find_trackers(html), ["mail.example.org", "a.example.net"]);
+    }
+
+    #[test]
+    fn remote_senders() {
+        let policy = Policy::new(false, "boss@example.com, @News.example.org");
+        assert!(policy.loads_remote_content("Boss@example.com"));
+        assert!(policy.loads_remote_content("daily@news.example.org"));
+        assert!(!policy.loads_remote_content("intern@example.com"));
+        assert!(!Policy::new(false, " , ").loads_remote_content("a@b"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Handles the identities emails can be sent from.
+//!
+//! Besides the `EMAIL` of the account, aliases can be listed in the
+//! `IDENTITIES` variable of the `.env` file, separated by semicolons. Each
+//! identity is an address with an optional display name, and can be sent
+//! through another SMTP server with `via <host>:<port>`:
+//!
+//! ```env
+//! NAME=Bob Smith
+//! IDENTITIES=Support <support@example.com>; Bob <bob@personal.org> via smtp.personal.org:465
+//! ```
+//!
+//! When replying, the identity the replied email was sent to is selected by
+//! default. If it wasn't sent to any of them, e.g. because it was received
+//! through a mailing list or a forward, the first identity is selected, and
+//! the writer warns about it.
+
+use mail_parser::HeaderName;
+
+use crate::fetch::parser::Email;
+
+/// Keyword introducing the SMTP server of an identity
+const ROUTE_KEYWORD: &str = " via ";
+
+/// Name and address an email is sent from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    /// Email address of the identity
+    address: String,
+    /// Display name of the identity, if any
+    name: Option<String>,
+    /// SMTP server to send the emails of this identity through, if it isn't
+    /// the one of the account
+    smtp_route: Option<(String, u16)>,
+}
+
+impl Identity {
+    /// Returns the email address of the identity
+    pub fn as_address(&self) -> &str {
+        &self.address
+    }
+
+    /// Returns the SMTP server of the identity, if it isn't the one of the
+    /// account
+    pub fn as_smtp_route(&self) -> Option<(&str, u16)> {
+        self.smtp_route
+            .as_ref()
+            .map(|(host, port)| (host.as_str(), *port))
+    }
+
+    /// Creates an identity sent through the SMTP server of the account
+    pub const fn new(address: String, name: Option<String>) -> Self {
+        Self { address, name, smtp_route: None }
+    }
+
+    /// Parses an identity, e.g. `Bob <bob@example.com> via
+    /// smtp.example.com:465`
+    ///
+    /// Returns `None` if the address is empty or the route is invalid.
+    pub fn parse(entry: &str) -> Option<Self> {
+        let (mailbox, smtp_route) = match entry.split_once(ROUTE_KEYWORD) {
+            Some((mailbox, route)) => {
+                let (host, port) = route.trim().rsplit_once(':')?;
+                (mailbox, Some((host.to_owned(), port.parse().ok()?)))
+            }
+            None => (entry, None),
+        };
+        let (name, address) = match mailbox.split_once('<') {
+            Some((name, rest)) => (
+                Some(name.trim().to_owned())
+                    .filter(|trimmed| !trimmed.is_empty()),
+                rest.trim_end().strip_suffix('>')?,
+            ),
+            None => (None, mailbox),
+        };
+        let trimmed = address.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+        Some(Self { address: trimmed.to_owned(), name, smtp_route })
+    }
+
+    /// Returns the value of the `From` header, e.g. `Bob <bob@example.com>`
+    pub fn to_from_header(&self) -> String {
+        self.name.as_ref().map_or_else(
+            || self.address.clone(),
+            |name| format!("{name} <{}>", self.address),
+        )
+    }
+}
+
+/// Returns the index of the identity a replied email was sent to
+///
+/// The identity is looked for with [`find_recipient`], and defaults to the
+/// first one.
+pub fn find_for_reply(identities: &[Identity], email: &Email) -> usize {
+    find_recipient(identities, email).unwrap_or_default()
+}
+
+/// Returns the index of the identity an email was sent to, looked for in the
+/// `To`, `Cc` and `Delivered-To` headers
+///
+/// Returns `None` if it wasn't sent to any identity.
+pub fn find_recipient(identities: &[Identity], email: &Email) -> Option<usize> {
+    let recipients = [
+        email.get_header_str(&HeaderName::To),
+        email.get_header_str(&HeaderName::Cc),
+        email.get_header_by_name("Delivered-To"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(", ")
+    .to_lowercase();
+    identities.iter().position(|identity| {
+        recipients.contains(&identity.address.to_lowercase())
+    })
+}
+
+/// Parses a list of identities separated by semicolons, ignoring the invalid
+/// ones
+pub fn parse_identities(value: &str) -> Vec<Identity> {
+    value.split(';').filter_map(Identity::parse).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fetch::parser::Email;
+    use crate::identity::{
+        Identity, find_for_reply, find_recipient, parse_identities
+    };
+
+    #[test]
+    #[expect(clippy::unwrap_used, clippy::indexing_slicing, reason = "test")]
+    fn identities() {
+        let identities = parse_identities(
+            "Support <support@example.com>; bob@personal.org via smtp.personal.org:587; <>",
+        );
+        assert_eq!(identities.len(), 2);
+        assert_eq!(
+            identities[0].to_from_header(),
+            "Support <support@example.com>"
+        );
+        assert_eq!(
+            identities[1].as_smtp_route(),
+            Some(("smtp.personal.org", 587))
+        );
+        assert_eq!(Identity::parse("bob@x via nowhere"), None);
+
+        let email = Email::try_from((
+            1,
+            b"To: Bob <BOB@personal.org>\n\nHello".as_slice(),
+        ))
+        .unwrap();
+        assert_eq!(find_for_reply(&identities, &email), 1);
+        let list = Email::try_from((
+            2,
+            b"To: list@lists.example.org\n\nHello".as_slice(),
+        ))
+        .unwrap();
+        assert_eq!(find_recipient(&identities, &list), None);
+        assert_eq!(find_for_reply(&identities, &list), 0);
+    }
+}
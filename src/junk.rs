@@ -0,0 +1,131 @@
+//! Trains the spam filter when emails are moved to or out of the junk folder.
+//!
+//! The learn commands are configured in the [`Config`](crate::config::Config)
+//! and receive the raw email on their standard input, e.g. for rspamd,
+//! bogofilter or `SpamAssassin`:
+//!
+//! ```env
+//! JUNK_FOLDER=Junk
+//! LEARN_SPAM_COMMAND=rspamc learn_spam      # bogofilter -s, sa-learn --spam
+//! LEARN_HAM_COMMAND=rspamc learn_ham        # bogofilter -n, sa-learn --ham
+//! ```
+//!
+//! Moving an email to the junk folder learns it as spam, and rescuing it from
+//! the junk folder learns it as ham. Nothing is learnt if the corresponding
+//! command isn't set.
+
+use crate::pipe::{self, pipe};
+
+/// Default name of the junk folder
+const DEFAULT_JUNK_FOLDER: &str = "Junk";
+
+/// Kind of email learnt by the spam filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Legitimate email, rescued from the junk folder
+    Ham,
+    /// Unwanted email, moved to the junk folder
+    Spam,
+}
+
+impl Verdict {
+    /// Returns the name of the verdict, to display it
+    pub const fn as_name(self) -> &'static str {
+        match self {
+            Self::Ham => "ham",
+            Self::Spam => "spam",
+        }
+    }
+}
+
+/// Commands training the spam filter
+#[derive(Debug)]
+pub struct Training {
+    /// Command learning the emails rescued from the junk folder
+    ham_command: Option<String>,
+    /// Name of the junk folder
+    junk_folder: String,
+    /// Command learning the emails moved to the junk folder
+    spam_command: Option<String>,
+}
+
+impl Training {
+    /// Returns the name of the junk folder
+    pub fn as_junk_folder(&self) -> &str {
+        &self.junk_folder
+    }
+
+    /// Pipes a raw email into the learn command of the verdict.
+    pub fn learn(
+        &self,
+        verdict: Verdict,
+        raw: Vec<u8>,
+    ) -> Result<(), pipe::Error> {
+        self.to_command(verdict)
+            .map_or(Ok(()), |command| pipe(command, raw).map(|_| ()))
+    }
+
+    /// Creates the training from the junk folder and the learn commands
+    ///
+    /// The junk folder defaults to `Junk`.
+    pub fn new(
+        junk_folder: Option<String>,
+        spam_command: Option<String>,
+        ham_command: Option<String>,
+    ) -> Self {
+        Self {
+            ham_command,
+            junk_folder: junk_folder
+                .unwrap_or_else(|| DEFAULT_JUNK_FOLDER.to_owned()),
+            spam_command,
+        }
+    }
+
+    /// Returns the learn command of a verdict, if any
+    fn to_command(&self, verdict: Verdict) -> Option<&str> {
+        match verdict {
+            Verdict::Ham => self.ham_command.as_deref(),
+            Verdict::Spam => self.spam_command.as_deref(),
+        }
+    }
+
+    /// Returns what an email moved between two folders must be learnt as,
+    /// if it must be learnt
+    pub fn to_verdict(&self, from: &str, to: &str) -> Option<Verdict> {
+        let verdict = match (self.is_junk(from), self.is_junk(to)) {
+            (false, true) => Verdict::Spam,
+            (true, false) => Verdict::Ham,
+            (false, false) | (true, true) => return None,
+        };
+        self.to_command(verdict).map(|_| verdict)
+    }
+
+    /// Checks if a folder is the junk folder, ignoring the case
+    pub fn is_junk(&self, folder: &str) -> bool {
+        folder.eq_ignore_ascii_case(&self.junk_folder)
+    }
+}
+
+impl Default for Training {
+    fn default() -> Self {
+        Self::new(None, None, None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::junk::{Training, Verdict};
+
+    #[test]
+    fn verdicts() {
+        let training = Training::new(
+            Some("Spam".to_owned()),
+            Some("bogofilter -s".to_owned()),
+            None,
+        );
+        assert_eq!(training.to_verdict("INBOX", "spam"), Some(Verdict::Spam));
+        assert_eq!(training.to_verdict("Spam", "INBOX"), None);
+        assert_eq!(training.to_verdict("INBOX", "Archive"), None);
+        assert_eq!(Training::default().to_verdict("INBOX", "Junk"), None);
+    }
+}
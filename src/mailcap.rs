@@ -0,0 +1,260 @@
+//! Opens attachments with the program associated to their MIME type.
+//!
+//! The associations are read from a mailcap file (see RFC 1524), which
+//! contains one entry per line, of the form:
+//!
+//! ```mailcap
+//! application/pdf; zathura %s
+//! image/*; feh %s
+//! ```
+//!
+//! The `%s` is replaced with the path of a temporary file containing the
+//! attachment. If there is no `%s`, the attachment is given on the standard
+//! input of the command.
+//!
+//! The temporary files are created in a directory only readable by the user,
+//! and are removed with it when the app exits, not when the command returns:
+//! openers like `xdg-open` return before the program has read the file.
+
+use std::fs::{DirBuilder, OpenOptions};
+use std::io::Write as _;
+use std::os::unix::fs::{DirBuilderExt as _, OpenOptionsExt as _};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, io, process};
+
+use crate::errors::Result;
+use crate::fetch::parser::Attachment;
+use crate::pipe::{run, shell_quote};
+
+/// Number of names tried for the directory of the temporary files, before
+/// giving up
+const MAX_DIR_ATTEMPTS: u32 = 16;
+
+/// Errors that may occur while opening an attachment.
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to create the directory of the temporary files
+    TempDir(io::Error),
+    /// Failed to write or remove the temporary attachment file.
+    TempFile(io::Error),
+}
+
+/// Entries of a mailcap file
+#[derive(Default)]
+pub struct Mailcap {
+    /// Entries, in the order of the file
+    entries: Vec<MailcapEntry>,
+}
+
+impl Mailcap {
+    /// Returns the command to open the given MIME type with
+    ///
+    /// The first matching entry is chosen.
+    pub fn find_command(&self, mime_type: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.matches(mime_type))
+            .map(|entry| entry.command.as_str())
+    }
+
+    /// Loads the mailcap file at the given path
+    ///
+    /// A missing or unreadable file results in an empty [`Mailcap`].
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|file| fs::read_to_string(file).ok())
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default()
+    }
+
+    /// Parses the content of a mailcap file
+    ///
+    /// Entries that are meant to display the content inside the pager (flagged
+    /// with `copiousoutput`) are ignored.
+    pub fn parse(content: &str) -> Self {
+        let entries = content
+            .replace("\\\n", "")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split(';').map(str::trim);
+                let mime_type = fields.next()?.to_lowercase();
+                let command = fields.next()?.to_owned();
+                if fields.any(|field| field == "copiousoutput") {
+                    return None;
+                }
+                Some(MailcapEntry { command, mime_type })
+            })
+            .collect();
+
+        Self { entries }
+    }
+}
+
+/// Entry of a mailcap file, associating a MIME type to a command
+struct MailcapEntry {
+    /// Command to open the file with
+    command: String,
+    /// MIME type, possibly with a wildcard subtype, e.g. `image/*`
+    mime_type: String,
+}
+
+impl MailcapEntry {
+    /// Checks if the entry applies to the given MIME type
+    ///
+    /// The entry matches all subtypes if it ends with `/*`, or if it has no
+    /// subtype.
+    fn matches(&self, mime_type: &str) -> bool {
+        let lowercase = mime_type.to_lowercase();
+        let entry_type =
+            self.mime_type.strip_suffix("/*").unwrap_or(&self.mime_type);
+        lowercase == self.mime_type
+            || lowercase
+                .split_once('/')
+                .is_some_and(|(main_type, _)| main_type == entry_type)
+    }
+}
+
+/// Temporary files of the opened attachments
+///
+/// They are written to a private directory, created when the first attachment
+/// is opened.
+#[derive(Default)]
+pub struct OpenedAttachments {
+    /// Number of attachments written to the directory
+    count: usize,
+    /// Path of the directory, once created
+    dir: Option<PathBuf>,
+}
+
+impl OpenedAttachments {
+    /// Creates the directory of the temporary files
+    ///
+    /// The directory is created with a new name, so that it can't be a
+    /// directory or a link prepared by another user, and only the user can
+    /// access it.
+    fn create_dir() -> Result<PathBuf, Error> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.subsec_nanos());
+        let mut last_err = io::Error::from(io::ErrorKind::AlreadyExists);
+        for attempt in 0..MAX_DIR_ATTEMPTS {
+            let path = env::temp_dir()
+                .join(format!("mailbox-{}-{nanos}-{attempt}", process::id()));
+            match DirBuilder::new().mode(0o700).create(&path) {
+                Ok(()) => return Ok(path),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists =>
+                    last_err = err,
+                Err(err) => return Err(Error::TempDir(err)),
+            }
+        }
+        Err(Error::TempDir(last_err))
+    }
+
+    /// Opens an attachment with the program associated to its MIME type
+    ///
+    /// If no program is found in the mailcap file, the `default_opener` is
+    /// used. The attachment is written to a new file of the private
+    /// directory, which is kept until [`Self::remove`] is called.
+    pub fn open(
+        &mut self,
+        attachment: &Attachment,
+        mailcap: &Mailcap,
+        default_opener: &str,
+    ) -> Result {
+        let dir = match self.dir.take() {
+            Some(created) => created,
+            None => Self::create_dir()?,
+        };
+        let name = format!("{}-{}", self.count, attachment.to_file_name());
+        let path = self.dir.insert(dir).join(name);
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .and_then(|mut file| file.write_all(attachment.as_data()))
+            .map_err(Error::TempFile)?;
+        self.count = self.count.saturating_add(1);
+
+        let command = mailcap
+            .find_command(attachment.as_content_type())
+            .unwrap_or(default_opener);
+        let quoted_path = shell_quote(&path.to_string_lossy());
+        let command_line = if command.contains("%s") {
+            command.replace("%s", &quoted_path)
+        } else {
+            format!("{command} < {quoted_path}")
+        };
+        Ok(run(&command_line)?)
+    }
+
+    /// Removes the temporary files of the opened attachments
+    pub fn remove(&mut self) -> Result {
+        if let Some(dir) = self.dir.take() {
+            fs::remove_dir_all(dir).map_err(Error::TempFile)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt as _;
+
+    use crate::fetch::parser::Email;
+    use crate::mailcap::{Mailcap, OpenedAttachments};
+
+    const MAILCAP: &str = "# comment
+text/html; w3m -dump %s; copiousoutput
+application/pdf; zathura %s
+image/*; feh \\
+    %s
+video; mpv %s
+";
+
+    #[test]
+    fn find_commands() {
+        let mailcap = Mailcap::parse(MAILCAP);
+        assert_eq!(mailcap.find_command("application/pdf"), Some("zathura %s"));
+        assert_eq!(mailcap.find_command("IMAGE/PNG"), Some("feh     %s"));
+        assert_eq!(mailcap.find_command("video/mp4"), Some("mpv %s"));
+        assert_eq!(mailcap.find_command("text/html"), None);
+        assert_eq!(mailcap.find_command("application/zip"), None);
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn temporary_files() {
+        let email = Email::try_from((
+            1,
+            b"Content-Type: multipart/mixed; boundary=\"b\"\n\n--b\n\
+              Content-Type: text/plain\n\nHello\n--b\n\
+              Content-Type: text/plain; name=\"notes.txt\"\n\
+              Content-Disposition: attachment\n\nNotes\n--b--\n"
+                .as_slice(),
+        ))
+        .unwrap();
+        let attachment = email.as_attachments().first().unwrap();
+        let mut opened = OpenedAttachments::default();
+        opened
+            .open(attachment, &Mailcap::default(), "true")
+            .unwrap();
+        opened
+            .open(attachment, &Mailcap::default(), "true")
+            .unwrap();
+
+        let dir = opened.dir.clone().unwrap();
+        let mode = |path| fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode(&dir) & 0o777, 0o700);
+        assert_eq!(mode(&dir.join("0-notes.txt")) & 0o777, 0o600);
+        assert_eq!(
+            fs::read_to_string(dir.join("1-notes.txt")).unwrap(),
+            "Notes"
+        );
+        opened.remove().unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,127 @@
+//! Parses `mailto:` URLs (see RFC 6068), to pre-fill the writer.
+//!
+//! This allows [`mailbox`](crate) to be the default mail handler of the
+//! system, with the `assets/mailbox.desktop` entry:
+//!
+//! ```sh
+//! xdg-mime default mailbox.desktop x-scheme-handler/mailto
+//! ```
+
+/// Content of a parsed `mailto:` URL
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Mailto {
+    /// Body of the email
+    body: String,
+    /// Subject of the email
+    subject: String,
+    /// Destinations of the email
+    to: Vec<String>,
+}
+
+impl Mailto {
+    /// Returns the body of the email
+    pub fn as_body(&self) -> &str {
+        &self.body
+    }
+
+    /// Returns the subject of the email
+    pub fn as_subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Returns the destinations of the email
+    pub fn as_to(&self) -> &[String] {
+        &self.to
+    }
+
+    /// Parses a `mailto:` URL
+    ///
+    /// Returns `None` if the URL doesn't have the `mailto` scheme. Unknown
+    /// fields are ignored.
+    pub fn parse(url: &str) -> Option<Self> {
+        let (scheme, rest) = url.split_once(':')?;
+        if !scheme.eq_ignore_ascii_case("mailto") {
+            return None;
+        }
+
+        let (recipients, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut mailto =
+            Self { to: split_addresses(recipients), ..Self::default() };
+
+        for field in query.split('&').filter(|field| !field.is_empty()) {
+            let (key, value) = field.split_once('=').unwrap_or((field, ""));
+            match key.to_lowercase().as_str() {
+                "to" => mailto.to.extend(split_addresses(value)),
+                "subject" => mailto.subject = percent_decode(value),
+                "body" => mailto.body = percent_decode(value),
+                _ => (),
+            }
+        }
+
+        Some(mailto)
+    }
+}
+
+/// Parses the value of a hexadecimal digit
+fn hex_value(digit: u8) -> Option<u8> {
+    u8::try_from(char::from(digit).to_digit(16)?).ok()
+}
+
+/// Decodes the percent-encoded characters of a URL component
+///
+/// Invalid escapes are kept as is.
+fn percent_decode(encoded: &str) -> String {
+    let mut bytes = encoded.bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let mut lookahead = bytes.clone();
+            if let (Some(high), Some(low)) = (
+                lookahead.next().and_then(hex_value),
+                lookahead.next().and_then(hex_value),
+            ) {
+                decoded.push(high.wrapping_shl(4) | low);
+                bytes = lookahead;
+                continue;
+            }
+        }
+        decoded.push(byte);
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Splits a comma-separated list of percent-encoded addresses
+fn split_addresses(addresses: &str) -> Vec<String> {
+    addresses
+        .split(',')
+        .map(|address| percent_decode(address).trim().to_owned())
+        .filter(|address| !address.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mailto::Mailto;
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn parse_mailto() {
+        let mailto = Mailto::parse(
+            "mailto:alice@example.com,bob@example.com?subject=Hi%20there&\
+             body=Line%201%0ALine+2%&to=carol%40example.com",
+        )
+        .unwrap();
+
+        assert_eq!(
+            mailto.as_to(),
+            ["alice@example.com", "bob@example.com", "carol@example.com"]
+        );
+        assert_eq!(mailto.as_subject(), "Hi there");
+        assert_eq!(mailto.as_body(), "Line 1\nLine+2%");
+
+        assert!(Mailto::parse("https://example.com").is_none());
+        assert!(Mailto::parse("MAILTO:").unwrap().as_to().is_empty());
+    }
+}
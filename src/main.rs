@@ -39,11 +39,67 @@
 #![allow(clippy::arbitrary_source_item_ordering, reason = "issue #14570")]
 #![allow(clippy::pattern_type_mismatch, reason = "conveniant")]
 
+extern crate alloc;
+
+mod annotations;
+mod archive;
+mod cache;
+mod calendar;
+mod cli;
+mod config;
 mod credentials;
 mod errors;
 mod fetch;
+mod filter;
+mod html;
+mod identity;
+mod junk;
+mod mailcap;
+mod mailto;
+mod otp;
+mod patch;
+mod pipe;
+mod print;
+mod priority;
+mod send;
+#[cfg(test)]
+mod test_utils;
 mod tui;
 
-fn main() -> errors::Result {
-    tui::app::Tui::new()?.run()
+use std::env;
+use std::io::{self, Write as _};
+use std::process::ExitCode;
+
+use cli::Cli;
+
+#[expect(clippy::use_debug, reason = "the errors only implement Debug")]
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            let code = ExitCode::from(err.to_exit_code());
+            match writeln!(io::stderr().lock(), "Error: {err:?}") {
+                Ok(()) => code,
+                Err(_) => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+/// Runs the action requested on the command line
+fn run() -> errors::Result {
+    match Cli::parse(env::args().skip(1))? {
+        Cli::Compose(url) => cli::compose::run(url.as_deref()),
+        Cli::Backup(args) => args.backup(),
+        Cli::Control(request) => cli::control::run(&request),
+        Cli::Digest(args) => args.run(),
+        Cli::Merge(args) => args.run(),
+        Cli::Migrate(args) => args.run(),
+        Cli::Restore(args) => args.restore(),
+        Cli::Send(args) => args.run(),
+        Cli::Sendmail(args) => args.run(),
+        Cli::Stats => cli::stats::run(),
+        Cli::Tui { fresh } => tui::app::Tui::new(fresh)?.run(),
+        Cli::Watch(args) => args.run(),
+    }
 }
@@ -0,0 +1,210 @@
+//! Finds the one-time codes of the verification emails, to copy them.
+//!
+//! A code is a word of 4 to 8 digits or uppercase letters, with at least a
+//! digit, e.g. `482913` or `K7Q2ZP`, or two groups of digits joined with a
+//! hyphen, e.g. `482-913`. It is only looked for in the emails that mention a
+//! code, e.g. `verification`, `OTP` or `passcode`: in their subject if the
+//! keyword is there, or else in their body after the first keyword, so that
+//! the numbers of the rest of the email, e.g. the year of the footer, aren't
+//! taken.
+//!
+//! The code of a received email is shown by `mailbox watch` and in the viewer,
+//! and `y` copies the code of the opened or selected email to the clipboard:
+//! it is piped into the clipboard command of the
+//! [`Config`](crate::config::Config), e.g.
+//!
+//! ```env
+//! CLIPBOARD_COMMAND=wl-copy
+//! ```
+//!
+//! or `xclip -selection clipboard`, and written to the terminal as an OSC 52
+//! sequence if there is none, which most terminal emulators copy, also over
+//! SSH.
+
+use core::mem;
+use std::io::{self, Write as _, stdout};
+
+use mail_parser::HeaderName;
+
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+use crate::pipe::pipe;
+use crate::send::encoding::encode_base64;
+
+/// Words announcing a code, in lowercase
+const KEYWORDS: [&str; 10] = [
+    "2fa",
+    "authentication",
+    "code",
+    "one-time",
+    "one time",
+    "otp",
+    "passcode",
+    "pin",
+    "verification",
+    "verify",
+];
+/// Minimum length of a code, without its hyphen
+const MIN_LENGTH: usize = 4;
+/// Maximum length of a code, without its hyphen
+const MAX_LENGTH: usize = 8;
+
+/// Errors that may occur while copying a code
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to write the code to the terminal
+    Clipboard(io::Error),
+}
+
+/// Returns the one-time code of an email, without its hyphen, if it has one
+pub fn find_code(email: &Email) -> Option<String> {
+    let subject = email
+        .get_header_str(&HeaderName::Subject)
+        .unwrap_or_default();
+    let in_subject = find_keyword(&subject).is_some();
+    if in_subject && let Some(code) = find_in(&subject) {
+        return Some(code);
+    }
+    let body = email.to_plain_body().ok()?;
+    let start = if in_subject { 0 } else { find_keyword(&body)? };
+    find_in(body.get(start..)?)
+}
+
+/// Copies a code to the clipboard, with the clipboard command if there is one
+pub fn copy(code: &str, command: Option<&str>) -> Result {
+    if let Some(clipboard) = command {
+        pipe(clipboard, code.as_bytes().to_vec())?;
+        return Ok(());
+    }
+    let mut terminal = stdout();
+    write!(terminal, "\u{1b}]52;c;{}\u{7}", encode_base64(code.as_bytes()))
+        .and_then(|()| terminal.flush())
+        .map_err(Error::Clipboard)?;
+    Ok(())
+}
+
+/// Returns the position of the first keyword announcing a code in a text
+///
+/// The keywords must be whole words, e.g. `pin` isn't found in `shipping`.
+fn find_keyword(text: &str) -> Option<usize> {
+    let lower = text.to_ascii_lowercase();
+    KEYWORDS
+        .iter()
+        .filter_map(|keyword| {
+            lower
+                .match_indices(keyword)
+                .map(|(start, _)| start)
+                .find(|start| {
+                    let before = lower
+                        .get(..*start)
+                        .and_then(|head| head.chars().next_back());
+                    let after = lower
+                        .get(start.saturating_add(keyword.len())..)
+                        .and_then(|tail| tail.chars().next());
+                    !before.is_some_and(char::is_alphanumeric)
+                        && !after.is_some_and(char::is_alphanumeric)
+                })
+        })
+        .min()
+}
+
+/// Returns the first code of a text, without its hyphen
+fn find_in(text: &str) -> Option<String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut words = vec![];
+    let mut word = String::new();
+    for (index, ch) in chars.iter().enumerate() {
+        let is_digit_at = |offset: Option<usize>| {
+            offset
+                .and_then(|position| chars.get(position))
+                .is_some_and(char::is_ascii_digit)
+        };
+        if ch.is_ascii_alphanumeric() {
+            word.push(*ch);
+        } else if *ch == '-'
+            && is_digit_at(index.checked_sub(1))
+            && is_digit_at(index.checked_add(1))
+        {
+            word.push('-');
+        } else {
+            words.push(mem::take(&mut word));
+        }
+    }
+    words.push(word);
+    words.into_iter().find_map(|candidate| {
+        let code = candidate.replace('-', "");
+        (is_code(&code) && candidate.matches('-').count() <= 1).then_some(code)
+    })
+}
+
+/// Checks if a word is a code
+fn is_code(word: &str) -> bool {
+    (MIN_LENGTH..=MAX_LENGTH).contains(&word.len())
+        && word.bytes().any(|byte| byte.is_ascii_digit())
+        && word
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || byte.is_ascii_uppercase())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::otp::find_code;
+    use crate::test_utils::to_email;
+
+    #[test]
+    fn otp() {
+        assert_eq!(
+            find_code(&to_email(
+                1,
+                "Subject: 482913 is your verification code",
+                ""
+            )),
+            Some("482913".to_owned())
+        );
+        assert_eq!(
+            find_code(&to_email(
+                1,
+                "Subject: Sign in to Acme",
+                "Hello Bob 42,\n\nYour one-time code: 482-913. It expires \
+                 soon.\n\n(c) 2024 Acme"
+            )),
+            Some("482913".to_owned())
+        );
+        assert_eq!(
+            find_code(&to_email(
+                1,
+                "Subject: Login",
+                "Use the code K7Q2ZP to sign in"
+            )),
+            Some("K7Q2ZP".to_owned())
+        );
+        assert_eq!(
+            find_code(&to_email(1, "Subject: Verify your account", "")),
+            None
+        );
+        assert_eq!(
+            find_code(&to_email(
+                1,
+                "Subject: Invoice 2024-05-02",
+                "Total: 1234 EUR"
+            )),
+            None
+        );
+        assert_eq!(
+            find_code(&to_email(
+                1,
+                "Subject: Your PIN",
+                "Meeting on 2024-05-02"
+            )),
+            None
+        );
+        assert_eq!(
+            find_code(&to_email(
+                1,
+                "Subject: Shipping",
+                "Parcel 8841 was decoded"
+            )),
+            None
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Handles the patches sent by `git format-patch` and `git send-email`.
+//!
+//! A patch series is a set of emails with subjects like `[PATCH v2 3/5] ...`,
+//! that can be saved to an mbox file, or applied with `git am`.
+
+use std::path::Path;
+use std::{fs, io};
+
+use mail_parser::HeaderName;
+
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+use crate::pipe::{pipe, shell_quote};
+
+/// Errors that may occur while handling patches.
+#[derive(Debug)]
+pub enum Error {
+    /// The given email is not a patch.
+    NotAPatch,
+    /// Failed to save the patch series to a file.
+    Save(io::Error),
+}
+
+/// Information given by the subject of a patch
+#[derive(Debug, PartialEq, Eq)]
+pub struct PatchSubject {
+    /// Position of the patch in the series, starting from 1
+    ///
+    /// The cover letter has an index of 0.
+    index: usize,
+    /// Content of the brackets without the numbering, e.g. `PATCH v2`
+    tag: String,
+    /// Number of patches in the series
+    total: usize,
+}
+
+impl PatchSubject {
+    /// Parses the subject of an email, returning `None` if it isn't a patch.
+    ///
+    /// Subjects without numbering (e.g. `[PATCH] fix`) are series of one
+    /// patch.
+    pub fn parse(subject: &str) -> Option<Self> {
+        let (brackets, _) =
+            subject.trim_start().strip_prefix('[')?.split_once(']')?;
+        if !brackets.to_uppercase().contains("PATCH") {
+            return None;
+        }
+
+        let mut words = brackets.split_whitespace().collect::<Vec<_>>();
+        let numbering = words
+            .last()
+            .and_then(|word| word.split_once('/'))
+            .and_then(|(index, total)| {
+                Some((index.parse().ok()?, total.parse().ok()?))
+            });
+        if numbering.is_some() {
+            words.pop();
+        }
+        let (index, total) = numbering.unwrap_or((1, 1));
+
+        Some(Self { index, tag: words.join(" "), total })
+    }
+}
+
+/// Returns the patches of the series `email` belongs to, sorted by index.
+///
+/// Patches belong to the same series if they have the same tag, total and
+/// sender. The cover letter isn't included.
+pub fn find_series<'emails>(
+    emails: &'emails [Email],
+    email: &Email,
+) -> Result<Vec<&'emails Email>> {
+    let subject = patch_subject(email).ok_or(Error::NotAPatch)?;
+    let from = email.get_header_str(&HeaderName::From);
+
+    let mut series = emails
+        .iter()
+        .filter_map(|other| Some((patch_subject(other)?, other)))
+        .filter(|(other_subject, other)| {
+            other_subject.index != 0
+                && other_subject.tag == subject.tag
+                && other_subject.total == subject.total
+                && other.get_header_str(&HeaderName::From) == from
+        })
+        .collect::<Vec<_>>();
+    series.sort_by_key(|(other_subject, _)| other_subject.index);
+    series.dedup_by_key(|(other_subject, _)| other_subject.index);
+
+    Ok(series.into_iter().map(|(_, other)| other).collect())
+}
+
+/// Applies a patch series with `git am` in the given repository.
+///
+/// The patches are the raw emails of the series, in order.
+pub fn apply_series(patches: &[String], repository: &Path) -> Result {
+    let command = format!(
+        "git -C {} am --patch-format=mboxrd",
+        shell_quote(&repository.to_string_lossy())
+    );
+    pipe(&command, to_mbox(patches).into_bytes())?;
+    Ok(())
+}
+
+/// Returns whether the email is a patch sent by `git send-email`.
+pub fn is_patch(email: &Email) -> bool {
+    patch_subject(email).is_some()
+}
+
+/// Saves a patch series to an mbox file, that can be applied with
+/// `git am --patch-format=mboxrd`.
+///
+/// The patches are the raw emails of the series, in order.
+pub fn save_series(patches: &[String], path: &Path) -> Result {
+    fs::write(path, to_mbox(patches)).map_err(Error::Save)?;
+    Ok(())
+}
+
+/// Parses the subject of an email as a patch subject.
+fn patch_subject(email: &Email) -> Option<PatchSubject> {
+    PatchSubject::parse(&email.get_header_str(&HeaderName::Subject)?)
+}
+
+/// Formats raw emails in the mboxrd format
+///
+/// The emails are written as received, with their headers and encoded parts,
+/// and their line breaks normalised to LF. Lines starting with `From `,
+/// possibly quoted with `>`, are escaped with an additional `>`.
+fn to_mbox(raws: &[String]) -> String {
+    let mut lines = vec![];
+
+    for raw in raws {
+        lines.push("From mailbox Mon Sep 17 00:00:00 2001".to_owned());
+        for line in raw.lines() {
+            if line.trim_start_matches('>').starts_with("From ") {
+                lines.push(format!(">{line}"));
+            } else {
+                lines.push(line.to_owned());
+            }
+        }
+        lines.push(String::new());
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patch::{PatchSubject, to_mbox};
+
+    #[test]
+    fn parse_subjects() {
+        assert_eq!(
+            PatchSubject::parse("[PATCH v2 3/5] fetch: fix parser"),
+            Some(PatchSubject {
+                index: 3,
+                tag: "PATCH v2".to_owned(),
+                total: 5
+            })
+        );
+        assert_eq!(
+            PatchSubject::parse("[RFC PATCH] tui: add tabs"),
+            Some(PatchSubject {
+                index: 1,
+                tag: "RFC PATCH".to_owned(),
+                total: 1
+            })
+        );
+        assert_eq!(
+            PatchSubject::parse("[PATCH 0/2] cover letter"),
+            Some(PatchSubject { index: 0, tag: "PATCH".to_owned(), total: 2 })
+        );
+        assert_eq!(PatchSubject::parse("Re: [PATCH 1/2] review"), None);
+        assert_eq!(PatchSubject::parse("[ANN] release 1.0"), None);
+        assert_eq!(PatchSubject::parse("Hello"), None);
+    }
+
+    #[test]
+    fn mbox_round_trip() {
+        let raws = [
+            "From: =?UTF-8?Q?Ren=C3=A9e?= <renee@example.com>\r\nSubject: \
+             [PATCH 1/2] caf\u{e9}: fix\r\nContent-Type: text/plain; \
+             charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\nFrom \
+             the start, \u{2192} na\u{ef}ve\r\n>From quoted\r\n---\r\n"
+                .to_owned(),
+            "From: bob@example.com\nSubject: [PATCH 2/2] \u{65e5}\u{672c}\n\n\
+             body\n"
+                .to_owned(),
+        ];
+        let mbox = to_mbox(&raws);
+        assert!(mbox.contains("\n>From the start"));
+        assert!(mbox.contains("\n>>From quoted"));
+
+        // Reads the mbox back, as `git mailsplit` does in mboxrd mode.
+        let read = mbox
+            .split("From mailbox Mon Sep 17 00:00:00 2001\n")
+            .skip(1)
+            .map(|message| {
+                message
+                    .lines()
+                    .map(|line| {
+                        if line.trim_start_matches('>').starts_with("From ") {
+                            line.strip_prefix('>').unwrap_or(line)
+                        } else {
+                            line
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>();
+        let expected = raws
+            .iter()
+            .map(|raw| raw.replace("\r\n", "\n").trim_end().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            read.iter()
+                .map(|message| message.trim_end())
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+}
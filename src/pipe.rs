@@ -0,0 +1,81 @@
+//! Runs user-provided commands, piping data through them.
+//!
+//! Commands are run through `sh -c`, so that the user can provide arguments,
+//! pipes, redirections, etc. in the `.env` file.
+
+use core::any::Any;
+use std::io::{self, Write as _};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Errors that may occur while running an external command.
+#[derive(Debug)]
+pub enum Error {
+    /// The command exited with a failure status code.
+    Failure(String, Option<i32>),
+    /// Failed to write the input to the standard input of the command.
+    Input(io::Error),
+    /// The thread writing the input to the command panicked.
+    InputThread(Box<dyn Any + Send>),
+    /// Failed to spawn the command.
+    Spawn(io::Error),
+    /// Failed to wait for the command to terminate.
+    Wait(io::Error),
+}
+
+/// Runs a command with the given input, and returns its standard output.
+pub fn pipe(command: &str, input: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(Error::Spawn)?;
+
+    // The input is written in another thread to prevent deadlocks when the
+    // command fills its output before reading all of its input.
+    let writer = child
+        .stdin
+        .take()
+        .map(|mut stdin| thread::spawn(move || stdin.write_all(&input)));
+
+    let output = child.wait_with_output().map_err(Error::Wait)?;
+
+    if let Some(handle) = writer {
+        handle
+            .join()
+            .map_err(Error::InputThread)?
+            .map_err(Error::Input)?;
+    }
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(Error::Failure(command.to_owned(), output.status.code()))
+    }
+}
+
+/// Runs a command in the current terminal, and waits for it to terminate.
+///
+/// The command inherits the standard input and outputs, so interactive
+/// programs can be run.
+pub fn run(command: &str) -> Result<(), Error> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .status()
+        .map_err(Error::Spawn)?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Failure(command.to_owned(), status.code()))
+    }
+}
+
+/// Quotes an argument to be safely inserted in a shell command.
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
@@ -0,0 +1,92 @@
+//! Formats emails into printable documents.
+//!
+//! The document is a plain text stream with the main headers, followed by the
+//! body wrapped to [`PRINT_WIDTH`] columns. It is then piped into the print
+//! command of the [`Config`](crate::config::Config), which defaults to `lpr`.
+
+use core::iter;
+use core::mem::{replace, take};
+
+use mail_parser::HeaderName;
+
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+use crate::pipe::pipe;
+
+/// Number of columns of the printed document.
+const PRINT_WIDTH: usize = 80;
+
+/// Headers printed at the top of the document, with their labels.
+const PRINTED_HEADERS: [(HeaderName<'static>, &str); 4] = [
+    (HeaderName::From, "From"),
+    (HeaderName::To, "To"),
+    (HeaderName::Date, "Date"),
+    (HeaderName::Subject, "Subject"),
+];
+
+/// Prints an email by piping it into the given print command.
+pub fn print_email(email: &Email, print_command: &str) -> Result {
+    pipe(print_command, to_printable(email)?.into_bytes())?;
+    Ok(())
+}
+
+/// Formats an email into a printable plain text document.
+fn to_printable(email: &Email) -> Result<String> {
+    let headers = PRINTED_HEADERS.iter().filter_map(|(header_name, label)| {
+        email
+            .get_header_str(header_name)
+            .map(|value| format!("{label}: {value}"))
+    });
+    let body = email.to_plain_body()?;
+
+    Ok(headers
+        .chain(iter::once(String::new()))
+        .chain(body.lines().map(ToOwned::to_owned))
+        .flat_map(|line| wrap_line(&line, PRINT_WIDTH))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Wraps a line on spaces so that no line exceeds `width` characters.
+///
+/// Words longer than `width` are split.
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+
+    for word in line.split(' ') {
+        let current_len = current.chars().count();
+        if current_len != 0
+            && current_len
+                .saturating_add(word.chars().count())
+                .saturating_add(1)
+                > width
+        {
+            lines.push(take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+        while let Some((idx, _)) = current.char_indices().nth(width) {
+            let rest = current.split_off(idx);
+            lines.push(replace(&mut current, rest));
+        }
+    }
+
+    lines.push(current);
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use crate::print::wrap_line;
+
+    #[test]
+    fn wrap_lines() {
+        assert_eq!(wrap_line("", 10), vec![""]);
+        assert_eq!(wrap_line("hello world", 11), vec!["hello world"]);
+        assert_eq!(wrap_line("hello world", 8), vec!["hello", "world"]);
+        assert_eq!(wrap_line("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+    }
+}
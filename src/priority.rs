@@ -0,0 +1,117 @@
+//! Reads and writes the priority of emails.
+//!
+//! There is no standard header for the priority, so the two most common ones
+//! are supported:
+//!
+//! - `X-Priority`, from `1` (highest) to `5` (lowest), often followed by a
+//!   comment, e.g. `1 (Highest)`,
+//! - `Importance` (RFC 2156), with `high`, `normal` or `low`.
+//!
+//! Both are written when sending an email that isn't of normal priority, as
+//! clients usually only read one of them.
+
+/// Priority of an email
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// Urgent email, marked in the list and in the viewer
+    High,
+    /// Less important email
+    Low,
+    /// Email without any priority header
+    #[default]
+    Normal,
+}
+
+impl Priority {
+    /// Returns the headers announcing the priority, or none for a normal
+    /// priority
+    pub const fn as_headers(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::High =>
+                &[("X-Priority", "1 (Highest)"), ("Importance", "high")],
+            Self::Low => &[("X-Priority", "5 (Lowest)"), ("Importance", "low")],
+            Self::Normal => &[],
+        }
+    }
+
+    /// Returns the marker displayed before the subject, empty for a normal
+    /// priority
+    pub const fn as_marker(self) -> &'static str {
+        match self {
+            Self::High => "!",
+            Self::Low => "\u{2193}",
+            Self::Normal => "",
+        }
+    }
+
+    /// Returns the name of the priority, as given on the command line
+    pub const fn as_name(self) -> &'static str {
+        match self {
+            Self::High => "high",
+            Self::Low => "low",
+            Self::Normal => "normal",
+        }
+    }
+
+    /// Reads the priority from the `X-Priority` and `Importance` headers
+    ///
+    /// `X-Priority` takes precedence, and unknown values are ignored.
+    pub fn from_headers(
+        x_priority: Option<&str>,
+        importance: Option<&str>,
+    ) -> Self {
+        let from_x_priority = x_priority.and_then(|value| {
+            match value.trim_start().chars().next()? {
+                '1' | '2' => Some(Self::High),
+                '3' => Some(Self::Normal),
+                '4' | '5' => Some(Self::Low),
+                _ => None,
+            }
+        });
+        from_x_priority
+            .or_else(|| Self::parse(importance?.trim()))
+            .unwrap_or_default()
+    }
+
+    /// Returns the next priority, cycling from normal to high and low.
+    pub const fn next(self) -> Self {
+        match self {
+            Self::High => Self::Low,
+            Self::Low => Self::Normal,
+            Self::Normal => Self::High,
+        }
+    }
+
+    /// Parses the name of a priority, ignoring the case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "high" => Some(Self::High),
+            "low" => Some(Self::Low),
+            "normal" => Some(Self::Normal),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::priority::Priority;
+
+    #[test]
+    fn read_priority() {
+        assert_eq!(
+            Priority::from_headers(Some("1 (Highest)"), None),
+            Priority::High
+        );
+        assert_eq!(Priority::from_headers(None, Some("Low")), Priority::Low);
+        assert_eq!(
+            Priority::from_headers(Some("3"), Some("high")),
+            Priority::Normal
+        );
+        assert_eq!(
+            Priority::from_headers(Some("urgent"), Some("high")),
+            Priority::High
+        );
+        assert_eq!(Priority::from_headers(None, None), Priority::Normal);
+    }
+}
@@ -0,0 +1,392 @@
+//! Handles the SMTP connections.
+//!
+//! SMTP is the protocol responsible for sending emails. This will allow
+//! [`mailbox`](crate) to send the emails written by the user.
+//!
+//! A session can send several messages, e.g. when the outbox is flushed. When
+//! the server advertises `PIPELINING` (RFC 2920), the commands of the envelope
+//! of a message are sent at once instead of waiting for every reply, and a
+//! session unused for [`IDLE_TIMEOUT`] is considered closed by the server.
+//!
+//! Every pipelined reply is read, even after a refused command, so that the
+//! replies of the next message aren't mixed up with them. A failed transaction
+//! is then reset with `RSET`, or the session is dropped if the server accepted
+//! `DATA` and waits for a message.
+
+use core::iter;
+use core::time::Duration;
+use std::io::{self, BufRead as _, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Instant;
+
+use native_tls::{HandshakeError, TlsConnector, TlsStream};
+
+use super::dsn::Dsn;
+use super::encoding::{encode_base64, has_controls};
+use crate::credentials::Credentials;
+use crate::errors::Result;
+
+/// Duration after which an unused session must be opened again
+pub const IDLE_TIMEOUT: Duration = Duration::from_mins(1);
+/// First digit of the replies of a successfully completed command.
+const POSITIVE_COMPLETION: u8 = b'2';
+/// First digit of the replies of a command waiting for more data.
+const POSITIVE_INTERMEDIATE: u8 = b'3';
+
+/// Errors that may occur while interaction in `SMTP`.
+#[derive(Debug)]
+pub enum Error {
+    /// The server closed the connection.
+    ConnectionClosed,
+    /// An address of the envelope contains control characters, which would
+    /// end the SMTP command.
+    InvalidAddress(String),
+    /// Failed to read from or write to the SMTP server.
+    Io(io::Error),
+    /// Failed to establish `TLS` connection.
+    TlsConnection(native_tls::Error),
+    /// Failed to negotiate `TLS` with the SMTP server.
+    TlsHandshake(HandshakeError<TcpStream>),
+    /// The server refused the command, with the given reply.
+    UnexpectedReply(String),
+}
+
+impl Error {
+    /// Checks if the server refused the command for good, with a `5xx` reply
+    /// (RFC 5321 section 4.2.1), or if the command couldn't be sent at all,
+    /// in which case sending the message again can't succeed.
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            Self::InvalidAddress(_) => true,
+            Self::UnexpectedReply(reply) => reply.starts_with('5'),
+            Self::ConnectionClosed
+            | Self::Io(_)
+            | Self::TlsConnection(_)
+            | Self::TlsHandshake(_) => false,
+        }
+    }
+}
+
+/// Represents the Smtp session to communicate with the server.
+///
+/// The stream is a `TLS` connection, except in the tests.
+pub struct SmtpSession<S = TlsStream<TcpStream>> {
+    /// Indicates whether the session can't be used anymore, because a
+    /// transaction failed and couldn't be reset
+    broken: bool,
+    /// Extensions advertised by the server, in uppercase, e.g. `DSN`
+    extensions: Vec<String>,
+    /// Time at which the session was last used
+    last_used: Instant,
+    /// Active connection, over implicit `TLS`
+    stream: BufReader<S>,
+}
+
+impl SmtpSession {
+    /// Creates a new [`SmtpSession`] with the given [`Credentials`].
+    ///
+    /// The user is authenticated with the `PLAIN` mechanism.
+    pub fn with_credentials(credentials: &Credentials) -> Result<Self> {
+        Self::with_route(credentials, credentials.as_smtp_socket_address())
+    }
+
+    /// Creates a new [`SmtpSession`] on the given SMTP server, e.g. the one
+    /// of an identity, with the given [`Credentials`].
+    pub fn with_route(
+        credentials: &Credentials,
+        (domain_name, port): (&str, u16),
+    ) -> Result<Self> {
+        let tls_connector =
+            TlsConnector::new().map_err(Error::TlsConnection)?;
+        let tcp_stream =
+            TcpStream::connect((domain_name, port)).map_err(Error::Io)?;
+        let tls_stream = tls_connector
+            .connect(domain_name, tcp_stream)
+            .map_err(Error::TlsHandshake)?;
+        Self::with_stream(credentials, tls_stream)
+    }
+}
+
+impl<S: Read + Write> SmtpSession<S> {
+    /// Checks if the server advertised an extension
+    fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    /// Checks if the session can send another message
+    ///
+    /// It can't if it wasn't used for [`IDLE_TIMEOUT`], in which case the
+    /// server may have closed it, or if a failed transaction couldn't be
+    /// reset.
+    pub fn is_reusable(&self) -> bool {
+        !self.broken && self.last_used.elapsed() < IDLE_TIMEOUT
+    }
+
+    /// Closes the session.
+    pub fn quit(mut self) -> Result {
+        self.command("QUIT", POSITIVE_COMPLETION)?;
+        Ok(())
+    }
+
+    /// Sends a message to the given recipients.
+    ///
+    /// The `from` address is the envelope sender, to which bounces are sent.
+    /// The delivery status notifications are requested only if the server
+    /// supports them.
+    ///
+    /// If the server refuses a command, the transaction is reset with `RSET`
+    /// so that the session can send the next message, or the session is
+    /// marked as not reusable if it can't be reset.
+    pub fn send(
+        &mut self,
+        from: &str,
+        recipients: &[String],
+        message: &[u8],
+        dsn: Dsn,
+    ) -> Result {
+        if let Some(address) = iter::once(from)
+            .chain(recipients.iter().map(String::as_str))
+            .find(|address| has_controls(address))
+        {
+            return Err(Error::InvalidAddress(address.to_owned()).into());
+        }
+        self.last_used = Instant::now();
+        let result = self.transaction(from, recipients, message, dsn);
+        if result.is_err()
+            && !self.broken
+            && self.command("RSET", POSITIVE_COMPLETION).is_err()
+        {
+            self.broken = true;
+        }
+        result
+    }
+
+    /// Sends the envelope and the content of a message
+    fn transaction(
+        &mut self,
+        from: &str,
+        recipients: &[String],
+        message: &[u8],
+        dsn: Dsn,
+    ) -> Result {
+        let requested = if self.has_extension("DSN") {
+            dsn
+        } else {
+            Dsn::default()
+        };
+        let rcpt_parameter = requested.to_rcpt_parameter();
+        let mut envelope = vec![(
+            format!(
+                "MAIL FROM:<{from}>{}",
+                requested.to_mail_parameters(message)
+            ),
+            POSITIVE_COMPLETION,
+        )];
+        envelope.extend(recipients.iter().map(|recipient| {
+            (
+                format!("RCPT TO:<{recipient}>{rcpt_parameter}"),
+                POSITIVE_COMPLETION,
+            )
+        }));
+        envelope.push(("DATA".to_owned(), POSITIVE_INTERMEDIATE));
+        if self.has_extension("PIPELINING") {
+            let commands = envelope
+                .iter()
+                .flat_map(|(command, _)| [command.as_str(), "\r\n"])
+                .collect::<String>();
+            self.stream
+                .get_mut()
+                .write_all(commands.as_bytes())
+                .map_err(Error::Io)?;
+            // Every reply is read before failing, not to take the remaining
+            // ones as the replies of the next commands.
+            let replies = envelope
+                .iter()
+                .map(|(_, expected)| Ok((self.read_any_reply()?, *expected)))
+                .collect::<Result<Vec<_>>>()?;
+            if let Some((reply, _)) = replies
+                .iter()
+                .find(|(reply, expected)| !has_code(reply, *expected))
+            {
+                // The server may accept `DATA` even if it refused a recipient,
+                // and then takes every line as the content of the message.
+                self.broken = replies
+                    .last()
+                    .is_some_and(|(data, expected)| has_code(data, *expected));
+                return Err(Error::UnexpectedReply(reply.clone()).into());
+            }
+        } else {
+            for (command, expected) in envelope {
+                self.command(&command, expected)?;
+            }
+        }
+        self.stream
+            .get_mut()
+            .write_all(&to_smtp_data(message))
+            .map_err(Error::Io)?;
+        self.read_reply(POSITIVE_COMPLETION)?;
+        Ok(())
+    }
+
+    /// Opens a session on a connected stream, and authenticates the user
+    /// with the `PLAIN` mechanism.
+    pub fn with_stream(credentials: &Credentials, stream: S) -> Result<Self> {
+        let mut session = Self {
+            broken: false,
+            extensions: vec![],
+            last_used: Instant::now(),
+            stream: BufReader::new(stream),
+        };
+        session.read_reply(POSITIVE_COMPLETION)?;
+        // Every line of the reply to `EHLO` but the first one is an extension
+        // supported by the server, after the reply code.
+        session.extensions = session
+            .command("EHLO localhost", POSITIVE_COMPLETION)?
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                Some(line.get(4..)?.split_whitespace().next()?.to_uppercase())
+            })
+            .collect();
+
+        let token = format!(
+            "\0{}\0{}",
+            credentials.as_email(),
+            credentials.as_password()
+        );
+        session.command(
+            &format!("AUTH PLAIN {}", encode_base64(token.as_bytes())),
+            POSITIVE_COMPLETION,
+        )?;
+
+        Ok(session)
+    }
+
+    /// Sends a command and reads the reply of the server.
+    fn command(&mut self, command: &str, expected: u8) -> Result<String> {
+        self.stream
+            .get_mut()
+            .write_all(format!("{command}\r\n").as_bytes())
+            .map_err(Error::Io)?;
+        self.read_reply(expected)
+    }
+
+    /// Reads a reply of the server, that may span on multiple lines.
+    ///
+    /// Returns an error if the reply code doesn't start with `expected`.
+    fn read_reply(&mut self, expected: u8) -> Result<String> {
+        let reply = self.read_any_reply()?;
+        if has_code(&reply, expected) {
+            Ok(reply)
+        } else {
+            Err(Error::UnexpectedReply(reply).into())
+        }
+    }
+
+    /// Reads a reply of the server, that may span on multiple lines, whatever
+    /// its code
+    fn read_any_reply(&mut self) -> Result<String> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).map_err(Error::Io)? == 0 {
+                return Err(Error::ConnectionClosed.into());
+            }
+            reply.push_str(&line);
+            // The last line of a reply has a space after the code, the others
+            // have a dash.
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+        }
+
+        Ok(reply)
+    }
+}
+
+/// Checks if the code of a reply starts with the `expected` digit
+fn has_code(reply: &str, expected: u8) -> bool {
+    reply.as_bytes().first() == Some(&expected)
+}
+
+/// Formats a message to be sent after the `DATA` command
+///
+/// Line breaks are normalised to CRLF, lines starting with a dot are escaped
+/// and the terminating `.` line is added.
+fn to_smtp_data(message: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(message.len());
+    let content = message.strip_suffix(b"\n").unwrap_or(message);
+
+    for line in content.split(|byte| *byte == b'\n') {
+        let without_cr = line.strip_suffix(b"\r").unwrap_or(line);
+        if without_cr.first() == Some(&b'.') {
+            data.push(b'.');
+        }
+        data.extend_from_slice(without_cr);
+        data.extend_from_slice(b"\r\n");
+    }
+
+    data.extend_from_slice(b".\r\n");
+    data
+}
+
+#[cfg(test)]
+mod test {
+    #![expect(clippy::unwrap_used, reason = "test")]
+
+    use crate::send::dsn::Dsn;
+    use crate::test_utils::ScriptedServer;
+
+    /// Replies to the greeting, `EHLO` and `AUTH`, with pipelining
+    const OPENING: [&str; 4] =
+        ["220 ready", "250-example.com", "250 PIPELINING", "235 ok"];
+
+    #[test]
+    fn pipelined_refusal() {
+        let mut server = ScriptedServer::new(
+            &[
+                OPENING.as_slice(),
+                &["250 ok", "550 no such user", "250 ok", "554 no recipients"],
+                &["250 reset", "250 ok", "250 ok", "354 go on", "250 queued"],
+            ]
+            .concat(),
+        );
+        let mut session = server.open_smtp();
+        let recipients =
+            ["eve@example.com".to_owned(), "al@example.com".to_owned()];
+        let err = session
+            .send("bob@example.com", &recipients, b"Hello", Dsn::default())
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("550 no such user"));
+        assert!(session.is_reusable());
+        session
+            .send("bob@example.com", &recipients[1..], b"Hello", Dsn::default())
+            .unwrap();
+        assert_eq!(
+            server.to_received_from("MAIL"),
+            "MAIL FROM:<bob@example.com>\r\nRCPT TO:<eve@example.com>\r\nRCPT \
+             TO:<al@example.com>\r\nDATA\r\nRSET\r\nMAIL \
+             FROM:<bob@example.com>\r\nRCPT \
+             TO:<al@example.com>\r\nDATA\r\nHello\r\n.\r\n"
+        );
+    }
+
+    #[test]
+    fn pipelined_data_accepted() {
+        let mut server = ScriptedServer::new(
+            &[
+                OPENING.as_slice(),
+                &["250 ok", "550 no such user", "250 ok", "354 go on"],
+            ]
+            .concat(),
+        );
+        let mut session = server.open_smtp();
+        let recipients =
+            ["eve@example.com".to_owned(), "al@example.com".to_owned()];
+        session
+            .send("bob@example.com", &recipients, b"Hello", Dsn::default())
+            .unwrap_err();
+        assert!(!session.is_reusable());
+        assert!(server.to_received_from("RSET").is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Requests delivery status notifications (RFC 3461) from the relay.
+//!
+//! When the SMTP server supports the `DSN` extension, it sends a notification
+//! back to the sender when an email is delivered (`success`), can't be
+//! delivered (`failure`) or is delayed (`delay`), for the events requested
+//! with the `NOTIFY` parameter of every recipient. Only the headers of the
+//! email are returned in the notification (`RET=HDRS`), and its `Message-ID`
+//! is given as the envelope id (`ENVID`), to match the notification with the
+//! sent email.
+//!
+//! The events are written as a comma-separated list, e.g. `failure,delay`, in
+//! the `DSN_NOTIFY` variable of the [`Config`](crate::config::Config), which
+//! the writer can change for every email. They are silently not requested if
+//! the server doesn't support the extension, not to block the sending.
+
+/// Events for which a delivery status notification is requested
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dsn {
+    /// Notify if the delivery is delayed
+    delay: bool,
+    /// Notify if the email couldn't be delivered
+    failure: bool,
+    /// Notify once the email was delivered
+    success: bool,
+}
+
+impl Dsn {
+    /// Checks if a notification is requested for any event
+    pub const fn is_requested(self) -> bool {
+        self.delay || self.failure || self.success
+    }
+
+    /// Returns the next set of events, cycling from none to failures, delays
+    /// and every event.
+    pub const fn next(self) -> Self {
+        match (self.success, self.delay, self.failure) {
+            (false, false, false) =>
+                Self { delay: false, failure: true, success: false },
+            (false, false, true) =>
+                Self { delay: true, failure: true, success: false },
+            (false, true, _) =>
+                Self { delay: true, failure: true, success: true },
+            (true, _, _) =>
+                Self { delay: false, failure: false, success: false },
+        }
+    }
+
+    /// Parses a comma-separated list of events
+    ///
+    /// The case is ignored, as well as the unknown events.
+    pub fn parse(list: &str) -> Self {
+        let mut dsn = Self::default();
+        for event in list.split(',') {
+            match event.trim().to_lowercase().as_str() {
+                "delay" => dsn.delay = true,
+                "failure" => dsn.failure = true,
+                "success" => dsn.success = true,
+                _ => (),
+            }
+        }
+        dsn
+    }
+
+    /// Returns the parameters of the `MAIL FROM` command for a message
+    ///
+    /// This is empty if no notification is requested.
+    pub fn to_mail_parameters(self, message: &[u8]) -> String {
+        if !self.is_requested() {
+            return String::new();
+        }
+        to_envid(message).map_or_else(
+            || " RET=HDRS".to_owned(),
+            |envid| format!(" RET=HDRS ENVID={envid}"),
+        )
+    }
+
+    /// Returns the comma-separated list of the requested events, as parsed by
+    /// [`Dsn::parse`]
+    pub fn to_names(self) -> String {
+        self.to_events().join(",")
+    }
+
+    /// Returns the parameter of the `RCPT TO` commands
+    ///
+    /// This is empty if no notification is requested.
+    pub fn to_rcpt_parameter(self) -> String {
+        if !self.is_requested() {
+            return String::new();
+        }
+        format!(" NOTIFY={}", self.to_events().join(",").to_uppercase())
+    }
+
+    /// Returns the names of the requested events
+    fn to_events(self) -> Vec<&'static str> {
+        [
+            (self.success, "success"),
+            (self.failure, "failure"),
+            (self.delay, "delay"),
+        ]
+        .into_iter()
+        .filter_map(|(requested, name)| requested.then_some(name))
+        .collect()
+    }
+}
+
+/// Returns the `Message-ID` of a message, encoded as `xtext` to be used as the
+/// envelope id
+fn to_envid(message: &[u8]) -> Option<String> {
+    let content = String::from_utf8_lossy(message);
+    let value = content
+        .lines()
+        .take_while(|line| !line.trim_end().is_empty())
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("Message-ID").then_some(value)
+        })?;
+    let id = value.trim().trim_start_matches('<').trim_end_matches('>');
+    if id.is_empty() {
+        return None;
+    }
+    Some(
+        id.bytes()
+            .map(|byte| {
+                if (b'!'..=b'~').contains(&byte) && byte != b'+' && byte != b'='
+                {
+                    char::from(byte).to_string()
+                } else {
+                    format!("+{byte:02X}")
+                }
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::send::dsn::Dsn;
+
+    #[test]
+    fn dsn() {
+        let dsn = Dsn::parse("Failure, delay,unknown");
+        assert_eq!(dsn.to_names(), "failure,delay");
+        assert_eq!(dsn.to_rcpt_parameter(), " NOTIFY=FAILURE,DELAY");
+        assert_eq!(
+            dsn.to_mail_parameters(
+                b"From: a@x\r\nMessage-ID: <1.a+b=c@x>\r\n\r\nMessage-ID: <no>"
+            ),
+            " RET=HDRS ENVID=1.a+2Bb+3Dc@x"
+        );
+        assert_eq!(dsn.to_mail_parameters(b"Subject: hi\n\nBody"), " RET=HDRS");
+
+        let none = Dsn::default();
+        assert!(!none.is_requested());
+        assert_eq!(none.to_mail_parameters(b"Message-ID: <1@x>"), "");
+        assert_eq!(none.to_rcpt_parameter(), "");
+        assert_eq!(none.next().to_names(), "failure");
+        assert_eq!(
+            none.next().next().next().to_names(),
+            "success,failure,delay"
+        );
+        assert_eq!(none.next().next().next().next(), none);
+    }
+}
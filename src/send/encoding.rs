@@ -0,0 +1,181 @@
+//! Encodes the content of the emails to send them over SMTP.
+//!
+//! SMTP only guarantees the transfer of short 7-bit lines, so the bodies and
+//! non-ASCII headers must be encoded.
+//!
+//! The header values may come from untrusted sources, e.g. the CSV file of
+//! `mailbox merge`, so their control characters are replaced with spaces: a
+//! line break would otherwise end the header and start a new one.
+
+/// Characters of the base64 alphabet, indexed by their value.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Maximum length of a quoted-printable line, without the line break.
+const QUOTED_PRINTABLE_LINE_LENGTH: usize = 76;
+/// Maximum number of bytes encoded in an encoded-word, so that it isn't
+/// longer than the 75 characters allowed by RFC 2047, with its 12 characters
+/// of delimiters
+const ENCODED_WORD_BYTES: usize = 45;
+
+/// Encodes bytes in base64 (see RFC 4648), without line breaks.
+#[expect(
+    clippy::indexing_slicing,
+    clippy::arithmetic_side_effects,
+    reason = "indexes are 6-bit values"
+)]
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let first = chunk.first().copied().unwrap_or_default();
+        let second = chunk.get(1).copied().unwrap_or_default();
+        let third = chunk.get(2).copied().unwrap_or_default();
+
+        let sextets = [
+            first.wrapping_shr(2),
+            (first & 0b11).wrapping_shl(4) | second.wrapping_shr(4),
+            (second & 0b1111).wrapping_shl(2) | third.wrapping_shr(6),
+            third & 0b11_1111,
+        ];
+
+        for (idx, sextet) in sextets.iter().enumerate() {
+            if idx <= chunk.len() {
+                encoded.push(char::from(BASE64_ALPHABET[usize::from(*sextet)]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Encodes a header value with RFC 2047 encoded-words if it isn't ASCII.
+///
+/// The control characters are replaced with spaces. The value is split in
+/// several encoded-words if it is too long for one, on separate folded lines.
+pub fn encode_header(value: &str) -> String {
+    let sanitized = strip_controls(value);
+    if sanitized.is_ascii() {
+        return sanitized;
+    }
+    let mut words = vec![];
+    let mut chunk = String::new();
+    for ch in sanitized.chars() {
+        if chunk.len().saturating_add(ch.len_utf8()) > ENCODED_WORD_BYTES {
+            words.push(encode_word(&chunk));
+            chunk.clear();
+        }
+        chunk.push(ch);
+    }
+    words.push(encode_word(&chunk));
+    words.join("\r\n ")
+}
+
+/// Encodes a text in a single RFC 2047 encoded-word
+fn encode_word(text: &str) -> String {
+    format!("=?UTF-8?B?{}?=", encode_base64(text.as_bytes()))
+}
+
+/// Checks if a value contains control characters, e.g. line breaks
+pub fn has_controls(value: &str) -> bool {
+    value.chars().any(char::is_control)
+}
+
+/// Replaces the control characters of a header value, e.g. line breaks, with
+/// spaces
+pub fn strip_controls(value: &str) -> String {
+    value
+        .chars()
+        .map(|ch| if ch.is_control() { ' ' } else { ch })
+        .collect()
+}
+
+/// Encodes a text body in quoted-printable (see RFC 2045), with CRLF line
+/// breaks.
+pub fn encode_quoted_printable(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+
+    for line in text.lines() {
+        let mut line_length: usize = 0;
+        let mut bytes = line.bytes().peekable();
+
+        while let Some(byte) = bytes.next() {
+            let is_last = bytes.peek().is_none();
+            let is_whitespace = byte == b' ' || byte == b'\t';
+            let chunk = if (byte.is_ascii_graphic() && byte != b'=')
+                || (is_whitespace && !is_last)
+            {
+                char::from(byte).to_string()
+            } else {
+                format!("={byte:02X}")
+            };
+
+            // Keep room for the `=` of the soft line break.
+            if line_length.saturating_add(chunk.len())
+                >= QUOTED_PRINTABLE_LINE_LENGTH
+            {
+                encoded.push_str("=\r\n");
+                line_length = 0;
+            }
+            line_length = line_length.saturating_add(chunk.len());
+            encoded.push_str(&chunk);
+        }
+
+        encoded.push_str("\r\n");
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use crate::send::encoding::{
+        encode_base64, encode_header, encode_quoted_printable, has_controls, strip_controls
+    };
+
+    #[test]
+    fn base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(b"\0bob\0pass"), "AGJvYgBwYXNz");
+    }
+
+    #[test]
+    #[expect(clippy::non_ascii_literal, reason = "test")]
+    fn headers() {
+        assert_eq!(encode_header("Hello"), "Hello");
+        assert_eq!(encode_header("Café"), "=?UTF-8?B?Q2Fmw6k=?=");
+        assert_eq!(
+            encode_header("Hi\r\nBcc: eve@example.com"),
+            "Hi  Bcc: eve@example.com"
+        );
+        assert_eq!(encode_header("Caf\u{e9}\nX"), "=?UTF-8?B?Q2Fmw6kgWA==?=");
+        let long = encode_header(&"\u{e9}".repeat(40));
+        let words = long.split("\r\n ").collect::<Vec<_>>();
+        assert_eq!(words.len(), 2);
+        assert!(words.iter().all(|word| word.len() <= 75));
+        assert_eq!(strip_controls("a\tb\0c"), "a b c");
+        assert!(has_controls("bob@example.com\r\nRCPT TO:<eve@example.com>"));
+        assert!(!has_controls("Bob <bob@example.com>"));
+    }
+
+    #[test]
+    #[expect(clippy::non_ascii_literal, reason = "test")]
+    fn quoted_printable() {
+        assert_eq!(
+            encode_quoted_printable("a=b \nc\t"),
+            "a=3Db=20\r\nc=09\r\n"
+        );
+        assert_eq!(encode_quoted_printable("é"), "=C3=A9\r\n");
+        let long = "x".repeat(80);
+        assert_eq!(
+            encode_quoted_printable(&long),
+            format!("{}=\r\n{}\r\n", "x".repeat(75), "x".repeat(5))
+        );
+    }
+}
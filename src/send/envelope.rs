@@ -0,0 +1,88 @@
+//! Chooses the envelope sender of the sent emails, to which bounces are sent.
+//!
+//! The envelope sender (`MAIL FROM`, which becomes the `Return-Path`) is the
+//! address of the identity by default. The `RETURN_PATH` variable of an
+//! account replaces it for every identity of the account, e.g. to collect the
+//! bounces in a dedicated mailbox, or to pass the checks of a forwarding
+//! server:
+//!
+//! ```env
+//! RETURN_PATH=bounces@example.com
+//! WORK_RETURN_PATH=bounces+%r@work.example.com
+//! ```
+//!
+//! A `%r` in the return path is replaced with the recipient, with its `@`
+//! written `=`, as done by VERP (variable envelope return path): the bounce of
+//! `alice@example.org` is then sent to `bounces+alice=example.org@...`, which
+//! tells which recipient bounced even if the bounce is unreadable. The message
+//! is then sent once per recipient.
+
+/// Placeholder of the return path replaced with the recipient
+const RECIPIENT_PLACEHOLDER: &str = "%r";
+
+/// Groups the recipients of a message by envelope sender
+///
+/// Without a return path, every recipient is sent the message from the
+/// `from` address.
+pub fn group_recipients(
+    return_path: Option<&str>,
+    from: &str,
+    recipients: &[String],
+) -> Vec<(String, Vec<String>)> {
+    match return_path {
+        Some(verp) if verp.contains(RECIPIENT_PLACEHOLDER) => recipients
+            .iter()
+            .map(|recipient| {
+                (
+                    verp.replace(
+                        RECIPIENT_PLACEHOLDER,
+                        &recipient.replace('@', "="),
+                    ),
+                    vec![recipient.clone()],
+                )
+            })
+            .collect(),
+        Some(sender) => vec![(sender.to_owned(), recipients.to_vec())],
+        None => vec![(from.to_owned(), recipients.to_vec())],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::send::envelope::group_recipients;
+
+    #[test]
+    fn groups() {
+        let recipients =
+            vec!["alice@example.org".to_owned(), "eve@example.net".to_owned()];
+        assert_eq!(
+            group_recipients(None, "bob@example.com", &recipients),
+            [("bob@example.com".to_owned(), recipients.clone())]
+        );
+        assert_eq!(
+            group_recipients(
+                Some("bounces@example.com"),
+                "bob@example.com",
+                &recipients
+            ),
+            [("bounces@example.com".to_owned(), recipients.clone())]
+        );
+        assert_eq!(
+            group_recipients(
+                Some("bounces+%r@example.com"),
+                "bob@example.com",
+                &recipients
+            ),
+            [
+                (
+                    "bounces+alice=example.org@example.com".to_owned(),
+                    vec!["alice@example.org".to_owned()]
+                ),
+                (
+                    "bounces+eve=example.net@example.com".to_owned(),
+                    vec!["eve@example.net".to_owned()]
+                )
+            ]
+        );
+    }
+}
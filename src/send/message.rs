@@ -0,0 +1,527 @@
+//! Builds the RFC 5322 messages to send over SMTP.
+
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local};
+
+use super::encoding::{
+    encode_base64, encode_header, encode_quoted_printable, strip_controls
+};
+use super::wrap::to_flowed;
+use crate::identity::Identity;
+use crate::priority::Priority;
+
+/// Maximum length of a line of base64 content, without the line break.
+const BASE64_LINE_LENGTH: usize = 76;
+
+/// Content types of the attached files, from their extensions
+///
+/// The files with other extensions are sent as `application/octet-stream`.
+const CONTENT_TYPES: [(&str, &str); 24] = [
+    ("csv", "text/csv"),
+    ("doc", "application/msword"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ics", "text/calendar"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("json", "application/json"),
+    ("md", "text/markdown"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("patch", "text/x-diff"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("txt", "text/plain"),
+    ("webp", "image/webp"),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    ("xml", "application/xml"),
+    ("zip", "application/zip"),
+];
+
+/// Email to be sent
+pub struct OutgoingEmail {
+    /// Files attached to the email, with their names and contents
+    attachments: Vec<(String, Vec<u8>)>,
+    /// Addresses of the blind copies, only given to the SMTP server, as they
+    /// must not appear in the headers
+    bcc: Vec<String>,
+    /// Plain text body of the email
+    body: String,
+    /// Addresses of the copies
+    cc: Vec<String>,
+    /// Column the body is wrapped at, if it is sent as `format=flowed`
+    flowed: Option<usize>,
+    /// Identity of the sender
+    from: Identity,
+    /// Additional headers, e.g. `X-Mailer`
+    headers: Vec<(String, String)>,
+    /// Priority of the email
+    priority: Priority,
+    /// Whether a read receipt is requested from the recipients
+    read_receipt: bool,
+    /// Address the replies must be sent to, if it isn't the sender
+    reply_to: Option<String>,
+    /// Subject of the email
+    subject: String,
+    /// Addresses of the destinations
+    to: Vec<String>,
+}
+
+impl OutgoingEmail {
+    /// Returns the files attached to the email, with their names and
+    /// contents
+    pub fn as_attachments(&self) -> &[(String, Vec<u8>)] {
+        &self.attachments
+    }
+
+    /// Returns the addresses of the blind copies
+    pub fn as_bcc(&self) -> &[String] {
+        &self.bcc
+    }
+
+    /// Returns the address of the sender
+    pub fn as_from(&self) -> &str {
+        self.from.as_address()
+    }
+
+    /// Returns the addresses the email must be delivered to, i.e. the
+    /// destinations, the copies and the blind copies
+    pub fn to_recipients(&self) -> Vec<String> {
+        self.to
+            .iter()
+            .chain(&self.cc)
+            .chain(&self.bcc)
+            .cloned()
+            .collect()
+    }
+
+    /// Creates a new [`OutgoingEmail`] with an empty subject and body.
+    pub const fn new(from: Identity, to: Vec<String>) -> Self {
+        Self {
+            attachments: vec![],
+            bcc: vec![],
+            body: String::new(),
+            cc: vec![],
+            flowed: None,
+            from,
+            headers: vec![],
+            priority: Priority::Normal,
+            read_receipt: false,
+            reply_to: None,
+            subject: String::new(),
+            to,
+        }
+    }
+
+    /// Formats the email as an RFC 5322 message, ready to be sent.
+    pub fn to_rfc5322(&self) -> String {
+        let now = SystemTime::now();
+        let (content_headers, content) = self.to_content(now);
+        let mut headers = vec![
+            ("Date".to_owned(), format_date(now)),
+            ("From".to_owned(), strip_controls(&self.from.to_from_header())),
+            ("To".to_owned(), strip_controls(&self.to.join(", "))),
+            ("Subject".to_owned(), encode_header(&self.subject)),
+            ("Message-ID".to_owned(), self.generate_message_id(now)),
+            ("MIME-Version".to_owned(), "1.0".to_owned()),
+        ];
+        if !self.cc.is_empty() {
+            headers.insert(
+                3,
+                ("Cc".to_owned(), strip_controls(&self.cc.join(", "))),
+            );
+        }
+        headers.extend(content_headers);
+        if let Some(reply_to) = &self.reply_to {
+            headers.push(("Reply-To".to_owned(), strip_controls(reply_to)));
+        }
+        if self.read_receipt {
+            headers.push((
+                "Disposition-Notification-To".to_owned(),
+                strip_controls(&self.from.to_from_header()),
+            ));
+        }
+        headers.extend(self.headers.iter().map(|(name, value)| {
+            (
+                strip_controls(name).replace([' ', ':'], "-"),
+                encode_header(value),
+            )
+        }));
+        headers.extend(
+            self.priority
+                .as_headers()
+                .iter()
+                .map(|(name, value)| ((*name).to_owned(), (*value).to_owned())),
+        );
+
+        let header_lines = headers
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+
+        format!("{header_lines}\r\n\r\n{content}")
+    }
+
+    /// Attaches a file to the email
+    pub fn with_attachment(mut self, name: String, data: Vec<u8>) -> Self {
+        self.attachments.push((name, data));
+        self
+    }
+
+    /// Returns the text part of the email, as sent before being encoded
+    ///
+    /// The body is wrapped if it is sent as `format=flowed`.
+    pub fn to_sent_body(&self) -> String {
+        self.flowed.map_or_else(
+            || self.body.clone(),
+            |column| to_flowed(&self.body, column),
+        )
+    }
+
+    /// Sets the plain text body of the email
+    pub fn with_body(mut self, body: String) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Sends the body as `format=flowed`, wrapped at the given column
+    pub const fn with_flowed(mut self, column: usize) -> Self {
+        self.flowed = Some(column);
+        self
+    }
+
+    /// Adds a header to the email, e.g. `X-Mailer`
+    pub fn with_header(mut self, name: String, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Sets the priority of the email
+    pub const fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Requests a read receipt from the recipients, sent to the sender.
+    pub const fn with_read_receipt(mut self) -> Self {
+        self.read_receipt = true;
+        self
+    }
+
+    /// Sets the addresses of the blind copies
+    pub fn with_bcc(mut self, bcc: Vec<String>) -> Self {
+        self.bcc = bcc;
+        self
+    }
+
+    /// Sets the addresses of the copies
+    pub fn with_cc(mut self, cc: Vec<String>) -> Self {
+        self.cc = cc;
+        self
+    }
+
+    /// Sets the address the replies must be sent to
+    pub fn with_reply_to(mut self, reply_to: String) -> Self {
+        self.reply_to = Some(reply_to);
+        self
+    }
+
+    /// Sets the subject of the email
+    pub fn with_subject(mut self, subject: String) -> Self {
+        self.subject = subject;
+        self
+    }
+
+    /// Generates a unique `Message-ID`, on the domain of the sender
+    fn generate_message_id(&self, now: SystemTime) -> String {
+        to_message_id(self.as_from(), now)
+    }
+
+    /// Returns the headers describing the content of the email, with the
+    /// encoded content
+    ///
+    /// The email is `multipart/mixed` if files are attached, with the text
+    /// first.
+    fn to_content(&self, now: SystemTime) -> (Vec<(String, String)>, String) {
+        let content_type = if self.flowed.is_some() {
+            "text/plain; charset=utf-8; format=flowed"
+        } else {
+            "text/plain; charset=utf-8"
+        };
+        let text = encode_quoted_printable(&self.to_sent_body());
+        if self.attachments.is_empty() {
+            return (
+                vec![
+                    ("Content-Type".to_owned(), content_type.to_owned()),
+                    (
+                        "Content-Transfer-Encoding".to_owned(),
+                        "quoted-printable".to_owned(),
+                    ),
+                ],
+                text,
+            );
+        }
+
+        let boundary = format!("mailbox-{}-{}", to_nanos(now), process::id());
+        let mut parts = vec![format!(
+            "Content-Type: {content_type}\r\nContent-Transfer-Encoding: \
+             quoted-printable\r\n\r\n{text}"
+        )];
+        parts.extend(self.attachments.iter().map(|(name, data)| {
+            let quoted = encode_header(&name.replace('"', "'"));
+            format!(
+                "Content-Type: {}; name=\"{quoted}\"\r\nContent-Disposition: attachment; \
+                 filename=\"{quoted}\"\r\nContent-Transfer-Encoding: \
+                 base64\r\n\r\n{}",
+                to_content_type(name),
+                encode_base64(data)
+                    .as_bytes()
+                    .chunks(BASE64_LINE_LENGTH)
+                    .map(String::from_utf8_lossy)
+                    .collect::<Vec<_>>()
+                    .join("\r\n")
+            )
+        }));
+        let content = parts
+            .iter()
+            .map(|part| format!("--{boundary}\r\n{part}\r\n"))
+            .chain([format!("--{boundary}--\r\n")])
+            .collect();
+        (
+            vec![(
+                "Content-Type".to_owned(),
+                format!("multipart/mixed; boundary=\"{boundary}\""),
+            )],
+            content,
+        )
+    }
+}
+
+/// Guesses the content type of an attached file from the extension of its
+/// name, e.g. `application/pdf` for `report.pdf`
+fn to_content_type(name: &str) -> &'static str {
+    name.rsplit_once('.')
+        .and_then(|(_, extension)| {
+            CONTENT_TYPES
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+        })
+        .map_or("application/octet-stream", |(_, content_type)| content_type)
+}
+
+/// Parses custom headers separated by semicolons, e.g.
+/// `X-Ticket: 42; X-Team: support`
+///
+/// Only the `X-` headers are kept, as the other ones are set by the app.
+pub fn parse_custom_headers(text: &str) -> Vec<(String, String)> {
+    text.split(';')
+        .filter_map(|header| {
+            let (name, value) = header.split_once(':')?;
+            let trimmed = name.trim();
+            let is_valid = trimmed.len() > 2
+                && trimmed
+                    .get(..2)
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case("x-"))
+                && trimmed
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '-');
+            is_valid.then(|| (trimmed.to_owned(), value.trim().to_owned()))
+        })
+        .collect()
+}
+
+/// Returns a received message, resent as is to new recipients
+///
+/// As defined by RFC 5322 section 3.6.6, the original headers, e.g. `From` and
+/// `Date`, are kept, and a block of `Resent-*` headers describing the
+/// resending is prepended to them.
+pub fn to_resent(
+    raw: &str,
+    from: &Identity,
+    to: &[String],
+    now: SystemTime,
+) -> String {
+    let resent_headers = [
+        ("Resent-Date", format_date(now)),
+        ("Resent-From", from.to_from_header()),
+        ("Resent-To", to.join(", ")),
+        ("Resent-Message-ID", to_message_id(from.as_address(), now)),
+    ]
+    .map(|(name, value)| format!("{name}: {value}\r\n"))
+    .concat();
+    format!("{resent_headers}{raw}")
+}
+
+/// Generates a unique `Message-ID`, on the domain of the given address
+fn to_message_id(address: &str, now: SystemTime) -> String {
+    let nanos = to_nanos(now);
+    let domain = address
+        .rsplit_once('@')
+        .map_or("localhost", |(_, domain)| domain.trim_end_matches('>'));
+    format!("<{nanos}.{}@{domain}>", process::id())
+}
+
+/// Returns the number of nanoseconds since the epoch, to generate unique ids
+fn to_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos())
+}
+
+/// Formats a date for the `Date` header, in the local time zone
+///
+/// The date is formatted as specified in RFC 5322, e.g.
+/// `Sat, 20 Nov 2021 23:22:01 +0100`.
+fn format_date(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).to_rfc2822()
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+    use std::time::UNIX_EPOCH;
+
+    use chrono::DateTime;
+
+    use crate::identity::Identity;
+    use crate::send::message::{
+        OutgoingEmail, format_date, parse_custom_headers, to_content_type, to_resent
+    };
+
+    #[test]
+    fn attachments() {
+        let plain = OutgoingEmail::new(
+            Identity::new("bob@example.com".to_owned(), None),
+            vec![],
+        )
+        .with_body("Hello".to_owned());
+        assert!(plain.to_rfc5322().contains("Content-Type: text/plain"));
+
+        let message = plain
+            .with_attachment("report.txt".to_owned(), b"report".to_vec())
+            .to_rfc5322();
+        assert!(message.contains("Content-Type: multipart/mixed; boundary="));
+        assert!(message.contains(
+            "Content-Type: text/plain; name=\"report.txt\"\r\n\
+             Content-Disposition: attachment; filename=\"report.txt\""
+        ));
+        assert!(message.contains("\r\n\r\ncmVwb3J0\r\n"));
+        assert!(message.contains("quoted-printable\r\n\r\nHello\r\n"));
+    }
+
+    #[test]
+    fn copies() {
+        let address = |name: &str| format!("{name}@example.com");
+        let email = OutgoingEmail::new(
+            Identity::new(address("bob"), None),
+            vec![address("alice")],
+        )
+        .with_cc(vec![address("carol"), address("dan")])
+        .with_bcc(vec![address("eve")]);
+        assert_eq!(
+            email.to_recipients(),
+            [
+                address("alice"),
+                address("carol"),
+                address("dan"),
+                address("eve")
+            ]
+        );
+        let message = email.to_rfc5322();
+        assert!(message.contains(
+            "\r\nTo: alice@example.com\r\nCc: carol@example.com, \
+             dan@example.com\r\nSubject: "
+        ));
+        assert!(!message.contains("eve@"));
+        assert!(!message.contains("Bcc"));
+
+        let injected = OutgoingEmail::new(
+            Identity::new(address("bob"), None),
+            vec![format!("{}\r\nBcc: {}", address("alice"), address("eve"))],
+        )
+        .with_subject("Hi\nBcc: eve@example.com".to_owned())
+        .with_header("X-Ticket".to_owned(), "42\r\nBcc: eve".to_owned())
+        .to_rfc5322();
+        assert!(!injected.contains("\nBcc"));
+        assert!(injected.contains("\r\nTo: alice@example.com  Bcc: "));
+    }
+
+    #[test]
+    fn content_types() {
+        assert_eq!(to_content_type("Report.PDF"), "application/pdf");
+        assert_eq!(to_content_type("photo.final.jpg"), "image/jpeg");
+        assert_eq!(to_content_type("Makefile"), "application/octet-stream");
+        assert_eq!(to_content_type("data.bin"), "application/octet-stream");
+    }
+
+    #[test]
+    fn custom_headers() {
+        assert_eq!(
+            parse_custom_headers(
+                "X-Ticket: 42; Subject: spam; X-: no;X-Team:a:b"
+            ),
+            [
+                ("X-Ticket".to_owned(), "42".to_owned()),
+                ("X-Team".to_owned(), "a:b".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn resent() {
+        let raw = "From: alice@example.com\r\nDate: Mon, 6 Jan 2025 10:00:00 \
+                   +0000\r\n\r\nHello\r\n";
+        let message = to_resent(
+            raw,
+            &Identity::new(
+                "bob@example.com".to_owned(),
+                Some("Bob".to_owned()),
+            ),
+            &["carol@example.com".to_owned(), "dan@example.com".to_owned()],
+            UNIX_EPOCH + Duration::from_secs(1_637_446_921),
+        );
+        let (date, headers) = message.split_once("\r\n").unwrap();
+        assert_eq!(
+            date.strip_prefix("Resent-Date: ")
+                .map(DateTime::parse_from_rfc2822),
+            Some(DateTime::parse_from_rfc2822(
+                "Sat, 20 Nov 2021 22:22:01 +0000"
+            ))
+        );
+        assert!(headers.starts_with(
+            "Resent-From: Bob <bob@example.com>\r\nResent-To: \
+             carol@example.com, dan@example.com\r\nResent-Message-ID: \
+             <1637446921000000000."
+        ));
+        assert!(
+            message.contains("@example.com>\r\nFrom: alice@example.com\r\n")
+        );
+        assert!(message.ends_with(raw));
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn dates() {
+        for secs in [0, 951_782_401, 1_637_446_921] {
+            let date = format_date(UNIX_EPOCH + Duration::from_secs(secs));
+            assert_eq!(
+                DateTime::parse_from_rfc2822(&date).unwrap().timestamp(),
+                i64::try_from(secs).unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Builds the emails and sends them to the server.
+
+pub mod connection;
+pub mod dsn;
+pub mod encoding;
+pub mod envelope;
+pub mod message;
+pub mod quota;
+pub mod vacation;
+pub mod wrap;
@@ -0,0 +1,148 @@
+//! Keeps the sending of many emails within the limits of the providers, so
+//! the account doesn't get suspended.
+//!
+//! Providers limit the number of recipients of a message, and the number of
+//! emails sent per minute. The limits of the well-known providers are applied
+//! from the SMTP server the email is sent through, and can be overridden with
+//! the `SMTP_MAX_RECIPIENTS` and `SMTP_RATE_LIMIT` (emails per minute)
+//! variables of the [`Credentials`].
+
+use core::time::Duration;
+use std::thread;
+use std::time::Instant;
+
+use crate::credentials::Credentials;
+
+/// Maximum number of recipients of a message, for unknown providers
+const DEFAULT_MAX_RECIPIENTS: usize = 50;
+/// Maximum number of emails sent per minute, for unknown providers
+const DEFAULT_RATE_LIMIT: u32 = 20;
+/// Domains of well-known providers, with their maximum number of recipients
+/// per message and of emails per minute
+const PROVIDERS: [(&str, usize, u32); 7] = [
+    ("fastmail.com", 50, 30),
+    ("gmail.com", 100, 20),
+    ("icloud.com", 100, 10),
+    ("office365.com", 100, 30),
+    ("outlook.com", 100, 30),
+    ("yahoo.com", 100, 10),
+    ("zoho.com", 50, 10),
+];
+
+/// Limits of the SMTP server an email is sent through
+#[derive(Debug, PartialEq, Eq)]
+pub struct SendLimits {
+    /// Maximum number of recipients of a message
+    max_recipients: usize,
+    /// Minimum duration between two emails
+    min_interval: Duration,
+}
+
+impl SendLimits {
+    /// Checks if a message can be sent to the given number of recipients
+    pub const fn allows_recipients(&self, count: usize) -> bool {
+        count <= self.max_recipients
+    }
+
+    /// Returns the maximum number of recipients of a message
+    pub const fn as_max_recipients(&self) -> usize {
+        self.max_recipients
+    }
+
+    /// Returns the limits of the given SMTP server, with the overrides of the
+    /// credentials
+    pub fn new(credentials: &Credentials, host: &str) -> Self {
+        let (max_recipients, rate_limit) = PROVIDERS
+            .iter()
+            .find(|(domain, ..)| {
+                host.eq_ignore_ascii_case(domain)
+                    || host
+                        .to_ascii_lowercase()
+                        .ends_with(&format!(".{domain}"))
+            })
+            .map_or((DEFAULT_MAX_RECIPIENTS, DEFAULT_RATE_LIMIT), |provider| {
+                (provider.1, provider.2)
+            });
+        let per_minute = credentials.as_smtp_rate_limit().unwrap_or(rate_limit);
+        Self {
+            max_recipients: credentials
+                .as_smtp_max_recipients()
+                .unwrap_or(max_recipients),
+            min_interval: Duration::from_mins(1)
+                .checked_div(per_minute)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns a throttle spacing out the emails, waiting at least
+    /// `min_interval` between them
+    pub fn to_throttle(&self, min_interval: Duration) -> Throttle {
+        Throttle {
+            last_send: None,
+            min_interval: self.min_interval.max(min_interval),
+        }
+    }
+}
+
+/// Spaces out the emails sent in a row
+pub struct Throttle {
+    /// Time at which the last email was sent
+    last_send: Option<Instant>,
+    /// Minimum duration between two emails
+    min_interval: Duration,
+}
+
+impl Throttle {
+    /// Waits until the minimum interval since the last email elapsed.
+    pub fn wait(&mut self) {
+        if let Some(remaining) = self
+            .last_send
+            .and_then(|last| self.min_interval.checked_sub(last.elapsed()))
+        {
+            thread::sleep(remaining);
+        }
+        self.last_send = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use crate::credentials::Credentials;
+    use crate::send::quota::SendLimits;
+
+    #[test]
+    fn limits() {
+        let credentials = Credentials::new(
+            "example.com".to_owned(),
+            "me@example.com".to_owned(),
+            "password".to_owned(),
+        );
+        assert_eq!(
+            SendLimits::new(&credentials, "smtp.gmail.com"),
+            SendLimits {
+                max_recipients: 100,
+                min_interval: Duration::from_secs(3)
+            }
+        );
+        assert_eq!(
+            SendLimits::new(&credentials, "mail.example.com"),
+            SendLimits {
+                max_recipients: 50,
+                min_interval: Duration::from_secs(3)
+            }
+        );
+
+        let overridden = credentials
+            .with_smtp_max_recipients(5)
+            .with_smtp_rate_limit(6);
+        assert_eq!(
+            SendLimits::new(&overridden, "smtp.gmail.com"),
+            SendLimits {
+                max_recipients: 5,
+                min_interval: Duration::from_secs(10)
+            }
+        );
+    }
+}
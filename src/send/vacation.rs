@@ -0,0 +1,213 @@
+//! Answers the received emails automatically while the user is away.
+//!
+//! The reply is read from the `VACATION_TEMPLATE` file of the
+//! [`Config`](crate::config::Config), which starts with an optional subject:
+//!
+//! ```text
+//! Subject: Out of office until Monday
+//!
+//! Hello, I am away and will answer your email when I am back.
+//! ```
+//!
+//! Every sender is answered at most once per `VACATION_WINDOW`, and the
+//! emails sent by robots or mailing lists are never answered, following
+//! [RFC 3834](https://www.rfc-editor.org/rfc/rfc3834). The replies are sent
+//! while `mailbox watch` runs: the vacation script of the server, if any, is
+//! left untouched.
+
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use mail_parser::HeaderName;
+
+use crate::fetch::parser::Email;
+use crate::identity::Identity;
+use crate::send::message::OutgoingEmail;
+
+/// Subject prefix of the replies whose template has no subject, recommended by
+/// RFC 3834
+const SUBJECT_PREFIX: &str = "Auto: ";
+/// Local parts of the addresses of robots, which are never answered
+const ROBOTS: [&str; 5] = [
+    "mailer-daemon",
+    "noreply",
+    "no-reply",
+    "postmaster",
+    "donotreply",
+];
+
+/// Automatic replies, with the senders that were already answered
+pub struct Vacation {
+    /// Body of the replies
+    body: String,
+    /// Time at which every sender was last answered, in seconds since the
+    /// epoch, indexed by lowercase address
+    replied: BTreeMap<String, u64>,
+    /// Subject of the replies, if the template has one
+    subject: Option<String>,
+    /// Minimum duration between two replies to the same sender
+    window: Duration,
+}
+
+impl Vacation {
+    /// Restores the senders answered before, from the content returned by
+    /// [`Self::to_state`].
+    pub fn load_state(&mut self, state: &str) {
+        self.replied.extend(state.lines().filter_map(|line| {
+            let (secs, address) = line.split_once(' ')?;
+            Some((address.to_owned(), secs.parse().ok()?))
+        }));
+    }
+
+    /// Creates the automatic replies from a template
+    pub fn parse(template: &str, window: Duration) -> Self {
+        let normalised = template.replace("\r\n", "\n");
+        let (subject, body) = normalised
+            .split_once('\n')
+            .and_then(|(first, rest)| {
+                let (name, value) = first.split_once(':')?;
+                name.trim()
+                    .eq_ignore_ascii_case("subject")
+                    .then(|| (Some(value.trim().to_owned()), rest.trim_start()))
+            })
+            .unwrap_or((None, &normalised));
+        Self {
+            body: body.to_owned(),
+            replied: BTreeMap::new(),
+            subject,
+            window,
+        }
+    }
+
+    /// Returns the reply to a received email, if its sender must be answered
+    ///
+    /// The sender is then considered answered until the end of the window.
+    pub fn answer(
+        &mut self,
+        email: &Email,
+        identity: &Identity,
+        now: SystemTime,
+    ) -> Option<OutgoingEmail> {
+        let sender = email.to_sender_address()?;
+        let secs = now.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let key = sender.to_lowercase();
+        if is_automated(email)
+            || key == identity.as_address().to_lowercase()
+            || self.replied.get(&key).is_some_and(|last| {
+                secs.saturating_sub(*last) < self.window.as_secs()
+            })
+        {
+            return None;
+        }
+        self.replied.insert(key, secs);
+
+        let subject = self.subject.clone().unwrap_or_else(|| {
+            format!(
+                "{SUBJECT_PREFIX}{}",
+                email
+                    .get_header_str(&HeaderName::Subject)
+                    .unwrap_or_default()
+            )
+        });
+        let mut reply = OutgoingEmail::new(identity.clone(), vec![sender])
+            .with_subject(subject)
+            .with_body(self.body.clone())
+            .with_header(
+                "Auto-Submitted".to_owned(),
+                "auto-replied".to_owned(),
+            );
+        if let Some(id) = email.get_header_str(&HeaderName::MessageId) {
+            reply = reply
+                .with_header("In-Reply-To".to_owned(), format!("<{id}>"))
+                .with_header("References".to_owned(), format!("<{id}>"));
+        }
+        Some(reply)
+    }
+
+    /// Returns the senders answered within the window, to be restored with
+    /// [`Self::load_state`]
+    pub fn to_state(&self, now: SystemTime) -> String {
+        let secs = now
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        self.replied
+            .iter()
+            .filter(|(_, last)| {
+                secs.saturating_sub(**last) < self.window.as_secs()
+            })
+            .map(|(address, last)| format!("{last} {address}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Checks if an email was sent by a robot or a mailing list
+///
+/// Answering these would at best be useless, and at worst start a loop of
+/// automatic replies.
+fn is_automated(email: &Email) -> bool {
+    email
+        .get_header_by_name("Auto-Submitted")
+        .is_some_and(|value| !value.trim().eq_ignore_ascii_case("no"))
+        || email.get_header_by_name("Precedence").is_some_and(|value| {
+            ["bulk", "junk", "list"]
+                .contains(&value.trim().to_lowercase().as_str())
+        })
+        || email.get_header_by_name("List-Id").is_some()
+        || email.get_header_by_name("List-Unsubscribe").is_some()
+        || email.to_sender_address().is_none_or(|sender| {
+            let local = sender.split('@').next().unwrap_or_default();
+            ROBOTS.contains(&local.to_lowercase().as_str())
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use crate::identity::Identity;
+    use crate::send::vacation::Vacation;
+    use crate::test_utils::email;
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn vacation() {
+        let identity = Identity::parse("me@example.com").unwrap();
+        let mut vacation = Vacation::parse(
+            "Subject: Away\n\nBack soon",
+            Duration::from_hours(24),
+        );
+        let now = UNIX_EPOCH.checked_add(Duration::from_hours(1000)).unwrap();
+        let received = email(
+            "From: Bob <bob@example.com>\nSubject: Hi\nMessage-ID: <1@x>",
+        );
+
+        let reply = vacation.answer(&received, &identity, now).unwrap();
+        assert_eq!(reply.to_recipients(), ["bob@example.com"]);
+        assert!(reply.to_rfc5322().contains("In-Reply-To: <1@x>"));
+        assert!(vacation.answer(&received, &identity, now).is_none());
+
+        let mut restored =
+            Vacation::parse("Back soon", Duration::from_hours(24));
+        restored.load_state(&vacation.to_state(now));
+        assert!(restored.answer(&received, &identity, now).is_none());
+        let later = now.checked_add(Duration::from_hours(25)).unwrap();
+        assert!(restored.answer(&received, &identity, later).is_some());
+
+        for headers in [
+            "From: list@example.com\nList-Id: <dev.example.com>",
+            "From: bot@example.com\nAuto-Submitted: auto-generated",
+            "From: noreply@example.com",
+            "From: me@example.com",
+        ] {
+            assert!(
+                vacation
+                    .answer(&email(headers), &identity, SystemTime::now())
+                    .is_none()
+            );
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Wraps the body of the emails, so plain text isn't sent as one long line.
+//!
+//! The lines are wrapped between words at a [configured
+//! column](crate::config::Config::as_wrap_column), and words longer than the
+//! column are left on their own line. Quoted lines, starting with `>`, are
+//! never wrapped.
+//!
+//! With `format=flowed` (RFC 3676), the lines broken by the wrapping end with a
+//! space, so the client of the recipient can join them back to fit its own
+//! window.
+
+use core::mem;
+
+use unicode_width::UnicodeWidthStr as _;
+
+/// Line separating the body from the signature, that must keep its trailing
+/// space
+const SIGNATURE_SEPARATOR: &str = "-- ";
+
+/// Re-wraps the paragraphs of a text
+///
+/// Unlike [`break_lines`], the lines of a paragraph are joined before being
+/// wrapped, so short lines left by earlier edits are filled again. Empty and
+/// quoted lines separate paragraphs.
+pub fn reflow(text: &str, column: usize) -> String {
+    let mut lines = vec![];
+    let mut paragraph = vec![];
+    for line in text.lines() {
+        if line.trim().is_empty()
+            || line.starts_with('>')
+            || line == SIGNATURE_SEPARATOR
+        {
+            flush_paragraph(&mut paragraph, &mut lines, column);
+            lines.push(line.to_owned());
+        } else {
+            paragraph.push(line.trim());
+        }
+    }
+    flush_paragraph(&mut paragraph, &mut lines, column);
+    lines.join("\n")
+}
+
+/// Formats a text as `format=flowed`, wrapped at the given column
+///
+/// The trailing spaces typed by the user are removed, as they would mark soft
+/// breaks, and the lines starting with a space or `From ` are stuffed with a
+/// space, as specified by RFC 3676.
+pub fn to_flowed(text: &str, column: usize) -> String {
+    let mut lines = vec![];
+    for line in text.lines() {
+        if line == SIGNATURE_SEPARATOR {
+            lines.push(line.to_owned());
+            continue;
+        }
+        let wrapped = break_line(line.trim_end_matches(' '), column);
+        let last = wrapped.len().saturating_sub(1);
+        lines.extend(wrapped.into_iter().enumerate().map(|(idx, part)| {
+            let stuffed = if part.starts_with(' ') || part.starts_with("From ")
+            {
+                format!(" {part}")
+            } else {
+                part
+            };
+            if idx < last {
+                format!("{stuffed} ")
+            } else {
+                stuffed
+            }
+        }));
+    }
+    lines.join("\n")
+}
+
+/// Wraps every line of a text at the given column, keeping the existing line
+/// breaks
+///
+/// Nothing is wrapped if the column is 0.
+pub fn break_lines(text: &str, column: usize) -> Vec<String> {
+    text.lines()
+        .flat_map(|line| break_line(line, column))
+        .collect()
+}
+
+/// Wraps the lines of a paragraph once joined, and empties it.
+fn flush_paragraph(
+    paragraph: &mut Vec<&str>,
+    lines: &mut Vec<String>,
+    column: usize,
+) {
+    if !paragraph.is_empty() {
+        lines.extend(break_line(&paragraph.join(" "), column));
+        paragraph.clear();
+    }
+}
+
+/// Wraps a line between its words, at the given column
+fn break_line(line: &str, column: usize) -> Vec<String> {
+    if column == 0 || line.starts_with('>') || line.width() <= column {
+        return vec![line.to_owned()];
+    }
+    let mut wrapped = vec![];
+    let mut current = String::new();
+    for word in line.split(' ') {
+        if current.is_empty() {
+            current.push_str(word);
+        } else if current.width().saturating_add(word.width()) < column {
+            current.push(' ');
+            current.push_str(word);
+        } else {
+            wrapped.push(mem::replace(&mut current, word.to_owned()));
+        }
+    }
+    wrapped.push(current);
+    wrapped
+}
+
+#[cfg(test)]
+mod test {
+    use crate::send::wrap::{break_lines, reflow, to_flowed};
+
+    #[test]
+    fn wrap() {
+        assert_eq!(
+            break_lines("the quick brown fox\n> jumps over the lazy dog", 10),
+            ["the quick", "brown fox", "> jumps over the lazy dog"]
+        );
+        assert_eq!(
+            break_lines("unbreakable_word here", 5),
+            ["unbreakable_word", "here"]
+        );
+        assert_eq!(break_lines("no wrapping", 0), ["no wrapping"]);
+        assert_eq!(
+            reflow("the quick\nbrown\nfox\n\n> quoted\njumps", 15),
+            "the quick brown\nfox\n\n> quoted\njumps"
+        );
+        assert_eq!(
+            to_flowed("the quick brown fox  \nFrom me\n-- \nBob", 10),
+            "the quick \nbrown fox\n From me\n-- \nBob"
+        );
+    }
+}
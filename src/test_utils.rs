@@ -0,0 +1,118 @@
+//! Fixtures shared by the tests of the modules.
+
+#![expect(clippy::unwrap_used, reason = "test")]
+
+use std::env::temp_dir;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, process};
+
+use crate::cache::Cache;
+use crate::credentials::Credentials;
+use crate::fetch::parser::Email;
+use crate::send::connection::SmtpSession;
+
+/// Temporary directory of a test, removed when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates an empty directory for the test of the given name
+    ///
+    /// The name is suffixed with the id of the process, so that two runs of
+    /// the tests don't share their directories.
+    pub fn new(name: &str) -> Self {
+        let path = temp_dir().join(format!("mailbox-{name}-{}", process::id()));
+        fs::remove_dir_all(&path).unwrap_or_default();
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    /// Returns the path of the directory
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Opens the cache of `bob@example.com` in the directory
+    pub fn open_cache(&self) -> Cache {
+        Cache::open(&self.0, "bob@example.com", None).unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).unwrap_or_default();
+    }
+}
+
+/// Parses an email with the given headers and a short body
+pub fn email(headers: &str) -> Email {
+    email_with_uid(1, headers)
+}
+
+/// Parses an email with the given uid and headers, and a short body
+pub fn email_with_uid(uid: u32, headers: &str) -> Email {
+    to_email(uid, headers, "Body\n")
+}
+
+/// Parses an email with the given uid, headers and body
+pub fn to_email(uid: u32, headers: &str, body: &str) -> Email {
+    Email::try_from((uid, format!("{headers}\n\n{body}").as_bytes())).unwrap()
+}
+
+/// Server replying with a script, whatever the commands it receives
+pub struct ScriptedServer {
+    /// Commands received from the client
+    received: Vec<u8>,
+    /// Replies left to send
+    replies: io::Cursor<Vec<u8>>,
+}
+
+impl ScriptedServer {
+    /// Creates a server sending the given replies, one per line
+    pub fn new(replies: &[&str]) -> Self {
+        let script = replies.iter().map(|reply| format!("{reply}\r\n"));
+        Self {
+            received: vec![],
+            replies: io::Cursor::new(script.collect::<String>().into_bytes()),
+        }
+    }
+
+    /// Opens an SMTP session on the server, which must first reply to the
+    /// greeting, `EHLO` and `AUTH`
+    pub fn open_smtp(&mut self) -> SmtpSession<&mut Self> {
+        let credentials = Credentials::new(
+            "example.com".to_owned(),
+            "bob@example.com".to_owned(),
+            "password".to_owned(),
+        );
+        SmtpSession::with_stream(&credentials, self).unwrap()
+    }
+
+    /// Returns the commands received after the given one, included
+    pub fn to_received_from(&self, command: &str) -> String {
+        let received = String::from_utf8_lossy(&self.received);
+        received
+            .find(command)
+            .and_then(|start| received.get(start..))
+            .unwrap_or_default()
+            .to_owned()
+    }
+}
+
+#[expect(clippy::missing_trait_methods, reason = "the defaults fit")]
+impl Read for ScriptedServer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.replies.read(buf)
+    }
+}
+
+#[expect(clippy::missing_trait_methods, reason = "the defaults fit")]
+impl Write for ScriptedServer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.received.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+//! Highlights the emails waiting for a reply, more as they get older.
+//!
+//! An email waits for a reply if it is flagged, or marked as needing a reply
+//! with `!`, which toggles the `$NeedsReply` keyword, and if it wasn't
+//! answered, i.e. it doesn't have the `\Answered` flag. Its row of the list is
+//! highlighted once it is a few days old:
+//!
+//! | Age        | Highlight |
+//! | ---------- | --------- |
+//! | 3 days     | yellow    |
+//! | 1 week     | light red |
+//! | 2 weeks    | bold red  |
+//!
+//! The emails marked as needing a reply are listed with the `F r` filter, see
+//! [`filters`](super::filters). When a reply to the email is sent from the
+//! writer, the mark is removed and the email is flagged as answered.
+
+use ratatui::style::{Color, Modifier, Style};
+
+use super::reminders::SECS_PER_DAY;
+use super::views::to_timestamp;
+use crate::cache::flags::FlagSet;
+use crate::fetch::parser::Email;
+
+/// Flag of the emails that were answered
+pub const ANSWERED_FLAG: &str = "\\Answered";
+/// Flag of the emails that were marked as important
+const FLAGGED_FLAG: &str = "\\Flagged";
+/// Keyword of the emails marked as needing a reply
+pub const NEEDS_REPLY_FLAG: &str = "$NeedsReply";
+/// Highlights of the waiting emails, from the oldest, with the age in days
+/// from which they apply
+const STAGES: [(i64, Color, bool); 3] = [
+    (14, Color::Red, true),
+    (7, Color::LightRed, false),
+    (3, Color::Yellow, false),
+];
+
+/// Checks if an email is marked as needing a reply, given its flags
+pub fn needs_reply(flags: Option<&FlagSet>) -> bool {
+    flags.is_some_and(|email_flags| email_flags.contains(NEEDS_REPLY_FLAG))
+}
+
+/// Returns the highlight of an email of the list, given its flags, if it is
+/// waiting for a reply for long enough
+pub fn to_style(
+    email: &Email,
+    flags: Option<&FlagSet>,
+    now: i64,
+) -> Option<Style> {
+    let email_flags = flags?;
+    if email_flags.contains(ANSWERED_FLAG)
+        || !(email_flags.contains(FLAGGED_FLAG)
+            || email_flags.contains(NEEDS_REPLY_FLAG))
+    {
+        return None;
+    }
+    let age = now.saturating_sub(to_timestamp(email));
+    STAGES
+        .iter()
+        .find(|(days, ..)| age >= days.saturating_mul(SECS_PER_DAY))
+        .map(|(_, color, is_bold)| {
+            let style = Style::new().fg(*color);
+            if *is_bold {
+                style.add_modifier(Modifier::BOLD)
+            } else {
+                style
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::style::{Color, Modifier};
+
+    use crate::cache::flags::FlagSet;
+    use crate::fetch::parser::Email;
+    use crate::tui::aging::{needs_reply, to_style};
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn aging() {
+        let email = Email::try_from((
+            1,
+            b"Date: Mon, 6 Jan 2025 10:00:00 +0000\n\nHello".as_slice(),
+        ))
+        .unwrap();
+        let sent = 1_736_157_600;
+        let day = 86_400;
+        let flags = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (*name).to_owned())
+                .collect::<FlagSet>()
+        };
+        let flagged = flags(&["\\Flagged"]);
+        assert_eq!(to_style(&email, Some(&flagged), sent + 2 * day), None);
+        assert_eq!(
+            to_style(&email, Some(&flagged), sent + 3 * day)
+                .and_then(|style| style.fg),
+            Some(Color::Yellow)
+        );
+        let marked = flags(&["$NeedsReply", "\\Seen"]);
+        assert!(needs_reply(Some(&marked)));
+        let oldest = to_style(&email, Some(&marked), sent + 20 * day).unwrap();
+        assert_eq!(oldest.fg, Some(Color::Red));
+        assert!(oldest.add_modifier.contains(Modifier::BOLD));
+        let answered = flags(&["\\Flagged", "\\Answered"]);
+        assert_eq!(to_style(&email, Some(&answered), sent + 20 * day), None);
+        assert_eq!(to_style(&email, Some(&flags(&[])), sent + 20 * day), None);
+        assert_eq!(to_style(&email, None, sent + 20 * day), None);
+        assert!(!needs_reply(Some(&flagged)));
+    }
+}
@@ -1,28 +1,163 @@
 //! Renders the app to the screen
 
+use alloc::collections::BTreeMap;
 use core::any::Any;
-use std::io;
+use core::ops::Range;
+use core::time::Duration;
+use core::{iter, mem};
+use std::path::Path;
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
+use std::{fs, io};
 
 use mail_parser::HeaderName;
-use ratatui::Frame;
-use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, read};
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Style};
-use ratatui::text::{Line, Text};
-use ratatui::widgets::{Block, List, ListItem, Paragraph, Wrap};
+use ratatui::crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags, poll, read
+};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    EnterAlternateScreen, enable_raw_mode, supports_keyboard_enhancement
+};
+use ratatui::layout::{Constraint, Direction, Layout, Position, Rect};
+use ratatui::style::{Style, Stylize as _};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use tui_input::Input;
+use tui_input::backend::crossterm::EventHandler as _;
 
+use super::aging::{self, ANSWERED_FLAG, NEEDS_REPLY_FLAG, needs_reply};
+use super::avatar::{AVATAR_WIDTH, to_avatar, to_recipient_avatar};
+use super::badges::IdentityBadge;
+use super::bidi::{fit_visual, to_visual_rows};
+use super::browser::{Browser, Choice, Purpose};
+use super::command::{Command, Prompt};
+use super::complete::{Completion, load_folders, store_folders};
 use super::components::new_simple_box;
+use super::control::{Listener, Request, is_running};
+use super::dashboard::Dashboard;
+use super::draft::{Autosave, clear_draft, load_draft};
+use super::export::export_attachments;
+use super::filters::{Filters, QuickFilter, SEEN_FLAG, is_unread};
+use super::find::{BodySearch, count_rows};
+use super::finder::{Finder, Target};
+use super::highlight::highlight_body;
+use super::index_format::to_attachment_marker;
+use super::links::{collapse, to_references};
 use super::manual::manual_page;
+use super::pictures::Pictures;
+use super::positions::ReadPositions;
+use super::reminders::{self, Reminders};
+use super::send_preview::{PendingSend, SendPreview};
+use super::senders::{BlockedAction, Edit, SenderLists};
+use super::session::{SavedMode, Snapshot};
+use super::shortcuts::RecentFolders;
+use super::split::Split;
 use super::states::TuiMode;
+use super::threads::Threads;
+use super::views::{FolderView, SortOrder, Views};
+use super::width::{fit, truncate};
+use super::writer::Writer;
+use super::{noise, sent_replies};
+use crate::annotations::to_summaries;
+use crate::archive::{self, Entry};
+use crate::cache::analytics::Statistics;
+use crate::cache::flags::Flags;
+use crate::cache::queue::{Location, Operation, Queue};
+use crate::cache::threads::ThreadIndex;
+use crate::cache::{Cache, integrity, local, retention};
+use crate::calendar::{find_invite, import_invite, save_invite, to_summary};
+use crate::cli::send_or_queue;
+use crate::config::Config;
 use crate::credentials::Credentials;
 use crate::errors::Result;
 use crate::fetch;
-use crate::fetch::connection::ImapSession;
-use crate::fetch::parser::{self, Email};
+use crate::fetch::background::{FetchEvent, FetchTask};
+use crate::fetch::connection::{ImapSession, MailboxSelected};
+use crate::fetch::mailbox::{Mailbox, Role, to_list_pattern};
+use crate::fetch::parser::{self, Attachment, Email};
+use crate::fetch::pool::SessionManager;
+use crate::filter::{filter_body, to_unfiltered_body};
+use crate::html::find_trackers;
+use crate::identity::{Identity, find_for_reply};
+use crate::junk::Verdict;
+use crate::mailcap::{Mailcap, OpenedAttachments};
+use crate::mailto::Mailto;
+use crate::otp::{self, find_code};
+use crate::patch::{apply_series, find_series, is_patch, save_series};
+use crate::print::print_email;
+use crate::send::message::to_resent;
+
+/// Interval at which the progress of a background fetch is displayed.
+const FETCH_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Folder opened when the TUI starts.
+const DEFAULT_MAILBOX: &str = "INBOX";
+
+/// Number of lines the body of the opened email is scrolled by with 'd' and
+/// 'u'.
+const SCROLL_LINES: u16 = 10;
+
+/// Maximum number of files of an archive attachment listed in the viewer
+const ARCHIVE_PREVIEW_LEN: usize = 10;
+
+/// Uids, first emails and flags of a folder, with a status message and whether
+/// the server couldn't be reached
+type LoadedFolder = (Vec<u32>, Vec<Email>, Flags, Option<String>, bool);
 
 /// Follows the state of the TUI application.
 #[derive(Default)]
+#[expect(clippy::struct_excessive_bools, reason = "independent states")]
 pub struct Tui {
+    /// Index of the displayed tab in `tabs`
+    active_tab: usize,
+    /// Uid of the displayed email, with the files of its archive attachments
+    ///
+    /// The archives are only read again when the displayed email changes.
+    archives: Option<(u32, Vec<Option<Vec<Entry>>>)>,
+    /// File browser to choose where the attachments are saved, opened with
+    /// `S`
+    browser: Option<Browser>,
+    /// Uid of the opened email, with the search in its body
+    ///
+    /// The search is ignored once another email is opened.
+    body_search: Option<(u32, BodySearch)>,
+    /// Uid of the displayed email, with the breadcrumb line of its
+    /// conversation if it is part of one
+    ///
+    /// The breadcrumb is kept to group the emails into conversations only when
+    /// the displayed email changes.
+    breadcrumb: Option<(u32, Option<String>)>,
+    /// Local storage of the emails, if a cache directory is configured
+    cache: Option<Cache>,
+    /// Indicates whether the terminal must be cleared before the next render
+    ///
+    /// This is needed after handing the terminal over to an external program.
+    clear_terminal: bool,
+    /// Completion of the folder of the command line, while `Tab` is pressed
+    completion: Option<Completion>,
+    /// Command line, opened when pressing `:`, or `/` to search the opened
+    /// email
+    ///
+    /// This is `None` when the command line is closed.
+    command_line: Option<(Prompt, Input)>,
+    /// Configuration of the app
+    config: Config,
+    /// Socket receiving the requests of external tools
+    ///
+    /// This is `None` without runtime directory, or if another instance
+    /// listens on the socket.
+    control: Option<Listener>,
+    /// Last save of the draft of the writer, to recover it after a crash
+    draft_save: Option<Autosave>,
+    /// Associations between MIME types and programs to open attachments
+    mailcap: Mailcap,
+    /// Credentials of the account, to reconnect and apply operations
+    credentials: Option<Credentials>,
+    /// Count typed before a motion, e.g. `5` in `5j`
+    count: Option<usize>,
+    /// Statistics of the cached emails, opened with `:stats`
+    dashboard: Option<Dashboard>,
     /// Current mode of the TUI, describing what is the current base of action
     /// of the client.
     ///
@@ -36,110 +171,3008 @@ pub struct Tui {
     /// coherent with the new email list.
     current_id: usize,
     /// Emails that were fetched from the server
+    ///
+    /// The emails hidden by the filters are moved to `filtered_out`.
     emails: Vec<Email>,
+    /// Loaded emails that don't match the filters
+    filtered_out: Vec<Email>,
+    /// Quick filters of the list
+    filters: Filters,
+    /// Flags of the emails of the folder, indexed by uid
+    flags: Flags,
+    /// Fuzzy finder popup, opened with `Ctrl-P`
+    finder: Option<Finder>,
+    /// Roles advertised by the server for the folders of the account, e.g.
+    /// the sent folder
+    folder_roles: BTreeMap<String, Role>,
+    /// Folder names of the account, listed once to complete them
+    folders: Vec<String>,
+    /// Indicates whether the list of the references of the links is hidden
+    /// at the bottom of the displayed body
+    links_hidden: bool,
+    /// Scroll position of the email list
+    list_state: ListState,
+    /// Name of the opened folder
+    mailbox: String,
+    /// Fetch running in the background, if any
+    fetch_task: Option<FetchTask>,
+    /// Indicates whether the blocks of style sheets and encoded data of the
+    /// displayed body are shown
+    noise_shown: bool,
     /// Id of the opened email
     ///
     /// This is the same id than `current_id`, so the same rules apply.
     open_email_id: Option<usize>,
+    /// Body of the opened email, after running it through the display filters
+    open_email_body: Option<String>,
+    /// Temporary files of the attachments opened with their program, removed
+    /// on exit
+    opened_attachments: OpenedAttachments,
+    /// Indicates whether the server couldn't be reached
+    ///
+    /// When offline, the emails are read from the cache and the operations are
+    /// queued, to be replayed once reconnected.
+    offline: bool,
     /// Email uids that exist in the INBOX
     uids: Vec<u32>,
+    /// Confirmed email, waiting for the undo send delay to be over
+    pending_send: Option<PendingSend>,
+    /// Indicates whether `F` was pressed, waiting for the key of a filter
+    pending_filter: bool,
+    /// Indicates whether `g` was pressed, waiting for a second `g`
+    pending_g: bool,
+    /// Indicates whether `c` was pressed, waiting for the digit of a canned
+    /// response
+    pending_response: bool,
+    /// Id of the email displayed in the viewer at the last render, with the
+    /// cell of the picture of its sender
+    picture_slot: Option<(usize, Position)>,
+    /// Pictures of the senders, drawn over their initials in the viewer
+    pictures: Pictures,
+    /// Scroll offsets of the bodies of the emails, restored when they are
+    /// opened again
+    positions: ReadPositions,
+    /// Folders opened by the user, from the most recent
+    recent_folders: RecentFolders,
+    /// Uid and body of the email displayed in the preview pane, if any
+    ///
+    /// The body is kept to run the display filters only when the hovered
+    /// email changes.
+    preview: Option<(u32, String)>,
+    /// Pruning of the cache running in the background, if any
+    ///
+    /// The thread returns the status message to display once it finishes.
+    prune_task: Option<JoinHandle<String>>,
+    /// Sent emails waiting for a reply, listed above the inbox once due
+    reminders: Reminders,
     /// Indicates whether the app is running
     running: bool,
+    /// Text searched in the emails of the tab, if it holds a search result
+    search: Option<String>,
+    /// Written email waiting for the confirmation to be sent, opened with `S`
+    /// in the writer
+    send_preview: Option<SendPreview>,
+    /// Blocked and allowed senders
+    senders: SenderLists,
+    /// Connections to the server, shared with the background fetches
+    sessions: SessionManager,
+    /// Order of the emails in the list
+    sort: SortOrder,
+    /// Layout of the list and the opened email
+    split: Split,
+    /// Message displayed at the bottom of the screen
+    ///
+    /// This gives feedback on the last command that was run.
+    status: Option<String>,
+    /// Opened tabs
+    ///
+    /// The state of the displayed tab is held by the [`Tui`] itself, so its
+    /// entry is empty until another tab is displayed.
+    tabs: Vec<Tab>,
+    /// Conversations of the emails of the folder, to group them without
+    /// threading them
+    thread_index: ThreadIndex,
+    /// Uid of the displayed email, with the number of its tracking pixels
+    trackers: Option<(u32, usize)>,
+    /// State of the email list of every visited folder
+    views: Views,
+}
+
+/// State of a tab that isn't displayed
+///
+/// The fields are the same as the ones of the [`Tui`] they are moved from.
+#[derive(Default)]
+struct Tab {
+    /// Id of the hovered email
+    current_id: usize,
+    /// Emails of the tab that match the filters
+    emails: Vec<Email>,
+    /// Loaded emails that don't match the filters
+    filtered_out: Vec<Email>,
+    /// Quick filters of the list
+    filters: Filters,
+    /// Flags of the emails, indexed by uid
+    flags: Flags,
+    /// Scroll position of the email list
+    list_state: ListState,
+    /// Name of the folder of the emails
+    mailbox: String,
+    /// Indicates whether the server couldn't be reached
+    offline: bool,
+    /// Body of the opened email, after running the display filters
+    open_email_body: Option<String>,
+    /// Id of the opened email
+    open_email_id: Option<usize>,
+    /// Text searched in the emails, if the tab holds a search result
+    search: Option<String>,
+    /// Order of the emails in the list
+    sort: SortOrder,
+    /// Uids of the emails of the folder
+    uids: Vec<u32>,
 }
 
 impl Tui {
     /// Creates a new [`Tui`]
-    pub fn new() -> Result<Self> {
+    ///
+    /// If the server can't be reached, the emails are read from the cache.
+    /// Unless `fresh` is set, the folder, the mode and the opened email of the
+    /// last session are restored.
+    pub fn new(fresh: bool) -> Result<Self> {
         let credentials = Credentials::load()?;
-        let mut session = ImapSession::with_credentials(&credentials)?
-            .select_mailbox("INBOX")?;
-        let uids = session.get_uids()?;
-        let first_email_bodies = uids
-            .iter()
-            .take(20)
-            .map(|uid| Ok((uid, session.get_mail_from_uid(*uid)?)))
-            .collect::<Result<Vec<_>>>()?;
-        let first_emails = first_email_bodies
-            .iter()
-            .map(|(uid, body)| Ok(Email::try_from((**uid, body.as_bytes()))?))
-            .collect::<Result<Vec<_>>>()?;
+        let sessions = SessionManager::default();
+        let config = Config::load();
+        let mailcap = Mailcap::load(config.as_mailcap_path());
+        let (cache, repairs) = Self::open_cache(&config, &credentials)?;
+
+        let snapshot = if fresh {
+            Snapshot::default()
+        } else {
+            Snapshot::load(cache.as_ref())
+        };
+        let saved_mailbox = snapshot.as_folder().unwrap_or(DEFAULT_MAILBOX);
+        let (mailbox, (uids, emails, flags, status, offline)) =
+            match Self::load_folder(
+                &credentials,
+                &sessions,
+                &config,
+                cache.as_ref(),
+                saved_mailbox,
+            ) {
+                Ok(folder) => (saved_mailbox, folder),
+                Err(_) if saved_mailbox != DEFAULT_MAILBOX => (
+                    DEFAULT_MAILBOX,
+                    Self::load_folder(
+                        &credentials,
+                        &sessions,
+                        &config,
+                        cache.as_ref(),
+                        DEFAULT_MAILBOX,
+                    )?,
+                ),
+                Err(err) => return Err(err),
+            };
+
+        let prune_task = cache
+            .as_ref()
+            .filter(|_| !config.as_cache_retention().is_unlimited())
+            .map(|storage| {
+                let thread_cache = storage.clone();
+                let policy = *config.as_cache_retention();
+                thread::spawn(move || {
+                    retention::prune(&thread_cache, &policy).map_or_else(
+                        |err| format!("Failed to prune the cache: {err:?}"),
+                        |pruned| pruned.to_string(),
+                    )
+                })
+            });
+
+        let views = Views::load(cache.as_ref());
+        let positions = ReadPositions::load(cache.as_ref());
+        let mut recent_folders = RecentFolders::load(cache.as_ref());
+        recent_folders.visit(mailbox);
+        let reminders = Reminders::load(cache.as_ref());
+        let pictures =
+            Pictures::new(cache.clone(), config.has_sender_pictures());
+        let senders = cache.as_ref().map(SenderLists::load).unwrap_or_default();
+        let split = config.as_split();
+        let (control, control_error) = Self::bind_control(&credentials);
+        let mut tui = Self {
+            cache,
+            config,
+            control,
+            credentials: Some(credentials),
+            emails,
+            flags,
+            mailbox: mailbox.to_owned(),
+            mailcap,
+            offline,
+            pictures,
+            positions,
+            prune_task,
+            recent_folders,
+            reminders,
+            senders,
+            sessions,
+            split,
+            status: status.or(repairs).or(control_error),
+            tabs: vec![Tab::default()],
+            uids,
+            views,
+            ..Self::default()
+        };
+        tui.restore_view();
+        tui.restore_session(&snapshot)?;
+        if tui.cache.as_ref().and_then(load_draft).is_some() {
+            tui.status = Some(
+                "A draft was recovered from a crash: press 'W' to restore it"
+                    .to_owned(),
+            );
+        }
+        if !tui.offline && tui.has_interrupted_sync() {
+            tui.status = Some(tui.start_fetch_task());
+        }
+        Ok(tui)
+    }
+
+    /// Opens the writer, pre-filled with the given `mailto:` URL if any
+    pub fn compose(mut self, mailto: Option<&Mailto>) -> Self {
+        self.open_writer(mailto);
+        self
+    }
+
+    /// Opens the writer, pre-filled with the given `mailto:` URL if any
+    fn open_writer(&mut self, mailto: Option<&Mailto>) {
+        match mailto {
+            Some(url) => self.mode.new_writer_from_mailto(url),
+            None => self.mode.new_writer(),
+        }
+        self.configure_writer();
+    }
+
+    /// Opens the cache of the account, if enabled, and repairs the files
+    /// damaged by a crash
+    ///
+    /// Returns what was repaired, if anything.
+    fn open_cache(
+        config: &Config,
+        credentials: &Credentials,
+    ) -> Result<(Option<Cache>, Option<String>)> {
+        let Some(dir) = config.as_cache_dir() else {
+            return Ok((None, None));
+        };
+        let cache = Cache::open(
+            dir,
+            credentials.as_email(),
+            credentials.as_cache_key_command(),
+        )?;
+        let repairs = integrity::repair(&cache)?;
+        Ok((Some(cache), (!repairs.is_empty()).then(|| repairs.to_string())))
+    }
+
+    /// Listens on the control socket, or returns why it couldn't
+    ///
+    /// A TUI already running for the same account is reported, as both can
+    /// change the same flags.
+    fn bind_control(
+        credentials: &Credentials,
+    ) -> (Option<Listener>, Option<String>) {
+        let account = credentials.as_email();
+        match Listener::bind(account) {
+            Ok(listener) => (listener, None),
+            Err(_) if is_running(account) => (
+                None,
+                Some(format!(
+                    "Another instance is running for {account}, and can change the same flags: 'mailbox control quit' stops it"
+                )),
+            ),
+            Err(err) => (
+                None,
+                Some(format!("Failed to listen for control requests: {err}")),
+            ),
+        }
+    }
+
+    /// Runs the requests sent by external tools through the control socket
+    fn handle_control_requests(&mut self) {
+        let requests = self
+            .control
+            .as_ref()
+            .map(Listener::accept_requests)
+            .unwrap_or_default();
+        for request in requests {
+            match request {
+                Request::Compose(_)
+                    if matches!(self.mode, TuiMode::Writing(_)) =>
+                    self.status = Some(
+                        "Send or close the written email before composing another one"
+                            .to_owned(),
+                    ),
+                Request::Compose(mailto) => self.open_writer(mailto.as_ref()),
+                Request::Goto(folder) =>
+                    self.status = Some(self.switch_folder(&folder)),
+                Request::Quit => self.running = false,
+                Request::Refresh => {
+                    let folder = self.mailbox.clone();
+                    self.status = Some(self.switch_folder(&folder));
+                }
+                Request::Sync => self.status = Some(self.reconnect()),
+                Request::Account => (),
+            }
+        }
+    }
+
+    /// Runs the [`Tui`]
+    ///
+    /// Handles key events and frame renders
+    pub fn run(&mut self) -> Result {
+        let mut terminal = ratatui::init();
+        self.running = true;
+        // The terminal is restored even if the loop failed, not to leave the
+        // shell in raw mode.
+        let result = Self::enable_keyboard_enhancement()
+            .and_then(|()| self.run_loop(&mut terminal));
+        let restored = Self::restore_terminal();
+        result?;
+        restored?;
+        if let Some(pending) = self.pending_send.take() {
+            self.send_message(pending);
+        }
+        self.save_view()?;
+        self.opened_attachments.remove()?;
+        if let Some(listener) = self.control.take() {
+            listener.close().map_err(Error::ControlSocket)?;
+        }
+        if let Some(cache) = &self.cache {
+            clear_draft(cache)?;
+            self.positions.save(cache)?;
+            self.recent_folders.save(cache)?;
+            self.reminders.save(cache)?;
+        }
+        self.save_session()
+    }
+
+    /// Draws the TUI and handles the events, until the app is quit
+    ///
+    /// The failures to save the draft or to draw the picture of the sender are
+    /// shown in the status line, as the app can go on without them.
+    #[expect(
+        clippy::unwrap_in_result,
+        clippy::unwrap_used,
+        reason = "inside closure"
+    )]
+    fn run_loop(&mut self, terminal: &mut DefaultTerminal) -> Result {
+        while self.running {
+            if self.clear_terminal {
+                terminal.clear().map_err(Error::ClearTerminal)?;
+                self.pictures.invalidate();
+                self.clear_terminal = false;
+            }
+            self.update_fetch_task();
+            self.update_prune_task();
+            self.update_pending_send();
+            self.handle_control_requests();
+            if let Err(err) = self.autosave_draft() {
+                self.status =
+                    Some(format!("Failed to save the draft: {err:?}"));
+            }
+            terminal
+                .draw(|frame| self.draw_tui(frame).unwrap())
+                .map_err(Error::Drawing)?;
+            if let Err(err) = self.draw_sender_picture() {
+                self.status = Some(format!(
+                    "Failed to draw the picture of the sender: {err:?}"
+                ));
+            }
+            self.handle_key_events()?;
+        }
+        Ok(())
+    }
+
+    /// Stores the content of the writer regularly, if it is opened
+    fn autosave_draft(&mut self) -> Result {
+        if let TuiMode::Writing(writer) = &self.mode
+            && let Some(cache) = &self.cache
+            && let Some(save) =
+                Autosave::save(self.draft_save.as_ref(), writer, cache)?
+        {
+            self.draft_save = Some(save);
+        }
+        Ok(())
+    }
+
+    /// Reopens the writer with the draft left by a crash, if any
+    fn restore_draft(&mut self) {
+        let Some(writer) = self.cache.as_ref().and_then(load_draft) else {
+            self.status = Some("No draft to restore".to_owned());
+            return;
+        };
+        self.mode = TuiMode::Writing(Box::new(writer));
+        self.configure_writer();
+    }
+
+    /// Opens the writer to reply to the selected email
+    ///
+    /// The subjects of the conversation of the email are offered as
+    /// suggestions, and the reply is sent from the identity the email was sent
+    /// to.
+    fn open_reply(&mut self) {
+        let Some(replied) = self.emails.get(self.current_id) else {
+            self.status = Some("No email to reply to".to_owned());
+            return;
+        };
+        let conversation = Threads::new(&self.emails)
+            .conversation(self.current_id)
+            .into_iter()
+            .filter_map(|id| self.emails.get(id))
+            .collect::<Vec<_>>();
+        self.mode = TuiMode::Writing(Box::new(Writer::new_reply(
+            replied,
+            &conversation,
+        )));
+        self.configure_writer();
+        if let TuiMode::Writing(writer) = &mut self.mode
+            && let Some(email) = self.emails.get(self.current_id)
+        {
+            writer.select_identity_for(email);
+        }
+    }
+
+    /// Opens the selected email in the writer, as a new email to send
+    fn edit_as_new(&mut self) {
+        let Some(email) = self.emails.get(self.current_id) else {
+            self.status = Some("No email to edit".to_owned());
+            return;
+        };
+        self.mode = TuiMode::Writing(Box::new(Writer::new_from_email(email)));
+        self.configure_writer();
+        if let TuiMode::Writing(writer) = &mut self.mode
+            && let Some(edited) = self.emails.get(self.current_id)
+        {
+            writer.select_identity_of(edited);
+        }
+    }
+
+    /// Replies to the opened email with the response template bound to a key,
+    /// without opening the writer
+    ///
+    /// The writer is opened with the reply if it can't be sent, e.g. without
+    /// a destination.
+    fn reply_with_template(&mut self, key: char) {
+        let Some(template) = self
+            .config
+            .as_response_templates()
+            .get(key)
+            .map(str::to_owned)
+        else {
+            self.status = Some(format!("No canned response bound to '{key}'"));
+            return;
+        };
+        self.open_reply();
+        let TuiMode::Writing(mut writer) =
+            mem::replace(&mut self.mode, TuiMode::Reading)
+        else {
+            return;
+        };
+        writer.set_body(&template);
+        match writer.to_email() {
+            Ok(Some(email)) if !email.to_recipients().is_empty() =>
+                self.schedule_send(SendPreview::new(email), writer),
+            Ok(_) => {
+                self.mode = TuiMode::Writing(writer);
+                self.status = Some("The reply can't be sent as is".to_owned());
+            }
+            Err(err) => {
+                self.mode = TuiMode::Writing(writer);
+                self.status =
+                    Some(format!("Failed to prepare the reply: {err:?}"));
+            }
+        }
+    }
+
+    /// Draws the picture of the sender of the email displayed in the viewer,
+    /// once the frame was rendered
+    fn draw_sender_picture(&mut self) -> Result {
+        let sender = self.picture_slot.and_then(|(id, position)| {
+            Some((self.emails.get(id)?.to_sender_address()?, position))
+        });
+        if self.pictures.draw(
+            sender
+                .as_ref()
+                .map(|(address, position)| (address.as_str(), *position)),
+        )? {
+            self.clear_terminal = true;
+        }
+        Ok(())
+    }
+
+    /// Sets the identities and the wrapping of the opened writer, if any
+    fn configure_writer(&mut self) {
+        let identities = self.to_identities();
+        if let TuiMode::Writing(writer) = &mut self.mode {
+            writer.set_identities(identities);
+            writer.set_wrapping(
+                self.config.as_wrap_column(),
+                self.config.has_format_flowed(),
+            );
+            writer.set_default_dsn(self.config.as_dsn());
+        }
+    }
+
+    /// Main drawer for the TUI
+    ///
+    /// This function is called every loop to re-render the TUI. The theme is
+    /// applied once every widget was drawn.
+    pub fn draw_tui(&mut self, frame: &mut Frame<'_>) -> Result {
+        self.picture_slot = None;
+        let drawn = match &mut self.mode {
+            TuiMode::Help => {
+                manual_page(frame);
+                Ok(())
+            }
+            TuiMode::Writing(writer) => {
+                writer.writer_page(frame);
+                if let Some(preview) = &self.send_preview {
+                    preview.draw(frame, frame.area());
+                }
+                if let Some(status) = &self.status {
+                    let area = frame.area();
+                    frame.render_widget(
+                        Paragraph::new(status.as_str()),
+                        Rect {
+                            y: area.bottom().saturating_sub(1),
+                            height: 1,
+                            ..area
+                        },
+                    );
+                }
+                Ok(())
+            }
+            TuiMode::Reading => self.draw_reader(frame).map(|()| {
+                if let Some(finder) = &self.finder {
+                    finder.draw(frame, frame.area());
+                }
+                if let Some(browser) = &self.browser {
+                    browser.draw(frame, frame.area());
+                }
+                if let Some(dashboard) = &self.dashboard {
+                    dashboard.draw(frame, frame.area());
+                }
+            }),
+        };
+        self.config.as_theme().apply(frame.buffer_mut());
+        drawn
+    }
+
+    /// Draws the reading mode, with the command line or status at the bottom
+    /// if there is one.
+    #[expect(
+        clippy::missing_asserts_for_indexing,
+        clippy::indexing_slicing,
+        reason = "manual check"
+    )]
+    fn draw_reader(&mut self, frame: &mut Frame<'_>) -> Result {
+        let area = self.draw_tab_bar(frame, frame.area())?;
+        let Some(bottom_line) = self.command_line.as_ref().map_or_else(
+            || self.status.clone(),
+            |(prompt, input)| {
+                Some(format!("{}{}", prompt.as_prefix(), input.value()))
+            },
+        ) else {
+            return self.draw_emails(frame, area);
+        };
+
+        let layout = Layout::new(
+            Direction::Vertical,
+            [Constraint::Fill(1), Constraint::Length(1)],
+        )
+        .split(area);
+
+        if layout.len() != 2 {
+            return Err(Error::LayoutLengthFailure.into());
+        }
+
+        self.draw_emails(frame, layout[0])?;
+        frame.render_widget(
+            Text::from(truncate(&bottom_line, usize::from(layout[1].width))),
+            layout[1],
+        );
+        Ok(())
+    }
+
+    /// Handles key events when the command line is opened
+    fn handle_command_line_events(&mut self, event: &Event) {
+        let Some((prompt, input)) = &mut self.command_line else {
+            return;
+        };
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            if *code == KeyCode::Tab && *prompt == Prompt::Command {
+                self.complete_folder();
+                return;
+            }
+            self.completion = None;
+            if *code == KeyCode::Esc {
+                self.command_line = None;
+            } else if *code == KeyCode::Enter {
+                let value = input.value().to_owned();
+                let entered = *prompt;
+                self.command_line = None;
+                let status = match entered {
+                    Prompt::Command => Command::parse(&value).map_or_else(
+                        || format!("Unknown command: {value}"),
+                        |command| self.run_command(&command),
+                    ),
+                    Prompt::Search => self.search_body(&value),
+                };
+                self.status = Some(status);
+            } else {
+                input.handle_event(event);
+            }
+        }
+    }
+
+    /// Completes the folder of the command line, or moves to the next
+    /// matching folder if it was already completed.
+    fn complete_folder(&mut self) {
+        if let Some(completion) = &mut self.completion {
+            completion.next();
+        } else if let Some((_, input)) = &self.command_line {
+            let line = input.value().to_owned();
+            self.completion = Completion::new(&line, self.known_folders());
+        } else {
+            return;
+        }
+        if let Some(completion) = &self.completion
+            && let Some((_, input)) = &mut self.command_line
+        {
+            *input = Input::new(completion.to_line());
+        }
+    }
+
+    /// Returns the folder names of the account, from the cache or else from
+    /// the server
+    fn known_folders(&mut self) -> &[String] {
+        if self.folders.is_empty() {
+            self.folders =
+                self.cache.as_ref().map(load_folders).unwrap_or_default();
+        }
+        if self.folders.is_empty()
+            && let Some(credentials) = &self.credentials
+            && let Ok(folders) =
+                self.sessions.run(credentials, ImapSession::list_mailboxes)
+            && let Err(err) = self.remember_folders(&folders, true)
+        {
+            self.status = Some(format!("Failed to store the folders: {err:?}"));
+        }
+        &self.folders
+    }
+
+    /// Remembers the folder names of the account, to complete them in this
+    /// session and the next ones
+    ///
+    /// The folders listed with a pattern are added to the known ones, that
+    /// are only replaced when every folder was listed.
+    fn remember_folders(
+        &mut self,
+        folders: &[Mailbox],
+        is_complete: bool,
+    ) -> Result {
+        let local_folders = self.to_local_folders();
+        if is_complete {
+            self.folders.clear();
+        }
+        self.folder_roles
+            .extend(folders.iter().filter_map(|folder| {
+                Some((folder.as_name().to_owned(), folder.as_role()?))
+            }));
+        self.folders.extend(
+            folders
+                .iter()
+                .map(|folder| folder.as_name().to_owned())
+                .chain(local_folders),
+        );
+        self.folders.sort();
+        self.folders.dedup();
+        self.cache
+            .as_ref()
+            .map_or(Ok(()), |cache| store_folders(cache, &self.folders))
+    }
+
+    /// Runs a command from the command line and returns the status message
+    fn run_command(&mut self, command: &Command) -> String {
+        let selected_uid = self.emails.get(self.current_id).map(Email::as_uid);
+        match command {
+            Command::Sync => self.start_fetch_task(),
+            Command::Folder(folder) => self.switch_folder(folder),
+            Command::Search(text) => self.search_emails(text),
+            Command::TabClose => self.close_tab(),
+            Command::TabNew(folder) => self.open_folder_tab(folder),
+            Command::Folders(argument) =>
+                self.list_folders(argument.as_deref()),
+            Command::Recent => self.list_recent_folders(),
+            Command::Sort(order) => {
+                self.sort = *order;
+                self.sort_emails();
+                format!("Sorted by {}", order.as_name())
+            }
+            Command::CacheClear => self.clear_cache(),
+            Command::CacheStats => self.cache_stats(),
+            Command::Stats => self.open_dashboard(),
+            Command::Reconnect => self.reconnect(),
+            Command::EditSenders(edit, entry) =>
+                self.edit_senders(*edit, entry.as_deref()),
+            Command::Senders => self.senders.to_description(),
+            Command::DismissReminders => format!(
+                "Dismissed {} reminders",
+                self.reminders.dismiss_due(reminders::to_now())
+            ),
+            Command::AddFlag(_)
+            | Command::Delete
+            | Command::Move(_)
+            | Command::RemoveFlag(_) => {
+                let Some(operation) = selected_uid
+                    .and_then(|uid| command.to_operation(&self.mailbox, uid))
+                else {
+                    return "No email selected".to_owned();
+                };
+                self.apply_operation(operation)
+            }
+            Command::OpenAttachment(index) => self.open_nth_attachment(*index),
+            Command::Extract(index, number, dir) =>
+                self.extract_from_attachment(*index, *number, dir),
+            Command::Resend(recipients) => self.resend(recipients),
+            Command::ExportAttachments(glob, dir) =>
+                match export_attachments(&self.emails, glob, dir) {
+                    Ok(saved) => format!(
+                        "Saved {saved} attachments to {}",
+                        dir.display()
+                    ),
+                    Err(err) =>
+                        format!("Failed to export the attachments: {err:?}"),
+                },
+            Command::ApplyPatches(repository) => {
+                let Some(email) =
+                    self.open_email_id.and_then(|id| self.emails.get(id))
+                else {
+                    return "No email is opened".to_owned();
+                };
+                match self
+                    .load_series(email)
+                    .and_then(|patches| apply_series(&patches, repository))
+                {
+                    Ok(()) => format!(
+                        "Applied the patch series in {}",
+                        repository.display()
+                    ),
+                    Err(err) => format!("Failed to apply patches: {err:?}"),
+                }
+            }
+            Command::Print => {
+                let Some(email) =
+                    self.open_email_id.and_then(|id| self.emails.get(id))
+                else {
+                    return "No email is opened".to_owned();
+                };
+                match print_email(email, self.config.as_print_command()) {
+                    Ok(()) => "Email sent to the printer".to_owned(),
+                    Err(err) => format!("Failed to print email: {err:?}"),
+                }
+            }
+            Command::SaveHtml(path) => self.save_html(path),
+            Command::Invite(path) => self.export_invite(path.as_deref()),
+            Command::Trackers => self.list_trackers(),
+            Command::SavePatches(path) => {
+                let Some(email) =
+                    self.open_email_id.and_then(|id| self.emails.get(id))
+                else {
+                    return "No email is opened".to_owned();
+                };
+                match self.load_series(email).and_then(|patches| {
+                    save_series(&patches, path)?;
+                    Ok(patches.len())
+                }) {
+                    Ok(count) => {
+                        format!("Saved {count} patches to {}", path.display())
+                    }
+                    Err(err) => format!("Failed to save patches: {err:?}"),
+                }
+            }
+        }
+    }
+
+    /// Returns the status message listing the domains of the tracking pixels
+    /// of the opened email
+    fn list_trackers(&self) -> String {
+        let Some(email) = self.open_email_id.and_then(|id| self.emails.get(id))
+        else {
+            return "No email is opened".to_owned();
+        };
+        let mut domains =
+            email.as_html_body().map(find_trackers).unwrap_or_default();
+        let count = domains.len();
+        domains.sort_unstable();
+        domains.dedup();
+        if domains.is_empty() {
+            "No trackers in the opened email".to_owned()
+        } else {
+            format!("{count} trackers from {}", domains.join(", "))
+        }
+    }
+
+    /// Saves the HTML body of the opened email, sanitized unless it is
+    /// trusted, and returns the status message
+    fn save_html(&self, path: &Path) -> String {
+        let Some(email) = self.open_email_id.and_then(|id| self.emails.get(id))
+        else {
+            return "No email is opened".to_owned();
+        };
+        let Some(html) = self.config.as_html_policy().to_html_body(email)
+        else {
+            return "The opened email has no HTML body".to_owned();
+        };
+        match fs::write(path, html) {
+            Ok(()) => format!("Saved the HTML body to {}", path.display()),
+            Err(err) => format!("Failed to save the HTML body: {err:?}"),
+        }
+    }
+
+    /// Saves the calendar invite of the opened email to a file, or pipes it
+    /// into the calendar command without path, and returns the status message
+    fn export_invite(&self, path: Option<&Path>) -> String {
+        let Some(email) = self.open_email_id.and_then(|id| self.emails.get(id))
+        else {
+            return "No email is opened".to_owned();
+        };
+        let Some(invite) = find_invite(email) else {
+            return "The opened email has no calendar invite".to_owned();
+        };
+        let summary = to_summary(invite.as_data());
+        let exported = match (path, self.config.as_calendar_command()) {
+            (Some(target), _) => save_invite(invite, target)
+                .map(|saved| format!("saved to {}", saved.display())),
+            (None, Some(command)) => import_invite(invite, command)
+                .map(|()| format!("imported with '{command}'")),
+            (None, None) =>
+                return "No CALENDAR_COMMAND is set: ':invite <path>' saves \
+                        the invite to a file"
+                    .to_owned(),
+        };
+        match exported {
+            Ok(done) => format!("Invite {summary} {done}"),
+            Err(err) => format!("Failed to export the invite: {err:?}"),
+        }
+    }
+
+    /// Marks the selected email as needing a reply, or unmarks it, and returns
+    /// the status message
+    fn toggle_needs_reply(&mut self) -> String {
+        let Some(uid) = self.emails.get(self.current_id).map(Email::as_uid)
+        else {
+            return "No email selected".to_owned();
+        };
+        let flag = NEEDS_REPLY_FLAG.to_owned();
+        let target = Location::new(&self.mailbox, uid);
+        self.apply_operation(if needs_reply(self.flags.get(&uid)) {
+            Operation::RemoveFlag { flag, target }
+        } else {
+            Operation::AddFlag { flag, target }
+        })
+    }
+
+    /// Flags the email replied to by a sent email as answered, and removes
+    /// its needing a reply mark, if it is listed
+    fn mark_replied(&mut self, writer: &Writer) {
+        let Some(uid) = writer.as_replied_id().and_then(|id| {
+            self.emails
+                .iter()
+                .find(|email| {
+                    email
+                        .to_message_ids(&HeaderName::MessageId)
+                        .iter()
+                        .any(|message_id| message_id == id)
+                })
+                .map(Email::as_uid)
+        }) else {
+            return;
+        };
+        if self
+            .flags
+            .get(&uid)
+            .is_none_or(|flags| !flags.contains(ANSWERED_FLAG))
+        {
+            self.apply_operation(Operation::AddFlag {
+                flag: ANSWERED_FLAG.to_owned(),
+                target: Location::new(&self.mailbox, uid),
+            });
+        }
+        if needs_reply(self.flags.get(&uid)) {
+            self.apply_operation(Operation::RemoveFlag {
+                flag: NEEDS_REPLY_FLAG.to_owned(),
+                target: Location::new(&self.mailbox, uid),
+            });
+        }
+    }
+
+    /// Copies the one-time code of the opened email, or of the selected one,
+    /// to the clipboard, and returns the status message
+    fn copy_code(&self) -> String {
+        let Some(email) = self
+            .emails
+            .get(self.open_email_id.unwrap_or(self.current_id))
+        else {
+            return "No email is selected".to_owned();
+        };
+        let Some(code) = find_code(email) else {
+            return "No one-time code found in the email".to_owned();
+        };
+        match otp::copy(&code, self.config.as_clipboard_command()) {
+            Ok(()) => format!("Code {code} copied"),
+            Err(err) => format!("Failed to copy the code: {err:?}"),
+        }
+    }
+
+    /// Extracts the file with the given number of the archive attached to
+    /// the opened email with the given index, both counted from 1, and
+    /// returns the status message
+    fn extract_from_attachment(
+        &self,
+        index: usize,
+        number: usize,
+        dir: &Path,
+    ) -> String {
+        let Some(attachment) = self
+            .open_email_id
+            .and_then(|id| self.emails.get(id))
+            .and_then(|email| {
+                email.as_attachments().get(index.checked_sub(1)?)
+            })
+        else {
+            return format!("No attachment {index} to extract from");
+        };
+        match archive::extract(attachment, number, dir) {
+            Ok(path) => format!("Extracted {}", path.display()),
+            Err(err) => format!("Failed to extract {index}.{number}: {err:?}"),
+        }
+    }
+
+    /// Opens the attachment of the opened email with the given number,
+    /// counted from 1, and returns the status message
+    fn open_nth_attachment(&mut self, index: usize) -> String {
+        let Some(attachment) = self
+            .open_email_id
+            .and_then(|id| self.emails.get(id))
+            .and_then(|email| {
+                email.as_attachments().get(index.checked_sub(1)?)
+            })
+        else {
+            return format!("No attachment {index} to open");
+        };
+        let opened = Self::suspend_terminal(|| {
+            self.opened_attachments.open(
+                attachment,
+                &self.mailcap,
+                self.config.as_attachment_opener(),
+            )
+        });
+        self.clear_terminal = true;
+        match opened {
+            Ok(Ok(())) => format!("Opened attachment {index}"),
+            Err(err) | Ok(Err(err)) => {
+                format!("Failed to open attachment: {err:?}")
+            }
+        }
+    }
+
+    /// Handles key events
+    fn handle_key_events(&mut self) -> Result {
+        // Wake up regularly to display the progress of the background tasks,
+        // and to run the control requests.
+        if (self.fetch_task.is_some()
+            || self.prune_task.is_some()
+            || self.pending_send.is_some()
+            || self.control.is_some())
+            && !poll(FETCH_POLL_INTERVAL).map_err(Error::IoKeyboard)?
+        {
+            return Ok(());
+        }
+        let event = read().map_err(Error::IoKeyboard)?;
+        if let Some(task) = &self.fetch_task
+            && let Event::Key(KeyEvent { code, modifiers, .. }) = event
+            && (code == KeyCode::Char('c')
+                && modifiers == KeyModifiers::CONTROL
+                || code == KeyCode::Esc
+                    && matches!(self.mode, TuiMode::Reading)
+                    && self.command_line.is_none())
+        {
+            task.cancel();
+            self.status = Some("Cancelling the fetch...".to_owned());
+            return Ok(());
+        }
+        if self.send_preview.is_some() {
+            self.handle_preview_events(&event);
+            return Ok(());
+        }
+        // The email can be sent while a field is edited, as 'S' is typed in it.
+        if matches!(self.mode, TuiMode::Writing(_))
+            && let Event::Key(KeyEvent {
+                code: KeyCode::Enter | KeyCode::Char('s'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) = event
+        {
+            self.open_preview();
+            return Ok(());
+        }
+        if let TuiMode::Writing(writer) = &mut self.mode
+            && writer.handle_key_events(&event)
+        {
+            return Ok(());
+        }
+        if self.command_line.is_some() {
+            self.handle_command_line_events(&event);
+            return Ok(());
+        }
+        if self.finder.is_some() {
+            return self.handle_finder_events(&event);
+        }
+        if self.browser.is_some() {
+            self.handle_browser_events(&event);
+            return Ok(());
+        }
+        if self.dashboard.is_some() {
+            self.handle_dashboard_events(&event);
+            return Ok(());
+        }
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('p'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) if matches!(self.mode, TuiMode::Reading) => self.open_finder(),
+            Event::Key(KeyEvent { code: KeyCode::Char(ch), .. }) =>
+                self.handle_char(ch)?,
+            Event::Key(_)
+            | Event::FocusGained
+            | Event::FocusLost
+            | Event::Mouse(_)
+            | Event::Paste(_)
+            | Event::Resize(..) => (),
+        }
+        Ok(())
+    }
+
+    /// Opens the fuzzy finder on the folders and the loaded emails.
+    fn open_finder(&mut self) {
+        let folders = self.known_folders().to_vec();
+        self.finder = Some(Finder::new(&folders, &self.emails));
+    }
+
+    /// Previews the written email, to confirm it must be sent
+    fn open_preview(&mut self) {
+        let TuiMode::Writing(writer) = &self.mode else {
+            return;
+        };
+        match writer.to_email() {
+            Ok(Some(email)) if email.to_recipients().is_empty() =>
+                self.status = Some("No destination to send to".to_owned()),
+            Ok(Some(email)) =>
+                self.send_preview = Some(SendPreview::new(email)),
+            Ok(None) =>
+                self.status = Some("No identity to send from".to_owned()),
+            Err(err) =>
+                self.status =
+                    Some(format!("Failed to prepare the email: {err:?}")),
+        }
+    }
+
+    /// Handles key events when the preview of the written email is opened
+    fn handle_preview_events(&mut self, event: &Event) {
+        let (Some(preview), Event::Key(key)) = (&mut self.send_preview, event)
+        else {
+            return;
+        };
+        // Only a plain `y` sends the email, to avoid sending it by mistake.
+        match (key.code, key.modifiers.is_empty()) {
+            (KeyCode::Char('y'), true) => self.confirm_send(),
+            (KeyCode::Char('n') | KeyCode::Esc, _) => self.send_preview = None,
+            (KeyCode::Char('j') | KeyCode::Down, _) => preview.scroll_down(),
+            (KeyCode::Char('k') | KeyCode::Up, _) => preview.scroll_up(),
+            _ => (),
+        }
+    }
+
+    /// Closes the writer and schedules the sending of the previewed email
+    ///
+    /// The email is sent right away if there is no undo send delay.
+    fn confirm_send(&mut self) {
+        let Some(preview) = self.send_preview.take() else {
+            return;
+        };
+        let TuiMode::Writing(writer) =
+            mem::replace(&mut self.mode, TuiMode::Reading)
+        else {
+            return;
+        };
+        self.schedule_send(preview, writer);
+    }
+
+    /// Schedules the sending of a previewed email after the undo send delay
+    ///
+    /// An email that was already waiting is sent right away, not to lose it.
+    fn schedule_send(&mut self, preview: SendPreview, writer: Box<Writer>) {
+        if let Some(previous) = self.pending_send.take() {
+            self.send_message(previous);
+        }
+        let pending =
+            PendingSend::new(preview, writer, self.config.as_undo_send_delay());
+        if pending.is_due() {
+            self.send_message(pending);
+        } else {
+            self.status = Some(pending.to_status());
+            self.pending_send = Some(pending);
+        }
+    }
+
+    /// Reopens the writer of the email waiting to be sent, and cancels the
+    /// sending.
+    fn undo_send(&mut self) {
+        if let Some(pending) = self.pending_send.take() {
+            let (_, writer) = pending.into_parts();
+            self.mode = TuiMode::Writing(writer);
+            self.status = Some("Sending cancelled".to_owned());
+        }
+    }
+
+    /// Sends the email once the undo send delay is over, or displays the
+    /// remaining time.
+    fn update_pending_send(&mut self) {
+        match self.pending_send.take() {
+            Some(pending) if pending.is_due() => self.send_message(pending),
+            Some(pending) => {
+                self.status = Some(pending.to_status());
+                self.pending_send = Some(pending);
+            }
+            None => (),
+        }
+    }
+
+    /// Sends a confirmed email
+    ///
+    /// The writer is reopened if the email can't be sent, not to lose it.
+    fn send_message(&mut self, pending: PendingSend) {
+        let (preview, writer) = pending.into_parts();
+        let Some(credentials) = &self.credentials else {
+            self.status = Some("No account to send from".to_owned());
+            return;
+        };
+        let email = preview.as_email();
+        let sent = send_or_queue(
+            credentials,
+            email.as_from(),
+            &email.to_recipients(),
+            preview.as_message().as_bytes(),
+            writer.as_dsn(),
+        );
+        self.status = Some(match sent {
+            Ok(()) => {
+                self.draft_save = None;
+                self.mark_replied(&writer);
+                if writer.as_reminder_days() != 0 {
+                    self.reminders.add(
+                        preview.as_message(),
+                        writer.as_reminder_days(),
+                        reminders::to_now(),
+                    );
+                }
+                match self.cache.as_ref().map(clear_draft) {
+                    Some(Err(err)) =>
+                        format!("Email sent, but the draft remains: {err:?}"),
+                    Some(Ok(())) | None => "Email sent".to_owned(),
+                }
+            }
+            Err(err) => {
+                self.mode = TuiMode::Writing(writer);
+                format!("Failed to send the email: {err:?}")
+            }
+        });
+    }
+
+    /// Opens the file browser to save the attachments of the opened email
+    fn open_save_browser(&mut self) {
+        if self
+            .open_email_id
+            .and_then(|id| self.emails.get(id))
+            .is_some_and(|email| !email.as_attachments().is_empty())
+        {
+            self.browser = Some(Browser::new(Purpose::Save));
+        } else {
+            self.status = Some("No attachment to save".to_owned());
+        }
+    }
+
+    /// Handles key events when the file browser is opened
+    fn handle_browser_events(&mut self, event: &Event) {
+        let (Some(browser), Event::Key(key)) = (&mut self.browser, event)
+        else {
+            return;
+        };
+        match browser.handle_key(key.code) {
+            Some(Choice::Chosen(paths)) => {
+                self.browser = None;
+                if let Some(dir) = paths.first() {
+                    self.status = Some(self.save_attachments(dir));
+                }
+            }
+            Some(Choice::Cancelled) => self.browser = None,
+            None => (),
+        }
+    }
+
+    /// Saves the attachments of the opened email in a directory
+    ///
+    /// Returns the status message to display.
+    fn save_attachments(&self, dir: &Path) -> String {
+        let Some(email) = self.open_email_id.and_then(|id| self.emails.get(id))
+        else {
+            return "No email is opened".to_owned();
+        };
+        let attachments = email.as_attachments();
+        match attachments.iter().try_for_each(|attachment| {
+            fs::write(dir.join(attachment.to_file_name()), attachment.as_data())
+        }) {
+            Ok(()) => format!(
+                "Saved {} attachments to {}",
+                attachments.len(),
+                dir.display()
+            ),
+            Err(err) => format!("Failed to save the attachments: {err:?}"),
+        }
+    }
+
+    /// Handles key events when the fuzzy finder is opened
+    fn handle_finder_events(&mut self, event: &Event) -> Result {
+        let Some(finder) = &mut self.finder else {
+            return Ok(());
+        };
+        let Event::Key(KeyEvent { code, modifiers, .. }) = event else {
+            return Ok(());
+        };
+        match (code, *modifiers == KeyModifiers::CONTROL) {
+            (KeyCode::Esc, _) => self.finder = None,
+            (KeyCode::Enter, _) => {
+                if let Some(target) = finder.as_selected().cloned() {
+                    self.jump_to(target)?;
+                }
+                self.finder = None;
+            }
+            (KeyCode::Up, _) | (KeyCode::Char('p'), true) =>
+                finder.select_previous(),
+            (KeyCode::Down, _) | (KeyCode::Char('n'), true) =>
+                finder.select_next(),
+            _ => {
+                finder.as_input_mut().handle_event(event);
+                finder.update_results();
+            }
+        }
+        Ok(())
+    }
+
+    /// Jumps to a result of the fuzzy finder
+    ///
+    /// A folder is opened, a contact filters the list on their emails, and an
+    /// email is selected.
+    fn jump_to(&mut self, target: Target) -> Result {
+        match target {
+            Target::Contact(sender) => {
+                self.filters.activate(QuickFilter::Sender(sender));
+                self.apply_filters();
+                self.status = Some(self.to_filters_status());
+            }
+            Target::Email(uid, _) => {
+                if let Some(id) =
+                    self.emails.iter().position(|email| email.as_uid() == uid)
+                {
+                    self.move_selection(id)?;
+                }
+            }
+            Target::Folder(folder) =>
+                self.status = Some(self.switch_folder(&folder)),
+        }
+        Ok(())
+    }
+
+    /// Handles a pressed character, outside of the command line and the
+    /// writer
+    fn handle_char(&mut self, ch: char) -> Result {
+        let count = self.count.take();
+        let pending_g = mem::take(&mut self.pending_g);
+        if mem::take(&mut self.pending_filter) {
+            self.status = Some(self.toggle_filter(ch));
+            return Ok(());
+        }
+        if mem::take(&mut self.pending_response) {
+            self.reply_with_template(ch);
+            return Ok(());
+        }
+        match ch {
+            'q' => self.running = false,
+            'w' => {
+                self.mode.new_writer();
+                self.configure_writer();
+            }
+            'W' => self.restore_draft(),
+            'U' => self.undo_send(),
+            'S' if matches!(self.mode, TuiMode::Writing(_)) =>
+                self.open_preview(),
+            'r' => self.mode = TuiMode::Reading,
+            'm' => self.mode = TuiMode::Help,
+            _ if matches!(self.mode, TuiMode::Reading) =>
+                self.handle_reading_char(ch, count, pending_g)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Handles a pressed character in reading mode, with the count typed
+    /// before it and whether it follows a `g`
+    fn handle_reading_char(
+        &mut self,
+        ch: char,
+        count: Option<usize>,
+        pending_g: bool,
+    ) -> Result {
+        match ch {
+            '0' if pending_g => self.status = Some(self.open_previous_folder()),
+            '1'..='9' if pending_g =>
+                self.status = Some(self.open_folder_shortcut(ch)),
+            '0'..='9' => {
+                let digit = ch
+                    .to_digit(10)
+                    .and_then(|digit| usize::try_from(digit).ok())
+                    .unwrap_or_default();
+                self.count = Some(
+                    count
+                        .unwrap_or_default()
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+            }
+            'j' => self.move_selection(
+                self.current_id.saturating_add(count.unwrap_or(1)),
+            )?,
+            'k' => self.move_selection(
+                self.current_id.saturating_sub(count.unwrap_or(1)),
+            )?,
+            'J' => self.select_unread(true)?,
+            'K' => self.select_unread(false)?,
+            'g' =>
+                if pending_g {
+                    self.select_email(count.unwrap_or(1).saturating_sub(1));
+                } else {
+                    self.count = count;
+                    self.pending_g = true;
+                },
+            't' if pending_g => self.status = Some(self.next_tab(count)),
+            'T' if pending_g => self.status = Some(self.previous_tab(count)),
+            'G' => self.select_email(
+                count.map_or(usize::MAX, |line| line.saturating_sub(1)),
+            ),
+            'l' => self.open_selected()?,
+            'R' => self.open_reply(),
+            'c' if self.open_email_id.is_some() => self.pending_response = true,
+            'E' => self.edit_as_new(),
+            'y' => self.status = Some(self.copy_code()),
+            '!' => self.status = Some(self.toggle_needs_reply()),
+            'd' => self.scroll_body(true, count),
+            'u' => self.scroll_body(false, count),
+            'h' => {
+                self.open_email_id = None;
+                self.open_email_body = None;
+            }
+            '[' => self.jump_in_thread(Threads::as_parent, "parent email")?,
+            ']' => self.jump_in_thread(
+                Threads::next_sibling,
+                "next reply to the parent",
+            )?,
+            '}' => self.jump_in_thread(
+                Threads::next_in_thread,
+                "next email in the conversation",
+            )?,
+            'F' => self.pending_filter = true,
+            'S' => self.open_save_browser(),
+            '<' => self.split.shrink(),
+            '>' => self.split.grow(),
+            'z' => self.split.toggle_zoom(),
+            'L' => self.links_hidden = !self.links_hidden,
+            'X' => self.toggle_noise()?,
+            ':' =>
+                self.command_line = Some((Prompt::Command, Input::default())),
+            '/' if self.open_email_id.is_some() =>
+                self.command_line = Some((Prompt::Search, Input::default())),
+            'n' => self.status = Some(self.move_in_body_search(true)),
+            'N' => self.status = Some(self.move_in_body_search(false)),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Scrolls the body of the opened email down, or up if `down` isn't set,
+    /// by `count` times [`SCROLL_LINES`]
+    ///
+    /// The position is remembered, to be restored when the email is opened
+    /// again.
+    fn scroll_body(&mut self, down: bool, count: Option<usize>) {
+        let Some(uid) = self
+            .open_email_id
+            .and_then(|id| self.emails.get(id))
+            .map(Email::as_uid)
+        else {
+            return;
+        };
+        let lines = u16::try_from(count.unwrap_or(1))
+            .unwrap_or(u16::MAX)
+            .saturating_mul(SCROLL_LINES);
+        let offset = self.positions.get(&self.mailbox, uid);
+        let scrolled = if down {
+            offset.saturating_add(lines)
+        } else {
+            offset.saturating_sub(lines)
+        };
+        self.positions.set(&self.mailbox, uid, scrolled);
+    }
+
+    /// Checks if an email wasn't read
+    ///
+    /// Emails whose flags are unknown are considered read.
+    fn is_unread(&self, email: &Email) -> bool {
+        self.flags
+            .get(&email.as_uid())
+            .is_some_and(|flags| !flags.contains(SEEN_FLAG))
+    }
+
+    /// Selects another email, and displays it in place of the opened one if
+    /// an email is opened.
+    fn move_selection(&mut self, id: usize) -> Result {
+        self.select_email(id);
+        if self.open_email_id.is_some()
+            && self.open_email_id != Some(self.current_id)
+        {
+            self.open_email()?;
+        }
+        Ok(())
+    }
+
+    /// Selects the next unread email below the selected one, or above it if
+    /// `below` isn't set
+    ///
+    /// The opened email is replaced like with [`Self::move_selection`].
+    fn select_unread(&mut self, below: bool) -> Result {
+        let unread = if below {
+            self.emails
+                .iter()
+                .enumerate()
+                .skip(self.current_id.saturating_add(1))
+                .find(|(_, email)| self.is_unread(email))
+        } else {
+            self.emails
+                .iter()
+                .enumerate()
+                .take(self.current_id)
+                .rfind(|(_, email)| self.is_unread(email))
+        };
+        match unread.map(|(id, _)| id) {
+            Some(id) => self.move_selection(id)?,
+            None =>
+                self.status = Some(if below {
+                    "No unread email below".to_owned()
+                } else {
+                    "No unread email above".to_owned()
+                }),
+        }
+        Ok(())
+    }
+
+    /// Selects the email with the given id, or the last one if it is out of
+    /// bounds.
+    const fn select_email(&mut self, id: usize) {
+        let last = self.emails.len().saturating_sub(1);
+        self.current_id = if id > last { last } else { id };
+    }
+
+    /// Applies an operation on the selected email
+    ///
+    /// When offline, the operation is queued to be replayed once reconnected.
+    /// If the server can't be reached, the TUI switches to offline mode.
+    fn apply_operation(&mut self, operation: Operation) -> String {
+        if let Some(target) = operation.as_target()
+            && sent_replies::is_merged(target.as_uid())
+        {
+            return "This reply was merged from the sent folder: change it \
+                    from there"
+                .to_owned();
+        }
+        if local::is_folder(&self.mailbox)
+            || matches!(&operation, Operation::Move { mailbox: target, .. } if local::is_folder(target))
+        {
+            return self.apply_local_operation(&operation);
+        }
+        let description = operation.to_string();
+        if !self.offline
+            && let Some(credentials) = &self.credentials
+        {
+            match Mailbox::try_from(self.mailbox.as_str())
+                .map_err(Into::into)
+                .and_then(|mailbox| {
+                    self.sessions.run_in(credentials, &mailbox, |session| {
+                        let training =
+                            self.load_training(&operation, Some(session));
+                        let applied = match &operation {
+                            Operation::AddFlag { flag, target } =>
+                                session.add_flag(target.as_uid(), flag),
+                            Operation::Delete { target } =>
+                                session.delete(target.as_uid()),
+                            Operation::Move {
+                                mailbox: destination,
+                                target,
+                            } => Mailbox::try_from(destination.as_str())
+                                .map_err(Into::into)
+                                .and_then(|folder| {
+                                    session.move_to(target.as_uid(), &folder)
+                                }),
+                            Operation::RemoveFlag { flag, target } =>
+                                session.remove_flag(target.as_uid(), flag),
+                            Operation::Send { .. } => Ok(()),
+                        };
+                        Ok((training, applied))
+                    })
+                }) {
+                Ok((training, applied)) => {
+                    return match applied {
+                        Ok(()) => {
+                            self.apply_operation_locally(&operation);
+                            format!(
+                                "Done: {description}{}",
+                                self.train_junk_filter(training)
+                            )
+                        }
+                        Err(err) => format!("Failed to {description}: {err:?}"),
+                    };
+                }
+                Err(_) => self.offline = true,
+            }
+        }
+
+        if self.cache.is_none() {
+            return format!("Offline, can't {description} without a cache");
+        }
+        let training = self.load_training(&operation, None);
+        self.apply_operation_locally(&operation);
+        if let Some(cache) = &self.cache
+            && let Err(err) = Queue::new(cache).push(operation)
+        {
+            return format!("Failed to queue {description}: {err:?}");
+        }
+        format!(
+            "Offline, queued: {description}{}",
+            self.train_junk_filter(training)
+        )
+    }
+
+    /// Applies an operation on an email of a local folder, or moving an email
+    /// to one, and returns the status message
+    ///
+    /// An email moved between the server and a local folder is copied first,
+    /// and removed from its folder only once the copy succeeded.
+    fn apply_local_operation(&mut self, operation: &Operation) -> String {
+        let description = operation.to_string();
+        let Some(cache) = self.cache.clone() else {
+            return format!("Can't {description} without a cache");
+        };
+        if let Operation::Move { mailbox: destination, target } = operation {
+            let uid = target.as_uid();
+            let copied = if local::is_folder(destination) {
+                self.load_raw(uid)
+                    .and_then(|raw| {
+                        let flags =
+                            self.flags.get(&uid).cloned().unwrap_or_default();
+                        local::add_email(&cache, destination, &raw, flags)
+                    })
+                    .map(|_| ())
+            } else if let Some(credentials) = &self.credentials {
+                self.upload_email(&cache, credentials, uid, destination)
+            } else {
+                return "No credentials to upload the email with".to_owned();
+            };
+            if let Err(err) = copied {
+                return format!("Failed to {description}: {err:?}");
+            }
+            if !local::is_folder(&self.mailbox) {
+                let removed = self.apply_operation(Operation::Delete {
+                    target: Location::new(&self.mailbox, uid),
+                });
+                return format!("Copied to {destination}. {removed}");
+            }
+        }
+        let stored = match operation {
+            Operation::Delete { target } | Operation::Move { target, .. } =>
+                local::remove_email(&cache, &self.mailbox, target.as_uid()),
+            Operation::AddFlag { .. }
+            | Operation::RemoveFlag { .. }
+            | Operation::Send { .. } => Ok(()),
+        };
+        self.apply_operation_locally(operation);
+        match stored
+            .and_then(|()| cache.store_flags(&self.mailbox, &self.flags))
+        {
+            Ok(()) => format!("Done: {description}"),
+            Err(err) => format!("Failed to store {description}: {err:?}"),
+        }
+    }
+
+    /// Uploads an email of the opened local folder to a folder of the server,
+    /// with its flags
+    fn upload_email(
+        &self,
+        cache: &Cache,
+        credentials: &Credentials,
+        uid: u32,
+        target: &str,
+    ) -> Result {
+        let raw = cache
+            .load_email(&self.mailbox, uid)?
+            .ok_or(Error::MissingEmail(uid))?;
+        let flags = self
+            .flags
+            .get(&uid)
+            .map(|flags| flags.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let mailbox = Mailbox::try_from(target)?;
+        self.sessions.run(credentials, |session| {
+            session.append(&mailbox, raw.as_bytes(), &flags, None)
+        })
+    }
+
+    /// Resends the selected email as is to new recipients, from the identity
+    /// it was sent to, and returns the status message
+    fn resend(&self, recipients: &[String]) -> String {
+        let Some(email) = self.emails.get(self.current_id) else {
+            return "No email selected".to_owned();
+        };
+        let identities = self.to_identities();
+        let Some(identity) = identities.get(find_for_reply(&identities, email))
+        else {
+            return "No identity to send from".to_owned();
+        };
+        let raw = match self.load_raw(email.as_uid()) {
+            Ok(raw) => raw,
+            Err(err) =>
+                return format!("Failed to load the email to resend: {err:?}"),
+        };
+        let Some(credentials) = &self.credentials else {
+            return "No account to send from".to_owned();
+        };
+        let message = to_resent(&raw, identity, recipients, SystemTime::now());
+        match send_or_queue(
+            credentials,
+            identity.as_address(),
+            recipients,
+            message.as_bytes(),
+            self.config.as_dsn(),
+        ) {
+            Ok(()) => format!("Resent to {}", recipients.join(", ")),
+            Err(err) => format!("Failed to resend the email: {err:?}"),
+        }
+    }
+
+    /// Returns the raw emails of the patch series the email belongs to, in
+    /// order
+    fn load_series(&self, email: &Email) -> Result<Vec<String>> {
+        find_series(&self.emails, email)?
+            .iter()
+            .map(|patch| self.load_raw(patch.as_uid()))
+            .collect()
+    }
+
+    /// Returns the raw content of an email of the opened folder, from the
+    /// cache or else from the server
+    fn load_raw(&self, uid: u32) -> Result<String> {
+        if let Some(cache) = &self.cache
+            && let Some(raw) = cache.load_email(&self.mailbox, uid)?
+        {
+            return Ok(raw);
+        }
+        let Some(credentials) =
+            self.credentials.as_ref().filter(|_| !self.offline)
+        else {
+            return Err(Error::MissingEmail(uid).into());
+        };
+        let mailbox = Mailbox::try_from(self.mailbox.as_str())?;
+        self.sessions.run_in(credentials, &mailbox, |session| {
+            session.peek_mail_from_uid(uid)
+        })
+    }
+
+    /// Returns the raw email to train the spam filter with, if the operation
+    /// moves it to or out of the junk folder
+    ///
+    /// The email is read from the cache, or from the server if it isn't
+    /// cached and the session is given.
+    fn load_training(
+        &self,
+        operation: &Operation,
+        session: Option<&mut ImapSession<MailboxSelected>>,
+    ) -> Result<Option<(Verdict, Vec<u8>)>> {
+        let Operation::Move { mailbox, target } = operation else {
+            return Ok(None);
+        };
+        let Some(verdict) = self
+            .config
+            .as_junk_training()
+            .to_verdict(&self.mailbox, mailbox)
+        else {
+            return Ok(None);
+        };
+        let uid = target.as_uid();
+        let cached = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.load_email(&self.mailbox, uid))
+            .transpose()?
+            .flatten();
+        let raw = match (cached, session) {
+            (Some(raw), _) => raw,
+            (None, Some(selected)) => selected.peek_mail_from_uid(uid)?,
+            (None, None) => return Ok(None),
+        };
+        Ok(Some((verdict, raw.into_bytes())))
+    }
+
+    /// Trains the spam filter with an email moved to or out of the junk
+    /// folder, and returns the status to append to the one of the move
+    fn train_junk_filter(
+        &self,
+        training: Result<Option<(Verdict, Vec<u8>)>>,
+    ) -> String {
+        match training {
+            Ok(Some((verdict, raw))) =>
+                match self.config.as_junk_training().learn(verdict, raw) {
+                    Ok(()) => format!(", learnt as {}", verdict.as_name()),
+                    Err(err) =>
+                        format!(", failed to train the spam filter: {err:?}"),
+                },
+            Ok(None) => String::new(),
+            Err(err) => format!(
+                ", failed to load the email to train the spam filter: {err:?}"
+            ),
+        }
+    }
+
+    /// Updates the list of emails after an operation
+    ///
+    /// Deleted and moved emails are removed from the list.
+    fn apply_operation_locally(&mut self, operation: &Operation) {
+        match operation {
+            Operation::AddFlag { flag, target } => {
+                self.flags
+                    .entry(target.as_uid())
+                    .or_default()
+                    .insert(flag.clone());
+            }
+            Operation::RemoveFlag { flag, target } => {
+                if let Some(flags) = self.flags.get_mut(&target.as_uid()) {
+                    flags.remove(flag);
+                }
+            }
+            Operation::Delete { target } | Operation::Move { target, .. } => {
+                self.flags.remove(&target.as_uid());
+            }
+            Operation::Send { .. } => (),
+        }
+        if let Operation::Delete { target } | Operation::Move { target, .. } =
+            operation
+            && let Some(position) = self
+                .emails
+                .iter()
+                .position(|email| email.as_uid() == target.as_uid())
+        {
+            self.emails.remove(position);
+            self.uids.retain(|other| *other != target.as_uid());
+            self.open_email_id = None;
+            self.open_email_body = None;
+            self.select_email(self.current_id);
+        }
+    }
+
+    /// Fetches the uids of the mailbox, its first emails and the flags of
+    /// every email
+    ///
+    /// The first emails get their flags, date of reception and size from the
+    /// server.
+    ///
+    /// The emails that are already in the cache aren't fetched again, and the
+    /// new ones are stored in the cache.
+    fn fetch_first_emails(
+        credentials: &Credentials,
+        sessions: &SessionManager,
+        cache: Option<&Cache>,
+        mailbox: &str,
+    ) -> Result<(Vec<u32>, Vec<Email>, Flags)> {
+        sessions.run_in(credentials, &Mailbox::try_from(mailbox)?, |session| {
+            let uids = session.get_uids()?;
+            if let Some(storage) = cache {
+                storage.store_uids(mailbox, &uids)?;
+                if let Some(validity) = session.as_validity() {
+                    storage.store_validity(mailbox, validity)?;
+                }
+            }
+
+            let attachments = session.get_attachment_counts()?;
+            if let Some(storage) = cache {
+                storage.store_attachment_counts(mailbox, &attachments)?;
+            }
+
+            let mut metadata = session.get_metadata()?;
+            let flags = metadata
+                .iter()
+                .map(|(uid, data)| (*uid, data.as_flags().clone()))
+                .collect();
+
+            let emails = uids
+                .iter()
+                .take(20)
+                .map(|uid| {
+                    let cached = cache
+                        .map(|storage| storage.load_email(mailbox, *uid))
+                        .transpose()?
+                        .flatten();
+                    let body = if let Some(body) = cached {
+                        body
+                    } else {
+                        let body = session.get_mail_from_uid(*uid)?;
+                        if let Some(storage) = cache {
+                            storage.store_email(mailbox, *uid, &body)?;
+                        }
+                        body
+                    };
+                    let mut email = Email::try_from((*uid, body.as_bytes()))?;
+                    if let Some(data) = metadata.remove(uid) {
+                        email.set_metadata(data);
+                    }
+                    Ok(email)
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            if let Some(storage) = cache {
+                let mut index = storage.load_thread_index(mailbox);
+                for email in &emails {
+                    index.add(email);
+                }
+                storage.store_thread_index(mailbox, &index)?;
+            }
+
+            Ok((uids, emails, flags))
+        })
+    }
+
+    /// Checks if a full sync of the mailbox was interrupted
+    ///
+    /// This is the case if some emails weren't fetched yet, but are older
+    /// than the last email fetched by the sync.
+    fn has_interrupted_sync(&self) -> bool {
+        self.cache
+            .as_ref()
+            .and_then(|cache| cache.load_sync_progress(&self.mailbox))
+            .is_some_and(|last| {
+                self.uids
+                    .iter()
+                    .skip(self.count_loaded())
+                    .any(|uid| *uid < last)
+            })
+    }
+
+    /// Returns the names of the local folders of the cache
+    fn to_local_folders(&self) -> Vec<String> {
+        self.cache
+            .as_ref()
+            .and_then(|cache| cache.list_folders().ok())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|folder| local::is_folder(folder))
+            .collect()
+    }
+
+    /// Lists the folders of the account, split between the synced, the
+    /// online-only and the local ones
+    ///
+    /// Only one level of the hierarchy is listed, or the folders matching a
+    /// filter, see [`to_list_pattern`], not to list thousands of folders at
+    /// once. The folders with children are followed by their delimiter, to
+    /// list them with `:folders <parent>/`. The folder names are remembered
+    /// to complete them.
+    fn list_folders(&mut self, argument: Option<&str>) -> String {
+        let Some(credentials) = &self.credentials else {
+            return "No credentials to list the folders with".to_owned();
+        };
+        let pattern = to_list_pattern(argument);
+        match self.sessions.run(credentials, |session| {
+            session.list_mailboxes_matching(&pattern)
+        }) {
+            Ok(folders) => {
+                if let Err(err) = self.remember_folders(&folders, false) {
+                    return format!("Failed to store the folders: {err:?}");
+                }
+                let names = folders
+                    .iter()
+                    .map(|folder| folder.as_name().to_owned())
+                    .collect::<Vec<_>>();
+                let (synced, online_only) =
+                    self.config.as_folder_filter().partition(&names);
+                let to_listed = |listed: Vec<&str>| {
+                    listed
+                        .into_iter()
+                        .filter_map(|name| {
+                            folders
+                                .iter()
+                                .find(|folder| folder.as_name() == name)
+                                .map(Mailbox::to_listed_name)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let local_only = self
+                    .to_local_folders()
+                    .into_iter()
+                    .filter(|folder| {
+                        argument.is_none_or(|filter| folder.contains(filter))
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "Synced: {} | Online-only: {} | Local: {}",
+                    to_listed(synced),
+                    to_listed(online_only),
+                    local_only.join(", ")
+                )
+            }
+            Err(err) => format!("Failed to list the folders: {err:?}"),
+        }
+    }
+
+    /// Loads the uids and the first emails of a folder, with a status message
+    ///
+    /// If the server can't be reached, the emails are read from the cache and
+    /// the folder is marked as offline.
+    fn load_folder(
+        credentials: &Credentials,
+        sessions: &SessionManager,
+        config: &Config,
+        cache: Option<&Cache>,
+        mailbox: &str,
+    ) -> Result<LoadedFolder> {
+        if local::is_folder(mailbox) {
+            let local_cache = cache.ok_or(Error::MissingCache)?;
+            let (uids, emails) =
+                Self::load_cached_emails(local_cache, mailbox)?;
+            let flags = local_cache.load_flags(mailbox)?;
+            return Ok((uids, emails, flags, None, false));
+        }
+        let mailbox_cache =
+            cache.filter(|_| config.as_folder_filter().is_synced(mailbox));
+        match Self::fetch_first_emails(
+            credentials,
+            sessions,
+            mailbox_cache,
+            mailbox,
+        ) {
+            Ok((uids, emails, server_flags)) => {
+                let (flags, status) = match cache {
+                    Some(storage) => {
+                        match storage.synchronise(credentials, mailbox) {
+                            Ok(status) =>
+                                (storage.load_flags(mailbox)?, Some(status)),
+                            Err(err) => (
+                                server_flags,
+                                Some(format!("Failed to synchronise: {err:?}")),
+                            ),
+                        }
+                    }
+                    None => (server_flags, None),
+                };
+                Ok((uids, emails, flags, status, false))
+            }
+            Err(err) => {
+                let Some(offline_cache) = cache else {
+                    return Err(err);
+                };
+                let (uids, emails) =
+                    Self::load_cached_emails(offline_cache, mailbox)?;
+                let status = format!(
+                    "Offline, showing the cached emails ({err:?}). Use :reconnect to retry."
+                );
+                let flags = offline_cache.load_flags(mailbox)?;
+                Ok((uids, emails, flags, Some(status), true))
+            }
+        }
+    }
+
+    /// Loads the emails stored in the cache, with the uids of the mailbox
+    ///
+    /// The emails whose body was pruned keep the number of attachments
+    /// announced by the server.
+    fn load_cached_emails(
+        cache: &Cache,
+        mailbox: &str,
+    ) -> Result<(Vec<u32>, Vec<Email>)> {
+        let uids = cache.load_uids(mailbox)?;
+        let attachments = cache.load_attachment_counts(mailbox);
+        let mut emails = vec![];
+        for uid in &uids {
+            if let Some(body) = cache.load_email(mailbox, *uid)? {
+                emails.extend(Email::try_from((*uid, body.as_bytes())).ok());
+                continue;
+            }
+            let Some(headers) = cache.load_headers(mailbox, *uid)? else {
+                continue;
+            };
+            if let Ok(mut email) = Email::try_from((*uid, headers.as_bytes())) {
+                if let Some(count) = attachments.get(uid) {
+                    email.set_attachment_count(*count);
+                }
+                emails.push(email);
+            }
+        }
+        Ok((uids, emails))
+    }
+
+    /// Returns the statistics of the cache
+    fn cache_stats(&self) -> String {
+        let Some(cache) = &self.cache else {
+            return "No cache is configured".to_owned();
+        };
+        match retention::stats(cache) {
+            Ok(stats) => stats.to_string(),
+            Err(err) => format!("Failed to read the cache: {err:?}"),
+        }
+    }
+
+    /// Opens the statistics of the cached emails, and returns the status
+    /// message
+    fn open_dashboard(&mut self) -> String {
+        let Some(cache) = &self.cache else {
+            return "No cache is configured".to_owned();
+        };
+        let identities = self.to_identities();
+        let addresses = identities
+            .iter()
+            .map(Identity::as_address)
+            .collect::<Vec<_>>();
+        match Statistics::compute(cache, &addresses) {
+            Ok(statistics) => {
+                self.dashboard = Some(Dashboard::new(&statistics));
+                "Statistics of the cached emails".to_owned()
+            }
+            Err(err) => format!("Failed to read the cache: {err:?}"),
+        }
+    }
+
+    /// Handles key events when the statistics are opened
+    fn handle_dashboard_events(&mut self, event: &Event) {
+        let (Some(dashboard), Event::Key(key)) = (&mut self.dashboard, event)
+        else {
+            return;
+        };
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('q') | KeyCode::Esc, _) => self.dashboard = None,
+            (KeyCode::Char('j') | KeyCode::Down, _) => dashboard.scroll_down(),
+            (KeyCode::Char('k') | KeyCode::Up, _) => dashboard.scroll_up(),
+            _ => (),
+        }
+    }
+
+    /// Removes every email from the cache
+    ///
+    /// The emails already loaded are kept in the list.
+    fn clear_cache(&self) -> String {
+        let Some(cache) = &self.cache else {
+            return "No cache is configured".to_owned();
+        };
+        match retention::clear(cache) {
+            Ok(()) => "Cache cleared".to_owned(),
+            Err(err) => format!("Failed to clear the cache: {err:?}"),
+        }
+    }
+
+    /// Loads the conversations of the opened folder from the cache, and
+    /// indexes the loaded emails that weren't synced
+    fn load_thread_index(&mut self) {
+        self.thread_index = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.load_thread_index(&self.mailbox))
+            .unwrap_or_default();
+        for email in self.emails.iter().chain(&self.filtered_out) {
+            self.thread_index.add(email);
+        }
+    }
+
+    /// Sorts the emails of the list, with the replies of the sent folder
+    /// merged into their conversations in the `thread` order
+    fn sort_list(&mut self) {
+        let is_own = |email: &Email| !sent_replies::is_merged(email.as_uid());
+        self.emails.retain(is_own);
+        self.filtered_out.retain(is_own);
+        if self.sort == SortOrder::Thread
+            && let Some(cache) = &self.cache
+            && let Some(sent_folder) = self.find_sent_folder()
+            && sent_folder != self.mailbox
+        {
+            match sent_replies::load(
+                cache,
+                &sent_folder,
+                &self.emails,
+                &self.thread_index,
+            ) {
+                Ok(loaded) => {
+                    let replies = loaded
+                        .into_iter()
+                        .filter(|reply| self.is_listed(reply))
+                        .collect::<Vec<_>>();
+                    for reply in &replies {
+                        self.thread_index.add(reply);
+                    }
+                    self.emails.extend(replies);
+                }
+                Err(err) =>
+                    self.status = Some(format!(
+                        "Failed to merge the sent replies: {err:?}"
+                    )),
+            }
+        }
+        self.sort.sort(&mut self.emails, &self.thread_index);
+    }
+
+    /// Returns the name of the sent folder, as advertised by the server or
+    /// else guessed from the names of the folders
+    fn find_sent_folder(&self) -> Option<String> {
+        let cached;
+        let folders = if self.folders.is_empty() {
+            cached = self.cache.as_ref().map(load_folders).unwrap_or_default();
+            &cached
+        } else {
+            &self.folders
+        };
+        self.folder_roles
+            .iter()
+            .find(|(_, role)| **role == Role::Sent)
+            .map(|(name, _)| name.clone())
+            .or_else(|| {
+                folders
+                    .iter()
+                    .find(|name| Role::guess(name) == Some(Role::Sent))
+                    .cloned()
+            })
+    }
+
+    /// Restores the state of the email list of the opened folder
+    fn restore_view(&mut self) {
+        self.load_thread_index();
+        let view = self.views.get(&self.mailbox);
+        self.sort = view.as_sort();
+        self.sort_list();
+        self.current_id = view
+            .as_selected_uid()
+            .and_then(|uid| {
+                self.emails.iter().position(|email| email.as_uid() == uid)
+            })
+            .unwrap_or_default();
+        self.list_state = ListState::default().with_offset(view.as_offset());
+        self.screen_blocked_senders();
+    }
+
+    /// Changes the blocked and allowed senders, and returns the status
+    /// message
+    ///
+    /// The entry defaults to the sender of the selected email. The lists are
+    /// stored in the cache, and the list of emails is updated with them.
+    fn edit_senders(&mut self, edit: Edit, entry: Option<&str>) -> String {
+        let Some(sender) = entry.map(ToOwned::to_owned).or_else(|| {
+            self.emails
+                .get(self.current_id)
+                .and_then(Email::to_sender_address)
+        }) else {
+            return "No email selected".to_owned();
+        };
+        let status = self.senders.apply(edit, &sender);
+        if let Some(cache) = &self.cache
+            && let Err(err) = self.senders.store(cache)
+        {
+            return format!(
+                "{status}, but failed to store the senders: {err:?}"
+            );
+        }
+        match self.screen_blocked_senders() {
+            0 => status,
+            moved => {
+                format!("{status}, moved {moved} emails to the junk folder")
+            }
+        }
+    }
+
+    /// Returns the badge of the identity an email was sent to, with the
+    /// identity, if it has one
+    fn find_badge(&self, email: &Email) -> Option<IdentityBadge<'_>> {
+        let identities = self.credentials.as_ref()?.as_identities();
+        self.config.as_badges().find_for_reply(identities, email)
+    }
+
+    /// Checks if the sender of an email is blocked
+    fn is_blocked_sender(&self, email: &Email) -> bool {
+        email
+            .to_sender_address()
+            .is_some_and(|sender| self.senders.is_blocked(&sender))
+    }
+
+    /// Checks if an email must be listed: it matches the filters, and its
+    /// sender isn't hidden
+    ///
+    /// The blocked senders are never hidden in the junk folder.
+    fn is_listed(&self, email: &Email) -> bool {
+        self.filters.matches(email, self.flags.get(&email.as_uid()))
+            && !(self.config.as_blocked_action() == BlockedAction::Hide
+                && !self.config.as_junk_training().is_junk(&self.mailbox)
+                && self.is_blocked_sender(email))
+    }
+
+    /// Hides the emails of the blocked senders, or moves them to the junk
+    /// folder, depending on the [`BlockedAction`]
+    ///
+    /// Returns the number of emails moved to the junk folder.
+    fn screen_blocked_senders(&mut self) -> usize {
+        let junk_folder =
+            self.config.as_junk_training().as_junk_folder().to_owned();
+        if self.config.as_blocked_action() == BlockedAction::Hide
+            || junk_folder.eq_ignore_ascii_case(&self.mailbox)
+            || self.search.is_some()
+        {
+            self.apply_filters();
+            return 0;
+        }
+        // The hidden emails are listed for the operations to remove them.
+        self.emails.append(&mut self.filtered_out);
+        let blocked = self
+            .emails
+            .iter()
+            .filter(|email| self.is_blocked_sender(email))
+            .map(Email::as_uid)
+            .collect::<Vec<_>>();
+        let mut moved: usize = 0;
+        for uid in blocked {
+            let status = self.apply_operation(Operation::Move {
+                mailbox: junk_folder.clone(),
+                target: Location::new(&self.mailbox, uid),
+            });
+            if self.emails.iter().any(|email| email.as_uid() == uid) {
+                self.status = Some(status);
+            } else {
+                moved = moved.saturating_add(1);
+            }
+        }
+        self.apply_filters();
+        moved
+    }
+
+    /// Remembers the state of the email list of the opened folder
+    ///
+    /// The state of a search result isn't remembered, as it would replace the
+    /// one of its folder.
+    fn remember_view(&mut self) {
+        if self.search.is_some() {
+            return;
+        }
+        let view = FolderView::new(
+            self.list_state.offset(),
+            self.emails.get(self.current_id).map(Email::as_uid),
+            self.sort,
+        );
+        self.views.set(&self.mailbox, view);
+    }
+
+    /// Saves the state of the email list of the opened folder, and stores the
+    /// states in the cache.
+    fn save_view(&mut self) -> Result {
+        self.remember_view();
+        match &self.cache {
+            Some(cache) => self.views.save(cache),
+            None => Ok(()),
+        }
+    }
+
+    /// Restores the mode and the opened email of the last session
+    ///
+    /// The opened email is ignored if it isn't in the folder anymore.
+    fn restore_session(&mut self, snapshot: &Snapshot) -> Result {
+        self.mode = snapshot.as_mode().to_mode();
+        self.configure_writer();
+        if let Some(uid) = snapshot.as_opened_uid()
+            && let Some(id) =
+                self.emails.iter().position(|email| email.as_uid() == uid)
+        {
+            self.current_id = id;
+            self.open_email()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the identities the emails can be sent from
+    fn to_identities(&self) -> Vec<Identity> {
+        self.credentials
+            .as_ref()
+            .map(|credentials| credentials.as_identities().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Stores the folder, the mode and the opened email in the cache.
+    fn save_session(&self) -> Result {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+        let opened_uid = self
+            .open_email_id
+            .and_then(|id| self.emails.get(id))
+            .map(Email::as_uid);
+        Snapshot::new(
+            self.mailbox.clone(),
+            SavedMode::from(&self.mode),
+            opened_uid,
+        )
+        .save(cache)
+    }
+
+    /// Sorts the emails, keeping the selected and opened emails
+    fn sort_emails(&mut self) {
+        let uid_at = |id: Option<usize>| {
+            id.and_then(|idx| self.emails.get(idx)).map(Email::as_uid)
+        };
+        let selected = uid_at(Some(self.current_id));
+        let opened = uid_at(self.open_email_id);
+        self.sort_list();
+        let position = |uid: Option<u32>| {
+            let searched = uid?;
+            self.emails
+                .iter()
+                .position(|email| email.as_uid() == searched)
+        };
+        self.current_id = position(selected).unwrap_or_default();
+        self.open_email_id = position(opened);
+    }
+
+    /// Opens another folder, saving the state of the current one
+    fn switch_folder(&mut self, folder: &str) -> String {
+        if self.fetch_task.is_some() {
+            return "Cancel the running fetch before switching folders"
+                .to_owned();
+        }
+        let Some(credentials) = &self.credentials else {
+            return "No credentials to open the folder with".to_owned();
+        };
+        match Self::load_folder(
+            credentials,
+            &self.sessions,
+            &self.config,
+            self.cache.as_ref(),
+            folder,
+        ) {
+            Ok((uids, emails, flags, status, offline)) => {
+                let saved = self.save_view();
+                folder.clone_into(&mut self.mailbox);
+                self.recent_folders.visit(folder);
+                self.search = None;
+                self.uids = uids;
+                self.emails = emails;
+                self.filtered_out.clear();
+                self.filters.clear();
+                self.flags = flags;
+                self.offline = offline;
+                self.open_email_id = None;
+                self.open_email_body = None;
+                self.preview = None;
+                self.restore_view();
+                match (saved, status) {
+                    (Err(err), _) => format!(
+                        "Opened {folder}, but failed to save the state of the previous folder: {err:?}"
+                    ),
+                    (Ok(()), Some(message)) => {
+                        format!("Opened {folder}. {message}")
+                    }
+                    (Ok(()), None) => format!("Opened {folder}"),
+                }
+            }
+            Err(err) => format!("Failed to open {folder}: {err:?}"),
+        }
+    }
+
+    /// Opens the favourite folder bound to a digit, after a `g`
+    fn open_folder_shortcut(&mut self, key: char) -> String {
+        let Some(folder) = self
+            .config
+            .as_folder_shortcuts()
+            .get(key)
+            .map(ToOwned::to_owned)
+        else {
+            return format!("No folder shortcut for 'g{key}'");
+        };
+        self.switch_folder(&folder)
+    }
+
+    /// Opens the most recent folder other than the displayed one
+    fn open_previous_folder(&mut self) -> String {
+        let Some(folder) = self
+            .recent_folders
+            .as_previous(&self.mailbox)
+            .map(ToOwned::to_owned)
+        else {
+            return "No previous folder".to_owned();
+        };
+        self.switch_folder(&folder)
+    }
+
+    /// Lists the recently opened folders, from the most recent
+    fn list_recent_folders(&self) -> String {
+        format!("Recent: {}", self.recent_folders.as_folders().join(", "))
+    }
+
+    /// Closes the displayed tab, and displays the next one
+    fn close_tab(&mut self) -> String {
+        if self.tabs.len() <= 1 {
+            return "Can't close the last tab".to_owned();
+        }
+        if self.fetch_task.is_some() {
+            return "Cancel the running fetch before closing the tab"
+                .to_owned();
+        }
+        self.remember_view();
+        if self.active_tab < self.tabs.len() {
+            self.tabs.remove(self.active_tab);
+        }
+        self.active_tab =
+            self.active_tab.min(self.tabs.len().saturating_sub(1));
+        let next = self
+            .tabs
+            .get_mut(self.active_tab)
+            .map(mem::take)
+            .unwrap_or_default();
+        self.put_tab(next);
+        format!("Tab closed, showing tab {}", self.active_tab.saturating_add(1))
+    }
+
+    /// Displays the next tab, or the n-th one if a count was typed, like
+    /// `gt` in vim
+    fn next_tab(&mut self, count: Option<usize>) -> String {
+        let next = count.map_or_else(
+            || self.active_tab.saturating_add(1),
+            |number| number.saturating_sub(1),
+        );
+        self.switch_tab(next.checked_rem(self.tabs.len()).unwrap_or_default())
+    }
+
+    /// Goes back by one tab, or by n tabs if a count was typed, like `gT` in
+    /// vim
+    fn previous_tab(&mut self, count: Option<usize>) -> String {
+        let len = self.tabs.len();
+        let back = count.unwrap_or(1).checked_rem(len).unwrap_or_default();
+        let previous = self
+            .active_tab
+            .saturating_add(len)
+            .saturating_sub(back)
+            .checked_rem(len)
+            .unwrap_or_default();
+        self.switch_tab(previous)
+    }
+
+    /// Opens a folder in a new tab
+    fn open_folder_tab(&mut self, folder: &str) -> String {
+        if self.fetch_task.is_some() {
+            return "Cancel the running fetch before opening a tab".to_owned();
+        }
+        let Some(credentials) = &self.credentials else {
+            return "No credentials to open the folder with".to_owned();
+        };
+        match Self::load_folder(
+            credentials,
+            &self.sessions,
+            &self.config,
+            self.cache.as_ref(),
+            folder,
+        ) {
+            Ok((uids, emails, flags, status, offline)) => {
+                self.open_tab(Tab {
+                    emails,
+                    flags,
+                    mailbox: folder.to_owned(),
+                    offline,
+                    uids,
+                    ..Tab::default()
+                });
+                self.recent_folders.visit(folder);
+                self.restore_view();
+                status.unwrap_or_else(|| {
+                    format!(
+                        "Opened {folder} in tab {}",
+                        self.active_tab.saturating_add(1)
+                    )
+                })
+            }
+            Err(err) => format!("Failed to open {folder}: {err:?}"),
+        }
+    }
+
+    /// Displays a new tab, after the opened ones.
+    fn open_tab(&mut self, tab: Tab) {
+        let current = self.take_tab();
+        if let Some(slot) = self.tabs.get_mut(self.active_tab) {
+            *slot = current;
+        }
+        self.tabs.push(Tab::default());
+        self.active_tab = self.tabs.len().saturating_sub(1);
+        self.put_tab(tab);
+    }
+
+    /// Moves the state of a tab into the [`Tui`], to display it.
+    fn put_tab(&mut self, tab: Tab) {
+        let Tab {
+            current_id,
+            emails,
+            filtered_out,
+            filters,
+            flags,
+            list_state,
+            mailbox,
+            offline,
+            open_email_body,
+            open_email_id,
+            search,
+            sort,
+            uids,
+        } = tab;
+        self.current_id = current_id;
+        self.emails = emails;
+        self.filtered_out = filtered_out;
+        self.filters = filters;
+        self.flags = flags;
+        self.list_state = list_state;
+        self.mailbox = mailbox;
+        self.offline = offline;
+        self.open_email_body = open_email_body;
+        self.open_email_id = open_email_id;
+        self.preview = None;
+        self.search = search;
+        self.sort = sort;
+        self.uids = uids;
+        self.load_thread_index();
+    }
+
+    /// Opens the loaded emails whose sender or subject contain the given text
+    /// in a new tab
+    ///
+    /// The search ignores the case. Only the emails already loaded in the
+    /// displayed tab are searched.
+    fn search_emails(&mut self, text: &str) -> String {
+        if self.fetch_task.is_some() {
+            return "Cancel the running fetch before searching".to_owned();
+        }
+        let searched = text.to_lowercase();
+        let emails = self
+            .emails
+            .iter()
+            .filter(|email| {
+                [HeaderName::From, HeaderName::Subject]
+                    .iter()
+                    .any(|header| {
+                        email.get_header_str(header).is_some_and(|value| {
+                            value.to_lowercase().contains(&searched)
+                        })
+                    })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if emails.is_empty() {
+            return format!("No email matches {text}");
+        }
+        let count = emails.len();
+        let flags = emails
+            .iter()
+            .filter_map(|email| {
+                let uid = email.as_uid();
+                Some((uid, self.flags.get(&uid)?.clone()))
+            })
+            .collect();
+        self.open_tab(Tab {
+            uids: emails.iter().map(Email::as_uid).collect(),
+            emails,
+            flags,
+            mailbox: self.mailbox.clone(),
+            offline: self.offline,
+            search: Some(text.to_owned()),
+            sort: self.sort,
+            ..Tab::default()
+        });
+        format!("{count} emails match {text}")
+    }
+
+    /// Displays another tab, keeping the state of the current one
+    ///
+    /// The tabs are numbered from 0.
+    fn switch_tab(&mut self, index: usize) -> String {
+        if self.fetch_task.is_some() {
+            return "Cancel the running fetch before switching tabs".to_owned();
+        }
+        if index >= self.tabs.len() {
+            return format!("No tab {}", index.saturating_add(1));
+        }
+        if index != self.active_tab {
+            let current = self.take_tab();
+            if let Some(slot) = self.tabs.get_mut(self.active_tab) {
+                *slot = current;
+            }
+            let next =
+                self.tabs.get_mut(index).map(mem::take).unwrap_or_default();
+            self.put_tab(next);
+            self.active_tab = index;
+        }
+        format!(
+            "Tab {}: {}",
+            index.saturating_add(1),
+            to_tab_title(&self.mailbox, self.search.as_deref())
+        )
+    }
+
+    /// Moves the state of the displayed tab out of the [`Tui`]
+    fn take_tab(&mut self) -> Tab {
+        self.remember_view();
+        Tab {
+            current_id: mem::take(&mut self.current_id),
+            emails: mem::take(&mut self.emails),
+            filtered_out: mem::take(&mut self.filtered_out),
+            filters: mem::take(&mut self.filters),
+            flags: mem::take(&mut self.flags),
+            list_state: mem::take(&mut self.list_state),
+            mailbox: mem::take(&mut self.mailbox),
+            offline: mem::take(&mut self.offline),
+            open_email_body: self.open_email_body.take(),
+            open_email_id: self.open_email_id.take(),
+            search: self.search.take(),
+            sort: mem::take(&mut self.sort),
+            uids: mem::take(&mut self.uids),
+        }
+    }
+
+    /// Draws the bar listing the tabs, if there are several, and returns the
+    /// area left for the emails
+    #[expect(
+        clippy::missing_asserts_for_indexing,
+        clippy::indexing_slicing,
+        reason = "manual check"
+    )]
+    fn draw_tab_bar(&self, frame: &mut Frame<'_>, area: Rect) -> Result<Rect> {
+        if self.tabs.len() <= 1 {
+            return Ok(area);
+        }
+        let layout = Layout::new(
+            Direction::Vertical,
+            [Constraint::Length(1), Constraint::Fill(1)],
+        )
+        .split(area);
+        if layout.len() != 2 {
+            return Err(Error::LayoutLengthFailure.into());
+        }
+        let bar = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let number = index.saturating_add(1);
+                if index == self.active_tab {
+                    let title =
+                        to_tab_title(&self.mailbox, self.search.as_deref());
+                    format!("[{number} {title}]")
+                } else {
+                    let title =
+                        to_tab_title(&tab.mailbox, tab.search.as_deref());
+                    format!(" {number} {title} ")
+                }
+            })
+            .collect::<String>();
+        frame.render_widget(
+            Text::from(truncate(&bar, usize::from(layout[0].width))),
+            layout[0],
+        );
+        Ok(layout[1])
+    }
+
+    /// Reconnects to the server and replays the queued operations
+    fn reconnect(&mut self) -> String {
+        let Some(credentials) = &self.credentials else {
+            return "No credentials to reconnect with".to_owned();
+        };
+        if let Err(err) = ImapSession::with_credentials(credentials) {
+            return format!("Still offline: {err:?}");
+        }
+        self.offline = false;
+        self.cache.as_ref().map_or_else(
+            || "Reconnected".to_owned(),
+            |cache| {
+                cache.synchronise(credentials, &self.mailbox).map_or_else(
+                    |err| {
+                        format!(
+                            "Reconnected, but failed to synchronise: {err:?}"
+                        )
+                    },
+                    |summary| format!("Reconnected. {summary}"),
+                )
+            },
+        )
+    }
+
+    /// Returns the number of loaded emails, including the ones hidden by the
+    /// filters
+    const fn count_loaded(&self) -> usize {
+        self.emails.len().saturating_add(self.filtered_out.len())
+    }
+
+    /// Lists only the loaded emails that match the filters, keeping the
+    /// selected and opened emails if they still match.
+    fn apply_filters(&mut self) {
+        let uid_at = |id: Option<usize>| {
+            id.and_then(|idx| self.emails.get(idx)).map(Email::as_uid)
+        };
+        let selected = uid_at(Some(self.current_id));
+        let opened = uid_at(self.open_email_id);
+
+        let mut loaded = mem::take(&mut self.emails);
+        loaded.append(&mut self.filtered_out);
+        let (listed, hidden) =
+            loaded.into_iter().partition(|email| self.is_listed(email));
+        self.emails = listed;
+        self.filtered_out = hidden;
+        self.sort_list();
+
+        let position = |uid: Option<u32>| {
+            let searched = uid?;
+            self.emails
+                .iter()
+                .position(|email| email.as_uid() == searched)
+        };
+        self.current_id = position(selected).unwrap_or_default();
+        self.open_email_id = position(opened);
+        if self.open_email_id.is_none() {
+            self.open_email_body = None;
+        }
+    }
+
+    /// Toggles the filter of a key pressed after `F`, or clears the filters
+    /// with `c`, and returns the status message
+    fn toggle_filter(&mut self, key: char) -> String {
+        if key == 'c' {
+            self.filters.clear();
+        } else {
+            let Some(filter) =
+                QuickFilter::from_key(key, self.emails.get(self.current_id))
+            else {
+                return format!("Unknown filter: F{key}");
+            };
+            self.filters.toggle(filter);
+        }
+        self.apply_filters();
+        self.to_filters_status()
+    }
+
+    /// Returns the status message describing the active filters
+    fn to_filters_status(&self) -> String {
+        if self.filters.is_empty() {
+            "Filters cleared".to_owned()
+        } else {
+            format!(
+                "Showing {} of {} emails: {}",
+                self.emails.len(),
+                self.count_loaded(),
+                self.filters.to_description()
+            )
+        }
+    }
+
+    /// Starts fetching the emails that weren't loaded yet, in the background
+    fn start_fetch_task(&mut self) -> String {
+        if self.fetch_task.is_some() {
+            return "A fetch is already running".to_owned();
+        }
+        let uids = self
+            .uids
+            .iter()
+            .skip(self.count_loaded())
+            .copied()
+            .collect::<Vec<_>>();
+        if uids.is_empty() {
+            return "Every email is already loaded".to_owned();
+        }
+        let cache = self.cache.clone().filter(|_| {
+            self.config.as_folder_filter().is_synced(&self.mailbox)
+        });
+        let task = FetchTask::spawn(
+            self.mailbox.clone(),
+            uids,
+            cache,
+            self.sessions.clone(),
+        );
+        let status =
+            format!("Fetching 0/{} emails (Esc to cancel)", task.as_total());
+        self.fetch_task = Some(task);
+        status
+    }
+
+    /// Adds the emails fetched in the background to the list, and updates the
+    /// status with the progress of the fetch.
+    fn update_fetch_task(&mut self) {
+        let Some(task) = &self.fetch_task else {
+            return;
+        };
+        let events = task.poll_events();
+        let has_new_emails = events
+            .iter()
+            .any(|event| matches!(event, FetchEvent::Email(..)));
+        for event in events {
+            match event {
+                FetchEvent::Email(uid, body, metadata) =>
+                    match Email::try_from((uid, body.as_bytes())) {
+                        Ok(mut email) => {
+                            if let Some(data) = metadata {
+                                email.set_metadata(data);
+                            }
+                            self.thread_index.add(&email);
+                            if self.is_listed(&email) {
+                                self.emails.push(email);
+                            } else {
+                                self.filtered_out.push(email);
+                            }
+                            self.status = Some(format!(
+                                "Fetching {}/{} emails (Esc to cancel)",
+                                self.count_loaded(),
+                                self.uids.len()
+                            ));
+                        }
+                        Err(err) =>
+                            self.status =
+                                Some(format!("Failed to parse email: {err:?}")),
+                    },
+                FetchEvent::Failed(err) => {
+                    self.status = Some(format!("Fetch failed: {err}"));
+                    self.fetch_task = None;
+                    break;
+                }
+                FetchEvent::Finished { cancelled } => {
+                    self.status = Some(if cancelled {
+                        format!(
+                            "Fetch cancelled, {}/{} emails loaded",
+                            self.count_loaded(),
+                            self.uids.len()
+                        )
+                    } else {
+                        format!("Fetched all {} emails", self.count_loaded())
+                    });
+                    self.fetch_task = None;
+                    break;
+                }
+            }
+        }
+        if has_new_emails && self.sort != SortOrder::Newest {
+            self.sort_emails();
+        }
+    }
 
-        Ok(Self { emails: first_emails, ..Self::default() })
+    /// Displays the outcome of the pruning of the cache once it finished
+    fn update_prune_task(&mut self) {
+        if self
+            .prune_task
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+            && let Some(task) = self.prune_task.take()
+        {
+            self.status = Some(task.join().unwrap_or_else(|_| {
+                "The pruning of the cache panicked".to_owned()
+            }));
+        }
     }
 
-    /// Runs the [`Tui`]
+    /// Hands the terminal over to an external program while running `run`
     ///
-    /// Handles key events and frame renders
-    #[expect(
-        clippy::unwrap_in_result,
-        clippy::unwrap_used,
-        reason = "inside closure"
-    )]
-    pub fn run(&mut self) -> Result {
-        let mut terminal = ratatui::init();
-        self.running = true;
-        while self.running {
-            terminal
-                .draw(|frame| self.draw_tui(frame).unwrap())
-                .map_err(Error::Drawing)?;
-            self.handle_key_events()?;
+    /// The terminal is restored to its normal state before running `run`, and
+    /// put back into raw mode afterwards. The caller must then clear the
+    /// terminal, as its content was overwritten.
+    fn suspend_terminal<T>(run: impl FnOnce() -> T) -> Result<T> {
+        Self::restore_terminal()?;
+        let output = run();
+        enable_raw_mode().map_err(Error::EnablingRawMode)?;
+        execute!(io::stdout(), EnterAlternateScreen)
+            .map_err(Error::EnablingRawMode)?;
+        Self::enable_keyboard_enhancement()?;
+        Ok(output)
+    }
+
+    /// Asks the terminal to report the modifiers of the keys like 'Enter'
+    ///
+    /// Without it, 'Ctrl-Enter' is read as a plain 'Enter'. The terminals that
+    /// don't support the kitty keyboard protocol are left as they are, and
+    /// 'Ctrl-S' is then the only way to send an email while editing a field.
+    fn enable_keyboard_enhancement() -> Result {
+        if supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                )
+            )
+            .map_err(Error::EnablingRawMode)?;
         }
-        ratatui::restore();
         Ok(())
     }
 
-    /// Main drawer for the TUI
+    /// Restores the terminal to its normal state, with the keyboard reporting
+    /// it had before [`Self::enable_keyboard_enhancement`]
     ///
-    /// This function is called every loop to re-render the TUI.
-    pub fn draw_tui(&mut self, frame: &mut Frame<'_>) -> Result {
-        match &mut self.mode {
-            TuiMode::Help => {
-                manual_page(frame);
-                Ok(())
-            }
-            TuiMode::Writing(writer) => {
-                writer.writer_page(frame);
-                Ok(())
-            }
-            TuiMode::Reading => self.draw_emails(frame),
-        }
+    /// The terminals without the kitty keyboard protocol ignore the request.
+    fn restore_terminal() -> Result {
+        let popped = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        ratatui::restore();
+        Ok(popped.map_err(Error::DisablingRawMode)?)
     }
 
-    /// Handles key events
-    fn handle_key_events(&mut self) -> Result {
-        let event = read().map_err(Error::IoKeyboard)?;
+    /// Opens the hovered email, or resumes it in the writer if the folder
+    /// holds the drafts
+    fn open_selected(&mut self) -> Result {
+        if self.to_folder_role() != Some(Role::Drafts) {
+            return self.open_email();
+        }
+        let Some(draft) = self.emails.get(self.current_id) else {
+            return Ok(());
+        };
+        self.mode = TuiMode::Writing(Box::new(Writer::new_from_draft(draft)));
+        self.configure_writer();
         if let TuiMode::Writing(writer) = &mut self.mode
-            && writer.handle_key_events(&event)
+            && let Some(resumed) = self.emails.get(self.current_id)
         {
-            return Ok(());
-        }
-        match event {
-            Event::Key(KeyEvent { code: KeyCode::Char(ch), .. }) => match ch {
-                'q' => self.running = false,
-                'j' if matches!(self.mode, TuiMode::Reading) => {
-                    let incremented = self.current_id.saturating_add(1);
-                    if incremented < self.emails.len() {
-                        self.current_id = incremented;
-                    }
-                }
-                'k' if matches!(self.mode, TuiMode::Reading) =>
-                    self.current_id = self.current_id.saturating_sub(1),
-                'l' if matches!(self.mode, TuiMode::Reading) =>
-                    self.open_email_id = Some(self.current_id),
-                'h' if matches!(self.mode, TuiMode::Reading) =>
-                    self.open_email_id = None,
-                'w' => self.mode.new_writer(),
-                'r' => self.mode = TuiMode::Reading,
-                'm' => self.mode = TuiMode::Help,
-                _ => (),
-            },
-            Event::Key(_)
-            | Event::FocusGained
-            | Event::FocusLost
-            | Event::Mouse(_)
-            | Event::Paste(_)
-            | Event::Resize(..) => (),
+            writer.select_identity_of(resumed);
         }
+        self.status = Some(
+            "Draft resumed: it stays in the folder until deleted".to_owned(),
+        );
+        Ok(())
+    }
+
+    /// Returns the role of the opened folder, as advertised by the server or
+    /// else guessed from its name
+    fn to_folder_role(&self) -> Option<Role> {
+        self.folder_roles
+            .get(&self.mailbox)
+            .copied()
+            .or_else(|| Role::guess(&self.mailbox))
+    }
+
+    /// Opens the hovered email
+    ///
+    /// The body is run through the display filters once, when the email is
+    /// opened, to avoid running them on every render. If a filter fails, the
+    /// plain text body, or else the text of the HTML body, is displayed
+    /// instead.
+    fn open_email(&mut self) -> Result {
+        let Some(email) = self.emails.get(self.current_id) else {
+            return Ok(());
+        };
+        let body = match filter_body(email, &self.config) {
+            Ok(body) => body,
+            Err(err) => {
+                self.status = Some(format!("Display filter failed: {err:?}"));
+                to_unfiltered_body(email)?
+            }
+        };
+        self.open_email_id = Some(self.current_id);
+        self.open_email_body = Some(self.to_displayed_body(&body));
         Ok(())
     }
 
@@ -149,46 +3182,513 @@ impl Tui {
         clippy::indexing_slicing,
         reason = "manual check"
     )]
-    fn draw_emails(&self, frame: &mut Frame<'_>) -> Result {
-        if let Some(open_email_id) = self.open_email_id {
-            let layout = Layout::new(
-                Direction::Horizontal,
-                [Constraint::Fill(1), Constraint::Fill(1)],
-            )
-            .split(Rect::new(
-                0,
-                0,
-                frame.area().width,
-                frame.area().height,
-            ));
+    fn draw_emails(&mut self, frame: &mut Frame<'_>, area: Rect) -> Result {
+        let displayed_id = match self.open_email_id {
+            Some(id) => Some(id),
+            None if self.config.has_preview_pane() => {
+                self.update_preview();
+                self.preview.is_some().then_some(self.current_id)
+            }
+            None => None,
+        };
+        let Some(email_id) = displayed_id else {
+            return self.render_email_explorer(frame, area);
+        };
+        self.update_breadcrumb(email_id);
+        self.update_archives(email_id);
+        self.update_trackers(email_id);
+        let title = self.to_viewer_title();
+        let is_sent = self.to_folder_role() == Some(Role::Sent)
+            || self
+                .emails
+                .get(email_id)
+                .is_some_and(|email| sent_replies::is_merged(email.as_uid()));
+        if self.split.is_zoomed() {
+            let scroll = self.clamp_body_scroll(email_id, area.width);
+            let email = &self.emails[email_id];
+            let picture = Self::get_email_viewer_widget(
+                frame,
+                area,
+                (email, self.as_archives(), self.find_badge(email), is_sent),
+                (self.as_displayed_body(), scroll),
+                &title,
+                self.as_body_search(),
+                self.is_blocked_sender(email),
+            )?;
+            self.picture_slot = picture.map(|position| (email_id, position));
+            return Ok(());
+        }
+        let layout = Layout::new(
+            self.split.as_direction(area.width),
+            self.split.as_constraints(),
+        )
+        .split(area);
+
+        if layout.len() != 2 {
+            return Err(Error::LayoutLengthFailure.into());
+        }
+
+        self.render_email_explorer(frame, layout[0])?;
+        let scroll = self.clamp_body_scroll(email_id, layout[1].width);
+        let email = &self.emails[email_id];
+        let picture = Self::get_email_viewer_widget(
+            frame,
+            layout[1],
+            (email, self.as_archives(), self.find_badge(email), is_sent),
+            (self.as_displayed_body(), scroll),
+            &title,
+            self.as_body_search(),
+            self.is_blocked_sender(email),
+        )?;
+        self.picture_slot = picture.map(|position| (email_id, position));
+        Ok(())
+    }
+
+    /// Returns the remembered scroll offset of the body of the displayed
+    /// email, wrapped at the given width
+    ///
+    /// The offset is lowered if it goes past the end of the body, e.g. after
+    /// the viewer was widened.
+    fn clamp_body_scroll(&mut self, email_id: usize, width: u16) -> u16 {
+        let Some(uid) = self.emails.get(email_id).map(Email::as_uid) else {
+            return 0;
+        };
+        let last_row = count_rows(self.as_displayed_body().lines(), width)
+            .saturating_sub(1);
+        let offset = self.positions.get(&self.mailbox, uid);
+        if offset > last_row {
+            self.positions.set(&self.mailbox, uid, last_row);
+        }
+        offset.min(last_row)
+    }
+
+    /// Returns the title of the email viewer, with the number of trackers
+    /// and the breadcrumb line of the conversation of the displayed email if
+    /// any
+    fn to_viewer_title(&self) -> String {
+        let mut title = vec!["Email viewer".to_owned()];
+        match self.trackers {
+            Some((_, 1)) => title
+                .push("This email contains 1 tracker (:trackers)".to_owned()),
+            Some((_, count @ 2..)) => title.push(format!(
+                "This email contains {count} trackers (:trackers)"
+            )),
+            Some((_, 0)) | None => (),
+        }
+        if let Some((_, Some(breadcrumb))) = &self.breadcrumb {
+            title.push(breadcrumb.clone());
+        }
+        title.join(" | ")
+    }
+
+    /// Counts the tracking pixels of the displayed email, if it changed since
+    /// the last render.
+    fn update_trackers(&mut self, email_id: usize) {
+        let Some(email) = self.emails.get(email_id) else {
+            self.trackers = None;
+            return;
+        };
+        if self
+            .trackers
+            .as_ref()
+            .is_some_and(|(other, _)| *other == email.as_uid())
+        {
+            return;
+        }
+        let count = email
+            .as_html_body()
+            .map_or(0, |html| find_trackers(html).len());
+        self.trackers = Some((email.as_uid(), count));
+    }
+
+    /// Computes the breadcrumb line of the displayed email, if it changed
+    /// since the last render.
+    fn update_breadcrumb(&mut self, email_id: usize) {
+        let Some(uid) = self.emails.get(email_id).map(Email::as_uid) else {
+            self.breadcrumb = None;
+            return;
+        };
+        if self
+            .breadcrumb
+            .as_ref()
+            .is_some_and(|(other, _)| *other == uid)
+        {
+            return;
+        }
+        let breadcrumb = if self.thread_index.is_alone(uid) {
+            None
+        } else {
+            Threads::new(&self.emails).to_breadcrumb(&self.emails, email_id)
+        };
+        self.breadcrumb = Some((uid, breadcrumb));
+    }
+
+    /// Returns the files of the archive attachments of the displayed email
+    fn as_archives(&self) -> &[Option<Vec<Entry>>] {
+        self.archives
+            .as_ref()
+            .map_or(&[], |(_, listings)| listings.as_slice())
+    }
+
+    /// Lists the files of the archive attachments of the displayed email, if
+    /// it changed since the last render.
+    fn update_archives(&mut self, email_id: usize) {
+        let Some(email) = self.emails.get(email_id) else {
+            self.archives = None;
+            return;
+        };
+        if self
+            .archives
+            .as_ref()
+            .is_some_and(|(other, _)| *other == email.as_uid())
+        {
+            return;
+        }
+        let listings =
+            email.as_attachments().iter().map(archive::list).collect();
+        self.archives = Some((email.as_uid(), listings));
+    }
+
+    /// Returns the search in the body of the opened email, if any
+    fn as_body_search(&self) -> Option<&BodySearch> {
+        let opened = self.open_email_id.and_then(|id| self.emails.get(id))?;
+        self.body_search
+            .as_ref()
+            .filter(|(uid, _)| *uid == opened.as_uid())
+            .map(|(_, search)| search)
+    }
+
+    /// Moves to the next match of the search in the opened email, or to the
+    /// previous one if `forward` isn't set, and returns the status message
+    fn move_in_body_search(&mut self, forward: bool) -> String {
+        let opened_uid = self
+            .open_email_id
+            .and_then(|id| self.emails.get(id))
+            .map(Email::as_uid);
+        match &mut self.body_search {
+            Some((uid, search)) if Some(*uid) == opened_uid => {
+                if forward {
+                    search.next();
+                } else {
+                    search.previous();
+                }
+                search.to_status()
+            }
+            Some(_) | None => "No search in the opened email".to_owned(),
+        }
+    }
+
+    /// Searches a text in the body of the opened email, and returns the
+    /// status message
+    fn search_body(&mut self, text: &str) -> String {
+        let Some(email) = self.open_email_id.and_then(|id| self.emails.get(id))
+        else {
+            return "No email is opened".to_owned();
+        };
+        let search = BodySearch::new(self.as_displayed_body(), text);
+        let status = search.to_status();
+        self.body_search = Some((email.as_uid(), search));
+        status
+    }
 
-            if layout.len() != 2 {
-                return Err(Error::LayoutLengthFailure.into());
+    /// Opens another email of the conversation of the opened one
+    ///
+    /// The `target` finds the email to open from the threads and the id of
+    /// the opened email, and `description` names it in the status if there is
+    /// none.
+    fn jump_in_thread(
+        &mut self,
+        target: fn(&Threads, usize) -> Option<usize>,
+        description: &str,
+    ) -> Result {
+        let Some(opened) = self.open_email_id else {
+            self.status = Some("No email is opened".to_owned());
+            return Ok(());
+        };
+        match target(&Threads::new(&self.emails), opened) {
+            Some(id) => {
+                self.select_email(id);
+                self.open_email()?;
             }
+            None => self.status = Some(format!("No {description} is loaded")),
+        }
+        Ok(())
+    }
+
+    /// Returns the body of the opened email, or of the previewed one if none
+    /// is opened
+    ///
+    /// The list of the references of the links is left out if it is hidden.
+    fn as_displayed_body(&self) -> &str {
+        let body = if self.open_email_id.is_some() {
+            self.open_email_body.as_deref().unwrap_or_default()
+        } else {
+            self.preview.as_ref().map_or("", |(_, body)| body)
+        };
+        if self.links_hidden {
+            collapse(body)
+        } else {
+            body
+        }
+    }
 
-            let email = &self.emails[open_email_id];
-            frame.render_widget(self.get_email_explorer_widget()?, layout[0]);
-            Self::get_email_viewer_widget(frame, layout[1], email)?;
+    /// Returns the body to display from the output of the display filters,
+    /// with the noise hidden unless it was asked for, and the links replaced
+    /// with references if configured
+    fn to_displayed_body(&self, body: &str) -> String {
+        let quiet = if self.noise_shown {
+            body.to_owned()
+        } else {
+            noise::hide(body)
+        };
+        if self.config.has_link_references() {
+            to_references(&quiet)
         } else {
-            frame
-                .render_widget(self.get_email_explorer_widget()?, frame.area());
+            quiet
+        }
+    }
+
+    /// Shows or hides the blocks of style sheets and encoded data of the
+    /// displayed body
+    ///
+    /// The body of the opened email is rendered again, and the preview is
+    /// rendered again on the next render.
+    fn toggle_noise(&mut self) -> Result {
+        self.noise_shown = !self.noise_shown;
+        self.preview = None;
+        if let Some(id) = self.open_email_id {
+            self.current_id = id;
+            self.open_email()?;
         }
         Ok(())
     }
 
+    /// Renders the body of the hovered email for the preview pane, if it
+    /// changed since the last render.
+    ///
+    /// Unlike opening it, previewing an email doesn't report the failures of
+    /// the display filters in the status.
+    fn update_preview(&mut self) {
+        let Some(email) = self.emails.get(self.current_id) else {
+            self.preview = None;
+            return;
+        };
+        if self
+            .preview
+            .as_ref()
+            .is_some_and(|(uid, _)| *uid == email.as_uid())
+        {
+            return;
+        }
+        let body = filter_body(email, &self.config)
+            .or_else(|_| email.to_plain_body())
+            .unwrap_or_default();
+        self.preview = Some((email.as_uid(), self.to_displayed_body(&body)));
+    }
+
+    /// Renders the list of emails, scrolled to keep the selection visible
+    ///
+    /// Only the emails that fit in the panel are formatted, so that the render
+    /// doesn't depend on the number of emails of the folder.
+    fn render_email_explorer(
+        &mut self,
+        frame: &mut Frame<'_>,
+        full_area: Rect,
+    ) -> Result {
+        let area = self.render_reminders(frame, full_area);
+        let rows_per_email = if self.config.as_index_format().is_some() {
+            1
+        } else {
+            2
+        };
+        let visible = usize::from(area.height.saturating_sub(2))
+            .checked_div(rows_per_email)
+            .unwrap_or_default()
+            .max(1);
+        let offset = self
+            .list_state
+            .offset()
+            .min(self.current_id)
+            .max(self.current_id.saturating_add(1).saturating_sub(visible));
+        let mut list_state = ListState::default()
+            .with_selected(Some(self.current_id.saturating_sub(offset)));
+        frame.render_stateful_widget(
+            self.get_email_explorer_widget(
+                area.width,
+                offset..offset.saturating_add(visible),
+            )?,
+            area,
+            &mut list_state,
+        );
+        self.list_state = ListState::default()
+            .with_offset(offset)
+            .with_selected(Some(self.current_id));
+        Ok(())
+    }
+
+    /// Draws the due reminders of the unanswered emails at the top of the
+    /// inbox, if there are any
+    ///
+    /// Returns the area left for the email list.
+    fn render_reminders(&mut self, frame: &mut Frame<'_>, area: Rect) -> Rect {
+        if self.mailbox != DEFAULT_MAILBOX {
+            return area;
+        }
+        let now = reminders::to_now();
+        self.reminders.resolve(&self.emails, now);
+        let lines = self.reminders.to_due_lines(now);
+        if lines.is_empty() {
+            return area;
+        }
+        let height = u16::try_from(lines.len())
+            .unwrap_or(u16::MAX)
+            .saturating_add(2)
+            .min(area.height.saturating_div(2));
+        frame.render_widget(
+            Paragraph::new(Text::from(
+                lines.into_iter().map(Line::from).collect::<Vec<_>>(),
+            ))
+            .block(new_simple_box("Follow-up reminders (':dismiss' to clear)")),
+            Rect { height, ..area },
+        );
+        Rect {
+            y: area.y.saturating_add(height),
+            height: area.height.saturating_sub(height),
+            ..area
+        }
+    }
+
     /// Creates the widget representing the email viewer
     ///
-    /// This is the panel displaying the content of the selected email.
-    #[expect(
-        clippy::missing_asserts_for_indexing,
-        clippy::indexing_slicing,
-        reason = "manual check"
-    )]
+    /// This is the panel displaying the content of the selected email,
+    /// scrolled by the given number of lines unless a search is running. A
+    /// banner is displayed above the email if its sender is blocked, and the
+    /// summaries of its schema.org annotations below it, if it has some.
+    ///
+    /// Returns the cell of the initials of the sender, where their picture can
+    /// be drawn, if it is visible.
+    #[expect(clippy::indexing_slicing, reason = "manual check")]
     fn get_email_viewer_widget(
         frame: &mut Frame<'_>,
         rect: Rect,
-        email: &Email,
-    ) -> Result {
+        (email, archives, badge, is_sent): (
+            &Email,
+            &[Option<Vec<Entry>>],
+            Option<IdentityBadge<'_>>,
+            bool,
+        ),
+        (body, body_scroll): (&str, u16),
+        title: &str,
+        search: Option<&BodySearch>,
+        blocked: bool,
+    ) -> Result<Option<Position>> {
+        let attachments = email.as_attachments();
+        let attachment_lines = to_attachment_lines(attachments, archives);
+        let attachments_height = if attachments.is_empty() {
+            0
+        } else {
+            u16::try_from(attachment_lines.len().saturating_add(2))
+                .unwrap_or(u16::MAX)
+        };
+
+        let summaries = to_summary_lines(email);
+        let summaries_height = if summaries.is_empty() {
+            0
+        } else {
+            u16::try_from(summaries.len().saturating_add(2)).unwrap_or(u16::MAX)
+        };
+
+        let layout = Layout::new(
+            Direction::Vertical,
+            [
+                Constraint::Length(u16::from(blocked)),
+                Constraint::Length(summaries_height),
+                Constraint::Max(5),
+                Constraint::Max(3),
+                Constraint::Max(5),
+                Constraint::Fill(1),
+                Constraint::Length(attachments_height),
+            ],
+        )
+        .split(rect);
+
+        if layout.len() != 7 {
+            return Err(Error::LayoutLengthFailure.into());
+        }
+
+        let attachments_width = usize::from(layout[6].width.saturating_sub(2));
+        let (subject_txt, date_txt, from_txt) = Self::get_email_header_widgets(
+            email,
+            badge,
+            is_sent,
+            usize::from(layout[2].width.saturating_sub(2)),
+        )?;
+
+        let mut body_txt = highlight_body(body, is_patch(email));
+        if let Some(found) = search {
+            body_txt = found.highlight(body_txt);
+        }
+        let body_rows = to_visual_rows(body_txt, usize::from(layout[5].width));
+
+        let attachments_txt = Paragraph::new(Text::from(
+            attachment_lines
+                .iter()
+                .map(|line| Line::from(truncate(line, attachments_width)))
+                .collect::<Vec<_>>(),
+        ))
+        .block(
+            Block::bordered()
+                .title("Attachments (:open <n>, :extract <n>.<m> <dir>)"),
+        );
+
+        frame.render_widget(
+            Paragraph::new(
+                " Blocked sender: ':unblock' to see their emails again ",
+            )
+            .reversed(),
+            layout[0],
+        );
+        frame.render_widget(
+            Paragraph::new(Text::from(summaries))
+                .block(new_simple_box("Summary")),
+            layout[1],
+        );
+        frame.render_widget(subject_txt, layout[2]);
+        frame.render_widget(date_txt, layout[3]);
+        frame.render_widget(from_txt, layout[4]);
+        let scroll = search.map_or(body_scroll, |found| {
+            found.as_scroll(body, layout[5].width)
+        });
+        frame.render_widget(
+            Paragraph::new(body_rows)
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0)),
+            layout[5],
+        );
+        frame.render_widget(attachments_txt, layout[6]);
+        frame.render_widget(new_simple_box(title), rect);
+
+        let sender = layout[4];
+        Ok((sender.height > 2 && !is_sent).then(|| {
+            Position::new(
+                sender.x.saturating_add(1),
+                sender.y.saturating_add(1),
+            )
+        }))
+    }
+
+    /// Creates the widgets of the subject, the date and the sender of the
+    /// email viewer
+    ///
+    /// The badge of the identity the email was sent to follows the sender,
+    /// with the address of the identity. The emails of the sent folder show
+    /// their recipients instead of the sender, i.e. the user.
+    fn get_email_header_widgets<'email>(
+        email: &'email Email,
+        badge: Option<IdentityBadge<'_>>,
+        is_sent: bool,
+        subject_width: usize,
+    ) -> Result<(Paragraph<'email>, Paragraph<'email>, Paragraph<'email>)> {
         let subject_str =
             email.as_headers().get(&HeaderName::Subject).map_or_else(
                 || Ok("No subject".to_owned()),
@@ -199,9 +3699,18 @@ impl Tui {
                         .ok_or(fetch::parser::Error::InvalidHeaderType)
                 },
             )?;
-        let subject_txt = Paragraph::new(Text::from(subject_str))
-            .wrap(Wrap { trim: false })
-            .block(Block::bordered());
+        let marker = email.to_priority().as_marker();
+        let subject_line = if marker.is_empty() {
+            subject_str
+        } else {
+            format!("{marker} {subject_str}")
+        };
+        let subject_txt = Paragraph::new(to_visual_rows(
+            Text::from(subject_line),
+            subject_width,
+        ))
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered());
 
         let date_str = email.as_headers().get(&HeaderName::Date).map_or_else(
             || Ok("No date".to_owned()),
@@ -216,8 +3725,13 @@ impl Tui {
             .wrap(Wrap { trim: false })
             .block(Block::bordered());
 
-        let from_str = email.as_headers().get(&HeaderName::From).map_or_else(
-            || Ok("No from".to_owned()),
+        let header = if is_sent {
+            HeaderName::To
+        } else {
+            HeaderName::From
+        };
+        let from_str = email.as_headers().get(&header).map_or_else(
+            || Ok(format!("No {}", header.as_str().to_lowercase())),
             |value| {
                 value
                     .as_address()
@@ -225,91 +3739,432 @@ impl Tui {
                     .map(|address| format!("{address:?}"))
             },
         )?;
-        let from_txt = Paragraph::new(Text::from(from_str))
+        let mut from_spans = if is_sent {
+            vec![
+                to_recipient_avatar(email),
+                Span::raw(format!(" To: {from_str}")),
+            ]
+        } else {
+            vec![to_avatar(email), Span::raw(format!(" {from_str}"))]
+        };
+        if let Some((found, identity)) = badge {
+            from_spans.extend([
+                Span::raw(" to "),
+                found.to_span(),
+                Span::raw(format!(" {}", identity.as_address())),
+            ]);
+        }
+        let from_txt = Paragraph::new(Line::from(from_spans))
             .wrap(Wrap { trim: false })
             .block(Block::bordered());
+        Ok((subject_txt, date_txt, from_txt))
+    }
 
-        let body_str = email.to_plain_body()?;
-        let body_txt =
-            Paragraph::new(Text::from(body_str)).wrap(Wrap { trim: false });
-
-        let layout = Layout::new(
-            Direction::Vertical,
-            [
-                Constraint::Max(5),
-                Constraint::Max(3),
-                Constraint::Max(5),
-                Constraint::Fill(1),
-            ],
-        )
-        .split(rect);
-
-        if layout.len() != 4 {
-            return Err(Error::LayoutLengthFailure.into());
+    /// Returns the style of the row of an email in the list: the selection,
+    /// or else the highlight of an email waiting for a reply, see
+    /// [`aging`], or else the one of the unread emails
+    fn to_row_style(
+        &self,
+        email: &Email,
+        is_selected: bool,
+        now: i64,
+    ) -> Style {
+        let theme = self.config.as_theme();
+        if is_selected {
+            return theme.selection_style();
         }
+        let flags = self.flags.get(&email.as_uid());
+        aging::to_style(email, flags, now)
+            .or_else(|| is_unread(flags).then(|| theme.unread_style()))
+            .unwrap_or_default()
+    }
 
-        frame.render_widget(subject_txt, layout[0]);
-        frame.render_widget(date_txt, layout[1]);
-        frame.render_widget(from_txt, layout[2]);
-        frame.render_widget(body_txt, layout[3]);
-        frame.render_widget(new_simple_box("Email viewer"), rect);
-
-        Ok(())
+    /// Returns the prefix of the row of an email in the list: its relative
+    /// number, and the markers of the selection and of the unread emails, if
+    /// they are enabled
+    fn to_list_prefix(
+        &self,
+        id: usize,
+        email: &Email,
+        number_width: usize,
+    ) -> String {
+        let number = if self.config.has_relative_numbers() {
+            let relative = id.abs_diff(self.current_id);
+            let displayed = if relative == 0 {
+                id.saturating_add(1)
+            } else {
+                relative
+            };
+            format!("{displayed:>number_width$} ")
+        } else {
+            String::new()
+        };
+        if !self.config.has_list_markers() {
+            return number;
+        }
+        let selection = if id == self.current_id { '>' } else { ' ' };
+        let unread = if is_unread(self.flags.get(&email.as_uid())) {
+            '*'
+        } else {
+            ' '
+        };
+        format!("{number}{selection}{unread} ")
     }
 
     /// Creates the widget representing the email explorer
     ///
     /// This is left panel of the editor, giving the list of received emails and
     /// enabling the user to select an email to display.
-    fn get_email_explorer_widget(&self) -> Result<List<'_>> {
+    ///
+    /// The lines are padded to the inner width of the panel, so that the
+    /// selection is highlighted on the whole width. Only the emails of the
+    /// given window are listed.
+    fn get_email_explorer_widget(
+        &self,
+        width: u16,
+        window: Range<usize>,
+    ) -> Result<List<'_>> {
+        let inner_width = usize::from(width.saturating_sub(2));
+        let is_sent_folder = self.to_folder_role() == Some(Role::Sent);
+        let now = reminders::to_now();
+        let number_width = self.emails.len().to_string().len();
         let email_subjects = self
             .emails
             .iter()
             .enumerate()
+            .skip(window.start)
+            .take(window.len())
             .map(|(id, email)| {
-                let subject = email
-                    .get_header(&HeaderName::Subject)?
-                    .as_text()
-                    .ok_or(parser::Error::InvalidHeaderType)?
-                    .to_owned();
-                let date = email
-                    .get_header(&HeaderName::Date)?
-                    .as_datetime()
-                    .ok_or(parser::Error::InvalidHeaderType)?
-                    .to_rfc3339();
-                let raw_text =
-                    Text::from(vec![Line::from(subject), Line::from(date)]);
-                let styled_text = if self.current_id == id {
-                    raw_text.style(Style::new().bg(Color::DarkGray))
+                let is_selected = self.current_id == id;
+                let is_sent =
+                    is_sent_folder || sent_replies::is_merged(email.as_uid());
+                let prefix = self.to_list_prefix(id, email, number_width);
+                let badge = self
+                    .find_badge(email)
+                    .filter(|_| !is_sent)
+                    .map(|(found, _)| found.to_span());
+                let text_width = inner_width
+                    .saturating_sub(prefix.len())
+                    .saturating_sub(AVATAR_WIDTH.saturating_add(1))
+                    .saturating_sub(
+                        badge
+                            .as_ref()
+                            .map_or(0, |span| span.width().saturating_add(1)),
+                    );
+                let rows = if let Some(format) = self.config.as_index_format() {
+                    vec![format.format(
+                        email,
+                        self.flags.get(&email.as_uid()),
+                        text_width,
+                        is_sent,
+                    )]
+                } else {
+                    let subject = email
+                        .get_header(&HeaderName::Subject)?
+                        .as_text()
+                        .ok_or(parser::Error::InvalidHeaderType)?
+                        .to_owned();
+                    let date = email
+                        .get_header(&HeaderName::Date)?
+                        .as_datetime()
+                        .ok_or(parser::Error::InvalidHeaderType)?
+                        .to_rfc3339();
+                    let parts = [
+                        email.to_priority().as_marker().to_owned(),
+                        to_attachment_marker(email),
+                        subject,
+                    ];
+                    let subject_line = parts
+                        .into_iter()
+                        .filter(|part| !part.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let date_line = if is_sent {
+                        format!("{date}  To: {}", email.to_recipient_names())
+                    } else {
+                        date
+                    };
+                    vec![
+                        fit_visual(&subject_line, text_width),
+                        fit(&date_line, text_width),
+                    ]
+                };
+                let avatar = if is_sent {
+                    to_recipient_avatar(email)
                 } else {
-                    raw_text
+                    to_avatar(email)
                 };
-                Ok(ListItem::from(styled_text))
+                Ok(ListItem::from(
+                    to_list_text(&prefix, avatar, badge, rows)
+                        .style(self.to_row_style(email, is_selected, now)),
+                ))
             })
             .collect::<Result<Vec<_>>>()?;
 
+        let title = if self.filters.is_empty() {
+            "Recent emails".to_owned()
+        } else {
+            format!("Recent emails ({})", self.filters.to_description())
+        };
         let email_explorer =
-            List::new(email_subjects).block(new_simple_box("Recent emails"));
+            List::new(email_subjects).block(new_simple_box(title));
 
         Ok(email_explorer)
     }
 }
 
+/// Returns the summaries of the schema.org annotations of an email, e.g. of
+/// a flight reservation, see [`annotations`](crate::annotations), and its
+/// one-time code, see [`otp`](crate::otp)
+fn to_summary_lines(email: &Email) -> Vec<Line<'static>> {
+    email
+        .as_html_body()
+        .map(to_summaries)
+        .unwrap_or_default()
+        .into_iter()
+        .chain(
+            find_code(email).map(|code| format!("Code {code} ('y' to copy)")),
+        )
+        .map(Line::from)
+        .collect()
+}
+
+/// Returns the lines of the attachments panel, with the files of the archive
+/// attachments below them, e.g. `  [1.2] report.pdf (12 KB)`
+fn to_attachment_lines(
+    attachments: &[Attachment],
+    archives: &[Option<Vec<Entry>>],
+) -> Vec<String> {
+    let mut lines = vec![];
+    for (idx, attachment) in attachments.iter().enumerate() {
+        let number = idx.saturating_add(1);
+        lines.push(format!(
+            "[{number}] {} ({})",
+            attachment.to_file_name(),
+            attachment.as_content_type()
+        ));
+        let Some(Some(entries)) = archives.get(idx) else {
+            continue;
+        };
+        lines.extend(entries.iter().take(ARCHIVE_PREVIEW_LEN).enumerate().map(
+            |(entry_idx, entry)| {
+                format!(
+                    "  [{number}.{}] {} ({})",
+                    entry_idx.saturating_add(1),
+                    entry.as_name(),
+                    retention::to_human_size(entry.as_size())
+                )
+            },
+        ));
+        if entries.len() > ARCHIVE_PREVIEW_LEN {
+            lines.push(format!(
+                "  ... {} more",
+                entries.len().saturating_sub(ARCHIVE_PREVIEW_LEN)
+            ));
+        }
+    }
+    lines
+}
+
+/// Returns the text of an email of the list, made of its rows
+///
+/// The first row starts with the prefix, the avatar of the sender, or of the
+/// recipient in the sent folder, and the badge of the identity the email was
+/// sent to, if any. The next rows are aligned with the avatar.
+fn to_list_text(
+    prefix: &str,
+    avatar: Span<'static>,
+    badge: Option<Span<'static>>,
+    rows: Vec<String>,
+) -> Text<'static> {
+    let indent =
+        " ".repeat(prefix.len().saturating_add(AVATAR_WIDTH).saturating_add(1));
+    let mut lines = rows.into_iter();
+    let mut spans = vec![Span::raw(prefix.to_owned()), avatar];
+    if let Some(span) = badge {
+        spans.extend([Span::raw(" "), span]);
+    }
+    spans.push(Span::raw(format!(" {}", lines.next().unwrap_or_default())));
+    iter::once(Line::from(spans))
+        .chain(lines.map(|row| Line::from(format!("{indent}{row}"))))
+        .collect()
+}
+
+/// Returns the title of a tab, as displayed in the tab bar
+///
+/// Search results are titled with the searched text, after a `/`.
+fn to_tab_title(mailbox: &str, search: Option<&str>) -> String {
+    search.map_or_else(|| mailbox.to_owned(), |text| format!("/{text}"))
+}
+
 /// Errors than occur because of the TUI rendering
 #[derive(Debug)]
 pub enum Error {
     /// Failed to clear the terminal
     ClearTerminal(io::Error),
+    /// Failed to remove the control socket
+    ControlSocket(io::Error),
     /// Failed to disable raw terminal mode.
     ///
     /// See [`disable_raw_mode`] for more information.
     DisablingRawMode(io::Error),
+    /// Failed to enable raw terminal mode.
+    EnablingRawMode(io::Error),
     /// Error occurred while drawing a frame.
     Drawing(io::Error),
     /// Error occurred while reading the keyboard presses.
     IoKeyboard(io::Error),
     /// Failed to create the layout
     LayoutLengthFailure,
+    /// A local folder was opened without a cache
+    MissingCache,
+    /// The email with the given uid is neither in the cache nor on the server
+    MissingEmail(u32),
+    /// Failed to read a file attached to the written email
+    ReadAttachment(io::Error),
     /// Error occurred while spawning keyboard listener thread.
     UnknownKeyboard(Box<dyn Any + Send>),
 }
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+    use std::time::Instant;
+    use std::{env, fs};
+
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::buffer::Cell;
+
+    use crate::fetch::parser::Email;
+    use crate::test_utils::TempDir;
+    use crate::tui::app::Tui;
+    use crate::tui::states::TuiMode;
+
+    /// Number of emails of the synthetic mailbox, unless overridden with the
+    /// `MAILBOX_STRESS_EMAILS` environment variable
+    const STRESS_EMAILS: u32 = 100_000;
+    /// Maximum duration to load the synthetic mailbox from the cache
+    const MAX_LOAD: Duration = Duration::from_secs(30);
+    /// Maximum memory taken by the emails of the synthetic mailbox, in bytes
+    const MAX_MEMORY: u64 = 512 << 20;
+    /// Maximum duration of the render of a frame
+    const MAX_FRAME: Duration = Duration::from_millis(50);
+
+    /// Returns the memory used by the process, in bytes, if the platform
+    /// exposes it
+    fn resident_memory() -> Option<u64> {
+        fs::read_to_string("/proc/self/status")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .strip_suffix(" kB")?
+            .parse::<u64>()
+            .ok()
+            .map(|kilobytes| kilobytes.saturating_mul(1024))
+    }
+
+    /// Generates an email of the synthetic mailbox
+    fn generate_email(uid: u32) -> String {
+        format!(
+            "From: Sender {sender} <sender{sender}@example.com>\nTo: me@example.com\nSubject: Synthetic email number {uid}\nDate: Mon, 6 Jan 2025 10:00:00 +0000\nMessage-ID: <{uid}@example.com>\n\nBody of the email {uid}.\n",
+            sender = uid.checked_rem(500).unwrap_or_default()
+        )
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn counts_and_canned_responses() {
+        let emails = (1..=20)
+            .rev()
+            .map(|uid| {
+                Email::try_from((uid, generate_email(uid).as_bytes())).unwrap()
+            })
+            .collect();
+        let mut tui = Tui {
+            emails,
+            mode: TuiMode::Reading,
+            open_email_id: Some(0),
+            ..Tui::default()
+        };
+        for ch in ['1', '2', 'j'] {
+            tui.handle_char(ch).unwrap();
+        }
+        assert_eq!(tui.current_id, 12);
+        assert_eq!(tui.open_email_id, Some(12));
+        assert!(tui.pending_send.is_none());
+        assert!(matches!(tui.mode, TuiMode::Reading));
+
+        for ch in ['c', '0', 'j'] {
+            tui.handle_char(ch).unwrap();
+        }
+        assert_eq!(
+            tui.status.as_deref(),
+            Some("No canned response bound to '0'")
+        );
+        assert_eq!(tui.current_id, 13);
+        assert!(tui.pending_send.is_none());
+    }
+
+    #[test]
+    #[ignore = "stress test, run with `cargo test -- --ignored`"]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn large_mailbox() {
+        let count = env::var("MAILBOX_STRESS_EMAILS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(STRESS_EMAILS);
+        let dir = TempDir::new("stress");
+        let cache = dir.open_cache();
+        let uids = (1..=count).rev().collect::<Vec<_>>();
+        cache.store_uids("INBOX", &uids).unwrap();
+        for uid in &uids {
+            cache
+                .store_email("INBOX", *uid, &generate_email(*uid))
+                .unwrap();
+        }
+
+        let memory_before = resident_memory();
+        let start = Instant::now();
+        let (loaded_uids, emails) =
+            Tui::load_cached_emails(&cache, "INBOX").unwrap();
+        let load = start.elapsed();
+        assert!(load < MAX_LOAD, "loaded {count} emails in {load:?}");
+        assert_eq!(emails.len(), uids.len());
+        if let (Some(before), Some(after)) = (memory_before, resident_memory())
+        {
+            let used = after.saturating_sub(before);
+            assert!(used < MAX_MEMORY, "{count} emails take {used} bytes");
+        }
+
+        let last = emails.len().saturating_sub(1);
+        let mut tui = Tui {
+            emails,
+            mode: TuiMode::Reading,
+            uids: loaded_uids,
+            ..Tui::default()
+        };
+        let mut terminal = Terminal::new(TestBackend::new(160, 50)).unwrap();
+        for current_id in [0, 1, last.checked_div(2).unwrap_or_default(), last]
+        {
+            tui.current_id = current_id;
+            let frame_start = Instant::now();
+            terminal.draw(|frame| tui.draw_tui(frame).unwrap()).unwrap();
+            let frame = frame_start.elapsed();
+            assert!(
+                frame < MAX_FRAME,
+                "rendered email {current_id} in {frame:?}"
+            );
+            let screen = terminal
+                .backend()
+                .buffer()
+                .content()
+                .iter()
+                .map(Cell::symbol)
+                .collect::<String>();
+            let uid = tui.uids.get(current_id).copied().unwrap_or_default();
+            assert!(screen.contains(&format!("Synthetic email number {uid}")));
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Draws a coloured block with the initials of the sender of an email, to scan
+//! the senders faster.
+//!
+//! The colour is derived from a hash of the address of the sender, so that a
+//! sender keeps the same colour in the list and in the viewer, across
+//! sessions. Everything is computed locally, no picture is fetched.
+
+use mail_parser::{HeaderName, HeaderValue};
+use ratatui::style::{Color, Style};
+use ratatui::text::Span;
+
+use super::width::fit;
+use crate::fetch::parser::Email;
+
+/// Number of columns taken by an avatar, with its padding
+pub const AVATAR_WIDTH: usize = 4;
+/// Background colours of the avatars, readable with black initials
+const COLORS: [Color; 12] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+];
+/// Initial value of the FNV-1a hash
+const FNV_OFFSET: u32 = 0x811c_9dc5;
+/// Multiplier of the FNV-1a hash
+const FNV_PRIME: u32 = 0x0100_0193;
+/// Number of columns taken by the initials
+const INITIALS_WIDTH: usize = 2;
+
+/// Returns the avatar of the sender of an email
+///
+/// The initials are the ones of the name of the sender, or the ones of the
+/// user part of their address if they have no name, e.g. `JD` for
+/// `jane.doe@example.com`.
+pub fn to_avatar(email: &Email) -> Span<'static> {
+    to_avatar_of(email, &HeaderName::From)
+}
+
+/// Returns the avatar of the first recipient of an email, shown in place of
+/// the sender in the sent folder
+pub fn to_recipient_avatar(email: &Email) -> Span<'static> {
+    to_avatar_of(email, &HeaderName::To)
+}
+
+/// Returns the avatar of the first address of a header of an email
+fn to_avatar_of(email: &Email, header: &HeaderName<'_>) -> Span<'static> {
+    let sender = email
+        .as_headers()
+        .get(header)
+        .and_then(HeaderValue::as_address)
+        .and_then(|address| address.first());
+    let address = sender
+        .and_then(|addr| addr.address())
+        .unwrap_or_default()
+        .to_lowercase();
+    let initials = sender
+        .and_then(|addr| addr.name())
+        .map(to_initials)
+        .filter(|initials| !initials.is_empty())
+        .unwrap_or_else(|| {
+            to_initials(address.split('@').next().unwrap_or_default())
+        });
+    Span::styled(
+        format!(" {} ", fit(&initials, INITIALS_WIDTH)),
+        Style::new().fg(Color::Black).bg(to_color(&address)),
+    )
+}
+
+/// Returns the colour of the avatar of an address
+fn to_color(address: &str) -> Color {
+    let hash = address.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    usize::try_from(hash)
+        .ok()
+        .and_then(|value| value.checked_rem(COLORS.len()))
+        .and_then(|index| COLORS.get(index))
+        .copied()
+        .unwrap_or(Color::Gray)
+}
+
+/// Returns the uppercase initials of the first two words of a name
+///
+/// Punctuation is skipped, e.g. the quotes or the `@` of an address.
+fn to_initials(name: &str) -> String {
+    name.split(|ch: char| !ch.is_alphanumeric())
+        .filter_map(|word| word.chars().next())
+        .take(INITIALS_WIDTH)
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::avatar::{to_color, to_initials};
+
+    #[test]
+    fn avatars() {
+        assert_eq!(to_initials("Jane Doe"), "JD");
+        assert_eq!(to_initials("\"doe, jane\" (work)"), "DJ");
+        assert_eq!(to_initials("jane.doe"), "JD");
+        assert_eq!(to_initials(""), "");
+        assert_eq!(to_color("jane@example.com"), to_color("jane@example.com"));
+    }
+}
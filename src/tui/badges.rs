@@ -0,0 +1,148 @@
+//! Shows which identity of the account an email belongs to.
+//!
+//! A badge is a short name with a colour, given to an identity by the `BADGES`
+//! variable of the `.env` file, as `address=name:colour` entries separated by
+//! semicolons:
+//!
+//! ```env
+//! BADGES=bob@example.com=Work:blue; support@example.com=SUP:light-yellow
+//! ```
+//!
+//! The colour is a name or `#rrggbb`, see [`Color`], and can be omitted.
+//!
+//! The badge of the identity an email was sent to, which is the one a reply
+//! would be sent from, is shown in its row of the list, and next to the sender
+//! in the viewer, with the address of the identity. The emails sent to an
+//! identity without a badge have none. As a TUI shows a single account, giving
+//! a badge to the address of the account tells its window apart from the ones
+//! of the other accounts.
+
+use core::str::FromStr as _;
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+
+use crate::fetch::parser::Email;
+use crate::identity::{Identity, find_for_reply};
+
+/// Short name and colour shown next to the emails of an identity
+#[derive(Debug, PartialEq, Eq)]
+pub struct Badge {
+    /// Colour of the name
+    color: Color,
+    /// Short name of the identity, e.g. `Work`
+    name: String,
+}
+
+impl Badge {
+    /// Returns the badge, in bold brackets, e.g. `[Work]`
+    pub fn to_span(&self) -> Span<'static> {
+        Span::styled(
+            format!("[{}]", self.name),
+            Style::new().fg(self.color).add_modifier(Modifier::BOLD),
+        )
+    }
+}
+
+/// Badge of the identity an email was sent to, with the identity
+pub type IdentityBadge<'found> = (&'found Badge, &'found Identity);
+
+/// Badges of the identities of the account
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Badges {
+    /// Address of every identity with a badge, and its badge
+    badges: Vec<(String, Badge)>,
+}
+
+impl Badges {
+    /// Returns the badge of the identity an email was sent to, with the
+    /// identity
+    ///
+    /// Returns `None` if the identity has no badge.
+    pub fn find_for_reply<'found>(
+        &'found self,
+        identities: &'found [Identity],
+        email: &Email,
+    ) -> Option<IdentityBadge<'found>> {
+        if self.badges.is_empty() {
+            return None;
+        }
+        let identity = identities.get(find_for_reply(identities, email))?;
+        self.badges
+            .iter()
+            .find(|(address, _)| {
+                address.eq_ignore_ascii_case(identity.as_address())
+            })
+            .map(|(_, badge)| (badge, identity))
+    }
+
+    /// Parses the badges of the `.env` file, ignoring the invalid ones
+    pub fn parse(value: &str) -> Self {
+        Self {
+            badges: value
+                .split(';')
+                .filter_map(|entry| {
+                    let (address, badge) = entry.split_once('=')?;
+                    let (name, color) = match badge.rsplit_once(':') {
+                        Some((name, color)) =>
+                            (name, Color::from_str(color.trim()).ok()?),
+                        None => (badge, Color::Reset),
+                    };
+                    let trimmed = name.trim();
+                    if trimmed.is_empty() {
+                        return None;
+                    }
+                    Some((
+                        address.trim().to_owned(),
+                        Badge { color, name: trimmed.to_owned() },
+                    ))
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::style::Color;
+
+    use crate::fetch::parser::Email;
+    use crate::identity::parse_identities;
+    use crate::tui::badges::Badges;
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn badges() {
+        let badges = Badges::parse(
+            "bob@example.com=Work:blue; Support@Example.com = SUP ; x=:red; \
+             y=Y:nocolour",
+        );
+        let identities =
+            parse_identities("bob@example.com; support@example.com; me@x.org");
+        let email = |to: &str| {
+            Email::try_from((1, format!("To: {to}\n\nHello").as_bytes()))
+                .unwrap()
+        };
+
+        let (badge, identity) = badges
+            .find_for_reply(&identities, &email("support@example.com"))
+            .unwrap();
+        assert_eq!(identity.as_address(), "support@example.com");
+        assert_eq!(badge.to_span().content, "[SUP]");
+        assert_eq!(badge.to_span().style.fg, Some(Color::Reset));
+        let (work, _) = badges
+            .find_for_reply(&identities, &email("unknown@example.com"))
+            .unwrap();
+        assert_eq!(work.to_span().style.fg, Some(Color::Blue));
+        assert!(
+            badges
+                .find_for_reply(&identities, &email("me@x.org"))
+                .is_none()
+        );
+        assert!(
+            Badges::default()
+                .find_for_reply(&identities, &email("bob@example.com"))
+                .is_none()
+        );
+    }
+}
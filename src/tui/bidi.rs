@@ -0,0 +1,136 @@
+//! Reorders right-to-left text for display.
+//!
+//! Text is stored in logical order, but the terminal displays the characters
+//! from left to right, so Arabic and Hebrew text would be displayed backwards.
+//! Each line is reordered independently, following the Unicode bidirectional
+//! algorithm.
+//!
+//! A line is reordered once it is wrapped or truncated to the width it is
+//! displayed on: reordering a long line first would display its end on the
+//! first row.
+
+use core::mem;
+
+use ratatui::text::{Line, Text};
+use unicode_bidi::BidiInfo;
+use unicode_width::{UnicodeWidthChar as _, UnicodeWidthStr as _};
+
+use super::width::{fit, truncate};
+
+/// Truncates a line to take at most `width` columns, reorders it, and pads it
+/// with spaces to take exactly `width` columns
+pub fn fit_visual(line: &str, width: usize) -> String {
+    fit(&to_visual_order(&truncate(line, width)), width)
+}
+
+/// Reorders each line of the text from logical to visual order
+pub fn to_visual_order(text: &str) -> String {
+    text.lines()
+        .map(reorder_line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wraps the lines of a text with right-to-left characters at the given
+/// width, and reorders each of their rows
+///
+/// The other lines are kept as is, to be wrapped by the terminal. The style
+/// of the reordered lines is kept, but not the ones of their spans.
+pub fn to_visual_rows(text: Text<'_>, width: usize) -> Text<'_> {
+    text.lines
+        .into_iter()
+        .flat_map(|line| {
+            let content = line
+                .spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect::<String>();
+            if !BidiInfo::new(&content, None).has_rtl() {
+                return vec![line];
+            }
+            wrap(&content, width)
+                .iter()
+                .map(|row| Line::styled(reorder_line(row), line.style))
+                .collect()
+        })
+        .collect()
+}
+
+/// Reorders a line from logical to visual order
+///
+/// Lines without right-to-left characters are returned as is.
+fn reorder_line(line: &str) -> String {
+    let info = BidiInfo::new(line, None);
+    if !info.has_rtl() {
+        return line.to_owned();
+    }
+
+    info.paragraphs
+        .iter()
+        .map(|paragraph| info.reorder_line(paragraph, paragraph.range.clone()))
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+/// Wraps a line in logical order into rows of at most `width` columns
+///
+/// The line is wrapped at the spaces, and the words longer than a row are
+/// split.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let columns = width.max(1);
+    let mut rows = vec![];
+    let mut row = String::new();
+    let mut used: usize = 0;
+    for word in line.split_inclusive(' ') {
+        let word_width = word.trim_end_matches(' ').width();
+        if used != 0 && used.saturating_add(word_width) > columns {
+            rows.push(mem::take(&mut row).trim_end().to_owned());
+            used = 0;
+        }
+        for ch in word.chars() {
+            let ch_width = ch.width().unwrap_or_default();
+            if used != 0 && ch != ' ' && used.saturating_add(ch_width) > columns
+            {
+                rows.push(mem::take(&mut row));
+                used = 0;
+            }
+            row.push(ch);
+            used = used.saturating_add(ch_width);
+        }
+    }
+    rows.push(row.trim_end().to_owned());
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::text::{Line, Text};
+
+    use crate::tui::bidi::{fit_visual, to_visual_order, to_visual_rows};
+
+    #[test]
+    #[expect(clippy::non_ascii_literal, reason = "test")]
+    fn reorder() {
+        assert_eq!(to_visual_order("Hello\nworld"), "Hello\nworld");
+        assert_eq!(to_visual_order("שלום"), "םולש");
+    }
+
+    #[test]
+    #[expect(clippy::non_ascii_literal, reason = "test")]
+    fn reorder_rows() {
+        let text = Text::from(vec![
+            Line::from("Hello world"),
+            Line::from("אבג דהו זחט"),
+        ]);
+        assert_eq!(
+            to_visual_rows(text, 8),
+            Text::from(vec![
+                Line::from("Hello world"),
+                Line::from("והד גבא"),
+                Line::from("טחז"),
+            ])
+        );
+        assert_eq!(fit_visual("אבג דהו", 5), "… גבא");
+        assert_eq!(fit_visual("abc", 5), "abc  ");
+    }
+}
@@ -0,0 +1,241 @@
+//! Browses the file system to choose files, so their paths don't have to be
+//! typed.
+//!
+//! The browser is opened with `A` in the writer to attach files, and with `S`
+//! on an opened email to choose where its attachments are saved. `Up` and
+//! `Down` (or `k` and `j`) move in the directory, `Enter` (or `l`) opens the
+//! selected directory and `Backspace` (or `h`) goes to the parent one. `Esc`
+//! closes the popup.
+//!
+//! To attach files, `Space` marks several files, and `Enter` on a file attaches
+//! the marked files, or the selected one if none are marked. To save the
+//! attachments, `s` chooses the displayed directory.
+
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use ratatui::Frame;
+use ratatui::crossterm::event::KeyCode;
+use ratatui::layout::Rect;
+use ratatui::text::Line;
+use ratatui::widgets::{Clear, List, ListState};
+
+use super::components::{POPUP_SIZE, centered_popup, new_simple_box};
+
+/// Reason the browser was opened for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Purpose {
+    /// Choosing files to attach to the written email
+    Attach,
+    /// Choosing the directory to save the attachments of an email in
+    Save,
+}
+
+/// Outcome of a key pressed in the browser
+#[derive(Debug, PartialEq, Eq)]
+pub enum Choice {
+    /// The browser was closed without choosing anything
+    Cancelled,
+    /// Files, or a directory to save in, were chosen
+    Chosen(Vec<PathBuf>),
+}
+
+/// Entry of a directory
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    /// Indicates whether the entry is a directory, to list directories first
+    is_file: bool,
+    /// Name of the entry in its directory
+    name: String,
+}
+
+/// Popup of the file browser
+pub struct Browser {
+    /// Displayed directory
+    dir: PathBuf,
+    /// Entries of the displayed directory, directories first
+    entries: Vec<Entry>,
+    /// Files marked with `Space`, to attach them together
+    marked: Vec<PathBuf>,
+    /// Reason the browser was opened for
+    purpose: Purpose,
+    /// Index of the selected entry in `entries`
+    selected: usize,
+}
+
+impl Browser {
+    /// Draws the popup in the middle of the given area
+    pub fn draw(&self, frame: &mut Frame<'_>, area: Rect) {
+        let popup = centered_popup(area, POPUP_SIZE);
+        let title = match self.purpose {
+            Purpose::Attach => format!(
+                "Attach from {} ({} marked)",
+                self.dir.display(),
+                self.marked.len()
+            ),
+            Purpose::Save =>
+                format!("Save to {} ('s' to confirm)", self.dir.display()),
+        };
+        let list = List::new(self.entries.iter().map(|entry| {
+            let path = self.dir.join(&entry.name);
+            let mark = if self.marked.contains(&path) {
+                "*"
+            } else {
+                " "
+            };
+            let suffix = if entry.is_file { "" } else { "/" };
+            Line::from(format!("{mark} {}{suffix}", entry.name))
+        }))
+        .block(new_simple_box(title))
+        .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_widget(Clear, popup);
+        frame.render_stateful_widget(list, popup, &mut state);
+    }
+
+    /// Handles a pressed key, and returns the choice of the user once they
+    /// made one
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<Choice> {
+        match (code, self.purpose) {
+            (KeyCode::Esc, _) => return Some(Choice::Cancelled),
+            (KeyCode::Up | KeyCode::Char('k'), _) =>
+                self.selected = self.selected.saturating_sub(1),
+            (KeyCode::Down | KeyCode::Char('j'), _) =>
+                self.selected = self
+                    .selected
+                    .saturating_add(1)
+                    .min(self.entries.len().saturating_sub(1)),
+            (KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h'), _) =>
+                if let Some(parent) = self.dir.parent() {
+                    self.open_dir(parent.to_path_buf());
+                },
+            (KeyCode::Enter | KeyCode::Right | KeyCode::Char('l'), _) =>
+                return self.open_selected(),
+            (KeyCode::Char(' '), Purpose::Attach) => self.toggle_mark(),
+            (KeyCode::Char('s'), Purpose::Save) =>
+                return Some(Choice::Chosen(vec![self.dir.clone()])),
+            _ => (),
+        }
+        None
+    }
+
+    /// Opens a browser in the working directory
+    pub fn new(purpose: Purpose) -> Self {
+        let mut browser = Self {
+            dir: PathBuf::new(),
+            entries: vec![],
+            marked: vec![],
+            purpose,
+            selected: 0,
+        };
+        browser.open_dir(env::current_dir().unwrap_or_default());
+        browser
+    }
+
+    /// Displays the entries of a directory
+    ///
+    /// The directory is displayed empty if it can't be read.
+    fn open_dir(&mut self, dir: PathBuf) {
+        self.entries = read_entries(&dir);
+        self.dir = dir;
+        self.selected = 0;
+    }
+
+    /// Opens the selected directory, or chooses the files to attach
+    fn open_selected(&mut self) -> Option<Choice> {
+        let entry = self.entries.get(self.selected)?;
+        let path = self.dir.join(&entry.name);
+        if !entry.is_file {
+            self.open_dir(path);
+            None
+        } else if self.purpose == Purpose::Save {
+            None
+        } else if self.marked.is_empty() {
+            Some(Choice::Chosen(vec![path]))
+        } else {
+            Some(Choice::Chosen(self.marked.clone()))
+        }
+    }
+
+    /// Marks or unmarks the selected file.
+    fn toggle_mark(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return;
+        };
+        if !entry.is_file {
+            return;
+        }
+        let path = self.dir.join(&entry.name);
+        if let Some(idx) = self.marked.iter().position(|marked| *marked == path)
+        {
+            self.marked.remove(idx);
+        } else {
+            self.marked.push(path);
+        }
+    }
+}
+
+/// Lists the entries of a directory, directories first, then by name
+fn read_entries(dir: &Path) -> Vec<Entry> {
+    let mut entries = fs::read_dir(dir)
+        .map(|read_dir| {
+            read_dir
+                .filter_map(|entry| {
+                    let found = entry.ok()?;
+                    Some(Entry {
+                        is_file: !found.file_type().ok()?.is_dir(),
+                        name: found.file_name().to_string_lossy().into_owned(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    entries.sort_unstable();
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use ratatui::crossterm::event::KeyCode;
+
+    use crate::test_utils::TempDir;
+    use crate::tui::browser::{Browser, Choice, Purpose};
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn browse_files() {
+        let temp = TempDir::new("browser");
+        let dir = temp.as_path().to_owned();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("sub/b.txt"), "b").unwrap();
+
+        let mut browser = Browser::new(Purpose::Attach);
+        browser.open_dir(dir.clone());
+        assert_eq!(browser.handle_key(KeyCode::Enter), None);
+        assert_eq!(browser.dir, dir.join("sub"));
+        assert_eq!(
+            browser.handle_key(KeyCode::Enter),
+            Some(Choice::Chosen(vec![dir.join("sub/b.txt")]))
+        );
+        browser.handle_key(KeyCode::Char('h'));
+        browser.handle_key(KeyCode::Char(' '));
+        browser.handle_key(KeyCode::Char('j'));
+        browser.handle_key(KeyCode::Char(' '));
+        assert_eq!(
+            browser.handle_key(KeyCode::Enter),
+            Some(Choice::Chosen(vec![dir.join("a.txt")]))
+        );
+
+        let mut saver = Browser::new(Purpose::Save);
+        saver.open_dir(dir.clone());
+        assert_eq!(saver.handle_key(KeyCode::Char(' ')), None);
+        assert_eq!(
+            saver.handle_key(KeyCode::Char('s')),
+            Some(Choice::Chosen(vec![dir]))
+        );
+        assert_eq!(saver.handle_key(KeyCode::Esc), Some(Choice::Cancelled));
+    }
+}
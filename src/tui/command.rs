@@ -0,0 +1,261 @@
+//! Defines the commands that can be run from the command line
+//!
+//! The command line is opened by pressing `:` in reading mode, like in vim,
+//! and the command is run when pressing `Enter`.
+
+use std::path::PathBuf;
+
+use super::senders::Edit;
+use super::views::SortOrder;
+use crate::cache::queue::{Location, Operation};
+
+/// Kind of text entered in the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prompt {
+    /// A command, opened with `:`
+    Command,
+    /// A text to search in the opened email, opened with `/`
+    Search,
+}
+
+impl Prompt {
+    /// Returns the character displayed before the entered text
+    pub const fn as_prefix(self) -> char {
+        match self {
+            Self::Command => ':',
+            Self::Search => '/',
+        }
+    }
+}
+
+/// Commands that can be entered in the command line
+pub enum Command {
+    /// Adds a flag, e.g. `\Seen`, to the selected email
+    AddFlag(String),
+    /// Applies the patch series of the opened email in the given repository
+    ApplyPatches(PathBuf),
+    /// Removes every email from the cache
+    CacheClear,
+    /// Displays the size of the cache
+    CacheStats,
+    /// Deletes the selected email
+    Delete,
+    /// Forgets the due reminders of the unanswered emails
+    DismissReminders,
+    /// Blocks, allows or unblocks an address or a domain, defaulting to the
+    /// sender of the selected email
+    EditSenders(Edit, Option<String>),
+    /// Saves the attachments of the listed emails whose name matches the
+    /// glob to the given directory
+    ExportAttachments(String, PathBuf),
+    /// Extracts a file of an archive attached to the opened email into the
+    /// given directory
+    ///
+    /// The attachments and the files of the archives are numbered from 1, in
+    /// the order they are displayed.
+    Extract(usize, usize, PathBuf),
+    /// Opens another folder
+    Folder(String),
+    /// Exports the calendar invite of the opened email, to the given file or
+    /// directory, or else to the calendar command
+    Invite(Option<PathBuf>),
+    /// Lists the folders of the account, and whether they are synced
+    ///
+    /// Without argument, only the top level of the hierarchy is listed. The
+    /// argument is either a parent folder followed by its delimiter, whose
+    /// children are listed, or a filter on the folder names.
+    Folders(Option<String>),
+    /// Moves the selected email to the given mailbox
+    Move(String),
+    /// Opens an attachment of the opened email
+    ///
+    /// The attachments are numbered from 1, in the order they are displayed.
+    OpenAttachment(usize),
+    /// Prints the opened email
+    Print,
+    /// Lists the recently opened folders
+    Recent,
+    /// Reconnects to the server after being offline, and replays the queued
+    /// operations
+    Reconnect,
+    /// Removes a flag, e.g. `\Seen`, from the selected email
+    RemoveFlag(String),
+    /// Resends the selected email as is to the given addresses, also known as
+    /// bouncing it
+    Resend(Vec<String>),
+    /// Saves the sanitized HTML body of the opened email to the given file
+    SaveHtml(PathBuf),
+    /// Saves the patch series of the opened email to the given mbox file
+    SavePatches(PathBuf),
+    /// Lists the blocked and allowed senders
+    Senders,
+    /// Opens the loaded emails whose sender or subject contain the given text
+    /// in a new tab
+    Search(String),
+    /// Changes the order of the emails in the list
+    Sort(SortOrder),
+    /// Opens the statistics of the cached emails
+    Stats,
+    /// Fetches the emails of the inbox that weren't loaded yet
+    ///
+    /// The fetch runs in the background, and can be cancelled with `Esc` or
+    /// `Ctrl-C`. An interrupted fetch is resumed when the app starts again.
+    Sync,
+    /// Closes the current tab
+    TabClose,
+    /// Opens a folder in a new tab
+    TabNew(String),
+    /// Lists the domains of the tracking pixels of the opened email
+    Trackers,
+}
+
+impl Command {
+    /// Parses a command from the content of the command line
+    ///
+    /// Returns `None` if the command doesn't exist.
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut words = input.split_whitespace();
+        let command = (words.next()?, words.next());
+        let rest = words.collect::<Vec<_>>().join(" ");
+        match (command, rest.is_empty()) {
+            (("allow", entry), true) => Some(Self::EditSenders(
+                Edit::Allow,
+                entry.map(ToOwned::to_owned),
+            )),
+            (("block", entry), true) => Some(Self::EditSenders(
+                Edit::Block,
+                entry.map(ToOwned::to_owned),
+            )),
+            (("bounce" | "resend", Some(first)), _) => Some(Self::Resend(
+                format!("{first} {rest}")
+                    .split([',', ' '])
+                    .filter(|address| !address.is_empty())
+                    .map(ToOwned::to_owned)
+                    .collect(),
+            )),
+            (("cache", Some("clear")), true) => Some(Self::CacheClear),
+            (("cache", Some("stats")), true) => Some(Self::CacheStats),
+            (("delete", None), true) => Some(Self::Delete),
+            (("dismiss", None), true) => Some(Self::DismissReminders),
+            (("export", Some(glob)), false) => Some(Self::ExportAttachments(
+                glob.to_owned(),
+                PathBuf::from(rest),
+            )),
+            (("extract", Some(entry)), false) => {
+                let (attachment, file) = entry.split_once('.')?;
+                Some(Self::Extract(
+                    attachment.parse().ok()?,
+                    file.parse().ok()?,
+                    PathBuf::from(rest),
+                ))
+            }
+            (("folder", Some(folder)), _) => Some(Self::Folder(
+                format!("{folder} {rest}").trim_end().to_owned(),
+            )),
+            (("folders", None), true) => Some(Self::Folders(None)),
+            (("folders", Some(argument)), _) => Some(Self::Folders(Some(
+                format!("{argument} {rest}").trim_end().to_owned(),
+            ))),
+            (("flag", Some(flag)), true) =>
+                Some(Self::AddFlag(to_imap_flag(flag))),
+            (("invite", None), true) => Some(Self::Invite(None)),
+            (("invite", Some(path)), _) => Some(Self::Invite(Some(
+                PathBuf::from(format!("{path} {rest}").trim_end()),
+            ))),
+            (("move", Some(mailbox)), _) => Some(Self::Move(
+                format!("{mailbox} {rest}").trim_end().to_owned(),
+            )),
+            (("open", index), true) => Some(Self::OpenAttachment(
+                index.map_or(Some(1), |idx| idx.parse().ok())?,
+            )),
+            (("html", Some(path)), _) => Some(Self::SaveHtml(PathBuf::from(
+                format!("{path} {rest}").trim_end(),
+            ))),
+            (("patch", Some("apply")), false) =>
+                Some(Self::ApplyPatches(PathBuf::from(rest))),
+            (("patch", Some("save")), false) =>
+                Some(Self::SavePatches(PathBuf::from(rest))),
+            (("print", None), true) => Some(Self::Print),
+            (("reconnect", None), true) => Some(Self::Reconnect),
+            (("recent", None), true) => Some(Self::Recent),
+            (("search", Some(text)), _) => Some(Self::Search(
+                format!("{text} {rest}").trim_end().to_owned(),
+            )),
+            (("senders", None), true) => Some(Self::Senders),
+            (("sort", Some(order)), true) =>
+                Some(Self::Sort(SortOrder::parse(order)?)),
+            (("stats", None), true) => Some(Self::Stats),
+            (("sync", None), true) => Some(Self::Sync),
+            (("tabclose", None), true) => Some(Self::TabClose),
+            (("tabnew", Some(folder)), _) => Some(Self::TabNew(
+                format!("{folder} {rest}").trim_end().to_owned(),
+            )),
+            (("trackers", None), true) => Some(Self::Trackers),
+            (("unblock", entry), true) => Some(Self::EditSenders(
+                Edit::Unblock,
+                entry.map(ToOwned::to_owned),
+            )),
+            (("unflag", Some(flag)), true) =>
+                Some(Self::RemoveFlag(to_imap_flag(flag))),
+            _ => None,
+        }
+    }
+
+    /// Returns the operation to apply on the email with the given uid, in the
+    /// given mailbox
+    ///
+    /// Returns `None` if the command doesn't change an email.
+    pub fn to_operation(&self, mailbox: &str, uid: u32) -> Option<Operation> {
+        let target = Location::new(mailbox, uid);
+        match self {
+            Self::AddFlag(flag) =>
+                Some(Operation::AddFlag { flag: flag.clone(), target }),
+            Self::Delete => Some(Operation::Delete { target }),
+            Self::Move(destination) =>
+                Some(Operation::Move { mailbox: destination.clone(), target }),
+            Self::RemoveFlag(flag) =>
+                Some(Operation::RemoveFlag { flag: flag.clone(), target }),
+            Self::ApplyPatches(_)
+            | Self::CacheClear
+            | Self::CacheStats
+            | Self::DismissReminders
+            | Self::EditSenders(..)
+            | Self::ExportAttachments(..)
+            | Self::Extract(..)
+            | Self::Folder(_)
+            | Self::Folders(_)
+            | Self::Invite(_)
+            | Self::OpenAttachment(_)
+            | Self::Print
+            | Self::Recent
+            | Self::Reconnect
+            | Self::Resend(_)
+            | Self::SaveHtml(_)
+            | Self::SavePatches(_)
+            | Self::Search(_)
+            | Self::Senders
+            | Self::Sort(_)
+            | Self::Stats
+            | Self::Sync
+            | Self::TabClose
+            | Self::TabNew(_)
+            | Self::Trackers => None,
+        }
+    }
+}
+
+/// Converts a flag name to an IMAP flag
+///
+/// The system flags can be written without the backslash, in any case, e.g.
+/// `seen` for `\Seen`. Other names are kept as keywords.
+fn to_imap_flag(name: &str) -> String {
+    let without_backslash = name.strip_prefix('\\').unwrap_or(name);
+    match without_backslash.to_lowercase().as_str() {
+        "answered" => "\\Answered".to_owned(),
+        "deleted" => "\\Deleted".to_owned(),
+        "draft" => "\\Draft".to_owned(),
+        "flagged" => "\\Flagged".to_owned(),
+        "seen" => "\\Seen".to_owned(),
+        _ => name.to_owned(),
+    }
+}
@@ -0,0 +1,125 @@
+//! Completes the folder names in the command line.
+//!
+//! Pressing `Tab` after a command that takes a folder, e.g. `:move`, replaces
+//! the typed text with the best matching folder, and pressing it again cycles
+//! through the other matches. The matching is fuzzy: the typed characters must
+//! appear in the folder name in order, but not next to each other, so `c24ac`
+//! matches `Clients/2024/Acme`.
+//!
+//! The folders are listed once from the server, and kept in the `folders` file
+//! of the cache for the next sessions.
+
+use fuzzy_matcher::FuzzyMatcher as _;
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+use crate::cache::Cache;
+use crate::errors::Result;
+
+/// Commands whose argument is a folder
+const FOLDER_COMMANDS: [&str; 3] = ["folder", "move", "tabnew"];
+/// Name of the file of the cache that stores the folder names
+const FOLDERS_FILE: &str = "folders";
+
+/// Completion of a folder name in progress
+#[derive(Debug, PartialEq, Eq)]
+pub struct Completion {
+    /// Matching folders, from the best match, then the shortest
+    candidates: Vec<String>,
+    /// Command the folder is completed for, e.g. `move`
+    command: String,
+    /// Index of the displayed candidate in `candidates`
+    current: usize,
+}
+
+impl Completion {
+    /// Starts completing the folder of a command line
+    ///
+    /// Returns `None` if the command doesn't take a folder, or if no folder
+    /// matches.
+    pub fn new(line: &str, folders: &[String]) -> Option<Self> {
+        let trimmed = line.trim_start();
+        let (command, typed) = trimmed.split_once(' ').unwrap_or((trimmed, ""));
+        if !FOLDER_COMMANDS.contains(&command) {
+            return None;
+        }
+        let query = typed.trim();
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let mut scored = folders
+            .iter()
+            .filter_map(|folder| {
+                Some((matcher.fuzzy_match(folder, query)?, folder))
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|(score, folder), (other_score, other)| {
+            other_score
+                .cmp(score)
+                .then(folder.len().cmp(&other.len()))
+                .then(folder.cmp(other))
+        });
+        let candidates = scored
+            .into_iter()
+            .map(|(_, folder)| folder.to_owned())
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(Self { candidates, command: command.to_owned(), current: 0 })
+    }
+
+    /// Moves to the next matching folder, going back to the best one after
+    /// the last.
+    pub fn next(&mut self) {
+        self.current = self
+            .current
+            .saturating_add(1)
+            .checked_rem(self.candidates.len())
+            .unwrap_or_default();
+    }
+
+    /// Returns the command line with the displayed candidate
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {}",
+            self.command,
+            self.candidates.get(self.current).map_or("", String::as_str)
+        )
+    }
+}
+
+/// Loads the folder names stored in the cache
+///
+/// Returns an empty list if they were never stored.
+pub fn load_folders(cache: &Cache) -> Vec<String> {
+    cache
+        .load_state(FOLDERS_FILE)
+        .map(|content| content.lines().map(ToOwned::to_owned).collect())
+        .unwrap_or_default()
+}
+
+/// Stores the folder names in the cache, one per line.
+pub fn store_folders(cache: &Cache, folders: &[String]) -> Result {
+    cache.store_state(FOLDERS_FILE, &folders.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::complete::Completion;
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn complete_folders() {
+        let folders =
+            ["INBOX", "Clients/2024/Acme", "Clients/2023/Acme", "Archive"]
+                .map(ToOwned::to_owned);
+        let mut completion = Completion::new("move c24ac", &folders).unwrap();
+        assert_eq!(completion.to_line(), "move Clients/2024/Acme");
+        completion.next();
+        assert_eq!(completion.to_line(), "move Clients/2024/Acme");
+        let mut all = Completion::new("folder ", &folders).unwrap();
+        assert_eq!(all.to_line(), "folder INBOX");
+        all.next();
+        assert_eq!(all.to_line(), "folder Archive");
+        assert_eq!(Completion::new("sort new", &folders), None);
+        assert_eq!(Completion::new("move zzz", &folders), None);
+    }
+}
@@ -1,17 +1,34 @@
 //! Module to define reusable TUI components for the app
 
-use ratatui::layout::Alignment;
+use ratatui::layout::{Alignment, Constraint, Flex, Layout, Rect};
 use ratatui::style::{Style, Stylize as _};
-use ratatui::text::Span;
+use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, BorderType};
 
+/// Percentage of the screen taken by a popup, in both directions
+pub const POPUP_SIZE: u16 = 70;
+
 pub fn title<'title>(title: &'title str) -> Span<'title> {
     Span::styled(title, Style::default().bold())
 }
 
-pub fn new_simple_box(title: &str) -> Block<'_> {
+pub fn new_simple_box<'title, T: Into<Line<'title>>>(
+    title: T,
+) -> Block<'title> {
     Block::bordered()
         .title(title)
         .title_alignment(Alignment::Center)
         .border_type(BorderType::Rounded)
 }
+
+/// Returns the area of a popup in the middle of the given area, taking the
+/// given percentage of it in both directions
+pub fn centered_popup(area: Rect, size: u16) -> Rect {
+    let [vertical] = Layout::vertical([Constraint::Percentage(size)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup] = Layout::horizontal([Constraint::Percentage(size)])
+        .flex(Flex::Center)
+        .areas(vertical);
+    popup
+}
@@ -0,0 +1,276 @@
+//! Lets external tools drive the running TUI through a unix socket.
+//!
+//! While the TUI runs, it listens on `$XDG_RUNTIME_DIR/mailbox-<account>.sock`,
+//! named after the address of its account, so that systemd timers or the
+//! keybindings of a window manager can send it a request, one per connection
+//! and line:
+//!
+//! ```sh
+//! echo refresh | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/mailbox-bob@example.com.sock
+//! mailbox control compose 'mailto:bob@example.com?subject=Hi'
+//! mailbox control goto INBOX
+//! ```
+//!
+//! The TUI answers `ok` once the request is accepted, or an error, and runs
+//! it at its next refresh. `refresh` reloads the displayed folder, `goto`
+//! opens another folder, `compose` opens the writer, pre-filled with the
+//! `mailto:` URL if one is given, `sync` replays the queued operations, as
+//! `:reconnect` does, and `quit` stops the TUI. `account` is
+//! answered right away with the address of the account, e.g. `ok
+//! bob@example.com`.
+//!
+//! Without the `XDG_RUNTIME_DIR` variable, the TUI doesn't listen. The socket
+//! of an instance that crashed is replaced, but not the one of an instance
+//! that is still running: the instances of different accounts listen on
+//! different sockets, and a second instance for the same account is detected
+//! with [`is_running`], to forward `mailbox compose` to the running one, or to
+//! warn that both can change the same flags.
+
+use core::time::Duration;
+use std::env::var;
+use std::fs;
+use std::io::{self, BufRead as _, BufReader, ErrorKind, Write as _};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use crate::mailto::Mailto;
+
+/// Maximum duration to wait for the request of a client, not to freeze the
+/// TUI
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Socket the running TUI listens on
+#[derive(Debug)]
+pub struct Listener {
+    /// Address of the account of the TUI, answered to `account`
+    account: String,
+    /// Non-blocking socket
+    socket: UnixListener,
+    /// Path of the socket, removed when the TUI closes it
+    path: PathBuf,
+}
+
+impl Listener {
+    /// Listens on the socket of the account in the runtime directory
+    ///
+    /// Returns `None` if there is no runtime directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if another instance is listening, or if the socket
+    /// can't be created.
+    pub fn bind(account: &str) -> io::Result<Option<Self>> {
+        to_socket_path(account)
+            .map(|path| Self::bind_at(&path, account))
+            .transpose()
+    }
+
+    /// Stops listening, and removes the socket
+    pub fn close(self) -> io::Result<()> {
+        fs::remove_file(self.path)
+    }
+
+    /// Listens on the socket at the given path, replacing a stale one
+    fn bind_at(path: &Path, account: &str) -> io::Result<Self> {
+        let socket = match UnixListener::bind(path) {
+            Err(err) if err.kind() == ErrorKind::AddrInUse => {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(err);
+                }
+                fs::remove_file(path)?;
+                UnixListener::bind(path)?
+            }
+            result => result?,
+        };
+        socket.set_nonblocking(true)?;
+        Ok(Self { account: account.to_owned(), path: path.to_owned(), socket })
+    }
+
+    /// Accepts the pending connections, and returns their valid requests
+    ///
+    /// Every client is answered with `ok` or with the reason its request was
+    /// rejected. The connections that fail are ignored, and `account` is
+    /// already answered.
+    pub fn accept_requests(&self) -> Vec<Request> {
+        let mut requests = vec![];
+        while let Ok((stream, _)) = self.socket.accept() {
+            if let Ok(Some(request)) = self.read_request(stream)
+                && request != Request::Account
+            {
+                requests.push(request);
+            }
+        }
+        requests
+    }
+
+    /// Reads the request of a client, and answers it
+    fn read_request(&self, stream: UnixStream) -> io::Result<Option<Request>> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let request = Request::parse(&line);
+        let answer = match request {
+            Some(Request::Account) => format!("ok {}\n", self.account),
+            Some(_) => "ok\n".to_owned(),
+            None => format!("error: unknown request '{}'\n", line.trim()),
+        };
+        reader.get_mut().write_all(answer.as_bytes())?;
+        Ok(request)
+    }
+}
+
+/// Request sent by an external tool
+#[derive(Debug, PartialEq, Eq)]
+pub enum Request {
+    /// Asks for the address of the account of the TUI
+    Account,
+    /// Opens the writer, pre-filled with the given `mailto:` URL
+    Compose(Option<Mailto>),
+    /// Opens another folder
+    Goto(String),
+    /// Stops the TUI
+    Quit,
+    /// Reloads the displayed folder
+    Refresh,
+    /// Synchronises the flags and replays the queued operations
+    Sync,
+}
+
+impl Request {
+    /// Parses a request, e.g. `goto INBOX`
+    ///
+    /// Returns `None` if the request doesn't exist or is malformed.
+    pub fn parse(line: &str) -> Option<Self> {
+        let trimmed = line.trim();
+        let (name, argument) = trimmed
+            .split_once(' ')
+            .map_or((trimmed, ""), |(name, rest)| (name, rest.trim()));
+        match (name, argument.is_empty()) {
+            ("account", true) => Some(Self::Account),
+            ("compose", true) => Some(Self::Compose(None)),
+            ("compose", false) =>
+                Some(Self::Compose(Some(Mailto::parse(argument)?))),
+            ("goto", false) => Some(Self::Goto(argument.to_owned())),
+            ("quit", true) => Some(Self::Quit),
+            ("refresh", true) => Some(Self::Refresh),
+            ("sync", true) => Some(Self::Sync),
+            _ => None,
+        }
+    }
+}
+
+/// Checks if a TUI is running for the given account
+pub fn is_running(account: &str) -> bool {
+    send_request(account, "account")
+        .is_ok_and(|answer| answer == format!("ok {account}"))
+}
+
+/// Sends a request to the TUI running for the given account, and returns its
+/// answer
+///
+/// # Errors
+///
+/// Returns an error if there is no runtime directory, if no TUI is running or
+/// if it doesn't answer.
+pub fn send_request(account: &str, line: &str) -> io::Result<String> {
+    let path = to_socket_path(account).ok_or_else(|| {
+        io::Error::new(ErrorKind::NotFound, "XDG_RUNTIME_DIR isn't set")
+    })?;
+    send_request_to(&path, line)
+}
+
+/// Sends a request to the TUI listening on the socket at the given path, and
+/// returns its answer
+fn send_request_to(path: &Path, line: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(TIMEOUT.saturating_mul(10)))?;
+    stream.write_all(format!("{}\n", line.trim()).as_bytes())?;
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer)?;
+    Ok(answer.trim_end().to_owned())
+}
+
+/// Returns the path of the socket of an account, in the runtime directory
+fn to_socket_path(account: &str) -> Option<PathBuf> {
+    var("XDG_RUNTIME_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Path::new(&dir).join(to_socket_name(account)))
+}
+
+/// Returns the name of the socket of an account, e.g.
+/// `mailbox-bob@example.com.sock`
+///
+/// The path separators are replaced, so that the socket stays in the runtime
+/// directory.
+fn to_socket_name(account: &str) -> String {
+    format!("mailbox-{}.sock", account.replace(['/', '\\'], "_"))
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+    use std::thread;
+
+    use crate::test_utils::TempDir;
+    use crate::tui::control::{
+        Listener, Request, send_request_to, to_socket_name
+    };
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn control() {
+        assert_eq!(Request::parse("refresh\n"), Some(Request::Refresh));
+        assert_eq!(Request::parse(" sync "), Some(Request::Sync));
+        assert_eq!(
+            Request::parse("goto Clients/2024 Acme"),
+            Some(Request::Goto("Clients/2024 Acme".to_owned()))
+        );
+        assert_eq!(Request::parse("compose"), Some(Request::Compose(None)));
+        assert!(matches!(
+            Request::parse("compose mailto:bob@example.com"),
+            Some(Request::Compose(Some(_)))
+        ));
+        assert_eq!(Request::parse("compose bob@example.com"), None);
+        assert_eq!(Request::parse("goto"), None);
+        assert_eq!(Request::parse("delete"), None);
+        assert_eq!(
+            to_socket_name("bob@example.com"),
+            "mailbox-bob@example.com.sock"
+        );
+        assert_eq!(to_socket_name("../bob"), "mailbox-.._bob.sock");
+
+        let dir = TempDir::new("control");
+        let path = dir.as_path().join("mailbox.sock");
+        let listener = Listener::bind_at(&path, "bob@example.com").unwrap();
+        Listener::bind_at(&path, "alice@example.com").unwrap_err();
+        let client_path = path.clone();
+        let client = thread::spawn(move || {
+            (
+                send_request_to(&client_path, "account").unwrap(),
+                send_request_to(&client_path, "goto INBOX").unwrap(),
+                send_request_to(&client_path, "unknown").unwrap(),
+            )
+        });
+        let mut requests = vec![];
+        while !client.is_finished() {
+            requests.extend(listener.accept_requests());
+            thread::sleep(Duration::from_millis(10));
+        }
+        requests.extend(listener.accept_requests());
+        assert_eq!(requests, [Request::Goto("INBOX".to_owned())]);
+        assert_eq!(
+            client.join().unwrap(),
+            (
+                "ok bob@example.com".to_owned(),
+                "ok".to_owned(),
+                "error: unknown request 'unknown'".to_owned()
+            )
+        );
+        listener.close().unwrap();
+        assert!(!path.exists());
+    }
+}
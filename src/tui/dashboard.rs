@@ -0,0 +1,60 @@
+//! Displays the statistics of the cached emails in a popup.
+//!
+//! The dashboard is opened with the `:stats` command, and shows the
+//! [`Statistics`] of the cache. `j` and `k` scroll, and `Esc` (or `q`) closes
+//! it.
+
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Clear, Paragraph};
+
+use super::components::{centered_popup, new_simple_box};
+use crate::cache::analytics::Statistics;
+
+/// Percentage of the screen taken by the dashboard, in both directions, larger
+/// than the other popups to fit its statistics
+const DASHBOARD_SIZE: u16 = 80;
+
+/// Popup displaying the statistics of the cache
+pub struct Dashboard {
+    /// Lines describing the statistics
+    lines: Vec<String>,
+    /// Number of lines scrolled down
+    scroll: u16,
+}
+
+impl Dashboard {
+    /// Draws the popup in the middle of the given area
+    pub fn draw(&self, frame: &mut Frame<'_>, area: Rect) {
+        let popup = centered_popup(area, DASHBOARD_SIZE);
+        let text = Text::from(
+            self.lines
+                .iter()
+                .map(|line| Line::from(line.as_str()))
+                .collect::<Vec<_>>(),
+        );
+        let paragraph = Paragraph::new(text)
+            .block(new_simple_box("Statistics ('Esc' to close)"))
+            .scroll((self.scroll, 0));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// Creates the dashboard of the given statistics
+    pub fn new(statistics: &Statistics) -> Self {
+        Self { lines: statistics.to_lines(), scroll: 0 }
+    }
+
+    /// Scrolls down by one line, until the last line is at the top.
+    pub fn scroll_down(&mut self) {
+        if usize::from(self.scroll) < self.lines.len().saturating_sub(1) {
+            self.scroll = self.scroll.saturating_add(1);
+        }
+    }
+
+    /// Scrolls up by one line.
+    pub const fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+}
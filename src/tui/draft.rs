@@ -0,0 +1,90 @@
+//! Saves the content of the writer regularly, to recover it after a crash.
+//!
+//! The draft is stored in the `draft` file of the cache, with the headers
+//! first, one per line, and the body after an empty line:
+//!
+//! ```text
+//! to bob@example.com, alice@example.com
+//! cc carol@example.com
+//! subject Report
+//! priority high
+//!
+//! Everything went fine.
+//! ```
+//!
+//! The draft is emptied when the app exits normally, so a draft found at
+//! startup means the app didn't: it can be restored with `W`.
+
+use core::time::Duration;
+use std::time::Instant;
+
+use super::writer::Writer;
+use crate::cache::Cache;
+use crate::errors::Result;
+
+/// Interval between two saves of the writer
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5);
+/// Name of the file of the cache that stores the draft
+const DRAFT_FILE: &str = "draft";
+
+/// Last save of the writer
+pub struct Autosave {
+    /// Content of the draft that was stored
+    content: String,
+    /// Instant the draft was stored
+    saved_at: Instant,
+}
+
+impl Autosave {
+    /// Stores the draft of the writer, if it changed and the last save is
+    /// old enough
+    ///
+    /// Returns the new save if the draft was stored.
+    pub fn save(
+        last: Option<&Self>,
+        writer: &Writer,
+        cache: &Cache,
+    ) -> Result<Option<Self>> {
+        if last.is_some_and(|save| save.saved_at.elapsed() < AUTOSAVE_INTERVAL)
+        {
+            return Ok(None);
+        }
+        let content = writer.to_draft();
+        if last.is_some_and(|save| save.content == content) {
+            return Ok(None);
+        }
+        cache.store_state(DRAFT_FILE, &content)?;
+        Ok(Some(Self { content, saved_at: Instant::now() }))
+    }
+}
+
+/// Empties the stored draft, once the writer is closed normally.
+pub fn clear_draft(cache: &Cache) -> Result {
+    cache.store_state(DRAFT_FILE, "")
+}
+
+/// Loads the draft left by a crash, if any
+pub fn load_draft(cache: &Cache) -> Option<Writer> {
+    cache
+        .load_state(DRAFT_FILE)
+        .filter(|content| !content.is_empty())
+        .map(|content| Writer::from_draft(&content))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::writer::Writer;
+
+    #[test]
+    fn draft_round_trip() {
+        let draft = "to bob@example.com\nsubject Report\nreply-to \
+                     team@example.com\nheaders X-Ticket: 42\npriority \
+                     high\nreceipt yes\nremind 3\n\nFirst line\n\nSecond line";
+        assert_eq!(Writer::from_draft(draft).to_draft(), draft);
+        assert_eq!(
+            Writer::from_draft("subject Hi").to_draft(),
+            "to \nsubject Hi\nreply-to \nheaders \npriority normal\nreceipt \
+             no\nremind 0\n\n"
+        );
+    }
+}
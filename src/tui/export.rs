@@ -0,0 +1,81 @@
+//! Saves the attachments of many emails at once, e.g. to collect invoices.
+//!
+//! `:export *.pdf ~/invoices` saves the attachments of every email of the
+//! list whose file name matches the glob, where `*` matches any text and `?`
+//! any single character, ignoring the case. The list can be narrowed down
+//! beforehand with a search or the quick filters.
+//!
+//! Files with the same name, already in the directory or from another email,
+//! aren't overwritten: a counter is added before the extension, e.g.
+//! `invoice (2).pdf`.
+
+use alloc::collections::BTreeSet;
+use std::path::Path;
+use std::{fs, io};
+
+use crate::fetch::folders::matches_glob;
+use crate::fetch::parser::Email;
+
+/// Saves the attachments of the emails whose name matches a glob into a
+/// directory, created if it doesn't exist
+///
+/// Returns the number of saved attachments.
+pub fn export_attachments(
+    emails: &[Email],
+    glob: &str,
+    dir: &Path,
+) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let pattern = glob.to_lowercase().chars().collect::<Vec<_>>();
+    let mut taken = BTreeSet::new();
+    for attachment in emails.iter().flat_map(Email::as_attachments) {
+        let name = attachment.to_file_name();
+        if !matches_glob(
+            &pattern,
+            &name.to_lowercase().chars().collect::<Vec<_>>(),
+        ) {
+            continue;
+        }
+        let unique = to_unique_name(&name, |candidate| {
+            taken.contains(candidate) || dir.join(candidate).exists()
+        });
+        fs::write(dir.join(&unique), attachment.as_data())?;
+        taken.insert(unique);
+    }
+    Ok(taken.len())
+}
+
+/// Returns the given file name, or the first name with a counter that isn't
+/// taken, e.g. `invoice (2).pdf`
+fn to_unique_name<Taken: Fn(&str) -> bool>(
+    name: &str,
+    is_taken: Taken,
+) -> String {
+    if !is_taken(name) {
+        return name.to_owned();
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() =>
+            (stem, format!(".{extension}")),
+        Some(_) | None => (name, String::new()),
+    };
+    (2..=u32::MAX)
+        .map(|counter| format!("{stem} ({counter}){extension}"))
+        .find(|candidate| !is_taken(candidate))
+        .unwrap_or_else(|| name.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::export::to_unique_name;
+
+    #[test]
+    fn unique_names() {
+        let taken = ["invoice.pdf", "invoice (2).pdf", ".bashrc", "notes"];
+        let is_taken = |name: &str| taken.contains(&name);
+        assert_eq!(to_unique_name("report.pdf", is_taken), "report.pdf");
+        assert_eq!(to_unique_name("invoice.pdf", is_taken), "invoice (3).pdf");
+        assert_eq!(to_unique_name(".bashrc", is_taken), ".bashrc (2)");
+        assert_eq!(to_unique_name("notes", is_taken), "notes (2)");
+    }
+}
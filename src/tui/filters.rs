@@ -0,0 +1,191 @@
+//! Filters the list of emails with one key.
+//!
+//! The filters are toggled with `F` followed by a key, and stack: an email is
+//! listed only if it matches every active filter.
+//!
+//! | Keys  | Listed emails                          |
+//! | ----- | -------------------------------------- |
+//! | `F u` | unread emails                          |
+//! | `F f` | flagged emails                         |
+//! | `F a` | emails with attachments                |
+//! | `F r` | emails marked as needing a reply       |
+//! | `F s` | emails from the sender of the selected |
+//! | `F c` | every email, clearing the filters      |
+//!
+//! The filters are run on the loaded emails, with the flags of the last
+//! synchronisation with the server.
+
+use mail_parser::HeaderName;
+
+use super::aging::needs_reply;
+use crate::cache::flags::FlagSet;
+use crate::fetch::parser::Email;
+
+/// Flag of the emails that were marked as important
+const FLAGGED_FLAG: &str = "\\Flagged";
+/// Flag of the emails that were read
+pub const SEEN_FLAG: &str = "\\Seen";
+
+/// Checks if an email is unread, given its flags if they are known
+///
+/// Emails whose flags are unknown are considered read.
+pub fn is_unread(flags: Option<&FlagSet>) -> bool {
+    flags.is_some_and(|email_flags| !email_flags.contains(SEEN_FLAG))
+}
+
+/// Condition on the listed emails
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuickFilter {
+    /// Emails with at least one attachment
+    Attachments,
+    /// Emails with the `\Flagged` flag
+    Flagged,
+    /// Emails marked as needing a reply, see [`aging`](super::aging)
+    NeedsReply,
+    /// Emails sent by the given sender
+    Sender(String),
+    /// Emails without the `\Seen` flag
+    Unread,
+}
+
+impl QuickFilter {
+    /// Returns the filter toggled by a key, after `F`
+    ///
+    /// The sender filter lists the emails from the sender of the selected
+    /// email. Returns `None` if the key isn't a filter.
+    pub fn from_key(key: char, selected: Option<&Email>) -> Option<Self> {
+        match key {
+            'a' => Some(Self::Attachments),
+            'f' => Some(Self::Flagged),
+            'r' => Some(Self::NeedsReply),
+            's' =>
+                Some(Self::Sender(selected?.get_header_str(&HeaderName::From)?)),
+            'u' => Some(Self::Unread),
+            _ => None,
+        }
+    }
+
+    /// Checks if an email matches the filter, given its flags if they are
+    /// known
+    ///
+    /// Emails whose flags are unknown are considered read and not flagged.
+    fn matches(&self, email: &Email, flags: Option<&FlagSet>) -> bool {
+        match self {
+            Self::Attachments => email.count_attachments() > 0,
+            Self::Flagged => flags
+                .is_some_and(|email_flags| email_flags.contains(FLAGGED_FLAG)),
+            Self::NeedsReply => needs_reply(flags),
+            Self::Sender(sender) =>
+                email.get_header_str(&HeaderName::From).as_ref() == Some(sender),
+            Self::Unread => is_unread(flags),
+        }
+    }
+
+    /// Returns the name of the filter, as displayed above the list
+    fn to_name(&self) -> String {
+        match self {
+            Self::Attachments => "with attachments".to_owned(),
+            Self::Flagged => "flagged".to_owned(),
+            Self::NeedsReply => "needing a reply".to_owned(),
+            Self::Sender(sender) => format!("from {sender}"),
+            Self::Unread => "unread".to_owned(),
+        }
+    }
+}
+
+/// Active filters of the list
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filters {
+    /// Filters every listed email matches, in the order they were toggled
+    active: Vec<QuickFilter>,
+}
+
+impl Filters {
+    /// Removes every filter.
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    /// Checks if no filter is active
+    pub const fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    /// Checks if an email matches every active filter
+    pub fn matches(&self, email: &Email, flags: Option<&FlagSet>) -> bool {
+        self.active
+            .iter()
+            .all(|filter| filter.matches(email, flags))
+    }
+
+    /// Returns the description of the active filters, e.g. `unread, flagged`
+    pub fn to_description(&self) -> String {
+        self.active
+            .iter()
+            .map(QuickFilter::to_name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Activates a filter, even if it was active
+    ///
+    /// A sender filter replaces the previous one.
+    pub fn activate(&mut self, filter: QuickFilter) {
+        self.active.retain(|active| {
+            !matches!(
+                (active, &filter),
+                (QuickFilter::Attachments, QuickFilter::Attachments)
+                    | (QuickFilter::Flagged, QuickFilter::Flagged)
+                    | (QuickFilter::NeedsReply, QuickFilter::NeedsReply)
+                    | (QuickFilter::Sender(_), QuickFilter::Sender(_))
+                    | (QuickFilter::Unread, QuickFilter::Unread)
+            )
+        });
+        self.active.push(filter);
+    }
+
+    /// Activates a filter, or deactivates it if it was active
+    ///
+    /// A sender filter replaces the previous one.
+    pub fn toggle(&mut self, filter: QuickFilter) {
+        if self.active.contains(&filter) {
+            self.active.retain(|active| *active != filter);
+        } else {
+            self.activate(filter);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::flags::FlagSet;
+    use crate::tui::filters::{Filters, QuickFilter, is_unread};
+
+    #[test]
+    fn unread() {
+        let flags = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (*name).to_owned())
+                .collect::<FlagSet>()
+        };
+        assert!(is_unread(Some(&flags(&[]))));
+        assert!(is_unread(Some(&flags(&["\\Flagged"]))));
+        assert!(!is_unread(Some(&flags(&["\\Seen"]))));
+        assert!(!is_unread(None));
+    }
+
+    #[test]
+    fn toggle_filters() {
+        let mut filters = Filters::default();
+        filters.toggle(QuickFilter::Unread);
+        filters.toggle(QuickFilter::Sender("alice".to_owned()));
+        assert_eq!(filters.to_description(), "unread, from alice");
+        filters.toggle(QuickFilter::Sender("bob".to_owned()));
+        assert_eq!(filters.to_description(), "unread, from bob");
+        filters.toggle(QuickFilter::Unread);
+        assert_eq!(filters.to_description(), "from bob");
+        filters.clear();
+        assert!(filters.is_empty());
+    }
+}
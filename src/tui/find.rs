@@ -0,0 +1,258 @@
+//! Searches the body of the opened email.
+//!
+//! The search is started with `/` in the viewer, and is independent of the
+//! list of emails. The matches are highlighted, and `n` and `N` jump to the
+//! next and previous ones, scrolling the body to keep the current match
+//! visible.
+//!
+//! The search ignores the case of ASCII letters if the searched text has no
+//! uppercase letter, like the smart case of vim.
+
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use unicode_width::UnicodeWidthStr as _;
+
+/// Number of lines kept above the current match when scrolling to it
+const SCROLL_MARGIN: u16 = 2;
+
+/// Position of a match in the body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Match {
+    /// Byte offset of the end of the match in its line
+    end: usize,
+    /// Index of the line of the match
+    line: usize,
+    /// Byte offset of the start of the match in its line
+    start: usize,
+}
+
+/// Search in the body of the opened email
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BodySearch {
+    /// Index of the current match in `matches`
+    current: usize,
+    /// Matches of the searched text, in reading order
+    matches: Vec<Match>,
+    /// Searched text
+    query: String,
+}
+
+impl BodySearch {
+    /// Returns the number of lines to scroll the body by to show the current
+    /// match, with the body wrapped at the given width
+    ///
+    /// The wrapping is estimated from the width of the lines, so the current
+    /// match is kept a few lines below the top of the viewer.
+    pub fn as_scroll(&self, body: &str, width: u16) -> u16 {
+        let Some(current) = self.matches.get(self.current) else {
+            return 0;
+        };
+        count_rows(body.lines().take(current.line), width)
+            .saturating_sub(SCROLL_MARGIN)
+    }
+
+    /// Highlights the matches in the highlighted body
+    ///
+    /// The current match is reversed, the other ones are underlined.
+    pub fn highlight<'body>(&self, text: Text<'body>) -> Text<'body> {
+        let current = self.matches.get(self.current);
+        text.lines
+            .into_iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                let ranges = self
+                    .matches
+                    .iter()
+                    .filter(|found| found.line == idx)
+                    .map(|found| {
+                        let modifier = if Some(found) == current {
+                            Modifier::REVERSED
+                        } else {
+                            Modifier::UNDERLINED
+                        };
+                        (found.start, found.end, modifier)
+                    })
+                    .collect::<Vec<_>>();
+                if ranges.is_empty() {
+                    line
+                } else {
+                    highlight_ranges(line, &ranges)
+                }
+            })
+            .collect()
+    }
+
+    /// Searches a text in a body
+    pub fn new(body: &str, query: &str) -> Self {
+        let ignore_case = !query.chars().any(char::is_uppercase);
+        let matches = body
+            .lines()
+            .enumerate()
+            .flat_map(|(line_idx, line)| {
+                find_in_line(line, query, ignore_case).into_iter().map(
+                    move |(start, end)| Match { end, line: line_idx, start },
+                )
+            })
+            .collect();
+        Self { current: 0, matches, query: query.to_owned() }
+    }
+
+    /// Moves to the next match, going back to the first one after the last.
+    pub fn next(&mut self) {
+        self.current = self
+            .current
+            .saturating_add(1)
+            .checked_rem(self.matches.len())
+            .unwrap_or_default();
+    }
+
+    /// Moves to the previous match, going to the last one before the first.
+    pub fn previous(&mut self) {
+        self.current = self
+            .current
+            .checked_sub(1)
+            .unwrap_or_else(|| self.matches.len().saturating_sub(1));
+    }
+
+    /// Returns the status message describing the current match
+    pub fn to_status(&self) -> String {
+        if self.matches.is_empty() {
+            format!("Pattern not found: {}", self.query)
+        } else {
+            format!(
+                "/{} [{}/{}]",
+                self.query,
+                self.current.saturating_add(1),
+                self.matches.len()
+            )
+        }
+    }
+}
+
+/// Returns the number of rows taken by the lines, wrapped at the given width
+///
+/// The wrapping is estimated from the width of the lines.
+pub fn count_rows<'body, Lines: Iterator<Item = &'body str>>(
+    lines: Lines,
+    width: u16,
+) -> u16 {
+    let columns = usize::from(width.max(1));
+    let rows = lines
+        .map(|line| line.width().div_ceil(columns).max(1))
+        .sum::<usize>();
+    u16::try_from(rows).unwrap_or(u16::MAX)
+}
+
+/// Finds the non-overlapping occurrences of a text in a line, as byte ranges
+fn find_in_line(
+    line: &str,
+    query: &str,
+    ignore_case: bool,
+) -> Vec<(usize, usize)> {
+    let mut found = vec![];
+    if query.is_empty() {
+        return found;
+    }
+    let mut next_start = 0;
+    for (start, _) in line.char_indices() {
+        let end = start.saturating_add(query.len());
+        if start < next_start {
+            continue;
+        }
+        if line.get(start..end).is_some_and(|candidate| {
+            if ignore_case {
+                candidate.eq_ignore_ascii_case(query)
+            } else {
+                candidate == query
+            }
+        }) {
+            found.push((start, end));
+            next_start = end;
+        }
+    }
+    found
+}
+
+/// Adds a modifier to byte ranges of a line, splitting its spans where needed
+fn highlight_ranges<'body>(
+    line: Line<'body>,
+    ranges: &[(usize, usize, Modifier)],
+) -> Line<'body> {
+    let mut spans = vec![];
+    let mut offset: usize = 0;
+    for span in line.spans {
+        let content = span.content.into_owned();
+        let span_end = offset.saturating_add(content.len());
+        let mut cuts = ranges
+            .iter()
+            .flat_map(|(start, end, _)| [*start, *end])
+            .filter(|cut| offset < *cut && *cut < span_end)
+            .map(|cut| cut.saturating_sub(offset))
+            .collect::<Vec<_>>();
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        let mut rest = content.as_str();
+        let mut consumed: usize = 0;
+        for cut in cuts.into_iter().chain([content.len()]) {
+            let Some((piece, tail)) =
+                rest.split_at_checked(cut.saturating_sub(consumed))
+            else {
+                continue;
+            };
+            let piece_start = offset.saturating_add(consumed);
+            let style = ranges
+                .iter()
+                .find(|(start, end, _)| {
+                    *start <= piece_start && piece_start < *end
+                })
+                .map_or(span.style, |(_, _, modifier)| {
+                    span.style.patch(Style::new().add_modifier(*modifier))
+                });
+            if !piece.is_empty() {
+                spans.push(Span::styled(piece.to_owned(), style));
+            }
+            consumed = cut;
+            rest = tail;
+        }
+        offset = span_end;
+    }
+    Line { spans, ..line }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::style::Modifier;
+    use ratatui::text::Line;
+
+    use crate::tui::find::{BodySearch, highlight_ranges};
+
+    #[test]
+    fn search_body() {
+        let body = "Hello world\nhello again, HELLO\nbye";
+        let mut search = BodySearch::new(body, "hello");
+        assert_eq!(search.matches.len(), 3);
+        assert_eq!(search.to_status(), "/hello [1/3]");
+        search.previous();
+        assert_eq!(search.to_status(), "/hello [3/3]");
+        search.next();
+        assert_eq!(search.to_status(), "/hello [1/3]");
+        assert_eq!(BodySearch::new(body, "Hello").matches.len(), 1);
+        assert_eq!(
+            BodySearch::new(body, "nothing").to_status(),
+            "Pattern not found: nothing"
+        );
+    }
+
+    #[test]
+    fn split_spans() {
+        let line = Line::from(vec!["Hello ".into(), "world".into()]);
+        let highlighted = highlight_ranges(line, &[(4, 8, Modifier::REVERSED)]);
+        let pieces = highlighted
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(pieces, ["Hell", "o ", "wo", "rld"]);
+    }
+}
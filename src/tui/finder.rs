@@ -0,0 +1,201 @@
+//! Finds folders, contacts and emails from a single popup.
+//!
+//! The finder is opened with `Ctrl-P` in reading mode, like in editors. The
+//! typed text is fuzzily matched against the folder names, the senders and
+//! the subjects of the loaded emails at the same time, and the best results
+//! are listed first. `Up` and `Down` (or `Ctrl-P` and `Ctrl-N`) move in the
+//! results, `Enter` jumps to the selected one and `Esc` closes the popup.
+//!
+//! Jumping to a folder opens it, to a contact lists only the emails they sent,
+//! and to an email selects it in the list.
+
+use fuzzy_matcher::FuzzyMatcher as _;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use mail_parser::HeaderName;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::text::Line;
+use ratatui::widgets::{Clear, List, ListState};
+use tui_input::Input;
+
+use super::components::{POPUP_SIZE, centered_popup, new_simple_box};
+use crate::fetch::parser::Email;
+
+/// Maximum number of results listed in the popup
+const MAX_RESULTS: usize = 50;
+
+/// Result of the finder
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// Sender of a loaded email
+    Contact(String),
+    /// Email, with its uid and subject
+    Email(u32, String),
+    /// Folder of the account
+    Folder(String),
+}
+
+impl Target {
+    /// Returns the text the typed text is matched against
+    fn as_text(&self) -> &str {
+        match self {
+            Self::Contact(text) | Self::Email(_, text) | Self::Folder(text) =>
+                text,
+        }
+    }
+
+    /// Returns the line displayed in the results, with the kind of target
+    fn to_line(&self) -> Line<'_> {
+        let kind = match self {
+            Self::Contact(_) => "contact",
+            Self::Email(..) => "email  ",
+            Self::Folder(_) => "folder ",
+        };
+        Line::from(format!("{kind} {}", self.as_text()))
+    }
+}
+
+/// Popup of the fuzzy finder
+#[derive(Default)]
+pub struct Finder {
+    /// Text typed to filter the targets
+    input: Input,
+    /// Indexes of the targets matching the typed text, from the best match
+    results: Vec<usize>,
+    /// Index of the selected result in `results`
+    selected: usize,
+    /// Every folder, contact and email that can be found
+    targets: Vec<Target>,
+}
+
+impl Finder {
+    /// Returns the input of the typed text, to update it
+    pub const fn as_input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    /// Returns the selected target, if any matches the typed text
+    pub fn as_selected(&self) -> Option<&Target> {
+        self.targets.get(*self.results.get(self.selected)?)
+    }
+
+    /// Draws the popup in the middle of the given area
+    pub fn draw(&self, frame: &mut Frame<'_>, area: Rect) {
+        let popup = centered_popup(area, POPUP_SIZE);
+        let list = List::new(
+            self.results
+                .iter()
+                .filter_map(|idx| Some(self.targets.get(*idx)?.to_line())),
+        )
+        .block(new_simple_box(format!("Find: {}", self.input.value())))
+        .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_widget(Clear, popup);
+        frame.render_stateful_widget(list, popup, &mut state);
+    }
+
+    /// Creates a finder on the folders, and the senders and subjects of the
+    /// emails
+    pub fn new(folders: &[String], emails: &[Email]) -> Self {
+        let mut contacts = emails
+            .iter()
+            .filter_map(|email| email.get_header_str(&HeaderName::From))
+            .collect::<Vec<_>>();
+        contacts.sort_unstable();
+        contacts.dedup();
+        let targets = folders
+            .iter()
+            .cloned()
+            .map(Target::Folder)
+            .chain(contacts.into_iter().map(Target::Contact))
+            .chain(emails.iter().map(|email| {
+                Target::Email(
+                    email.as_uid(),
+                    email
+                        .get_header_str(&HeaderName::Subject)
+                        .unwrap_or_default(),
+                )
+            }))
+            .collect();
+        let mut finder = Self { targets, ..Self::default() };
+        finder.update_results();
+        finder
+    }
+
+    /// Selects the next result, staying on the last one.
+    pub fn select_next(&mut self) {
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .min(self.results.len().saturating_sub(1));
+    }
+
+    /// Selects the previous result, staying on the first one.
+    pub const fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Matches the targets against the typed text, and selects the best
+    /// match.
+    pub fn update_results(&mut self) {
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let query = self.input.value();
+        let mut scored = self
+            .targets
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, target)| {
+                Some((matcher.fuzzy_match(target.as_text(), query)?, idx))
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|(score, idx), (other_score, other_idx)| {
+            other_score.cmp(score).then(idx.cmp(other_idx))
+        });
+        self.results = scored
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, idx)| idx)
+            .collect();
+        self.selected = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fetch::parser::Email;
+    use crate::tui::finder::{Finder, Target};
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn find_targets() {
+        let emails = [
+            Email::try_from((
+                1,
+                b"From: bob@x\nSubject: Acme invoice\n\nBody".as_slice(),
+            ))
+            .unwrap(),
+            Email::try_from((
+                2,
+                b"From: bob@x\nSubject: Lunch\n\nBody".as_slice(),
+            ))
+            .unwrap(),
+        ];
+        let folders = ["INBOX".to_owned(), "Clients/2024/Acme".to_owned()];
+        let mut finder = Finder::new(&folders, &emails);
+        assert_eq!(finder.results.len(), 5);
+
+        *finder.as_input_mut() = "acme".into();
+        finder.update_results();
+        assert_eq!(finder.results.len(), 2);
+
+        *finder.as_input_mut() = "lunch".into();
+        finder.update_results();
+        assert_eq!(
+            finder.as_selected(),
+            Some(&Target::Email(2, "Lunch".to_owned()))
+        );
+        finder.select_next();
+        assert_eq!(finder.results.len(), 1);
+        assert!(finder.as_selected().is_some());
+    }
+}
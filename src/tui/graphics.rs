@@ -0,0 +1,322 @@
+//! Draws pictures in the terminal, with the kitty or the sixel graphics
+//! protocol.
+//!
+//! The protocol is detected from the environment variables set by the
+//! terminal. The kitty protocol decodes the PNG pictures itself, so they are
+//! sent as they are. For sixel, the pictures are decoded, scaled and reduced
+//! to a palette of 216 colours.
+//!
+//! The pictures aren't known by [`ratatui`], so they are drawn over the cells
+//! once the frame was rendered.
+
+use std::env::var;
+use std::io::{self, Write};
+
+use openssl::base64::encode_block;
+use png::{ColorType, Decoder, Transformations};
+use ratatui::crossterm::cursor::MoveTo;
+use ratatui::crossterm::queue;
+use ratatui::crossterm::terminal::window_size;
+use ratatui::layout::Position;
+
+/// Number of levels of every channel of the sixel palette
+const CUBE_LEVELS: u16 = 6;
+/// Id of the picture in the kitty protocol, to delete it once it is replaced
+const KITTY_ID: u32 = 1;
+/// Maximum number of base64 bytes sent per chunk with the kitty protocol
+const KITTY_CHUNK: usize = 4096;
+/// Height in pixels of a cell, if the terminal doesn't give its size
+const CELL_HEIGHT_DEFAULT: u16 = 20;
+/// Number of rows in a sixel band
+const SIXEL_BAND: usize = 6;
+/// Offset of the characters of the sixel bands
+const SIXEL_OFFSET: u8 = 63;
+
+/// Graphics protocol supported by the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Graphics {
+    /// Protocol of kitty, also supported by `WezTerm` and `Ghostty`
+    Kitty,
+    /// Sixel protocol, supported by `foot`, `mlterm` and `xterm -ti vt340`
+    Sixel,
+}
+
+impl Graphics {
+    /// Detects the graphics protocol supported by the terminal, if any
+    pub fn detect() -> Option<Self> {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        if var("KITTY_WINDOW_ID").is_ok()
+            || term.contains("kitty")
+            || ["WezTerm", "ghostty"].contains(&program.as_str())
+        {
+            Some(Self::Kitty)
+        } else if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+        {
+            Some(Self::Sixel)
+        } else {
+            None
+        }
+    }
+
+    /// Draws a PNG picture at a cell of the terminal, in a square of one row
+    ///
+    /// Pictures that can't be decoded are ignored.
+    pub fn draw<W: Write>(
+        self,
+        writer: &mut W,
+        picture: &[u8],
+        position: Position,
+    ) -> io::Result<()> {
+        let sequence = match self {
+            Self::Kitty => to_kitty(picture),
+            Self::Sixel => {
+                let size = window_size()
+                    .ok()
+                    .and_then(|window| window.height.checked_div(window.rows));
+                let Some(sixel) =
+                    to_sixel(picture, size.unwrap_or(CELL_HEIGHT_DEFAULT))
+                else {
+                    return Ok(());
+                };
+                sixel
+            }
+        };
+        queue!(writer, MoveTo(position.x, position.y))?;
+        writer.write_all(sequence.as_bytes())?;
+        writer.flush()
+    }
+
+    /// Removes the drawn picture
+    ///
+    /// Returns `false` if the cells under the picture must be drawn again to
+    /// remove it.
+    pub fn erase<W: Write>(self, writer: &mut W) -> io::Result<bool> {
+        match self {
+            Self::Kitty => {
+                write!(writer, "\x1b_Ga=d,d=i,i={KITTY_ID},q=2\x1b\\")?;
+                writer.flush()?;
+                Ok(true)
+            }
+            Self::Sixel => Ok(false),
+        }
+    }
+}
+
+/// Returns the kitty sequences that display a PNG picture
+///
+/// The picture is one row high, with its width computed by the terminal to
+/// keep its ratio.
+fn to_kitty(picture: &[u8]) -> String {
+    let encoded = encode_block(picture);
+    let chunks = encoded.as_bytes().chunks(KITTY_CHUNK).collect::<Vec<_>>();
+    let last = chunks.len().saturating_sub(1);
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let more = u8::from(index != last);
+            let payload = String::from_utf8_lossy(chunk);
+            if index == 0 {
+                format!(
+                    "\x1b_Ga=T,f=100,i={KITTY_ID},r=1,C=1,q=2,m={more};{payload}\x1b\\"
+                )
+            } else {
+                format!("\x1b_Gm={more};{payload}\x1b\\")
+            }
+        })
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+/// Returns the sixel sequence that displays a PNG picture, scaled to a square
+/// of `side` pixels
+///
+/// Returns `None` if the picture can't be decoded. Transparent pixels are left
+/// untouched.
+fn to_sixel(picture: &[u8], side: u16) -> Option<String> {
+    let mut decoder = Decoder::new(picture);
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).ok()?;
+    let channels = match info.color_type {
+        ColorType::Grayscale => 1,
+        ColorType::GrayscaleAlpha => 2,
+        ColorType::Rgb => 3,
+        ColorType::Rgba => 4,
+        ColorType::Indexed => return None,
+    };
+    let width = usize::try_from(info.width).ok()?;
+    let height = usize::try_from(info.height).ok()?;
+    let square = usize::from(side.max(1));
+
+    let colors = (0..square)
+        .map(|row| {
+            (0..square)
+                .map(|column| {
+                    let x = column.saturating_mul(width).checked_div(square)?;
+                    let y = row.saturating_mul(height).checked_div(square)?;
+                    let start = y
+                        .saturating_mul(width)
+                        .saturating_add(x)
+                        .saturating_mul(channels);
+                    to_palette_index(buffer.get(start..)?.get(..channels)?)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let palette = (0..CUBE_LEVELS.pow(3))
+        .map(|index| {
+            let [red, green, blue] = to_cube(index).map(|level| {
+                level
+                    .saturating_mul(100)
+                    .checked_div(CUBE_LEVELS - 1)
+                    .unwrap_or_default()
+            });
+            format!("#{index};2;{red};{green};{blue}")
+        })
+        .collect::<Vec<_>>()
+        .concat();
+    let bands = colors
+        .chunks(SIXEL_BAND)
+        .map(|band| to_sixel_band(band, square))
+        .collect::<Vec<_>>()
+        .concat();
+    Some(format!("\x1bP0;1;0q\"1;1;{square};{square}{palette}{bands}\x1b\\"))
+}
+
+/// Returns the sixels of a band of at most [`SIXEL_BAND`] rows of palette
+/// colours, one pass per colour
+fn to_sixel_band(band: &[Vec<Option<u16>>], width: usize) -> String {
+    let mut used = band.iter().flatten().flatten().copied().collect::<Vec<_>>();
+    used.sort_unstable();
+    used.dedup();
+    let passes = used
+        .into_iter()
+        .map(|color| {
+            let columns = (0..width).map(|column| {
+                band.iter()
+                    .enumerate()
+                    .filter(|(_, row)| {
+                        row.get(column).copied().flatten() == Some(color)
+                    })
+                    .fold(u8::MIN, |bits, (shift, _)| {
+                        bits | u8::pow(
+                            2,
+                            u32::try_from(shift).unwrap_or_default(),
+                        )
+                    })
+            });
+            format!("#{color}{}$", to_sixel_row(columns))
+        })
+        .collect::<Vec<_>>()
+        .concat();
+    format!("{passes}-")
+}
+
+/// Returns the characters of a row of sixels, compressing the repetitions
+fn to_sixel_row<Columns: Iterator<Item = u8>>(columns: Columns) -> String {
+    let mut runs: Vec<(u8, usize)> = vec![];
+    for bits in columns {
+        match runs.last_mut() {
+            Some((last, count)) if *last == bits =>
+                *count = count.saturating_add(1),
+            Some(_) | None => runs.push((bits, 1)),
+        }
+    }
+    runs.into_iter()
+        .map(|(bits, count)| {
+            let ch = char::from(SIXEL_OFFSET.saturating_add(bits));
+            if count > 3 {
+                format!("!{count}{ch}")
+            } else {
+                ch.to_string().repeat(count)
+            }
+        })
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+/// Returns the levels of red, green and blue of a colour of the palette
+fn to_cube(index: u16) -> [u16; 3] {
+    let levels = CUBE_LEVELS;
+    [
+        index.checked_div(levels * levels).unwrap_or_default(),
+        index
+            .checked_div(levels)
+            .and_then(|value| value.checked_rem(levels))
+            .unwrap_or_default(),
+        index.checked_rem(levels).unwrap_or_default(),
+    ]
+}
+
+/// Returns the colour of the palette closest to a pixel, or `None` if the
+/// pixel is transparent
+fn to_palette_index(pixel: &[u8]) -> Option<u16> {
+    let (red, green, blue, alpha) = match *pixel {
+        [gray] => (gray, gray, gray, u8::MAX),
+        [gray, alpha] => (gray, gray, gray, alpha),
+        [red, green, blue] => (red, green, blue, u8::MAX),
+        [red, green, blue, alpha] => (red, green, blue, alpha),
+        _ => return None,
+    };
+    if alpha < 128 {
+        return None;
+    }
+    let level = |value: u8| {
+        u16::from(value)
+            .saturating_mul(CUBE_LEVELS - 1)
+            .saturating_add(127)
+            .checked_div(255)
+            .unwrap_or_default()
+    };
+    Some(
+        level(red)
+            .saturating_mul(CUBE_LEVELS * CUBE_LEVELS)
+            .saturating_add(level(green).saturating_mul(CUBE_LEVELS))
+            .saturating_add(level(blue)),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use png::{ColorType, Encoder};
+
+    use crate::tui::graphics::{to_cube, to_kitty, to_palette_index, to_sixel};
+
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn red_square() -> Vec<u8> {
+        let mut picture = vec![];
+        let mut encoder = Encoder::new(&mut picture, 2, 2);
+        encoder.set_color(ColorType::Rgba);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[255, 0, 0, 255].repeat(4))
+            .unwrap();
+        drop(writer);
+        picture
+    }
+
+    #[test]
+    fn graphics() {
+        assert_eq!(to_palette_index(&[255, 0, 0]), Some(180));
+        assert_eq!(to_cube(180), [5, 0, 0]);
+        assert_eq!(to_palette_index(&[255, 0, 0, 0]), None);
+
+        let picture = red_square();
+        assert!(to_kitty(&picture).starts_with("\x1b_Ga=T,f=100,i=1,"));
+        assert_eq!(
+            to_sixel(&picture, 6).map(|sixel| {
+                sixel
+                    .split_once("#215;2;100;100;100")
+                    .map(|(_, bands)| bands.to_owned())
+            }),
+            Some(Some("#180!6~$-\x1b\\".to_owned()))
+        );
+        assert_eq!(to_sixel(b"not a picture", 6), None);
+    }
+}
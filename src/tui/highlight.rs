@@ -0,0 +1,213 @@
+//! Highlights the structured content of the bodies in the email viewer.
+//!
+//! The following content is highlighted:
+//! - unified diffs, in the patches sent by `git send-email`, from their first
+//!   `diff`, `--- a/`, `Index:` or `@@ -` line to the first line that isn't
+//!   part of the diff,
+//! - quoted replies, with a colour per quoting level,
+//! - fenced code blocks,
+//! - URLs and email addresses,
+//! - the signature, after the `-- ` line.
+
+use ratatui::style::{Color, Style, Stylize as _};
+use ratatui::text::{Line, Span, Text};
+
+/// Colours of the quoted lines, cycled through by quoting level.
+const QUOTE_COLORS: [Color; 3] = [Color::Blue, Color::Magenta, Color::Cyan];
+
+/// Characters that may surround a URL or address without being part of it.
+pub const SURROUNDING_PUNCTUATION: [char; 10] =
+    ['<', '>', '(', ')', '.', ',', ';', ':', '!', '?'];
+
+/// Section of the body being highlighted
+#[derive(PartialEq, Eq)]
+enum Section {
+    /// Inside a fenced code block
+    Code,
+    /// Inside a unified diff
+    Diff,
+    /// Inside the signature
+    Signature,
+    /// Normal text
+    Text,
+}
+
+/// Highlights a plain text body, with its diffs if it is a patch.
+pub fn highlight_body(body: &str, is_patch: bool) -> Text<'_> {
+    let mut section = Section::Text;
+
+    body.lines()
+        .map(|line| {
+            if line.starts_with("```") {
+                section = if section == Section::Code {
+                    Section::Text
+                } else {
+                    Section::Code
+                };
+                return Line::styled(line, Style::new().fg(Color::Yellow));
+            }
+            if line == "-- " {
+                section = Section::Signature;
+            }
+            if section == Section::Diff && !is_diff_line(line) {
+                section = Section::Text;
+            }
+            if is_patch && section == Section::Text && is_diff_start(line) {
+                section = Section::Diff;
+            }
+
+            match section {
+                Section::Code =>
+                    Line::styled(line, Style::new().fg(Color::Yellow)),
+                Section::Diff => Line::styled(line, diff_style(line)),
+                Section::Signature => Line::styled(line, Style::new().dim()),
+                Section::Text => highlight_inline(line, quote_style(line)),
+            }
+        })
+        .collect()
+}
+
+/// Returns the style of a line inside a diff
+fn diff_style(line: &str) -> Style {
+    if line.starts_with("diff ")
+        || line.starts_with("+++ ")
+        || line.starts_with("--- ")
+    {
+        Style::new().bold()
+    } else if line.starts_with("@@") {
+        Style::new().fg(Color::Cyan)
+    } else if line.starts_with('+') {
+        Style::new().fg(Color::Green)
+    } else if line.starts_with('-') {
+        Style::new().fg(Color::Red)
+    } else {
+        Style::new()
+    }
+}
+
+/// Highlights the URLs and email addresses of a line.
+fn highlight_inline(line: &str, base: Style) -> Line<'_> {
+    let mut spans = vec![];
+
+    for (idx, word) in line.split(' ').enumerate() {
+        if idx != 0 {
+            spans.push(Span::styled(" ", base));
+        }
+
+        let start_trimmed = word.trim_start_matches(SURROUNDING_PUNCTUATION);
+        let trimmed = start_trimmed.trim_end_matches(SURROUNDING_PUNCTUATION);
+        let prefix_len = word.len().saturating_sub(start_trimmed.len());
+        let style = if is_url(trimmed) {
+            Some(base.fg(Color::Blue).underlined())
+        } else if is_email_address(trimmed) {
+            Some(base.fg(Color::Magenta))
+        } else {
+            None
+        };
+
+        if let Some(link_style) = style
+            && let Some((prefix, rest)) = word.split_at_checked(prefix_len)
+            && let Some((link, suffix)) = rest.split_at_checked(trimmed.len())
+        {
+            spans.push(Span::styled(prefix, base));
+            spans.push(Span::styled(link, link_style));
+            spans.push(Span::styled(suffix, base));
+        } else {
+            spans.push(Span::styled(word, base));
+        }
+    }
+
+    Line::from(spans)
+}
+
+/// Checks if a line starts a diff
+fn is_diff_start(line: &str) -> bool {
+    line.starts_with("diff ")
+        || line.starts_with("--- a/")
+        || line.starts_with("Index: ")
+        || line.starts_with("@@ -")
+}
+
+/// Checks if a line continues a diff: a context, added or removed line, or a
+/// hunk header
+fn is_diff_line(line: &str) -> bool {
+    line.starts_with([' ', '+', '-', '@'])
+}
+
+/// Checks if a word looks like an email address
+fn is_email_address(word: &str) -> bool {
+    word.split_once('@').is_some_and(|(user, domain)| {
+        !user.is_empty()
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && word
+                .chars()
+                .all(|ch| ch.is_alphanumeric() || "@.-_+".contains(ch))
+    })
+}
+
+/// Checks if a word looks like a URL
+pub fn is_url(word: &str) -> bool {
+    ["https://", "http://", "www."].iter().any(|prefix| {
+        word.strip_prefix(prefix)
+            .is_some_and(|rest| !rest.is_empty())
+    })
+}
+
+/// Returns the style of a line depending on its quoting level
+fn quote_style(line: &str) -> Style {
+    let level = line
+        .chars()
+        .filter(|ch| *ch != ' ')
+        .take_while(|ch| *ch == '>')
+        .count();
+    level
+        .checked_sub(1)
+        .and_then(|idx| idx.checked_rem(QUOTE_COLORS.len()))
+        .and_then(|idx| QUOTE_COLORS.get(idx))
+        .map_or_else(Style::new, |color| Style::new().fg(*color))
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::style::{Color, Modifier, Style};
+
+    use crate::tui::highlight::highlight_body;
+
+    #[test]
+    fn diffs() {
+        let body = "Fix the parser.\n\n- handle empty lines\n---\n\
+                    diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n\
+                    +++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn main() {\n\
+                    -    old();\n+    new();\nThanks,\n- Bob";
+        let styles = |is_patch| {
+            highlight_body(body, is_patch)
+                .lines
+                .into_iter()
+                .map(|line| line.style)
+                .collect::<Vec<_>>()
+        };
+        let plain = Style::new();
+        let bold = plain.add_modifier(Modifier::BOLD);
+        assert_eq!(
+            styles(true),
+            [
+                plain,
+                plain,
+                plain,
+                plain,
+                bold,
+                bold,
+                bold,
+                plain.fg(Color::Cyan),
+                plain,
+                plain.fg(Color::Red),
+                plain.fg(Color::Green),
+                plain,
+                plain,
+            ]
+        );
+        assert_eq!(styles(false), [plain; 13]);
+    }
+}
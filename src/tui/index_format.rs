@@ -0,0 +1,327 @@
+//! Formats the rows of the email explorer from a mutt-like format string.
+//!
+//! The format string contains fields like `%subject`, that are replaced by the
+//! value of the email, and literal text. A width can be given to pad or
+//! truncate the field, e.g. `%-20from` to left-align the sender on 20 columns,
+//! or `%10date` to right-align the date on 10 columns.
+//!
+//! The available fields are:
+//! - `%attachments`: paperclip and number of attachments, blank if there are
+//!   none,
+//! - `%date`: date of the email, e.g. `2021-11-20 14:22`,
+//! - `%flags`: flags of the email at the last synchronisation, as letters: `N`
+//!   if it is unread, `F` if it is flagged, `r` if it was answered and `D` if
+//!   it is deleted, blank if they aren't known,
+//! - `%from`: sender of the email, or its recipients after `To:` in the sent
+//!   folder,
+//! - `%priority`: `!` for high priority emails, `↓` for low priority ones,
+//! - `%subject`: subject of the email,
+//! - `%to`: destination of the email.
+//!
+//! Use `%%` for a literal `%`.
+
+use mail_parser::HeaderName;
+use unicode_width::UnicodeWidthStr as _;
+
+use super::bidi::fit_visual;
+use super::filters::is_unread;
+use super::width::{fit, truncate};
+use crate::cache::flags::FlagSet;
+use crate::fetch::parser::Email;
+
+/// Letters of the `%flags` field after the unread one, with the flag each
+/// one stands for
+const FLAG_LETTERS: [(&str, char); 3] =
+    [("\\Flagged", 'F'), ("\\Answered", 'r'), ("\\Deleted", 'D')];
+
+/// Field of an email that can be displayed in the explorer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    /// Number of attachments of the email
+    Attachments,
+    /// Date of the email
+    Date,
+    /// Flags of the email
+    Flags,
+    /// Sender of the email
+    From,
+    /// Priority of the email
+    Priority,
+    /// Subject of the email
+    Subject,
+    /// Destination of the email
+    To,
+}
+
+impl Field {
+    /// Returns the field with the given name, if it exists.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "attachments" => Some(Self::Attachments),
+            "date" => Some(Self::Date),
+            "flags" => Some(Self::Flags),
+            "from" => Some(Self::From),
+            "priority" => Some(Self::Priority),
+            "subject" => Some(Self::Subject),
+            "to" => Some(Self::To),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the field for the given email, with its flags if
+    /// they are known
+    ///
+    /// In the sent folder, the sender is replaced by the recipients.
+    fn value(
+        self,
+        email: &Email,
+        flags: Option<&FlagSet>,
+        is_sent: bool,
+    ) -> String {
+        match self {
+            Self::Attachments => to_attachment_marker(email),
+            Self::Date => email
+                .as_headers()
+                .get(&HeaderName::Date)
+                .and_then(|date| date.as_datetime())
+                .map_or_else(String::new, |date| {
+                    format!(
+                        "{:04}-{:02}-{:02} {:02}:{:02}",
+                        date.year, date.month, date.day, date.hour, date.minute
+                    )
+                }),
+            Self::Flags => to_flag_letters(flags),
+            Self::From if is_sent =>
+                format!("To: {}", email.to_recipient_names()),
+            Self::From =>
+                email.get_header_str(&HeaderName::From).unwrap_or_default(),
+            Self::Priority => email.to_priority().as_marker().to_owned(),
+            Self::Subject => email
+                .get_header_str(&HeaderName::Subject)
+                .unwrap_or_default(),
+            Self::To =>
+                email.get_header_str(&HeaderName::To).unwrap_or_default(),
+        }
+    }
+}
+
+/// Returns the paperclip marker of an email with its number of attachments,
+/// e.g. `📎2`, or an empty string if it has none
+pub fn to_attachment_marker(email: &Email) -> String {
+    match email.count_attachments() {
+        0 => String::new(),
+        count => format!("\u{1f4ce}{count}"),
+    }
+}
+
+/// Returns the letters of the flags of an email, e.g. `NF` for an unread
+/// flagged email, or an empty string if they aren't known
+fn to_flag_letters(flags: Option<&FlagSet>) -> String {
+    let Some(email_flags) = flags else {
+        return String::new();
+    };
+    let unread = is_unread(flags).then_some('N');
+    unread
+        .into_iter()
+        .chain(FLAG_LETTERS.iter().filter_map(|(flag, letter)| {
+            email_flags.contains(*flag).then_some(*letter)
+        }))
+        .collect()
+}
+
+/// Part of a format string
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    /// Field of the email, with its alignment
+    Field {
+        /// Field to display
+        field: Field,
+        /// Indicates whether the field is aligned to the left
+        left_aligned: bool,
+        /// Number of columns the field takes, if fixed
+        width: Option<usize>,
+    },
+    /// Text displayed as is
+    Literal(String),
+}
+
+/// Parsed format string of the rows of the email explorer
+#[derive(Debug, PartialEq, Eq)]
+pub struct IndexFormat {
+    /// Parts of the format string, in order
+    tokens: Vec<Token>,
+}
+
+impl IndexFormat {
+    /// Formats the row of an email, with its flags if they are known, fitting
+    /// it in `width` columns.
+    ///
+    /// `is_sent` is set for the emails of the sent folder, to show who they
+    /// were sent to.
+    pub fn format(
+        &self,
+        email: &Email,
+        flags: Option<&FlagSet>,
+        width: usize,
+        is_sent: bool,
+    ) -> String {
+        let row = self
+            .tokens
+            .iter()
+            .map(|token| match token {
+                Token::Field { field, left_aligned, width: field_width } => {
+                    let value = field.value(email, flags, is_sent);
+                    match (field_width, left_aligned) {
+                        (None, _) => value,
+                        (Some(columns), true) => fit(&value, *columns),
+                        (Some(columns), false) => {
+                            let truncated = truncate(&value, *columns);
+                            let padding =
+                                columns.saturating_sub(truncated.width());
+                            format!("{}{truncated}", " ".repeat(padding))
+                        }
+                    }
+                }
+                Token::Literal(literal) => literal.clone(),
+            })
+            .collect::<String>();
+        fit_visual(&row, width)
+    }
+
+    /// Parses a format string
+    ///
+    /// Unknown fields are kept as literal text.
+    pub fn parse(format: &str) -> Self {
+        let mut tokens = vec![];
+        let mut literal = String::new();
+        let mut rest = format;
+
+        while let Some((before, after)) = rest.split_once('%') {
+            literal.push_str(before);
+            if let Some(escaped) = after.strip_prefix('%') {
+                literal.push('%');
+                rest = escaped;
+                continue;
+            }
+
+            let unaligned = after.strip_prefix('-');
+            let without_alignment = unaligned.unwrap_or(after);
+            let digits_end = without_alignment
+                .find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(without_alignment.len());
+            let (digits, with_name) = without_alignment.split_at(digits_end);
+            let name_end = with_name
+                .find(|ch: char| !ch.is_ascii_alphabetic())
+                .unwrap_or(with_name.len());
+            let (name, remaining) = with_name.split_at(name_end);
+
+            if let Some(field) = Field::from_name(name) {
+                if !literal.is_empty() {
+                    tokens.push(Token::Literal(literal));
+                    literal = String::new();
+                }
+                tokens.push(Token::Field {
+                    field,
+                    left_aligned: unaligned.is_some(),
+                    width: digits.parse().ok(),
+                });
+                rest = remaining;
+            } else {
+                literal.push('%');
+                rest = after;
+            }
+        }
+
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+
+        Self { tokens }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::flags::FlagSet;
+    use crate::fetch::parser::Email;
+    use crate::tui::index_format::{Field, IndexFormat, Token};
+
+    #[test]
+    fn parse_format() {
+        assert_eq!(
+            IndexFormat::parse("%date %-20from 100%% %unknown%subject"),
+            IndexFormat {
+                tokens: vec![
+                    Token::Field {
+                        field: Field::Date,
+                        left_aligned: false,
+                        width: None
+                    },
+                    Token::Literal(" ".to_owned()),
+                    Token::Field {
+                        field: Field::From,
+                        left_aligned: true,
+                        width: Some(20)
+                    },
+                    Token::Literal(" 100% %unknown".to_owned()),
+                    Token::Field {
+                        field: Field::Subject,
+                        left_aligned: false,
+                        width: None
+                    },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn sent_format() {
+        let email = Email::try_from((
+            1,
+            "From: me@example.com\nTo: Alice <alice@example.com>, \
+             bob@example.com\nSubject: Hi\n\nHello"
+                .as_bytes(),
+        ))
+        .unwrap();
+        let format = IndexFormat::parse("%-30from|%subject");
+        assert_eq!(
+            format.format(&email, None, 50, true),
+            "To: Alice, bob@example.com    |Hi                 "
+        );
+        assert!(
+            format
+                .format(&email, None, 50, false)
+                .starts_with("me@example.com")
+        );
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn flags_format() {
+        let email =
+            Email::try_from((1, b"Subject: Hi\n\nHello".as_slice())).unwrap();
+        let format = IndexFormat::parse("[%flags] %subject");
+        let flags = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (*name).to_owned())
+                .collect::<FlagSet>()
+        };
+        assert_eq!(
+            format.format(&email, Some(&flags(&["\\Flagged"])), 10, false),
+            "[NF] Hi   "
+        );
+        assert_eq!(
+            format.format(
+                &email,
+                Some(&flags(&["\\Seen", "\\Answered", "$NeedsReply"])),
+                10,
+                false
+            ),
+            "[r] Hi    "
+        );
+        assert_eq!(format.format(&email, None, 10, false), "[] Hi     ");
+    }
+}
@@ -0,0 +1,108 @@
+//! Replaces the URLs of the displayed bodies with numbered references.
+//!
+//! Long URLs break the wrapping of the body, so when the `LINK_REFERENCES`
+//! flag of the [`Config`](crate::config::Config) is set, each URL is replaced
+//! with a reference like `[1]`, and listed at the bottom of the body:
+//!
+//! ```text
+//! The report is on the wiki [1], see also the dashboard [2].
+//!
+//! -- Links ('L' to show or hide) --
+//! [1] https://wiki.example.com/reports/2025/q1?view=full
+//! [2] https://dashboard.example.com
+//! ```
+//!
+//! The same URL gets the same number every time it appears. The list of
+//! references can be hidden with `L`, in which case only its title is
+//! displayed.
+
+use super::highlight::{SURROUNDING_PUNCTUATION, is_url};
+
+/// Title of the list of references, at the bottom of the body
+const REFERENCES_TITLE: &str = "-- Links ('L' to show or hide) --";
+
+/// Returns the body without the list of references, but with its title
+///
+/// The body is returned as is if it has no references.
+pub fn collapse(body: &str) -> &str {
+    body.rfind(REFERENCES_TITLE)
+        .and_then(|start| {
+            body.get(..start.saturating_add(REFERENCES_TITLE.len()))
+        })
+        .unwrap_or(body)
+}
+
+/// Replaces the URLs of a body with numbered references, listed at the
+/// bottom of the body
+pub fn to_references(body: &str) -> String {
+    let mut urls: Vec<&str> = vec![];
+    let lines = body
+        .lines()
+        .map(|line| {
+            line.split(' ')
+                .map(|word| {
+                    let start_trimmed =
+                        word.trim_start_matches(SURROUNDING_PUNCTUATION);
+                    let trimmed =
+                        start_trimmed.trim_end_matches(SURROUNDING_PUNCTUATION);
+                    if !is_url(trimmed) {
+                        return word.to_owned();
+                    }
+                    let number = urls
+                        .iter()
+                        .position(|url| *url == trimmed)
+                        .unwrap_or_else(|| {
+                            urls.push(trimmed);
+                            urls.len().saturating_sub(1)
+                        })
+                        .saturating_add(1);
+                    let prefix_len =
+                        word.len().saturating_sub(start_trimmed.len());
+                    let prefix = word.get(..prefix_len).unwrap_or_default();
+                    let suffix =
+                        start_trimmed.get(trimmed.len()..).unwrap_or_default();
+                    format!("{prefix}[{number}]{suffix}")
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+    if urls.is_empty() {
+        return body.to_owned();
+    }
+    let references = urls
+        .iter()
+        .enumerate()
+        .map(|(idx, url)| format!("[{}] {url}", idx.saturating_add(1)));
+    lines
+        .into_iter()
+        .chain([String::new(), REFERENCES_TITLE.to_owned()])
+        .chain(references)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::links::{collapse, to_references};
+
+    #[test]
+    fn references() {
+        let body = "See https://example.com/a, or (https://example.com/b).\n> \
+                    Quoted https://example.com/a\nNo links here";
+        let linked = to_references(body);
+        assert_eq!(
+            linked,
+            "See [1], or ([2]).\n> Quoted [1]\nNo links here\n\n-- Links ('L' \
+             to show or hide) --\n[1] https://example.com/a\n[2] \
+             https://example.com/b"
+        );
+        assert_eq!(
+            collapse(&linked),
+            "See [1], or ([2]).\n> Quoted [1]\nNo links here\n\n-- Links ('L' \
+             to show or hide) --"
+        );
+        assert_eq!(to_references("No links"), "No links");
+        assert_eq!(collapse("No links"), "No links");
+    }
+}
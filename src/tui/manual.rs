@@ -14,7 +14,7 @@ fn bold(title: &str) -> Span<'_> {
 
 /// Displays the manual page to the current frame
 pub fn manual_page(frame: &mut Frame<'_>) {
-    let lines = vec![
+    let mut lines = vec![
         Line::from(bold("mailbox-tui")),
         Line::from(""),
         Line::from("A TUI app to read, write and manage emails."),
@@ -40,17 +40,204 @@ pub fn manual_page(frame: &mut Frame<'_>) {
             "Mode to display emails from an inbox. Press 'r' to switch to this mode.",
         ),
         Line::from(""),
-        Line::from("Keybindings:"),
-        Line::from("- 'k': select previous email"),
-        Line::from("- 'j': select next email"),
-        Line::from("- 'h': close email reader"),
-        Line::from("- 'm': open email reader"),
+    ];
+    lines.extend(reading_keys());
+    lines.push(Line::from(""));
+    lines.extend(commands());
+    lines.extend([
         Line::from(""),
         Line::from(bold("Write mode")),
         Line::from(""),
         Line::from("Mode to write emails. Press 'w' to switch to this mode."),
-    ];
+        Line::from(
+            "The email is saved every few seconds: if the app crashes, press 'W' at the next startup to restore it.",
+        ),
+        Line::from(""),
+        Line::from(
+            "- 'S' (or 'Ctrl-S', and 'Ctrl-Enter' in the terminals supporting the kitty keyboard protocol, even while editing a field): preview the email exactly as it will be sent, then 'y' to send it after the UNDO_SEND_DELAY of the .env file, during which 'U' cancels the sending",
+        ),
+        Line::from(
+            "- 'c': edit the Cc recipients, 'B': edit the Bcc recipients, who receive the email without being written in its headers",
+        ),
+        Line::from(
+            "- 'A': attach files chosen in a file browser ('Space' to mark several), 'u': remove the last attachment",
+        ),
+        Line::from(
+            "- 'Ctrl-E': while editing the subject or the body, insert an emoji or a unicode character, found by name or code point (e.g. 'U+2192')",
+        ),
+        Line::from(
+            "- 'f': re-wrap the paragraphs of the body at the WRAP_COLUMN of the .env file",
+        ),
+        Line::from(
+            "- 'p': change the priority of the email, between normal, high and low",
+        ),
+        Line::from(
+            "- 'i': change the identity to send from, between the EMAIL and the IDENTITIES of the .env file (a reply is sent from the identity the email was sent to, with a warning if it wasn't sent to any)",
+        ),
+        Line::from(
+            "- 'a': show the advanced headers, 'R': edit the Reply-To, 'x': edit the custom headers (e.g. 'X-Ticket: 42; X-Team: support'), 'd': request a read receipt, 'n': request delivery notifications on failure, delay or success, 'r': be reminded if there is no reply after 1, 3, 7 or 14 days",
+        ),
+        Line::from(
+            "- 'Tab': while editing the subject of a reply, cycle through the subjects of the conversation",
+        ),
+    ]);
 
     let help = Paragraph::new(Text::from(lines)).wrap(Wrap { trim: false });
     frame.render_widget(help, frame.area());
 }
+
+/// Lists the keybindings of the reading mode
+fn reading_keys() -> Vec<Line<'static>> {
+    vec![
+        Line::from("Keybindings:"),
+        Line::from("- 'k': select previous email"),
+        Line::from("- 'j': select next email"),
+        Line::from("- 'J', 'K': select the next or previous unread email"),
+        Line::from(
+            "  (when an email is opened, these keys open the newly selected email in place)",
+        ),
+        Line::from("- 'gg': select first email"),
+        Line::from("- 'G': select last email"),
+        Line::from(
+            "- '<n>j', '<n>k': move by n emails, '<n>gg', '<n>G': select the n-th email",
+        ),
+        Line::from(
+            "- 'gt', 'gT': go to the next or previous tab, '<n>gt': go to the n-th tab",
+        ),
+        Line::from(
+            "- 'g1' to 'g9': open the n-th folder of FOLDER_SHORTCUTS, 'g0': go back to the previous folder",
+        ),
+        Line::from(
+            "- 'h': close email reader (with PREVIEW_PANE set, the hovered email is previewed instead)",
+        ),
+        Line::from(
+            "- '<', '>': shrink or grow the email list, 'z': zoom the opened email to full screen",
+        ),
+        Line::from(
+            "- 'L': show or hide the list of the links at the bottom of the email, when LINK_REFERENCES is set",
+        ),
+        Line::from(
+            "- 'X': show or hide the long blocks of CSS or encoded data of the email",
+        ),
+        Line::from("- 'm': open email reader"),
+        Line::from(
+            "- 'S': choose a directory to save the attachments of the opened email in",
+        ),
+        Line::from(
+            "- 'Fu', 'Ff', 'Fr', 'Fa', 'Fs': only list the unread, flagged, needing a reply, with attachments or from the selected sender emails",
+        ),
+        Line::from(
+            "  (the filters stack, pressing the keys again removes a filter, and 'Fc' clears them)",
+        ),
+        Line::from(
+            "- 'R': reply to the selected email, quoting it, with the threading headers already set",
+        ),
+        Line::from(
+            "- 'E': edit the selected email as a new one, with its recipients, body and attachments (e.g. to send a corrected version)",
+        ),
+        Line::from(
+            "- '!': mark the selected email as needing a reply, or unmark it (the mark is removed and the email is flagged as answered when a reply is sent, and the flagged or marked emails left unanswered are highlighted in yellow after 3 days, light red after a week and bold red after two weeks)",
+        ),
+        Line::from(
+            "- 'y': copy the one-time code of the opened or selected email (e.g. a sign-in code) with the CLIPBOARD_COMMAND of the .env file, or through the terminal",
+        ),
+        Line::from(
+            "- 'l': open the selected email, or resume it in the writer in the drafts folder (the sent folder shows the recipients instead of the sender)",
+        ),
+        Line::from("- ':': open the command line"),
+        Line::from(
+            "- 'Ctrl-P': find a folder, a contact or an email by typing parts of its name ('Enter' to jump to it)",
+        ),
+        Line::from(
+            "- '/': search the opened email, 'n', 'N': go to the next or previous match",
+        ),
+        Line::from(
+            "- 'd', 'u': scroll the opened email down or up (the position is restored when the email is opened again)",
+        ),
+        Line::from(
+            "- 'c' then '1' to '9': reply to the opened email with the RESPONSE_TEMPLATES of the .env file ('c1': thanks, 'c2': will get back to you by default, 'U' to undo)",
+        ),
+    ]
+}
+
+/// Lists the commands of the command line
+fn commands() -> Vec<Line<'static>> {
+    vec![
+        Line::from("Commands (press 'Enter' to run, 'Esc' to cancel):"),
+        Line::from(
+            "(press 'Tab' to complete the folder of 'folder', 'move' and 'tabnew', fuzzily: 'c24ac' completes 'Clients/2024/Acme')",
+        ),
+        Line::from("- 'print': print the opened email"),
+        Line::from(
+            "- 'invite [path]': save the calendar invite of the opened email to a file, or import it with CALENDAR_COMMAND",
+        ),
+        Line::from(
+            "- 'trackers': list the domains of the tracking pixels of the opened email, counted in the title of the viewer",
+        ),
+        Line::from(
+            "- 'html <file>': save the HTML body of the opened email, without its scripts, forms and remote content (see REMOTE_CONTENT_SENDERS and TRUSTED_HTML)",
+        ),
+        Line::from(
+            "- 'dismiss': forget the reminders of the unanswered emails, listed above the inbox",
+        ),
+        Line::from(
+            "- 'open <n>': open the n-th attachment of the opened email",
+        ),
+        Line::from(
+            "- 'extract <n>.<m> <dir>': extract the m-th file of the n-th attachment, a zip or tar archive, into a directory",
+        ),
+        Line::from(
+            "- 'bounce <addresses>': resend the selected email as is to other addresses, with Resent-* headers",
+        ),
+        Line::from(
+            "- 'export <glob> <dir>': save the attachments of the listed emails matching the glob, e.g. 'export *.pdf ~/invoices'",
+        ),
+        Line::from(
+            "- 'flag <flag>', 'unflag <flag>': change the flags of the selected email, e.g. 'flag seen'",
+        ),
+        Line::from(
+            "- 'move <mailbox>': move the selected email to another mailbox, or to a local folder that is only kept in the cache, e.g. 'local:Archives 2019' (the email is then deleted from the server)",
+        ),
+        Line::from(
+            "  (moving to or out of the JUNK_FOLDER of the .env file trains the spam filter with LEARN_SPAM_COMMAND or LEARN_HAM_COMMAND)",
+        ),
+        Line::from("- 'delete': delete the selected email"),
+        Line::from(
+            "- 'block [sender]', 'allow [sender]', 'unblock [sender]': block, allow or forget an address or a domain, the sender of the selected email by default",
+        ),
+        Line::from(
+            "  (the emails of the blocked senders are hidden, or moved to the junk folder with BLOCKED_SENDERS=junk in the .env file; 'senders' lists them)",
+        ),
+        Line::from(
+            "- 'reconnect': reconnect after being offline and replay the queued operations",
+        ),
+        Line::from(
+            "- 'sync': fetch the remaining emails in the background ('Esc' or 'Ctrl-C' to cancel)",
+        ),
+        Line::from(
+            "- 'folder <name>': open another folder, back where you left it",
+        ),
+        Line::from(
+            "- 'sort <order>': sort the emails by 'newest', 'oldest', 'from', 'subject', 'attachments' or 'thread'",
+        ),
+        Line::from(
+            "  ('thread' also lists your replies cached from the sent folder in their conversations, read-only)",
+        ),
+        Line::from(
+            "- 'tabnew <name>': open a folder in a new tab, 'tabclose': close the current tab",
+        ),
+        Line::from(
+            "- 'search <text>': open the loaded emails whose sender or subject contain the text in a new tab",
+        ),
+        Line::from(
+            "- 'stats': show statistics of the cached emails, per sender, folder, weekday and attachment type, and the average response time",
+        ),
+        Line::from(
+            "- 'folders [<parent>/ | <filter>]': list the top-level folders, the children of a parent or the folders whose name contains the filter, and whether they are synced to the cache, online-only or local (a folder followed by '/' has children)",
+        ),
+        Line::from("- 'recent': list the recently opened folders"),
+        Line::from(
+            "- 'cache stats', 'cache clear': show the size of the local cache, or remove the cached emails",
+        ),
+    ]
+}
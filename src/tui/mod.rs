@@ -1,7 +1,41 @@
 //! Runs and manages the TUI and its interactions.
 
+mod aging;
 pub mod app;
+mod avatar;
+pub mod badges;
+mod bidi;
+mod browser;
+mod command;
+mod complete;
 mod components;
+pub mod control;
+mod dashboard;
+mod draft;
+mod export;
+mod filters;
+mod find;
+mod finder;
+mod graphics;
+mod highlight;
+pub mod index_format;
+mod links;
 mod manual;
+mod noise;
+mod picker;
+pub mod pictures;
+mod positions;
+mod reminders;
+pub mod responses;
+mod send_preview;
+pub mod senders;
+mod sent_replies;
+mod session;
+pub mod shortcuts;
+pub mod split;
 mod states;
+pub mod theme;
+mod threads;
+mod views;
+mod width;
 mod writer;
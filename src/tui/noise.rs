@@ -0,0 +1,94 @@
+//! Hides the blocks of style sheets and encoded data of the displayed bodies.
+//!
+//! The emails sent only as HTML often come out of the display filters with
+//! kilobytes of CSS, or with base64 data that wasn't decoded. A block of at
+//! least [`MIN_HIDDEN_LINES`] consecutive lines that look like such noise is
+//! replaced with a marker, e.g. `[… 120 lines hidden, 'X' to show …]`,
+//! and `X` shows or hides them again.
+//!
+//! A line is noise if it is a long word of base64 characters, or if it looks
+//! like CSS: a selector opening a block, a closing brace, an at-rule or a
+//! `property: value;` declaration.
+
+/// Minimum number of consecutive noisy lines that are hidden
+const MIN_HIDDEN_LINES: usize = 5;
+/// Minimum length of a line of base64 data
+const MIN_BASE64_LEN: usize = 60;
+
+/// Replaces the blocks of noise of a body with a marker counting their lines
+pub fn hide(body: &str) -> String {
+    let mut output = vec![];
+    let mut noise = vec![];
+    for line in body.lines() {
+        if is_noise(line) {
+            noise.push(line);
+            continue;
+        }
+        flush(&mut output, &mut noise);
+        output.push(line.to_owned());
+    }
+    flush(&mut output, &mut noise);
+    output.join("\n")
+}
+
+/// Moves the pending noisy lines to the output, replaced with a marker if
+/// there are enough of them
+fn flush(output: &mut Vec<String>, noise: &mut Vec<&str>) {
+    if noise.len() >= MIN_HIDDEN_LINES {
+        output.push(format!(
+            "[\u{2026} {} lines hidden, 'X' to show \u{2026}]",
+            noise.len()
+        ));
+    } else {
+        output.extend(noise.iter().map(|line| (*line).to_owned()));
+    }
+    noise.clear();
+}
+
+/// Checks if a line looks like base64 data or CSS
+fn is_noise(line: &str) -> bool {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return false;
+    }
+    let is_base64 = trimmed.len() >= MIN_BASE64_LEN
+        && trimmed.chars().all(|ch| {
+            ch.is_ascii_alphanumeric() || matches!(ch, '+' | '/' | '=')
+        });
+    let is_declaration = trimmed.ends_with(';')
+        && trimmed.split_once(':').is_some_and(|(property, _)| {
+            !property.is_empty()
+                && property
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+        });
+    is_base64
+        || is_declaration
+        || trimmed.ends_with('{')
+        || trimmed.starts_with('}')
+        || trimmed.starts_with('@')
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::noise::hide;
+
+    #[test]
+    fn hidden() {
+        let css = "@media screen {\n  .header {\n    color: #333;\n    \
+                   font-family: Arial, sans-serif;\n  }\n}";
+        let body = format!("Hello,\n{css}\nThe report is ready.\n{}\nBye", [
+            "QUJDREVGR0hJSktMTU5PUFFSU1RVVldYWVphYmNkZWZnaGlqa2xtbm9wcXJzdHV2";
+            6
+        ]
+        .join("\n"));
+        assert_eq!(
+            hide(&body),
+            "Hello,\n[\u{2026} 6 lines hidden, 'X' to show \u{2026}]\nThe \
+             report is ready.\n[\u{2026} 6 lines hidden, 'X' to show \
+             \u{2026}]\nBye"
+        );
+        let short = "Note: see below;\ncolor: red;\nThanks";
+        assert_eq!(hide(short), short);
+    }
+}
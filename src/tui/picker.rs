@@ -0,0 +1,221 @@
+//! Inserts emoji and other unicode characters in the writer.
+//!
+//! The picker is opened with `Ctrl-E` while editing the subject or the body.
+//! The typed text is fuzzily matched against the names of the characters, and
+//! a code point like `U+2192` is also accepted to insert any other character.
+//! `Up` and `Down` (or `Ctrl-P` and `Ctrl-N`) move in the results, `Enter`
+//! inserts the selected character at the cursor and `Esc` closes the popup.
+
+use fuzzy_matcher::FuzzyMatcher as _;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::text::Line;
+use ratatui::widgets::{Clear, List, ListState};
+use tui_input::Input;
+
+use super::components::{centered_popup, new_simple_box};
+
+/// Characters that can be found by name
+const CHARACTERS: [(&str, char); 72] = [
+    ("grinning face", '\u{1f600}'),
+    ("smiling face", '\u{1f60a}'),
+    ("laughing tears of joy", '\u{1f602}'),
+    ("winking face", '\u{1f609}'),
+    ("heart eyes", '\u{1f60d}'),
+    ("thinking face", '\u{1f914}'),
+    ("neutral face", '\u{1f610}'),
+    ("sad face", '\u{1f622}'),
+    ("crying face", '\u{1f62d}'),
+    ("angry face", '\u{1f620}'),
+    ("surprised face", '\u{1f62e}'),
+    ("sweat smile", '\u{1f605}'),
+    ("upside down face", '\u{1f643}'),
+    ("rolling eyes", '\u{1f644}'),
+    ("sunglasses cool", '\u{1f60e}'),
+    ("party face", '\u{1f973}'),
+    ("thumbs up", '\u{1f44d}'),
+    ("thumbs down", '\u{1f44e}'),
+    ("clapping hands", '\u{1f44f}'),
+    ("waving hand", '\u{1f44b}'),
+    ("folded hands thanks please", '\u{1f64f}'),
+    ("raised hands", '\u{1f64c}'),
+    ("ok hand", '\u{1f44c}'),
+    ("muscle strong", '\u{1f4aa}'),
+    ("eyes", '\u{1f440}'),
+    ("red heart", '\u{2764}'),
+    ("broken heart", '\u{1f494}'),
+    ("fire", '\u{1f525}'),
+    ("sparkles", '\u{2728}'),
+    ("star", '\u{2b50}'),
+    ("party popper tada", '\u{1f389}'),
+    ("gift", '\u{1f381}'),
+    ("birthday cake", '\u{1f382}'),
+    ("rocket", '\u{1f680}'),
+    ("light bulb idea", '\u{1f4a1}'),
+    ("warning", '\u{26a0}'),
+    ("no entry", '\u{26d4}'),
+    ("check mark", '\u{2705}'),
+    ("cross mark", '\u{274c}'),
+    ("question mark", '\u{2753}'),
+    ("exclamation mark", '\u{2757}'),
+    ("hundred points", '\u{1f4af}'),
+    ("calendar", '\u{1f4c5}'),
+    ("paperclip", '\u{1f4ce}'),
+    ("pushpin", '\u{1f4cc}'),
+    ("memo", '\u{1f4dd}'),
+    ("email envelope", '\u{1f4e7}'),
+    ("telephone", '\u{1f4de}'),
+    ("laptop computer", '\u{1f4bb}'),
+    ("lock", '\u{1f512}'),
+    ("key", '\u{1f511}'),
+    ("bug", '\u{1f41b}'),
+    ("coffee", '\u{2615}'),
+    ("beer", '\u{1f37a}'),
+    ("pizza", '\u{1f355}'),
+    ("sun", '\u{2600}'),
+    ("cloud", '\u{2601}'),
+    ("umbrella rain", '\u{2614}'),
+    ("snowflake", '\u{2744}'),
+    ("right arrow", '\u{2192}'),
+    ("left arrow", '\u{2190}'),
+    ("up arrow", '\u{2191}'),
+    ("down arrow", '\u{2193}'),
+    ("left right arrow", '\u{2194}'),
+    ("em dash", '\u{2014}'),
+    ("ellipsis", '\u{2026}'),
+    ("bullet", '\u{2022}'),
+    ("degree", '\u{b0}'),
+    ("euro sign", '\u{20ac}'),
+    ("pound sign", '\u{a3}'),
+    ("copyright", '\u{a9}'),
+    ("trade mark", '\u{2122}'),
+];
+/// Maximum number of results listed in the popup
+const MAX_RESULTS: usize = 50;
+/// Percentage of the screen taken by the picker, in both directions, smaller
+/// than the other popups as it only lists characters
+const PICKER_SIZE: u16 = 50;
+
+/// Popup to insert a unicode character
+#[derive(Default)]
+pub struct Picker {
+    /// Text typed to find the characters
+    input: Input,
+    /// Characters matching the typed text, with their names, from the best
+    /// match
+    results: Vec<(String, char)>,
+    /// Index of the selected result in `results`
+    selected: usize,
+}
+
+impl Picker {
+    /// Returns the input of the typed text, to update it
+    pub const fn as_input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    /// Returns the selected character, if any matches the typed text
+    pub fn as_selected(&self) -> Option<char> {
+        self.results.get(self.selected).map(|(_, ch)| *ch)
+    }
+
+    /// Draws the popup in the middle of the given area
+    pub fn draw(&self, frame: &mut Frame<'_>, area: Rect) {
+        let popup = centered_popup(area, PICKER_SIZE);
+        let list = List::new(
+            self.results
+                .iter()
+                .map(|(name, ch)| Line::from(format!("{ch} {name}"))),
+        )
+        .block(new_simple_box(format!("Insert: {}", self.input.value())))
+        .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_widget(Clear, popup);
+        frame.render_stateful_widget(list, popup, &mut state);
+    }
+
+    /// Creates a picker listing every known character
+    pub fn new() -> Self {
+        let mut picker = Self::default();
+        picker.update_results();
+        picker
+    }
+
+    /// Selects the next result, staying on the last one.
+    pub fn select_next(&mut self) {
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .min(self.results.len().saturating_sub(1));
+    }
+
+    /// Selects the previous result, staying on the first one.
+    pub const fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Matches the characters against the typed text, and selects the best
+    /// match
+    ///
+    /// A typed code point is listed first.
+    pub fn update_results(&mut self) {
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let query = self.input.value();
+        let mut scored = CHARACTERS
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, (name, ch))| {
+                Some((matcher.fuzzy_match(name, query)?, idx, *name, *ch))
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|(score, idx, ..), (other_score, other_idx, ..)| {
+            other_score.cmp(score).then(idx.cmp(other_idx))
+        });
+        self.results = parse_code_point(query)
+            .map(|ch| (format!("U+{:04X}", u32::from(ch)), ch))
+            .into_iter()
+            .chain(
+                scored
+                    .into_iter()
+                    .map(|(_, _, name, ch)| (name.to_owned(), ch)),
+            )
+            .take(MAX_RESULTS)
+            .collect();
+        self.selected = 0;
+    }
+}
+
+/// Parses a code point written as `U+2192`
+fn parse_code_point(text: &str) -> Option<char> {
+    let hex = text
+        .trim()
+        .strip_prefix("U+")
+        .or_else(|| text.trim().strip_prefix("u+"))?;
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::picker::Picker;
+
+    #[test]
+    fn pick_characters() {
+        let mut picker = Picker::new();
+        assert_eq!(picker.as_selected(), Some('\u{1f600}'));
+
+        *picker.as_input_mut() = "thumbs down".into();
+        picker.update_results();
+        assert_eq!(picker.as_selected(), Some('\u{1f44e}'));
+
+        *picker.as_input_mut() = "U+2192".into();
+        picker.update_results();
+        assert_eq!(picker.as_selected(), Some('\u{2192}'));
+        picker.select_next();
+        assert_eq!(picker.as_selected(), Some('\u{2192}'));
+
+        *picker.as_input_mut() = "zzzz".into();
+        picker.update_results();
+        assert_eq!(picker.as_selected(), None);
+    }
+}
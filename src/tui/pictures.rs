@@ -0,0 +1,235 @@
+//! Fetches the pictures of the senders from Gravatar, to draw them in the
+//! viewer over their initials.
+//!
+//! This is only done if the `SENDER_PICTURES` flag of the `.env` file is set,
+//! as it tells Gravatar whose emails are read, and if the terminal supports a
+//! [graphics protocol](super::graphics). The initials are displayed
+//! otherwise, and while the picture is fetched.
+//!
+//! The pictures are requested with the SHA-256 hash of the address, in the
+//! background, and stored in the `avatars/` directory of the cache. Senders
+//! without a picture are stored with an empty file, so that every sender is
+//! only requested once.
+//!
+//! The BIMI logos of the domains aren't fetched, as they are SVG files that
+//! the graphics protocols can't draw.
+
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+use std::io::{self, Read as _, Write as _};
+use std::net::TcpStream;
+use std::thread::{self, JoinHandle};
+
+use native_tls::TlsConnector;
+use openssl::sha::sha256;
+use ratatui::layout::Position;
+
+use super::app::Error;
+use super::graphics::Graphics;
+use crate::cache::Cache;
+use crate::errors::Result;
+
+/// Host serving the pictures
+const GRAVATAR_HOST: &str = "www.gravatar.com";
+/// Port of the HTTPS server of Gravatar
+const HTTPS_PORT: u16 = 443;
+/// Maximum size of a response, to ignore oversized pictures
+const MAX_RESPONSE_SIZE: u64 = 0x0010_0000;
+/// Size in pixels of the requested pictures
+const PICTURE_SIZE: u16 = 64;
+/// Maximum duration of a request
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pictures of the senders, and the one drawn in the viewer
+#[derive(Default)]
+pub struct Pictures {
+    /// Cache storing the fetched pictures, if any
+    cache: Option<Cache>,
+    /// Sender and position of the drawn picture, if any
+    drawn: Option<(String, Position)>,
+    /// Protocol to draw the pictures, or `None` if they aren't fetched
+    graphics: Option<Graphics>,
+    /// Pictures of the senders, empty for the senders without one, indexed
+    /// by address
+    loaded: BTreeMap<String, Vec<u8>>,
+    /// Pictures being fetched in the background, indexed by address
+    pending: BTreeMap<String, JoinHandle<io::Result<Vec<u8>>>>,
+}
+
+impl Pictures {
+    /// Draws the picture of a sender at the given cell, in place of the drawn
+    /// one
+    ///
+    /// Nothing is drawn if the sender has no picture or if it is still being
+    /// fetched. Returns `true` if the terminal must be cleared to remove the
+    /// previous picture, in which case the new one is drawn at the next call.
+    pub fn draw(&mut self, sender: Option<(&str, Position)>) -> Result<bool> {
+        let Some(graphics) = self.graphics else {
+            return Ok(false);
+        };
+        let wanted = match sender {
+            Some((address, position)) => {
+                let key = address.to_lowercase();
+                self.load(&key)?;
+                self.loaded
+                    .get(&key)
+                    .is_some_and(|picture| !picture.is_empty())
+                    .then_some((key, position))
+            }
+            None => None,
+        };
+        if wanted == self.drawn {
+            return Ok(false);
+        }
+        let mut stdout = io::stdout().lock();
+        if self.drawn.take().is_some()
+            && !graphics.erase(&mut stdout).map_err(Error::Drawing)?
+        {
+            return Ok(true);
+        }
+        if let Some((key, position)) = &wanted
+            && let Some(picture) = self.loaded.get(key)
+        {
+            graphics
+                .draw(&mut stdout, picture, *position)
+                .map_err(Error::Drawing)?;
+        }
+        self.drawn = wanted;
+        Ok(false)
+    }
+
+    /// Forgets the drawn picture, after the terminal was cleared.
+    pub fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+
+    /// Loads the picture of a sender from the cache, or from Gravatar in the
+    /// background
+    ///
+    /// The pictures that failed to be fetched aren't stored, to try again at
+    /// the next session.
+    fn load(&mut self, key: &str) -> Result {
+        if self.loaded.contains_key(key) {
+            return Ok(());
+        }
+        if let Some(handle) = self.pending.remove(key) {
+            if !handle.is_finished() {
+                self.pending.insert(key.to_owned(), handle);
+                return Ok(());
+            }
+            let picture = match handle.join() {
+                Ok(Ok(picture)) => {
+                    if let Some(cache) = &self.cache {
+                        cache.store_avatar(&to_hash(key), &picture)?;
+                    }
+                    picture
+                }
+                Ok(Err(_)) | Err(_) => vec![],
+            };
+            self.loaded.insert(key.to_owned(), picture);
+            return Ok(());
+        }
+        let hash = to_hash(key);
+        match self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.load_avatar(&hash))
+        {
+            Some(picture) => {
+                self.loaded.insert(key.to_owned(), picture);
+            }
+            None => {
+                self.pending.insert(
+                    key.to_owned(),
+                    thread::spawn(move || fetch(&hash)),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the pictures, fetched only if `enabled` is set and if the
+    /// terminal can draw them
+    pub fn new(cache: Option<Cache>, enabled: bool) -> Self {
+        Self {
+            cache,
+            graphics: enabled.then(Graphics::detect).flatten(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Fetches the PNG picture of an address from its hash
+///
+/// Returns an empty picture if the address has none.
+fn fetch(hash: &str) -> io::Result<Vec<u8>> {
+    let stream = TcpStream::connect((GRAVATAR_HOST, HTTPS_PORT))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut tls = TlsConnector::new()
+        .map_err(io::Error::other)?
+        .connect(GRAVATAR_HOST, stream)
+        .map_err(io::Error::other)?;
+    write!(
+        tls,
+        "GET /avatar/{hash}.png?s={PICTURE_SIZE}&d=404 HTTP/1.0\r\nHost: \
+         {GRAVATAR_HOST}\r\nConnection: close\r\n\r\n"
+    )?;
+    let mut response = vec![];
+    tls.take(MAX_RESPONSE_SIZE).read_to_end(&mut response)?;
+    to_body(&response)
+}
+
+/// Returns the body of an HTTP response, or an empty body for a 404
+fn to_body(response: &[u8]) -> io::Result<Vec<u8>> {
+    let separator = b"\r\n\r\n";
+    let split = response
+        .windows(separator.len())
+        .position(|window| window == separator)
+        .ok_or_else(|| io::Error::other("incomplete response"))?;
+    let head =
+        String::from_utf8_lossy(response.get(..split).unwrap_or_default());
+    match head.split_whitespace().nth(1) {
+        Some("200") => Ok(response
+            .get(split.saturating_add(separator.len())..)
+            .unwrap_or_default()
+            .to_vec()),
+        Some("404") => Ok(vec![]),
+        _ => Err(io::Error::other(
+            head.lines().next().unwrap_or_default().to_owned(),
+        )),
+    }
+}
+
+/// Returns the hash of an address, as expected by Gravatar
+fn to_hash(address: &str) -> String {
+    sha256(address.trim().to_lowercase().as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::pictures::{to_body, to_hash};
+
+    #[test]
+    fn gravatar() {
+        assert_eq!(
+            to_hash(" Jane@Example.com"),
+            "8c87b489ce35cf2e2f39f80e282cb2e804932a56a213983eeeb428407d43b52d"
+        );
+        assert_eq!(
+            to_body(b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\nPNG")
+                .ok(),
+            Some(b"PNG".to_vec())
+        );
+        assert_eq!(
+            to_body(b"HTTP/1.1 404 Not Found\r\n\r\n").ok(),
+            Some(vec![])
+        );
+        assert_eq!(to_body(b"HTTP/1.1 500 Error\r\n\r\n").ok(), None);
+        assert_eq!(to_body(b"HTTP/1.1 200 OK\r\n").ok(), None);
+    }
+}
@@ -0,0 +1,104 @@
+//! Remembers where the user stopped reading long emails.
+//!
+//! The body of the opened email is scrolled with `d` and `u`, and the scroll
+//! offset of every email is kept, so that opening it again resumes where the
+//! user stopped. The offsets are stored in the `positions` file of the cache,
+//! with one email per line:
+//!
+//! ```text
+//! 42 120 INBOX
+//! 7 35 Archives/2024
+//! ```
+//!
+//! The line starts with the uid of the email and the number of lines scrolled,
+//! followed by its folder. Emails scrolled back to the top are forgotten.
+
+use alloc::collections::BTreeMap;
+
+use crate::cache::Cache;
+use crate::errors::Result;
+
+/// Name of the file of the cache that stores the positions
+const POSITIONS_FILE: &str = "positions";
+
+/// Scroll offsets of the bodies of the emails, indexed by folder and uid
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReadPositions {
+    /// Number of lines scrolled in the body of every email that isn't at the
+    /// top
+    offsets: BTreeMap<(String, u32), u16>,
+}
+
+impl ReadPositions {
+    /// Returns the scroll offset of the body of an email, or 0 if it was never
+    /// scrolled
+    pub fn get(&self, folder: &str, uid: u32) -> u16 {
+        self.offsets
+            .get(&(folder.to_owned(), uid))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Loads the positions stored in the cache
+    ///
+    /// Returns empty positions if there is no cache or if none were stored.
+    pub fn load(cache: Option<&Cache>) -> Self {
+        let offsets = cache
+            .and_then(|storage| storage.load_state(POSITIONS_FILE))
+            .map(|content| content.lines().filter_map(parse).collect())
+            .unwrap_or_default();
+        Self { offsets }
+    }
+
+    /// Stores the positions in the cache.
+    pub fn save(&self, cache: &Cache) -> Result {
+        let content = self
+            .offsets
+            .iter()
+            .map(|((folder, uid), offset)| format!("{uid} {offset} {folder}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        cache.store_state(POSITIONS_FILE, &content)
+    }
+
+    /// Updates the scroll offset of the body of an email.
+    pub fn set(&mut self, folder: &str, uid: u32, offset: u16) {
+        let key = (folder.to_owned(), uid);
+        if offset == 0 {
+            self.offsets.remove(&key);
+        } else {
+            self.offsets.insert(key, offset);
+        }
+    }
+}
+
+/// Parses a line of the positions file
+fn parse(line: &str) -> Option<((String, u32), u16)> {
+    let mut words = line.splitn(3, ' ');
+    let uid = words.next()?.parse().ok()?;
+    let offset = words.next()?.parse().ok()?;
+    let folder = words.next()?.to_owned();
+    Some(((folder, uid), offset))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::positions::{ReadPositions, parse};
+
+    #[test]
+    fn positions() {
+        assert_eq!(
+            parse("42 120 Archives 2024"),
+            Some((("Archives 2024".to_owned(), 42), 120))
+        );
+        assert_eq!(parse("42 -1 INBOX"), None);
+        assert_eq!(parse("42 120"), None);
+
+        let mut positions = ReadPositions::default();
+        positions.set("INBOX", 42, 120);
+        assert_eq!(positions.get("INBOX", 42), 120);
+        assert_eq!(positions.get("Sent", 42), 0);
+        positions.set("INBOX", 42, 0);
+        assert_eq!(positions, ReadPositions::default());
+    }
+}
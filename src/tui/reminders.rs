@@ -0,0 +1,210 @@
+//! Reminds the user of the sent emails that weren't answered.
+//!
+//! A reminder is requested in the writer with `r`, which chooses after how
+//! many days. Once the email is sent, its `Message-ID` is tracked until an
+//! email of the inbox replies to it, with the id in its `References` or
+//! `In-Reply-To` header. Past the due date, the unanswered emails are listed
+//! above the inbox, until they are answered or dismissed with `:dismiss`.
+//!
+//! The reminders are stored in the `reminders` file of the cache, with one
+//! email per line:
+//!
+//! ```text
+//! 1736409600 1736150400 1736150400.4242@example.com Report
+//! ```
+//!
+//! The line starts with the due date and the sending date, as UNIX
+//! timestamps, followed by the id and the subject of the sent email. Only the
+//! replies loaded in the inbox are noticed, so the replies filed elsewhere
+//! must be dismissed by hand.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use mail_parser::{DateTime, HeaderName};
+
+use crate::cache::Cache;
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+
+/// Number of seconds in a day
+pub const SECS_PER_DAY: i64 = 86_400;
+/// Name of the file of the cache that stores the reminders
+const REMINDERS_FILE: &str = "reminders";
+
+/// Sent email waiting for a reply
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Reminder {
+    /// Date the reminder is displayed from, as a UNIX timestamp
+    due: i64,
+    /// `Message-ID` of the sent email, without the angle brackets
+    message_id: String,
+    /// Date the email was sent, as a UNIX timestamp
+    sent: i64,
+    /// Subject of the sent email
+    subject: String,
+}
+
+/// Sent emails waiting for a reply, oldest first
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Reminders {
+    /// Emails that weren't answered yet
+    pending: Vec<Reminder>,
+}
+
+impl Reminders {
+    /// Tracks a sent message, to be reminded of it if it isn't answered
+    /// within the given number of days
+    ///
+    /// Messages without a `Message-ID` can't be tracked, and are ignored.
+    pub fn add(&mut self, message: &str, days: u16, now: i64) {
+        let Ok(email) = Email::try_from((0, message.as_bytes())) else {
+            return;
+        };
+        let Some(message_id) =
+            email.to_message_ids(&HeaderName::MessageId).pop()
+        else {
+            return;
+        };
+        self.pending.push(Reminder {
+            due: now
+                .saturating_add(i64::from(days).saturating_mul(SECS_PER_DAY)),
+            message_id,
+            sent: now,
+            subject: email
+                .get_header_str(&HeaderName::Subject)
+                .unwrap_or_default(),
+        });
+    }
+
+    /// Forgets the due reminders, and returns how many there were
+    pub fn dismiss_due(&mut self, now: i64) -> usize {
+        let before = self.pending.len();
+        self.pending.retain(|reminder| reminder.due > now);
+        before.saturating_sub(self.pending.len())
+    }
+
+    /// Loads the reminders stored in the cache
+    ///
+    /// Returns no reminders if there is no cache or if none were stored.
+    pub fn load(cache: Option<&Cache>) -> Self {
+        let pending = cache
+            .and_then(|storage| storage.load_state(REMINDERS_FILE))
+            .map(|content| content.lines().filter_map(parse).collect())
+            .unwrap_or_default();
+        Self { pending }
+    }
+
+    /// Forgets the due reminders of the emails answered by one of the given
+    /// emails
+    ///
+    /// Only the due reminders are checked, so nothing is done most of the
+    /// time.
+    pub fn resolve(&mut self, emails: &[Email], now: i64) {
+        if self.pending.iter().all(|reminder| reminder.due > now) {
+            return;
+        }
+        let replied = emails
+            .iter()
+            .flat_map(|email| {
+                let mut ids = email.to_message_ids(&HeaderName::References);
+                ids.extend(email.to_message_ids(&HeaderName::InReplyTo));
+                ids
+            })
+            .collect::<Vec<_>>();
+        self.pending.retain(|reminder| {
+            reminder.due > now || !replied.contains(&reminder.message_id)
+        });
+    }
+
+    /// Stores the reminders in the cache.
+    pub fn save(&self, cache: &Cache) -> Result {
+        let content = self
+            .pending
+            .iter()
+            .map(|reminder| {
+                format!(
+                    "{} {} {} {}",
+                    reminder.due,
+                    reminder.sent,
+                    reminder.message_id,
+                    reminder.subject
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        cache.store_state(REMINDERS_FILE, &content)
+    }
+
+    /// Returns the lines describing the due reminders, e.g. `No reply to
+    /// "Report", sent on 2025-01-06`
+    pub fn to_due_lines(&self, now: i64) -> Vec<String> {
+        self.pending
+            .iter()
+            .filter(|reminder| reminder.due <= now)
+            .map(|reminder| {
+                let date = DateTime::from_timestamp(reminder.sent);
+                format!(
+                    "No reply to \"{}\", sent on {:04}-{:02}-{:02}",
+                    reminder.subject, date.year, date.month, date.day
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the current date, as a UNIX timestamp
+pub fn to_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|elapsed| i64::try_from(elapsed.as_secs()).ok())
+        .unwrap_or_default()
+}
+
+/// Parses a line of the reminders file
+fn parse(line: &str) -> Option<Reminder> {
+    let mut words = line.splitn(4, ' ');
+    let due = words.next()?.parse().ok()?;
+    let sent = words.next()?.parse().ok()?;
+    let message_id = words.next()?.to_owned();
+    let subject = words.next().unwrap_or_default().to_owned();
+    Some(Reminder { due, message_id, sent, subject })
+}
+
+#[cfg(test)]
+mod test {
+    use core::slice;
+
+    use crate::test_utils::email;
+    use crate::tui::reminders::{Reminders, parse};
+
+    #[test]
+    fn reminders() {
+        let mut reminders = Reminders::default();
+        reminders.add(
+            "From: me@example.com\r\nSubject: Report\r\nMessage-ID: \
+             <1.2@example.com>\r\n\r\nBody\r\n",
+            3,
+            1_736_150_400,
+        );
+        let stored = "1736409600 1736150400 1.2@example.com Report";
+        assert_eq!(parse(stored), reminders.pending.first().cloned());
+        assert_eq!(parse("1736409600 1736150400"), None);
+
+        assert!(reminders.to_due_lines(1_736_409_599).is_empty());
+        assert_eq!(
+            reminders.to_due_lines(1_736_409_600),
+            ["No reply to \"Report\", sent on 2025-01-06"]
+        );
+
+        let unrelated = email("Message-ID: <3@x>\nIn-Reply-To: <9@x>");
+        reminders.resolve(&[unrelated], 1_736_409_600);
+        assert_eq!(reminders.pending.len(), 1);
+        let reply =
+            email("Message-ID: <3@x>\nReferences: <0@x> <1.2@example.com>");
+        reminders.resolve(slice::from_ref(&reply), 1_736_409_599);
+        assert_eq!(reminders.pending.len(), 1);
+        reminders.resolve(&[reply], 1_736_409_600);
+        assert_eq!(reminders, Reminders::default());
+    }
+}
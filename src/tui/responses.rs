@@ -0,0 +1,81 @@
+//! Canned responses, sent as replies with a single key.
+//!
+//! The templates are given by the `RESPONSE_TEMPLATES` variable of the `.env`
+//! file, separated by `|`, with `\n` for the line breaks:
+//!
+//! ```env
+//! RESPONSE_TEMPLATES=Thanks, received.|I will get back to you soon.\n\nBob
+//! ```
+//!
+//! While an email is opened, pressing `c` then the digit `n` replies to it
+//! with the n-th template, without opening the writer. The reply goes through
+//! the undo send delay like any other email, so it can still be cancelled with
+//! `U`. The templates are behind `c` so that the digits keep prefixing the
+//! motions with a count, e.g. `12j`.
+//!
+//! Without the variable, `c1` acknowledges the email and `c2` promises an
+//! answer.
+
+/// Templates used if none were configured
+const DEFAULT_TEMPLATES: [&str; 2] =
+    ["Thanks, received.", "I will get back to you soon."];
+/// Separator of the templates in the `.env` file
+const SEPARATOR: char = '|';
+
+/// Bodies of the canned responses, bound to the digits from `1`
+#[derive(Debug, PartialEq, Eq)]
+pub struct ResponseTemplates {
+    /// Body of every template, in the order of their keys
+    bodies: Vec<String>,
+}
+
+impl ResponseTemplates {
+    /// Returns the template bound to a key, if there is one
+    ///
+    /// The empty templates leave their key unbound.
+    pub fn get(&self, key: char) -> Option<&str> {
+        let index = usize::try_from(key.to_digit(10)?.checked_sub(1)?).ok()?;
+        self.bodies
+            .get(index)
+            .map(String::as_str)
+            .filter(|body| !body.is_empty())
+    }
+
+    /// Parses the templates of the `.env` file
+    ///
+    /// Only the first nine are kept, as the other ones have no key.
+    pub fn parse(value: &str) -> Self {
+        Self {
+            bodies: value
+                .split(SEPARATOR)
+                .take(9)
+                .map(|body| body.trim().replace("\\n", "\n"))
+                .collect(),
+        }
+    }
+}
+
+impl Default for ResponseTemplates {
+    fn default() -> Self {
+        Self { bodies: DEFAULT_TEMPLATES.map(ToOwned::to_owned).to_vec() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::responses::ResponseTemplates;
+
+    #[test]
+    fn templates() {
+        let defaults = ResponseTemplates::default();
+        assert_eq!(defaults.get('1'), Some("Thanks, received."));
+        assert_eq!(defaults.get('3'), None);
+        assert_eq!(defaults.get('0'), None);
+
+        let parsed = ResponseTemplates::parse("Ok.| |Done.\\n\\nBob|");
+        assert_eq!(parsed.get('1'), Some("Ok."));
+        assert_eq!(parsed.get('2'), None);
+        assert_eq!(parsed.get('3'), Some("Done.\n\nBob"));
+        assert_eq!(parsed.get('4'), None);
+    }
+}
@@ -0,0 +1,176 @@
+//! Previews the written email before sending it.
+//!
+//! The preview is opened with `S` in the writer, and displays exactly what
+//! will be sent: the headers, the text part as wrapped for sending, the
+//! attached files and the size of the message. The message is generated once,
+//! so the sent message is the previewed one. `y` sends it, `j` and `k` scroll,
+//! and `Esc` (or `n`) goes back to the writer.
+//!
+//! Once confirmed, the email is only sent after the [undo send
+//! delay](crate::config::Config::as_undo_send_delay), during which `U` cancels
+//! the sending and reopens the writer.
+
+use core::time::Duration;
+use std::time::Instant;
+
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Clear, Paragraph, Wrap};
+
+use super::components::{centered_popup, new_simple_box};
+use super::writer::Writer;
+use crate::cache::retention::to_human_size;
+use crate::send::message::OutgoingEmail;
+
+/// Percentage of the screen taken by the preview, in both directions, larger
+/// than the other popups to fit the whole email
+const PREVIEW_SIZE: u16 = 90;
+
+/// Confirmed email, waiting for the undo send delay to be over
+pub struct PendingSend {
+    /// Instant the email must be sent at
+    deadline: Instant,
+    /// Previewed email, with the message to send
+    preview: SendPreview,
+    /// Writer the email was written in, to reopen it if the sending is
+    /// cancelled
+    writer: Box<Writer>,
+}
+
+impl PendingSend {
+    /// Returns the previewed email and its writer
+    pub fn into_parts(self) -> (SendPreview, Box<Writer>) {
+        (self.preview, self.writer)
+    }
+
+    /// Checks if the delay is over, and the email must be sent
+    pub fn is_due(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// Schedules the sending of a previewed email after a delay
+    pub fn new(
+        preview: SendPreview,
+        writer: Box<Writer>,
+        delay: Duration,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            deadline: now.checked_add(delay).unwrap_or(now),
+            preview,
+            writer,
+        }
+    }
+
+    /// Returns the status message displayed while the email is waiting
+    pub fn to_status(&self) -> String {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        format!(
+            "Sending in {}s: press 'U' to undo",
+            remaining.as_secs().saturating_add(1)
+        )
+    }
+}
+
+/// Email waiting for the confirmation to be sent
+pub struct SendPreview {
+    /// Email to send
+    email: OutgoingEmail,
+    /// Message as it will be sent
+    message: String,
+    /// Number of lines scrolled down
+    scroll: u16,
+}
+
+impl SendPreview {
+    /// Returns the email to send
+    pub const fn as_email(&self) -> &OutgoingEmail {
+        &self.email
+    }
+
+    /// Returns the message as it will be sent
+    pub fn as_message(&self) -> &str {
+        &self.message
+    }
+
+    /// Draws the popup in the middle of the given area
+    pub fn draw(&self, frame: &mut Frame<'_>, area: Rect) {
+        let popup = centered_popup(area, PREVIEW_SIZE);
+        let paragraph = Paragraph::new(self.to_text())
+            .block(new_simple_box(
+                "Send this email? 'y' to send, 'Esc' to edit",
+            ))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// Generates the message to send, to preview it
+    pub fn new(email: OutgoingEmail) -> Self {
+        let message = email.to_rfc5322();
+        Self { email, message, scroll: 0 }
+    }
+
+    /// Scrolls down by one line.
+    pub const fn scroll_down(&mut self) {
+        self.scroll = self.scroll.saturating_add(1);
+    }
+
+    /// Scrolls up by one line.
+    pub const fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    /// Returns the content of the preview
+    fn to_text(&self) -> Text<'_> {
+        let headers = self
+            .message
+            .split_once("\r\n\r\n")
+            .map_or(self.message.as_str(), |(header_lines, _)| header_lines);
+        let mut lines = headers.lines().map(Line::from).collect::<Vec<_>>();
+        if !self.email.as_bcc().is_empty() {
+            lines.push(Line::from(format!(
+                "Bcc (not written in the headers): {}",
+                self.email.as_bcc().join(", ")
+            )));
+        }
+        lines.extend([
+            Line::from(""),
+            Line::from("Text part:"),
+            Line::from(""),
+        ]);
+        lines.extend(
+            self.email
+                .to_sent_body()
+                .lines()
+                .map(|line| Line::from(line.to_owned())),
+        );
+        lines.extend([
+            Line::from(""),
+            Line::from("HTML part: none, the email is sent as plain text"),
+        ]);
+        let attachments = self.email.as_attachments();
+        if attachments.is_empty() {
+            lines.push(Line::from("Attachments: none"));
+        } else {
+            lines.push(Line::from("Attachments:"));
+            lines.extend(attachments.iter().map(|(name, data)| {
+                Line::from(format!(
+                    "- {name} ({})",
+                    to_human_size(
+                        u64::try_from(data.len()).unwrap_or(u64::MAX)
+                    )
+                ))
+            }));
+        }
+        lines.push(Line::from(format!(
+            "Size: {}",
+            to_human_size(
+                u64::try_from(self.message.len()).unwrap_or(u64::MAX)
+            )
+        )));
+        Text::from(lines)
+    }
+}
@@ -0,0 +1,197 @@
+//! Blocks or allows senders, by address or by domain.
+//!
+//! The lists are edited from the command line, and default to the sender of
+//! the selected email:
+//!
+//! ```text
+//! :block                  blocks the sender of the selected email
+//! :block spam.example.com blocks every sender of a domain
+//! :allow boss@spam.example.com
+//! :unblock spam.example.com
+//! :senders                lists the blocked and allowed senders
+//! ```
+//!
+//! An allowed entry takes precedence over a blocked one, so that a domain can
+//! be blocked except for some addresses. Depending on the `BLOCKED_SENDERS`
+//! variable of the [`Config`](crate::config::Config), the emails of the
+//! blocked senders are hidden from the list (`hide`, by default) or moved to
+//! the junk folder (`junk`). Opening an email of a blocked sender, e.g. from
+//! the junk folder, displays a banner.
+//!
+//! The lists are stored in the cache, one entry per line.
+
+use alloc::collections::BTreeSet;
+
+use crate::cache::Cache;
+use crate::errors::Result;
+
+/// Name of the state file of the lists in the cache
+const SENDERS_FILE: &str = "senders";
+
+/// What happens to the emails of the blocked senders
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlockedAction {
+    /// The emails are hidden from the list
+    #[default]
+    Hide,
+    /// The emails are moved to the junk folder
+    Junk,
+}
+
+impl BlockedAction {
+    /// Parses an action from its name, `hide` or `junk`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "hide" => Some(Self::Hide),
+            "junk" => Some(Self::Junk),
+            _ => None,
+        }
+    }
+}
+
+/// Change of the lists of senders
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Allows the sender
+    Allow,
+    /// Blocks the sender
+    Block,
+    /// Removes the sender from both lists
+    Unblock,
+}
+
+/// Blocked and allowed senders
+#[derive(Debug, Default)]
+pub struct SenderLists {
+    /// Allowed addresses and domains, in lowercase
+    allowed: BTreeSet<String>,
+    /// Blocked addresses and domains, in lowercase
+    blocked: BTreeSet<String>,
+}
+
+impl SenderLists {
+    /// Allows an address or a domain, removing it from the blocked senders.
+    pub fn allow(&mut self, entry: &str) {
+        let key = to_key(entry);
+        self.blocked.remove(&key);
+        self.allowed.insert(key);
+    }
+
+    /// Applies a change to the lists, and returns the status message
+    pub fn apply(&mut self, edit: Edit, entry: &str) -> String {
+        match edit {
+            Edit::Allow => {
+                self.allow(entry);
+                format!("Allowed {entry}")
+            }
+            Edit::Block => {
+                self.block(entry);
+                format!("Blocked {entry}")
+            }
+            Edit::Unblock if self.remove(entry) =>
+                format!("Removed {entry} from the blocked and allowed senders"),
+            Edit::Unblock => format!("{entry} isn't blocked nor allowed"),
+        }
+    }
+
+    /// Blocks an address or a domain, removing it from the allowed senders.
+    pub fn block(&mut self, entry: &str) {
+        let key = to_key(entry);
+        self.allowed.remove(&key);
+        self.blocked.insert(key);
+    }
+
+    /// Checks if an address is blocked, by itself or by its domain, and isn't
+    /// allowed
+    pub fn is_blocked(&self, address: &str) -> bool {
+        let matches = |list: &BTreeSet<String>| {
+            let lowercase = address.to_lowercase();
+            list.contains(&lowercase)
+                || lowercase
+                    .rsplit_once('@')
+                    .is_some_and(|(_, domain)| list.contains(domain))
+        };
+        matches(&self.blocked) && !matches(&self.allowed)
+    }
+
+    /// Loads the lists stored in the cache, or empty lists if none were
+    /// stored
+    pub fn load(cache: &Cache) -> Self {
+        let mut lists = Self::default();
+        for line in cache.load_state(SENDERS_FILE).unwrap_or_default().lines() {
+            match line.split_once(' ') {
+                Some(("allow", entry)) => lists.allow(entry),
+                Some(("block", entry)) => lists.block(entry),
+                _ => (),
+            }
+        }
+        lists
+    }
+
+    /// Stores the lists in the cache.
+    pub fn store(&self, cache: &Cache) -> Result {
+        cache.store_state(SENDERS_FILE, &self.to_state())
+    }
+
+    /// Returns the description of the lists, for the status bar
+    pub fn to_description(&self) -> String {
+        let join = |list: &BTreeSet<String>| {
+            if list.is_empty() {
+                "none".to_owned()
+            } else {
+                list.iter().cloned().collect::<Vec<_>>().join(", ")
+            }
+        };
+        format!(
+            "Blocked: {}; allowed: {}",
+            join(&self.blocked),
+            join(&self.allowed)
+        )
+    }
+
+    /// Returns the content of the state file of the lists
+    fn to_state(&self) -> String {
+        self.blocked
+            .iter()
+            .map(|entry| format!("block {entry}"))
+            .chain(self.allowed.iter().map(|entry| format!("allow {entry}")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Removes an address or a domain from both lists
+    ///
+    /// Returns `false` if it wasn't in any list.
+    pub fn remove(&mut self, entry: &str) -> bool {
+        let key = to_key(entry);
+        let blocked = self.blocked.remove(&key);
+        self.allowed.remove(&key) || blocked
+    }
+}
+
+/// Normalises an entry of the lists, so that `@Example.com` and `example.com`
+/// are the same domain
+fn to_key(entry: &str) -> String {
+    let trimmed = entry.trim();
+    trimmed.strip_prefix('@').unwrap_or(trimmed).to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::senders::SenderLists;
+
+    #[test]
+    fn senders() {
+        let mut lists = SenderLists::default();
+        lists.block("@Spam.example.com");
+        lists.block("bob@example.com");
+        lists.allow("boss@spam.example.com");
+        assert!(lists.is_blocked("anyone@spam.example.com"));
+        assert!(lists.is_blocked("Bob@Example.com"));
+        assert!(!lists.is_blocked("boss@spam.example.com"));
+        assert!(!lists.is_blocked("alice@example.com"));
+        assert!(lists.remove("spam.example.com"));
+        assert!(!lists.is_blocked("anyone@spam.example.com"));
+        assert!(!lists.remove("spam.example.com"));
+    }
+}
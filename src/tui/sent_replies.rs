@@ -0,0 +1,124 @@
+//! Interleaves the sent replies into the conversations of the other folders.
+//!
+//! In the `thread` order, the emails of the sent folder that belong to a
+//! conversation of the listed emails are listed with them, so that a
+//! conversation reads from the oldest email to the newest across folders. They
+//! are found with the conversation index of the sent folder in the cache, see
+//! [`ThreadIndex`], and their content is read from the cache, so only the sent
+//! emails synced once, when the sent folder was opened, are merged.
+//!
+//! A merged email keeps its uid of the sent folder with the highest bit set,
+//! so that it never takes the uid of an email of the listed folder. It is
+//! read-only: the operations, e.g. deleting it, must be done from the sent
+//! folder. The sent emails already in the listed folder, e.g. when they were
+//! sent to the user too, aren't merged again.
+
+use std::collections::HashSet;
+
+use mail_parser::HeaderName;
+
+use crate::cache::Cache;
+use crate::cache::threads::ThreadIndex;
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+
+/// Bit set in the uids of the merged emails
+const MERGED_BIT: u32 = 1 << 31;
+
+/// Checks if an email of the list was merged from the sent folder
+pub const fn is_merged(uid: u32) -> bool {
+    uid & MERGED_BIT != 0
+}
+
+/// Loads the emails of the sent folder that belong to a conversation of the
+/// listed emails, from the cache
+///
+/// The conversations are the ones of `threads`, the index of the listed
+/// folder. The merged emails must be indexed in it too, to be grouped with
+/// their conversation.
+pub fn load(
+    cache: &Cache,
+    sent_folder: &str,
+    emails: &[Email],
+    threads: &ThreadIndex,
+) -> Result<Vec<Email>> {
+    let conversations = emails
+        .iter()
+        .filter_map(|email| threads.as_thread(email.as_uid()))
+        .collect::<HashSet<_>>();
+    if conversations.is_empty() {
+        return Ok(vec![]);
+    }
+    let listed_ids = emails
+        .iter()
+        .flat_map(|email| email.to_message_ids(&HeaderName::MessageId))
+        .collect::<HashSet<_>>();
+    let mut merged = vec![];
+    for uid in cache.load_thread_index(sent_folder).find_in(&conversations) {
+        if is_merged(uid) {
+            continue;
+        }
+        let Some(raw) = cache.load_email(sent_folder, uid)? else {
+            continue;
+        };
+        let Ok(email) = Email::try_from((uid | MERGED_BIT, raw.as_bytes()))
+        else {
+            continue;
+        };
+        if !email
+            .to_message_ids(&HeaderName::MessageId)
+            .iter()
+            .any(|id| listed_ids.contains(id))
+        {
+            merged.push(email);
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::threads::ThreadIndex;
+    use crate::fetch::parser::Email;
+    use crate::test_utils::TempDir;
+    use crate::tui::sent_replies::{is_merged, load};
+
+    #[test]
+    #[expect(clippy::unwrap_used, reason = "test")]
+    fn sent_replies() {
+        let dir = TempDir::new("sent");
+        let cache = dir.open_cache();
+        let sent = [
+            (3, "Message-ID: <r@x>\nIn-Reply-To: <a@x>\n\nReply"),
+            (4, "Message-ID: <s@x>\nIn-Reply-To: <z@x>\n\nOther"),
+            (5, "Message-ID: <b@x>\nIn-Reply-To: <a@x>\n\nCopied"),
+        ];
+        let mut sent_index = ThreadIndex::default();
+        for (uid, raw) in sent {
+            cache.store_email("Sent", uid, raw).unwrap();
+            sent_index.add(&Email::try_from((uid, raw.as_bytes())).unwrap());
+        }
+        cache.store_thread_index("Sent", &sent_index).unwrap();
+
+        let emails = [
+            Email::try_from((1, b"Message-ID: <a@x>\n\nHi".as_slice()))
+                .unwrap(),
+            Email::try_from((
+                2,
+                b"Message-ID: <b@x>\nIn-Reply-To: <a@x>\n\nCopy".as_slice(),
+            ))
+            .unwrap(),
+        ];
+        let mut threads = ThreadIndex::default();
+        for email in &emails {
+            threads.add(email);
+        }
+        let merged = load(&cache, "Sent", &emails, &threads).unwrap();
+        assert_eq!(merged.len(), 1);
+        let reply = merged.first().unwrap();
+        assert!(is_merged(reply.as_uid()));
+        assert_eq!(reply.as_uid() & 0xff, 3);
+        assert!(!is_merged(3));
+        assert!(load(&cache, "Sent", &[], &threads).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+//! Saves the state of the TUI on exit, to reopen it where the user left off.
+//!
+//! The state is stored in the `session` file of the cache of the account, with
+//! one value per line:
+//!
+//! ```text
+//! folder Archives/2024
+//! mode reading
+//! opened 42
+//! ```
+//!
+//! The selected email of every folder is already remembered by the
+//! [`views`](super::views). Run `mailbox --fresh` to start from the inbox
+//! instead.
+
+use super::states::TuiMode;
+use crate::cache::Cache;
+use crate::errors::Result;
+
+/// Name of the file of the cache that stores the session
+const SESSION_FILE: &str = "session";
+
+/// Mode of the TUI, without the content of the writer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SavedMode {
+    /// The help page was displayed
+    #[default]
+    Help,
+    /// The emails were displayed
+    Reading,
+    /// The writer was opened
+    Writing,
+}
+
+impl SavedMode {
+    /// Returns the name of the mode, as stored in the session file
+    const fn as_name(self) -> &'static str {
+        match self {
+            Self::Help => "help",
+            Self::Reading => "reading",
+            Self::Writing => "writing",
+        }
+    }
+
+    /// Parses a mode from its name
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "help" => Some(Self::Help),
+            "reading" => Some(Self::Reading),
+            "writing" => Some(Self::Writing),
+            _ => None,
+        }
+    }
+
+    /// Returns the mode to restore
+    ///
+    /// The writer is reopened empty, as its content isn't saved.
+    pub fn to_mode(self) -> TuiMode {
+        match self {
+            Self::Help => TuiMode::Help,
+            Self::Reading => TuiMode::Reading,
+            Self::Writing => TuiMode::Writing(Box::default()),
+        }
+    }
+}
+
+impl From<&TuiMode> for SavedMode {
+    fn from(mode: &TuiMode) -> Self {
+        match mode {
+            TuiMode::Help => Self::Help,
+            TuiMode::Reading => Self::Reading,
+            TuiMode::Writing(_) => Self::Writing,
+        }
+    }
+}
+
+/// State of the TUI when it was closed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Name of the opened folder
+    folder: Option<String>,
+    /// Mode of the TUI
+    mode: SavedMode,
+    /// Uid of the opened email, if any
+    opened_uid: Option<u32>,
+}
+
+impl Snapshot {
+    /// Returns the name of the opened folder, if any was saved
+    pub fn as_folder(&self) -> Option<&str> {
+        self.folder.as_deref()
+    }
+
+    /// Returns the mode of the TUI
+    pub const fn as_mode(&self) -> SavedMode {
+        self.mode
+    }
+
+    /// Returns the uid of the opened email, if any
+    pub const fn as_opened_uid(&self) -> Option<u32> {
+        self.opened_uid
+    }
+
+    /// Loads the session stored in the cache
+    ///
+    /// Returns an empty session if there is no cache or if none was stored.
+    pub fn load(cache: Option<&Cache>) -> Self {
+        cache
+            .and_then(|storage| storage.load_state(SESSION_FILE))
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default()
+    }
+
+    /// Creates the snapshot of a session
+    pub const fn new(
+        folder: String,
+        mode: SavedMode,
+        opened_uid: Option<u32>,
+    ) -> Self {
+        Self { folder: Some(folder), mode, opened_uid }
+    }
+
+    /// Parses the content of the session file
+    ///
+    /// Unknown or invalid lines are ignored.
+    fn parse(content: &str) -> Self {
+        let mut snapshot = Self::default();
+        for (key, value) in
+            content.lines().filter_map(|line| line.split_once(' '))
+        {
+            match key {
+                "folder" => snapshot.folder = Some(value.to_owned()),
+                "mode" =>
+                    snapshot.mode = SavedMode::parse(value).unwrap_or_default(),
+                "opened" => snapshot.opened_uid = value.parse().ok(),
+                _ => (),
+            }
+        }
+        snapshot
+    }
+
+    /// Stores the session in the cache.
+    pub fn save(&self, cache: &Cache) -> Result {
+        let mut lines = vec![];
+        if let Some(folder) = &self.folder {
+            lines.push(format!("folder {folder}"));
+        }
+        lines.push(format!("mode {}", self.mode.as_name()));
+        if let Some(uid) = self.opened_uid {
+            lines.push(format!("opened {uid}"));
+        }
+        cache.store_state(SESSION_FILE, &lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::session::{SavedMode, Snapshot};
+
+    #[test]
+    fn parse_session() {
+        assert_eq!(
+            Snapshot::parse(
+                "folder Archives 2024\nmode reading\nopened 42\nunknown line"
+            ),
+            Snapshot {
+                folder: Some("Archives 2024".to_owned()),
+                mode: SavedMode::Reading,
+                opened_uid: Some(42),
+            }
+        );
+        assert_eq!(Snapshot::parse("mode flying"), Snapshot::default());
+    }
+}
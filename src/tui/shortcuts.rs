@@ -0,0 +1,136 @@
+//! Jumps straight to the favourite and the recently opened folders.
+//!
+//! The favourite folders are given by the `FOLDER_SHORTCUTS` variable of the
+//! `.env` file, separated by commas:
+//!
+//! ```env
+//! FOLDER_SHORTCUTS=INBOX, Clients/2024, local:Archives
+//! ```
+//!
+//! Pressing `g` followed by the digit `n` opens the n-th folder of the list,
+//! and `g0` goes back to the previously opened folder.
+//!
+//! The opened folders are remembered from the most recent, and stored in the
+//! `recent_folders` file of the cache, one per line, to be listed with
+//! `:recent` in the next sessions too.
+
+use crate::cache::Cache;
+use crate::errors::Result;
+
+/// Maximum number of remembered recent folders
+const MAX_RECENT: usize = 10;
+/// Name of the file of the cache that stores the recent folders
+const RECENT_FILE: &str = "recent_folders";
+
+/// Favourite folders, bound to the digits from `1` after a `g`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FavouriteFolders {
+    /// Name of every favourite folder, in the order of their keys
+    folders: Vec<String>,
+}
+
+impl FavouriteFolders {
+    /// Returns the folder bound to a key, if there is one
+    pub fn get(&self, key: char) -> Option<&str> {
+        let index = usize::try_from(key.to_digit(10)?.checked_sub(1)?).ok()?;
+        self.folders
+            .get(index)
+            .map(String::as_str)
+            .filter(|folder| !folder.is_empty())
+    }
+
+    /// Parses the favourite folders of the `.env` file
+    ///
+    /// Only the first nine are kept, as the other ones have no key.
+    pub fn parse(value: &str) -> Self {
+        Self {
+            folders: value
+                .split(',')
+                .take(9)
+                .map(|folder| folder.trim().to_owned())
+                .collect(),
+        }
+    }
+}
+
+/// Folders opened by the user, from the most recent
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RecentFolders {
+    /// Names of the folders, without duplicates
+    folders: Vec<String>,
+}
+
+impl RecentFolders {
+    /// Returns the recent folders, from the most recent
+    pub fn as_folders(&self) -> &[String] {
+        &self.folders
+    }
+
+    /// Returns the most recent folder other than the displayed one
+    pub fn as_previous(&self, current: &str) -> Option<&str> {
+        self.folders
+            .iter()
+            .find(|folder| *folder != current)
+            .map(String::as_str)
+    }
+
+    /// Loads the recent folders stored in the cache
+    ///
+    /// Returns no folders if there is no cache or if none were stored.
+    pub fn load(cache: Option<&Cache>) -> Self {
+        let folders = cache
+            .and_then(|storage| storage.load_state(RECENT_FILE))
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(ToOwned::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { folders }
+    }
+
+    /// Stores the recent folders in the cache.
+    pub fn save(&self, cache: &Cache) -> Result {
+        cache.store_state(RECENT_FILE, &self.folders.join("\n"))
+    }
+
+    /// Records that a folder was opened, making it the most recent one
+    pub fn visit(&mut self, folder: &str) {
+        self.folders.retain(|other| other != folder);
+        self.folders.insert(0, folder.to_owned());
+        self.folders.truncate(MAX_RECENT);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::shortcuts::{FavouriteFolders, RecentFolders};
+
+    #[test]
+    fn shortcuts() {
+        let shortcuts = FavouriteFolders::parse("INBOX, ,Clients/2024");
+        assert_eq!(shortcuts.get('1'), Some("INBOX"));
+        assert_eq!(shortcuts.get('2'), None);
+        assert_eq!(shortcuts.get('3'), Some("Clients/2024"));
+        assert_eq!(shortcuts.get('0'), None);
+        assert_eq!(FavouriteFolders::default().get('1'), None);
+
+        let mut recent = RecentFolders::default();
+        assert_eq!(recent.as_previous("INBOX"), None);
+        for folder in ["INBOX", "Archive", "INBOX"] {
+            recent.visit(folder);
+        }
+        assert_eq!(recent.as_folders(), ["INBOX", "Archive"]);
+        assert_eq!(recent.as_previous("INBOX"), Some("Archive"));
+        for letter in 'a'..='t' {
+            recent.visit(&format!("Folder {letter}"));
+        }
+        assert_eq!(recent.as_folders().len(), 10);
+        assert_eq!(
+            recent.as_folders().first().map(String::as_str),
+            Some("Folder t")
+        );
+    }
+}
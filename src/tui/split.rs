@@ -0,0 +1,140 @@
+//! Splits the reading mode between the email list and the opened email.
+//!
+//! The panes are side by side or stacked, depending on the
+//! [`Orientation`], and the list takes a configurable percentage of the
+//! screen. The ratio can be changed at runtime with `<` and `>`, and the
+//! opened email can be zoomed to full screen with `z`.
+
+use ratatui::layout::{Constraint, Direction};
+
+/// Minimum width, in columns, for the panes to be side by side in
+/// [`Orientation::Auto`]
+const AUTO_MIN_WIDTH: u16 = 120;
+/// Percentage of the whole screen
+const FULL_SCREEN: u16 = 100;
+/// Percentage of the screen taken by the list if none was configured
+const DEFAULT_RATIO: u16 = 50;
+/// Maximum percentage of the screen taken by the list
+const MAX_RATIO: u16 = 90;
+/// Minimum percentage of the screen taken by the list
+const MIN_RATIO: u16 = 10;
+/// Percentage by which the list grows or shrinks at every key press
+const RATIO_STEP: u16 = 10;
+
+/// Position of the opened email relatively to the list
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// Side by side on wide screens, stacked otherwise
+    #[default]
+    Auto,
+    /// Side by side, the list on the left
+    Horizontal,
+    /// Stacked, the list on top
+    Vertical,
+}
+
+impl Orientation {
+    /// Parses the name of an orientation, as written in the configuration.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "horizontal" => Some(Self::Horizontal),
+            "vertical" => Some(Self::Vertical),
+            _ => None,
+        }
+    }
+}
+
+/// Layout of the list and the opened email
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Split {
+    /// Position of the opened email relatively to the list
+    orientation: Orientation,
+    /// Percentage of the screen taken by the list
+    ratio: u16,
+    /// Indicates whether the opened email is displayed in full screen
+    zoomed: bool,
+}
+
+impl Default for Split {
+    fn default() -> Self {
+        Self::new(Orientation::default(), None)
+    }
+}
+
+impl Split {
+    /// Returns the sizes of the list and of the opened email
+    pub const fn as_constraints(self) -> [Constraint; 2] {
+        [
+            Constraint::Percentage(self.ratio),
+            Constraint::Percentage(FULL_SCREEN.saturating_sub(self.ratio)),
+        ]
+    }
+
+    /// Returns the direction in which the panes are laid out, for a screen of
+    /// the given width
+    pub const fn as_direction(self, width: u16) -> Direction {
+        match self.orientation {
+            Orientation::Auto if width >= AUTO_MIN_WIDTH =>
+                Direction::Horizontal,
+            Orientation::Auto | Orientation::Vertical => Direction::Vertical,
+            Orientation::Horizontal => Direction::Horizontal,
+        }
+    }
+
+    /// Grows the list, shrinking the opened email.
+    pub fn grow(&mut self) {
+        self.ratio = self.ratio.saturating_add(RATIO_STEP).min(MAX_RATIO);
+    }
+
+    /// Checks if the opened email is displayed in full screen
+    pub const fn is_zoomed(self) -> bool {
+        self.zoomed
+    }
+
+    /// Creates a layout, with the percentage of the screen taken by the list
+    ///
+    /// The ratio is kept between 10% and 90%, so that both panes stay visible.
+    pub fn new(orientation: Orientation, ratio: Option<u16>) -> Self {
+        Self {
+            orientation,
+            ratio: ratio.unwrap_or(DEFAULT_RATIO).clamp(MIN_RATIO, MAX_RATIO),
+            zoomed: false,
+        }
+    }
+
+    /// Shrinks the list, growing the opened email.
+    pub fn shrink(&mut self) {
+        self.ratio = self.ratio.saturating_sub(RATIO_STEP).max(MIN_RATIO);
+    }
+
+    /// Displays the opened email in full screen, or goes back to the split.
+    pub const fn toggle_zoom(&mut self) {
+        self.zoomed = !self.zoomed;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::layout::Direction;
+
+    use crate::tui::split::{MAX_RATIO, Orientation, Split};
+
+    #[test]
+    fn split_layout() {
+        let mut split = Split::new(Orientation::Auto, Some(95));
+        assert_eq!(split.ratio, 90);
+        split.grow();
+        assert_eq!(split.ratio, 90);
+        for _ in 0..MAX_RATIO {
+            split.shrink();
+        }
+        assert_eq!(split.ratio, 10);
+        assert_eq!(split.as_direction(200), Direction::Horizontal);
+        assert_eq!(split.as_direction(80), Direction::Vertical);
+        assert_eq!(
+            Split::new(Orientation::Horizontal, None).as_direction(80),
+            Direction::Horizontal
+        );
+    }
+}
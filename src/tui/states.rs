@@ -4,6 +4,7 @@
 //! associated keybindings.
 
 use super::writer::Writer;
+use crate::mailto::Mailto;
 
 /// Current mode of the TUI, specifying what is the user doing
 #[derive(Default)]
@@ -14,7 +15,7 @@ pub enum TuiMode {
     /// Displaying emails to read different inboxes
     Reading,
     /// Writing an email
-    Writing(Writer),
+    Writing(Box<Writer>),
 }
 
 impl TuiMode {
@@ -22,6 +23,11 @@ impl TuiMode {
     ///
     /// This creates a default writer and opens it in the TUI app.
     pub fn new_writer(&mut self) {
-        *self = Self::Writing(Writer::default());
+        *self = Self::Writing(Box::default());
+    }
+
+    /// Switch to writer mode, pre-filled with a `mailto:` URL
+    pub fn new_writer_from_mailto(&mut self, mailto: &Mailto) {
+        *self = Self::Writing(Box::new(Writer::from(mailto)));
     }
 }
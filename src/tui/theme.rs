@@ -0,0 +1,93 @@
+//! Adapts the colours of the TUI to the terminal and the user's needs.
+//!
+//! The widgets are drawn with the default colours, and the theme is then
+//! applied on the whole frame, so that every widget follows it.
+
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier, Style, Stylize as _};
+
+/// Colour theme of the TUI
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Default colours
+    #[default]
+    Default,
+    /// Bright colours on the default background, without dimmed text
+    HighContrast,
+    /// No colours at all, only bold, reversed and underlined text
+    ///
+    /// This is used when the `NO_COLOR` environment variable is set.
+    NoColor,
+}
+
+impl Theme {
+    /// Applies the theme to a drawn frame.
+    pub fn apply(self, buffer: &mut Buffer) {
+        match self {
+            Self::Default => (),
+            Self::HighContrast =>
+                for cell in &mut buffer.content {
+                    cell.fg = to_high_contrast(cell.fg);
+                    cell.modifier.remove(Modifier::DIM);
+                },
+            Self::NoColor =>
+                for cell in &mut buffer.content {
+                    cell.fg = Color::Reset;
+                    cell.bg = Color::Reset;
+                },
+        }
+    }
+
+    /// Parses the name of a theme, as written in the configuration.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "high-contrast" => Some(Self::HighContrast),
+            "no-color" => Some(Self::NoColor),
+            _ => None,
+        }
+    }
+
+    /// Returns the style of the unread emails of the list
+    pub fn unread_style(self) -> Style {
+        match self {
+            Self::Default => Style::new().fg(Color::Cyan).bold(),
+            Self::HighContrast => Style::new().fg(Color::LightCyan).bold(),
+            Self::NoColor => Style::new().bold(),
+        }
+    }
+
+    /// Returns the style of the selected line
+    pub fn selection_style(self) -> Style {
+        match self {
+            Self::Default => Style::new().bg(Color::DarkGray),
+            Self::HighContrast =>
+                Style::new().fg(Color::Black).bg(Color::White),
+            Self::NoColor => Style::new().reversed(),
+        }
+    }
+}
+
+/// Returns the brightest version of a text colour
+const fn to_high_contrast(color: Color) -> Color {
+    match color {
+        Color::Red => Color::LightRed,
+        Color::Green => Color::LightGreen,
+        Color::Yellow => Color::LightYellow,
+        Color::Blue => Color::LightBlue,
+        Color::Magenta => Color::LightMagenta,
+        Color::Cyan => Color::LightCyan,
+        Color::DarkGray | Color::Gray => Color::White,
+        Color::Reset
+        | Color::Black
+        | Color::LightRed
+        | Color::LightGreen
+        | Color::LightYellow
+        | Color::LightBlue
+        | Color::LightMagenta
+        | Color::LightCyan
+        | Color::White
+        | Color::Rgb(..)
+        | Color::Indexed(_) => color,
+    }
+}
@@ -0,0 +1,171 @@
+//! Groups the loaded emails into conversations.
+//!
+//! The parent of an email is found from its `In-Reply-To` header, or else
+//! from the closest ancestor of its `References` header that is loaded. The
+//! replies to an email are ordered from the oldest, and a conversation is read
+//! depth-first, like in a threaded view.
+
+use std::collections::HashMap;
+
+use mail_parser::HeaderName;
+
+use crate::fetch::parser::Email;
+
+/// Separator between the senders of the breadcrumb line
+const BREADCRUMB_SEPARATOR: &str = " > ";
+
+/// Relations between the loaded emails, indexed by their position in the list
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Threads {
+    /// Replies to every email, from the oldest
+    children: Vec<Vec<usize>>,
+    /// Email every email replies to, if it is loaded
+    parents: Vec<Option<usize>>,
+}
+
+impl Threads {
+    /// Returns the emails the given one replies to, from the first email of
+    /// the conversation
+    ///
+    /// The given email isn't included.
+    pub fn ancestors(&self, id: usize) -> Vec<usize> {
+        let mut ancestors = vec![];
+        let mut current = id;
+        while let Some(parent) = self.as_parent(current)
+            && ancestors.len() < self.parents.len()
+        {
+            ancestors.push(parent);
+            current = parent;
+        }
+        ancestors.reverse();
+        ancestors
+    }
+
+    /// Returns the email the given one replies to, if it is loaded
+    pub fn as_parent(&self, id: usize) -> Option<usize> {
+        self.parents.get(id).copied().flatten()
+    }
+
+    /// Lists the emails of the conversation of the given one, in reading
+    /// order
+    pub fn conversation(&self, id: usize) -> Vec<usize> {
+        let root = self.ancestors(id).first().copied().unwrap_or(id);
+        let mut conversation = vec![];
+        let mut stack = vec![root];
+        while let Some(current) = stack.pop()
+            && conversation.len() < self.parents.len()
+        {
+            conversation.push(current);
+            if let Some(children) = self.children.get(current) {
+                stack.extend(children.iter().rev());
+            }
+        }
+        conversation
+    }
+
+    /// Groups the emails into conversations
+    pub fn new(emails: &[Email]) -> Self {
+        let positions = emails
+            .iter()
+            .enumerate()
+            .filter_map(|(id, email)| {
+                Some((email.to_message_ids(&HeaderName::MessageId).pop()?, id))
+            })
+            .collect::<HashMap<_, _>>();
+        let parents = emails
+            .iter()
+            .enumerate()
+            .map(|(id, email)| {
+                let mut references =
+                    email.to_message_ids(&HeaderName::References);
+                references.extend(email.to_message_ids(&HeaderName::InReplyTo));
+                references
+                    .iter()
+                    .rev()
+                    .filter_map(|reference| positions.get(reference).copied())
+                    .find(|parent| *parent != id)
+            })
+            .collect::<Vec<_>>();
+
+        let mut children = vec![vec![]; emails.len()];
+        for (id, parent) in parents.iter().enumerate() {
+            if let Some(replies) =
+                parent.and_then(|parent_id| children.get_mut(parent_id))
+            {
+                replies.push(id);
+            }
+        }
+        for replies in &mut children {
+            replies.sort_by_key(|id| emails.get(*id).map(Email::as_uid));
+        }
+        Self { children, parents }
+    }
+
+    /// Returns the email after the given one in the conversation, in reading
+    /// order
+    pub fn next_in_thread(&self, id: usize) -> Option<usize> {
+        let conversation = self.conversation(id);
+        let position = conversation.iter().position(|other| *other == id)?;
+        conversation.get(position.saturating_add(1)).copied()
+    }
+
+    /// Returns the next reply to the same email as the given one
+    pub fn next_sibling(&self, id: usize) -> Option<usize> {
+        let siblings = self.children.get(self.as_parent(id)?)?;
+        let position = siblings.iter().position(|other| *other == id)?;
+        siblings.get(position.saturating_add(1)).copied()
+    }
+
+    /// Returns the breadcrumb line of an email, with the position of the email
+    /// in its conversation and the senders of the emails it replies to
+    ///
+    /// Returns `None` if the email isn't part of a conversation.
+    pub fn to_breadcrumb(&self, emails: &[Email], id: usize) -> Option<String> {
+        let conversation = self.conversation(id);
+        if conversation.len() <= 1 {
+            return None;
+        }
+        let position = conversation.iter().position(|other| *other == id)?;
+        let senders = self
+            .ancestors(id)
+            .into_iter()
+            .chain([id])
+            .filter_map(|other| {
+                emails.get(other)?.get_header_str(&HeaderName::From)
+            })
+            .collect::<Vec<_>>();
+        Some(format!(
+            "Thread {}/{}: {}",
+            position.saturating_add(1),
+            conversation.len(),
+            senders.join(BREADCRUMB_SEPARATOR)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::email_with_uid;
+    use crate::tui::threads::Threads;
+
+    #[test]
+    fn thread_navigation() {
+        let emails = [
+            email_with_uid(4, "Message-ID: <d@x>\nIn-Reply-To: <a@x>"),
+            email_with_uid(3, "Message-ID: <c@x>\nReferences: <a@x> <b@x>"),
+            email_with_uid(2, "Message-ID: <b@x>\nIn-Reply-To: <a@x>"),
+            email_with_uid(1, "Message-ID: <a@x>\nFrom: alice@x"),
+            email_with_uid(5, "Message-ID: <e@x>"),
+        ];
+        let threads = Threads::new(&emails);
+        assert_eq!(threads.as_parent(1), Some(2));
+        assert_eq!(threads.ancestors(1), [3, 2]);
+        assert_eq!(threads.next_sibling(2), Some(0));
+        assert_eq!(threads.next_sibling(0), None);
+        assert_eq!(threads.conversation(0), [3, 2, 1, 0]);
+        assert_eq!(threads.next_in_thread(1), Some(0));
+        assert_eq!(threads.next_in_thread(0), None);
+        assert_eq!(threads.to_breadcrumb(&emails, 4), None);
+        assert!(threads.to_breadcrumb(&emails, 3).is_some());
+    }
+}
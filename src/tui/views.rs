@@ -0,0 +1,266 @@
+//! Remembers the state of the email list of every folder.
+//!
+//! The selected email, the scroll position and the sort order are saved when
+//! switching to another folder and when quitting, and restored when coming
+//! back. They are stored in the `views` file of the cache, with one folder per
+//! line:
+//!
+//! ```text
+//! 42 10 newest INBOX
+//! - 0 subject Archives/2024
+//! ```
+//!
+//! The selection is stored as the uid of the email, or `-` if no email was
+//! selected, so that it stays on the same email when new ones arrive.
+
+use alloc::collections::BTreeMap;
+use core::cmp::Reverse;
+use std::collections::HashMap;
+
+use mail_parser::HeaderName;
+
+use crate::cache::Cache;
+use crate::cache::threads::ThreadIndex;
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+
+/// Name of the file of the cache that stores the views
+const VIEWS_FILE: &str = "views";
+
+/// Order of the emails in the list
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Emails with attachments first
+    Attachments,
+    /// Most recent emails first
+    #[default]
+    Newest,
+    /// Oldest emails first
+    Oldest,
+    /// Emails sorted by sender
+    Sender,
+    /// Emails sorted by subject
+    Subject,
+    /// Emails grouped by conversation
+    Thread,
+}
+
+impl SortOrder {
+    /// Returns the name of the order, as written in the `:sort` command
+    pub const fn as_name(self) -> &'static str {
+        match self {
+            Self::Attachments => "attachments",
+            Self::Newest => "newest",
+            Self::Oldest => "oldest",
+            Self::Sender => "from",
+            Self::Subject => "subject",
+            Self::Thread => "thread",
+        }
+    }
+
+    /// Parses an order from its name
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "attachments" => Some(Self::Attachments),
+            "newest" => Some(Self::Newest),
+            "oldest" => Some(Self::Oldest),
+            "from" => Some(Self::Sender),
+            "subject" => Some(Self::Subject),
+            "thread" => Some(Self::Thread),
+            _ => None,
+        }
+    }
+
+    /// Sorts the emails in this order
+    ///
+    /// Emails with the same sender, subject or presence of attachments stay
+    /// sorted from the most recent. The conversations are read from the
+    /// index, so that they are grouped without threading the emails: the most
+    /// recent conversations come first, with their emails from the oldest.
+    /// The conversations are ordered by the dates of the emails, as the
+    /// replies merged from the sent folder don't share the uids of the folder.
+    pub fn sort(self, emails: &mut [Email], threads: &ThreadIndex) {
+        emails.sort_by_key(|email| Reverse(email.as_uid()));
+        match self {
+            Self::Attachments =>
+                emails.sort_by_key(|email| email.count_attachments() == 0),
+            Self::Newest => (),
+            Self::Oldest => emails.reverse(),
+            Self::Sender => emails.sort_by_cached_key(|email| {
+                email.get_header_str(&HeaderName::From).unwrap_or_default()
+            }),
+            Self::Subject => emails.sort_by_cached_key(|email| {
+                email
+                    .get_header_str(&HeaderName::Subject)
+                    .unwrap_or_default()
+            }),
+            Self::Thread => {
+                let mut newest = HashMap::new();
+                for email in emails.iter() {
+                    if let Some(thread) = threads.as_thread(email.as_uid()) {
+                        let date = newest.entry(thread).or_insert(i64::MIN);
+                        *date = (*date).max(to_timestamp(email));
+                    }
+                }
+                emails.sort_by_cached_key(|email| {
+                    let uid = email.as_uid();
+                    let date = to_timestamp(email);
+                    let thread = threads.as_thread(uid);
+                    let group = thread
+                        .and_then(|found| newest.get(found))
+                        .copied()
+                        .unwrap_or(date);
+                    (Reverse(group), thread.map(ToOwned::to_owned), date, uid)
+                });
+            }
+        }
+    }
+}
+
+/// Returns the date of an email, as a UNIX timestamp, from its `Date` header
+/// or else from the date the server received it
+pub fn to_timestamp(email: &Email) -> i64 {
+    email
+        .as_headers()
+        .get(&HeaderName::Date)
+        .and_then(|date| date.as_datetime())
+        .copied()
+        .or_else(|| email.to_internal_date())
+        .map_or(0, |date| date.to_timestamp())
+}
+
+/// State of the email list of a folder
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FolderView {
+    /// Index of the first email displayed in the list
+    offset: usize,
+    /// Uid of the selected email, if any
+    selected_uid: Option<u32>,
+    /// Order of the emails
+    sort: SortOrder,
+}
+
+impl FolderView {
+    /// Returns the index of the first email displayed in the list
+    pub const fn as_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the uid of the selected email, if any
+    pub const fn as_selected_uid(&self) -> Option<u32> {
+        self.selected_uid
+    }
+
+    /// Returns the order of the emails
+    pub const fn as_sort(&self) -> SortOrder {
+        self.sort
+    }
+
+    /// Creates the state of a folder
+    pub const fn new(
+        offset: usize,
+        selected_uid: Option<u32>,
+        sort: SortOrder,
+    ) -> Self {
+        Self { offset, selected_uid, sort }
+    }
+
+    /// Parses a line of the views file, with the name of its folder
+    fn parse(line: &str) -> Option<(String, Self)> {
+        let mut words = line.splitn(4, ' ');
+        let selected_uid = match words.next()? {
+            "-" => None,
+            uid => Some(uid.parse().ok()?),
+        };
+        let offset = words.next()?.parse().ok()?;
+        let sort = SortOrder::parse(words.next()?)?;
+        let folder = words.next()?.to_owned();
+        Some((folder, Self { offset, selected_uid, sort }))
+    }
+}
+
+/// States of the email lists of every folder
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Views {
+    /// State of every visited folder, indexed by folder name
+    folders: BTreeMap<String, FolderView>,
+}
+
+impl Views {
+    /// Returns the state of a folder, or the default state if it was never
+    /// visited
+    pub fn get(&self, folder: &str) -> FolderView {
+        self.folders.get(folder).copied().unwrap_or_default()
+    }
+
+    /// Loads the views stored in the cache
+    ///
+    /// Returns empty views if there is no cache or if none were stored.
+    pub fn load(cache: Option<&Cache>) -> Self {
+        let folders = cache
+            .and_then(|storage| storage.load_state(VIEWS_FILE))
+            .map(|content| {
+                content.lines().filter_map(FolderView::parse).collect()
+            })
+            .unwrap_or_default();
+        Self { folders }
+    }
+
+    /// Stores the views in the cache.
+    pub fn save(&self, cache: &Cache) -> Result {
+        let content = self
+            .folders
+            .iter()
+            .map(|(folder, view)| {
+                let selected = view
+                    .selected_uid
+                    .map_or_else(|| "-".to_owned(), |uid| uid.to_string());
+                format!(
+                    "{selected} {} {} {folder}",
+                    view.offset,
+                    view.sort.as_name()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        cache.store_state(VIEWS_FILE, &content)
+    }
+
+    /// Updates the state of a folder.
+    pub fn set(&mut self, folder: &str, view: FolderView) {
+        self.folders.insert(folder.to_owned(), view);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::views::{FolderView, SortOrder};
+
+    #[test]
+    fn parse_views() {
+        assert_eq!(
+            FolderView::parse("42 10 newest INBOX"),
+            Some((
+                "INBOX".to_owned(),
+                FolderView {
+                    offset: 10,
+                    selected_uid: Some(42),
+                    sort: SortOrder::Newest,
+                }
+            ))
+        );
+        assert_eq!(
+            FolderView::parse("- 0 subject Archives 2024"),
+            Some((
+                "Archives 2024".to_owned(),
+                FolderView {
+                    offset: 0,
+                    selected_uid: None,
+                    sort: SortOrder::Subject,
+                }
+            ))
+        );
+        assert_eq!(FolderView::parse("42 10 random INBOX"), None);
+        assert_eq!(FolderView::parse("42 10"), None);
+    }
+}
@@ -0,0 +1,63 @@
+//! Fits text into a given number of terminal columns.
+//!
+//! Wide characters, like CJK text and most emoji, take two columns in the
+//! terminal, so the number of bytes or characters of a string can't be used to
+//! align it.
+
+use unicode_width::{UnicodeWidthChar as _, UnicodeWidthStr as _};
+
+/// Character appended to truncated text.
+const ELLIPSIS: char = '\u{2026}';
+
+/// Pads text with spaces to take exactly `width` columns, truncating it if it
+/// is too long.
+pub fn fit(text: &str, width: usize) -> String {
+    let truncated = truncate(text, width);
+    let padding = width.saturating_sub(truncated.width());
+    format!("{truncated}{}", " ".repeat(padding))
+}
+
+/// Truncates text to take at most `width` columns
+///
+/// An ellipsis is added at the end of the text if it was truncated. Wide
+/// characters are never cut in half.
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_owned();
+    }
+
+    let available = width.saturating_sub(ELLIPSIS.width().unwrap_or(1));
+    let mut used: usize = 0;
+    let mut truncated = String::new();
+
+    for ch in text.chars() {
+        let ch_width = ch.width().unwrap_or_default();
+        if used.saturating_add(ch_width) > available {
+            break;
+        }
+        used = used.saturating_add(ch_width);
+        truncated.push(ch);
+    }
+
+    if width != 0 {
+        truncated.push(ELLIPSIS);
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tui::width::{fit, truncate};
+
+    #[test]
+    #[expect(clippy::non_ascii_literal, reason = "test")]
+    fn wide_characters() {
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello world", 6), "hello…");
+        assert_eq!(truncate("日本語のメール", 6), "日本…");
+        assert_eq!(truncate("🎉🎉🎉", 4), "🎉…");
+        assert_eq!(truncate("日本", 0), "");
+        assert_eq!(fit("日本", 6), "日本  ");
+        assert_eq!(fit("日本語", 5), "日本…");
+    }
+}
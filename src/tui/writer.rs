@@ -1,15 +1,91 @@
+use alloc::borrow::Cow;
+use core::iter;
+use std::fs;
+use std::path::PathBuf;
+
+use mail_parser::{HeaderName, HeaderValue};
 use ratatui::Frame;
-use ratatui::crossterm::event::{Event, KeyCode};
+use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::layout::{Constraint, Direction, Layout};
-use ratatui::text::Text;
-use tui_input::Input;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::Paragraph;
 use tui_input::backend::crossterm::EventHandler as _;
+use tui_input::{Input, InputRequest};
+
+use super::app::Error;
+use super::browser::{Browser, Choice, Purpose};
+use super::components::new_simple_box;
+use super::picker::Picker;
+use crate::errors::Result;
+use crate::fetch::parser::Email;
+use crate::identity::{Identity, find_for_reply, find_recipient};
+use crate::mailto::Mailto;
+use crate::priority::Priority;
+use crate::send::dsn::Dsn;
+use crate::send::message::{OutgoingEmail, parse_custom_headers};
+use crate::send::wrap::{break_lines, reflow};
+
+/// Names of the threading headers of a reply, set from the replied email
+const THREAD_HEADERS: [&str; 2] = ["In-Reply-To", "References"];
+/// Prefix of the subject of a reply
+const REPLY_PREFIX: &str = "Re: ";
+/// Numbers of days to wait for a reply before a reminder, cycled through with
+/// `r`, 0 for no reminder
+const REMINDER_DAYS: [u16; 5] = [0, 1, 3, 7, 14];
 
 /// Representation of the writer, with the different boxes.
 #[derive(Default)]
+#[expect(clippy::struct_excessive_bools, reason = "independent options")]
 pub struct Writer {
+    /// Indicates whether the advanced headers panel is displayed, toggled
+    /// with `a`
+    advanced: bool,
+    /// Files attached to the email, chosen with `A`
+    attachments: Vec<PathBuf>,
+    /// Input to enter the blind copies of the email, edited with `B`
+    ///
+    /// They receive the email, but aren't written in its headers.
+    bcc: Input,
+    /// File browser to choose the attachments, opened with `A`
+    browser: Option<Browser>,
+    /// Attachments of the email the writer was opened from, with their names
+    /// and contents
+    carried_attachments: Vec<(String, Vec<u8>)>,
+    /// Input to enter the copies of the email, edited with `c`, separated
+    /// with commas
+    cc: Input,
+    /// Delivery status notifications requested, changed with `n`
+    ///
+    /// This is `None` until the default of the config is set.
+    dsn: Option<Dsn>,
+    /// Indicates whether the body is sent as `format=flowed`
+    flowed: bool,
+    /// Input to enter custom `X-` headers, separated by semicolons
+    headers: Input,
+    /// Popup to insert a unicode character, opened with `Ctrl-E`
+    picker: Option<Picker>,
+    /// Index of the identity to send from in `identities`, changed with `i`
+    identity: usize,
+    /// Identities the email can be sent from
+    identities: Vec<Identity>,
+    /// Priority of the email, changed with `p`
+    priority: Priority,
+    /// Indicates whether a read receipt is requested, toggled with `d`
+    read_receipt: bool,
+    /// Number of days after which the user is reminded of the email if it
+    /// wasn't answered, 0 for no reminder, changed with `r`
+    reminder_days: u16,
+    /// Input to enter the address the replies must be sent to
+    reply_to: Input,
     /// Input to enter the subject of the email
     subject: Input,
+    /// Subjects offered for a reply, cycled through with `Tab` while the
+    /// subject is edited
+    subject_suggestions: Vec<String>,
+    /// Threading headers of a reply, with the names of [`THREAD_HEADERS`],
+    /// which can't be edited
+    thread_headers: Vec<(String, String)>,
     /// Input to enter the destination(s) of the email
     ///
     /// If there are multiple destinations, they must be seperated with a
@@ -17,6 +93,15 @@ pub struct Writer {
     to: Input,
     /// Input to enter the body of the email
     body: Input,
+    /// Indicates whether the replied email wasn't sent to any identity
+    ///
+    /// The first identity is then selected, and a warning is displayed until
+    /// another one is chosen with `i`.
+    unknown_recipient: bool,
+    /// Column the body is wrapped at, 0 if it mustn't be wrapped
+    ///
+    /// The body is displayed wrapped, and re-wrapped for good with `f`.
+    wrap_column: usize,
     /// State of the writer
     ///
     /// Specifies what Input is being edited
@@ -24,52 +109,816 @@ pub struct Writer {
 }
 
 impl Writer {
+    /// Returns the `Message-ID` of the replied email, without its angle
+    /// brackets, if the email is a reply
+    pub fn as_replied_id(&self) -> Option<&str> {
+        self.thread_headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("In-Reply-To"))
+            .map(|(_, id)| {
+                id.trim().trim_start_matches('<').trim_end_matches('>')
+            })
+    }
+
+    /// Returns the identity to send the email from, if any is known
+    pub fn as_identity(&self) -> Option<&Identity> {
+        self.identities.get(self.identity)
+    }
+
+    /// Creates a writer composing a new email from an existing one, e.g. to
+    /// send a corrected version of it
+    ///
+    /// The destinations, the copies, the subject, the `Reply-To`, the
+    /// priority, the body and the attachments are copied. The threading
+    /// headers aren't kept.
+    pub fn new_from_email(email: &Email) -> Self {
+        let addresses = |name: &HeaderName<'_>| {
+            email
+                .as_headers()
+                .get(name)
+                .and_then(HeaderValue::as_address)
+                .map(|address| {
+                    address
+                        .iter()
+                        .filter_map(|addr| addr.address())
+                        .map(ToOwned::to_owned)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+        let reply_to = addresses(&HeaderName::ReplyTo).join(", ");
+        Self {
+            advanced: !reply_to.is_empty(),
+            body: Input::new(email.to_plain_body().unwrap_or_default()),
+            carried_attachments: email
+                .as_attachments()
+                .iter()
+                .map(|attachment| {
+                    (attachment.to_file_name(), attachment.as_data().to_vec())
+                })
+                .collect(),
+            cc: Input::new(addresses(&HeaderName::Cc).join(", ")),
+            priority: email.to_priority(),
+            reply_to: Input::new(reply_to),
+            subject: Input::new(
+                email
+                    .get_header_str(&HeaderName::Subject)
+                    .unwrap_or_default(),
+            ),
+            to: Input::new(addresses(&HeaderName::To).join(", ")),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a writer resuming a draft, e.g. an email of the drafts folder
+    ///
+    /// The draft is copied as with [`Self::new_from_email`], but its threading
+    /// headers are kept, for a resumed reply to stay in its conversation.
+    pub fn new_from_draft(draft: &Email) -> Self {
+        let thread_headers = THREAD_HEADERS
+            .into_iter()
+            .zip([HeaderName::InReplyTo, HeaderName::References])
+            .filter_map(|(name, header)| {
+                let ids = draft.to_message_ids(&header);
+                (!ids.is_empty()).then(|| {
+                    let value = ids
+                        .iter()
+                        .map(|id| format!("<{id}>"))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    (name.to_owned(), value)
+                })
+            })
+            .collect();
+        Self { thread_headers, ..Self::new_from_email(draft) }
+    }
+
+    /// Creates a writer replying to an email
+    ///
+    /// The reply is sent to the `Reply-To` of the email, or else to its
+    /// sender, and its threading headers are set and locked. The body quotes
+    /// the replied email, below an attribution line. The subjects of
+    /// the emails of the `conversation` are offered as suggestions, the one of
+    /// the replied email first.
+    pub fn new_reply(replied: &Email, conversation: &[&Email]) -> Self {
+        let recipient = replied
+            .as_headers()
+            .get(&HeaderName::ReplyTo)
+            .and_then(HeaderValue::as_address)
+            .and_then(|address| address.first())
+            .and_then(|addr| addr.address())
+            .map(ToOwned::to_owned)
+            .or_else(|| replied.to_sender_address())
+            .unwrap_or_default();
+
+        let thread_headers = replied
+            .get_header_str(&HeaderName::MessageId)
+            .map(|id| {
+                let mut references =
+                    replied.to_message_ids(&HeaderName::References);
+                if references.is_empty() {
+                    references = replied.to_message_ids(&HeaderName::InReplyTo);
+                }
+                references.push(id.clone());
+                let references_value = references
+                    .iter()
+                    .map(|reference| format!("<{reference}>"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                THREAD_HEADERS
+                    .into_iter()
+                    .map(ToOwned::to_owned)
+                    .zip([format!("<{id}>"), references_value])
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut writer = Self {
+            body: Input::new(to_quoted_body(replied)),
+            thread_headers,
+            to: Input::new(recipient),
+            state: WriterState::Body,
+            ..Self::default()
+        };
+        writer.suggest_subjects_of(
+            &iter::once(replied)
+                .chain(conversation.iter().copied())
+                .collect::<Vec<_>>(),
+        );
+        writer
+    }
+
+    /// Offers the subjects of the emails of a conversation as suggestions for
+    /// the subject of a reply
+    ///
+    /// The subjects are turned into the subjects of replies, and the first
+    /// one replaces the current subject.
+    pub fn suggest_subjects_of(&mut self, conversation: &[&Email]) {
+        self.subject_suggestions.clear();
+        for email in conversation {
+            let suggestion = to_reply_subject(
+                &email
+                    .get_header_str(&HeaderName::Subject)
+                    .unwrap_or_default(),
+            );
+            if !self.subject_suggestions.contains(&suggestion) {
+                self.subject_suggestions.push(suggestion);
+            }
+        }
+        if let Some(first) = self.subject_suggestions.first() {
+            self.subject = Input::new(first.clone());
+        }
+    }
+
+    /// Replaces the body of the email.
+    pub fn set_body(&mut self, body: &str) {
+        self.body = Input::new(body.to_owned());
+    }
+
+    /// Selects the identity the replied email was sent to, or else the first
+    /// one with a warning.
+    pub fn select_identity_for(&mut self, replied: &Email) {
+        let found = find_recipient(&self.identities, replied);
+        self.identity = found.unwrap_or_default();
+        self.unknown_recipient = found.is_none();
+    }
+
+    /// Selects the identity an email was sent from, or else the one it was
+    /// sent to.
+    pub fn select_identity_of(&mut self, email: &Email) {
+        let sender = email.to_sender_address().unwrap_or_default();
+        self.identity = self
+            .identities
+            .iter()
+            .position(|identity| {
+                identity.as_address().eq_ignore_ascii_case(&sender)
+            })
+            .unwrap_or_else(|| find_for_reply(&self.identities, email));
+    }
+
+    /// Replaces the subject with the next suggestion, if there are any
+    fn suggest_subject(&mut self) {
+        let next = self
+            .subject_suggestions
+            .iter()
+            .position(|suggestion| suggestion == self.subject.value())
+            .map_or(0, |current| current.saturating_add(1))
+            .checked_rem(self.subject_suggestions.len())
+            .and_then(|index| self.subject_suggestions.get(index));
+        if let Some(suggestion) = next {
+            self.subject = Input::new(suggestion.clone());
+        }
+    }
+
+    /// Sets the identities the email can be sent from, selecting the first
+    /// one.
+    pub fn set_identities(&mut self, identities: Vec<Identity>) {
+        self.identities = identities;
+        self.identity = 0;
+    }
+
+    /// Sets the column the body is wrapped at, and whether it is sent as
+    /// `format=flowed`.
+    pub const fn set_wrapping(&mut self, column: usize, flowed: bool) {
+        self.wrap_column = column;
+        self.flowed = flowed;
+    }
+
+    /// Sets the delivery status notifications requested by default, unless
+    /// they were already chosen
+    pub fn set_default_dsn(&mut self, dsn: Dsn) {
+        self.dsn.get_or_insert(dsn);
+    }
+
+    /// Returns the delivery status notifications requested for the email
+    pub fn as_dsn(&self) -> Dsn {
+        self.dsn.unwrap_or_default()
+    }
+
+    /// Returns the priority of the email
+    pub const fn as_priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Returns the number of days to wait for a reply before reminding the
+    /// user, 0 for no reminder
+    pub const fn as_reminder_days(&self) -> u16 {
+        self.reminder_days
+    }
+
+    /// Selects the next number of days of [`REMINDER_DAYS`]
+    fn next_reminder_days(&mut self) {
+        let next = REMINDER_DAYS
+            .iter()
+            .position(|days| *days == self.reminder_days)
+            .map_or(0, |current| current.saturating_add(1))
+            .checked_rem(REMINDER_DAYS.len())
+            .and_then(|index| REMINDER_DAYS.get(index));
+        self.reminder_days = next.copied().unwrap_or_default();
+    }
+
+    /// Returns the email to send, if an identity to send it from is known
+    ///
+    /// The destinations are separated by commas, and the empty advanced
+    /// headers are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an attached file can't be read.
+    pub fn to_email(&self) -> Result<Option<OutgoingEmail>> {
+        let Some(identity) = self.as_identity() else {
+            return Ok(None);
+        };
+        let body = if self.flowed {
+            self.body.value().to_owned()
+        } else {
+            break_lines(self.body.value(), self.wrap_column).join("\n")
+        };
+        let mut email =
+            OutgoingEmail::new(identity.clone(), to_addresses(&self.to))
+                .with_cc(to_addresses(&self.cc))
+                .with_bcc(to_addresses(&self.bcc))
+                .with_subject(self.subject.value().to_owned())
+                .with_body(body)
+                .with_priority(self.priority);
+        if self.flowed {
+            email = email.with_flowed(self.wrap_column);
+        }
+        let reply_to = self.reply_to.value().trim();
+        if !reply_to.is_empty() {
+            email = email.with_reply_to(reply_to.to_owned());
+        }
+        if self.read_receipt {
+            email = email.with_read_receipt();
+        }
+        for (name, value) in parse_custom_headers(self.headers.value()) {
+            if !self
+                .thread_headers
+                .iter()
+                .any(|(locked, _)| locked.eq_ignore_ascii_case(&name))
+            {
+                email = email.with_header(name, value);
+            }
+        }
+        for (name, value) in &self.thread_headers {
+            email = email.with_header(name.clone(), value.clone());
+        }
+        for (name, data) in &self.carried_attachments {
+            email = email.with_attachment(name.clone(), data.clone());
+        }
+        for path in &self.attachments {
+            let data = fs::read(path).map_err(Error::ReadAttachment)?;
+            let name = path.file_name().map_or_else(
+                || "attachment".to_owned(),
+                |file_name| file_name.to_string_lossy().into_owned(),
+            );
+            email = email.with_attachment(name, data);
+        }
+        Ok(Some(email))
+    }
+
+    /// Returns the line listing the attached files
+    fn to_attachments_line(&self) -> Line<'_> {
+        Line::from(format!(
+            "Attachments ('A'): {}",
+            self.carried_attachments
+                .iter()
+                .map(|(name, _)| Cow::from(name.as_str()))
+                .chain(
+                    self.attachments
+                        .iter()
+                        .filter_map(|path| path.file_name())
+                        .map(|name| name.to_string_lossy())
+                )
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+
+    /// Returns the content of the writer, to store it as a draft
+    ///
+    /// See [`draft`](super::draft) for the format.
+    pub fn to_draft(&self) -> String {
+        let thread = self.thread_headers.iter().fold(
+            String::new(),
+            |mut thread, (name, value)| {
+                thread.push('\n');
+                thread.push_str(&name.to_lowercase());
+                thread.push(' ');
+                thread.push_str(value);
+                thread
+            },
+        );
+        format!(
+            "to {}{}{}\nsubject {}\nreply-to {}\nheaders {}\npriority \
+             {}\nreceipt {}{}\nremind {}{thread}\n\n{}",
+            self.to.value(),
+            to_draft_line("cc", &self.cc),
+            to_draft_line("bcc", &self.bcc),
+            self.subject.value(),
+            self.reply_to.value(),
+            self.headers.value(),
+            self.priority.as_name(),
+            if self.read_receipt { "yes" } else { "no" },
+            self.dsn
+                .map(|dsn| format!("\ndsn {}", dsn.to_names()))
+                .unwrap_or_default(),
+            self.reminder_days,
+            self.body.value()
+        )
+    }
+
+    /// Restores a writer from a draft
+    ///
+    /// Unknown or invalid lines are ignored.
+    pub fn from_draft(content: &str) -> Self {
+        let (headers, body) =
+            content.split_once("\n\n").unwrap_or((content, ""));
+        let mut writer =
+            Self { body: Input::new(body.to_owned()), ..Self::default() };
+        for (key, value) in
+            headers.lines().filter_map(|line| line.split_once(' '))
+        {
+            match key {
+                "to" => writer.to = Input::new(value.to_owned()),
+                "cc" => writer.cc = Input::new(value.to_owned()),
+                "bcc" => writer.bcc = Input::new(value.to_owned()),
+                "subject" => writer.subject = Input::new(value.to_owned()),
+                "reply-to" => writer.reply_to = Input::new(value.to_owned()),
+                "headers" => writer.headers = Input::new(value.to_owned()),
+                "priority" =>
+                    writer.priority = Priority::parse(value).unwrap_or_default(),
+                "receipt" => writer.read_receipt = value == "yes",
+                "dsn" => writer.dsn = Some(Dsn::parse(value)),
+                "remind" =>
+                    writer.reminder_days = value.parse().unwrap_or_default(),
+                _ => {
+                    if let Some(name) = THREAD_HEADERS
+                        .iter()
+                        .find(|name| name.eq_ignore_ascii_case(key))
+                    {
+                        writer
+                            .thread_headers
+                            .push(((*name).to_owned(), value.to_owned()));
+                    }
+                }
+            }
+        }
+        writer.advanced = writer.read_receipt
+            || writer.as_dsn().is_requested()
+            || writer.reminder_days != 0
+            || !writer.reply_to.value().is_empty()
+            || !writer.headers.value().is_empty();
+        writer
+    }
+
+    /// Returns the lines of the advanced headers panel
+    ///
+    /// The threading headers of a reply are displayed, but can't be edited.
+    fn to_advanced_lines(&self) -> Text<'_> {
+        let mut lines = vec![
+            Line::from(format!("Reply-To ('R'): {}", self.reply_to.value())),
+            Line::from(format!("Headers ('x'): {}", self.headers.value())),
+            Line::from(format!(
+                "Read receipt ('d'): {}",
+                if self.read_receipt { "requested" } else { "no" }
+            )),
+            Line::from(if self.as_dsn().is_requested() {
+                format!(
+                    "Delivery notifications ('n'): {}",
+                    self.as_dsn().to_names()
+                )
+            } else {
+                "Delivery notifications ('n'): no".to_owned()
+            }),
+            Line::from(match self.reminder_days {
+                0 => "Remind if no reply ('r'): no".to_owned(),
+                days => format!("Remind if no reply ('r'): after {days} days"),
+            }),
+        ];
+        lines.extend(
+            self.thread_headers
+                .iter()
+                .map(|(name, value)| Line::from(format!("{name}: {value}"))),
+        );
+        Text::from(lines)
+    }
+
+    /// Returns the lines of the recipients: the destinations, and the copies
+    /// and blind copies if there are some or if they are being edited
+    fn to_recipient_lines(&self) -> Text<'_> {
+        let mut lines = vec![Line::from(self.to.value())];
+        if !self.cc.value().is_empty() || matches!(self.state, WriterState::Cc)
+        {
+            lines.push(Line::from(format!("Cc ('c'): {}", self.cc.value())));
+        }
+        if !self.bcc.value().is_empty()
+            || matches!(self.state, WriterState::Bcc)
+        {
+            lines.push(Line::from(format!("Bcc ('B'): {}", self.bcc.value())));
+        }
+        Text::from(lines)
+    }
+
     /// Main method to display the layout on every re-render of the page
     #[expect(clippy::indexing_slicing, reason = "constant size and indexes")]
     pub fn writer_page(&self, frame: &mut Frame<'_>) {
+        let advanced_height = if self.advanced {
+            u16::try_from(self.to_advanced_lines().lines.len())
+                .unwrap_or(u16::MAX)
+                .saturating_add(2)
+        } else {
+            0
+        };
         let layout = Layout::new(
             Direction::Vertical,
-            [Constraint::Max(5), Constraint::Max(5), Constraint::Fill(1)],
+            [
+                Constraint::Length(u16::from(self.unknown_recipient)),
+                Constraint::Max(5),
+                Constraint::Max(5),
+                Constraint::Length(advanced_height),
+                Constraint::Fill(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ],
         )
         .split(frame.area());
 
-        assert!(layout.len() == 3, "Layout has 3 elements");
+        assert!(layout.len() == 8, "Layout has 8 elements");
 
         frame.render_widget(Text::from("hello wrold"), frame.area());
-        frame.render_widget(self.subject.value(), layout[0]);
-        frame.render_widget(self.to.value(), layout[1]);
-        frame.render_widget(self.body.value(), layout[2]);
+        if self.unknown_recipient {
+            frame.render_widget(
+                Line::from(
+                    "The replied email wasn't sent to any of your identities: \
+                     check the From below, or press 'i' to choose another one",
+                )
+                .style(Style::new().fg(Color::Black).bg(Color::Yellow)),
+                layout[0],
+            );
+        }
+        frame.render_widget(self.subject.value(), layout[1]);
+        frame.render_widget(self.to_recipient_lines(), layout[2]);
+        if self.advanced {
+            frame.render_widget(
+                Paragraph::new(self.to_advanced_lines())
+                    .block(new_simple_box("Advanced headers")),
+                layout[3],
+            );
+        }
+        frame.render_widget(
+            Text::from(
+                break_lines(self.body.value(), self.wrap_column)
+                    .into_iter()
+                    .map(Line::from)
+                    .collect::<Vec<_>>(),
+            ),
+            layout[4],
+        );
+        frame.render_widget(
+            Text::from(format!("Priority: {}", self.priority.as_name())),
+            layout[5],
+        );
+        frame.render_widget(
+            Text::from(format!(
+                "From: {}",
+                self.as_identity()
+                    .map_or_else(String::new, Identity::to_from_header)
+            )),
+            layout[6],
+        );
+        frame.render_widget(self.to_attachments_line(), layout[7]);
         frame.render_widget(Text::from("hello wrolu2"), frame.area());
+        if let Some(picker) = &self.picker {
+            picker.draw(frame, frame.area());
+        }
+        if let Some(browser) = &self.browser {
+            browser.draw(frame, frame.area());
+        }
+    }
+
+    /// Handles a key pressed while the file browser is opened
+    fn handle_browser_events(&mut self, event: &Event) {
+        let (Some(browser), Event::Key(key)) = (&mut self.browser, event)
+        else {
+            return;
+        };
+        match browser.handle_key(key.code) {
+            Some(Choice::Chosen(paths)) => {
+                self.attachments.extend(paths);
+                self.browser = None;
+            }
+            Some(Choice::Cancelled) => self.browser = None,
+            None => (),
+        }
+    }
+
+    /// Handles a key pressed while the character picker is opened
+    ///
+    /// The selected character is inserted at the cursor of the edited input.
+    fn handle_picker_events(&mut self, event: &Event) {
+        let Some(picker) = &mut self.picker else {
+            return;
+        };
+        let Event::Key(KeyEvent { code, modifiers, .. }) = event else {
+            return;
+        };
+        match (code, *modifiers == KeyModifiers::CONTROL) {
+            (KeyCode::Esc, _) => self.picker = None,
+            (KeyCode::Enter, _) => {
+                if let Some(ch) = picker.as_selected() {
+                    let input = match self.state {
+                        WriterState::Subject => &mut self.subject,
+                        WriterState::Body => &mut self.body,
+                        WriterState::Bcc
+                        | WriterState::Cc
+                        | WriterState::Headers
+                        | WriterState::None
+                        | WriterState::ReplyTo
+                        | WriterState::To => return,
+                    };
+                    input.handle(InputRequest::InsertChar(ch));
+                }
+                self.picker = None;
+            }
+            (KeyCode::Up, _) | (KeyCode::Char('p'), true) =>
+                picker.select_previous(),
+            (KeyCode::Down, _) | (KeyCode::Char('n'), true) =>
+                picker.select_next(),
+            _ => {
+                picker.as_input_mut().handle_event(event);
+                picker.update_results();
+            }
+        }
     }
 
     /// Handler to manage keypresses.
     pub fn handle_key_events(&mut self, event: &Event) -> bool {
+        if self.picker.is_some() {
+            self.handle_picker_events(event);
+            return true;
+        }
+        if self.browser.is_some() {
+            self.handle_browser_events(event);
+            return true;
+        }
         if let Event::Key(key) = event {
             match (&self.state, key.code) {
+                (
+                    WriterState::Subject | WriterState::Body,
+                    KeyCode::Char('e'),
+                ) if key.modifiers == KeyModifiers::CONTROL =>
+                    self.picker = Some(Picker::new()),
                 (WriterState::None, KeyCode::Char('t')) =>
                     self.state = WriterState::To,
+                (WriterState::None, KeyCode::Char('c')) =>
+                    self.state = WriterState::Cc,
+                (WriterState::None, KeyCode::Char('B')) =>
+                    self.state = WriterState::Bcc,
                 (WriterState::None, KeyCode::Char('s')) =>
                     self.state = WriterState::Subject,
                 (WriterState::None, KeyCode::Char('b')) =>
                     self.state = WriterState::Body,
-                (
-                    WriterState::To | WriterState::Subject | WriterState::Body,
-                    KeyCode::Esc,
-                ) => self.state = WriterState::None,
-                (WriterState::Subject, _) => {
-                    self.subject.handle_event(event);
+                (WriterState::None, KeyCode::Char('f')) =>
+                    self.body =
+                        Input::new(reflow(self.body.value(), self.wrap_column)),
+                (WriterState::None, KeyCode::Char('p')) =>
+                    self.priority = self.priority.next(),
+                (WriterState::None, KeyCode::Char('A')) =>
+                    self.browser = Some(Browser::new(Purpose::Attach)),
+                (WriterState::None, KeyCode::Char('u')) =>
+                    if self.attachments.pop().is_none() {
+                        self.carried_attachments.pop();
+                    },
+                (WriterState::None, KeyCode::Char('a')) =>
+                    self.advanced = !self.advanced,
+                (WriterState::None, KeyCode::Char('R')) => {
+                    self.advanced = true;
+                    self.state = WriterState::ReplyTo;
+                }
+                (WriterState::None, KeyCode::Char('x')) => {
+                    self.advanced = true;
+                    self.state = WriterState::Headers;
+                }
+                (WriterState::None, KeyCode::Char('d')) => {
+                    self.advanced = true;
+                    self.read_receipt = !self.read_receipt;
+                }
+                (WriterState::None, KeyCode::Char('n')) => {
+                    self.advanced = true;
+                    self.dsn = Some(self.as_dsn().next());
                 }
-                (WriterState::Body, _) => {
-                    self.body.handle_event(event);
+                (WriterState::None, KeyCode::Char('r')) => {
+                    self.advanced = true;
+                    self.next_reminder_days();
                 }
-                (WriterState::To, _) => {
-                    self.to.handle_event(event);
+                (WriterState::None, KeyCode::Char('i')) => {
+                    self.unknown_recipient = false;
+                    self.identity = self
+                        .identity
+                        .saturating_add(1)
+                        .checked_rem(self.identities.len())
+                        .unwrap_or_default();
                 }
-                _ => return false,
+                (
+                    WriterState::Bcc
+                    | WriterState::Body
+                    | WriterState::Cc
+                    | WriterState::Headers
+                    | WriterState::ReplyTo
+                    | WriterState::Subject
+                    | WriterState::To,
+                    KeyCode::Esc,
+                ) => self.state = WriterState::None,
+                (WriterState::Subject, KeyCode::Tab) => self.suggest_subject(),
+                _ => match self.as_edited_input_mut() {
+                    Some(input) => {
+                        input.handle_event(event);
+                    }
+                    None => return false,
+                },
             }
         }
         true
     }
+
+    /// Returns the input being edited, if any
+    const fn as_edited_input_mut(&mut self) -> Option<&mut Input> {
+        match self.state {
+            WriterState::None => None,
+            WriterState::To => Some(&mut self.to),
+            WriterState::Cc => Some(&mut self.cc),
+            WriterState::Bcc => Some(&mut self.bcc),
+            WriterState::Subject => Some(&mut self.subject),
+            WriterState::Body => Some(&mut self.body),
+            WriterState::Headers => Some(&mut self.headers),
+            WriterState::ReplyTo => Some(&mut self.reply_to),
+        }
+    }
+}
+
+impl From<&Mailto> for Writer {
+    fn from(mailto: &Mailto) -> Self {
+        Self {
+            advanced: false,
+            attachments: vec![],
+            bcc: Input::default(),
+            browser: None,
+            carried_attachments: vec![],
+            cc: Input::default(),
+            dsn: None,
+            flowed: false,
+            headers: Input::default(),
+            picker: None,
+            identity: 0,
+            identities: vec![],
+            priority: Priority::default(),
+            read_receipt: false,
+            reminder_days: 0,
+            reply_to: Input::default(),
+            subject: Input::new(mailto.as_subject().to_owned()),
+            subject_suggestions: vec![],
+            thread_headers: vec![],
+            to: Input::new(mailto.as_to().join(", ")),
+            body: Input::new(mailto.as_body().to_owned()),
+            state: WriterState::default(),
+            unknown_recipient: false,
+            wrap_column: 0,
+        }
+    }
+}
+
+/// Returns the addresses entered in an input, separated with commas
+///
+/// Spaces around the addresses and empty entries are ignored.
+fn to_addresses(input: &Input) -> Vec<String> {
+    input
+        .value()
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Returns the line of an optional recipient field of a draft, e.g.
+/// `\ncc carol@example.com`, or nothing if the field is empty
+fn to_draft_line(key: &str, input: &Input) -> String {
+    if input.value().is_empty() {
+        String::new()
+    } else {
+        format!("\n{key} {}", input.value())
+    }
+}
+
+/// Returns the body of a reply, quoting the replied email below an
+/// attribution line, e.g. `On 2024-05-02 10:00, Bob wrote:`
+///
+/// The lines are prefixed with `> `, or with `>` if they are already quoted,
+/// and the body ends with an empty line to write the reply below the quote.
+fn to_quoted_body(replied: &Email) -> String {
+    let sender = replied
+        .as_headers()
+        .get(&HeaderName::From)
+        .and_then(HeaderValue::as_address)
+        .and_then(|address| address.first())
+        .and_then(|addr| addr.name().or_else(|| addr.address()))
+        .unwrap_or("the sender")
+        .to_owned();
+    let attribution = replied
+        .as_headers()
+        .get(&HeaderName::Date)
+        .and_then(HeaderValue::as_datetime)
+        .map_or_else(
+            || format!("{sender} wrote:"),
+            |date| {
+                format!(
+                    "On {:04}-{:02}-{:02} {:02}:{:02}, {sender} wrote:",
+                    date.year, date.month, date.day, date.hour, date.minute
+                )
+            },
+        );
+    let body = replied.to_plain_body().unwrap_or_default();
+    let quoted = body
+        .trim_end()
+        .lines()
+        .map(|line| {
+            if line.is_empty() || line.starts_with('>') {
+                format!(">{line}")
+            } else {
+                format!("> {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{attribution}\n{quoted}\n\n")
+}
+
+/// Returns the subject of a reply to an email with the given subject
+///
+/// The chain of `Re:` prefixes of the subject, in any case and with optional
+/// counters like `Re[2]:`, is collapsed into a single `Re: `.
+fn to_reply_subject(subject: &str) -> String {
+    let mut rest = subject.trim();
+    while let Some(stripped) = strip_reply_prefix(rest) {
+        rest = stripped.trim_start();
+    }
+    format!("{REPLY_PREFIX}{rest}")
+}
+
+/// Removes a `Re:` prefix from the start of a subject, if it has one
+fn strip_reply_prefix(subject: &str) -> Option<&str> {
+    let after_re = subject
+        .get(..2)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("re"))
+        .and_then(|_| subject.get(2..))?;
+    let after_counter = after_re
+        .strip_prefix('[')
+        .and_then(|counter| counter.split_once(']'))
+        .filter(|(count, _)| count.chars().all(|ch| ch.is_ascii_digit()))
+        .map_or(after_re, |(_, rest)| rest);
+    after_counter.trim_start().strip_prefix(':')
 }
 
 /// State of the writer, informing on which input is being edited by the client.
@@ -84,6 +933,14 @@ enum WriterState {
     ///
     /// Press `t` to enter this mode.
     To,
+    /// The copies input is being edited.
+    ///
+    /// Press `c` to enter this mode.
+    Cc,
+    /// The blind copies input is being edited.
+    ///
+    /// Press `B` to enter this mode.
+    Bcc,
     /// The subject input is being edited.
     ///
     /// Press `s` to enter this mode.
@@ -92,4 +949,102 @@ enum WriterState {
     ///
     /// Press `b` to enter this mode.
     Body,
+    /// The custom headers input is being edited.
+    ///
+    /// Press `x` to enter this mode.
+    Headers,
+    /// The reply-to input is being edited.
+    ///
+    /// Press `R` to enter this mode.
+    ReplyTo,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::email;
+    use crate::tui::writer::{Writer, to_reply_subject};
+
+    #[test]
+    fn edit_as_new() {
+        let sent = email(
+            "From: me@example.com\nTo: Bob <bob@example.com>, \
+             alice@example.com\nCc: carol@example.com\nReply-To: \
+             team@example.com\nSubject: Report\nX-Priority: 1\nIn-Reply-To: \
+             <a@x>",
+        );
+        let writer = Writer::new_from_email(&sent);
+        assert_eq!(
+            writer.to_draft(),
+            "to bob@example.com, alice@example.com\ncc \
+             carol@example.com\nsubject Report\nreply-to team@example.com\nheaders \npriority \
+             high\nreceipt no\nremind 0\n\nBody\n"
+        );
+        assert!(
+            Writer::new_from_draft(&sent)
+                .to_draft()
+                .contains("\nremind 0\nin-reply-to <a@x>\n")
+        );
+        let draft = "to bob@example.com\ncc carol@example.com\nbcc \
+                     dan@example.com\nsubject Hi\nreply-to \nheaders \
+                     \npriority normal\nreceipt no\nremind 0\n\nBody";
+        assert_eq!(Writer::from_draft(draft).to_draft(), draft);
+    }
+
+    #[test]
+    fn reply_subject() {
+        assert_eq!(to_reply_subject("Report"), "Re: Report");
+        assert_eq!(to_reply_subject("Re: Re: RE: re[3]: Report"), "Re: Report");
+        assert_eq!(to_reply_subject("Re : Report"), "Re: Report");
+        assert_eq!(to_reply_subject("Rejected"), "Re: Rejected");
+    }
+
+    #[test]
+    fn subject_suggestions() {
+        let root = email("Subject: Report\nMessage-ID: <a@x>");
+        let replied = email("Subject: Re: Re: Report v2\nMessage-ID: <b@x>");
+        let mut writer = Writer::default();
+        writer.suggest_subjects_of(&[&replied, &root, &replied]);
+        assert!(writer.to_draft().contains("\nsubject Re: Report v2\n"));
+        writer.suggest_subject();
+        assert!(writer.to_draft().contains("\nsubject Re: Report\n"));
+        writer.suggest_subject();
+        assert!(writer.to_draft().contains("\nsubject Re: Report v2\n"));
+    }
+
+    #[test]
+    fn thread_headers() {
+        let draft = Writer::from_draft(
+            "to bob@example.com\nin-reply-to <b@x>\nreferences <a@x> <b@x>\n\nHi",
+        )
+        .to_draft();
+        assert!(
+            draft
+                .ends_with("\nin-reply-to <b@x>\nreferences <a@x> <b@x>\n\nHi")
+        );
+        assert_eq!(Writer::from_draft(&draft).to_draft(), draft);
+    }
+
+    #[test]
+    fn reply() {
+        let root =
+            email("From: bob@example.com\nSubject: Report\nMessage-ID: <a@x>");
+        let replied = email(
+            "From: Bob <bob@example.com>\nReply-To: team@example.com\nSubject: Re: Re: Report v2\nMessage-ID: <b@x>\nReferences: <a@x>",
+        );
+        let mut writer = Writer::new_reply(&replied, &[&root, &replied]);
+        let draft = writer.to_draft();
+        assert!(
+            draft.starts_with("to team@example.com\nsubject Re: Report v2\n")
+        );
+        assert!(
+            draft.contains("\nin-reply-to <b@x>\nreferences <a@x> <b@x>\n")
+        );
+        assert!(draft.ends_with("\n\nBob wrote:\n> Body\n\n"));
+        assert_eq!(Writer::from_draft(&draft).to_draft(), draft);
+
+        writer.suggest_subject();
+        assert!(writer.to_draft().contains("\nsubject Re: Report\n"));
+        writer.suggest_subject();
+        assert!(writer.to_draft().contains("\nsubject Re: Report v2\n"));
+    }
 }